@@ -1,17 +1,36 @@
 use std::net::{IpAddr, Ipv4Addr};
 
+pub const DEFAULT_ADMIN_SECRET_FILE: &str = "admin_secret.hex";
 pub const DEFAULT_BEACON_API_ENDPOINT: &str = "http://localhost:5052";
-pub const DEFAULT_DEVNET: &str = "1";
+pub const DEFAULT_BLOCK_SOURCE_TIMEOUT_MS: u64 = 500;
+pub const DEFAULT_CLOCK_DRIFT_WARN_THRESHOLD_MS: u64 = 250;
+pub const DEFAULT_DB_CACHE_SIZE_MIB: usize = 1_024;
+pub const DEFAULT_DB_FLUSH_INTERVAL_SECS: u64 = 5;
+pub const DEFAULT_DEV_GENESIS_DELAY: u64 = 5;
+pub const DEFAULT_DEV_VALIDATORS: u64 = 4;
+pub const DEFAULT_DEVNET_GENESIS_DELAY: u64 = 60;
+pub const DEFAULT_DEVNET_IMAGE: &str = "ghcr.io/reamlabs/ream:latest";
+pub const DEFAULT_DEVNET_IP: Ipv4Addr = Ipv4Addr::LOCALHOST;
+pub const DEFAULT_DEVNET_NODES: u64 = 4;
 pub const DEFAULT_DISABLE_DISCOVERY: bool = false;
 pub const DEFAULT_DISCOVERY_PORT: u16 = 9000;
+pub const DEFAULT_ERA_STATE_INTERVAL: u64 = 64;
 pub const DEFAULT_HTTP_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
 pub const DEFAULT_HTTP_ALLOW_ORIGIN: bool = false;
 pub const DEFAULT_HTTP_PORT: u16 = 5052;
+pub const DEFAULT_IMPORTED_KEYSTORES_DIR: &str = "lean_keystores";
 pub const DEFAULT_KEY_MANAGER_HTTP_PORT: u16 = 8008;
+pub const DEFAULT_KEYMANAGER_TOKEN_FILE: &str = "keymanager_token.hex";
+pub const DEFAULT_LEAN_NODE_URL: &str = "http://127.0.0.1:5052";
+pub const DEFAULT_LOG_FILTER_FILE: &str = "log_filter.txt";
 pub const DEFAULT_METRICS_ENABLED: bool = false;
 pub const DEFAULT_METRICS_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1));
 pub const DEFAULT_METRICS_PORT: u16 = 8080;
 pub const DEFAULT_NETWORK: &str = "mainnet";
+pub const DEFAULT_NETWORK_KEY_FILE: &str = "network/key";
+pub const DEFAULT_NTP_SERVERS: &str = "pool.ntp.org:123,time.cloudflare.com:123";
 pub const DEFAULT_REQUEST_TIMEOUT: &str = "60";
+pub const DEFAULT_SLASHING_PROTECTION_FILE: &str = "lean_slashing_protection.json";
+pub const DEFAULT_SNAPSHOTS_DIR: &str = "snapshots";
 pub const DEFAULT_SOCKET_ADDRESS: IpAddr = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
 pub const DEFAULT_SOCKET_PORT: u16 = 9000;
@@ -0,0 +1,211 @@
+use std::{fs, path::PathBuf, str::FromStr};
+
+use alloy_primitives::{B256, hex};
+use anyhow::{anyhow, ensure};
+use clap::{Parser, Subcommand, ValueEnum};
+use ream_storage::{
+    db::{DatabaseOptions, ReamDB},
+    tables::field::REDBField,
+};
+use serde::Serialize;
+use ssz::Encode;
+use tracing::{info, warn};
+
+#[derive(Debug, Parser)]
+pub struct DbConfig {
+    #[command(subcommand)]
+    pub command: DbCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DbCommands {
+    /// Print the number of entries and stored bytes of every lean table
+    #[command(name = "stats")]
+    Stats,
+
+    /// Dump a block by root or slot
+    #[command(name = "get-block")]
+    GetBlock(Box<GetBlockConfig>),
+
+    /// Dump a state by block root
+    #[command(name = "get-state")]
+    GetState(Box<GetStateConfig>),
+
+    /// Remove blocks and states older than a finalized slot
+    #[command(name = "prune")]
+    Prune(Box<PruneConfig>),
+
+    /// Check that the indices match the block table and the head and checkpoints are stored
+    #[command(name = "verify")]
+    Verify,
+
+    /// Rebuild the slot and state root indices from the block table
+    #[command(name = "repair")]
+    Repair,
+}
+
+#[derive(Debug, Parser)]
+pub struct GetBlockConfig {
+    /// Block root (0x-prefixed) or slot number
+    pub block_id: BlockId,
+
+    #[command(flatten)]
+    pub output: DumpOutputConfig,
+}
+
+#[derive(Debug, Parser)]
+pub struct GetStateConfig {
+    /// Root of the block the state belongs to
+    pub block_root: B256,
+
+    #[command(flatten)]
+    pub output: DumpOutputConfig,
+}
+
+#[derive(Debug, Parser)]
+pub struct DumpOutputConfig {
+    /// Encoding of the dumped value
+    #[arg(long, value_enum, default_value_t = DumpFormat::Json)]
+    pub format: DumpFormat,
+
+    /// Write the dump to a file instead of stdout. SSZ is written as raw bytes.
+    #[arg(long)]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Parser)]
+pub struct PruneConfig {
+    /// Remove every block (and its state) with a slot lower than this
+    #[arg(long)]
+    pub before_slot: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DumpFormat {
+    Json,
+    Ssz,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockId {
+    Root(B256),
+    Slot(u64),
+}
+
+impl FromStr for BlockId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.starts_with("0x") {
+            B256::from_str(s)
+                .map(BlockId::Root)
+                .map_err(|err| format!("Invalid block root {s}: {err}"))
+        } else {
+            s.parse::<u64>()
+                .map(BlockId::Slot)
+                .map_err(|err| format!("Invalid slot {s}: {err}"))
+        }
+    }
+}
+
+impl DumpOutputConfig {
+    fn dump<T: Serialize + Encode>(&self, value: &T) -> anyhow::Result<()> {
+        match (self.format, &self.output) {
+            (DumpFormat::Json, None) => println!("{}", serde_json::to_string_pretty(value)?),
+            (DumpFormat::Json, Some(path)) => fs::write(path, serde_json::to_vec_pretty(value)?)?,
+            (DumpFormat::Ssz, None) => println!("0x{}", hex::encode(value.as_ssz_bytes())),
+            (DumpFormat::Ssz, Some(path)) => fs::write(path, value.as_ssz_bytes())?,
+        }
+        Ok(())
+    }
+}
+
+/// Runs a `db` subcommand against the lean tables of the database in `data_dir`, opened with
+/// `options`.
+///
+/// Only `prune` and `repair` open the database for writing, every other subcommand uses a
+/// read-only handle.
+/// Either way the node must not be running, as redb doesn't allow other processes to open a
+/// database while it is open for writing.
+pub fn run_db(
+    config: DbConfig,
+    data_dir: PathBuf,
+    options: &DatabaseOptions,
+) -> anyhow::Result<()> {
+    if let DbCommands::Prune(prune_config) = &config.command {
+        let lean_db = ReamDB::with_options(data_dir, options)?.init_lean_db()?;
+        let finalized_slot = lean_db.latest_finalized_provider().get()?.slot;
+        ensure!(
+            prune_config.before_slot <= finalized_slot,
+            "Refusing to prune past the latest finalized slot {finalized_slot}"
+        );
+        let pruned = lean_db.prune_before_slot(prune_config.before_slot)?;
+        info!(
+            "Pruned {pruned} blocks before slot {}",
+            prune_config.before_slot
+        );
+        return Ok(());
+    }
+    if let DbCommands::Repair = &config.command {
+        let lean_db = ReamDB::with_options(data_dir, options)?.init_lean_db()?;
+        let indexed = lean_db.rebuild_indices()?;
+        info!("Rebuilt the slot and state root indices of {indexed} blocks");
+        return Ok(());
+    }
+
+    let lean_db = ReamDB::open_read_only_with_options(data_dir, options)?;
+    match config.command {
+        DbCommands::Stats => {
+            println!("{:<32} {:>12} {:>16}", "table", "entries", "stored bytes");
+            for summary in lean_db.table_stats()? {
+                println!(
+                    "{:<32} {:>12} {:>16}",
+                    summary.name, summary.entries, summary.stored_bytes
+                );
+            }
+        }
+        DbCommands::GetBlock(get_block_config) => {
+            let block_root = match get_block_config.block_id {
+                BlockId::Root(root) => root,
+                BlockId::Slot(slot) => lean_db
+                    .block_root_at_slot(slot)?
+                    .ok_or_else(|| anyhow!("No block found at slot {slot}"))?,
+            };
+            let block = lean_db
+                .block(block_root)?
+                .ok_or_else(|| anyhow!("No block found with root {block_root}"))?;
+            get_block_config.output.dump(&block)?;
+        }
+        DbCommands::GetState(get_state_config) => {
+            let state = lean_db.state(get_state_config.block_root)?.ok_or_else(|| {
+                anyhow!(
+                    "No state found for block root {}",
+                    get_state_config.block_root
+                )
+            })?;
+            get_state_config.output.dump(&state)?;
+        }
+        DbCommands::Prune(_) | DbCommands::Repair => {
+            unreachable!("prune and repair are handled with a writable database above")
+        }
+        DbCommands::Verify => {
+            let issues = lean_db.verify_integrity()?;
+            for issue in &issues {
+                warn!("{issue}");
+            }
+            let repairable = issues.iter().filter(|issue| issue.is_repairable()).count();
+            ensure!(
+                issues.is_empty(),
+                "Found {} integrity issues, {}",
+                issues.len(),
+                match repairable {
+                    0 => "none of them can be repaired, the database has to be purged".to_string(),
+                    repairable => format!("`ream db repair` fixes {repairable} of them"),
+                }
+            );
+            info!("Indices match the block table, and the head and checkpoints are stored");
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,126 @@
+use std::{
+    ffi::OsString,
+    fs,
+    net::IpAddr,
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::anyhow;
+use clap::Parser;
+use ream_fork_choice_lean::genesis::VALIDATOR_KEYS_MANIFEST_PATH;
+use ream_keystore::lean_keystore::ValidatorKeysManifest;
+use tracing::info;
+
+use crate::cli::{
+    constants::{
+        DEFAULT_DEV_GENESIS_DELAY, DEFAULT_DEV_VALIDATORS, DEFAULT_HTTP_ADDRESS, DEFAULT_HTTP_PORT,
+    },
+    generate_validator_registry::{
+        DEFAULT_REGISTRY_GENESIS_TIME, KeyGenerationConfig, generate_validator_registry,
+    },
+    lean_node::LeanNodeConfig,
+};
+
+/// The directory of the data directory the dev node keeps its keys and database in.
+pub const DEV_DIR: &str = "dev";
+
+/// The directory of [DEV_DIR] the dev node keeps its database in, started afresh on every run.
+pub const DEV_DB_DIR: &str = "db";
+
+#[derive(Debug, Parser)]
+pub struct DevConfig {
+    #[arg(
+        long,
+        help = "Number of validators, all of them run by this node",
+        default_value_t = DEFAULT_DEV_VALIDATORS
+    )]
+    pub validators: u64,
+
+    #[arg(
+        long,
+        help = "Seconds from now until genesis",
+        default_value_t = DEFAULT_DEV_GENESIS_DELAY
+    )]
+    pub genesis_delay: u64,
+
+    #[arg(long, help = "Override the seconds per slot, and so the block time")]
+    pub seconds_per_slot: Option<u64>,
+
+    #[arg(long, help = "Set HTTP address", default_value_t = DEFAULT_HTTP_ADDRESS)]
+    pub http_address: IpAddr,
+
+    #[arg(long, help = "Set HTTP Port", default_value_t = DEFAULT_HTTP_PORT)]
+    pub http_port: u16,
+
+    #[arg(
+        long,
+        help = "Generate new validator keys even if the ones of an earlier run have as many validators"
+    )]
+    pub regenerate_keys: bool,
+}
+
+/// Prepares `dev_dir` for a single lean node which runs every validator, and returns its config.
+///
+/// Validator keys take a while to generate, so the ones of an earlier run are reused if there are
+/// as many validators with the default key parameters. The chain starts at a new genesis on every
+/// run, so the database of the earlier run is removed.
+pub fn prepare_dev_node(config: &DevConfig, dev_dir: &Path) -> anyhow::Result<LeanNodeConfig> {
+    let key_generation = KeyGenerationConfig::default();
+    let manifest_validators = fs::read_to_string(dev_dir.join(VALIDATOR_KEYS_MANIFEST_PATH))
+        .ok()
+        .and_then(|manifest| serde_yaml::from_str::<ValidatorKeysManifest>(&manifest).ok())
+        .filter(|manifest| key_generation.matches(manifest))
+        .map(|manifest| manifest.num_validators);
+    if config.regenerate_keys || manifest_validators != Some(config.validators) {
+        info!(
+            "Generating the keys of {} validator(s) in {}",
+            config.validators,
+            dev_dir.display()
+        );
+        generate_validator_registry(
+            dev_dir.to_path_buf(),
+            1,
+            config.validators,
+            DEFAULT_REGISTRY_GENESIS_TIME,
+            &key_generation,
+        )?;
+    } else {
+        info!("Reusing the validator keys in {}", dev_dir.display());
+    }
+
+    let db_dir = dev_dir.join(DEV_DB_DIR);
+    if db_dir.exists() {
+        fs::remove_dir_all(&db_dir)?;
+    }
+    fs::create_dir_all(&db_dir)?;
+
+    let mut node_config = LeanNodeConfig::try_parse_from([
+        OsString::from("dev"),
+        "--network".into(),
+        dev_dir.join("config.yaml").into(),
+        "--validator-registry-path".into(),
+        dev_dir.join("validators.yaml").into(),
+        "--bootnodes".into(),
+        "none".into(),
+        "--http-address".into(),
+        config.http_address.to_string().into(),
+        "--http-port".into(),
+        config.http_port.to_string().into(),
+    ])?;
+
+    node_config.network.genesis_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| anyhow!("System time is before UNIX epoch: {err}"))?
+        .as_secs()
+        + config.genesis_delay;
+    if let Some(seconds_per_slot) = config.seconds_per_slot {
+        node_config.network.seconds_per_slot = seconds_per_slot;
+    }
+    node_config
+        .network
+        .validate()
+        .map_err(|err| anyhow!("Invalid dev network: {err}"))?;
+
+    Ok(node_config)
+}
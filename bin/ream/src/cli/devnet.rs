@@ -0,0 +1,315 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::{IpAddr, Ipv4Addr},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use alloy_primitives::hex;
+use anyhow::{anyhow, ensure};
+use clap::{Parser, Subcommand};
+use libp2p_identity::{Keypair, secp256k1};
+use ream_node::secret_file::write_secret_file;
+use ream_p2p::network::lean::{build_local_enr, enr_signing_key};
+use serde::Serialize;
+use tracing::info;
+
+use crate::cli::{
+    constants::{
+        DEFAULT_DEVNET_GENESIS_DELAY, DEFAULT_DEVNET_IMAGE, DEFAULT_DEVNET_IP,
+        DEFAULT_DEVNET_NODES, DEFAULT_HTTP_PORT, DEFAULT_METRICS_PORT, DEFAULT_SOCKET_PORT,
+    },
+    generate_validator_registry::{KeyGenerationConfig, generate_validator_registry},
+};
+
+/// Where the compose file mounts the devnet directory in the containers.
+const DEVNET_MOUNT: &str = "/devnet";
+
+/// The ENRs of every node, passed to each of them as its bootnodes.
+pub const DEVNET_BOOTNODES_FILE: &str = "nodes.yaml";
+
+pub const DEVNET_COMPOSE_FILE: &str = "docker-compose.yaml";
+
+/// The hex encoded secp256k1 libp2p key of a node, in its data directory.
+pub const DEVNET_NODE_KEY_FILE: &str = "node.key";
+
+#[derive(Debug, Parser)]
+pub struct DevnetConfig {
+    #[command(subcommand)]
+    pub command: DevnetCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum DevnetCommands {
+    /// Generate the keys, data directories, bootnodes and compose file of a local lean devnet
+    #[command(name = "generate")]
+    Generate(Box<GenerateDevnetConfig>),
+}
+
+#[derive(Debug, Parser)]
+pub struct GenerateDevnetConfig {
+    #[arg(
+        long,
+        default_value = "devnet",
+        help = "Directory to write the devnet to"
+    )]
+    pub output: PathBuf,
+
+    #[arg(long, default_value_t = DEFAULT_DEVNET_NODES)]
+    pub number_of_nodes: u64,
+
+    #[arg(long, default_value_t = 1)]
+    pub number_of_validators_per_node: u64,
+
+    #[arg(
+        long,
+        help = "Seconds from now until genesis, leave enough time to start the nodes",
+        default_value_t = DEFAULT_DEVNET_GENESIS_DELAY
+    )]
+    pub genesis_delay: u64,
+
+    #[arg(
+        long,
+        help = "IP address the nodes are reached at, advertised in their ENRs",
+        default_value_t = DEFAULT_DEVNET_IP
+    )]
+    pub ip: Ipv4Addr,
+
+    #[arg(
+        long,
+        help = "P2P socket port of the first node, the next nodes use the following ports",
+        default_value_t = DEFAULT_SOCKET_PORT
+    )]
+    pub base_socket_port: u16,
+
+    #[arg(
+        long,
+        help = "HTTP port of the first node, the next nodes use the following ports",
+        default_value_t = DEFAULT_HTTP_PORT
+    )]
+    pub base_http_port: u16,
+
+    #[arg(
+        long,
+        help = "Metrics port of the first node, the next nodes use the following ports",
+        default_value_t = DEFAULT_METRICS_PORT
+    )]
+    pub base_metrics_port: u16,
+
+    #[arg(long, help = "Docker image the nodes run", default_value = DEFAULT_DEVNET_IMAGE)]
+    pub image: String,
+
+    #[command(flatten)]
+    pub key_generation: KeyGenerationConfig,
+}
+
+#[derive(Debug, Serialize)]
+struct ComposeFile {
+    services: BTreeMap<String, ComposeService>,
+}
+
+#[derive(Debug, Serialize)]
+struct ComposeService {
+    image: String,
+    container_name: String,
+    network_mode: String,
+    volumes: Vec<String>,
+    command: Vec<String>,
+}
+
+pub fn run_devnet(config: DevnetConfig) -> anyhow::Result<()> {
+    match config.command {
+        DevnetCommands::Generate(config) => generate_devnet(&config),
+    }
+}
+
+/// The port of the node at `node_index`, counting up from `base_port`.
+fn node_port(base_port: u16, node_index: u64) -> anyhow::Result<u16> {
+    u16::try_from(base_port as u64 + node_index)
+        .map_err(|err| anyhow!("Port {base_port} + {node_index} is out of range: {err}"))
+}
+
+/// Writes a devnet of `number_of_nodes` lean nodes to the output directory: the validator
+/// registry and network config shared by every node, a data directory with a libp2p key for each
+/// node, the ENRs of the nodes as their bootnodes, and a compose file running the nodes.
+///
+/// The nodes share the host network, so each node gets its own P2P, HTTP and metrics port.
+pub fn generate_devnet(config: &GenerateDevnetConfig) -> anyhow::Result<()> {
+    ensure!(
+        config.number_of_nodes > 0,
+        "A devnet needs at least one node"
+    );
+    ensure!(
+        config.number_of_validators_per_node > 0,
+        "Every node needs at least one validator"
+    );
+
+    let genesis_time = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| anyhow!("System time is before UNIX epoch: {err}"))?
+        .as_secs()
+        + config.genesis_delay;
+    info!(
+        "Generating the keys of {} validator(s) in {}",
+        config.number_of_nodes * config.number_of_validators_per_node,
+        config.output.display()
+    );
+    generate_validator_registry(
+        config.output.clone(),
+        config.number_of_nodes,
+        config.number_of_validators_per_node,
+        genesis_time,
+        &config.key_generation,
+    )?;
+
+    let mut enrs = vec![];
+    let mut services = BTreeMap::new();
+    for node_index in 0..config.number_of_nodes {
+        let node_id = format!("ream_{node_index}");
+        let socket_port = node_port(config.base_socket_port, node_index)?;
+        let http_port = node_port(config.base_http_port, node_index)?;
+        let metrics_port = node_port(config.base_metrics_port, node_index)?;
+
+        let node_dir = config.output.join(&node_id);
+        fs::create_dir_all(&node_dir)?;
+        let keypair = secp256k1::Keypair::generate();
+        write_secret_file(
+            node_dir.join(DEVNET_NODE_KEY_FILE),
+            hex::encode(keypair.secret().to_bytes()),
+        )?;
+        enrs.push(build_local_enr(
+            &enr_signing_key(&Keypair::from(keypair))?,
+            IpAddr::V4(config.ip),
+            socket_port,
+        )?);
+
+        let data_dir = format!("{DEVNET_MOUNT}/{node_id}");
+        services.insert(
+            node_id.clone(),
+            ComposeService {
+                image: config.image.clone(),
+                container_name: node_id.clone(),
+                network_mode: "host".to_string(),
+                volumes: vec![format!(".:{DEVNET_MOUNT}")],
+                command: [
+                    "--data-dir",
+                    &data_dir,
+                    "lean_node",
+                    "--network",
+                    &format!("{DEVNET_MOUNT}/config.yaml"),
+                    "--validator-registry-path",
+                    &format!("{DEVNET_MOUNT}/validators.yaml"),
+                    "--node-id",
+                    &node_id,
+                    "--private-key-path",
+                    &format!("{data_dir}/{DEVNET_NODE_KEY_FILE}"),
+                    "--bootnodes",
+                    &format!("{DEVNET_MOUNT}/{DEVNET_BOOTNODES_FILE}"),
+                    "--socket-port",
+                    &socket_port.to_string(),
+                    "--http-address",
+                    "0.0.0.0",
+                    "--http-port",
+                    &http_port.to_string(),
+                    "--metrics",
+                    "--metrics-address",
+                    "0.0.0.0",
+                    "--metrics-port",
+                    &metrics_port.to_string(),
+                ]
+                .map(str::to_string)
+                .to_vec(),
+            },
+        );
+        info!(
+            "{node_id}: P2P port {socket_port}, HTTP port {http_port}, metrics port {metrics_port}"
+        );
+    }
+
+    fs::write(
+        config.output.join(DEVNET_BOOTNODES_FILE),
+        serde_yaml::to_string(&enrs)?,
+    )?;
+    fs::write(
+        config.output.join(DEVNET_COMPOSE_FILE),
+        serde_yaml::to_string(&ComposeFile { services })?,
+    )?;
+
+    info!(
+        "Devnet written to {}, run `docker compose up` there before genesis at {genesis_time}",
+        config.output.display()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::{
+        DEVNET_BOOTNODES_FILE, DEVNET_COMPOSE_FILE, DEVNET_NODE_KEY_FILE, GenerateDevnetConfig,
+        generate_devnet, node_port,
+    };
+    use crate::cli::generate_validator_registry::{KeyGenerationConfig, KeyScheme};
+
+    #[test]
+    fn test_generate_devnet() {
+        let dir = TempDir::new().unwrap();
+        let config = GenerateDevnetConfig {
+            output: dir.path().join("devnet"),
+            number_of_nodes: 2,
+            number_of_validators_per_node: 1,
+            genesis_delay: 60,
+            ip: "127.0.0.1".parse().unwrap(),
+            base_socket_port: 9000,
+            base_http_port: 5052,
+            base_metrics_port: 8080,
+            image: "ream".to_string(),
+            key_generation: KeyGenerationConfig {
+                signature_scheme: KeyScheme::Test,
+                num_active_epochs: 8,
+                ..Default::default()
+            },
+        };
+        generate_devnet(&config).unwrap();
+
+        for file in ["config.yaml", "validators.yaml", DEVNET_COMPOSE_FILE] {
+            assert!(config.output.join(file).is_file());
+        }
+        let enrs = serde_yaml::from_str::<Vec<String>>(
+            &fs::read_to_string(config.output.join(DEVNET_BOOTNODES_FILE)).unwrap(),
+        )
+        .unwrap();
+        assert_eq!(enrs.len(), 2);
+        assert_ne!(enrs[0], enrs[1]);
+
+        let compose = serde_yaml::from_str::<serde_yaml::Value>(
+            &fs::read_to_string(config.output.join(DEVNET_COMPOSE_FILE)).unwrap(),
+        )
+        .unwrap();
+        let command = compose["services"]["ream_1"]["command"]
+            .as_sequence()
+            .unwrap();
+        assert!(command.contains(&serde_yaml::Value::from("9001")));
+        assert!(command.contains(&serde_yaml::Value::from("5053")));
+
+        let node_key = config.output.join("ream_1").join(DEVNET_NODE_KEY_FILE);
+        assert!(node_key.is_file());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(node_key).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+
+    #[test]
+    fn test_node_port() {
+        assert_eq!(node_port(9000, 3).unwrap(), 9003);
+        assert!(node_port(u16::MAX, 1).is_err());
+    }
+}
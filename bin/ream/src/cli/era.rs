@@ -0,0 +1,91 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::PathBuf,
+};
+
+use anyhow::ensure;
+use clap::Parser;
+use ream_storage::{
+    db::{DatabaseOptions, ReamDB},
+    era::{export_era, import_era},
+};
+use tracing::info;
+
+use crate::cli::constants::DEFAULT_ERA_STATE_INTERVAL;
+
+#[derive(Debug, Parser)]
+pub struct ExportConfig {
+    /// First slot to export
+    #[arg(long, default_value_t = 0)]
+    pub from_slot: u64,
+
+    /// Last slot to export. Defaults to the latest finalized slot.
+    #[arg(long)]
+    pub to_slot: Option<u64>,
+
+    /// Also export the state of every block whose slot is a multiple of this
+    #[arg(long, default_value_t = DEFAULT_ERA_STATE_INTERVAL, value_parser = clap::value_parser!(u64).range(1..))]
+    pub state_interval: u64,
+
+    /// Path of the archive to write
+    #[arg(long)]
+    pub output: PathBuf,
+}
+
+#[derive(Debug, Parser)]
+pub struct ImportConfig {
+    /// Path of the archive to import
+    pub input: PathBuf,
+}
+
+/// Exports finalized lean blocks and periodic states to an era archive, reading the database in
+/// `data_dir` read-only.
+pub fn run_export(
+    config: ExportConfig,
+    data_dir: PathBuf,
+    options: &DatabaseOptions,
+) -> anyhow::Result<()> {
+    let lean_db = ReamDB::open_read_only_with_options(data_dir, options)?;
+    let finalized_slot = lean_db.latest_finalized()?.slot;
+    let to_slot = config.to_slot.unwrap_or(finalized_slot);
+    ensure!(
+        to_slot <= finalized_slot,
+        "Only finalized blocks can be exported, latest finalized slot is {finalized_slot}"
+    );
+    ensure!(
+        config.from_slot <= to_slot,
+        "--from-slot must not be greater than --to-slot"
+    );
+
+    let mut writer = BufWriter::new(File::create(&config.output)?);
+    let summary = export_era(
+        &lean_db,
+        config.from_slot,
+        to_slot,
+        config.state_interval,
+        &mut writer,
+    )?;
+    info!(
+        "Exported {} blocks and {} states from slots {}..={to_slot} to {}",
+        summary.blocks,
+        summary.states,
+        config.from_slot,
+        config.output.display()
+    );
+    Ok(())
+}
+
+/// Imports the blocks and states of an era archive into the lean database.
+pub fn run_import(config: ImportConfig, ream_db: ReamDB) -> anyhow::Result<()> {
+    let lean_db = ream_db.init_lean_db()?;
+    let mut reader = BufReader::new(File::open(&config.input)?);
+    let summary = import_era(&lean_db, &mut reader, true)?;
+    info!(
+        "Imported {} blocks and {} states from {}",
+        summary.blocks,
+        summary.states,
+        config.input.display()
+    );
+    Ok(())
+}
@@ -2,17 +2,203 @@ use std::{
     collections::HashMap,
     fs::{self, create_dir_all},
     path::PathBuf,
+    str::FromStr,
 };
 
+use alloy_primitives::FixedBytes;
 use anyhow::ensure;
-use clap::Parser;
-use rand::rng;
+use bip39::Mnemonic;
+use clap::{Parser, ValueEnum};
+use leansig::{
+    serialization::Serializable,
+    signature::{
+        SignatureScheme,
+        generalized_xmss::instantiations_poseidon_top_level::lifetime_2_to_the_8::SIGTopLevelTargetSumLifetime8Dim64Base8,
+    },
+};
+use rand::{Rng, SeedableRng, rng};
+use rand_chacha::ChaCha20Rng;
+use rayon::prelude::*;
+use ream_account_manager::seed::derive_seed_with_domain;
 use ream_keystore::lean_keystore::{
     ConfigFile, ValidatorKeysManifest, ValidatorKeystoreRaw, ValidatorRegistry,
 };
-use ream_post_quantum_crypto::leansig::{private_key::PrivateKey, public_key::PublicKey};
+use ream_node::secret_file::write_secret_file;
+use ream_post_quantum_crypto::leansig::{
+    LEAN_SIG_LIFETIME, LEAN_SIG_SCHEME_NAME, private_key::PrivateKey, public_key::PublicKey,
+};
+
+/// The epochs keys are active for unless overridden, 2^18 slots.
+pub const DEFAULT_NUM_ACTIVE_EPOCHS: u64 = 1 << 18;
+
+/// Separates the seeds of validator keys from the seeds the account manager derives from the same
+/// seed phrase.
+const VALIDATOR_KEY_SEED_DOMAIN: &[u8] = b"ream-lean-validator-key";
+
+/// The genesis time of the network config written by `generate_validator_registry`.
+pub const DEFAULT_REGISTRY_GENESIS_TIME: u64 = 1704085200;
+
+/// The XMSS instantiation the keys are generated for.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum KeyScheme {
+    /// A lifetime of 2^32 epochs, which lean nodes sign with
+    #[default]
+    Prod,
+    /// A lifetime of 2^8 epochs, which generates quickly for tests
+    Test,
+}
+
+impl KeyScheme {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyScheme::Prod => LEAN_SIG_SCHEME_NAME,
+            KeyScheme::Test => "SIGTopLevelTargetSumLifetime8Dim64Base8",
+        }
+    }
+
+    /// The number of epochs a key of the scheme can be active for at most.
+    pub fn lifetime(self) -> u64 {
+        match self {
+            KeyScheme::Prod => LEAN_SIG_LIFETIME,
+            KeyScheme::Test => 1 << 8,
+        }
+    }
 
-const NUM_ACTIVE_EPOCHS: u64 = 262144;
+    /// Generates a key pair, returning the public key and the JSON of the private key.
+    fn generate_key_pair<R: Rng>(
+        self,
+        rng: &mut R,
+        activation_epoch: usize,
+        num_active_epochs: usize,
+    ) -> anyhow::Result<(PublicKey, String)> {
+        match self {
+            KeyScheme::Prod => {
+                let (public_key, private_key) =
+                    PrivateKey::generate_key_pair(rng, activation_epoch, num_active_epochs);
+                Ok((public_key, serde_json::to_string(&private_key.inner)?))
+            }
+            KeyScheme::Test => {
+                let (public_key, private_key) = SIGTopLevelTargetSumLifetime8Dim64Base8::key_gen(
+                    rng,
+                    activation_epoch,
+                    num_active_epochs,
+                );
+                Ok((
+                    PublicKey::new(FixedBytes::try_from(public_key.to_bytes().as_slice())?),
+                    serde_json::to_string(&private_key)?,
+                ))
+            }
+        }
+    }
+}
+
+/// Checks `seed_phrase` is a valid BIP39 mnemonic.
+pub fn seed_phrase_parser(seed_phrase: &str) -> Result<String, String> {
+    Mnemonic::from_str(seed_phrase)
+        .map(|_| seed_phrase.to_string())
+        .map_err(|err| format!("Invalid seed phrase: {err}"))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Parser)]
+pub struct KeyGenerationConfig {
+    #[arg(
+        long,
+        help = "Signature scheme of the keys, the test scheme only lives for 256 epochs",
+        value_enum,
+        default_value_t = KeyScheme::Prod
+    )]
+    pub signature_scheme: KeyScheme,
+
+    #[arg(long, help = "First epoch the keys can sign for", default_value_t = 0)]
+    pub activation_epoch: u64,
+
+    #[arg(
+        long,
+        help = "Number of epochs the keys can sign for, a power of two",
+        default_value_t = DEFAULT_NUM_ACTIVE_EPOCHS
+    )]
+    pub num_active_epochs: u64,
+
+    #[arg(
+        long,
+        help = "BIP39 mnemonic to derive the keys from, so the same keys can be generated again",
+        value_parser = seed_phrase_parser
+    )]
+    pub seed_phrase: Option<String>,
+
+    #[arg(long, help = "Optional BIP39 passphrase used with the seed phrase")]
+    pub passphrase: Option<String>,
+}
+
+impl Default for KeyGenerationConfig {
+    fn default() -> Self {
+        Self {
+            signature_scheme: KeyScheme::Prod,
+            activation_epoch: 0,
+            num_active_epochs: DEFAULT_NUM_ACTIVE_EPOCHS,
+            seed_phrase: None,
+            passphrase: None,
+        }
+    }
+}
+
+impl KeyGenerationConfig {
+    /// Checks the active epochs can be written to a manifest, which records their number by its
+    /// logarithm, and fit into the lifetime of the scheme.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.num_active_epochs.is_power_of_two(),
+            "Number of active epochs must be a power of two, got {}",
+            self.num_active_epochs
+        );
+        let lifetime = self.signature_scheme.lifetime();
+        ensure!(
+            self.activation_epoch
+                .checked_add(self.num_active_epochs)
+                .is_some_and(|end| end <= lifetime),
+            "Active epochs {}..{} exceed the lifetime of {lifetime} epochs of the {} scheme",
+            self.activation_epoch,
+            self.activation_epoch.saturating_add(self.num_active_epochs),
+            self.signature_scheme.name()
+        );
+        Ok(())
+    }
+
+    /// Generates the key pair of the validator `index`, derived from the seed phrase if there is
+    /// one and random otherwise. Returns the public key and the JSON of the private key.
+    pub fn generate_key_pair(&self, index: u64) -> anyhow::Result<(PublicKey, String)> {
+        let activation_epoch = self.activation_epoch as usize;
+        let num_active_epochs = self.num_active_epochs as usize;
+        match &self.seed_phrase {
+            Some(seed_phrase) => {
+                let seed = derive_seed_with_domain(
+                    seed_phrase,
+                    VALIDATOR_KEY_SEED_DOMAIN,
+                    u32::try_from(index)?,
+                    self.passphrase.as_deref().unwrap_or(""),
+                );
+                self.signature_scheme.generate_key_pair(
+                    &mut ChaCha20Rng::from_seed(seed),
+                    activation_epoch,
+                    num_active_epochs,
+                )
+            }
+            None => self.signature_scheme.generate_key_pair(
+                &mut rng(),
+                activation_epoch,
+                num_active_epochs,
+            ),
+        }
+    }
+
+    /// Whether the keys of `manifest` were generated with this config.
+    pub fn matches(&self, manifest: &ValidatorKeysManifest) -> bool {
+        manifest.key_scheme == self.signature_scheme.name()
+            && manifest.lifetime == self.signature_scheme.lifetime()
+            && manifest.num_active_epochs == self.num_active_epochs
+            && manifest.log_num_active_epochs == u64::from(self.num_active_epochs.trailing_zeros())
+    }
+}
 
 #[derive(Debug, Parser)]
 pub struct GenerateValidatorRegistryConfig {
@@ -24,30 +210,53 @@ pub struct GenerateValidatorRegistryConfig {
 
     #[arg(long, default_value_t = 1)]
     pub number_of_validators_per_node: u64,
+
+    #[command(flatten)]
+    pub key_generation: KeyGenerationConfig,
 }
 
 pub fn run_generate_validator_registry(
     keystore_config: GenerateValidatorRegistryConfig,
 ) -> anyhow::Result<()> {
-    ensure!(
-        !keystore_config.output.is_file(),
-        "Output must be a directory path"
-    );
-    create_dir_all(&keystore_config.output)?;
+    generate_validator_registry(
+        keystore_config.output,
+        keystore_config.number_of_nodes,
+        keystore_config.number_of_validators_per_node,
+        DEFAULT_REGISTRY_GENESIS_TIME,
+        &keystore_config.key_generation,
+    )
+}
+
+/// Writes a validator registry assigning `number_of_validators_per_node` validators to each of
+/// the nodes `ream_0` to `ream_{number_of_nodes - 1}` to `output`, together with the keys of the
+/// validators and a network config with them as the genesis validators, starting at
+/// `genesis_time`.
+///
+/// The keys are generated in parallel, as XMSS key generation takes seconds per key. With a seed
+/// phrase, the key of each validator is derived from it and the validator index.
+pub fn generate_validator_registry(
+    output: PathBuf,
+    number_of_nodes: u64,
+    number_of_validators_per_node: u64,
+    genesis_time: u64,
+    key_generation: &KeyGenerationConfig,
+) -> anyhow::Result<()> {
+    ensure!(!output.is_file(), "Output must be a directory path");
+    key_generation.validate()?;
+    create_dir_all(&output)?;
 
-    let mut rng = rng();
     let mut validator_registry = HashMap::new();
     let mut validator_index = 0;
-    for node_index in 0..keystore_config.number_of_nodes {
+    for node_index in 0..number_of_nodes {
         let mut validator_ids = vec![];
-        for _ in 0..keystore_config.number_of_validators_per_node {
+        for _ in 0..number_of_validators_per_node {
             validator_ids.push(validator_index);
             validator_index += 1
         }
         validator_registry.insert(format!("ream_{node_index}"), validator_ids);
     }
 
-    let mut path = keystore_config.output;
+    let mut path = output;
     path.push("validators.yaml");
     fs::write(
         &path,
@@ -59,22 +268,26 @@ pub fn run_generate_validator_registry(
 
     path.push("hash-sig-keys");
     create_dir_all(&path)?;
+    let key_pairs = (0..(number_of_nodes * number_of_validators_per_node))
+        .into_par_iter()
+        .map(|index| key_generation.generate_key_pair(index))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
     let mut validators: Vec<ValidatorKeystoreRaw> = Vec::new();
     let mut genesis_validators: Vec<PublicKey> = vec![];
-    for i in 0..(keystore_config.number_of_nodes * keystore_config.number_of_validators_per_node) {
-        let (public_key, private_key) =
-            PrivateKey::generate_key_pair(&mut rng, 0, NUM_ACTIVE_EPOCHS as usize);
+    for (i, (public_key, private_key)) in (0..).zip(key_pairs) {
         genesis_validators.push(public_key);
 
         let filename: String = format!("validator_{i}_sk.json");
         path.push(&filename);
-        fs::write(&path, serde_json::to_string(&private_key.inner)?)?;
+        write_secret_file(&path, private_key)?;
         path.pop();
 
         validators.push(ValidatorKeystoreRaw {
             index: i,
             public_key,
             privkey_file: filename,
+            rotations: vec![],
         });
     }
 
@@ -82,12 +295,12 @@ pub fn run_generate_validator_registry(
     fs::write(
         &path,
         serde_yaml::to_string(&ValidatorKeysManifest {
-            key_scheme: "SIGTopLevelTargetSumLifetime32Dim64Base8".to_string(),
+            key_scheme: key_generation.signature_scheme.name().to_string(),
             hash_function: "Poseidon2".to_string(),
             encoding: "TargetSum".to_string(),
-            lifetime: 4294967296u64,
-            log_num_active_epochs: 18,
-            num_active_epochs: NUM_ACTIVE_EPOCHS,
+            lifetime: key_generation.signature_scheme.lifetime(),
+            log_num_active_epochs: u64::from(key_generation.num_active_epochs.trailing_zeros()),
+            num_active_epochs: key_generation.num_active_epochs,
             num_validators: validators.len() as u64,
             validators,
         })?,
@@ -99,7 +312,7 @@ pub fn run_generate_validator_registry(
     fs::write(
         &path,
         serde_yaml::to_string(&ConfigFile {
-            genesis_time: 1704085200,
+            genesis_time,
             num_validators: genesis_validators.len() as u64,
             genesis_validators,
         })?,
@@ -107,3 +320,81 @@ pub fn run_generate_validator_registry(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha20Rng;
+    use ream_account_manager::seed::derive_seed_with_user_input;
+
+    use super::{DEFAULT_NUM_ACTIVE_EPOCHS, KeyGenerationConfig, KeyScheme, seed_phrase_parser};
+
+    const SEED_PHRASE: &str = "abandon abandon abandon abandon abandon abandon abandon abandon \
+                               abandon abandon abandon about";
+
+    #[test]
+    fn test_validate_key_generation_config() {
+        assert!(KeyGenerationConfig::default().validate().is_ok());
+
+        let config = KeyGenerationConfig {
+            signature_scheme: KeyScheme::Test,
+            activation_epoch: 0,
+            num_active_epochs: 256,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        assert!(
+            KeyGenerationConfig {
+                activation_epoch: 1,
+                ..config.clone()
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            KeyGenerationConfig {
+                num_active_epochs: 100,
+                ..config
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(
+            KeyGenerationConfig {
+                signature_scheme: KeyScheme::Test,
+                ..Default::default()
+            }
+            .validate()
+            .is_err()
+        );
+        assert_eq!(DEFAULT_NUM_ACTIVE_EPOCHS.trailing_zeros(), 18);
+    }
+
+    #[test]
+    fn test_seeded_key_generation() {
+        let config = KeyGenerationConfig {
+            signature_scheme: KeyScheme::Test,
+            num_active_epochs: 8,
+            seed_phrase: Some(SEED_PHRASE.to_string()),
+            ..Default::default()
+        };
+        let (public_key, private_key) = config.generate_key_pair(1).unwrap();
+        assert_eq!(
+            config.generate_key_pair(1).unwrap(),
+            (public_key, private_key)
+        );
+        assert_ne!(config.generate_key_pair(2).unwrap().0, public_key);
+        // The account manager derives different keys from the same seed phrase and index.
+        let account_seed = derive_seed_with_user_input(SEED_PHRASE, 1, "");
+        assert_ne!(
+            KeyScheme::Test
+                .generate_key_pair(&mut ChaCha20Rng::from_seed(account_seed), 0, 8)
+                .unwrap()
+                .0,
+            public_key
+        );
+
+        assert!(seed_phrase_parser(SEED_PHRASE).is_ok());
+        assert!(seed_phrase_parser("abandon abandon").is_err());
+    }
+}
@@ -0,0 +1,235 @@
+use std::{
+    fs::{self, create_dir_all},
+    path::PathBuf,
+};
+
+use anyhow::{anyhow, ensure};
+use clap::{Parser, Subcommand};
+use ream_consensus_lean::validator::{KeyRotation, SignedKeyRotation};
+use ream_keystore::lean_keystore::KeyRotationRaw;
+use ream_node::secret_file::write_secret_file;
+use ream_validator_lean::registry::{
+    MANIFEST_FILE, keys_dir, load_keys_manifest, load_validator_keystores,
+};
+use tracing::info;
+use tree_hash::TreeHash;
+
+use crate::cli::generate_validator_registry::KeyGenerationConfig;
+
+#[derive(Debug, Parser)]
+pub struct KeysConfig {
+    #[command(subcommand)]
+    pub command: KeysCommands,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum KeysCommands {
+    /// Recover the key of a single validator from the seed phrase it was generated from
+    #[command(name = "derive")]
+    Derive(Box<DeriveKeyConfig>),
+
+    /// Generate the next key of a validator and the key rotation its current key signs for it
+    #[command(name = "rotate")]
+    Rotate(Box<RotateKeyConfig>),
+}
+
+#[derive(Debug, Parser)]
+pub struct DeriveKeyConfig {
+    /// Index of the validator whose key to derive
+    #[arg(long)]
+    pub index: u64,
+
+    /// Directory to write the private key file to
+    #[arg(long, default_value = ".")]
+    pub output: PathBuf,
+
+    #[command(flatten)]
+    pub key_generation: KeyGenerationConfig,
+}
+
+#[derive(Debug, Parser)]
+pub struct RotateKeyConfig {
+    /// Path to the validator registry, whose keys manifest the new key is added to
+    #[arg(long)]
+    pub validator_registry_path: PathBuf,
+
+    /// Index of the validator whose key to rotate
+    #[arg(long)]
+    pub index: u64,
+
+    /// First slot the validator signs at with the new key
+    #[arg(long)]
+    pub activation_slot: u64,
+
+    #[command(flatten)]
+    pub key_generation: KeyGenerationConfig,
+}
+
+pub fn run_keys(config: KeysConfig) -> anyhow::Result<()> {
+    match config.command {
+        KeysCommands::Derive(config) => derive_key(*config),
+        KeysCommands::Rotate(config) => rotate_key(*config),
+    }
+}
+
+/// Derives the key of validator `index` the same way `generate_validator_registry` does, and
+/// writes its private key file under the same name.
+fn derive_key(config: DeriveKeyConfig) -> anyhow::Result<()> {
+    let key_generation = config.key_generation;
+    ensure!(
+        key_generation.seed_phrase.is_some(),
+        "A seed phrase is needed to derive a key"
+    );
+    key_generation.validate()?;
+
+    let (public_key, private_key) = key_generation.generate_key_pair(config.index)?;
+    create_dir_all(&config.output)?;
+    let path = config
+        .output
+        .join(format!("validator_{}_sk.json", config.index));
+    write_secret_file(&path, private_key)?;
+
+    info!(
+        "Wrote the private key of validator {} to {}",
+        config.index,
+        path.display()
+    );
+    println!("{}", serde_json::to_string(&public_key)?);
+    Ok(())
+}
+
+/// Generates the next key of validator `index` and adds it to the keys manifest, so the
+/// validator switches to it at the activation slot. Prints the signed key rotation, which has to
+/// be included in a block before that slot.
+fn rotate_key(config: RotateKeyConfig) -> anyhow::Result<()> {
+    let RotateKeyConfig {
+        validator_registry_path,
+        index,
+        activation_slot,
+        key_generation,
+    } = config;
+    // A key derived from the same seed phrase and index would share one-time keys with the key it
+    // replaces.
+    ensure!(
+        key_generation.seed_phrase.is_none(),
+        "Rotated keys are generated from fresh randomness, not from a seed phrase"
+    );
+    key_generation.validate()?;
+    ensure!(
+        (key_generation.activation_epoch
+            ..key_generation.activation_epoch + key_generation.num_active_epochs)
+            .contains(&activation_slot),
+        "The new key can't sign at the activation slot {activation_slot}"
+    );
+
+    let mut keystore = load_validator_keystores(&validator_registry_path, &[index])?
+        .pop()
+        .ok_or_else(|| anyhow!("Validator {index} has no key"))?;
+    let private_key = &mut keystore.private_key;
+    ensure!(
+        private_key
+            .get_activation_interval()
+            .contains(&activation_slot),
+        "The key of validator {index} can't sign at the activation slot {activation_slot}"
+    );
+    while !private_key
+        .get_prepared_interval()
+        .contains(&activation_slot)
+    {
+        let prepared_interval = private_key.get_prepared_interval();
+        private_key.prepare_signature();
+        ensure!(
+            private_key.get_prepared_interval() != prepared_interval,
+            "The key of validator {index} can't be prepared for slot {activation_slot}"
+        );
+    }
+
+    let (new_public_key, new_private_key) = key_generation.generate_key_pair(index)?;
+    let message = KeyRotation {
+        validator_index: index,
+        new_public_key,
+        activation_slot,
+    };
+    let signed_rotation = SignedKeyRotation {
+        signature: private_key.sign(&message.tree_hash_root(), activation_slot as u32)?,
+        message,
+    };
+
+    let keys_dir = keys_dir(&validator_registry_path);
+    let mut manifest = load_keys_manifest(&keys_dir)?;
+    let validator = manifest
+        .validators
+        .iter_mut()
+        .find(|validator| validator.index == index)
+        .ok_or_else(|| anyhow!("Validator {index} isn't in the keys manifest"))?;
+    ensure!(
+        validator
+            .rotations
+            .iter()
+            .all(|rotation| rotation.activation_slot != activation_slot),
+        "Validator {index} already rotates its key at slot {activation_slot}"
+    );
+    let privkey_file = format!("validator_{index}_sk_{activation_slot}.json");
+    write_secret_file(keys_dir.join(&privkey_file), new_private_key)?;
+    validator.rotations.push(KeyRotationRaw {
+        activation_slot,
+        public_key: new_public_key,
+        privkey_file,
+    });
+    fs::write(
+        keys_dir.join(MANIFEST_FILE),
+        serde_yaml::to_string(&manifest)?,
+    )?;
+
+    info!(
+        "Added the key validator {index} signs with from slot {activation_slot} to the keys \
+         manifest"
+    );
+    println!("{}", serde_json::to_string(&signed_rotation)?);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ream_validator_lean::registry::{keys_dir, load_key_rotations, load_keys_manifest};
+    use tempfile::TempDir;
+
+    use super::{RotateKeyConfig, rotate_key};
+    use crate::cli::generate_validator_registry::{
+        KeyGenerationConfig, KeyScheme, generate_validator_registry,
+    };
+
+    #[test]
+    fn test_rotate_key() {
+        let dir = TempDir::new().unwrap();
+        // Nodes only load keys of the scheme they sign with.
+        let key_generation = KeyGenerationConfig {
+            signature_scheme: KeyScheme::Prod,
+            num_active_epochs: 8,
+            ..Default::default()
+        };
+        generate_validator_registry(dir.path().to_path_buf(), 1, 2, 0, &key_generation).unwrap();
+        let validator_registry_path = dir.path().join("validators.yaml");
+        let config = |activation_slot| RotateKeyConfig {
+            validator_registry_path: validator_registry_path.clone(),
+            index: 0,
+            activation_slot,
+            key_generation: key_generation.clone(),
+        };
+
+        rotate_key(config(3)).unwrap();
+        let rotations = load_key_rotations(&validator_registry_path, &[0, 1]).unwrap();
+        assert_eq!(rotations.len(), 1);
+        assert_eq!(rotations[0].activation_slot, 3);
+        assert_eq!(rotations[0].keystore.index, 0);
+        let manifest = load_keys_manifest(&keys_dir(&validator_registry_path)).unwrap();
+        assert_ne!(
+            manifest.validators[0].public_key,
+            rotations[0].keystore.public_key
+        );
+
+        // A validator rotates at most once per slot, and only within the lifetime of its key.
+        assert!(rotate_key(config(3)).is_err());
+        assert!(rotate_key(config(8)).is_err());
+    }
+}
@@ -1,23 +1,63 @@
-use std::{net::IpAddr, path::PathBuf};
+use std::{net::IpAddr, path::PathBuf, sync::Arc, time::Duration};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
+use discv5::multiaddr::Multiaddr;
+use ream_chain_lean::{
+    block_source::BlockSourceEndpoint,
+    clock::{ClockDriftConfig, ClockDriftSource},
+    recent_blocks::DEFAULT_RECENT_BLOCKS_SLOTS,
+    service::DEFAULT_FINALITY_STALL_SLOTS,
+};
+use ream_fork_choice_lean::constants::{ATTESTATION_WRITE_BATCH_SIZE, MAX_ATTESTATION_POOL_SIZE};
 use ream_network_spec::{
     cli::{lean_devnet_parser, lean_network_parser},
     networks::{Devnet, LeanNetworkSpec},
 };
-use ream_p2p::bootnodes::Bootnodes;
+use ream_network_state_lean::NetworkState;
+use ream_p2p::{
+    bootnodes::Bootnodes,
+    constants::TARGET_PEER_COUNT,
+    network::lean::peer_limits::{
+        DEFAULT_MAX_INBOUND_PEERS, DEFAULT_MAX_INBOUND_PEERS_PER_IP,
+        DEFAULT_MAX_INBOUND_PEERS_PER_SUBNET, DEFAULT_MAX_OUTBOUND_PEERS, PeerLimits,
+    },
+};
+use ream_rpc_common::config::{RpcServerConfig, TlsConfig};
 
 use crate::cli::constants::{
-    DEFAULT_DEVNET, DEFAULT_HTTP_ADDRESS, DEFAULT_HTTP_ALLOW_ORIGIN, DEFAULT_HTTP_PORT,
-    DEFAULT_METRICS_ADDRESS, DEFAULT_METRICS_ENABLED, DEFAULT_METRICS_PORT, DEFAULT_SOCKET_ADDRESS,
-    DEFAULT_SOCKET_PORT,
+    DEFAULT_BLOCK_SOURCE_TIMEOUT_MS, DEFAULT_CLOCK_DRIFT_WARN_THRESHOLD_MS, DEFAULT_HTTP_ADDRESS,
+    DEFAULT_HTTP_ALLOW_ORIGIN, DEFAULT_HTTP_PORT, DEFAULT_METRICS_ADDRESS, DEFAULT_METRICS_ENABLED,
+    DEFAULT_METRICS_PORT, DEFAULT_NTP_SERVERS, DEFAULT_SOCKET_ADDRESS, DEFAULT_SOCKET_PORT,
 };
 
+/// What a lean node measures its clock offset against.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ClockSource {
+    /// Nothing, the clock offset isn't measured
+    #[default]
+    None,
+    /// The NTP servers of --ntp-servers
+    Ntp,
+    /// The times blocks of peers arrive at after the start of their slot
+    Peers,
+}
+
+/// Where a lean node serves its metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum MetricsServer {
+    /// On the separate metrics address and port
+    Separate,
+    /// At `GET /metrics` of the HTTP API, so only one port has to be opened
+    Http,
+    /// On both
+    Both,
+}
+
 #[derive(Debug, Parser)]
 pub struct LeanNodeConfig {
     #[arg(
       long,
-      help = "Provide a path to a YAML config file, or use 'ephemery' for the Ephemery network",
+      help = "Provide a path to a YAML or TOML config file, or the name of a built-in network: ephemery, devnet1 or devnet2",
       value_parser = lean_network_parser
   )]
     pub network: LeanNetworkSpec,
@@ -29,6 +69,54 @@ pub struct LeanNodeConfig {
     )]
     pub bootnodes: Bootnodes,
 
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Comma-delimited multiaddrs of peers to dial before the bootnodes and redial whenever they disconnect. They are never banned or disconnected for their score, and are preferred to sync from"
+    )]
+    pub trusted_peers: Vec<Multiaddr>,
+
+    #[arg(
+        long,
+        help = "Number of peers to keep connected, the lowest scoring peers beyond it are disconnected every 30 seconds",
+        default_value_t = TARGET_PEER_COUNT
+    )]
+    pub target_peers: usize,
+
+    #[arg(
+        long,
+        help = "Maximum number of peers connected to us, further peers are refused",
+        default_value_t = DEFAULT_MAX_INBOUND_PEERS
+    )]
+    pub max_inbound_peers: usize,
+
+    #[arg(
+        long,
+        help = "Maximum number of peers we connect to, further peers are refused",
+        default_value_t = DEFAULT_MAX_OUTBOUND_PEERS
+    )]
+    pub max_outbound_peers: usize,
+
+    #[arg(
+        long,
+        help = "Maximum number of peers connected to us from a single public IP address",
+        default_value_t = DEFAULT_MAX_INBOUND_PEERS_PER_IP
+    )]
+    pub max_inbound_peers_per_ip: usize,
+
+    #[arg(
+        long,
+        help = "Maximum number of peers connected to us from a single public /24 (IPv4) or /64 (IPv6) subnet",
+        default_value_t = DEFAULT_MAX_INBOUND_PEERS_PER_SUBNET
+    )]
+    pub max_inbound_peers_per_subnet: usize,
+
+    #[arg(
+        long,
+        help = "Map the QUIC port on the gateway with UPnP and advertise the external address in the ENR"
+    )]
+    pub enable_upnp: bool,
+
     #[arg(long, help = "The path to the validator registry")]
     pub validator_registry_path: PathBuf,
 
@@ -39,6 +127,12 @@ pub struct LeanNodeConfig {
     )]
     pub node_id: String,
 
+    #[arg(
+        long,
+        help = "Pick up validators added to or removed from the validator registry without restarting"
+    )]
+    pub reload_keystores: bool,
+
     #[arg(
         long,
         help = "The path to the hex encoded secp256k1 libp2p key",
@@ -46,6 +140,13 @@ pub struct LeanNodeConfig {
     )]
     pub private_key_path: Option<PathBuf>,
 
+    #[arg(
+        long,
+        help = "Delete the network key stored in the data directory, so the node starts with a new peer id",
+        conflicts_with = "private_key_path"
+    )]
+    pub purge_network_key: bool,
+
     #[arg(long, help = "Set P2P socket address", default_value_t = DEFAULT_SOCKET_ADDRESS)]
     pub socket_address: IpAddr,
 
@@ -61,6 +162,39 @@ pub struct LeanNodeConfig {
     #[arg(long, default_value_t = DEFAULT_HTTP_ALLOW_ORIGIN)]
     pub http_allow_origin: bool,
 
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Comma-delimited origins allowed to make cross-origin requests to the lean API, or '*' for any origin"
+    )]
+    pub http_allowed_origins: Vec<String>,
+
+    #[arg(
+        long,
+        requires = "http_tls_key",
+        help = "Path to a PEM encoded certificate chain to serve the lean API over HTTPS"
+    )]
+    pub http_tls_cert: Option<PathBuf>,
+
+    #[arg(
+        long,
+        requires = "http_tls_cert",
+        help = "Path to the PEM encoded private key of --http-tls-cert"
+    )]
+    pub http_tls_key: Option<PathBuf>,
+
+    #[arg(long, help = "Don't serve the debug namespace of the lean API")]
+    pub disable_debug_api: bool,
+
+    #[arg(long, help = "Don't serve the admin namespace of the lean API")]
+    pub disable_admin_api: bool,
+
+    #[arg(
+        long,
+        help = "Path to the hex encoded secret authenticating admin API requests, generated if missing [default: <data-dir>/admin_secret.hex]"
+    )]
+    pub admin_secret_path: Option<PathBuf>,
+
     #[arg(long = "metrics", help = "Enable metrics", default_value_t = DEFAULT_METRICS_ENABLED)]
     pub enable_metrics: bool,
 
@@ -70,6 +204,146 @@ pub struct LeanNodeConfig {
     #[arg(long, help = "Set metrics port", default_value_t = DEFAULT_METRICS_PORT)]
     pub metrics_port: u16,
 
-    #[arg(long, help = "Set which devnet version to run, options are 1 and 2", default_value = DEFAULT_DEVNET, value_parser = lean_devnet_parser)]
-    pub devnet: Devnet,
+    #[arg(
+        long,
+        help = "Where metrics are served with --metrics",
+        value_enum,
+        default_value_t = MetricsServer::Separate
+    )]
+    pub metrics_server: MetricsServer,
+
+    #[arg(long, help = "Override which devnet version the network runs, options are 1 and 2", value_parser = lean_devnet_parser)]
+    pub devnet: Option<Devnet>,
+
+    #[arg(
+        long,
+        help = "External block source to request candidate blocks from: an http(s) URL or a path to a Unix domain socket"
+    )]
+    pub block_source: Option<BlockSourceEndpoint>,
+
+    #[arg(
+        long,
+        help = "Milliseconds to wait for the external block source before producing the block locally",
+        default_value_t = DEFAULT_BLOCK_SOURCE_TIMEOUT_MS
+    )]
+    pub block_source_timeout_ms: u64,
+
+    #[arg(
+        long,
+        help = "Warn and raise the lean_finality_stalled metric once finality hasn't advanced for this many slots",
+        default_value_t = DEFAULT_FINALITY_STALL_SLOTS
+    )]
+    pub finality_stall_slots: u64,
+
+    #[arg(
+        long,
+        help = "Keep the blocks imported in this many recent slots in memory to answer BlocksByRoot requests, 0 disables the cache",
+        default_value_t = DEFAULT_RECENT_BLOCKS_SLOTS
+    )]
+    pub recent_blocks_slots: u64,
+
+    #[arg(
+        long,
+        help = "Export the proposals and attestations of every validator as metrics labelled by validator index"
+    )]
+    pub validator_performance_metrics: bool,
+
+    #[arg(
+        long,
+        help = "Only serve blocks and states from the existing database over the lean API, without running the chain, network or validators"
+    )]
+    pub read_only: bool,
+
+    #[arg(
+        long,
+        help = "Rebuild the slot and state root indices from the block table if the startup integrity check finds them inconsistent"
+    )]
+    pub repair: bool,
+
+    #[arg(
+        long,
+        help = "Maximum number of attestations each of the new and known attestation pools holds, raised to the number of validators if there are more",
+        default_value_t = MAX_ATTESTATION_POOL_SIZE
+    )]
+    pub attestation_pool_size: u64,
+
+    #[arg(
+        long,
+        help = "Number of gossip attestations buffered before they are written in one transaction, the buffer is also written every interval. 1 writes each attestation immediately",
+        default_value_t = ATTESTATION_WRITE_BATCH_SIZE
+    )]
+    pub attestation_batch_size: usize,
+
+    #[arg(
+        long,
+        help = "Gossip blob sidecars and reject blocks whose blob sidecars haven't all arrived, for data availability experiments on devnets"
+    )]
+    pub data_availability: bool,
+
+    #[arg(
+        long,
+        help = "What to measure the system clock offset against, and warn if it drifts",
+        value_enum,
+        default_value_t = ClockSource::None
+    )]
+    pub clock_source: ClockSource,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Comma-delimited NTP servers to measure the system clock offset against with --clock-source ntp",
+        default_value = DEFAULT_NTP_SERVERS
+    )]
+    pub ntp_servers: Vec<String>,
+
+    #[arg(
+        long,
+        help = "Warn when the system clock is off by more than this many milliseconds",
+        default_value_t = DEFAULT_CLOCK_DRIFT_WARN_THRESHOLD_MS
+    )]
+    pub clock_drift_warn_threshold_ms: u64,
+}
+
+impl LeanNodeConfig {
+    pub fn rpc_server_config(&self) -> RpcServerConfig {
+        RpcServerConfig::new(self.http_address, self.http_port, self.http_allow_origin)
+            .with_allowed_origins(self.http_allowed_origins.clone())
+            .with_tls(
+                self.http_tls_cert
+                    .clone()
+                    .zip(self.http_tls_key.clone())
+                    .map(|(cert_path, key_path)| TlsConfig {
+                        cert_path,
+                        key_path,
+                    }),
+            )
+            .with_namespaces(!self.disable_debug_api, !self.disable_admin_api)
+            .with_metrics_endpoint(
+                self.enable_metrics && self.metrics_server != MetricsServer::Separate,
+            )
+    }
+
+    pub fn peer_limits(&self) -> PeerLimits {
+        PeerLimits {
+            target_peers: self.target_peers,
+            max_inbound_peers: self.max_inbound_peers,
+            max_outbound_peers: self.max_outbound_peers,
+            max_inbound_peers_per_ip: self.max_inbound_peers_per_ip,
+            max_inbound_peers_per_subnet: self.max_inbound_peers_per_subnet,
+        }
+    }
+
+    /// Returns where to measure the clock offset against, or `None` if it isn't measured. Block
+    /// arrival times are read from `network_state`.
+    pub fn clock_drift_config(&self, network_state: Arc<NetworkState>) -> Option<ClockDriftConfig> {
+        let source = match self.clock_source {
+            ClockSource::None => return None,
+            ClockSource::Ntp => ClockDriftSource::Ntp(self.ntp_servers.clone()),
+            ClockSource::Peers => ClockDriftSource::Peers(network_state),
+        };
+        Some(ClockDriftConfig {
+            source,
+            warn_threshold: Duration::from_millis(self.clock_drift_warn_threshold_ms),
+        })
+    }
 }
@@ -0,0 +1,82 @@
+use std::{net::IpAddr, path::PathBuf, time::Duration};
+
+use clap::Parser;
+use ream_network_spec::{
+    cli::{lean_devnet_parser, lean_network_parser},
+    networks::{Devnet, LeanNetworkSpec},
+};
+use ream_rpc_common::config::RpcServerConfig;
+use url::Url;
+
+use crate::cli::{
+    constants::{
+        DEFAULT_HTTP_ADDRESS, DEFAULT_KEY_MANAGER_HTTP_PORT, DEFAULT_LEAN_NODE_URL,
+        DEFAULT_REQUEST_TIMEOUT,
+    },
+    validator_node::duration_parser,
+};
+
+#[derive(Debug, Parser)]
+pub struct LeanValidatorConfig {
+    #[arg(long, help = "Set HTTP url of the lean node to validate through", default_value = DEFAULT_LEAN_NODE_URL)]
+    pub lean_node_url: Url,
+
+    #[arg(long, help = "Set HTTP request timeout for lean api calls", default_value = DEFAULT_REQUEST_TIMEOUT, value_parser = duration_parser)]
+    pub request_timeout: Duration,
+
+    #[arg(
+        long,
+        help = "Path to the hex encoded admin secret of the lean node, authenticating block production and submission requests"
+    )]
+    pub admin_secret_path: PathBuf,
+
+    #[arg(
+        long,
+        help = "Provide a path to a YAML or TOML config file, or the name of a built-in network: ephemery, devnet1 or devnet2",
+        value_parser = lean_network_parser
+    )]
+    pub network: LeanNetworkSpec,
+
+    #[arg(long, help = "Override which devnet version the network runs, options are 1 and 2", value_parser = lean_devnet_parser)]
+    pub devnet: Option<Devnet>,
+
+    #[arg(long, help = "The path to the validator registry")]
+    pub validator_registry_path: PathBuf,
+
+    #[arg(
+        default_value = "ream_0",
+        long,
+        help = "Node identifier for validator registry (e.g., 'ream_0', 'zeam_0')"
+    )]
+    pub node_id: String,
+
+    #[arg(
+        long,
+        help = "Pick up validators added to or removed from the validator registry without restarting"
+    )]
+    pub reload_keystores: bool,
+
+    #[arg(
+        long = "keymanager",
+        help = "Serve the keymanager API to list, import and delete keystores"
+    )]
+    pub enable_keymanager: bool,
+
+    #[arg(long, help = "Set HTTP address of the keymanager API", default_value_t = DEFAULT_HTTP_ADDRESS)]
+    pub keymanager_address: IpAddr,
+
+    #[arg(long, help = "Set HTTP port of the keymanager API", default_value_t = DEFAULT_KEY_MANAGER_HTTP_PORT)]
+    pub keymanager_port: u16,
+
+    #[arg(
+        long,
+        help = "Path to the hex encoded token authenticating keymanager API requests, generated if missing [default: <data-dir>/keymanager_token.hex]"
+    )]
+    pub keymanager_token_path: Option<PathBuf>,
+}
+
+impl LeanValidatorConfig {
+    pub fn keymanager_server_config(&self) -> RpcServerConfig {
+        RpcServerConfig::new(self.keymanager_address, self.keymanager_port, false)
+    }
+}
@@ -0,0 +1,26 @@
+use clap::ValueEnum;
+use tracing_subscriber::EnvFilter;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum LogFormat {
+    /// Human readable lines
+    #[default]
+    Text,
+    /// One JSON object per event, for log collectors
+    Json,
+}
+
+/// Parses comma-delimited `target=level` directives, e.g. `ream_fork_choice=debug,libp2p=warn`.
+pub fn log_levels_parser(s: &str) -> Result<String, String> {
+    for directive in s.split(',') {
+        if !directive.contains('=') {
+            return Err(format!(
+                "log level {directive:?} must have the form target=level"
+            ));
+        }
+    }
+    EnvFilter::builder()
+        .parse(s)
+        .map_err(|err| format!("invalid log levels {s:?}: {err}"))?;
+    Ok(s.to_string())
+}
@@ -1,10 +1,19 @@
 pub mod account_manager;
 pub mod beacon_node;
 pub mod constants;
+pub mod db;
+pub mod dev;
+pub mod devnet;
+pub mod era;
 pub mod generate_private_key;
 pub mod generate_validator_registry;
 pub mod import_keystores;
+pub mod keys;
 pub mod lean_node;
+pub mod lean_validator;
+pub mod logging;
+pub mod node_id;
+pub mod snapshot;
 pub mod validator_node;
 pub mod verbosity;
 pub mod voluntary_exit;
@@ -13,13 +22,28 @@ use std::path::PathBuf;
 
 use clap::{Parser, Subcommand};
 use ream_node::version::FULL_VERSION;
+use ream_storage::{
+    db::{Chain, DatabaseOptions, ResetScope},
+    durability::DurabilityProfile,
+};
+use url::Url;
 
 use crate::cli::{
     account_manager::AccountManagerConfig,
     beacon_node::BeaconNodeConfig,
+    constants::{DEFAULT_DB_CACHE_SIZE_MIB, DEFAULT_DB_FLUSH_INTERVAL_SECS},
+    db::DbConfig,
+    dev::DevConfig,
+    devnet::DevnetConfig,
+    era::{ExportConfig, ImportConfig},
     generate_private_key::GeneratePrivateKeyConfig,
     generate_validator_registry::GenerateValidatorRegistryConfig,
+    keys::KeysConfig,
     lean_node::LeanNodeConfig,
+    lean_validator::LeanValidatorConfig,
+    logging::{LogFormat, log_levels_parser},
+    node_id::NodeIdConfig,
+    snapshot::SnapshotConfig,
     validator_node::ValidatorNodeConfig,
     verbosity::{Verbosity, verbosity_parser},
     voluntary_exit::VoluntaryExitConfig,
@@ -32,6 +56,16 @@ pub struct Cli {
     #[arg(short, long, default_value = "3", value_parser = verbosity_parser)]
     pub verbosity: Verbosity,
 
+    #[arg(long, help = "Format of the logs", value_enum, default_value_t = LogFormat::Text)]
+    pub log_format: LogFormat,
+
+    #[arg(
+        long,
+        help = "Comma-delimited log levels of single targets on top of the verbosity, e.g. ream_fork_choice=debug,libp2p=warn",
+        value_parser = log_levels_parser
+    )]
+    pub log_levels: Option<String>,
+
     #[command(subcommand)]
     pub command: Commands,
 
@@ -50,6 +84,77 @@ pub struct Cli {
 
     #[arg(long, help = "Purges the database.")]
     pub purge_db: bool,
+
+    #[arg(
+        long,
+        help = "What --purge-db removes: all of the data directory, only the lean tables and lean slashing protection, or only the beacon tables",
+        default_value = "all"
+    )]
+    pub purge_scope: ResetScope,
+
+    #[arg(
+        long,
+        alias = "force",
+        help = "Purge the database without asking for confirmation, for containers and services without a terminal"
+    )]
+    pub yes: bool,
+
+    #[arg(
+        long,
+        help = "Keep the lean and beacon chains in separate database files, lean.redb and beacon.redb, instead of the shared ream.redb"
+    )]
+    pub separate_dbs: bool,
+
+    #[arg(
+        long,
+        help = "Cache size of each database in MiB",
+        default_value_t = DEFAULT_DB_CACHE_SIZE_MIB
+    )]
+    pub db_cache_size: usize,
+
+    #[arg(
+        long,
+        help = "Cache size of the lean database in MiB, overriding --db-cache-size"
+    )]
+    pub lean_db_cache_size: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Cache size of the beacon database in MiB, overriding --db-cache-size"
+    )]
+    pub beacon_db_cache_size: Option<usize>,
+
+    #[arg(
+        long,
+        help = "Durability of the database commits of a node: immediate fsyncs every commit, eventual fsyncs every --db-flush-interval and loses the commits since the last flush on a crash",
+        default_value = "immediate"
+    )]
+    pub db_durability: DurabilityProfile,
+
+    #[arg(
+        long,
+        help = "Seconds between flushes of the database to disk with --db-durability eventual",
+        default_value_t = DEFAULT_DB_FLUSH_INTERVAL_SECS
+    )]
+    pub db_flush_interval: u64,
+
+    #[arg(
+        long,
+        help = "OTLP/HTTP endpoint to export tracing spans to (e.g. http://localhost:4318/v1/traces)"
+    )]
+    pub tracing_endpoint: Option<Url>,
+}
+
+impl Cli {
+    /// The database file and cache size `chain` is opened with.
+    pub fn db_options(&self, chain: Chain) -> DatabaseOptions {
+        let cache_size_mib = match chain {
+            Chain::Lean => self.lean_db_cache_size,
+            Chain::Beacon => self.beacon_db_cache_size,
+        }
+        .unwrap_or(self.db_cache_size);
+        DatabaseOptions::for_chain(chain, self.separate_dbs, cache_size_mib * 1_024 * 1_024)
+    }
 }
 
 #[derive(Debug, Subcommand)]
@@ -58,6 +163,14 @@ pub enum Commands {
     #[command(name = "lean_node")]
     LeanNode(Box<LeanNodeConfig>),
 
+    /// Start a lean validator client connected to a lean node
+    #[command(name = "lean_validator")]
+    LeanValidator(Box<LeanValidatorConfig>),
+
+    /// Start a single lean node running every validator of a new local chain
+    #[command(name = "dev")]
+    Dev(Box<DevConfig>),
+
     /// Start the beacon node
     #[command(name = "beacon_node")]
     BeaconNode(Box<BeaconNodeConfig>),
@@ -81,6 +194,34 @@ pub enum Commands {
     /// Generate a validator registry config
     #[command(name = "generate_validator_registry")]
     GenerateKeystore(Box<GenerateValidatorRegistryConfig>),
+
+    /// Recover validator keys generated from a seed phrase, and rotate them
+    #[command(name = "keys")]
+    Keys(Box<KeysConfig>),
+
+    /// Print the peer id, ENR, multiaddr and gossip topic fork of the lean node's network key
+    #[command(name = "node_id", alias = "node-id")]
+    NodeId(Box<NodeIdConfig>),
+
+    /// Generate local multi-node lean devnets
+    #[command(name = "devnet")]
+    Devnet(Box<DevnetConfig>),
+
+    /// Inspect and maintain the lean database offline
+    #[command(name = "db")]
+    Db(Box<DbConfig>),
+
+    /// Export finalized lean blocks and states to an era archive
+    #[command(name = "export")]
+    Export(Box<ExportConfig>),
+
+    /// Import lean blocks and states from an era archive
+    #[command(name = "import")]
+    Import(Box<ImportConfig>),
+
+    /// Write a consistent copy of the database
+    #[command(name = "snapshot")]
+    Snapshot(Box<SnapshotConfig>),
 }
 
 #[cfg(test)]
@@ -90,11 +231,19 @@ mod tests {
         time::Duration,
     };
 
-    use ream_network_spec::networks::Network;
+    use ream_network_spec::networks::{Devnet, IntervalDuties, Network};
     use url::Url;
 
     use super::*;
-    use crate::cli::constants::DEFAULT_BEACON_API_ENDPOINT;
+    use crate::cli::{
+        constants::{
+            DEFAULT_BEACON_API_ENDPOINT, DEFAULT_DEV_GENESIS_DELAY, DEFAULT_DEVNET_IP,
+            DEFAULT_SOCKET_PORT,
+        },
+        db::{BlockId, DbCommands, DumpFormat},
+        devnet::DevnetCommands,
+        lean_node::MetricsServer,
+    };
 
     #[test]
     fn test_cli_lean_node_command() {
@@ -124,6 +273,8 @@ mod tests {
                 // Verify the network spec was loaded from the YAML file (sample_spec.yml)
                 assert_eq!(config.network.seconds_per_slot, 4);
                 assert_eq!(config.network.justification_lookback_slots, 3);
+                assert_eq!(config.network.intervals_per_slot, 4);
+                assert_eq!(config.network.interval_duties, IntervalDuties::default());
                 // Will be set later in main.rs
                 assert_eq!(config.network.num_validators, 3);
 
@@ -136,6 +287,177 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_lean_node_rpc_options() {
+        let cli = Cli::parse_from([
+            "program",
+            "lean_node",
+            "--network",
+            "./assets/lean/config.yaml",
+            "--validator-registry-path",
+            "./assets/lean/validator_registry.yml",
+            "--http-allowed-origins",
+            "http://localhost:3000,https://dashboard.example",
+            "--http-tls-cert",
+            "cert.pem",
+            "--http-tls-key",
+            "key.pem",
+            "--disable-admin-api",
+        ]);
+
+        match cli.command {
+            Commands::LeanNode(config) => {
+                let server_config = config.rpc_server_config();
+                assert_eq!(
+                    server_config.http_allowed_origins,
+                    vec!["http://localhost:3000", "https://dashboard.example"]
+                );
+                let tls = server_config.tls.expect("TLS should be configured");
+                assert_eq!(tls.cert_path, PathBuf::from("cert.pem"));
+                assert_eq!(tls.key_path, PathBuf::from("key.pem"));
+                assert!(server_config.enable_debug_api);
+                assert!(!server_config.enable_admin_api);
+            }
+            _ => unreachable!("This test should only validate the lean node cli"),
+        }
+
+        let result = Cli::try_parse_from([
+            "program",
+            "lean_node",
+            "--network",
+            "./assets/lean/config.yaml",
+            "--validator-registry-path",
+            "./assets/lean/validator_registry.yml",
+            "--http-tls-cert",
+            "cert.pem",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_lean_node_metrics_server() {
+        let lean_node = |args: &[&str]| {
+            let cli = Cli::parse_from(
+                [
+                    "program",
+                    "lean_node",
+                    "--network",
+                    "./assets/lean/config.yaml",
+                    "--validator-registry-path",
+                    "./assets/lean/validator_registry.yml",
+                ]
+                .iter()
+                .chain(args)
+                .copied(),
+            );
+            match cli.command {
+                Commands::LeanNode(config) => config,
+                _ => unreachable!("This test should only validate the lean node cli"),
+            }
+        };
+
+        let config = lean_node(&["--metrics"]);
+        assert_eq!(config.metrics_server, MetricsServer::Separate);
+        assert!(!config.rpc_server_config().enable_metrics_endpoint);
+
+        let config = lean_node(&["--metrics", "--metrics-server", "http"]);
+        assert_eq!(config.metrics_server, MetricsServer::Http);
+        assert!(config.rpc_server_config().enable_metrics_endpoint);
+
+        // The endpoint is only served with metrics enabled.
+        let config = lean_node(&["--metrics-server", "both"]);
+        assert!(!config.rpc_server_config().enable_metrics_endpoint);
+    }
+
+    #[test]
+    fn test_cli_lean_named_network() {
+        let cli = Cli::parse_from([
+            "program",
+            "lean_node",
+            "--network",
+            "devnet2",
+            "--validator-registry-path",
+            "./assets/lean/validator_registry.yml",
+        ]);
+
+        match cli.command {
+            Commands::LeanNode(config) => {
+                assert_eq!(config.network.devnet, Devnet::Two);
+                assert_eq!(config.devnet, None);
+            }
+            _ => unreachable!("This test should only validate the lean node cli"),
+        }
+
+        let result = Cli::try_parse_from([
+            "program",
+            "lean_node",
+            "--network",
+            "./assets/lean/missing.yaml",
+            "--validator-registry-path",
+            "./assets/lean/validator_registry.yml",
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_cli_lean_validator_command() {
+        let cli = Cli::parse_from([
+            "program",
+            "lean_validator",
+            "--lean-node-url",
+            "http://10.0.0.1:5052",
+            "--request-timeout",
+            "5",
+            "--admin-secret-path",
+            "/data/admin_secret.hex",
+            "--network",
+            "./assets/lean/config.yaml",
+            "--validator-registry-path",
+            "./assets/lean/validator_registry.yml",
+            "--node-id",
+            "ream_1",
+        ]);
+
+        match cli.command {
+            Commands::LeanValidator(config) => {
+                assert_eq!(
+                    config.lean_node_url,
+                    Url::parse("http://10.0.0.1:5052").expect("Invalid URL")
+                );
+                assert_eq!(config.request_timeout, Duration::from_secs(5));
+                assert_eq!(
+                    config.admin_secret_path,
+                    PathBuf::from("/data/admin_secret.hex")
+                );
+                assert_eq!(config.node_id, "ream_1");
+                assert_eq!(config.network.seconds_per_slot, 4);
+            }
+            _ => unreachable!("This test should only validate the lean validator cli"),
+        }
+    }
+
+    #[test]
+    fn test_cli_dev_command() {
+        let cli = Cli::parse_from([
+            "program",
+            "dev",
+            "--validators",
+            "8",
+            "--seconds-per-slot",
+            "2",
+        ]);
+
+        match cli.command {
+            Commands::Dev(config) => {
+                assert_eq!(config.validators, 8);
+                assert_eq!(config.genesis_delay, DEFAULT_DEV_GENESIS_DELAY);
+                assert_eq!(config.seconds_per_slot, Some(2));
+                assert!(!config.regenerate_keys);
+            }
+            _ => unreachable!("This test should only validate the dev cli"),
+        }
+    }
+
     #[test]
     fn test_cli_beacon_node_command() {
         let cli = Cli::parse_from([
@@ -230,6 +552,197 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cli_db_command() {
+        let cli = Cli::parse_from([
+            "program",
+            "db",
+            "get-block",
+            "42",
+            "--format",
+            "ssz",
+            "--output",
+            "block.ssz",
+        ]);
+
+        match cli.command {
+            Commands::Db(config) => match config.command {
+                DbCommands::GetBlock(get_block_config) => {
+                    assert_eq!(get_block_config.block_id, BlockId::Slot(42));
+                    assert_eq!(get_block_config.output.format, DumpFormat::Ssz);
+                    assert_eq!(
+                        get_block_config.output.output,
+                        Some(PathBuf::from("block.ssz"))
+                    );
+                }
+                _ => unreachable!("Expected the get-block subcommand"),
+            },
+            _ => unreachable!("This test should only validate the db cli"),
+        }
+
+        let cli = Cli::parse_from(["program", "db", "prune", "--before-slot", "100"]);
+        match cli.command {
+            Commands::Db(config) => match config.command {
+                DbCommands::Prune(prune_config) => assert_eq!(prune_config.before_slot, 100),
+                _ => unreachable!("Expected the prune subcommand"),
+            },
+            _ => unreachable!("This test should only validate the db cli"),
+        }
+    }
+
+    #[test]
+    fn test_cli_devnet_command() {
+        let cli = Cli::parse_from([
+            "program",
+            "devnet",
+            "generate",
+            "--output",
+            "local-devnet",
+            "--number-of-nodes",
+            "3",
+            "--base-http-port",
+            "6052",
+        ]);
+
+        match cli.command {
+            Commands::Devnet(config) => match config.command {
+                DevnetCommands::Generate(generate_config) => {
+                    assert_eq!(generate_config.output, PathBuf::from("local-devnet"));
+                    assert_eq!(generate_config.number_of_nodes, 3);
+                    assert_eq!(generate_config.number_of_validators_per_node, 1);
+                    assert_eq!(generate_config.ip, DEFAULT_DEVNET_IP);
+                    assert_eq!(generate_config.base_socket_port, DEFAULT_SOCKET_PORT);
+                    assert_eq!(generate_config.base_http_port, 6052);
+                }
+            },
+            _ => unreachable!("This test should only validate the devnet cli"),
+        }
+    }
+
+    #[test]
+    fn test_cli_node_id_command() {
+        let cli = Cli::parse_from([
+            "program",
+            "node-id",
+            "--network",
+            "./assets/lean/config.yaml",
+            "--validator-registry-path",
+            "./assets/lean/validator_registry.yml",
+            "--socket-address",
+            "10.0.0.1",
+        ]);
+
+        match cli.command {
+            Commands::NodeId(config) => {
+                assert_eq!(config.private_key_path, None);
+                assert_eq!(
+                    config.socket_address,
+                    IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1))
+                );
+                assert_eq!(config.socket_port, DEFAULT_SOCKET_PORT);
+            }
+            _ => unreachable!("This test should only validate the node_id cli"),
+        }
+    }
+
+    #[test]
+    fn test_cli_log_flags() {
+        let cli = Cli::parse_from([
+            "program",
+            "--log-format",
+            "json",
+            "--log-levels",
+            "ream_fork_choice=debug,libp2p=warn",
+            "beacon_node",
+        ]);
+        assert_eq!(cli.log_format, LogFormat::Json);
+        assert_eq!(
+            cli.log_levels.as_deref(),
+            Some("ream_fork_choice=debug,libp2p=warn")
+        );
+
+        let cli = Cli::parse_from(["program", "beacon_node"]);
+        assert_eq!(cli.log_format, LogFormat::Text);
+        assert_eq!(cli.log_levels, None);
+
+        assert!(Cli::try_parse_from(["program", "--log-levels", "debug", "beacon_node"]).is_err());
+        assert!(
+            Cli::try_parse_from(["program", "--log-levels", "libp2p=loud", "beacon_node"]).is_err()
+        );
+    }
+
+    #[test]
+    fn test_cli_purge_flags() {
+        let cli = Cli::parse_from([
+            "program",
+            "--purge-db",
+            "--purge-scope",
+            "lean",
+            "--force",
+            "beacon_node",
+        ]);
+        assert!(cli.purge_db);
+        assert_eq!(cli.purge_scope, ResetScope::Lean);
+        assert!(cli.yes);
+
+        let cli = Cli::parse_from(["program", "beacon_node"]);
+        assert_eq!(cli.purge_scope, ResetScope::All);
+        assert!(!cli.yes);
+    }
+
+    #[test]
+    fn test_cli_db_options() {
+        let cli = Cli::parse_from([
+            "program",
+            "--separate-dbs",
+            "--lean-db-cache-size",
+            "256",
+            "beacon_node",
+        ]);
+        assert!(cli.separate_dbs);
+        assert_eq!(
+            cli.db_options(Chain::Lean),
+            DatabaseOptions {
+                file_name: "lean.redb".to_string(),
+                cache_size: 256 * 1_024 * 1_024,
+            }
+        );
+        assert_eq!(
+            cli.db_options(Chain::Beacon).file_name,
+            "beacon.redb".to_string()
+        );
+
+        let cli = Cli::parse_from(["program", "beacon_node"]);
+        assert_eq!(cli.db_options(Chain::Lean), DatabaseOptions::default());
+        assert_eq!(cli.db_options(Chain::Beacon), DatabaseOptions::default());
+        assert_eq!(cli.db_durability, DurabilityProfile::Immediate);
+        assert_eq!(cli.db_flush_interval, DEFAULT_DB_FLUSH_INTERVAL_SECS);
+
+        let cli = Cli::parse_from([
+            "program",
+            "--db-cache-size",
+            "512",
+            "--beacon-db-cache-size",
+            "2048",
+            "--db-durability",
+            "eventual",
+            "--db-flush-interval",
+            "2",
+            "beacon_node",
+        ]);
+        assert_eq!(cli.db_options(Chain::Lean).cache_size, 512 * 1_024 * 1_024);
+        assert_eq!(
+            cli.db_options(Chain::Beacon).cache_size,
+            2048 * 1_024 * 1_024
+        );
+        assert_eq!(cli.db_durability, DurabilityProfile::Eventual);
+        assert_eq!(cli.db_flush_interval, 2);
+
+        assert!(
+            Cli::try_parse_from(["program", "--db-durability", "never", "beacon_node"]).is_err()
+        );
+    }
+
     #[test]
     fn test_verbosity_levels() {
         // Test error level (1)
@@ -0,0 +1,84 @@
+use std::{net::IpAddr, path::PathBuf};
+
+use clap::Parser;
+use discv5::multiaddr::{Multiaddr, Protocol};
+use ream_fork_choice_lean::genesis::load_genesis;
+use ream_network_spec::{
+    cli::{lean_devnet_parser, lean_network_parser},
+    networks::{Devnet, LeanNetworkSpec},
+};
+use ream_p2p::network::lean::{
+    build_local_enr, enr_signing_key, load_or_generate_network_key, read_network_key,
+};
+use tree_hash::TreeHash;
+
+use crate::cli::constants::{
+    DEFAULT_NETWORK_KEY_FILE, DEFAULT_SOCKET_ADDRESS, DEFAULT_SOCKET_PORT,
+};
+
+#[derive(Debug, Parser)]
+pub struct NodeIdConfig {
+    #[arg(
+        long,
+        help = "Provide a path to a YAML or TOML config file, or the name of a built-in network: ephemery, devnet1 or devnet2",
+        value_parser = lean_network_parser
+    )]
+    pub network: LeanNetworkSpec,
+
+    #[arg(long, help = "Override which devnet version the network runs, options are 1 and 2", value_parser = lean_devnet_parser)]
+    pub devnet: Option<Devnet>,
+
+    #[arg(
+        long,
+        help = "The path to the validator registry, the topic fork is computed from its genesis"
+    )]
+    pub validator_registry_path: PathBuf,
+
+    #[arg(
+        long,
+        help = "The path to the hex encoded secp256k1 libp2p key, instead of the network key stored in the data directory",
+        alias = "node-key"
+    )]
+    pub private_key_path: Option<PathBuf>,
+
+    #[arg(long, help = "The IP address other nodes reach the node at", default_value_t = DEFAULT_SOCKET_ADDRESS)]
+    pub socket_address: IpAddr,
+
+    #[arg(long, help = "The QUIC port of the node", default_value_t = DEFAULT_SOCKET_PORT)]
+    pub socket_port: u16,
+}
+
+/// Prints the identity a lean node started with the same key and network would have. Without a
+/// `--private-key-path`, the network key in `data_dir` is used, generating it the way the node
+/// would on its first start.
+pub fn run_node_id(config: NodeIdConfig, data_dir: PathBuf) -> anyhow::Result<()> {
+    let mut network = config.network;
+    if let Some(devnet) = config.devnet {
+        network.devnet = devnet;
+    }
+
+    let local_key = match &config.private_key_path {
+        Some(path) => read_network_key(path)?,
+        None => load_or_generate_network_key(&data_dir.join(DEFAULT_NETWORK_KEY_FILE))?,
+    };
+    let peer_id = local_key.public().to_peer_id();
+    let enr = build_local_enr(
+        &enr_signing_key(&local_key)?,
+        config.socket_address,
+        config.socket_port,
+    )?;
+
+    let mut multi_addr = Multiaddr::from(config.socket_address);
+    multi_addr.push(Protocol::Udp(config.socket_port));
+    multi_addr.push(Protocol::QuicV1);
+    multi_addr.push(Protocol::P2p(peer_id));
+
+    let (genesis_block, _) = load_genesis(&network, &config.validator_registry_path)?;
+    let topic_fork = network.topic_fork(genesis_block.tree_hash_root(), network.current_slot());
+
+    println!("Peer ID:     {peer_id}");
+    println!("ENR:         {}", enr.to_base64());
+    println!("Multiaddr:   {multi_addr}");
+    println!("Topic fork:  {topic_fork}");
+    Ok(())
+}
@@ -0,0 +1,12 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+
+#[derive(Debug, Parser)]
+pub struct SnapshotConfig {
+    #[arg(
+        long,
+        help = "Directory to write the snapshot to, usable as --data-dir. Must not already contain a database"
+    )]
+    pub output: PathBuf,
+}
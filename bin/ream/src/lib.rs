@@ -1,2 +1,3 @@
 pub mod cli;
 pub mod startup_message;
+pub mod telemetry;
@@ -8,9 +8,11 @@ use std::{
 };
 
 use alloy_primitives::hex;
+use anyhow::ensure;
 use bip39::Mnemonic;
-use clap::Parser;
+use clap::{CommandFactory, FromArgMatches, parser::ValueSource};
 use libp2p_identity::secp256k1;
+use opentelemetry::trace::TracerProvider;
 use rand::SeedableRng;
 use rand_chacha::ChaCha20Rng;
 use ream::{
@@ -18,27 +20,42 @@ use ream::{
         Cli, Commands,
         account_manager::AccountManagerConfig,
         beacon_node::BeaconNodeConfig,
+        constants::{
+            DEFAULT_ADMIN_SECRET_FILE, DEFAULT_IMPORTED_KEYSTORES_DIR,
+            DEFAULT_KEYMANAGER_TOKEN_FILE, DEFAULT_LOG_FILTER_FILE, DEFAULT_NETWORK_KEY_FILE,
+            DEFAULT_SLASHING_PROTECTION_FILE, DEFAULT_SNAPSHOTS_DIR,
+        },
+        db::run_db,
+        dev::{DEV_DB_DIR, DEV_DIR, prepare_dev_node},
+        devnet::run_devnet,
+        era::{run_export, run_import},
         generate_private_key::GeneratePrivateKeyConfig,
         generate_validator_registry::run_generate_validator_registry,
         import_keystores::{load_keystore_directory, load_password_from_config, process_password},
-        lean_node::LeanNodeConfig,
+        keys::run_keys,
+        lean_node::{LeanNodeConfig, MetricsServer},
+        lean_validator::LeanValidatorConfig,
+        logging::LogFormat,
+        node_id::run_node_id,
+        snapshot::SnapshotConfig,
         validator_node::ValidatorNodeConfig,
         voluntary_exit::VoluntaryExitConfig,
     },
     startup_message::startup_message,
+    telemetry::init_tracer_provider,
 };
 use ream_account_manager::{message_types::MessageType, seed::derive_seed_with_user_input};
 use ream_api_types_beacon::id::ValidatorID;
 use ream_api_types_common::id::ID;
 use ream_chain_lean::{
-    messages::LeanChainServiceMessage, p2p_request::LeanP2PRequest, service::LeanChainService,
+    clock::monitor_clock_drift, messages::LeanChainServiceMessage, p2p_request::LeanP2PRequest,
+    service::LeanChainService,
 };
 use ream_checkpoint_sync::initialize_db_from_checkpoint;
 use ream_consensus_lean::{
     attestation::{Attestation, AttestationData},
     block::{BlockWithAttestation, SignedBlockWithAttestation},
     checkpoint::Checkpoint,
-    validator::Validator,
 };
 use ream_consensus_misc::{
     constants::beacon::set_genesis_validator_root, misc::compute_epoch_at_slot,
@@ -46,10 +63,15 @@ use ream_consensus_misc::{
 use ream_executor::ReamExecutor;
 use ream_fork_choice_lean::{genesis as lean_genesis, store::Store};
 use ream_keystore::keystore::EncryptedKeystore;
+use ream_metrics::register_process_metrics;
 use ream_network_manager::service::NetworkManagerService;
 use ream_network_spec::networks::{
     beacon_network_spec, lean_network_spec, set_beacon_network_spec, set_lean_network_spec,
 };
+use ream_node::diagnostics::{
+    LogLevelController, debug_invariants, log_level_controller, set_log_level_controller,
+    startup_filters,
+};
 use ream_operation_pool::OperationPool;
 use ream_p2p::{
     gossipsub::lean::{
@@ -58,13 +80,16 @@ use ream_p2p::{
     },
     network::lean::{LeanNetworkConfig, LeanNetworkService},
 };
-use ream_post_quantum_crypto::leansig::{
-    private_key::PrivateKey as LeanSigPrivateKey, public_key::PublicKey,
-};
+use ream_post_quantum_crypto::leansig::private_key::PrivateKey as LeanSigPrivateKey;
 use ream_rpc_common::config::RpcServerConfig;
+use ream_rpc_lean::{
+    auth::AdminAuth,
+    handlers::{admin::SnapshotDir, duties::LocalValidators},
+};
 use ream_storage::{
-    db::{ReamDB, reset_db},
+    db::{Chain, ReamDB, ResetScope, lean::LeanDB, read_only::ReadOnlyLeanDB, reset_db},
     dir::setup_data_dir,
+    durability::{DurabilityProfile, set_durability_profile},
     tables::table::REDBTable,
 };
 use ream_sync::rwlock::Writer;
@@ -73,46 +98,128 @@ use ream_validator_beacon::{
     voluntary_exit::process_voluntary_exit,
 };
 use ream_validator_lean::{
-    registry::load_validator_registry, service::ValidatorService as LeanValidatorService,
+    chain_client::{channel::ChannelChainClient, http::HttpChainClient},
+    keymanager::ImportedKeystores,
+    registry::{load_key_rotations, load_validator_registry},
+    service::ValidatorService as LeanValidatorService,
+    slashing_protection::SlashingProtection,
 };
 use ssz_types::VariableList;
-use tokio::{sync::mpsc, time::Instant};
-use tracing::{error, info};
-use tracing_subscriber::EnvFilter;
+use tokio::{
+    signal::unix::{SignalKind, signal},
+    sync::mpsc,
+    time::Instant,
+};
+use tracing::{error, info, warn};
+use tracing_subscriber::{EnvFilter, layer::SubscriberExt, reload, util::SubscriberInitExt};
+use tree_hash::TreeHash;
 
 pub const APP_NAME: &str = "ream";
 
+/// How long services spawned with [ReamExecutor::spawn_graceful] get to finish on Ctrl-C.
+const GRACEFUL_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The startup integrity check of the lean database only reads the slot index of this many of
+/// the last indexed slots, `ream db verify` checks everything.
+const STARTUP_INTEGRITY_CHECK_SLOTS: u64 = 64;
+
 /// Entry point for the Ream client. Initializes logging, parses CLI arguments, and runs the
 /// appropriate node type (beacon node, validator node, or account manager) based on the command
 /// line arguments. Handles graceful shutdown on Ctrl-C.
 fn main() {
-    let cli = Cli::parse();
+    let matches = Cli::command().get_matches();
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|err| err.exit());
+
+    let ream_dir = setup_data_dir(APP_NAME, cli.data_dir.clone(), cli.ephemeral)
+        .expect("Unable to initialize database directory");
 
-    // Set the default log level based on verbosity flag or RUST_LOG env var
+    // Set the default log level based on the RUST_LOG env var, or else on a verbosity flag
+    // passed explicitly, the filter persisted through the admin API or the default verbosity,
+    // with the per-target log levels on top
     let rust_log = env::var(EnvFilter::DEFAULT_ENV).unwrap_or_default();
-    let env_filter = match rust_log.is_empty() {
-        true => EnvFilter::builder().parse_lossy(cli.verbosity.directive()),
-        false => EnvFilter::builder().parse_lossy(rust_log),
-    };
-    tracing_subscriber::fmt().with_env_filter(env_filter).init();
+    let log_filter_path = ream_dir.join(DEFAULT_LOG_FILTER_FILE);
+    let (startup_filter, default_filter) = startup_filters(
+        &rust_log,
+        cli.verbosity.directive(),
+        matches.value_source("verbosity") == Some(ValueSource::CommandLine),
+        cli.log_levels.as_deref(),
+        &log_filter_path,
+    );
+    // The filter is reloadable so the admin API and SIGHUP can change it at runtime
+    let (env_filter, log_filter_handle) =
+        reload::Layer::new(EnvFilter::builder().parse_lossy(&startup_filter));
+    set_log_level_controller(
+        LogLevelController::new(log_filter_handle, startup_filter)
+            .with_persist_path(log_filter_path, default_filter),
+    );
+    let tracer_provider = cli.tracing_endpoint.as_ref().map(|endpoint| {
+        init_tracer_provider(APP_NAME, endpoint).expect("Unable to initialize tracing exporter")
+    });
+    tracing_subscriber::registry()
+        .with(env_filter)
+        .with((cli.log_format == LogFormat::Text).then(tracing_subscriber::fmt::layer))
+        .with((cli.log_format == LogFormat::Json).then(|| tracing_subscriber::fmt::layer().json()))
+        .with(
+            tracer_provider.as_ref().map(|provider| {
+                tracing_opentelemetry::layer().with_tracer(provider.tracer(APP_NAME))
+            }),
+        )
+        .init();
     info!("\n{}", startup_message());
 
     let executor = ReamExecutor::new().expect("unable to create executor");
     let executor_clone = executor.clone();
-    let ream_dir = setup_data_dir(APP_NAME, cli.data_dir.clone(), cli.ephemeral)
-        .expect("Unable to initialize database directory");
 
     if cli.purge_db {
-        reset_db(&ream_dir).expect("Unable to delete database");
+        let cleared =
+            reset_db(&ream_dir, cli.purge_scope, cli.yes).expect("Unable to delete database");
+        // The lean validators signed on the purged chain, a chain synced or started anew doesn't
+        // have to keep them from signing at its slots.
+        let slashing_protection_path = ream_dir.join(DEFAULT_SLASHING_PROTECTION_FILE);
+        if cleared && cli.purge_scope == ResetScope::Lean && slashing_protection_path.exists() {
+            fs::remove_file(slashing_protection_path)
+                .expect("Unable to delete slashing protection");
+        }
     }
 
+    let lean_db_options = cli.db_options(Chain::Lean);
+    let beacon_db_options = cli.db_options(Chain::Beacon);
+    let db_durability = cli.db_durability;
+    let db_flush_interval = Duration::from_secs(cli.db_flush_interval);
+
     match cli.command {
+        Commands::LeanNode(config) if config.read_only => {
+            let lean_db = ReamDB::open_read_only_with_options(ream_dir.clone(), &lean_db_options)
+                .expect("unable to open Ream Database read-only");
+            executor_clone.spawn(toggle_diagnostics_on_sighup());
+            executor_clone.spawn(async move { run_read_only_lean_node(*config, lean_db).await });
+        }
         Commands::LeanNode(config) => {
-            let ream_db = ReamDB::new(ream_dir.clone()).expect("unable to init Ream Database");
+            let ream_db = ReamDB::with_options(ream_dir.clone(), &lean_db_options)
+                .expect("unable to init Ream Database");
+            apply_db_durability(&executor, &ream_db, db_durability, db_flush_interval);
+            executor_clone.spawn(toggle_diagnostics_on_sighup());
             executor_clone.spawn(async move { run_lean_node(*config, executor, ream_db).await });
         }
+        Commands::LeanValidator(config) => {
+            executor_clone
+                .spawn(async move { run_lean_validator(*config, executor, ream_dir).await });
+        }
+        Commands::Dev(config) => {
+            let dev_dir = ream_dir.join(DEV_DIR);
+            let node_config =
+                prepare_dev_node(&config, &dev_dir).expect("Failed to prepare the dev node");
+            let ream_db = ReamDB::with_options(dev_dir.join(DEV_DB_DIR), &lean_db_options)
+                .expect("unable to init Ream Database");
+            apply_db_durability(&executor, &ream_db, db_durability, db_flush_interval);
+            executor_clone.spawn(toggle_diagnostics_on_sighup());
+            executor_clone
+                .spawn(async move { run_lean_node(node_config, executor, ream_db).await });
+        }
         Commands::BeaconNode(config) => {
-            let ream_db = ReamDB::new(ream_dir.clone()).expect("unable to init Ream Database");
+            let ream_db = ReamDB::with_options(ream_dir.clone(), &beacon_db_options)
+                .expect("unable to init Ream Database");
+            apply_db_durability(&executor, &ream_db, db_durability, db_flush_interval);
             executor_clone.spawn(async move { run_beacon_node(*config, executor, ream_db).await });
         }
         Commands::ValidatorNode(config) => {
@@ -131,6 +238,55 @@ fn main() {
             run_generate_validator_registry(*config).expect("failed to generate hash-sig keystore");
             process::exit(0);
         }
+        Commands::Keys(config) => {
+            if let Err(err) = run_keys(*config) {
+                error!("Keys command failed: {err:?}");
+                process::exit(1);
+            }
+            process::exit(0);
+        }
+        Commands::NodeId(config) => {
+            if let Err(err) = run_node_id(*config, ream_dir.clone()) {
+                error!("Node id command failed: {err:?}");
+                process::exit(1);
+            }
+            process::exit(0);
+        }
+        Commands::Devnet(config) => {
+            if let Err(err) = run_devnet(*config) {
+                error!("Devnet command failed: {err:?}");
+                process::exit(1);
+            }
+            process::exit(0);
+        }
+        Commands::Db(config) => {
+            if let Err(err) = run_db(*config, ream_dir.clone(), &lean_db_options) {
+                error!("Database command failed: {err:?}");
+                process::exit(1);
+            }
+            process::exit(0);
+        }
+        Commands::Export(config) => {
+            if let Err(err) = run_export(*config, ream_dir.clone(), &lean_db_options) {
+                error!("Export failed: {err:?}");
+                process::exit(1);
+            }
+            process::exit(0);
+        }
+        Commands::Import(config) => {
+            let ream_db = ReamDB::with_options(ream_dir.clone(), &lean_db_options)
+                .expect("unable to init Ream Database");
+            if let Err(err) = run_import(*config, ream_db) {
+                error!("Import failed: {err:?}");
+                process::exit(1);
+            }
+            process::exit(0);
+        }
+        Commands::Snapshot(config) => {
+            let ream_db = ReamDB::with_options(ream_dir.clone(), &lean_db_options)
+                .expect("unable to init Ream Database");
+            run_snapshot(*config, ream_db);
+        }
     }
 
     executor_clone.runtime().block_on(async {
@@ -139,13 +295,124 @@ fn main() {
             .expect("failed to pause until ctrl-c");
         info!("Ctrl-C received, shutting down...");
         executor_clone.shutdown_signal();
+        if !executor_clone
+            .wait_for_graceful_shutdown(GRACEFUL_SHUTDOWN_TIMEOUT)
+            .await
+        {
+            warn!("Services didn't stop within {GRACEFUL_SHUTDOWN_TIMEOUT:?}, stopping anyway");
+        }
     });
 
     executor_clone.shutdown_runtime();
 
+    if let Some(tracer_provider) = tracer_provider
+        && let Err(err) = tracer_provider.shutdown()
+    {
+        error!("Failed to flush tracing spans: {err:?}");
+    }
+
     process::exit(0);
 }
 
+/// Applies `--db-durability` to the database of a node. With eventual durability, a task flushes
+/// the database every `flush_interval` and once more when the node shuts down, so at most one
+/// interval of commits is lost on a crash.
+fn apply_db_durability(
+    executor: &ReamExecutor,
+    ream_db: &ReamDB,
+    profile: DurabilityProfile,
+    flush_interval: Duration,
+) {
+    set_durability_profile(profile);
+    if profile != DurabilityProfile::Eventual {
+        return;
+    }
+
+    info!("Database commits are flushed to disk every {flush_interval:?}");
+    let ream_db = ream_db.clone();
+    executor.spawn_graceful(move |mut shutdown| async move {
+        let mut interval = tokio::time::interval(flush_interval);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.wait() => break,
+            }
+            if let Err(err) = ream_db.flush() {
+                warn!("Failed to flush the database: {err:?}");
+            }
+        }
+        if let Err(err) = ream_db.flush() {
+            error!("Failed to flush the database on shutdown: {err:?}");
+        }
+    });
+}
+
+/// Toggles verbose logging and debug invariants every time SIGHUP is received, so operators can
+/// capture diagnostics during an incident without restarting the node.
+async fn toggle_diagnostics_on_sighup() {
+    let mut sighup = match signal(SignalKind::hangup()) {
+        Ok(sighup) => sighup,
+        Err(err) => {
+            error!("Failed to listen for SIGHUP: {err:?}");
+            return;
+        }
+    };
+
+    while sighup.recv().await.is_some() {
+        let Some(controller) = log_level_controller() else {
+            continue;
+        };
+        match controller.toggle_verbose() {
+            Ok(filter) => info!(
+                "SIGHUP received, log filter is now {filter:?} and debug invariants are {}",
+                match debug_invariants() {
+                    true => "enabled",
+                    false => "disabled",
+                }
+            ),
+            Err(err) => error!("Failed to toggle diagnostics: {err:?}"),
+        }
+    }
+}
+
+/// Checks the lean database for what a crash could have left half written near the head.
+/// Inconsistent indices are rebuilt from the block table if `repair` is set, anything else needs
+/// the database to be purged.
+fn check_lean_db_integrity(lean_db: &LeanDB, repair: bool) -> anyhow::Result<()> {
+    let issues = lean_db.verify_recent_integrity(STARTUP_INTEGRITY_CHECK_SLOTS)?;
+    if issues.is_empty() {
+        return Ok(());
+    }
+    for issue in &issues {
+        warn!("Lean database integrity issue: {issue}");
+    }
+    let repairable = issues.iter().filter(|issue| issue.is_repairable()).count();
+    ensure!(
+        repairable > 0,
+        "Found {} integrity issues which can't be repaired, restart with --purge-db to start over",
+        issues.len()
+    );
+    ensure!(
+        repair,
+        "Found {} integrity issues, restart with --repair to rebuild the indices of {repairable} \
+         of them from the block table, or with --purge-db to start over",
+        issues.len()
+    );
+
+    let indexed = lean_db.rebuild_indices()?;
+    info!("Rebuilt the slot and state root indices of {indexed} blocks");
+    let issues = lean_db.verify_integrity()?;
+    for issue in &issues {
+        error!("Lean database integrity issue left after the repair: {issue}");
+    }
+    ensure!(
+        issues.is_empty(),
+        "{} integrity issues can't be repaired, restart with --purge-db to start over",
+        issues.len()
+    );
+    Ok(())
+}
+
 /// Runs the lean node.
 ///
 /// A lean node runs several services with different responsibilities.
@@ -160,26 +427,46 @@ pub async fn run_lean_node(config: LeanNodeConfig, executor: ReamExecutor, ream_
 
     // Initialize prometheus metrics
     if config.enable_metrics {
-        let address = SocketAddr::new(config.metrics_address, config.metrics_port);
-        prometheus_exporter::start(address).expect("Failed to start prometheus exporter");
-        info!(
-            "Metrics started on {}:{}",
-            config.metrics_address, config.metrics_port
-        );
+        if let Err(err) = register_process_metrics() {
+            warn!("Failed to register the process metrics: {err:?}");
+        }
+        if config.metrics_server == MetricsServer::Http {
+            info!("Metrics are served at /metrics of the HTTP API");
+        } else {
+            let address = SocketAddr::new(config.metrics_address, config.metrics_port);
+            prometheus_exporter::start(address).expect("Failed to start prometheus exporter");
+            info!(
+                "Metrics started on {}:{}",
+                config.metrics_address, config.metrics_port
+            );
+        }
     }
 
+    let server_config = config.rpc_server_config();
     let keystores = load_validator_registry(&config.validator_registry_path, &config.node_id)
         .expect("Failed to load validator registry");
 
     // Fill in which devnet we are running
     let mut network = config.network;
-    network.devnet = config.devnet;
+    if let Some(devnet) = config.devnet {
+        network.devnet = devnet;
+    }
     set_lean_network_spec(Arc::new(network));
 
+    let admin_auth = AdminAuth::load_or_generate(
+        &config
+            .admin_secret_path
+            .clone()
+            .unwrap_or_else(|| ream_db.data_dir().join(DEFAULT_ADMIN_SECRET_FILE)),
+    )
+    .expect("Failed to load admin API secret");
+
     // Initialize the lean database
     let lean_db = ream_db
         .init_lean_db()
         .expect("unable to init Ream Lean Database");
+    check_lean_db_integrity(&lean_db, config.repair)
+        .expect("Lean database failed the integrity check");
 
     info!("ream lean database has been initialized");
 
@@ -188,17 +475,12 @@ pub async fn run_lean_node(config: LeanNodeConfig, executor: ReamExecutor, ream_
     let (outbound_p2p_sender, outbound_p2p_receiver) = mpsc::unbounded_channel::<LeanP2PRequest>();
 
     // Initialize the lean chain with genesis block and state.
-    let validators = lean_network_spec()
-        .validator_public_keys
-        .iter()
-        .enumerate()
-        .map(|(index, public_key)| Validator {
-            public_key: PublicKey::new(*public_key),
-            index: index as u64,
-        })
-        .collect::<Vec<_>>();
     let (genesis_block, genesis_state) =
-        lean_genesis::setup_genesis(lean_network_spec().genesis_time, validators);
+        lean_genesis::load_genesis(&lean_network_spec(), &config.validator_registry_path)
+            .expect("Failed to load genesis");
+    lean_genesis::check_database_genesis(&lean_db, &genesis_block)
+        .expect("Database doesn't match the genesis of the config");
+    let genesis_root = genesis_block.tree_hash_root();
     let (lean_chain_writer, lean_chain_reader) = Writer::new(
         Store::get_forkchoice_store(
             SignedBlockWithAttestation {
@@ -220,25 +502,45 @@ pub async fn run_lean_node(config: LeanNodeConfig, executor: ReamExecutor, ream_
             lean_db,
             None,
         )
-        .expect("Could not get forkchoice store"),
+        .expect("Could not get forkchoice store")
+        .with_attestation_pool_size(config.attestation_pool_size)
+        .with_attestation_batch_size(config.attestation_batch_size)
+        .with_data_availability(config.data_availability),
     );
 
     let network_state = lean_chain_reader.read().await.network_state.clone();
+    if let Some(clock_drift_config) = config.clock_drift_config(network_state.clone()) {
+        executor.spawn(monitor_clock_drift(clock_drift_config));
+    }
 
     // Initialize the lean network service
 
-    let fork = "devnet0".to_string();
-    let topics: Vec<LeanGossipTopic> = vec![
+    // The network service subscribes to the topics of each fork around its activation.
+    let fork = lean_network_spec().topic_fork(genesis_root, lean_network_spec().current_slot());
+    let mut topics: Vec<LeanGossipTopic> = vec![
         LeanGossipTopic {
             fork: fork.clone(),
             kind: LeanGossipTopicKind::Block,
         },
         LeanGossipTopic {
-            fork,
+            fork: fork.clone(),
             kind: LeanGossipTopicKind::Attestation,
         },
     ];
+    if config.data_availability {
+        topics.push(LeanGossipTopic {
+            fork,
+            kind: LeanGossipTopicKind::BlobSidecar,
+        });
+    }
 
+    let network_key_path = ream_db.data_dir().join(DEFAULT_NETWORK_KEY_FILE);
+    if config.purge_network_key && network_key_path.exists() {
+        fs::remove_file(&network_key_path).expect("Failed to remove the network key");
+        info!("Removed the network key at {}", network_key_path.display());
+    }
+
+    let peer_limits = config.peer_limits();
     let mut network_service = LeanNetworkService::new(
         Arc::new(LeanNetworkConfig {
             gossipsub_config: LeanGossipsubConfig {
@@ -248,6 +550,11 @@ pub async fn run_lean_node(config: LeanNodeConfig, executor: ReamExecutor, ream_
             socket_address: config.socket_address,
             socket_port: config.socket_port,
             private_key_path: config.private_key_path,
+            network_key_path: Some(network_key_path),
+            trusted_peers: config.trusted_peers,
+            peer_limits,
+            enable_upnp: config.enable_upnp,
+            genesis_root,
         }),
         executor.clone(),
         chain_sender.clone(),
@@ -257,35 +564,81 @@ pub async fn run_lean_node(config: LeanNodeConfig, executor: ReamExecutor, ream_
     .await
     .expect("Failed to create network service");
 
-    let chain_service =
-        LeanChainService::new(lean_chain_writer, chain_receiver, outbound_p2p_sender).await;
-
-    let validator_service = LeanValidatorService::new(keystores, chain_sender).await;
+    let mut chain_service = LeanChainService::new(
+        lean_chain_writer,
+        chain_receiver,
+        outbound_p2p_sender.clone(),
+    )
+    .await
+    .with_finality_stall_slots(config.finality_stall_slots)
+    .with_recent_blocks_slots(config.recent_blocks_slots)
+    .with_validator_performance_metrics(config.validator_performance_metrics);
+    if let Some(block_source) = &config.block_source {
+        chain_service = chain_service.with_block_source(
+            block_source
+                .build(Duration::from_millis(config.block_source_timeout_ms))
+                .expect("Failed to create external block source"),
+            Duration::from_millis(config.block_source_timeout_ms),
+        );
+    }
 
-    let server_config = RpcServerConfig::new(
-        config.http_address,
-        config.http_port,
-        config.http_allow_origin,
-    );
+    let local_validators = LocalValidators::new(keystores.iter().map(|keystore| keystore.index));
+    let validator_indices = keystores
+        .iter()
+        .map(|keystore| keystore.index)
+        .collect::<Vec<_>>();
+    let key_rotations = load_key_rotations(&config.validator_registry_path, &validator_indices)
+        .expect("Failed to load key rotations");
+    let slashing_protection = SlashingProtection::load(
+        ream_db.data_dir().join(DEFAULT_SLASHING_PROTECTION_FILE),
+        lean_network_spec().genesis_time,
+    )
+    .expect("Failed to load slashing protection");
+    let mut validator_service = LeanValidatorService::new(
+        keystores,
+        Box::new(ChannelChainClient::new(chain_sender.clone())),
+        slashing_protection,
+    )
+    .await
+    .with_key_rotations(key_rotations);
+    if config.reload_keystores {
+        validator_service = validator_service.with_keystore_reload(
+            config.validator_registry_path.clone(),
+            config.node_id.clone(),
+        );
+    }
 
-    // Start the services concurrently.
-    let chain_future = executor.spawn(async move {
-        if let Err(err) = chain_service.start().await {
+    // Start the services concurrently. They are told when the node shuts down, and are given
+    // time to finish their in-flight work.
+    let chain_future = executor.spawn_graceful(|shutdown| async move {
+        if let Err(err) = chain_service.start(shutdown).await {
             panic!("Chain service exited with error: {err:?}");
         }
     });
-    let network_future = executor.spawn(async move {
-        if let Err(err) = network_service.start(config.bootnodes).await {
+    let network_future = executor.spawn_graceful(|shutdown| async move {
+        if let Err(err) = network_service.start(config.bootnodes, shutdown).await {
             panic!("Network service exited with error: {err:?}");
         }
     });
-    let validator_future = executor.spawn(async move {
-        if let Err(err) = validator_service.start().await {
+    let validator_future = executor.spawn_graceful(|shutdown| async move {
+        if let Err(err) = validator_service.start(shutdown).await {
             panic!("Validator service exited with error: {err:?}");
         }
     });
-    let http_future = executor.spawn(async move {
-        ream_rpc_lean::server::start(server_config, lean_chain_reader, network_state).await
+    let snapshot_dir = SnapshotDir(ream_db.data_dir().join(DEFAULT_SNAPSHOTS_DIR));
+    let http_future = executor.spawn_graceful(|shutdown| async move {
+        ream_rpc_lean::server::start(
+            server_config,
+            lean_chain_reader,
+            network_state,
+            local_validators,
+            chain_sender,
+            outbound_p2p_sender,
+            admin_auth,
+            snapshot_dir,
+            shutdown,
+        )
+        .await
     });
 
     tokio::select! {
@@ -304,6 +657,105 @@ pub async fn run_lean_node(config: LeanNodeConfig, executor: ReamExecutor, ream_
     }
 }
 
+/// Runs a lean node which only serves blocks and states from an existing database over the lean
+/// API. No chain, network or validator services are started, so nothing is ever written.
+pub async fn run_read_only_lean_node(config: LeanNodeConfig, lean_db: ReadOnlyLeanDB) {
+    info!("starting up read-only lean node...");
+
+    if let Err(err) =
+        ream_rpc_lean::server::start_read_only(config.rpc_server_config(), lean_db).await
+    {
+        error!("Read-only lean API server exited with error: {err:?}");
+    }
+}
+
+/// Runs a lean validator client which gets its duties, blocks and attestation data from a lean
+/// node over the lean API, and submits the signed blocks and attestations back to it.
+pub async fn run_lean_validator(
+    config: LeanValidatorConfig,
+    executor: ReamExecutor,
+    ream_dir: PathBuf,
+) {
+    info!("starting up lean validator...");
+
+    let keystores = load_validator_registry(&config.validator_registry_path, &config.node_id)
+        .expect("Failed to load validator registry");
+
+    let mut network = config.network;
+    if let Some(devnet) = config.devnet {
+        network.devnet = devnet;
+    }
+    set_lean_network_spec(Arc::new(network));
+
+    let validator_indices = keystores
+        .iter()
+        .map(|keystore| keystore.index)
+        .collect::<Vec<_>>();
+    let key_rotations = load_key_rotations(&config.validator_registry_path, &validator_indices)
+        .expect("Failed to load key rotations");
+    let slashing_protection = SlashingProtection::load(
+        ream_dir.join(DEFAULT_SLASHING_PROTECTION_FILE),
+        lean_network_spec().genesis_time,
+    )
+    .expect("Failed to load slashing protection");
+    let mut validator_service = LeanValidatorService::new(
+        keystores,
+        Box::new(
+            HttpChainClient::new(
+                config.lean_node_url,
+                config.request_timeout,
+                &config.admin_secret_path,
+            )
+            .expect("Failed to create lean api client"),
+        ),
+        slashing_protection,
+    )
+    .await
+    .with_key_rotations(key_rotations);
+    if config.enable_keymanager {
+        let (keymanager_sender, keymanager_receiver) = mpsc::unbounded_channel();
+        let imported_keystores =
+            ImportedKeystores::new(ream_dir.join(DEFAULT_IMPORTED_KEYSTORES_DIR))
+                .expect("Failed to open imported keystores");
+        validator_service = validator_service
+            .with_keymanager(keymanager_receiver, imported_keystores)
+            .expect("Failed to load imported keystores");
+
+        let admin_auth = AdminAuth::load_or_generate(
+            &config
+                .keymanager_token_path
+                .clone()
+                .unwrap_or_else(|| ream_dir.join(DEFAULT_KEYMANAGER_TOKEN_FILE)),
+        )
+        .expect("Failed to load keymanager API token");
+        let server_config = config.keymanager_server_config();
+        executor.spawn_graceful(|shutdown| async move {
+            if let Err(err) = ream_rpc_lean::server::start_keymanager(
+                server_config,
+                keymanager_sender,
+                admin_auth,
+                shutdown,
+            )
+            .await
+            {
+                error!("Keymanager API server exited with error: {err:?}");
+            }
+        });
+    }
+    if config.reload_keystores {
+        validator_service =
+            validator_service.with_keystore_reload(config.validator_registry_path, config.node_id);
+    }
+
+    let validator_future =
+        executor.spawn_graceful(|shutdown| async move { validator_service.start(shutdown).await });
+    match validator_future.await {
+        Ok(Err(err)) => error!("Lean validator exited with error: {err:?}"),
+        Err(err) => error!("Lean validator task failed: {err:?}"),
+        Ok(Ok(())) => {}
+    }
+}
+
 /// Runs the beacon node.
 ///
 /// This function initializes the beacon node by setting up the network specification,
@@ -597,6 +1049,23 @@ fn get_current_epoch(genesis_time: u64) -> u64 {
     )
 }
 
+/// Writes a consistent copy of the database to the output directory.
+///
+/// This opens the database itself, so it is meant for stopped nodes. Use the
+/// `/lean/v0/admin/snapshot` endpoint to snapshot a running lean node.
+pub fn run_snapshot(config: SnapshotConfig, ream_db: ReamDB) {
+    info!(
+        "Writing database snapshot to {}...",
+        config.output.display()
+    );
+
+    ream_db
+        .snapshot(&config.output)
+        .expect("Failed to write database snapshot");
+
+    process::exit(0);
+}
+
 /// Generates a new secp256k1 keypair and saves it to the specified path in hex encoding.
 ///
 /// This allows the lean node to reuse the same network identity across restarts by loading
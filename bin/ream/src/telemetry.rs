@@ -0,0 +1,22 @@
+use anyhow::anyhow;
+use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+use opentelemetry_sdk::{Resource, trace::SdkTracerProvider};
+use url::Url;
+
+/// Builds a tracer provider that exports spans in batches to an OTLP/HTTP collector such as
+/// Jaeger or Tempo.
+pub fn init_tracer_provider(
+    service_name: &'static str,
+    endpoint: &Url,
+) -> anyhow::Result<SdkTracerProvider> {
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_endpoint(endpoint.as_str())
+        .build()
+        .map_err(|err| anyhow!("Failed to build OTLP span exporter: {err:?}"))?;
+
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(Resource::builder().with_service_name(service_name).build())
+        .build())
+}
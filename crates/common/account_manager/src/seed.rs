@@ -25,3 +25,24 @@ pub fn derive_seed_with_user_input(
 
     hasher.finalize().into()
 }
+
+/// Derives a seed for [ChaCha20Rng] like [derive_seed_with_user_input], but separated by
+/// `domain`, so keys for different purposes derived from the same seed phrase and index are
+/// unrelated.
+pub fn derive_seed_with_domain(
+    seed_phrase: &str,
+    domain: &[u8],
+    index: u32,
+    passphrase: &str,
+) -> <ChaCha20Rng as SeedableRng>::Seed {
+    let mnemonic = Mnemonic::from_str(seed_phrase).expect("Invalid mnemonic phrase");
+    let seed = mnemonic.to_seed(passphrase);
+
+    let mut hasher = Sha256::new();
+    hasher.update(seed);
+    hasher.update((domain.len() as u64).to_be_bytes());
+    hasher.update(domain);
+    hasher.update(index.to_be_bytes());
+
+    hasher.finalize().into()
+}
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+/// Changes to apply to the node's diagnostics. Omitted fields are left unchanged.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct LogLevelRequest {
+    /// Tracing filter directives in the `RUST_LOG` syntax, e.g. `info,ream_p2p=trace`.
+    pub filter: Option<String>,
+    /// Levels of single targets, e.g. `ream_fork_choice=debug,libp2p=warn`, applied on top of
+    /// the filter.
+    pub levels: Option<String>,
+    pub debug_invariants: Option<bool>,
+    /// Write the resulting filter to the data directory, so the node starts with it next time
+    /// unless `--verbosity` is passed. `DELETE /admin/log_level` removes it again.
+    #[serde(default)]
+    pub persist: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LogLevelResponse {
+    pub filter: String,
+    pub debug_invariants: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SnapshotRequest {
+    /// Directory to write the snapshot to, relative to the `snapshots` directory in the data
+    /// directory. It must not already contain a database.
+    pub destination: PathBuf,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SnapshotResponse {
+    /// Path of the redb file which was written.
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TrustedPeerRequest {
+    /// Multiaddress of the peer, including its `/p2p/<peer_id>` suffix.
+    pub address: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PruneRequest {
+    /// Remove every block, and its state, with a slot lower than this. Must not be after the
+    /// latest finalized slot.
+    pub before_slot: u64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PruneResponse {
+    /// Number of blocks which were removed.
+    pub pruned: u64,
+}
+
+/// The share of the messages received from peers which the node drops or delays, to test it on a
+/// bad network. Omitted percentages are 0, which stops injecting that fault.
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct NetworkFaultsRequest {
+    /// Peer whose messages the faults apply to. Without one, they apply to every peer and replace
+    /// the faults of single peers.
+    pub peer_id: Option<String>,
+    #[serde(default)]
+    pub drop_percentage: u8,
+    #[serde(default)]
+    pub delay_percentage: u8,
+    #[serde(default)]
+    pub delay_ms: u64,
+}
@@ -0,0 +1,18 @@
+use alloy_primitives::B256;
+use ream_consensus_lean::checkpoint::Checkpoint;
+use serde::{Deserialize, Serialize};
+
+/// What a block contributed to the chain, found by diffing the state before and after it.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct BlockSummary {
+    pub block_root: B256,
+    pub slot: u64,
+    pub proposer_index: u64,
+    pub attestation_count: u64,
+    /// The number of distinct validators with an attestation in the block.
+    pub unique_voters: u64,
+    /// The checkpoints the block's attestations justified, in slot order.
+    pub new_justifications: Vec<Checkpoint>,
+    /// The checkpoint the block finalized, if finalization moved.
+    pub new_finalization: Option<Checkpoint>,
+}
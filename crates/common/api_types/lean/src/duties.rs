@@ -0,0 +1,83 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+/// Largest number of slots a single proposer duties request may cover.
+pub const MAX_DUTIES_SLOT_RANGE: u64 = 1024;
+
+/// An inclusive range of slots, written as `{start}-{end}` or as a single `{slot}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl SlotRange {
+    pub fn slots(&self) -> impl Iterator<Item = u64> {
+        self.start..=self.end
+    }
+}
+
+impl FromStr for SlotRange {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let parse_slot = |slot: &str| {
+            slot.trim()
+                .parse::<u64>()
+                .map_err(|err| format!("Invalid slot {slot:?}: {err}"))
+        };
+        let (start, end) = match value.split_once('-') {
+            Some((start, end)) => (parse_slot(start)?, parse_slot(end)?),
+            None => {
+                let slot = parse_slot(value)?;
+                (slot, slot)
+            }
+        };
+        if start > end {
+            return Err(format!("Slot range start {start} is after its end {end}"));
+        }
+        if end - start >= MAX_DUTIES_SLOT_RANGE {
+            return Err(format!(
+                "Slot range {start}-{end} covers more than {MAX_DUTIES_SLOT_RANGE} slots"
+            ));
+        }
+        Ok(SlotRange { start, end })
+    }
+}
+
+impl fmt::Display for SlotRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start, self.end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ProposerDuty {
+    pub slot: u64,
+    pub validator_index: u64,
+    /// Whether the validator's keys are loaded by this node, so it proposes without help.
+    pub is_local: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct AttesterDuty {
+    pub slot: u64,
+    pub validator_index: u64,
+    /// Whether the validator's keys are loaded by this node, so it attests without help.
+    pub is_local: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_slot_range() {
+        assert_eq!("5".parse(), Ok(SlotRange { start: 5, end: 5 }));
+        assert_eq!("3-10".parse(), Ok(SlotRange { start: 3, end: 10 }));
+        assert!("10-3".parse::<SlotRange>().is_err());
+        assert!("0-1024".parse::<SlotRange>().is_err());
+        assert!("a-3".parse::<SlotRange>().is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use alloy_primitives::FixedBytes;
+use serde::{Deserialize, Serialize};
+
+/// A key the validator client signs with.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KeystoreInfo {
+    pub validating_pubkey: FixedBytes<52>,
+    pub validator_index: u64,
+    /// Keys loaded from the validator registry can't be deleted through the keymanager API.
+    pub readonly: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ListKeystoresResponse {
+    pub data: Vec<KeystoreInfo>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImportKeystoresRequest {
+    /// JSON encoded EIP-2335 keystores of lean private keys.
+    pub keystores: Vec<String>,
+    /// The password of each keystore, in the same order.
+    pub passwords: Vec<String>,
+    /// JSON encoded slashing protection records, as returned when the keys were deleted.
+    pub slashing_protection: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportStatus {
+    Imported,
+    Duplicate,
+    Error,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeleteStatus {
+    Deleted,
+    NotActive,
+    NotFound,
+    Error,
+}
+
+/// The outcome for one keystore of a request.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct KeystoreStatus<S> {
+    pub status: S,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl<S> KeystoreStatus<S> {
+    pub fn new(status: S) -> Self {
+        Self {
+            status,
+            message: None,
+        }
+    }
+
+    pub fn with_message(status: S, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            message: Some(message.into()),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ImportKeystoresResponse {
+    pub data: Vec<KeystoreStatus<ImportStatus>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteKeystoresRequest {
+    pub pubkeys: Vec<FixedBytes<52>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DeleteKeystoresResponse {
+    pub data: Vec<KeystoreStatus<DeleteStatus>>,
+    /// JSON encoded slashing protection records of the requested keys, to import along with them
+    /// into another validator client.
+    pub slashing_protection: String,
+}
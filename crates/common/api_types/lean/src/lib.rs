@@ -1 +1,9 @@
+pub mod admin;
+pub mod block;
+pub mod duties;
 pub mod head;
+pub mod keymanager;
+pub mod node;
+pub mod performance;
+pub mod query;
+pub mod state;
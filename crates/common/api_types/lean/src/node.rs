@@ -0,0 +1,49 @@
+use serde::{Deserialize, Serialize};
+
+/// How this node is reachable by its peers.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Identity {
+    pub peer_id: String,
+    pub enr: String,
+    pub p2p_addresses: Vec<String>,
+}
+
+/// How far the node's head is behind the heads reported by its peers.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SyncStatus {
+    pub head_slot: u64,
+    pub peer_head_slot: u64,
+    pub sync_distance: u64,
+    pub is_syncing: bool,
+}
+
+impl SyncStatus {
+    /// Heads within one slot of the best peer head count as synced, since gossip of the latest
+    /// block may still be in flight.
+    pub fn new(head_slot: u64, peer_head_slot: Option<u64>) -> Self {
+        let peer_head_slot = peer_head_slot.unwrap_or(head_slot);
+        let sync_distance = peer_head_slot.saturating_sub(head_slot);
+        Self {
+            head_slot,
+            peer_head_slot,
+            sync_distance,
+            is_syncing: sync_distance > 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sync_status() {
+        assert!(!SyncStatus::new(10, None).is_syncing);
+        assert!(!SyncStatus::new(10, Some(11)).is_syncing);
+        assert!(!SyncStatus::new(10, Some(5)).is_syncing);
+
+        let status = SyncStatus::new(10, Some(20));
+        assert_eq!(status.sync_distance, 10);
+        assert!(status.is_syncing);
+    }
+}
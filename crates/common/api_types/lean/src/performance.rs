@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// How a validator performed over the slots tracked by the node.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct ValidatorPerformance {
+    pub validator_index: u64,
+    /// The first and last tracked slots, or `None` if no slot has been tracked yet.
+    pub first_slot: Option<u64>,
+    pub last_slot: Option<u64>,
+    pub proposals_made: u64,
+    pub proposals_missed: u64,
+    pub attestations_included: u64,
+    pub attestations_missed: u64,
+}
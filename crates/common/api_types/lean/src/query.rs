@@ -0,0 +1,19 @@
+use ream_peer::{ConnectionState, Direction};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+pub struct ConnectionStateQuery {
+    pub state: Option<Vec<ConnectionState>>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct DirectionQuery {
+    pub direction: Option<Vec<Direction>>,
+}
+
+/// An inclusive range of slots.
+#[derive(Debug, Deserialize)]
+pub struct SlotRangeQuery {
+    pub from_slot: u64,
+    pub to_slot: u64,
+}
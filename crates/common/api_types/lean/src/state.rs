@@ -0,0 +1,11 @@
+use ream_consensus_lean::checkpoint::Checkpoint;
+use serde::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+
+/// The justified and finalized checkpoints a state had at its slot.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize, Encode, Decode)]
+pub struct FinalityCheckpoints {
+    pub slot: u64,
+    pub latest_justified: Checkpoint,
+    pub latest_finalized: Checkpoint,
+}
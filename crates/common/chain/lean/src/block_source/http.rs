@@ -0,0 +1,53 @@
+use std::time::Duration;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use ream_consensus_lean::block::BlockWithSignatures;
+use reqwest::{Client, StatusCode};
+use url::Url;
+
+use crate::block_source::{BlockRequest, ExternalBlockSource};
+
+/// Requests candidate blocks from an external builder over HTTP.
+///
+/// The builder is expected to serve `POST /lean/v0/builder/block` taking a JSON encoded
+/// [BlockRequest] and returning a JSON encoded [BlockWithSignatures].
+pub struct HttpBlockSource {
+    client: Client,
+    endpoint: Url,
+}
+
+impl HttpBlockSource {
+    pub fn new(endpoint: Url, timeout: Duration) -> anyhow::Result<Self> {
+        Ok(Self {
+            client: Client::builder()
+                .timeout(timeout)
+                .build()
+                .map_err(|err| anyhow!("Failed to build block source HTTP client: {err:?}"))?,
+            endpoint,
+        })
+    }
+}
+
+#[async_trait]
+impl ExternalBlockSource for HttpBlockSource {
+    fn name(&self) -> String {
+        self.endpoint.to_string()
+    }
+
+    async fn get_block(&self, request: &BlockRequest) -> anyhow::Result<BlockWithSignatures> {
+        let url = self
+            .endpoint
+            .join("/lean/v0/builder/block")
+            .map_err(|err| anyhow!("Failed to build block source URL: {err:?}"))?;
+        let response = self.client.post(url).json(request).send().await?;
+
+        match response.status() {
+            StatusCode::OK => Ok(response.json::<BlockWithSignatures>().await?),
+            StatusCode::NO_CONTENT => {
+                Err(anyhow!("Builder has no block for slot {}", request.slot))
+            }
+            status => Err(anyhow!("Builder returned unexpected status: {status:?}")),
+        }
+    }
+}
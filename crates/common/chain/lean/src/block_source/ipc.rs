@@ -0,0 +1,58 @@
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+use async_trait::async_trait;
+use ream_consensus_lean::block::BlockWithSignatures;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+    net::UnixStream,
+};
+
+use crate::block_source::{BlockRequest, ExternalBlockSource};
+
+/// Requests candidate blocks from a local builder process over a Unix domain socket.
+///
+/// Each request opens a new connection and writes a single line of JSON encoded [BlockRequest].
+/// The builder answers with a single line of JSON encoded [BlockWithSignatures] and may then close
+/// the connection.
+pub struct IpcBlockSource {
+    path: PathBuf,
+}
+
+impl IpcBlockSource {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+}
+
+#[async_trait]
+impl ExternalBlockSource for IpcBlockSource {
+    fn name(&self) -> String {
+        self.path.display().to_string()
+    }
+
+    async fn get_block(&self, request: &BlockRequest) -> anyhow::Result<BlockWithSignatures> {
+        let stream = UnixStream::connect(&self.path).await.map_err(|err| {
+            anyhow!(
+                "Failed to connect to block source at {}: {err:?}",
+                self.path.display()
+            )
+        })?;
+        let (reader, mut writer) = stream.into_split();
+
+        let mut payload = serde_json::to_vec(request)?;
+        payload.push(b'\n');
+        writer.write_all(&payload).await?;
+        writer.flush().await?;
+
+        let mut line = String::new();
+        let read = BufReader::new(reader).read_line(&mut line).await?;
+        if read == 0 {
+            return Err(anyhow!(
+                "Block source closed the connection without a response"
+            ));
+        }
+
+        Ok(serde_json::from_str(&line)?)
+    }
+}
@@ -0,0 +1,163 @@
+pub mod http;
+pub mod ipc;
+
+use std::{path::PathBuf, str::FromStr, sync::Arc, time::Duration};
+
+use alloy_primitives::B256;
+use anyhow::{anyhow, ensure};
+use async_trait::async_trait;
+use ream_consensus_lean::block::BlockWithSignatures;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use crate::block_source::{http::HttpBlockSource, ipc::IpcBlockSource};
+
+/// The parameters an external builder needs to produce a candidate block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockRequest {
+    pub slot: u64,
+    pub proposer_index: u64,
+    pub parent_root: B256,
+}
+
+/// A source of candidate blocks living outside of the node, e.g. a builder process.
+///
+/// The [LeanChainService] only uses the returned block if it is received before the configured
+/// timeout and passes validation against the local view of the chain, otherwise it falls back to
+/// producing the block locally.
+#[async_trait]
+pub trait ExternalBlockSource: Send + Sync {
+    /// Human readable identifier used in logs.
+    fn name(&self) -> String;
+
+    /// Requests a candidate block for the given slot and proposer.
+    async fn get_block(&self, request: &BlockRequest) -> anyhow::Result<BlockWithSignatures>;
+}
+
+/// Where an external block source can be reached.
+///
+/// `http://` and `https://` values are treated as HTTP endpoints, anything else is treated as a
+/// path to a Unix domain socket.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockSourceEndpoint {
+    Http(Url),
+    Ipc(PathBuf),
+}
+
+impl FromStr for BlockSourceEndpoint {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        ensure!(!value.is_empty(), "Block source endpoint must not be empty");
+        if value.starts_with("http://") || value.starts_with("https://") {
+            return Ok(Self::Http(Url::parse(value).map_err(|err| {
+                anyhow!("Invalid block source URL {value}: {err:?}")
+            })?));
+        }
+        Ok(Self::Ipc(PathBuf::from(value)))
+    }
+}
+
+impl BlockSourceEndpoint {
+    pub fn build(&self, timeout: Duration) -> anyhow::Result<Arc<dyn ExternalBlockSource>> {
+        Ok(match self {
+            BlockSourceEndpoint::Http(url) => Arc::new(HttpBlockSource::new(url.clone(), timeout)?),
+            BlockSourceEndpoint::Ipc(path) => Arc::new(IpcBlockSource::new(path.clone())),
+        })
+    }
+}
+
+/// Checks that a block received from an external source is the block we asked for.
+pub fn validate_block_response(
+    request: &BlockRequest,
+    response: &BlockWithSignatures,
+) -> anyhow::Result<()> {
+    let block = &response.block;
+    ensure!(
+        block.slot == request.slot,
+        "External block slot mismatch, expected {}, got {}",
+        request.slot,
+        block.slot
+    );
+    ensure!(
+        block.proposer_index == request.proposer_index,
+        "External block proposer mismatch, expected {}, got {}",
+        request.proposer_index,
+        block.proposer_index
+    );
+    ensure!(
+        block.parent_root == request.parent_root,
+        "External block parent mismatch, expected {}, got {}",
+        request.parent_root,
+        block.parent_root
+    );
+    ensure!(
+        block.body.attestations.len() == response.signatures.len(),
+        "External block has {} attestations but {} signatures",
+        block.body.attestations.len(),
+        response.signatures.len()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use alloy_primitives::B256;
+    use ream_consensus_lean::block::{Block, BlockBody, BlockWithSignatures};
+    use ssz_types::VariableList;
+
+    use super::{BlockRequest, BlockSourceEndpoint, validate_block_response};
+
+    fn sample_response(slot: u64, proposer_index: u64, parent_root: B256) -> BlockWithSignatures {
+        BlockWithSignatures {
+            block: Block {
+                slot,
+                proposer_index,
+                parent_root,
+                state_root: B256::ZERO,
+                body: BlockBody::default(),
+            },
+            signatures: VariableList::empty(),
+        }
+    }
+
+    #[test]
+    fn test_parse_block_source_endpoint() {
+        assert!(matches!(
+            "http://127.0.0.1:9500".parse::<BlockSourceEndpoint>(),
+            Ok(BlockSourceEndpoint::Http(_))
+        ));
+        assert_eq!(
+            "/tmp/builder.sock".parse::<BlockSourceEndpoint>().ok(),
+            Some(BlockSourceEndpoint::Ipc(PathBuf::from("/tmp/builder.sock")))
+        );
+        assert!("".parse::<BlockSourceEndpoint>().is_err());
+    }
+
+    #[test]
+    fn test_validate_block_response() {
+        let request = BlockRequest {
+            slot: 5,
+            proposer_index: 1,
+            parent_root: B256::repeat_byte(1),
+        };
+
+        assert!(
+            validate_block_response(&request, &sample_response(5, 1, B256::repeat_byte(1))).is_ok()
+        );
+        assert!(
+            validate_block_response(&request, &sample_response(6, 1, B256::repeat_byte(1)))
+                .is_err()
+        );
+        assert!(
+            validate_block_response(&request, &sample_response(5, 2, B256::repeat_byte(1)))
+                .is_err()
+        );
+        assert!(
+            validate_block_response(&request, &sample_response(5, 1, B256::repeat_byte(2)))
+                .is_err()
+        );
+    }
+}
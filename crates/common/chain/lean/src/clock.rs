@@ -1,9 +1,17 @@
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::{
+    sync::Arc,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
 
-use anyhow::anyhow;
-use ream_consensus_misc::constants::lean::INTERVALS_PER_SLOT;
+use anyhow::{anyhow, ensure};
+use ream_metrics::{CLOCK_OFFSET_MILLISECONDS, set_int_gauge_vec};
 use ream_network_spec::networks::lean_network_spec;
-use tokio::time::{Instant, Interval, MissedTickBehavior, interval_at};
+use ream_network_state_lean::NetworkState;
+use tokio::{
+    net::UdpSocket,
+    time::{Instant, Interval, MissedTickBehavior, interval_at, timeout},
+};
+use tracing::{debug, warn};
 
 pub fn create_lean_clock_interval() -> anyhow::Result<Interval> {
     let genesis_instant = UNIX_EPOCH + Duration::from_secs(lean_network_spec().genesis_time);
@@ -22,11 +30,330 @@ pub fn create_lean_clock_interval() -> anyhow::Result<Interval> {
                 ))
             })?;
 
-    let mut interval = interval_at(
-        interval_start,
-        Duration::from_secs(lean_network_spec().seconds_per_slot / INTERVALS_PER_SLOT),
-    );
+    let mut interval = interval_at(interval_start, lean_network_spec().interval_duration());
     interval.set_missed_tick_behavior(MissedTickBehavior::Burst);
 
     Ok(interval)
 }
+
+/// How often the clock offset is measured again.
+const CLOCK_DRIFT_CHECK_INTERVAL: Duration = Duration::from_secs(300);
+const NTP_TIMEOUT: Duration = Duration::from_secs(5);
+/// Seconds from the NTP epoch, 1900, to the UNIX epoch.
+const NTP_UNIX_EPOCH_OFFSET: u64 = 2_208_988_800;
+/// The leap indicator of a server whose clock isn't synchronized.
+const NTP_LEAP_UNSYNCHRONIZED: u8 = 3;
+const NTP_MODE_SERVER: u8 = 4;
+/// The clock offset is only measured against peers once this many of their blocks arrived, so a
+/// few blocks don't decide it.
+const MIN_BLOCK_ARRIVAL_OFFSETS: usize = 8;
+
+/// What the clock offset is measured against.
+#[derive(Debug, Clone)]
+pub enum ClockDriftSource {
+    /// The median offset of the local clock from these NTP servers.
+    Ntp(Vec<String>),
+    /// The median time after the start of their slot that valid blocks arrived at over gossip,
+    /// which includes the time they took to propagate.
+    Peers(Arc<NetworkState>),
+}
+
+/// Where the clock offset is measured against, and by how much it may be off before a warning is
+/// logged.
+#[derive(Debug, Clone)]
+pub struct ClockDriftConfig {
+    pub source: ClockDriftSource,
+    pub warn_threshold: Duration,
+}
+
+/// How far the system clock is off, see [clock_drift].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockDrift {
+    Synchronized,
+    /// Off by more than the warn threshold.
+    Drifting,
+    /// Off by more than the maximum gossip clock disparity, so messages of peers are rejected.
+    BeyondDisparity,
+}
+
+/// Classifies a clock offset of `offset_millis` against the maximum gossip clock disparity and the
+/// warn threshold.
+pub fn clock_drift(
+    offset_millis: i64,
+    maximum_disparity: u64,
+    warn_threshold: Duration,
+) -> ClockDrift {
+    let offset = offset_millis.unsigned_abs();
+    if offset > maximum_disparity {
+        ClockDrift::BeyondDisparity
+    } else if offset > warn_threshold.as_millis() as u64 {
+        ClockDrift::Drifting
+    } else {
+        ClockDrift::Synchronized
+    }
+}
+
+/// Periodically measures how far the system clock is off, publishing it as a metric and warning
+/// when it exceeds the threshold. A clock off by more than the maximum gossip clock disparity
+/// makes the node reject, or be rejected by, the messages of its peers.
+pub async fn monitor_clock_drift(config: ClockDriftConfig) {
+    let mut check_interval = tokio::time::interval(CLOCK_DRIFT_CHECK_INTERVAL);
+    check_interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        check_interval.tick().await;
+
+        let offset_millis = match &config.source {
+            ClockDriftSource::Ntp(ntp_servers) => {
+                let offset_millis = measure_clock_offset(ntp_servers).await;
+                if offset_millis.is_none() {
+                    warn!("Failed to measure the clock offset, none of the NTP servers answered");
+                }
+                offset_millis
+            }
+            ClockDriftSource::Peers(network_state) => {
+                let offset_millis = peer_clock_offset(network_state.block_arrival_offsets());
+                if offset_millis.is_none() {
+                    debug!("Not enough blocks arrived yet to measure the clock offset against");
+                }
+                offset_millis
+            }
+        };
+        let Some(offset_millis) = offset_millis else {
+            continue;
+        };
+        set_int_gauge_vec(&CLOCK_OFFSET_MILLISECONDS, offset_millis, &[]);
+
+        let maximum_disparity = lean_network_spec().maximum_gossip_clock_disparity;
+        match clock_drift(offset_millis, maximum_disparity, config.warn_threshold) {
+            ClockDrift::BeyondDisparity => warn!(
+                offset_millis,
+                maximum_disparity,
+                "System clock is off by more than the maximum gossip clock disparity, peers' \
+                 messages will be rejected. Check that the system clock is synchronized"
+            ),
+            ClockDrift::Drifting => warn!(offset_millis, "System clock is drifting"),
+            ClockDrift::Synchronized => debug!(offset_millis, "Measured the clock offset"),
+        }
+    }
+}
+
+/// Returns how many milliseconds the system clock is ahead of the NTP servers, the median of the
+/// servers which answered.
+pub async fn measure_clock_offset(ntp_servers: &[String]) -> Option<i64> {
+    let mut offsets = vec![];
+    for server in ntp_servers {
+        match timeout(NTP_TIMEOUT, query_ntp_offset(server)).await {
+            Ok(Ok(offset)) => offsets.push(offset),
+            Ok(Err(err)) => debug!(server, "Failed to query NTP server: {err:?}"),
+            Err(_) => debug!(server, "NTP server timed out"),
+        }
+    }
+    median(offsets)
+}
+
+/// Returns how many milliseconds the system clock is ahead of the proposers of the blocks which
+/// arrived at `block_arrival_offsets`, or `None` if too few blocks arrived.
+pub fn peer_clock_offset(block_arrival_offsets: Vec<i64>) -> Option<i64> {
+    if block_arrival_offsets.len() < MIN_BLOCK_ARRIVAL_OFFSETS {
+        return None;
+    }
+    median(block_arrival_offsets)
+}
+
+fn median(mut values: Vec<i64>) -> Option<i64> {
+    values.sort_unstable();
+    values.get(values.len() / 2).copied()
+}
+
+/// Sends a single SNTP request to `server`, returning how many milliseconds the system clock is
+/// ahead of it.
+async fn query_ntp_offset(server: &str) -> anyhow::Result<i64> {
+    let socket = UdpSocket::bind("0.0.0.0:0").await?;
+    socket.connect(server).await?;
+
+    // Leap indicator 0, version 4, client mode. The transmit timestamp comes back as the origin
+    // timestamp, tying the response to this request.
+    let mut request = [0u8; 48];
+    request[0] = 0x23;
+    let sent_at = unix_millis_now();
+    request[40..48].copy_from_slice(&unix_millis_to_ntp_timestamp(sent_at));
+    socket.send(&request).await?;
+
+    let mut response = [0u8; 48];
+    let length = socket.recv(&mut response).await?;
+    let received_at = unix_millis_now();
+    ensure!(
+        length == response.len(),
+        "NTP response is {length} bytes, expected 48"
+    );
+
+    let (server_received_at, server_sent_at) = parse_ntp_response(&request, &response)?;
+    Ok(clock_offset(
+        sent_at,
+        server_received_at,
+        server_sent_at,
+        received_at,
+    ))
+}
+
+/// Returns the receive and transmit times of a server's `response` to `request`, refusing
+/// responses of unsynchronized servers and responses which don't answer `request`.
+fn parse_ntp_response(request: &[u8; 48], response: &[u8; 48]) -> anyhow::Result<(i64, i64)> {
+    let leap_indicator = response[0] >> 6;
+    ensure!(
+        leap_indicator != NTP_LEAP_UNSYNCHRONIZED,
+        "NTP server clock isn't synchronized"
+    );
+    let mode = response[0] & 0x07;
+    ensure!(
+        mode == NTP_MODE_SERVER,
+        "NTP response has mode {mode}, expected {NTP_MODE_SERVER}"
+    );
+    // Stratum 0 is a kiss-o'-death message, 16 and above unsynchronized.
+    let stratum = response[1];
+    ensure!(
+        (1..=15).contains(&stratum),
+        "NTP response has stratum {stratum}"
+    );
+    ensure!(
+        response[24..32] == request[40..48],
+        "NTP response doesn't answer the request"
+    );
+
+    Ok((
+        ntp_timestamp_to_unix_millis(&response[32..40])?,
+        ntp_timestamp_to_unix_millis(&response[40..48])?,
+    ))
+}
+
+/// The NTP clock offset of the local clock, from the local send and receive times and the
+/// server's receive and send times.
+fn clock_offset(
+    sent_at: i64,
+    server_received_at: i64,
+    server_sent_at: i64,
+    received_at: i64,
+) -> i64 {
+    ((sent_at - server_received_at) + (received_at - server_sent_at)) / 2
+}
+
+fn ntp_timestamp_to_unix_millis(timestamp: &[u8]) -> anyhow::Result<i64> {
+    let timestamp = u64::from_be_bytes(timestamp.try_into()?);
+    let seconds = (timestamp >> 32)
+        .checked_sub(NTP_UNIX_EPOCH_OFFSET)
+        .ok_or_else(|| anyhow!("NTP timestamp is before the UNIX epoch"))?;
+    let millis = ((timestamp & 0xffff_ffff) * 1000) >> 32;
+    Ok((seconds * 1000 + millis) as i64)
+}
+
+fn unix_millis_to_ntp_timestamp(unix_millis: i64) -> [u8; 8] {
+    let unix_millis = unix_millis as u64;
+    let seconds = unix_millis / 1000 + NTP_UNIX_EPOCH_OFFSET;
+    let fraction = ((unix_millis % 1000) << 32) / 1000;
+    ((seconds << 32) | fraction).to_be_bytes()
+}
+
+fn unix_millis_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .expect("system time before UNIX EPOCH")
+        .as_millis() as i64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ntp_timestamp_to_unix_millis() {
+        let seconds = NTP_UNIX_EPOCH_OFFSET + 1_700_000_000;
+        let timestamp = (seconds << 32) | (1 << 31);
+        assert_eq!(
+            ntp_timestamp_to_unix_millis(&timestamp.to_be_bytes()).unwrap(),
+            1_700_000_000_500
+        );
+        assert!(ntp_timestamp_to_unix_millis(&0u64.to_be_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_clock_offset() {
+        // The local clock is 300ms ahead, and each way takes 20ms.
+        assert_eq!(clock_offset(1_300, 1_020, 1_030, 1_350), 300);
+        // The local clock is 200ms behind.
+        assert_eq!(clock_offset(1_000, 1_220, 1_230, 1_050), -200);
+    }
+
+    #[test]
+    fn test_unix_millis_to_ntp_timestamp() {
+        let timestamp = unix_millis_to_ntp_timestamp(1_700_000_000_500);
+        assert_eq!(
+            ntp_timestamp_to_unix_millis(&timestamp).unwrap(),
+            1_700_000_000_500
+        );
+    }
+
+    #[test]
+    fn test_parse_ntp_response() {
+        let mut request = [0u8; 48];
+        request[0] = 0x23;
+        request[40..48].copy_from_slice(&unix_millis_to_ntp_timestamp(1_000));
+        let response = |first_byte: u8, stratum: u8, origin: i64| {
+            let mut response = [0u8; 48];
+            response[0] = first_byte;
+            response[1] = stratum;
+            response[24..32].copy_from_slice(&unix_millis_to_ntp_timestamp(origin));
+            response[32..40].copy_from_slice(&unix_millis_to_ntp_timestamp(1_020));
+            response[40..48].copy_from_slice(&unix_millis_to_ntp_timestamp(1_030));
+            response
+        };
+
+        // Leap indicator 0, version 4, server mode.
+        assert_eq!(
+            parse_ntp_response(&request, &response(0x24, 2, 1_000)).unwrap(),
+            (1_020, 1_030)
+        );
+        // Unsynchronized servers.
+        assert!(parse_ntp_response(&request, &response(0xe4, 2, 1_000)).is_err());
+        assert!(parse_ntp_response(&request, &response(0x24, 16, 1_000)).is_err());
+        // Kiss-o'-death.
+        assert!(parse_ntp_response(&request, &response(0x24, 0, 1_000)).is_err());
+        // Not a server response, or the response to another request.
+        assert!(parse_ntp_response(&request, &response(0x23, 2, 1_000)).is_err());
+        assert!(parse_ntp_response(&request, &response(0x24, 2, 2_000)).is_err());
+    }
+
+    #[test]
+    fn test_clock_drift() {
+        let warn_threshold = Duration::from_millis(250);
+        assert_eq!(
+            clock_drift(0, 500, warn_threshold),
+            ClockDrift::Synchronized
+        );
+        assert_eq!(
+            clock_drift(-250, 500, warn_threshold),
+            ClockDrift::Synchronized
+        );
+        assert_eq!(clock_drift(251, 500, warn_threshold), ClockDrift::Drifting);
+        assert_eq!(clock_drift(-500, 500, warn_threshold), ClockDrift::Drifting);
+        assert_eq!(
+            clock_drift(501, 500, warn_threshold),
+            ClockDrift::BeyondDisparity
+        );
+        assert_eq!(
+            clock_drift(-501, 500, warn_threshold),
+            ClockDrift::BeyondDisparity
+        );
+    }
+
+    #[test]
+    fn test_peer_clock_offset() {
+        assert_eq!(
+            peer_clock_offset(vec![100; MIN_BLOCK_ARRIVAL_OFFSETS - 1]),
+            None
+        );
+        // A few blocks arriving late, or early, don't move the offset.
+        let mut offsets = vec![100; MIN_BLOCK_ARRIVAL_OFFSETS];
+        offsets.extend([-3_000, 3_000, 3_000]);
+        assert_eq!(peer_clock_offset(offsets), Some(100));
+    }
+}
@@ -0,0 +1,56 @@
+//! Whether finality stalled, so the node warns once when finality stops advancing and once when
+//! it advances again, rather than every slot.
+
+#[derive(Debug)]
+pub struct FinalityStall {
+    stall_slots: u64,
+    stalled: bool,
+}
+
+impl FinalityStall {
+    /// Reports finality as stalled once it hasn't advanced for `stall_slots` slots.
+    pub fn new(stall_slots: u64) -> Self {
+        Self {
+            stall_slots,
+            stalled: false,
+        }
+    }
+
+    /// Records that finality last advanced `slots_since_finalization` slots ago. Returns whether
+    /// finality is stalled now if that changed.
+    pub fn update(&mut self, slots_since_finalization: u64) -> Option<bool> {
+        let stalled = slots_since_finalization >= self.stall_slots;
+        if stalled == self.stalled {
+            return None;
+        }
+        self.stalled = stalled;
+        Some(stalled)
+    }
+
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FinalityStall;
+
+    #[test]
+    fn test_finality_stall() {
+        let mut finality_stall = FinalityStall::new(4);
+        assert_eq!(finality_stall.update(0), None);
+        assert_eq!(finality_stall.update(3), None);
+        assert!(!finality_stall.is_stalled());
+
+        // The change is only reported once, however long finality stays stalled.
+        assert_eq!(finality_stall.update(4), Some(true));
+        assert_eq!(finality_stall.update(5), None);
+        assert_eq!(finality_stall.update(100), None);
+        assert!(finality_stall.is_stalled());
+
+        assert_eq!(finality_stall.update(1), Some(false));
+        assert_eq!(finality_stall.update(2), None);
+        assert!(!finality_stall.is_stalled());
+    }
+}
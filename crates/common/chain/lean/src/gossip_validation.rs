@@ -0,0 +1,138 @@
+use alloy_primitives::B256;
+use libp2p::gossipsub::MessageAcceptance;
+use ream_fork_choice_lean::rejection::{ForkChoiceError, RejectReason};
+use tokio::sync::oneshot;
+use tracing::debug;
+
+/// Where the [MessageAcceptance] of a message received over gossip is sent, `None` for messages
+/// from elsewhere. Queued blocks keep it until they are imported or dropped, so gossipsub only
+/// forwards them once they turn out valid.
+pub type Validation = Option<oneshot::Sender<MessageAcceptance>>;
+
+/// What gossipsub does with a block or attestation from a peer which fork choice didn't import.
+///
+/// Messages breaking a consensus rule are rejected, which drops them and counts against the peer
+/// which sent them. Messages which may still turn out valid, like ones for a block we haven't
+/// seen yet, and failures of the node itself are ignored, which drops them without a penalty.
+pub fn message_acceptance(err: &ForkChoiceError) -> MessageAcceptance {
+    match err {
+        ForkChoiceError::FutureBlock(_) => MessageAcceptance::Ignore,
+        ForkChoiceError::Rejected(rejected) => match rejected.reason {
+            RejectReason::FutureSlot
+            | RejectReason::UnknownParent
+            | RejectReason::Duplicate
+            | RejectReason::UnknownBlock
+            | RejectReason::DataUnavailable => MessageAcceptance::Ignore,
+            RejectReason::InvalidSignature | RejectReason::Invalid => MessageAcceptance::Reject,
+        },
+        ForkChoiceError::InvalidStateTransition(err) if err.is_internal() => {
+            MessageAcceptance::Ignore
+        }
+        ForkChoiceError::InvalidStateTransition(_) => MessageAcceptance::Reject,
+        ForkChoiceError::Storage(_) | ForkChoiceError::Internal(_) => MessageAcceptance::Ignore,
+    }
+}
+
+/// The [MessageAcceptance] of a block from a peer, once it is imported, queued or rejected.
+/// Queued blocks are only ignored if they couldn't keep their validation, see [Validation].
+pub fn block_acceptance(result: &Result<Option<B256>, ForkChoiceError>) -> MessageAcceptance {
+    match result {
+        Ok(Some(_)) => MessageAcceptance::Accept,
+        Ok(None) => MessageAcceptance::Ignore,
+        Err(err) => message_acceptance(err),
+    }
+}
+
+/// Sends the [MessageAcceptance] of a message back to the network, if it came over gossip.
+pub fn send_acceptance(validation: Validation, acceptance: MessageAcceptance) {
+    if let Some(validation) = validation
+        && validation.send(acceptance).is_err()
+    {
+        debug!("Network stopped waiting for the gossip validation result");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use anyhow::anyhow;
+    use libp2p::gossipsub::MessageAcceptance;
+    use ream_consensus_lean::errors::StateTransitionError;
+    use ream_fork_choice_lean::{
+        rejection::{ForkChoiceError, RejectReason, RejectedError},
+        store::FutureBlockError,
+    };
+
+    use super::{block_acceptance, message_acceptance};
+
+    fn rejected(reason: RejectReason) -> ForkChoiceError {
+        ForkChoiceError::Rejected(RejectedError::new(reason, "Rejected"))
+    }
+
+    #[test]
+    fn test_message_acceptance() {
+        for reason in [
+            RejectReason::FutureSlot,
+            RejectReason::UnknownParent,
+            RejectReason::Duplicate,
+            RejectReason::UnknownBlock,
+            RejectReason::DataUnavailable,
+        ] {
+            assert!(matches!(
+                message_acceptance(&rejected(reason)),
+                MessageAcceptance::Ignore
+            ));
+        }
+        for reason in [RejectReason::InvalidSignature, RejectReason::Invalid] {
+            assert!(matches!(
+                message_acceptance(&rejected(reason)),
+                MessageAcceptance::Reject
+            ));
+        }
+
+        assert!(matches!(
+            message_acceptance(&ForkChoiceError::FutureBlock(FutureBlockError {
+                slot: 2,
+                latest_slot: 1,
+            })),
+            MessageAcceptance::Ignore
+        ));
+        assert!(matches!(
+            message_acceptance(&ForkChoiceError::InvalidStateTransition(
+                StateTransitionError::InvalidStateRoot
+            )),
+            MessageAcceptance::Reject
+        ));
+
+        // The node failing to process a message says nothing about the peer which sent it.
+        assert!(matches!(
+            message_acceptance(&ForkChoiceError::InvalidStateTransition(
+                StateTransitionError::NetworkSpecNotSet { slot: 1 }
+            )),
+            MessageAcceptance::Ignore
+        ));
+        assert!(matches!(
+            message_acceptance(&ForkChoiceError::from(anyhow!(
+                "Failed to read the block table"
+            ))),
+            MessageAcceptance::Ignore
+        ));
+    }
+
+    #[test]
+    fn test_block_acceptance() {
+        assert!(matches!(
+            block_acceptance(&Ok(Some(B256::ZERO))),
+            MessageAcceptance::Accept
+        ));
+        // A queued block which couldn't keep its validation is dropped without a penalty.
+        assert!(matches!(
+            block_acceptance(&Ok(None)),
+            MessageAcceptance::Ignore
+        ));
+        assert!(matches!(
+            block_acceptance(&Err(rejected(RejectReason::Invalid))),
+            MessageAcceptance::Reject
+        ));
+    }
+}
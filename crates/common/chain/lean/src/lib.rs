@@ -1,5 +1,12 @@
+pub mod block_source;
 pub mod clock;
+pub mod finality;
+pub mod gossip_validation;
 pub mod messages;
+pub mod orphan_blocks;
 pub mod p2p_request;
+pub mod pending_blobs;
+pub mod performance;
+pub mod recent_blocks;
 pub mod service;
 pub mod slot;
@@ -1,9 +1,15 @@
+use std::sync::Arc;
+
+use alloy_primitives::B256;
+use libp2p::gossipsub::MessageAcceptance;
 use libp2p_identity::PeerId;
 use ream_consensus_lean::{
     attestation::{AttestationData, SignedAttestation},
+    blob_sidecar::{BlobIdentifier, BlobSidecar},
     block::{BlockWithSignatures, SignedBlockWithAttestation},
     checkpoint::Checkpoint,
 };
+use ream_post_quantum_crypto::leansig::public_key::PublicKey;
 use tokio::sync::oneshot;
 
 /// Messages that exchange information between the [LeanChainService] and other components.
@@ -12,17 +18,46 @@ use tokio::sync::oneshot;
 ///
 /// `BuildAttestationData`: Request to build an [AttestationData] for a given slot.
 ///
+/// `GetProposerIndex`: Request for the index of the validator proposing at a given slot on the
+/// current head.
+///
+/// `GetValidatorPublicKey`: Request for the key a validator signs with at a given slot on the
+/// current head.
+///
 /// `ProcessBlock`: Request to process a new [SignedBlock], with a couple of flags. For flags, see
 /// below for the explanation.
 ///
 /// `ProcessAttestation`: Request to process a new [SignedAttestation], with a couple of flags. For
 /// flags, see below for the explanation.
 ///
+/// `ProcessBlobSidecar`: Request to store a new [BlobSidecar], with the `need_gossip` flag and a
+/// `validation` sender.
+///
+/// `GetBlocksByRoot`: Request for the stored blocks of the given roots, to serve a BlocksByRoot
+/// request of a peer. Roots of blocks we don't have are skipped.
+///
+/// `GetBlobSidecarsByRoot`: Request for the stored blob sidecars of the given identifiers, to serve
+/// a BlobsByRoot request of a peer. Sidecars we don't have are skipped.
+///
+/// `BlockLookupFailed`: Notification that the lookup of a block was given up, so the orphan blocks
+/// waiting for it are dropped.
+///
 /// Flags:
 /// `need_gossip`: If true, the block/vote should be gossiped to other peers. In 3SF-mini, a node
 /// enqueues an item if it is not ready for processing. The node would later consume the queue
 /// (`self.dependencies` in the original Python implementation) for the items. In this case, the
 /// node doesn't have to publish block/vote.
+/// `from_peer`: If true, the vote was received from a peer over gossip rather than submitted to
+/// this node by a validator or the API.
+/// `peer_id`: The peer a block was received from, if any, so a peer can only queue a few blocks
+/// whose parent is unknown.
+///
+/// `validation`: Set for messages received over gossip, which gossipsub holds until it is told
+/// whether to forward, drop or penalize them. The [MessageAcceptance] is sent back on it once the
+/// message is processed, see [message_acceptance], or for a queued block once it is imported.
+/// Gossipsub forwards the accepted messages itself, so these don't need gossiping again.
+///
+/// [message_acceptance]: crate::gossip_validation::message_acceptance
 #[derive(Debug)]
 pub enum LeanChainServiceMessage {
     ProduceBlock {
@@ -33,17 +68,46 @@ pub enum LeanChainServiceMessage {
         slot: u64,
         sender: oneshot::Sender<AttestationData>,
     },
+    GetProposerIndex {
+        slot: u64,
+        sender: oneshot::Sender<anyhow::Result<u64>>,
+    },
+    GetValidatorPublicKey {
+        validator_index: u64,
+        slot: u64,
+        sender: oneshot::Sender<anyhow::Result<Option<PublicKey>>>,
+    },
     ProcessBlock {
         signed_block_with_attestation: Box<SignedBlockWithAttestation>,
         need_gossip: bool,
+        peer_id: Option<PeerId>,
+        validation: Option<oneshot::Sender<MessageAcceptance>>,
     },
     ProcessAttestation {
         signed_attestation: Box<SignedAttestation>,
         need_gossip: bool,
+        from_peer: bool,
+        validation: Option<oneshot::Sender<MessageAcceptance>>,
+    },
+    ProcessBlobSidecar {
+        blob_sidecar: Box<BlobSidecar>,
+        need_gossip: bool,
+        validation: Option<oneshot::Sender<MessageAcceptance>>,
     },
     CheckIfCanonicalCheckpoint {
         peer_id: PeerId,
         checkpoint: Checkpoint,
         sender: oneshot::Sender<(PeerId, bool)>,
     },
+    GetBlocksByRoot {
+        roots: Vec<B256>,
+        sender: oneshot::Sender<Vec<Arc<SignedBlockWithAttestation>>>,
+    },
+    GetBlobSidecarsByRoot {
+        identifiers: Vec<BlobIdentifier>,
+        sender: oneshot::Sender<Vec<Arc<BlobSidecar>>>,
+    },
+    BlockLookupFailed {
+        root: B256,
+    },
 }
@@ -0,0 +1,299 @@
+//! The blocks whose parent isn't known yet, kept until the parent is fetched from peers.
+//!
+//! Each peer can only queue a few blocks, so a single peer gossiping blocks on unknown parents
+//! can't fill the queue for everyone else. Once the lookup of a parent is given up, the blocks
+//! waiting for it, and the blocks waiting for those, are dropped.
+//!
+//! Blocks from gossip keep their [Validation] while they wait, dropped blocks are ignored.
+
+use std::{collections::HashMap, mem};
+
+use alloy_primitives::B256;
+use libp2p::gossipsub::MessageAcceptance;
+use libp2p_identity::PeerId;
+use ream_consensus_lean::block::SignedBlockWithAttestation;
+use tree_hash::TreeHash;
+
+use crate::gossip_validation::{Validation, send_acceptance};
+
+/// Blocks beyond this many are rejected instead of waiting for their parent to be fetched.
+pub const MAX_ORPHAN_BLOCKS: usize = 64;
+
+/// Blocks from one peer beyond this many are rejected, so the queue has room for other peers.
+pub const MAX_ORPHAN_BLOCKS_PER_PEER: usize = 8;
+
+#[derive(Debug)]
+struct OrphanBlock {
+    block_root: B256,
+    block: SignedBlockWithAttestation,
+    peer_id: Option<PeerId>,
+    validation: Validation,
+}
+
+#[derive(Debug, Default)]
+pub struct OrphanBlocks {
+    /// The blocks waiting for their parent, by parent root.
+    blocks: HashMap<B256, Vec<OrphanBlock>>,
+    /// How many of the blocks each peer queued.
+    peer_counts: HashMap<PeerId, usize>,
+    len: usize,
+}
+
+impl OrphanBlocks {
+    /// Queues `block`, received from `peer_id` if it came from a peer, until its parent is
+    /// imported, taking its `validation`. Returns whether the parent has to be fetched, as no other
+    /// block waits for it, or `None` if the queue is full.
+    pub fn insert(
+        &mut self,
+        block: SignedBlockWithAttestation,
+        peer_id: Option<PeerId>,
+        validation: &mut Validation,
+    ) -> Option<bool> {
+        if self.len >= MAX_ORPHAN_BLOCKS {
+            return None;
+        }
+        if let Some(peer_id) = peer_id {
+            let count = self.peer_counts.entry(peer_id).or_default();
+            if *count >= MAX_ORPHAN_BLOCKS_PER_PEER {
+                return None;
+            }
+            *count += 1;
+        }
+
+        let block_root = block.message.block.tree_hash_root();
+        let siblings = self
+            .blocks
+            .entry(block.message.block.parent_root)
+            .or_default();
+        let fetch_parent = siblings.is_empty();
+        if siblings
+            .iter()
+            .any(|orphan| orphan.block_root == block_root)
+        {
+            self.release(peer_id);
+            return Some(false);
+        }
+        siblings.push(OrphanBlock {
+            block_root,
+            block,
+            peer_id,
+            validation: validation.take(),
+        });
+        self.len += 1;
+        Some(fetch_parent)
+    }
+
+    /// Removes and returns the blocks waiting for `parent_root`, once it is imported, with their
+    /// validations.
+    pub fn take_children(
+        &mut self,
+        parent_root: &B256,
+    ) -> Vec<(SignedBlockWithAttestation, Validation)> {
+        self.blocks
+            .remove(parent_root)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|orphan| {
+                self.len -= 1;
+                self.release(orphan.peer_id);
+                (orphan.block, orphan.validation)
+            })
+            .collect()
+    }
+
+    /// Drops the blocks waiting for `parent_root`, whose lookup was given up, and the blocks
+    /// waiting for those. Returns how many were dropped.
+    pub fn remove_descendants(&mut self, parent_root: B256) -> usize {
+        let mut dropped = 0;
+        let mut roots = vec![parent_root];
+        while let Some(root) = roots.pop() {
+            for orphan in self.blocks.remove(&root).unwrap_or_default() {
+                self.len -= 1;
+                self.release(orphan.peer_id);
+                send_acceptance(orphan.validation, MessageAcceptance::Ignore);
+                roots.push(orphan.block_root);
+                dropped += 1;
+            }
+        }
+        dropped
+    }
+
+    /// Drops the blocks at or before `finalized_slot`, which can't be imported anymore.
+    pub fn prune(&mut self, finalized_slot: u64) {
+        let mut released = vec![];
+        self.blocks.retain(|_, blocks| {
+            let (kept, pruned) = mem::take(blocks)
+                .into_iter()
+                .partition(|orphan| orphan.block.message.block.slot > finalized_slot);
+            *blocks = kept;
+            released.extend(pruned);
+            !blocks.is_empty()
+        });
+        self.len -= released.len();
+        for orphan in released {
+            self.release(orphan.peer_id);
+            send_acceptance(orphan.validation, MessageAcceptance::Ignore);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn release(&mut self, peer_id: Option<PeerId>) {
+        if let Some(peer_id) = peer_id
+            && let Some(count) = self.peer_counts.get_mut(&peer_id)
+        {
+            *count -= 1;
+            if *count == 0 {
+                self.peer_counts.remove(&peer_id);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use libp2p::gossipsub::MessageAcceptance;
+    use libp2p_identity::PeerId;
+    use ream_test_utils::sample_block;
+    use tokio::sync::oneshot;
+    use tree_hash::TreeHash;
+
+    use super::{MAX_ORPHAN_BLOCKS, MAX_ORPHAN_BLOCKS_PER_PEER, OrphanBlocks};
+
+    #[test]
+    fn test_orphan_blocks_per_peer() {
+        let mut orphan_blocks = OrphanBlocks::default();
+        let (peer_id, other_peer_id) = (PeerId::random(), PeerId::random());
+        let parent_root = B256::repeat_byte(1);
+
+        // Only the first block waiting for a parent fetches it.
+        assert_eq!(
+            orphan_blocks.insert(sample_block(1, 0, parent_root), Some(peer_id), &mut None),
+            Some(true)
+        );
+        for slot in 2..=MAX_ORPHAN_BLOCKS_PER_PEER as u64 {
+            assert_eq!(
+                orphan_blocks.insert(sample_block(slot, 0, parent_root), Some(peer_id), &mut None),
+                Some(false)
+            );
+        }
+        assert_eq!(
+            orphan_blocks.insert(sample_block(100, 0, parent_root), Some(peer_id), &mut None),
+            None
+        );
+        assert!(
+            orphan_blocks
+                .insert(
+                    sample_block(100, 0, parent_root),
+                    Some(other_peer_id),
+                    &mut None
+                )
+                .is_some()
+        );
+
+        // Importing the parent frees the room of the peer.
+        assert_eq!(
+            orphan_blocks.take_children(&parent_root).len(),
+            MAX_ORPHAN_BLOCKS_PER_PEER + 1
+        );
+        assert!(orphan_blocks.is_empty());
+        assert_eq!(
+            orphan_blocks.insert(sample_block(100, 0, parent_root), Some(peer_id), &mut None),
+            Some(true)
+        );
+    }
+
+    #[test]
+    fn test_orphan_blocks_limit() {
+        let mut orphan_blocks = OrphanBlocks::default();
+        for slot in 0..MAX_ORPHAN_BLOCKS as u64 {
+            assert!(
+                orphan_blocks
+                    .insert(
+                        sample_block(slot, 0, B256::with_last_byte(slot as u8)),
+                        None,
+                        &mut None
+                    )
+                    .is_some()
+            );
+        }
+        let (validation, mut receiver) = oneshot::channel();
+        let mut validation = Some(validation);
+        assert_eq!(
+            orphan_blocks.insert(sample_block(100, 0, B256::ZERO), None, &mut validation),
+            None
+        );
+        // A rejected block keeps its validation, for the caller to answer.
+        assert!(validation.is_some());
+
+        orphan_blocks.prune(1);
+        assert_eq!(orphan_blocks.len(), MAX_ORPHAN_BLOCKS - 2);
+        assert!(
+            orphan_blocks
+                .take_children(&B256::with_last_byte(1))
+                .is_empty()
+        );
+        assert!(receiver.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_orphan_block_validation() {
+        let mut orphan_blocks = OrphanBlocks::default();
+        let parent_root = B256::repeat_byte(1);
+        let (validation, mut receiver) = oneshot::channel();
+        let mut validation = Some(validation);
+        orphan_blocks.insert(sample_block(2, 0, parent_root), None, &mut validation);
+        assert!(validation.is_none());
+
+        // The validation waits with the block, for the caller to answer once it is imported.
+        let mut children = orphan_blocks.take_children(&parent_root);
+        let (_, validation) = children.pop().expect("block is queued");
+        validation
+            .expect("block came over gossip")
+            .send(MessageAcceptance::Accept)
+            .expect("receiver is alive");
+        assert!(matches!(receiver.try_recv(), Ok(MessageAcceptance::Accept)));
+
+        // Pruned blocks are ignored.
+        let (validation, mut receiver) = oneshot::channel();
+        orphan_blocks.insert(sample_block(2, 0, parent_root), None, &mut Some(validation));
+        orphan_blocks.prune(2);
+        assert!(matches!(receiver.try_recv(), Ok(MessageAcceptance::Ignore)));
+    }
+
+    #[test]
+    fn test_remove_descendants() {
+        let mut orphan_blocks = OrphanBlocks::default();
+        let peer_id = PeerId::random();
+        let missing_root = B256::repeat_byte(1);
+        let child = sample_block(2, 0, missing_root);
+        let grandchild = sample_block(3, 0, child.message.block.tree_hash_root());
+        let (validation, mut receiver) = oneshot::channel();
+        orphan_blocks.insert(child, Some(peer_id), &mut None);
+        orphan_blocks.insert(grandchild, Some(peer_id), &mut Some(validation));
+        orphan_blocks.insert(
+            sample_block(2, 0, B256::repeat_byte(2)),
+            Some(peer_id),
+            &mut None,
+        );
+
+        // Giving up the lookup drops the blocks which can never be imported.
+        assert_eq!(orphan_blocks.remove_descendants(missing_root), 2);
+        assert_eq!(orphan_blocks.len(), 1);
+        assert_eq!(orphan_blocks.remove_descendants(missing_root), 0);
+        assert!(matches!(receiver.try_recv(), Ok(MessageAcceptance::Ignore)));
+
+        // A later block on the missing parent fetches it again.
+        assert_eq!(
+            orphan_blocks.insert(sample_block(4, 0, missing_root), None, &mut None),
+            Some(true)
+        );
+    }
+}
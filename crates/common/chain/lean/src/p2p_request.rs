@@ -1,7 +1,39 @@
-use ream_consensus_lean::{attestation::SignedAttestation, block::SignedBlockWithAttestation};
+use alloy_primitives::B256;
+use libp2p::{Multiaddr, PeerId};
+use ream_consensus_lean::{
+    attestation::SignedAttestation, blob_sidecar::BlobSidecar, block::SignedBlockWithAttestation,
+};
+
+/// What happens to the gossip and req/resp messages received from a peer, to simulate a bad
+/// network in tests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkFaults {
+    /// Percentage of the messages which are dropped.
+    pub drop_percentage: u8,
+    /// Percentage of the messages which aren't dropped that are held back for `delay_ms`.
+    pub delay_percentage: u8,
+    pub delay_ms: u64,
+}
 
 #[derive(Debug, Clone)]
 pub enum LeanP2PRequest {
     GossipBlock(Box<SignedBlockWithAttestation>),
     GossipAttestation(Box<SignedAttestation>),
+    GossipBlobSidecar(Box<BlobSidecar>),
+    /// Dial the peer and redial it whenever the connection drops.
+    AddTrustedPeer(Multiaddr),
+    RemoveTrustedPeer(PeerId),
+    /// Disconnect the peer and refuse its connections from now on.
+    BanPeer(PeerId),
+    /// Fetch the block with this root from peers, and send it back to be processed.
+    RequestBlock(B256),
+    /// Sent last by the chain service once it stopped on shutdown, so the network service knows
+    /// everything it has to gossip is queued.
+    ChainServiceStopped,
+    /// Apply the faults to the messages of the peer, or of every peer without a peer. Ignored
+    /// unless the network service is built with the `testing` feature.
+    SetNetworkFaults {
+        peer_id: Option<PeerId>,
+        faults: NetworkFaults,
+    },
 }
@@ -0,0 +1,212 @@
+//! The blocks rejected because their blob sidecars are incomplete, kept until the missing
+//! sidecars arrive, and the sidecars which arrived before their block.
+//!
+//! Only the sidecars of known blocks and of the blocks waiting here are stored, so peers can't
+//! fill the database with sidecars of blocks which don't exist. Sidecars arriving first are held
+//! in memory for a few blocks, the least recently used ones are dropped beyond that.
+
+use std::{collections::HashMap, num::NonZeroUsize};
+
+use alloy_primitives::B256;
+use libp2p::gossipsub::MessageAcceptance;
+use lru::LruCache;
+use ream_consensus_lean::{
+    blob_sidecar::{BlobSidecar, MAX_BLOBS_PER_BLOCK},
+    block::SignedBlockWithAttestation,
+};
+
+use crate::gossip_validation::{Validation, send_acceptance};
+
+/// Blocks waiting for their sidecars beyond this many are rejected.
+pub const MAX_PENDING_BLOCKS: usize = 16;
+
+/// Sidecars which arrived before their block are held for at most this many blocks.
+pub const MAX_EARLY_SIDECAR_BLOCKS: usize = 16;
+
+#[derive(Debug)]
+pub struct PendingBlobs {
+    blocks: HashMap<B256, (SignedBlockWithAttestation, Validation)>,
+    early_sidecars: LruCache<B256, Vec<BlobSidecar>>,
+}
+
+impl Default for PendingBlobs {
+    fn default() -> Self {
+        Self {
+            blocks: HashMap::new(),
+            early_sidecars: LruCache::new(
+                NonZeroUsize::new(MAX_EARLY_SIDECAR_BLOCKS).expect("Invalid cache size"),
+            ),
+        }
+    }
+}
+
+impl PendingBlobs {
+    /// Keeps `block` until its sidecars arrive, taking its `validation`, and returns the sidecars
+    /// of it which arrived first, or `None` if too many blocks are waiting already.
+    pub fn insert_block(
+        &mut self,
+        block_root: B256,
+        block: SignedBlockWithAttestation,
+        validation: &mut Validation,
+    ) -> Option<Vec<BlobSidecar>> {
+        if !self.blocks.contains_key(&block_root) && self.blocks.len() >= MAX_PENDING_BLOCKS {
+            return None;
+        }
+        if let Some((_, earlier_validation)) =
+            self.blocks.insert(block_root, (block, validation.take()))
+        {
+            send_acceptance(earlier_validation, MessageAcceptance::Ignore);
+        }
+        Some(self.early_sidecars.pop(&block_root).unwrap_or_default())
+    }
+
+    pub fn contains_block(&self, block_root: &B256) -> bool {
+        self.blocks.contains_key(block_root)
+    }
+
+    /// Removes a waiting block, with its validation.
+    pub fn remove_block(
+        &mut self,
+        block_root: &B256,
+    ) -> Option<(SignedBlockWithAttestation, Validation)> {
+        self.blocks.remove(block_root)
+    }
+
+    /// Holds a sidecar of a block which hasn't arrived yet, replacing an earlier one of the same
+    /// index.
+    pub fn insert_early_sidecar(&mut self, blob_sidecar: BlobSidecar) {
+        let sidecars = self
+            .early_sidecars
+            .get_or_insert_mut(blob_sidecar.block_root, Vec::new);
+        sidecars.retain(|sidecar| sidecar.index != blob_sidecar.index);
+        // Sidecars are validated first, so a block never has more than this many.
+        if sidecars.len() < MAX_BLOBS_PER_BLOCK as usize {
+            sidecars.push(blob_sidecar);
+        }
+    }
+
+    /// Drops the blocks at or before `finalized_slot`, which can't be imported anymore, and
+    /// returns their roots. Their validations are ignored.
+    pub fn prune(&mut self, finalized_slot: u64) -> Vec<B256> {
+        let pruned = self
+            .blocks
+            .iter()
+            .filter(|(_, (block, _))| block.message.block.slot <= finalized_slot)
+            .map(|(block_root, _)| *block_root)
+            .collect::<Vec<_>>();
+        for block_root in &pruned {
+            if let Some((_, validation)) = self.blocks.remove(block_root) {
+                send_acceptance(validation, MessageAcceptance::Ignore);
+            }
+        }
+        pruned
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use libp2p::gossipsub::MessageAcceptance;
+    use ream_consensus_lean::blob_sidecar::{BlobSidecar, MAX_BLOBS_PER_BLOCK};
+    use ream_test_utils::sample_block;
+    use ssz_types::VariableList;
+    use tokio::sync::oneshot;
+
+    use super::{MAX_EARLY_SIDECAR_BLOCKS, MAX_PENDING_BLOCKS, PendingBlobs};
+
+    fn sidecar(block_root: B256, index: u64) -> BlobSidecar {
+        BlobSidecar {
+            block_root,
+            index,
+            blob_count: MAX_BLOBS_PER_BLOCK,
+            blob: VariableList::empty(),
+        }
+    }
+
+    #[test]
+    fn test_pending_blocks() {
+        let mut pending_blobs = PendingBlobs::default();
+        for slot in 0..MAX_PENDING_BLOCKS as u64 {
+            assert_eq!(
+                pending_blobs.insert_block(
+                    B256::with_last_byte(slot as u8),
+                    sample_block(slot, 0, B256::ZERO),
+                    &mut None
+                ),
+                Some(vec![])
+            );
+        }
+        // The queue is full, but a block waiting already can be queued again.
+        assert_eq!(
+            pending_blobs.insert_block(
+                B256::repeat_byte(0xff),
+                sample_block(1, 0, B256::ZERO),
+                &mut None
+            ),
+            None
+        );
+        assert!(
+            pending_blobs
+                .insert_block(
+                    B256::with_last_byte(1),
+                    sample_block(1, 0, B256::ZERO),
+                    &mut None
+                )
+                .is_some()
+        );
+
+        let (validation, mut receiver) = oneshot::channel();
+        pending_blobs.insert_block(
+            B256::with_last_byte(0),
+            sample_block(0, 0, B256::ZERO),
+            &mut Some(validation),
+        );
+        assert_eq!(pending_blobs.prune(1).len(), 2);
+        // Pruned blocks are ignored by gossipsub.
+        assert!(matches!(receiver.try_recv(), Ok(MessageAcceptance::Ignore)));
+        assert_eq!(pending_blobs.len(), MAX_PENDING_BLOCKS - 2);
+        assert!(!pending_blobs.contains_block(&B256::with_last_byte(1)));
+        assert!(
+            pending_blobs
+                .remove_block(&B256::with_last_byte(2))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_early_sidecars() {
+        let mut pending_blobs = PendingBlobs::default();
+        let block_root = B256::repeat_byte(1);
+        pending_blobs.insert_early_sidecar(sidecar(block_root, 0));
+        pending_blobs.insert_early_sidecar(sidecar(block_root, 0));
+        pending_blobs.insert_early_sidecar(sidecar(block_root, 1));
+
+        for byte in 2..=MAX_EARLY_SIDECAR_BLOCKS as u8 {
+            pending_blobs.insert_early_sidecar(sidecar(B256::repeat_byte(byte), 0));
+        }
+        assert_eq!(
+            pending_blobs.insert_block(block_root, sample_block(1, 0, B256::ZERO), &mut None),
+            Some(vec![sidecar(block_root, 0), sidecar(block_root, 1)])
+        );
+
+        // Beyond the limit, the sidecars of the least recently used block are dropped.
+        pending_blobs.insert_early_sidecar(sidecar(B256::repeat_byte(0xff), 0));
+        pending_blobs.insert_early_sidecar(sidecar(B256::repeat_byte(0xfe), 0));
+        assert_eq!(
+            pending_blobs.insert_block(
+                B256::repeat_byte(2),
+                sample_block(2, 0, B256::ZERO),
+                &mut None
+            ),
+            Some(vec![])
+        );
+    }
+}
@@ -0,0 +1,182 @@
+use std::{collections::HashSet, iter};
+
+use ream_metrics::{VALIDATOR_ATTESTATIONS_TOTAL, VALIDATOR_PROPOSALS_TOTAL, inc_int_counter_vec};
+use ream_storage::{
+    db::lean::LeanDB,
+    tables::{field::REDBField, lean::validator_performance::SlotPerformance, table::REDBTable},
+};
+
+/// Number of most recent slots the performance of each validator is kept for.
+pub const PERFORMANCE_WINDOW_SLOTS: u64 = 256;
+
+/// Attestations included in blocks up to this many slots after the slot they attest in count as
+/// included, so a slot is tracked once this many later slots have passed.
+pub const ATTESTATION_INCLUSION_SLOTS: u64 = 2;
+
+/// Records, for every validator, whether it proposed the blocks it was due to and whether its
+/// attestations made it into the canonical chain.
+#[derive(Debug, Clone, Default)]
+pub struct ValidatorPerformanceTracker {
+    /// Whether to also export the performance as metrics labelled by validator.
+    export_metrics: bool,
+}
+
+impl ValidatorPerformanceTracker {
+    pub fn new(export_metrics: bool) -> Self {
+        Self { export_metrics }
+    }
+
+    /// Tracks what each validator did in `slot` according to the chain of the current head.
+    /// Slots the head hasn't reached yet, such as while syncing, are left untracked.
+    pub async fn track_slot(&self, lean_db: &LeanDB, slot: u64) -> anyhow::Result<()> {
+        let head = lean_db.head_provider().get()?;
+        let Some((proposed, attesters, head_state)) = lean_db
+            .run_blocking(move |lean_db| {
+                let block_provider = lean_db.block_provider();
+                let mut proposed = false;
+                let mut attesters = HashSet::new();
+                let mut reached_slot = false;
+                let mut current = head;
+                while let Some(block) = block_provider.get(current)? {
+                    let block = block.message;
+                    if block.block.slot < slot {
+                        break;
+                    }
+                    reached_slot = true;
+                    if block.block.slot <= slot + ATTESTATION_INCLUSION_SLOTS {
+                        proposed |= block.block.slot == slot;
+                        attesters.extend(
+                            block
+                                .block
+                                .body
+                                .attestations
+                                .iter()
+                                .chain(iter::once(&block.proposer_attestation))
+                                .filter(|attestation| attestation.data.slot == slot)
+                                .map(|attestation| attestation.validator_id),
+                        );
+                    }
+                    current = block.block.parent_root;
+                }
+                if !reached_slot {
+                    return Ok(None);
+                }
+                Ok(lean_db
+                    .state_provider()
+                    .get(head)?
+                    .map(|head_state| (proposed, attesters, head_state)))
+            })
+            .await?
+        else {
+            return Ok(());
+        };
+
+        // The validators due at `slot` are the ones the head state had, with the same proposer
+        // the state transition expects.
+        let proposer_index = head_state.proposer_index_at(slot)?;
+        let performances = (0..head_state.validators.len() as u64)
+            .filter(|validator_index| head_state.is_active_validator(*validator_index, slot))
+            .map(|validator_index| {
+                let is_proposer = validator_index == proposer_index;
+                (
+                    validator_index,
+                    SlotPerformance {
+                        slot,
+                        is_proposer,
+                        proposed: is_proposer && proposed,
+                        attestation_included: attesters.contains(&validator_index),
+                    },
+                )
+            })
+            .collect::<Vec<_>>();
+        let recorded_performances = performances.clone();
+        lean_db
+            .run_blocking(move |lean_db| {
+                lean_db
+                    .validator_performance_provider()
+                    .record_slot(recorded_performances, PERFORMANCE_WINDOW_SLOTS)
+            })
+            .await?;
+
+        if self.export_metrics {
+            for (validator_index, performance) in performances {
+                let validator = validator_index.to_string();
+                if performance.is_proposer {
+                    let outcome = if performance.proposed {
+                        "made"
+                    } else {
+                        "missed"
+                    };
+                    inc_int_counter_vec(&VALIDATOR_PROPOSALS_TOTAL, &[&validator, outcome]);
+                }
+                let outcome = if performance.attestation_included {
+                    "included"
+                } else {
+                    "missed"
+                };
+                inc_int_counter_vec(&VALIDATOR_ATTESTATIONS_TOTAL, &[&validator, outcome]);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ream_consensus_lean::attestation::Attestation;
+    use ream_storage::{
+        db::ReamDB,
+        tables::{field::REDBField, table::REDBTable},
+    };
+    use ream_test_utils::{chain::ChainBuilder, genesis};
+
+    use super::ValidatorPerformanceTracker;
+
+    #[tokio::test]
+    async fn test_track_slot() {
+        let (genesis_block, genesis_state) = genesis(4);
+        let mut chain = ChainBuilder::new(genesis_block, genesis_state);
+        let slot_1 = chain.build_block(chain.genesis_root(), 1, vec![]).unwrap();
+        let attestation = |validator_id| Attestation {
+            validator_id,
+            data: chain.attestation_data(slot_1, 1).unwrap(),
+        };
+        let (early_attestations, late_attestation) =
+            (vec![attestation(0), attestation(2)], attestation(3));
+        let slot_2 = chain.build_block(slot_1, 2, early_attestations).unwrap();
+        let slot_3 = chain.build_block(slot_2, 3, vec![]).unwrap();
+        // Included too late to count.
+        let head = chain
+            .build_block(slot_3, 4, vec![late_attestation])
+            .unwrap();
+
+        let lean_db = ReamDB::in_memory().unwrap().init_lean_db().unwrap();
+        chain.store_blocks(&lean_db).unwrap();
+        lean_db.head_provider().insert(head).unwrap();
+        lean_db
+            .state_provider()
+            .insert(head, chain.state(head).unwrap().clone())
+            .unwrap();
+
+        let tracker = ValidatorPerformanceTracker::default();
+        tracker.track_slot(&lean_db, 1).await.unwrap();
+        let performance_provider = lean_db.validator_performance_provider();
+        let performance = |validator_index| {
+            performance_provider
+                .get((validator_index, 1))
+                .unwrap()
+                .unwrap()
+        };
+        assert!(performance(1).is_proposer && performance(1).proposed);
+        assert!(!performance(0).is_proposer);
+        assert!(performance(0).attestation_included);
+        assert!(performance(1).attestation_included);
+        assert!(performance(2).attestation_included);
+        assert!(!performance(3).attestation_included);
+
+        // Slots the head hasn't reached are left untracked.
+        tracker.track_slot(&lean_db, 5).await.unwrap();
+        assert!(performance_provider.get((0, 5)).unwrap().is_none());
+    }
+}
@@ -0,0 +1,109 @@
+//! The blocks imported in the last few slots, kept in memory by root. Peers which fall a few slots
+//! behind ask for exactly these blocks with BlocksByRoot, so most requests are answered without
+//! reading the database. Lookups are counted as hits or misses in
+//! `lean_recent_blocks_cache_lookups_total`.
+//!
+//! Gossip messages requested with IWANT are served by gossipsub from its own message cache.
+
+use std::{collections::HashMap, sync::Arc};
+
+use alloy_primitives::B256;
+use ream_consensus_lean::block::SignedBlockWithAttestation;
+use ream_metrics::{RECENT_BLOCKS_CACHE_LOOKUPS_TOTAL, inc_int_counter_vec};
+
+/// Blocks are kept for this many slots, unless overridden with
+/// [LeanChainService::with_recent_blocks_slots].
+///
+/// [LeanChainService::with_recent_blocks_slots]: crate::service::LeanChainService::with_recent_blocks_slots
+pub const DEFAULT_RECENT_BLOCKS_SLOTS: u64 = 8;
+
+#[derive(Debug)]
+pub struct RecentBlocks {
+    slots: u64,
+    blocks: HashMap<B256, Arc<SignedBlockWithAttestation>>,
+}
+
+impl Default for RecentBlocks {
+    fn default() -> Self {
+        Self::new(DEFAULT_RECENT_BLOCKS_SLOTS)
+    }
+}
+
+impl RecentBlocks {
+    /// Keeps the blocks of the last `slots` slots. With `slots` set to 0 nothing is kept.
+    pub fn new(slots: u64) -> Self {
+        Self {
+            slots,
+            blocks: HashMap::new(),
+        }
+    }
+
+    pub fn insert(&mut self, block_root: B256, block: Arc<SignedBlockWithAttestation>) {
+        if self.slots > 0 {
+            self.blocks.insert(block_root, block);
+        }
+    }
+
+    /// Returns the cached block of `block_root`, counting the lookup as a hit or a miss.
+    pub fn get(&self, block_root: &B256) -> Option<Arc<SignedBlockWithAttestation>> {
+        let block = self.blocks.get(block_root).cloned();
+        inc_int_counter_vec(
+            &RECENT_BLOCKS_CACHE_LOOKUPS_TOTAL,
+            &[if block.is_some() { "hit" } else { "miss" }],
+        );
+        block
+    }
+
+    /// Drops the blocks older than the last `slots` slots before `current_slot`.
+    pub fn prune(&mut self, current_slot: u64) {
+        let min_slot = (current_slot + 1).saturating_sub(self.slots);
+        self.blocks
+            .retain(|_, block| block.message.block.slot >= min_slot);
+    }
+
+    pub fn len(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use alloy_primitives::B256;
+    use ream_test_utils::sample_block;
+
+    use super::RecentBlocks;
+
+    #[test]
+    fn test_prune_recent_blocks() {
+        let mut recent_blocks = RecentBlocks::new(2);
+        for slot in 1..=3 {
+            recent_blocks.insert(
+                B256::repeat_byte(slot as u8),
+                Arc::new(sample_block(slot, 0, B256::ZERO)),
+            );
+        }
+
+        recent_blocks.prune(3);
+        assert_eq!(recent_blocks.len(), 2);
+        assert!(recent_blocks.get(&B256::repeat_byte(1)).is_none());
+        assert_eq!(
+            recent_blocks
+                .get(&B256::repeat_byte(3))
+                .map(|block| block.message.block.slot),
+            Some(3)
+        );
+
+        let mut disabled = RecentBlocks::new(0);
+        disabled.insert(
+            B256::repeat_byte(1),
+            Arc::new(sample_block(1, 0, B256::ZERO)),
+        );
+        assert!(disabled.is_empty());
+    }
+}
@@ -1,25 +1,61 @@
-use std::sync::Arc;
+use std::{collections::BTreeMap, mem, sync::Arc, time::Duration};
 
-use anyhow::anyhow;
+use alloy_primitives::B256;
+use anyhow::{anyhow, ensure};
+use libp2p::gossipsub::MessageAcceptance;
+use libp2p_identity::PeerId;
 use ream_consensus_lean::{
     attestation::{AttestationData, SignedAttestation},
+    blob_sidecar::{Blob, BlobIdentifier, BlobSidecar},
     block::{BlockWithSignatures, SignedBlockWithAttestation},
 };
-use ream_fork_choice_lean::store::LeanStoreWriter;
-use ream_network_spec::networks::lean_network_spec;
+use ream_executor::ShutdownSignal;
+use ream_fork_choice_lean::{
+    rejection::{BLOCK, ForkChoiceError, RejectReason, record_rejected},
+    store::{AttestationSource, FutureBlockError, LeanStoreWriter},
+};
+use ream_metrics::{
+    ATTESTATION_PARTICIPATION_RATE, FINALITY_STALLED, HEAD_FINALIZED_DISTANCE,
+    HEAD_JUSTIFIED_DISTANCE, SLOTS_SINCE_FINALIZATION, set_gauge_vec, set_int_gauge_vec,
+    slot_report::finish_slot,
+};
+use ream_network_spec::networks::{IntervalDuty, lean_network_spec};
 use ream_network_state_lean::NetworkState;
 use ream_storage::tables::{field::REDBField, table::REDBTable};
-use tokio::sync::{mpsc, oneshot};
+use tokio::{
+    sync::{mpsc, oneshot},
+    task::JoinHandle,
+    time::timeout,
+};
 use tracing::{Level, debug, enabled, error, info, warn};
 use tree_hash::TreeHash;
 
 use crate::{
-    clock::create_lean_clock_interval, messages::LeanChainServiceMessage,
-    p2p_request::LeanP2PRequest, slot::get_current_slot,
+    block_source::{BlockRequest, ExternalBlockSource, validate_block_response},
+    clock::create_lean_clock_interval,
+    finality::FinalityStall,
+    gossip_validation::{Validation, block_acceptance, message_acceptance, send_acceptance},
+    messages::LeanChainServiceMessage,
+    orphan_blocks::OrphanBlocks,
+    p2p_request::LeanP2PRequest,
+    pending_blobs::PendingBlobs,
+    performance::{ATTESTATION_INCLUSION_SLOTS, ValidatorPerformanceTracker},
+    recent_blocks::RecentBlocks,
+    slot::get_current_slot,
 };
 
+/// Finality is reported as stalled once it hasn't advanced for this many slots, unless
+/// overridden with [LeanChainService::with_finality_stall_slots].
+pub const DEFAULT_FINALITY_STALL_SLOTS: u64 = 32;
+
+/// Blocks up to this many slots early are queued until their slot starts instead of rejected.
+const MAX_EARLY_BLOCK_SLOTS: u64 = 1;
+
+/// Early blocks beyond this many are rejected, so peers can't fill the queue.
+const MAX_EARLY_BLOCKS: usize = 64;
+
 /// LeanChainService is responsible for updating the [LeanChain] state. `LeanChain` is updated when:
-/// 1. Every third (t=2/4) and fourth (t=3/4) ticks.
+/// 1. At the safe target and accept attestations intervals of the network spec.
 /// 2. Receiving new blocks or attestations from the network.
 ///
 /// NOTE: This service will be the core service to implement `receive()` function.
@@ -28,6 +64,17 @@ pub struct LeanChainService {
     receiver: mpsc::UnboundedReceiver<LeanChainServiceMessage>,
     outbound_gossip: mpsc::UnboundedSender<LeanP2PRequest>,
     network_state: Arc<NetworkState>,
+    block_source: Option<(Arc<dyn ExternalBlockSource>, Duration)>,
+    finality_stall: FinalityStall,
+    performance_tracker: ValidatorPerformanceTracker,
+    /// Blocks which arrived before their slot started, by slot, with the peer they came from and
+    /// their validation.
+    early_blocks: BTreeMap<u64, Vec<(SignedBlockWithAttestation, Option<PeerId>, Validation)>>,
+    orphan_blocks: OrphanBlocks,
+    pending_blobs: PendingBlobs,
+    recent_blocks: RecentBlocks,
+    /// The task advancing the head state to the next slot, see [Self::spawn_head_state_advance].
+    head_state_advance: Option<JoinHandle<()>>,
 }
 
 impl LeanChainService {
@@ -42,16 +89,57 @@ impl LeanChainService {
             store,
             receiver,
             outbound_gossip,
+            block_source: None,
+            finality_stall: FinalityStall::new(DEFAULT_FINALITY_STALL_SLOTS),
+            performance_tracker: ValidatorPerformanceTracker::default(),
+            early_blocks: BTreeMap::new(),
+            orphan_blocks: OrphanBlocks::default(),
+            pending_blobs: PendingBlobs::default(),
+            recent_blocks: RecentBlocks::default(),
+            head_state_advance: None,
         }
     }
 
-    pub async fn start(mut self) -> anyhow::Result<()> {
+    /// Requests candidate blocks from `block_source` before producing them locally. If the source
+    /// doesn't answer within `timeout`, or answers with an invalid block, the block is produced
+    /// locally instead.
+    pub fn with_block_source(
+        mut self,
+        block_source: Arc<dyn ExternalBlockSource>,
+        timeout: Duration,
+    ) -> Self {
+        self.block_source = Some((block_source, timeout));
+        self
+    }
+
+    /// Reports finality as stalled once it hasn't advanced for `finality_stall_slots` slots.
+    pub fn with_finality_stall_slots(mut self, finality_stall_slots: u64) -> Self {
+        self.finality_stall = FinalityStall::new(finality_stall_slots);
+        self
+    }
+
+    /// Keeps the blocks imported in the last `slots` slots in memory, to answer BlocksByRoot
+    /// requests without reading the database.
+    pub fn with_recent_blocks_slots(mut self, slots: u64) -> Self {
+        self.recent_blocks = RecentBlocks::new(slots);
+        self
+    }
+
+    /// Exports the performance of each validator as metrics labelled by validator index.
+    pub fn with_validator_performance_metrics(mut self, export_metrics: bool) -> Self {
+        self.performance_tracker = ValidatorPerformanceTracker::new(export_metrics);
+        self
+    }
+
+    /// Runs until `shutdown` fires and every queued block and attestation has been imported.
+    pub async fn start(mut self, mut shutdown: ShutdownSignal) -> anyhow::Result<()> {
         info!(
             genesis_time = lean_network_spec().genesis_time,
             "LeanChainService started",
         );
 
         let mut tick_count = 0u64;
+        let mut shutting_down = false;
 
         let mut interval = create_lean_clock_interval()
             .map_err(|err| anyhow!("Failed to create clock interval: {err:?}"))?;
@@ -59,46 +147,91 @@ impl LeanChainService {
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                    self.store.write().await.tick_interval(tick_count % 4 == 1).await.expect("Failed to tick interval");
-                    match tick_count % 4 {
-                        0 => {
-                            // First tick (t=0/4): Log current head state, including its justification/finalization status.
-                            let (head, state_provider) = {
-                                let fork_choice = self.store.read().await;
-                                let store = fork_choice.store.lock().await;
-                                (store.head_provider().get()?, store.state_provider())
-                            };
-                            let head_state = state_provider
-                                .get(head)?.ok_or_else(|| anyhow!("Post state not found for head: {head}"))?;
+                    let spec = lean_network_spec();
+                    let slot_interval = tick_count % spec.intervals_per_slot;
+                    self.store.write().await.tick_interval(spec.interval_duty(slot_interval) == Some(IntervalDuty::Attest)).await.expect("Failed to tick interval");
+                    if let Err(err) = self.process_early_blocks().await {
+                        warn!("Failed to process early blocks: {err:?}");
+                    }
+                    if slot_interval == spec.intervals_per_slot - 1 {
+                        // End of the slot: Advance the head state to the next slot in the
+                        // background, so importing and producing its block skip the slot
+                        // processing.
+                        self.spawn_head_state_advance(get_current_slot() + 1).await;
+                    }
+                    if slot_interval == 0 {
+                        self.recent_blocks.prune(get_current_slot());
 
+                        // Log the performance report of the slot that just ended.
+                        if tick_count > 0 {
+                            let report = finish_slot(get_current_slot().saturating_sub(1));
                             info!(
-                                "\n\
-                            ============================================================\n\
-                            REAM's CHAIN STATUS: Next Slot: {current_slot} | Head Slot: {head_slot}\n\
-                            ------------------------------------------------------------\n\
-                            Connected Peers:   {connected_peer_count}\n\
-                            ------------------------------------------------------------\n\
-                            Head Block Root:   {head_block_root}\n\
-                            Parent Block Root: {parent_block_root}\n\
-                            State Root:        {state_root}\n\
-                            ------------------------------------------------------------\n\
-                            Latest Justified:  Slot {justified_slot} | Root: {justified_root}\n\
-                            Latest Finalized:  Slot {finalized_slot} | Root: {finalized_root}\n\
-                            ============================================================",
-                                current_slot     = get_current_slot(),
-                                head_slot        = head_state.slot,
-                                connected_peer_count = self.network_state.connected_peers(),
-                                head_block_root   = head.to_string(),
-                                parent_block_root = head_state.latest_block_header.parent_root,
-                                state_root        = head_state.tree_hash_root(),
-                                justified_slot = head_state.latest_justified.slot,
-                                justified_root = head_state.latest_justified.root,
-                                finalized_slot = head_state.latest_finalized.slot,
-                                finalized_root = head_state.latest_finalized.root,
+                                slot = report.slot,
+                                blocks_imported = report.blocks_imported,
+                                block_import_time_micros = report.block_import_time_micros,
+                                attestations_processed = report.attestations_processed,
+                                signature_verifications = report.signature_verifications,
+                                head_changes = report.head_changes,
+                                db_writes = report.db_writes,
+                                "Slot performance report"
                             );
                         }
-                        2 => {
-                            // Third tick (t=2/4): Compute the safe target.
+
+                        // Log current head state, including its justification/finalization status.
+                        let (head, state_provider) = {
+                            let fork_choice = self.store.read().await;
+                            let store = &fork_choice.store;
+                            (store.head_provider().get()?, store.state_provider())
+                        };
+                        let head_state = state_provider
+                            .get(head)?.ok_or_else(|| anyhow!("Post state not found for head: {head}"))?;
+
+                        info!(
+                            "\n\
+                        ============================================================\n\
+                        REAM's CHAIN STATUS: Next Slot: {current_slot} | Head Slot: {head_slot}\n\
+                        ------------------------------------------------------------\n\
+                        Connected Peers:   {connected_peer_count}\n\
+                        ------------------------------------------------------------\n\
+                        Head Block Root:   {head_block_root}\n\
+                        Parent Block Root: {parent_block_root}\n\
+                        State Root:        {state_root}\n\
+                        ------------------------------------------------------------\n\
+                        Latest Justified:  Slot {justified_slot} | Root: {justified_root}\n\
+                        Latest Finalized:  Slot {finalized_slot} | Root: {finalized_root}\n\
+                        ============================================================",
+                            current_slot     = get_current_slot(),
+                            head_slot        = head_state.slot,
+                            connected_peer_count = self.network_state.connected_peers(),
+                            head_block_root   = head.to_string(),
+                            parent_block_root = head_state.latest_block_header.parent_root,
+                            state_root        = head_state.tree_hash_root(),
+                            justified_slot = head_state.latest_justified.slot,
+                            justified_root = head_state.latest_justified.root,
+                            finalized_slot = head_state.latest_finalized.slot,
+                            finalized_root = head_state.latest_finalized.root,
+                        );
+                        self.record_finality_progress(
+                            head_state.slot,
+                            head_state.latest_justified.slot,
+                            head_state.latest_finalized.slot,
+                        ).await;
+                        self.orphan_blocks.prune(head_state.latest_finalized.slot);
+                        self.prune_pending_blobs(head_state.latest_finalized.slot).await;
+
+                        // The attestations of this slot have had their chance to be included.
+                        if let Some(slot) = get_current_slot().checked_sub(ATTESTATION_INCLUSION_SLOTS + 1)
+                            && slot > 0
+                        {
+                            let lean_db = self.store.read().await.store.clone();
+                            if let Err(err) = self.performance_tracker.track_slot(&lean_db, slot).await {
+                                warn!("Failed to track validator performance of slot {slot}: {err:?}");
+                            }
+                        }
+                    }
+                    match spec.interval_duty(slot_interval) {
+                        Some(IntervalDuty::SafeTarget) => {
+                            // Compute the safe target.
                             info!(
                                 slot = get_current_slot(),
                                 tick = tick_count,
@@ -106,8 +239,8 @@ impl LeanChainService {
                             );
                             self.store.write().await.update_safe_target().await.expect("Failed to update safe target");
                         }
-                        3 => {
-                            // Fourth tick (t=3/4): Accept new attestations.
+                        Some(IntervalDuty::AcceptAttestations) => {
+                            // Accept new attestations.
                             info!(
                                 slot = get_current_slot(),
                                 tick = tick_count,
@@ -116,12 +249,21 @@ impl LeanChainService {
                             self.store.write().await.accept_new_attestations().await.expect("Failed to accept new attestations");
                         }
                         _ => {
-                            // Other ticks (t=0, t=1/4): Do nothing.
+                            // Proposing and attesting are done by the ValidatorService.
                         }
                     }
                     tick_count += 1;
                 }
-                Some(message) = self.receiver.recv() => {
+                _ = shutdown.wait(), if !shutting_down => {
+                    // Stop accepting messages, the ones already queued are still processed below.
+                    info!("LeanChainService shutting down, draining queued messages");
+                    self.receiver.close();
+                    shutting_down = true;
+                }
+                message = self.receiver.recv() => {
+                    let Some(message) = message else {
+                        return self.finish_shutdown().await;
+                    };
                     match message {
                         LeanChainServiceMessage::ProduceBlock { slot, sender } => {
                             if let Err(err) = self.handle_produce_block(slot, sender).await {
@@ -133,7 +275,24 @@ impl LeanChainService {
                                 error!("Failed to handle build attestation data message: {err:?}");
                             }
                         }
-                        LeanChainServiceMessage::ProcessBlock { signed_block_with_attestation, need_gossip } => {
+                        LeanChainServiceMessage::GetProposerIndex { slot, sender } => {
+                            let proposer_index = self.store.read().await.proposer_index(slot).await;
+                            if sender.send(proposer_index).is_err() {
+                                warn!(slot, "Failed to send proposer index response");
+                            }
+                        }
+                        LeanChainServiceMessage::GetValidatorPublicKey { validator_index, slot, sender } => {
+                            let public_key = self
+                                .store
+                                .read()
+                                .await
+                                .validator_public_key(validator_index, slot)
+                                .await;
+                            if sender.send(public_key).is_err() {
+                                warn!(slot, "Failed to send validator public key response");
+                            }
+                        }
+                        LeanChainServiceMessage::ProcessBlock { signed_block_with_attestation, need_gossip, peer_id, validation } => {
                             if enabled!(Level::DEBUG) {
                                 debug!(
                                     slot = signed_block_with_attestation.message.block.slot,
@@ -153,7 +312,11 @@ impl LeanChainService {
                                 );
                             }
 
-                            if let Err(err) = self.handle_process_block(&signed_block_with_attestation).await {
+                            if need_gossip && let Err(err) = self.announce_blobless_block(signed_block_with_attestation.message.block.tree_hash_root()).await {
+                                warn!("Failed to announce that the block has no blobs: {err:?}");
+                            }
+
+                            if let Err(err) = self.handle_process_block(&signed_block_with_attestation, peer_id, validation).await {
                                 warn!("Failed to handle process block message: {err:?}");
                             }
 
@@ -161,7 +324,7 @@ impl LeanChainService {
                                 warn!("Failed to send item to outbound gossip channel: {err:?}");
                             }
                         }
-                        LeanChainServiceMessage::ProcessAttestation { signed_attestation, need_gossip } => {
+                        LeanChainServiceMessage::ProcessAttestation { signed_attestation, need_gossip, from_peer, validation } => {
                             if enabled!(Level::DEBUG) {
                                 debug!(
                                     slot = signed_attestation.message.slot(),
@@ -181,53 +344,184 @@ impl LeanChainService {
                                 );
                             }
 
-                            if let Err(err) = self.handle_process_attestation(*signed_attestation.clone()).await {
-                                warn!("Failed to handle process block message: {err:?}");
-                            }
+                            let source = if from_peer { AttestationSource::Gossip } else { AttestationSource::Api };
+                            let acceptance = match self.handle_process_attestation(*signed_attestation.clone(), source).await {
+                                Ok(()) => MessageAcceptance::Accept,
+                                Err(err) => {
+                                    warn!("Failed to handle process block message: {err:?}");
+                                    message_acceptance(&err)
+                                }
+                            };
+                            send_acceptance(validation, acceptance);
 
                             if need_gossip && let Err(err) = self.outbound_gossip.send(LeanP2PRequest::GossipAttestation(signed_attestation)) {
                                 warn!("Failed to send item to outbound gossip channel: {err:?}");
                             }
                         }
-                        LeanChainServiceMessage::CheckIfCanonicalCheckpoint { peer_id, checkpoint, sender } => {
-                            let slot_index_provider = self.store.read().await.store.lock().await.slot_index_provider();
-                            let is_canonical = match slot_index_provider.get(checkpoint.slot)  {
-                                Ok(Some(block_root)) => block_root == checkpoint.root,
-                                Ok(None) => true,
+                        LeanChainServiceMessage::ProcessBlobSidecar { blob_sidecar, need_gossip, validation } => {
+                            debug!(
+                                block_root = ?blob_sidecar.block_root,
+                                index = blob_sidecar.index,
+                                blob_count = blob_sidecar.blob_count,
+                                "Processing blob sidecar",
+                            );
+
+                            // Failing to store a sidecar doesn't make it invalid, so it is never rejected.
+                            let acceptance = match self.handle_process_blob_sidecar(*blob_sidecar.clone()).await {
+                                Ok(()) => MessageAcceptance::Accept,
                                 Err(err) => {
-                                    warn!("Failed to get slot index for checkpoint: {err:?}");
-                                    false
+                                    warn!("Failed to handle process blob sidecar message: {err:?}");
+                                    MessageAcceptance::Ignore
                                 }
                             };
+                            send_acceptance(validation, acceptance);
 
-                            // Special case: Genesis checkpoint is always canonical.
-                            let is_canonical = if checkpoint.slot < 5 {
-                                true
-                            } else {
-                                is_canonical
+                            if need_gossip && let Err(err) = self.outbound_gossip.send(LeanP2PRequest::GossipBlobSidecar(blob_sidecar)) {
+                                warn!("Failed to send item to outbound gossip channel: {err:?}");
+                            }
+                        }
+                        LeanChainServiceMessage::CheckIfCanonicalCheckpoint { peer_id, checkpoint, sender } => {
+                            let is_canonical = match self.store.read().await.is_compatible_finalized_checkpoint(checkpoint).await {
+                                Ok(is_canonical) => is_canonical,
+                                Err(err) => {
+                                    warn!("Failed to check finalized checkpoint of peer: {err:?}");
+                                    false
+                                }
                             };
 
                             if let Err(err) = sender.send((peer_id, is_canonical)) {
                                 warn!("Failed to send canonical checkpoint response: {err:?}");
                             }
                         }
+                        LeanChainServiceMessage::GetBlocksByRoot { roots, sender } => {
+                            if let Err(err) = sender.send(self.get_blocks_by_root(roots).await) {
+                                warn!("Failed to send blocks by root response: {err:?}");
+                            }
+                        }
+                        LeanChainServiceMessage::GetBlobSidecarsByRoot { identifiers, sender } => {
+                            if let Err(err) = sender.send(self.get_blob_sidecars_by_root(identifiers).await) {
+                                warn!("Failed to send blob sidecars by root response: {err:?}");
+                            }
+                        }
+                        LeanChainServiceMessage::BlockLookupFailed { root } => {
+                            let dropped = self.orphan_blocks.remove_descendants(root);
+                            if dropped > 0 {
+                                warn!(?root, dropped, "Dropping orphan blocks whose parent couldn't be fetched");
+                            }
+                        }
                     }
                 }
             }
         }
     }
 
+    /// Moves the attestations received since the last interval into the known attestations, so
+    /// the next start doesn't lose them, and stops the service.
+    async fn finish_shutdown(&mut self) -> anyhow::Result<()> {
+        self.store
+            .write()
+            .await
+            .accept_new_attestations()
+            .await
+            .map_err(|err| anyhow!("Failed to flush new attestations on shutdown: {err:?}"))?;
+        if self
+            .outbound_gossip
+            .send(LeanP2PRequest::ChainServiceStopped)
+            .is_err()
+        {
+            warn!("Network service stopped before the chain service");
+        }
+        info!("LeanChainService stopped");
+        Ok(())
+    }
+
+    /// Advances the head state to `slot` on a task of its own, so the service keeps handling
+    /// messages meanwhile. Only one advance runs at a time, one still running from the slot before
+    /// is left to finish instead.
+    async fn spawn_head_state_advance(&mut self, slot: u64) {
+        if self
+            .head_state_advance
+            .as_ref()
+            .is_some_and(|task| !task.is_finished())
+        {
+            debug!(
+                slot,
+                "Skipping head state advance, the previous one is still running"
+            );
+            return;
+        }
+
+        let store = self.store.read().await.clone();
+        self.head_state_advance = Some(tokio::spawn(async move {
+            if let Err(err) = store.advance_head_state(slot).await {
+                warn!(slot, "Failed to advance the head state: {err:?}");
+            }
+        }));
+    }
+
+    /// Records how far justification and finalization lag behind the head and the current slot,
+    /// and warns once finality stops advancing for the configured number of slots.
+    async fn record_finality_progress(
+        &mut self,
+        head_slot: u64,
+        justified_slot: u64,
+        finalized_slot: u64,
+    ) {
+        set_int_gauge_vec(
+            &HEAD_JUSTIFIED_DISTANCE,
+            head_slot.saturating_sub(justified_slot) as i64,
+            &[],
+        );
+        set_int_gauge_vec(
+            &HEAD_FINALIZED_DISTANCE,
+            head_slot.saturating_sub(finalized_slot) as i64,
+            &[],
+        );
+        match self.store.read().await.canonical_participation().await {
+            Ok(participation) => set_gauge_vec(&ATTESTATION_PARTICIPATION_RATE, participation, &[]),
+            Err(err) => warn!("Failed to compute attestation participation: {err:?}"),
+        }
+
+        let current_slot = get_current_slot();
+        let slots_since_finalization = current_slot.saturating_sub(finalized_slot);
+        set_int_gauge_vec(
+            &SLOTS_SINCE_FINALIZATION,
+            slots_since_finalization as i64,
+            &[],
+        );
+
+        match self.finality_stall.update(slots_since_finalization) {
+            Some(true) => warn!(
+                current_slot,
+                finalized_slot, "Finality has not advanced for {slots_since_finalization} slots"
+            ),
+            Some(false) => info!(current_slot, finalized_slot, "Finality is advancing again"),
+            None => {}
+        }
+        set_int_gauge_vec(
+            &FINALITY_STALLED,
+            self.finality_stall.is_stalled() as i64,
+            &[],
+        );
+    }
+
     async fn handle_produce_block(
         &mut self,
         slot: u64,
         response: oneshot::Sender<BlockWithSignatures>,
     ) -> anyhow::Result<()> {
-        let block_with_signatures = self
-            .store
-            .write()
-            .await
-            .produce_block_with_signatures(slot, slot % lean_network_spec().num_validators)
-            .await?;
+        let proposer_index = self.store.read().await.proposer_index(slot).await?;
+
+        let block_with_signatures = match self.request_external_block(slot, proposer_index).await {
+            Some(block_with_signatures) => block_with_signatures,
+            None => {
+                self.store
+                    .write()
+                    .await
+                    .produce_block_with_signatures(slot, proposer_index)
+                    .await?
+            }
+        };
 
         // Send the produced block back to the requester
         response
@@ -237,6 +531,106 @@ impl LeanChainService {
         Ok(())
     }
 
+    /// Asks the configured external block source for a candidate block. Returns `None` if no
+    /// source is configured, or if the source failed to produce a valid block in time.
+    async fn request_external_block(
+        &self,
+        slot: u64,
+        proposer_index: u64,
+    ) -> Option<BlockWithSignatures> {
+        let (block_source, request_timeout) = self.block_source.as_ref()?;
+
+        let parent_root = match self.store.write().await.get_proposal_head(slot).await {
+            Ok(parent_root) => parent_root,
+            Err(err) => {
+                warn!("Failed to get proposal head for external block request: {err:?}");
+                return None;
+            }
+        };
+        let request = BlockRequest {
+            slot,
+            proposer_index,
+            parent_root,
+        };
+
+        let block_with_signatures = match timeout(
+            *request_timeout,
+            block_source.get_block(&request),
+        )
+        .await
+        {
+            Ok(Ok(block_with_signatures)) => block_with_signatures,
+            Ok(Err(err)) => {
+                warn!(
+                    slot,
+                    source = block_source.name(),
+                    "External block source failed, producing block locally: {err:?}"
+                );
+                return None;
+            }
+            Err(_) => {
+                warn!(
+                    slot,
+                    source = block_source.name(),
+                    "External block source timed out after {request_timeout:?}, producing block locally"
+                );
+                return None;
+            }
+        };
+
+        if let Err(err) = self
+            .verify_external_block(&request, &block_with_signatures)
+            .await
+        {
+            warn!(
+                slot,
+                source = block_source.name(),
+                "Rejected external block, producing block locally: {err:?}"
+            );
+            return None;
+        }
+
+        info!(
+            slot,
+            block_root = ?block_with_signatures.block.tree_hash_root(),
+            source = block_source.name(),
+            "Using block from external block source"
+        );
+
+        Some(block_with_signatures)
+    }
+
+    /// Checks that an external block matches the request, that its attestations and validator
+    /// operations are signed by their validators, and that its state root is the one we get by
+    /// applying it on top of the parent state.
+    async fn verify_external_block(
+        &self,
+        request: &BlockRequest,
+        block_with_signatures: &BlockWithSignatures,
+    ) -> anyhow::Result<()> {
+        validate_block_response(request, block_with_signatures)?;
+
+        let state_provider = self.store.read().await.store.state_provider();
+        let mut state = state_provider
+            .get(request.parent_root)?
+            .ok_or_else(|| anyhow!("State not found for parent root: {}", request.parent_root))?;
+        ensure!(
+            block_with_signatures.verify_signatures(&state)?,
+            "External block has invalid signatures"
+        );
+        state.process_slots(request.slot)?;
+        state.process_block(&block_with_signatures.block)?;
+
+        ensure!(
+            state.tree_hash_root() == block_with_signatures.block.state_root,
+            "External block state root mismatch, expected {}, got {}",
+            state.tree_hash_root(),
+            block_with_signatures.block.state_root
+        );
+
+        Ok(())
+    }
+
     async fn handle_build_attestation_data(
         &mut self,
         slot: u64,
@@ -257,27 +651,353 @@ impl LeanChainService {
         Ok(())
     }
 
+    /// Imports a block, received from `peer_id` if it came from a peer, and then the orphan blocks
+    /// which were waiting for it. Returns the root of the block, or `None` if it was queued.
+    ///
+    /// The [MessageAcceptance] of each block is sent on its `validation` once it is imported or
+    /// rejected. Queued blocks keep theirs until then, so gossipsub doesn't forward them yet.
     async fn handle_process_block(
         &mut self,
         signed_block_with_attestation: &SignedBlockWithAttestation,
-    ) -> anyhow::Result<()> {
-        self.store
+        peer_id: Option<PeerId>,
+        mut validation: Validation,
+    ) -> Result<Option<B256>, ForkChoiceError> {
+        let result = self
+            .import_block(signed_block_with_attestation, peer_id, &mut validation)
+            .await;
+        send_acceptance(validation, block_acceptance(&result));
+        let Some(block_root) = result? else {
+            return Ok(None);
+        };
+
+        let mut imported_roots = vec![block_root];
+        while let Some(parent_root) = imported_roots.pop() {
+            for (orphan_block, mut validation) in self.orphan_blocks.take_children(&parent_root) {
+                let result = self
+                    .import_block(&orphan_block, None, &mut validation)
+                    .await;
+                send_acceptance(validation, block_acceptance(&result));
+                match result {
+                    Ok(Some(block_root)) => imported_roots.push(block_root),
+                    Ok(None) => {}
+                    Err(err) => warn!(
+                        slot = orphan_block.message.block.slot,
+                        "Failed to process orphan block: {err:?}"
+                    ),
+                }
+            }
+        }
+        Ok(Some(block_root))
+    }
+
+    /// Imports a block, returning its root, or `None` if it was queued until its slot starts, its
+    /// parent is fetched or its blob sidecars arrive. Queued blocks take their `validation`.
+    async fn import_block(
+        &mut self,
+        signed_block_with_attestation: &SignedBlockWithAttestation,
+        peer_id: Option<PeerId>,
+        validation: &mut Validation,
+    ) -> Result<Option<B256>, ForkChoiceError> {
+        let block_root = signed_block_with_attestation.message.block.tree_hash_root();
+        let mut result = self
+            .store
             .write()
             .await
             .on_block(signed_block_with_attestation, true)
-            .await?;
+            .await;
+
+        if let Err(err) = &result
+            && err.reject_reason() == RejectReason::DataUnavailable
+        {
+            match self
+                .queue_unavailable_block(block_root, signed_block_with_attestation, validation)
+                .await
+            {
+                // The sidecars which arrived before the block complete it.
+                Some(true) => {
+                    result = self
+                        .store
+                        .write()
+                        .await
+                        .on_block(signed_block_with_attestation, true)
+                        .await;
+                }
+                Some(false) => return Ok(None),
+                None => {}
+            }
+        }
+
+        if let Err(ForkChoiceError::FutureBlock(FutureBlockError { slot, latest_slot })) = result
+            && slot <= latest_slot + MAX_EARLY_BLOCK_SLOTS
+            && self.early_blocks.values().map(Vec::len).sum::<usize>() < MAX_EARLY_BLOCKS
+        {
+            debug!(
+                slot,
+                latest_slot, "Queueing early block until its slot starts"
+            );
+            self.early_blocks.entry(slot).or_default().push((
+                signed_block_with_attestation.clone(),
+                peer_id,
+                validation.take(),
+            ));
+            return Ok(None);
+        }
+
+        if let Err(err) = &result
+            && err.reject_reason() == RejectReason::UnknownParent
+            && let Some(fetch_parent) = self.orphan_blocks.insert(
+                signed_block_with_attestation.clone(),
+                peer_id,
+                validation,
+            )
+        {
+            let parent_root = signed_block_with_attestation.message.block.parent_root;
+            debug!(
+                slot = signed_block_with_attestation.message.block.slot,
+                ?parent_root,
+                "Queueing block until its parent is fetched"
+            );
+            // A parent which other orphans wait for is already being fetched.
+            if fetch_parent
+                && let Err(err) = self
+                    .outbound_gossip
+                    .send(LeanP2PRequest::RequestBlock(parent_root))
+            {
+                warn!("Failed to request the parent block: {err:?}");
+            }
+            return Ok(None);
+        }
+
+        if let Err(ForkChoiceError::FutureBlock(_)) = &result {
+            record_rejected(BLOCK, RejectReason::FutureSlot);
+        }
+        result?;
+        self.recent_blocks
+            .insert(block_root, Arc::new(signed_block_with_attestation.clone()));
+        Ok(Some(block_root))
+    }
+
+    /// Queues a block rejected for incomplete blob sidecars until they arrive, and stores the ones
+    /// which arrived before it. Returns whether those complete the block, or `None` if too many
+    /// blocks are waiting already. The block keeps its `validation` only while it waits.
+    async fn queue_unavailable_block(
+        &mut self,
+        block_root: B256,
+        signed_block_with_attestation: &SignedBlockWithAttestation,
+        validation: &mut Validation,
+    ) -> Option<bool> {
+        let early_sidecars = self.pending_blobs.insert_block(
+            block_root,
+            signed_block_with_attestation.clone(),
+            validation,
+        )?;
+        debug!(
+            slot = signed_block_with_attestation.message.block.slot,
+            ?block_root,
+            "Queueing block until its blob sidecars arrive"
+        );
+
+        let store = self.store.read().await;
+        for blob_sidecar in early_sidecars {
+            if let Err(err) = store.on_blob_sidecar(blob_sidecar) {
+                warn!("Failed to store blob sidecar which arrived before its block: {err:?}");
+            }
+        }
+        match store.is_data_available(block_root) {
+            Ok(true) => {
+                if let Some((_, queued_validation)) = self.pending_blobs.remove_block(&block_root) {
+                    *validation = queued_validation;
+                }
+                Some(true)
+            }
+            Ok(false) => Some(false),
+            Err(err) => {
+                warn!("Failed to check data availability of block {block_root}: {err:?}");
+                Some(false)
+            }
+        }
+    }
+
+    /// Stores and gossips the empty sidecar announcing that a block of ours carries no blobs, so it
+    /// is available with data availability on. Blocks whose sidecars are known are left alone.
+    async fn announce_blobless_block(&mut self, block_root: B256) -> anyhow::Result<()> {
+        let store = self.store.read().await;
+        if !store.data_availability
+            || !store
+                .store
+                .blob_sidecar_provider()
+                .get_block_sidecars(block_root)?
+                .is_empty()
+        {
+            return Ok(());
+        }
+
+        let announcement = BlobSidecar {
+            block_root,
+            index: 0,
+            blob_count: 0,
+            blob: Blob::empty(),
+        };
+        store.on_blob_sidecar(announcement.clone())?;
+        self.outbound_gossip
+            .send(LeanP2PRequest::GossipBlobSidecar(Box::new(announcement)))?;
+        Ok(())
+    }
+
+    /// Stores a blob sidecar of a known block or of a block waiting for its sidecars, and imports
+    /// the waiting block once the sidecar completes it. Sidecars of other blocks are held until
+    /// their block arrives.
+    async fn handle_process_blob_sidecar(
+        &mut self,
+        blob_sidecar: BlobSidecar,
+    ) -> anyhow::Result<()> {
+        blob_sidecar.validate()?;
+        let block_root = blob_sidecar.block_root;
+        let is_pending = self.pending_blobs.contains_block(&block_root);
+        {
+            let store = self.store.read().await;
+            if !is_pending && store.store.block_provider().get(block_root)?.is_none() {
+                self.pending_blobs.insert_early_sidecar(blob_sidecar);
+                return Ok(());
+            }
+            store.on_blob_sidecar(blob_sidecar)?;
+            if !is_pending || !store.is_data_available(block_root)? {
+                return Ok(());
+            }
+        }
+
+        let Some((signed_block_with_attestation, validation)) =
+            self.pending_blobs.remove_block(&block_root)
+        else {
+            return Ok(());
+        };
+        if let Err(err) = self
+            .handle_process_block(&signed_block_with_attestation, None, validation)
+            .await
+        {
+            warn!(
+                slot = signed_block_with_attestation.message.block.slot,
+                "Failed to process block whose blob sidecars arrived: {err:?}"
+            );
+            self.store
+                .read()
+                .await
+                .store
+                .blob_sidecar_provider()
+                .remove_block_sidecars(block_root)?;
+        }
+        Ok(())
+    }
+
+    /// Drops the blocks waiting for their blob sidecars at or before `finalized_slot`, with the
+    /// sidecars stored for them.
+    async fn prune_pending_blobs(&mut self, finalized_slot: u64) {
+        let pruned = self.pending_blobs.prune(finalized_slot);
+        if pruned.is_empty() {
+            return;
+        }
+        let blob_sidecar_provider = self.store.read().await.store.blob_sidecar_provider();
+        for block_root in pruned {
+            if let Err(err) = blob_sidecar_provider.remove_block_sidecars(block_root) {
+                warn!("Failed to remove blob sidecars of block {block_root}: {err:?}");
+            }
+        }
+    }
+
+    /// Returns the blocks of `roots` which we have, from the recent blocks if they are there and
+    /// from the database otherwise.
+    async fn get_blocks_by_root(&self, roots: Vec<B256>) -> Vec<Arc<SignedBlockWithAttestation>> {
+        let mut blocks = vec![];
+        let mut missing_roots = vec![];
+        for root in roots {
+            match self.recent_blocks.get(&root) {
+                Some(block) => blocks.push(block),
+                None => missing_roots.push(root),
+            }
+        }
+        if missing_roots.is_empty() {
+            return blocks;
+        }
 
+        let lean_db = self.store.read().await.store.clone();
+        match lean_db
+            .run_blocking(move |lean_db| {
+                let block_provider = lean_db.block_provider();
+                let mut blocks = vec![];
+                for root in missing_roots {
+                    if let Some(block) = block_provider.get(root)? {
+                        blocks.push(Arc::new(block));
+                    }
+                }
+                Ok(blocks)
+            })
+            .await
+        {
+            Ok(stored_blocks) => blocks.extend(stored_blocks),
+            Err(err) => warn!("Failed to get blocks by root: {err:?}"),
+        }
+        blocks
+    }
+
+    /// Returns the stored blob sidecars of `identifiers`.
+    async fn get_blob_sidecars_by_root(
+        &self,
+        identifiers: Vec<BlobIdentifier>,
+    ) -> Vec<Arc<BlobSidecar>> {
+        let lean_db = self.store.read().await.store.clone();
+        match lean_db
+            .run_blocking(move |lean_db| {
+                let blob_sidecar_provider = lean_db.blob_sidecar_provider();
+                let mut blob_sidecars = vec![];
+                for identifier in identifiers {
+                    if let Some(blob_sidecar) = blob_sidecar_provider.get(identifier)? {
+                        blob_sidecars.push(Arc::new(blob_sidecar));
+                    }
+                }
+                Ok(blob_sidecars)
+            })
+            .await
+        {
+            Ok(blob_sidecars) => blob_sidecars,
+            Err(err) => {
+                warn!("Failed to get blob sidecars by root: {err:?}");
+                vec![]
+            }
+        }
+    }
+
+    /// Imports the queued early blocks whose slot has started.
+    async fn process_early_blocks(&mut self) -> anyhow::Result<()> {
+        let time = self.store.read().await.store.time_provider().get()?;
+        let latest_slot = lean_network_spec().latest_gossip_slot(time);
+        let later_blocks = self.early_blocks.split_off(&(latest_slot + 1));
+        for (signed_block_with_attestation, peer_id, validation) in
+            mem::replace(&mut self.early_blocks, later_blocks)
+                .into_values()
+                .flatten()
+        {
+            if let Err(err) = self
+                .handle_process_block(&signed_block_with_attestation, peer_id, validation)
+                .await
+            {
+                warn!(
+                    slot = signed_block_with_attestation.message.block.slot,
+                    "Failed to process early block: {err:?}"
+                );
+            }
+        }
         Ok(())
     }
 
     async fn handle_process_attestation(
         &mut self,
         signed_attestation: SignedAttestation,
-    ) -> anyhow::Result<()> {
+        source: AttestationSource,
+    ) -> Result<(), ForkChoiceError> {
         self.store
             .write()
             .await
-            .on_attestation(signed_attestation, false)
+            .on_attestation(signed_attestation, source)
             .await?;
 
         Ok(())
@@ -0,0 +1,127 @@
+use alloy_primitives::B256;
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use ream_consensus_lean::{
+    attestation::{Attestation, AttestationData},
+    block::{Block, BlockBody},
+    checkpoint::Checkpoint,
+    state::LeanState,
+    utils::generate_default_validators,
+};
+use ream_network_spec::networks::initialize_test_lean_network_spec;
+use ssz_types::VariableList;
+use tree_hash::TreeHash;
+
+const VALIDATOR_COUNT: usize = 4096;
+const SLOT_GAPS: [u64; 3] = [32, 1_024, 16_384];
+/// Empty blocks processed before the block with the attestations, so they have targets.
+const HISTORY_LENGTH: u64 = 8;
+/// How many targets the attestations are spread over. None of them gets 2/3 of the votes, so
+/// every vote stays tracked for justification.
+const TARGET_COUNT: u64 = 4;
+
+fn genesis_state() -> LeanState {
+    initialize_test_lean_network_spec();
+    LeanState::generate_genesis(0, Some(generate_default_validators(VALIDATOR_COUNT)))
+}
+
+/// The block of the current slot of `state` on top of its latest block header.
+fn block(state: &LeanState, attestations: Vec<Attestation>) -> Block {
+    Block {
+        slot: state.slot,
+        proposer_index: state.proposer_index().expect("Failed to get proposer"),
+        parent_root: state.latest_block_header.tree_hash_root(),
+        state_root: B256::ZERO,
+        body: BlockBody::new(VariableList::new(attestations).expect("Too many attestations")),
+    }
+}
+
+/// A state [HISTORY_LENGTH] empty blocks after genesis, advanced to the next slot, and a block
+/// for that slot with an attestation of every validator.
+fn state_and_full_block() -> (LeanState, Block) {
+    let mut state = genesis_state();
+    for slot in 1..=HISTORY_LENGTH {
+        state.process_slots(slot).expect("Failed to process slots");
+        let block = block(&state, vec![]);
+        state
+            .process_block(&block)
+            .expect("Failed to process block");
+    }
+    state
+        .process_slots(HISTORY_LENGTH + 1)
+        .expect("Failed to process slots");
+
+    let checkpoint = |slot: u64| Checkpoint {
+        root: state.historical_block_hashes[slot as usize],
+        slot,
+    };
+    let attestations = (0..VALIDATOR_COUNT as u64)
+        .map(|validator_id| {
+            let target = checkpoint(1 + validator_id % TARGET_COUNT);
+            Attestation {
+                validator_id,
+                data: AttestationData {
+                    slot: target.slot,
+                    head: target,
+                    target,
+                    source: checkpoint(0),
+                },
+            }
+        })
+        .collect();
+    let block = block(&state, attestations);
+    (state, block)
+}
+
+fn bench_process_slots(c: &mut Criterion) {
+    let state = genesis_state();
+    let mut group = c.benchmark_group("process_slots");
+    group.sample_size(10);
+    for gap in SLOT_GAPS {
+        group.bench_with_input(BenchmarkId::new("slots", gap), &gap, |b, gap| {
+            b.iter_batched(
+                || state.clone(),
+                |mut state| state.process_slots(*gap).expect("Failed to process slots"),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+fn bench_process_block(c: &mut Criterion) {
+    let (state, block) = state_and_full_block();
+    c.bench_function("process_block_full_attestations", |b| {
+        b.iter_batched(
+            || state.clone(),
+            |mut state| {
+                state
+                    .process_block(&block)
+                    .expect("Failed to process block")
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+fn bench_tree_hash_root(c: &mut Criterion) {
+    let genesis_state = genesis_state();
+    let (mut state, block) = state_and_full_block();
+    state
+        .process_block(&block)
+        .expect("Failed to process block");
+
+    let mut group = c.benchmark_group("state_tree_hash_root");
+    group.bench_function("genesis", |b| b.iter(|| genesis_state.tree_hash_root()));
+    group.bench_function("tracked_justifications", |b| {
+        b.iter(|| state.tree_hash_root())
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_process_slots,
+    bench_process_block,
+    bench_tree_hash_root
+);
+criterion_main!(benches);
@@ -1,11 +1,12 @@
-use alloy_primitives::FixedBytes;
-use ream_post_quantum_crypto::leansig::signature::Signature;
+use anyhow::{anyhow, ensure};
+use ream_post_quantum_crypto::leansig::{aggregate::AggregateSignature, signature::Signature};
 use serde::{Deserialize, Serialize};
 use ssz_derive::{Decode, Encode};
 use ssz_types::{BitList, VariableList, typenum::U4096};
+use tree_hash::TreeHash;
 use tree_hash_derive::TreeHash;
 
-use crate::checkpoint::Checkpoint;
+use crate::{checkpoint::Checkpoint, state::LeanState};
 
 /// Attestation content describing the validator's observed chain view.
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
@@ -25,21 +26,25 @@ pub struct Attestation {
 
 impl Attestation {
     /// Return the attested slot.
+    #[inline]
     pub fn slot(&self) -> u64 {
         self.data.slot
     }
 
     /// Return the attested head checkpoint.
+    #[inline]
     pub fn head(&self) -> Checkpoint {
         self.data.head
     }
 
     /// Return the attested target checkpoint.
+    #[inline]
     pub fn target(&self) -> Checkpoint {
         self.data.target
     }
 
     /// Return the attested source checkpoint.
+    #[inline]
     pub fn source(&self) -> Checkpoint {
         self.data.source
     }
@@ -61,22 +66,108 @@ pub struct AggregatedAttestations {
     pub message: AttestationData,
 }
 
+impl AggregatedAttestations {
+    /// Returns the indices of the participating validators, in ascending order.
+    pub fn participants(&self) -> impl Iterator<Item = u64> + '_ {
+        self.aggregation_bits
+            .iter()
+            .enumerate()
+            .filter(|(_, participated)| *participated)
+            .map(|(validator_id, _)| validator_id as u64)
+    }
+}
+
 /// Aggregated attestation bundled with aggregated signatures.
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct SignedAggregatedAttestation {
     pub message: AggregatedAttestations,
-    /// U4096 = VALIDATOR_REGISTRY_LIMIT
-    pub signature: VariableList<FixedBytes<4000>, U4096>,
+    /// The signatures of the participants, in the order of their validator indices.
+    pub signature: AggregateSignature,
+}
+
+impl SignedAggregatedAttestation {
+    /// Aggregates attestations of distinct validators to the same data.
+    pub fn aggregate(signed_attestations: &[SignedAttestation]) -> anyhow::Result<Self> {
+        let first = signed_attestations
+            .first()
+            .ok_or_else(|| anyhow!("No attestations to aggregate"))?;
+
+        let mut signed_attestations = signed_attestations.iter().collect::<Vec<_>>();
+        signed_attestations
+            .sort_by_key(|signed_attestation| signed_attestation.message.validator_id);
+        let mut aggregation_bits =
+            BitList::with_capacity(signed_attestations.last().map_or(0, |signed_attestation| {
+                signed_attestation.message.validator_id as usize + 1
+            }))
+            .map_err(|err| anyhow!("Too many validators to aggregate: {err:?}"))?;
+
+        for signed_attestation in &signed_attestations {
+            let attestation = &signed_attestation.message;
+            ensure!(
+                attestation.data == first.message.data,
+                "Attestation of validator {} has different data",
+                attestation.validator_id
+            );
+            let validator_id = attestation.validator_id as usize;
+            ensure!(
+                !aggregation_bits.get(validator_id).unwrap_or(false),
+                "Validator {validator_id} attests twice"
+            );
+            aggregation_bits
+                .set(validator_id, true)
+                .map_err(|err| anyhow!("Failed to set aggregation bit: {err:?}"))?;
+        }
+
+        Ok(Self {
+            message: AggregatedAttestations {
+                aggregation_bits,
+                message: first.message.data.clone(),
+            },
+            signature: AggregateSignature::aggregate(
+                signed_attestations
+                    .iter()
+                    .map(|signed_attestation| signed_attestation.signature),
+            )?,
+        })
+    }
+
+    /// Verifies the signature of every participant against its key in `state`. Each participant
+    /// signed its own [Attestation] to the aggregated data. An aggregate without participants is
+    /// rejected, as nobody attested to it.
+    pub fn verify(&self, state: &LeanState) -> anyhow::Result<bool> {
+        ensure!(
+            !self.message.aggregation_bits.is_zero(),
+            "Aggregate has no participants"
+        );
+        let signers = self
+            .message
+            .participants()
+            .map(|validator_id| {
+                let attestation = Attestation {
+                    validator_id,
+                    data: self.message.message.clone(),
+                };
+                let public_key = state
+                    .validator_public_key_at(validator_id, attestation.data.slot)
+                    .ok_or_else(|| anyhow!("Validator index {validator_id} out of range"))?;
+                Ok((*public_key, attestation.tree_hash_root().0))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        self.signature
+            .verify(&signers, self.message.message.slot as u32)
+    }
 }
 
 #[cfg(test)]
 mod tests {
 
-    use alloy_primitives::hex;
+    use alloy_primitives::{FixedBytes, hex};
     use ssz::{Decode, Encode};
 
     use super::*;
-    use crate::{attestation::AttestationData, checkpoint::Checkpoint};
+    use crate::{
+        attestation::AttestationData, checkpoint::Checkpoint, utils::generate_default_validators,
+    };
 
     #[test]
     fn test_encode_decode_signed_attestation_roundtrip() -> anyhow::Result<()> {
@@ -105,4 +196,64 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_aggregate_attestations() {
+        let data = AttestationData {
+            slot: 2,
+            head: Checkpoint::default(),
+            target: Checkpoint::default(),
+            source: Checkpoint::default(),
+        };
+        let signed_attestation = |validator_id, data: &AttestationData| SignedAttestation {
+            message: Attestation {
+                validator_id,
+                data: data.clone(),
+            },
+            signature: Signature::blank(),
+        };
+
+        let aggregate = SignedAggregatedAttestation::aggregate(&[
+            signed_attestation(3, &data),
+            signed_attestation(1, &data),
+        ])
+        .unwrap();
+        assert_eq!(
+            aggregate.message.participants().collect::<Vec<_>>(),
+            vec![1, 3]
+        );
+        assert_eq!(aggregate.message.aggregation_bits.len(), 4);
+        assert_eq!(aggregate.signature.len(), 2);
+
+        let other_data = AttestationData {
+            slot: 3,
+            ..data.clone()
+        };
+        assert!(
+            SignedAggregatedAttestation::aggregate(&[
+                signed_attestation(1, &data),
+                signed_attestation(2, &other_data),
+            ])
+            .is_err()
+        );
+        assert!(
+            SignedAggregatedAttestation::aggregate(&[
+                signed_attestation(1, &data),
+                signed_attestation(1, &data),
+            ])
+            .is_err()
+        );
+        assert!(SignedAggregatedAttestation::aggregate(&[]).is_err());
+
+        // An aggregate whose bits are all cleared has nothing to verify.
+        let state = LeanState::generate_genesis(0, Some(generate_default_validators(4)));
+        let empty = SignedAggregatedAttestation {
+            message: AggregatedAttestations {
+                aggregation_bits: BitList::with_capacity(4).unwrap(),
+                message: data.clone(),
+            },
+            signature: AggregateSignature::default(),
+        };
+        assert!(empty.verify(&state).is_err());
+    }
 }
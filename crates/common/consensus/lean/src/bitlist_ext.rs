@@ -0,0 +1,113 @@
+use std::ops::Range;
+
+use anyhow::{anyhow, ensure};
+use ssz_types::{BitList, typenum::Unsigned};
+
+/// Operations on [BitList] the state transition keeps needing, which `ssz_types` leaves to a
+/// `with_capacity` and `union` of a new bitlist, or to setting the bits one at a time.
+pub trait BitListExt: Sized {
+    /// Lengthens the bitlist to `len` bits, the new bits unset.
+    fn grow_to(&mut self, len: usize) -> anyhow::Result<()>;
+
+    /// Sets every bit in `range` to `value`.
+    fn set_range(&mut self, range: Range<usize>, value: bool) -> anyhow::Result<()>;
+
+    /// A bitlist of `len` bits where only the bits at `indices` are set.
+    fn from_indices(len: usize, indices: impl IntoIterator<Item = usize>) -> anyhow::Result<Self>;
+
+    /// The indices of the set bits, in order. Bytes without any set bit are skipped over.
+    fn iter_set_indices(&self) -> impl Iterator<Item = usize> + '_;
+}
+
+impl<N: Unsigned + Clone> BitListExt for BitList<N> {
+    fn grow_to(&mut self, len: usize) -> anyhow::Result<()> {
+        ensure!(
+            len >= self.len(),
+            "Can't grow a bitlist of {} bits to {len} bits",
+            self.len()
+        );
+        let grown = BitList::<N>::with_capacity(len)
+            .map_err(|err| anyhow!("Failed to create BitList of {len} bits: {err:?}"))?;
+        *self = grown.union(self);
+        Ok(())
+    }
+
+    fn set_range(&mut self, range: Range<usize>, value: bool) -> anyhow::Result<()> {
+        for index in range {
+            self.set(index, value)
+                .map_err(|err| anyhow!("Failed to set bit {index}: {err:?}"))?;
+        }
+        Ok(())
+    }
+
+    fn from_indices(len: usize, indices: impl IntoIterator<Item = usize>) -> anyhow::Result<Self> {
+        let mut bitlist = BitList::<N>::with_capacity(len)
+            .map_err(|err| anyhow!("Failed to create BitList of {len} bits: {err:?}"))?;
+        for index in indices {
+            bitlist
+                .set(index, true)
+                .map_err(|err| anyhow!("Failed to set bit {index}: {err:?}"))?;
+        }
+        Ok(bitlist)
+    }
+
+    fn iter_set_indices(&self) -> impl Iterator<Item = usize> + '_ {
+        let len = self.len();
+        self.as_slice()
+            .iter()
+            .enumerate()
+            .filter(|(_, byte)| **byte != 0)
+            .flat_map(move |(byte_index, byte)| {
+                (0..8)
+                    .filter(move |bit| (byte >> bit) & 1 == 1)
+                    .map(move |bit| byte_index * 8 + bit)
+                    .filter(move |index| *index < len)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ssz_types::{BitList, typenum::U64};
+
+    use super::BitListExt;
+
+    #[test]
+    fn test_grow_to() {
+        let mut bitlist = BitList::<U64>::from_indices(3, [0, 2]).unwrap();
+        bitlist.grow_to(11).unwrap();
+        assert_eq!(bitlist.len(), 11);
+        assert_eq!(bitlist.iter_set_indices().collect::<Vec<_>>(), vec![0, 2]);
+
+        assert!(bitlist.grow_to(10).is_err());
+        assert!(bitlist.grow_to(65).is_err());
+    }
+
+    #[test]
+    fn test_set_range() {
+        let mut bitlist = BitList::<U64>::with_capacity(20).unwrap();
+        bitlist.set_range(3..12, true).unwrap();
+        bitlist.set_range(5..7, false).unwrap();
+        assert_eq!(
+            bitlist.iter_set_indices().collect::<Vec<_>>(),
+            vec![3, 4, 7, 8, 9, 10, 11]
+        );
+        assert!(bitlist.set_range(18..21, true).is_err());
+    }
+
+    #[test]
+    fn test_from_indices() {
+        let bitlist = BitList::<U64>::from_indices(17, [16, 1, 8]).unwrap();
+        let mut expected = BitList::<U64>::with_capacity(17).unwrap();
+        for index in [1, 8, 16] {
+            expected.set(index, true).unwrap();
+        }
+        assert_eq!(bitlist, expected);
+        assert_eq!(
+            bitlist.iter_set_indices().collect::<Vec<_>>(),
+            vec![1, 8, 16]
+        );
+
+        assert!(BitList::<U64>::from_indices(17, [17]).is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use alloy_primitives::B256;
+use anyhow::ensure;
+use serde::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+use ssz_types::{VariableList, typenum::U131072};
+use tree_hash_derive::TreeHash;
+
+/// The most blobs a single lean block may carry.
+pub const MAX_BLOBS_PER_BLOCK: u64 = 6;
+
+/// A 128 KiB blob of opaque data.
+pub type Blob = VariableList<u8, U131072>;
+
+/// A blob published next to the block at `block_root`, for data availability experiments on
+/// lean devnets. Lean blocks don't commit to their blobs yet, so a sidecar carries the number of
+/// blobs of its block, and a block is available once every one of them has been received.
+///
+/// A block without blobs is announced by a single empty sidecar with a `blob_count` of 0, so a
+/// block whose sidecars haven't arrived yet isn't mistaken for one without blobs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct BlobSidecar {
+    pub block_root: B256,
+    pub index: u64,
+    pub blob_count: u64,
+    pub blob: Blob,
+}
+
+impl BlobSidecar {
+    pub fn identifier(&self) -> BlobIdentifier {
+        BlobIdentifier {
+            block_root: self.block_root,
+            index: self.index,
+        }
+    }
+
+    /// The number of sidecars published for the block, counting the one announcing that it has
+    /// no blobs.
+    pub fn sidecar_count(&self) -> u64 {
+        self.blob_count.max(1)
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        ensure!(
+            self.blob_count <= MAX_BLOBS_PER_BLOCK,
+            "Blob count {} is over the maximum of {MAX_BLOBS_PER_BLOCK}",
+            self.blob_count
+        );
+        ensure!(
+            self.index < self.sidecar_count(),
+            "Blob index {} is out of range for {} blobs",
+            self.index,
+            self.blob_count
+        );
+        ensure!(
+            self.blob_count > 0 || self.blob.is_empty(),
+            "Sidecar announcing a block without blobs carries a blob"
+        );
+        Ok(())
+    }
+}
+
+#[derive(
+    Debug,
+    Default,
+    Clone,
+    Copy,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    Serialize,
+    Deserialize,
+    Encode,
+    Decode,
+    TreeHash,
+)]
+pub struct BlobIdentifier {
+    pub block_root: B256,
+    pub index: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ssz_types::VariableList;
+
+    use super::{BlobSidecar, MAX_BLOBS_PER_BLOCK};
+
+    fn sidecar(index: u64, blob_count: u64) -> BlobSidecar {
+        BlobSidecar {
+            block_root: B256::repeat_byte(1),
+            index,
+            blob_count,
+            blob: VariableList::empty(),
+        }
+    }
+
+    #[test]
+    fn test_validate_blob_sidecar() {
+        assert!(sidecar(0, 1).validate().is_ok());
+        assert!(sidecar(1, 1).validate().is_err());
+        assert!(sidecar(0, 0).validate().is_ok());
+        assert!(sidecar(1, 0).validate().is_err());
+        assert!(
+            BlobSidecar {
+                blob: VariableList::from(vec![1]),
+                ..sidecar(0, 0)
+            }
+            .validate()
+            .is_err()
+        );
+        assert!(sidecar(0, MAX_BLOBS_PER_BLOCK + 1).validate().is_err());
+    }
+}
@@ -1,13 +1,29 @@
 use alloy_primitives::B256;
 use anyhow::{anyhow, ensure};
+use ream_metrics::slot_report::record_signature_verifications;
 use ream_post_quantum_crypto::leansig::signature::Signature;
 use serde::{Deserialize, Serialize};
+use ssz::{
+    BYTES_PER_LENGTH_OFFSET, Decode as _, DecodeError, Encode as _, SszDecoderBuilder, SszEncoder,
+};
 use ssz_derive::{Decode, Encode};
-use ssz_types::{VariableList, typenum::U4096};
+use ssz_types::{
+    VariableList,
+    typenum::{U16, U4096},
+};
+use tracing::instrument;
 use tree_hash::TreeHash;
+#[cfg(not(feature = "stable_container"))]
+use tree_hash::{Hash256, PackedEncoding, TreeHashType, merkle_root};
 use tree_hash_derive::TreeHash;
 
-use crate::{attestation::Attestation, state::LeanState};
+use crate::{
+    attestation::Attestation,
+    state::LeanState,
+    validator::{
+        SignedKeyRotation, SignedValidatorExit, SignedValidatorRegistration, ValidatorExit,
+    },
+};
 
 /// Envelope carrying a block, an attestation from proposer, and aggregated signatures.
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode)]
@@ -17,49 +33,96 @@ pub struct SignedBlockWithAttestation {
 }
 
 impl SignedBlockWithAttestation {
+    #[instrument(skip(self, parent_state), fields(slot = self.message.block.slot))]
     pub fn verify_signatures(
         &self,
         parent_state: &LeanState,
         verify_signatures: bool,
     ) -> anyhow::Result<bool> {
-        let block = &self.message.block;
-        let signatures = &self.signature;
-        let mut all_attestations = block.body.attestations.to_vec();
-
+        let mut all_attestations = self.message.block.body.attestations.to_vec();
         all_attestations.push(self.message.proposer_attestation.clone());
+        verify_block_signatures(
+            &self.message.block,
+            &all_attestations,
+            &self.signature,
+            parent_state,
+            verify_signatures,
+        )?;
+        Ok(true)
+    }
+}
 
-        ensure!(
-            signatures.len() == all_attestations.len(),
-            "Number of signatures {} does not match number of attestations {}",
-            signatures.len(),
-            all_attestations.len(),
-        );
-        let validators = &parent_state.validators;
+/// Verifies the `signatures` of `attestations`, in the same order, and the signatures of the
+/// validator operations in `block` against their keys in `parent_state`. Without
+/// `verify_signatures`, only checks that every attester is known.
+fn verify_block_signatures(
+    block: &Block,
+    attestations: &[Attestation],
+    signatures: &[Signature],
+    parent_state: &LeanState,
+    verify_signatures: bool,
+) -> anyhow::Result<()> {
+    ensure!(
+        signatures.len() == attestations.len(),
+        "Number of signatures {} does not match number of attestations {}",
+        signatures.len(),
+        attestations.len(),
+    );
+    for (attestation, signature) in attestations.iter().zip(signatures.iter()) {
+        let validator_id = attestation.validator_id;
+        let public_key = parent_state
+            .validator_public_key_at(validator_id, attestation.data.slot)
+            .ok_or(anyhow!("Validator index out of range"))?;
 
-        for (attestation, signature) in all_attestations.iter().zip(signatures.iter()) {
-            let validator_id = attestation.validator_id as usize;
+        if verify_signatures {
             ensure!(
-                validator_id < validators.len(),
-                "Validator index out of range"
+                signature.verify(
+                    public_key,
+                    attestation.data.slot as u32,
+                    &attestation.tree_hash_root(),
+                )?,
+                "Failed to verify"
             );
-            let validator = validators
-                .get(validator_id)
-                .ok_or(anyhow!("Failed to get validator"))?;
-
-            if verify_signatures {
-                ensure!(
-                    signature.verify(
-                        &validator.public_key,
-                        attestation.data.slot as u32,
-                        &attestation.tree_hash_root(),
-                    )?,
-                    "Failed to verify"
-                );
-            }
         }
+    }
 
-        Ok(true)
+    if verify_signatures {
+        for registration in block.body.validator_registrations.iter() {
+            ensure!(
+                registration.verify()?,
+                "Failed to verify registration of {:?}",
+                registration.message.public_key
+            );
+        }
+        for exit in block.body.validator_exits.iter() {
+            let ValidatorExit {
+                validator_index,
+                exit_slot,
+            } = exit.message;
+            let public_key = parent_state
+                .validator_public_key_at(validator_index, exit_slot)
+                .ok_or_else(|| anyhow!("Exit of unknown validator {validator_index}"))?;
+            ensure!(
+                exit.verify(public_key)?,
+                "Failed to verify exit of validator {validator_index}"
+            );
+        }
+        for rotation in block.body.key_rotations.iter() {
+            let validator_index = rotation.message.validator_index;
+            let public_key = parent_state
+                .validator_public_key_at(validator_index, block.slot)
+                .ok_or_else(|| anyhow!("Key rotation of unknown validator {validator_index}"))?;
+            ensure!(
+                rotation.verify(public_key)?,
+                "Failed to verify key rotation of validator {validator_index}"
+            );
+        }
+        record_signature_verifications(
+            (attestations.len() + block.body.validator_operation_count()) as u64,
+        );
     }
+
+    Ok(())
 }
 
 /// Bundle containing a block and the proposer's attestation.
@@ -70,7 +133,11 @@ pub struct BlockWithAttestation {
 }
 
 /// Represents a block in the Lean chain.
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+///
+/// With the `stable_container` feature, this, [BlockHeader] and [BlockBody] hash as EIP-7495
+/// profiles instead.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode)]
+#[cfg_attr(not(feature = "stable_container"), derive(TreeHash))]
 pub struct Block {
     pub slot: u64,
     pub proposer_index: u64,
@@ -81,8 +148,22 @@ pub struct Block {
     pub body: BlockBody,
 }
 
-/// Represents a block header in the Lean chain.
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+impl Block {
+    /// The first block of a chain, on top of the genesis state whose root is `state_root`.
+    pub fn genesis(state_root: B256) -> Self {
+        Self {
+            slot: 0,
+            proposer_index: 0,
+            parent_root: B256::ZERO,
+            state_root,
+            body: BlockBody::default(),
+        }
+    }
+}
+
+/// Represents a block header in the Lean chain, which has the same root as its [Block].
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode)]
+#[cfg_attr(not(feature = "stable_container"), derive(TreeHash))]
 pub struct BlockHeader {
     pub slot: u64,
     pub proposer_index: u64,
@@ -104,9 +185,143 @@ impl From<Block> for BlockHeader {
 }
 
 /// Represents the body of a block in the Lean chain.
-#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+///
+/// The validator operations come with the Devnet3 fork. A body without any encodes and hashes as
+/// the leanSpec body, which only has `attestations`, so blocks before the fork are unchanged. A
+/// body with operations encodes and hashes as a container of all four fields.
+#[derive(Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize)]
 pub struct BlockBody {
     pub attestations: VariableList<Attestation, U4096>,
+    #[serde(default, skip_serializing_if = "VariableList::is_empty")]
+    pub validator_registrations: VariableList<SignedValidatorRegistration, U16>,
+    #[serde(default, skip_serializing_if = "VariableList::is_empty")]
+    pub validator_exits: VariableList<SignedValidatorExit, U16>,
+    #[serde(default, skip_serializing_if = "VariableList::is_empty")]
+    pub key_rotations: VariableList<SignedKeyRotation, U16>,
+}
+
+impl BlockBody {
+    /// A body of just `attestations`, without validator operations.
+    pub fn new(attestations: VariableList<Attestation, U4096>) -> Self {
+        Self {
+            attestations,
+            ..Default::default()
+        }
+    }
+
+    /// The number of registrations, exits and key rotations in the body.
+    pub fn validator_operation_count(&self) -> usize {
+        self.validator_registrations.len() + self.validator_exits.len() + self.key_rotations.len()
+    }
+
+    pub fn has_validator_operations(&self) -> bool {
+        self.validator_operation_count() > 0
+    }
+
+    /// The number of fields the body encodes and hashes.
+    fn field_count(&self) -> usize {
+        if self.has_validator_operations() {
+            4
+        } else {
+            1
+        }
+    }
+
+    /// The roots of the fields the body hashes, in order.
+    pub fn field_roots(&self) -> Vec<B256> {
+        let mut field_roots = vec![self.attestations.tree_hash_root()];
+        if self.has_validator_operations() {
+            field_roots.extend([
+                self.validator_registrations.tree_hash_root(),
+                self.validator_exits.tree_hash_root(),
+                self.key_rotations.tree_hash_root(),
+            ]);
+        }
+        field_roots
+    }
+}
+
+impl ssz::Encode for BlockBody {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn ssz_append(&self, buf: &mut Vec<u8>) {
+        let mut encoder = SszEncoder::container(buf, self.field_count() * BYTES_PER_LENGTH_OFFSET);
+        encoder.append(&self.attestations);
+        if self.has_validator_operations() {
+            encoder.append(&self.validator_registrations);
+            encoder.append(&self.validator_exits);
+            encoder.append(&self.key_rotations);
+        }
+        encoder.finalize();
+    }
+
+    fn ssz_bytes_len(&self) -> usize {
+        let mut length =
+            self.field_count() * BYTES_PER_LENGTH_OFFSET + self.attestations.ssz_bytes_len();
+        if self.has_validator_operations() {
+            length += self.validator_registrations.ssz_bytes_len()
+                + self.validator_exits.ssz_bytes_len()
+                + self.key_rotations.ssz_bytes_len();
+        }
+        length
+    }
+}
+
+impl ssz::Decode for BlockBody {
+    fn is_ssz_fixed_len() -> bool {
+        false
+    }
+
+    fn from_ssz_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        // The first offset points past the offsets, which tells how many fields there are.
+        let has_validator_operations = ssz::read_offset(bytes)? != BYTES_PER_LENGTH_OFFSET;
+
+        let mut builder = SszDecoderBuilder::new(bytes);
+        builder.register_type::<VariableList<Attestation, U4096>>()?;
+        if has_validator_operations {
+            builder.register_type::<VariableList<SignedValidatorRegistration, U16>>()?;
+            builder.register_type::<VariableList<SignedValidatorExit, U16>>()?;
+            builder.register_type::<VariableList<SignedKeyRotation, U16>>()?;
+        }
+        let mut decoder = builder.build()?;
+
+        let mut body = BlockBody::new(decoder.decode_next()?);
+        if has_validator_operations {
+            body.validator_registrations = decoder.decode_next()?;
+            body.validator_exits = decoder.decode_next()?;
+            body.key_rotations = decoder.decode_next()?;
+            // Every body has a single encoding, so it has a single root too.
+            if !body.has_validator_operations() {
+                return Err(DecodeError::BytesInvalid(
+                    "Block body without validator operations must only encode attestations"
+                        .to_string(),
+                ));
+            }
+        }
+        Ok(body)
+    }
+}
+
+#[cfg(not(feature = "stable_container"))]
+impl TreeHash for BlockBody {
+    fn tree_hash_type() -> TreeHashType {
+        TreeHashType::Container
+    }
+
+    fn tree_hash_packed_encoding(&self) -> PackedEncoding {
+        unreachable!("Struct should never be packed.")
+    }
+
+    fn tree_hash_packing_factor() -> usize {
+        unreachable!("Struct should never be packed.")
+    }
+
+    fn tree_hash_root(&self) -> Hash256 {
+        let field_roots = self.field_roots();
+        merkle_root(&field_roots.concat(), field_roots.len())
+    }
 }
 
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
@@ -115,6 +330,21 @@ pub struct BlockWithSignatures {
     pub signatures: VariableList<Signature, U4096>,
 }
 
+impl BlockWithSignatures {
+    /// Verifies the signatures of the attestations and validator operations in the block against
+    /// their keys in `parent_state`, for a block built by someone else before proposing it.
+    pub fn verify_signatures(&self, parent_state: &LeanState) -> anyhow::Result<bool> {
+        verify_block_signatures(
+            &self.block,
+            &self.block.body.attestations,
+            &self.signatures,
+            parent_state,
+            true,
+        )?;
+        Ok(true)
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -122,7 +352,24 @@ mod tests {
     use ssz::{Decode, Encode};
 
     use super::*;
-    use crate::{attestation::AttestationData, checkpoint::Checkpoint};
+    use crate::{
+        attestation::AttestationData, checkpoint::Checkpoint, utils::generate_default_validators,
+    };
+
+    #[test]
+    fn test_block_header_root_matches_block_root() {
+        let block = Block {
+            slot: 3,
+            proposer_index: 1,
+            parent_root: B256::repeat_byte(1),
+            state_root: B256::repeat_byte(2),
+            body: BlockBody::default(),
+        };
+        assert_eq!(
+            BlockHeader::from(block.clone()).tree_hash_root(),
+            block.tree_hash_root()
+        );
+    }
 
     #[test]
     fn test_encode_decode_signed_block_with_attestation_roundtrip() -> anyhow::Result<()> {
@@ -133,9 +380,7 @@ mod tests {
                     proposer_index: 0,
                     parent_root: B256::ZERO,
                     state_root: B256::ZERO,
-                    body: BlockBody {
-                        attestations: Default::default(),
-                    },
+                    body: BlockBody::default(),
                 },
                 proposer_attestation: Attestation {
                     validator_id: 0,
@@ -160,4 +405,64 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_block_body_layouts() {
+        let body = BlockBody::default();
+        // Without validator operations, the body is the leanSpec body of just attestations.
+        assert_eq!(hex::encode(body.as_ssz_bytes()), "04000000");
+        assert_eq!(body.tree_hash_root(), body.attestations.tree_hash_root());
+
+        let body_with_exit = BlockBody {
+            validator_exits: VariableList::try_from(vec![SignedValidatorExit {
+                message: ValidatorExit {
+                    validator_index: 1,
+                    exit_slot: 10,
+                },
+                signature: Signature::blank(),
+            }])
+            .unwrap(),
+            ..body.clone()
+        };
+        let encoded = body_with_exit.as_ssz_bytes();
+        assert_eq!(&encoded[..4], &16_u32.to_le_bytes());
+        assert_eq!(
+            BlockBody::from_ssz_bytes(&encoded),
+            Ok(body_with_exit.clone())
+        );
+        assert_ne!(body_with_exit.tree_hash_root(), body.tree_hash_root());
+
+        // Empty operations have to use the leanSpec layout, so a body has one encoding.
+        let extended_empty = hex::decode("10000000100000001000000010000000").unwrap();
+        assert!(BlockBody::from_ssz_bytes(&extended_empty).is_err());
+    }
+
+    #[test]
+    fn test_block_with_signatures_verify_signatures() {
+        let state = LeanState::generate_genesis(0, Some(generate_default_validators(4)));
+        let mut block_with_signatures = BlockWithSignatures {
+            block: Block::genesis(B256::ZERO),
+            signatures: VariableList::empty(),
+        };
+        assert!(block_with_signatures.verify_signatures(&state).unwrap());
+
+        // An attestation needs a signature of its validator.
+        block_with_signatures.block.body.attestations = VariableList::try_from(vec![Attestation {
+            validator_id: 1,
+            data: AttestationData {
+                slot: 1,
+                head: Checkpoint::default(),
+                target: Checkpoint::default(),
+                source: Checkpoint::default(),
+            },
+        }])
+        .unwrap();
+        assert!(block_with_signatures.verify_signatures(&state).is_err());
+        block_with_signatures.signatures =
+            VariableList::try_from(vec![Signature::blank()]).unwrap();
+        assert!(!matches!(
+            block_with_signatures.verify_signatures(&state),
+            Ok(true)
+        ));
+    }
 }
@@ -0,0 +1,80 @@
+use ream_network_spec::networks::LeanFork;
+
+/// Why an attestation makes the block including it invalid.
+#[derive(Debug, PartialEq, Eq, thiserror::Error)]
+pub enum AttestationError {
+    #[error("Attestation by unknown validator {validator_id}, the state has {validator_count}")]
+    UnknownValidator {
+        validator_id: u64,
+        validator_count: usize,
+    },
+
+    #[error("Validator {validator_id} has more than one attestation in the block")]
+    DuplicateValidator { validator_id: u64 },
+
+    #[error("Attestation target slot {target_slot} is after the state slot {slot}")]
+    FutureTarget { target_slot: u64, slot: u64 },
+}
+
+impl AttestationError {
+    /// The label the rejection is counted under.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            AttestationError::UnknownValidator { .. } => "unknown_validator",
+            AttestationError::DuplicateValidator { .. } => "duplicate_validator",
+            AttestationError::FutureTarget { .. } => "future_target",
+        }
+    }
+}
+
+/// Why processing slots or a block failed.
+#[derive(Debug, thiserror::Error)]
+pub enum StateTransitionError {
+    #[error("Target slot must be in the future, expected {slot} < {target_slot}")]
+    SlotNotInFuture { slot: u64, target_slot: u64 },
+
+    #[error("Can't upgrade to the {0} fork")]
+    UnsupportedFork(LeanFork),
+
+    /// The network spec wasn't set up, so which fork's rules apply at `slot` is unknown.
+    #[error("Network spec isn't set, so the fork at slot {slot} is unknown")]
+    NetworkSpecNotSet { slot: u64 },
+
+    #[error("State is at slot {slot}, not advanced to the block slot {block_slot}")]
+    NotAdvancedToBlockSlot { slot: u64, block_slot: u64 },
+
+    #[error("Signatures are not valid")]
+    InvalidSignatures,
+
+    #[error("Invalid block state root")]
+    InvalidStateRoot,
+
+    #[error(transparent)]
+    InvalidAttestation(#[from] AttestationError),
+
+    /// The state breaks an invariant the state transition keeps, which no block can cause.
+    #[error("Invalid state: {0:#}")]
+    InvalidState(anyhow::Error),
+
+    /// Any other rule of the state transition the block breaks.
+    #[error("Invalid block: {0:#}")]
+    InvalidBlock(anyhow::Error),
+}
+
+impl StateTransitionError {
+    /// Whether the failure is the node's own rather than the block's.
+    pub fn is_internal(&self) -> bool {
+        matches!(
+            self,
+            StateTransitionError::NetworkSpecNotSet { .. } | StateTransitionError::InvalidState(_)
+        )
+    }
+
+    /// Sorts an error of processing a block into an invalid attestation or an invalid block.
+    pub fn from_block_error(err: anyhow::Error) -> Self {
+        match err.downcast::<AttestationError>() {
+            Ok(err) => StateTransitionError::InvalidAttestation(err),
+            Err(err) => StateTransitionError::InvalidBlock(err),
+        }
+    }
+}
@@ -0,0 +1,335 @@
+use std::collections::HashMap;
+
+use alloy_primitives::B256;
+use anyhow::{anyhow, ensure};
+use itertools::Itertools;
+use ssz_types::{
+    BitList, VariableList,
+    typenum::{U262144, U1073741824},
+};
+
+use crate::bitlist_ext::BitListExt;
+
+/// The votes of the validators for one target, a bit per validator packed into words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Votes {
+    words: Vec<u64>,
+    len: usize,
+    count: usize,
+}
+
+impl Votes {
+    pub fn new(validator_count: usize) -> Self {
+        Self {
+            words: vec![0; validator_count.div_ceil(64)],
+            len: validator_count,
+            count: 0,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// How many validators voted.
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn get(&self, validator_index: usize) -> anyhow::Result<bool> {
+        ensure!(
+            validator_index < self.len,
+            "Validator {validator_index} is out of range of {} votes",
+            self.len
+        );
+        Ok((self.words[validator_index / 64] >> (validator_index % 64)) & 1 == 1)
+    }
+
+    /// Records the vote of `validator_index`, returning whether it hadn't voted yet.
+    pub fn set(&mut self, validator_index: usize) -> anyhow::Result<bool> {
+        if self.get(validator_index)? {
+            return Ok(false);
+        }
+        self.words[validator_index / 64] |= 1 << (validator_index % 64);
+        self.count += 1;
+        Ok(true)
+    }
+
+    /// Drops the vote of `validator_index`, if it voted.
+    pub fn clear(&mut self, validator_index: usize) -> anyhow::Result<()> {
+        if self.get(validator_index)? {
+            self.words[validator_index / 64] &= !(1 << (validator_index % 64));
+            self.count -= 1;
+        }
+        Ok(())
+    }
+
+    /// The indices of the validators which voted, in order.
+    pub fn iter_set(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words
+            .iter()
+            .enumerate()
+            .filter(|(_, word)| **word != 0)
+            .flat_map(|(word_index, word)| {
+                (0..64)
+                    .filter(move |bit| (word >> bit) & 1 == 1)
+                    .map(move |bit| word_index * 64 + bit)
+            })
+    }
+
+    /// Makes room for the votes of validators registered after the ones already tracked.
+    fn grow(&mut self, validator_count: usize) {
+        self.words.resize(validator_count.div_ceil(64), 0);
+        self.len = validator_count;
+    }
+}
+
+/// The votes of the targets which aren't justified yet, by target root.
+///
+/// The state keeps them flattened into `justifications_roots` and `justifications_validators`,
+/// with the roots sorted. The flattened bits are read once when a block starts updating the
+/// votes, and written back once when it is done, instead of rebuilding a bitlist for every root.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Justifications {
+    validator_count: usize,
+    votes: HashMap<B256, Votes>,
+}
+
+impl Justifications {
+    /// Reads the votes of `roots` from the flattened `validators` bits, of `validator_count`
+    /// bits per root.
+    pub fn from_state(
+        roots: &[B256],
+        validators: &BitList<U1073741824>,
+        validator_count: usize,
+    ) -> anyhow::Result<Self> {
+        ensure!(
+            validators.len() == roots.len() * validator_count,
+            "Expected {} justification bits for {} roots, got {}",
+            roots.len() * validator_count,
+            roots.len(),
+            validators.len()
+        );
+
+        let mut votes = roots
+            .iter()
+            .map(|_| Votes::new(validator_count))
+            .collect::<Vec<_>>();
+        for index in validators.iter_set_indices() {
+            votes[index / validator_count].set(index % validator_count)?;
+        }
+
+        Ok(Self {
+            validator_count,
+            votes: roots.iter().copied().zip(votes).collect(),
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.votes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.votes.is_empty()
+    }
+
+    pub fn get(&self, root: &B256) -> Option<&Votes> {
+        self.votes.get(root)
+    }
+
+    /// The votes already recorded for `root`, or none if it isn't tracked yet.
+    pub fn votes_for(&self, root: &B256) -> Votes {
+        self.votes
+            .get(root)
+            .cloned()
+            .unwrap_or_else(|| Votes::new(self.validator_count))
+    }
+
+    pub fn insert(&mut self, root: B256, votes: Votes) -> anyhow::Result<()> {
+        ensure!(
+            votes.len() == self.validator_count,
+            "Vote list for root {root} has incorrect length expected: {}, got: {}",
+            self.validator_count,
+            votes.len(),
+        );
+        self.votes.insert(root, votes);
+        Ok(())
+    }
+
+    /// Stops tracking `root`, once it is justified.
+    pub fn remove(&mut self, root: &B256) -> Option<Votes> {
+        self.votes.remove(root)
+    }
+
+    /// Tracks the votes of validators registered after the ones already tracked, who haven't
+    /// voted yet.
+    pub fn grow(&mut self, validator_count: usize) {
+        for votes in self.votes.values_mut() {
+            votes.grow(validator_count);
+        }
+        self.validator_count = validator_count;
+    }
+
+    /// Drops the votes of `validator_index` for every target.
+    pub fn clear_validator(&mut self, validator_index: usize) -> anyhow::Result<()> {
+        self.votes
+            .values_mut()
+            .try_for_each(|votes| votes.clear(validator_index))
+    }
+
+    /// Flattens the votes back into the state's justification fields, with the roots sorted.
+    pub fn into_state(self) -> anyhow::Result<(VariableList<B256, U262144>, BitList<U1073741824>)> {
+        let mut roots = VariableList::<B256, U262144>::empty();
+        let mut indices = vec![];
+        for (root_index, (root, votes)) in self
+            .votes
+            .into_iter()
+            .sorted_by_key(|(root, _)| *root)
+            .enumerate()
+        {
+            roots
+                .push(root)
+                .map_err(|err| anyhow!("Could not append root: {err:?}"))?;
+            indices.extend(
+                votes
+                    .iter_set()
+                    .map(|validator_index| root_index * self.validator_count + validator_index),
+            );
+        }
+        let validators = BitList::from_indices(roots.len() * self.validator_count, indices)?;
+
+        Ok((roots, validators))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ssz_types::{BitList, typenum::U1073741824};
+    use tree_hash::TreeHash;
+
+    use super::{Justifications, Votes};
+
+    const VALIDATOR_COUNT: usize = 13;
+
+    fn roots() -> Vec<B256> {
+        (1..=3).map(B256::repeat_byte).collect()
+    }
+
+    /// Flattened votes where validator `v` voted for root `r` when `(r + v) % 3 == 0`.
+    fn flattened_votes(roots: usize, validator_count: usize) -> BitList<U1073741824> {
+        let mut bits = BitList::with_capacity(roots * validator_count).unwrap();
+        for root_index in 0..roots {
+            for validator_index in 0..validator_count {
+                if (root_index + validator_index) % 3 == 0 {
+                    bits.set(root_index * validator_count + validator_index, true)
+                        .unwrap();
+                }
+            }
+        }
+        bits
+    }
+
+    #[test]
+    fn test_votes() {
+        let mut votes = Votes::new(130);
+        assert!(votes.set(0).unwrap());
+        assert!(votes.set(129).unwrap());
+        assert!(!votes.set(129).unwrap());
+        assert_eq!(votes.count(), 2);
+        assert!(votes.set(130).is_err());
+
+        votes.clear(0).unwrap();
+        assert_eq!(votes.iter_set().collect::<Vec<_>>(), vec![129]);
+        assert_eq!(votes.count(), 1);
+    }
+
+    #[test]
+    fn test_roundtrip_keeps_roots() {
+        let roots = roots();
+        let validators = flattened_votes(roots.len(), VALIDATOR_COUNT);
+
+        let justifications =
+            Justifications::from_state(&roots, &validators, VALIDATOR_COUNT).unwrap();
+        assert_eq!(justifications.get(&roots[1]).unwrap().count(), 4);
+
+        let (new_roots, new_validators) = justifications.into_state().unwrap();
+        assert_eq!(new_roots.to_vec(), roots);
+        assert_eq!(new_validators, validators);
+        assert_eq!(new_validators.tree_hash_root(), validators.tree_hash_root());
+
+        // The flattened bits must match the roots.
+        assert!(Justifications::from_state(&roots[..2], &validators, VALIDATOR_COUNT).is_err());
+    }
+
+    #[test]
+    fn test_updates_match_flattened_bits() {
+        let roots = roots();
+        let mut justifications = Justifications::from_state(
+            &roots,
+            &flattened_votes(roots.len(), VALIDATOR_COUNT),
+            VALIDATOR_COUNT,
+        )
+        .unwrap();
+
+        // Validator 5 votes for the second root, validator 6 exits, and validator 0 votes for a
+        // root which sorts before the others.
+        let mut votes = justifications.votes_for(&roots[1]);
+        votes.set(5).unwrap();
+        justifications.insert(roots[1], votes).unwrap();
+        justifications.clear_validator(6).unwrap();
+        let mut votes = justifications.votes_for(&B256::ZERO);
+        votes.set(0).unwrap();
+        justifications.insert(B256::ZERO, votes).unwrap();
+
+        let mut expected =
+            BitList::<U1073741824>::with_capacity((roots.len() + 1) * VALIDATOR_COUNT).unwrap();
+        expected.set(0, true).unwrap();
+        for root_index in 0..roots.len() {
+            for validator_index in 0..VALIDATOR_COUNT {
+                let voted = (root_index + validator_index) % 3 == 0
+                    || (root_index, validator_index) == (1, 5);
+                if voted && validator_index != 6 {
+                    expected
+                        .set((root_index + 1) * VALIDATOR_COUNT + validator_index, true)
+                        .unwrap();
+                }
+            }
+        }
+
+        let (new_roots, new_validators) = justifications.into_state().unwrap();
+        assert_eq!(new_roots[0], B256::ZERO);
+        assert_eq!(new_roots[1..].to_vec(), roots);
+        assert_eq!(new_validators, expected);
+        assert_eq!(new_validators.tree_hash_root(), expected.tree_hash_root());
+    }
+
+    #[test]
+    fn test_grow() {
+        let roots = roots();
+        let validators = flattened_votes(roots.len(), VALIDATOR_COUNT);
+        let mut justifications =
+            Justifications::from_state(&roots, &validators, VALIDATOR_COUNT).unwrap();
+
+        justifications.grow(VALIDATOR_COUNT + 2);
+        let (_, grown) = justifications.into_state().unwrap();
+        assert_eq!(grown.len(), roots.len() * (VALIDATOR_COUNT + 2));
+        for root_index in 0..roots.len() {
+            for validator_index in 0..VALIDATOR_COUNT + 2 {
+                assert_eq!(
+                    grown
+                        .get(root_index * (VALIDATOR_COUNT + 2) + validator_index)
+                        .unwrap(),
+                    validator_index < VALIDATOR_COUNT
+                        && validators
+                            .get(root_index * VALIDATOR_COUNT + validator_index)
+                            .unwrap()
+                );
+            }
+        }
+    }
+}
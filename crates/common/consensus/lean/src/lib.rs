@@ -1,7 +1,17 @@
 pub mod attestation;
+pub mod bitlist_ext;
+pub mod blob_sidecar;
 pub mod block;
 pub mod checkpoint;
 pub mod config;
+pub mod errors;
+pub mod justifications;
+pub mod light_client;
+#[cfg(feature = "parallel_tree_hash")]
+mod parallel_tree_hash;
+pub mod proposer_schedule;
+#[cfg(feature = "stable_container")]
+pub mod stable_container;
 pub mod state;
 pub mod utils;
 pub mod validator;
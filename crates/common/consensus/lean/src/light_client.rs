@@ -0,0 +1,168 @@
+//! Light client data for following the lean chain without its states, after the Altair light
+//! client protocol. Lean has no sync committees, so a bootstrap hands the client the validator
+//! set instead, and the updates prove the finalized checkpoint against the state root of an
+//! attested block.
+
+use alloy_primitives::B256;
+use anyhow::{anyhow, ensure};
+use ream_merkle::is_valid_merkle_branch;
+use serde::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+use ssz_types::{FixedVector, VariableList, typenum::U4096};
+use tree_hash::TreeHash;
+use tree_hash_derive::TreeHash;
+
+use crate::{
+    block::{Block, BlockHeader},
+    checkpoint::Checkpoint,
+    state::{
+        FINALIZED_CHECKPOINT_INDEX, LeanState, STATE_PROOF_DEPTH, StateProofLength,
+        VALIDATORS_INDEX,
+    },
+    validator::Validator,
+};
+
+/// The Merkle proof of a field of the state against the state root of a header.
+pub type StateBranch = FixedVector<B256, StateProofLength>;
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct LightClientBootstrap {
+    pub header: BlockHeader,
+    /// The validators of the post state of `header`.
+    pub validators: VariableList<Validator, U4096>,
+    pub validators_branch: StateBranch,
+}
+
+impl LightClientBootstrap {
+    /// Builds the bootstrap of `block` from its post state.
+    pub fn new(block: &Block, state: &LeanState) -> anyhow::Result<Self> {
+        Ok(Self {
+            header: BlockHeader::from(block.clone()),
+            validators: state.validators.clone(),
+            validators_branch: StateBranch::new(state.field_inclusion_proof(VALIDATORS_INDEX)?)
+                .map_err(|err| anyhow!("Invalid validators branch: {err:?}"))?,
+        })
+    }
+
+    pub fn is_valid(&self) -> bool {
+        is_valid_merkle_branch(
+            self.validators.tree_hash_root(),
+            &self.validators_branch,
+            STATE_PROOF_DEPTH,
+            VALIDATORS_INDEX,
+            self.header.state_root,
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct LightClientFinalityUpdate {
+    pub attested_header: BlockHeader,
+    /// The block finalized by the post state of `attested_header`.
+    pub finalized_header: BlockHeader,
+    pub finality_branch: StateBranch,
+}
+
+impl LightClientFinalityUpdate {
+    /// Builds the update of `attested_block`, whose post state is `attested_state`, and the
+    /// `finalized_block` that state finalized.
+    pub fn new(
+        attested_block: &Block,
+        attested_state: &LeanState,
+        finalized_block: &Block,
+    ) -> anyhow::Result<Self> {
+        let finalized_root = finalized_block.tree_hash_root();
+        ensure!(
+            attested_state.latest_finalized.root == finalized_root,
+            "Block {finalized_root} isn't the finalized block of the attested state"
+        );
+
+        Ok(Self {
+            attested_header: BlockHeader::from(attested_block.clone()),
+            finalized_header: BlockHeader::from(finalized_block.clone()),
+            finality_branch: StateBranch::new(
+                attested_state.field_inclusion_proof(FINALIZED_CHECKPOINT_INDEX)?,
+            )
+            .map_err(|err| anyhow!("Invalid finality branch: {err:?}"))?,
+        })
+    }
+
+    pub fn finalized_checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            root: self.finalized_header.tree_hash_root(),
+            slot: self.finalized_header.slot,
+        }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        is_valid_merkle_branch(
+            self.finalized_checkpoint().tree_hash_root(),
+            &self.finality_branch,
+            STATE_PROOF_DEPTH,
+            FINALIZED_CHECKPOINT_INDEX,
+            self.attested_header.state_root,
+        )
+    }
+}
+
+/// The latest head, which the client follows before it is finalized.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct LightClientOptimisticUpdate {
+    pub attested_header: BlockHeader,
+}
+
+impl From<&Block> for LightClientOptimisticUpdate {
+    fn from(block: &Block) -> Self {
+        Self {
+            attested_header: BlockHeader::from(block.clone()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tree_hash::TreeHash;
+
+    use super::{LightClientBootstrap, LightClientFinalityUpdate};
+    use crate::{
+        block::{Block, BlockBody},
+        checkpoint::Checkpoint,
+        state::LeanState,
+        utils::generate_default_validators,
+    };
+
+    fn block(slot: u64, state: &LeanState) -> Block {
+        Block {
+            slot,
+            proposer_index: 0,
+            parent_root: Default::default(),
+            state_root: state.tree_hash_root(),
+            body: BlockBody::default(),
+        }
+    }
+
+    #[test]
+    fn test_light_client_proofs() {
+        let mut state = LeanState::generate_genesis(0, Some(generate_default_validators(4)));
+        let finalized_block = block(0, &state);
+
+        state.slot = 3;
+        state.latest_finalized = Checkpoint {
+            root: finalized_block.tree_hash_root(),
+            slot: 0,
+        };
+        let attested_block = block(3, &state);
+
+        let bootstrap = LightClientBootstrap::new(&attested_block, &state).unwrap();
+        assert!(bootstrap.is_valid());
+
+        let mut update =
+            LightClientFinalityUpdate::new(&attested_block, &state, &finalized_block).unwrap();
+        assert!(update.is_valid());
+        assert_eq!(update.finalized_checkpoint(), state.latest_finalized);
+
+        update.finalized_header.slot = 1;
+        assert!(!update.is_valid());
+        assert!(LightClientFinalityUpdate::new(&finalized_block, &state, &attested_block).is_err());
+    }
+}
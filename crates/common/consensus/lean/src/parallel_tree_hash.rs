@@ -0,0 +1,57 @@
+//! [TreeHash] implementation for [LeanState] that Merkleizes its large lists in parallel.
+//!
+//! Enabled with the `parallel_tree_hash` feature. The resulting root is identical to the one
+//! produced by the derived serial implementation. With the `stable_container` feature as well,
+//! the field roots are still computed here, and Merkleized as a profile.
+
+use ream_merkle::parallel::{bitlist_root_parallel, list_root_parallel};
+use ssz_types::typenum::{U262144, U1073741824, Unsigned};
+use tree_hash::Hash256;
+#[cfg(not(feature = "stable_container"))]
+use tree_hash::{PackedEncoding, TreeHash, TreeHashType, merkle_root};
+
+use crate::state::LeanState;
+
+/// The roots of the fields of `state`, in order, with the large lists Merkleized in parallel.
+pub(crate) fn state_field_roots(state: &LeanState) -> Vec<Hash256> {
+    state.field_roots_with(|state| {
+        [
+            list_root_parallel(&state.historical_block_hashes, U262144::to_usize())
+                .expect("historical_block_hashes length is bounded by its type"),
+            bitlist_root_parallel(
+                state.justified_slots.as_slice(),
+                state.justified_slots.len(),
+                U262144::to_usize(),
+            )
+            .expect("justified_slots length is bounded by its type"),
+            list_root_parallel(&state.justifications_roots, U262144::to_usize())
+                .expect("justifications_roots length is bounded by its type"),
+            bitlist_root_parallel(
+                state.justifications_validators.as_slice(),
+                state.justifications_validators.len(),
+                U1073741824::to_usize(),
+            )
+            .expect("justifications_validators length is bounded by its type"),
+        ]
+    })
+}
+
+#[cfg(not(feature = "stable_container"))]
+impl TreeHash for LeanState {
+    fn tree_hash_type() -> TreeHashType {
+        TreeHashType::Container
+    }
+
+    fn tree_hash_packed_encoding(&self) -> PackedEncoding {
+        unreachable!("Struct should never be packed.")
+    }
+
+    fn tree_hash_packing_factor() -> usize {
+        unreachable!("Struct should never be packed.")
+    }
+
+    fn tree_hash_root(&self) -> Hash256 {
+        let field_roots = state_field_roots(self);
+        merkle_root(&field_roots.concat(), field_roots.len())
+    }
+}
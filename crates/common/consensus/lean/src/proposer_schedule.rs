@@ -0,0 +1,95 @@
+use anyhow::ensure;
+
+use crate::{state::LeanState, validator::FAR_FUTURE_SLOT};
+
+/// How many slots from the current slot the proposer schedule of the API lists.
+pub const PROPOSER_LOOKAHEAD_SLOTS: u64 = 32;
+
+/// The proposers of the slots after a state, the validators active at each slot taking turns.
+/// Validators only join and schedule exits with blocks, so the schedule of the head state holds
+/// until the next block changes the validator set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProposerSchedule {
+    /// The exit slot of every validator, by validator index.
+    exit_slots: Vec<u64>,
+}
+
+impl ProposerSchedule {
+    pub fn new(state: &LeanState) -> anyhow::Result<Self> {
+        let exit_slots = state.validator_exit_slots();
+        // A validator without a scheduled exit proposes at every slot nobody else does.
+        ensure!(
+            exit_slots.contains(&FAR_FUTURE_SLOT),
+            "State has no validators without a scheduled exit"
+        );
+        Ok(Self { exit_slots })
+    }
+
+    /// Returns the proposer of `slot`.
+    pub fn proposer_index(&self, slot: u64) -> u64 {
+        let active_validator_count = self
+            .exit_slots
+            .iter()
+            .filter(|exit_slot| slot < **exit_slot)
+            .count() as u64;
+        let turn = (slot % active_validator_count) as usize;
+        self.exit_slots
+            .iter()
+            .enumerate()
+            .filter(|(_, exit_slot)| slot < **exit_slot)
+            .nth(turn)
+            .map(|(index, _)| index as u64)
+            .expect("turn is less than the number of active validators")
+    }
+
+    /// Returns the `(slot, proposer_index)` assignments of `slot_count` slots from `start_slot`.
+    pub fn upcoming(&self, start_slot: u64, slot_count: u64) -> impl Iterator<Item = (u64, u64)> {
+        (start_slot..=u64::MAX)
+            .take(slot_count as usize)
+            .map(|slot| (slot, self.proposer_index(slot)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ream_network_spec::networks::LeanFork;
+
+    use super::ProposerSchedule;
+    use crate::{state::LeanState, utils::generate_default_validators, validator::FAR_FUTURE_SLOT};
+
+    #[test]
+    fn test_proposer_schedule() {
+        let mut state = LeanState::generate_genesis(0, Some(generate_default_validators(3)));
+        let schedule = ProposerSchedule::new(&state).unwrap();
+        assert_eq!(
+            schedule.upcoming(0, 5).collect::<Vec<_>>(),
+            vec![(0, 0), (1, 1), (2, 2), (3, 0), (4, 1)]
+        );
+        assert_eq!(
+            schedule.proposer_index(10),
+            state.proposer_index_at(10).unwrap()
+        );
+
+        // The schedule ends at the last slot rather than overflowing.
+        assert_eq!(
+            schedule.upcoming(u64::MAX - 1, 5).collect::<Vec<_>>().len(),
+            2
+        );
+
+        // Validators stop proposing at their exit slot.
+        state.upgrade_to_fork(LeanFork::Devnet3).unwrap();
+        state.validator_statuses.as_mut().unwrap()[1].exit_slot = 2;
+        let schedule = ProposerSchedule::new(&state).unwrap();
+        assert_eq!(
+            schedule.upcoming(0, 5).collect::<Vec<_>>(),
+            vec![(0, 0), (1, 1), (2, 0), (3, 2), (4, 0)]
+        );
+
+        for status in state.validator_statuses.as_mut().unwrap().iter_mut() {
+            status.exit_slot = 2;
+        }
+        assert!(ProposerSchedule::new(&state).is_err());
+        state.validator_statuses.as_mut().unwrap()[0].exit_slot = FAR_FUTURE_SLOT;
+        assert!(ProposerSchedule::new(&state).is_ok());
+    }
+}
@@ -0,0 +1,320 @@
+//! [EIP-7495](https://eips.ethereum.org/EIPS/eip-7495) stable containers, which leanSpec is moving
+//! towards so forks can add fields without moving the existing ones in the tree.
+//!
+//! A `StableContainer[N]` reserves `N` field slots up front. Its root Merkleizes the fields present
+//! into their slot, with zero for the absent ones, and mixes in a bitvector of the fields present.
+//! A field therefore keeps its generalized index, and an absent field doesn't change the root, no
+//! matter how many fields later forks add. Its encoding is the bitvector followed by the present
+//! fields, encoded like a container.
+//!
+//! A `Profile` is a view of a stable container with some of its fields required. It has the same
+//! root as the stable container, but leaves the required fields out of the bitvector. With the
+//! `stable_container` feature, [Block], [BlockHeader], [BlockBody] and [LeanState] hash as
+//! profiles with the capacities below, while their encoding stays the same as a plain
+//! container's. Their fields are required, except for the fields of the Devnet3 fork, which are
+//! absent from bodies without validator operations and from states before the fork. A header
+//! shares the slots of its block, so both have the same root.
+
+use alloy_primitives::B256;
+use ream_merkle::{generate_proof, merkle_tree};
+use ssz::{BYTES_PER_LENGTH_OFFSET, DecodeError, Encode};
+use tree_hash::{BYTES_PER_CHUNK, Hash256, PackedEncoding, TreeHash, TreeHashType, merkle_root};
+
+use crate::{
+    block::{Block, BlockBody, BlockHeader},
+    state::LeanState,
+};
+
+/// Field slots reserved in [Block].
+pub const BLOCK_MAX_FIELDS: usize = 16;
+
+/// Field slots reserved in [BlockBody].
+pub const BLOCK_BODY_MAX_FIELDS: usize = 16;
+
+/// Field slots reserved in [LeanState].
+pub const STATE_MAX_FIELDS: usize = 32;
+
+/// Returns the packed bytes of a bitvector of `max_fields` bits, set for the fields present.
+fn active_fields_bytes(max_fields: usize, active_fields: impl Iterator<Item = bool>) -> Vec<u8> {
+    let mut bytes = vec![0; max_fields.div_ceil(8)];
+    for (index, active) in active_fields.enumerate() {
+        if active {
+            bytes[index / 8] |= 1 << (index % 8);
+        }
+    }
+    bytes
+}
+
+/// The root of a stable container of `max_fields` slots with the given field roots, `None` for
+/// the absent fields. Slots past the end of `field_roots` are absent.
+pub fn stable_container_root(max_fields: usize, field_roots: &[Option<Hash256>]) -> Hash256 {
+    assert!(
+        field_roots.len() <= max_fields,
+        "Stable container has {} fields but only {max_fields} slots",
+        field_roots.len()
+    );
+
+    let leaves = field_roots
+        .iter()
+        .flat_map(|root| root.unwrap_or_default().0)
+        .collect::<Vec<_>>();
+    let data_root = merkle_root(&leaves, max_fields);
+    let active_fields_root =
+        active_fields_root(max_fields, field_roots.iter().map(Option::is_some));
+    merkle_root(&[data_root.0, active_fields_root.0].concat(), 2)
+}
+
+fn active_fields_root(max_fields: usize, active_fields: impl Iterator<Item = bool>) -> Hash256 {
+    merkle_root(
+        &active_fields_bytes(max_fields, active_fields),
+        max_fields.div_ceil(BYTES_PER_CHUNK * 8),
+    )
+}
+
+/// The root of a profile whose fields are all required, which is the root of its stable container
+/// with every field present.
+pub fn profile_root(max_fields: usize, field_roots: &[Hash256]) -> Hash256 {
+    stable_container_root(
+        max_fields,
+        &field_roots.iter().copied().map(Some).collect::<Vec<_>>(),
+    )
+}
+
+/// Returns the Merkle proof of the field at `index` against the root of a profile whose fields are
+/// all required. The proof runs through the field slots, and ends with the root of the active
+/// fields which is mixed in on top.
+pub fn profile_proof(
+    max_fields: usize,
+    field_roots: &[Hash256],
+    index: u64,
+) -> anyhow::Result<Vec<B256>> {
+    let depth = u64::from(max_fields.next_power_of_two().trailing_zeros());
+    let mut proof = generate_proof(&merkle_tree(field_roots, depth)?, index, depth)?;
+    proof.push(active_fields_root(
+        max_fields,
+        field_roots.iter().map(|_| true),
+    ));
+    Ok(proof)
+}
+
+/// Encodes a stable container field by field, in slot order.
+#[derive(Debug)]
+pub struct StableContainerEncoder {
+    max_fields: usize,
+    active_fields: Vec<bool>,
+    /// The encoding of each present field, and whether it has a fixed length.
+    fields: Vec<(bool, Vec<u8>)>,
+}
+
+impl StableContainerEncoder {
+    pub fn new(max_fields: usize) -> Self {
+        Self {
+            max_fields,
+            active_fields: vec![],
+            fields: vec![],
+        }
+    }
+
+    /// Appends the field of the next slot, `None` if it is absent.
+    pub fn field<T: Encode>(mut self, value: Option<&T>) -> Self {
+        assert!(
+            self.active_fields.len() < self.max_fields,
+            "Stable container only has {} slots",
+            self.max_fields
+        );
+        self.active_fields.push(value.is_some());
+        if let Some(value) = value {
+            self.fields
+                .push((T::is_ssz_fixed_len(), value.as_ssz_bytes()));
+        }
+        self
+    }
+
+    pub fn finish(self) -> Vec<u8> {
+        let mut bytes = active_fields_bytes(self.max_fields, self.active_fields.into_iter());
+        let fixed_length = self
+            .fields
+            .iter()
+            .map(|(is_fixed, field)| {
+                if *is_fixed {
+                    field.len()
+                } else {
+                    BYTES_PER_LENGTH_OFFSET
+                }
+            })
+            .sum::<usize>();
+
+        let mut variable_bytes = vec![];
+        for (is_fixed, field) in &self.fields {
+            if *is_fixed {
+                bytes.extend_from_slice(field);
+            } else {
+                bytes.extend_from_slice(&ssz::encode_length(fixed_length + variable_bytes.len()));
+                variable_bytes.extend_from_slice(field);
+            }
+        }
+        bytes.extend(variable_bytes);
+        bytes
+    }
+}
+
+/// Splits the encoding of a stable container of `max_fields` slots into which slots are present,
+/// and the encoding of the present fields, which decodes like a container of just those fields
+/// with [ssz::SszDecoderBuilder].
+///
+/// Fails if a slot at or past `known_fields` is present, as this version can't decode it.
+pub fn decode_active_fields(
+    max_fields: usize,
+    known_fields: usize,
+    bytes: &[u8],
+) -> Result<(Vec<bool>, &[u8]), DecodeError> {
+    let bitvector_length = max_fields.div_ceil(8);
+    if bytes.len() < bitvector_length {
+        return Err(DecodeError::InvalidByteLength {
+            len: bytes.len(),
+            expected: bitvector_length,
+        });
+    }
+    let (bitvector, fields) = bytes.split_at(bitvector_length);
+
+    let active_fields = (0..bitvector_length * 8)
+        .map(|index| bitvector[index / 8] & (1 << (index % 8)) != 0)
+        .collect::<Vec<_>>();
+    if let Some(index) = active_fields[known_fields..]
+        .iter()
+        .position(|active| *active)
+    {
+        return Err(DecodeError::BytesInvalid(format!(
+            "Stable container has unknown field {}",
+            known_fields + index
+        )));
+    }
+
+    Ok((active_fields[..known_fields].to_vec(), fields))
+}
+
+/// Implements [TreeHash] as a profile with the fields `$field_roots` returns present, and the
+/// slots after them absent.
+macro_rules! impl_profile_tree_hash {
+    ($type:ty, $max_fields:expr, $field_roots:expr) => {
+        impl TreeHash for $type {
+            fn tree_hash_type() -> TreeHashType {
+                TreeHashType::Container
+            }
+
+            fn tree_hash_packed_encoding(&self) -> PackedEncoding {
+                unreachable!("Struct should never be packed.")
+            }
+
+            fn tree_hash_packing_factor() -> usize {
+                unreachable!("Struct should never be packed.")
+            }
+
+            fn tree_hash_root(&self) -> Hash256 {
+                let field_roots: fn(&$type) -> Vec<Hash256> = $field_roots;
+                profile_root($max_fields, &field_roots(self))
+            }
+        }
+    };
+}
+
+impl_profile_tree_hash!(Block, BLOCK_MAX_FIELDS, |block| vec![
+    block.slot.tree_hash_root(),
+    block.proposer_index.tree_hash_root(),
+    block.parent_root.tree_hash_root(),
+    block.state_root.tree_hash_root(),
+    block.body.tree_hash_root(),
+]);
+
+// The header is a view of the block with the body replaced by its root, so it shares its slots.
+impl_profile_tree_hash!(BlockHeader, BLOCK_MAX_FIELDS, |header| vec![
+    header.slot.tree_hash_root(),
+    header.proposer_index.tree_hash_root(),
+    header.parent_root.tree_hash_root(),
+    header.state_root.tree_hash_root(),
+    header.body_root,
+]);
+
+impl_profile_tree_hash!(BlockBody, BLOCK_BODY_MAX_FIELDS, BlockBody::field_roots);
+
+#[cfg(feature = "parallel_tree_hash")]
+impl_profile_tree_hash!(
+    LeanState,
+    STATE_MAX_FIELDS,
+    crate::parallel_tree_hash::state_field_roots
+);
+
+#[cfg(not(feature = "parallel_tree_hash"))]
+impl_profile_tree_hash!(LeanState, STATE_MAX_FIELDS, LeanState::field_roots);
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ssz::{Decode, Encode, SszDecoderBuilder};
+    use ssz_types::{VariableList, typenum::U16};
+    use tree_hash::TreeHash;
+
+    use super::{
+        BLOCK_BODY_MAX_FIELDS, StableContainerEncoder, decode_active_fields, profile_root,
+        stable_container_root,
+    };
+    use crate::block::BlockBody;
+
+    #[test]
+    fn test_absent_fields_keep_root() {
+        let roots = [B256::repeat_byte(1), B256::repeat_byte(2)];
+        let root = profile_root(8, &roots);
+
+        // A later fork adding a field which is absent doesn't change the root.
+        assert_eq!(
+            stable_container_root(8, &[Some(roots[0]), Some(roots[1]), None]),
+            root
+        );
+        assert_ne!(
+            stable_container_root(8, &[Some(roots[0]), None]),
+            stable_container_root(8, &[Some(roots[0]), Some(B256::ZERO)])
+        );
+        assert_ne!(root, profile_root(16, &roots));
+    }
+
+    #[test]
+    fn test_encode_decode_stable_container() {
+        let list = VariableList::<u64, U16>::try_from(vec![3, 4]).unwrap();
+        let bytes = StableContainerEncoder::new(4)
+            .field(Some(&1_u64))
+            .field::<u64>(None)
+            .field(Some(&list))
+            .finish();
+
+        let (active_fields, fields) = decode_active_fields(4, 3, &bytes).unwrap();
+        assert_eq!(active_fields, vec![true, false, true]);
+
+        let mut builder = SszDecoderBuilder::new(fields);
+        builder.register_type::<u64>().unwrap();
+        builder.register_type::<VariableList<u64, U16>>().unwrap();
+        let mut decoder = builder.build().unwrap();
+        assert_eq!(decoder.decode_next::<u64>().unwrap(), 1);
+        assert_eq!(
+            decoder.decode_next::<VariableList<u64, U16>>().unwrap(),
+            list
+        );
+
+        // A field of a later fork can't be decoded.
+        assert!(decode_active_fields(4, 2, &bytes).is_err());
+    }
+
+    #[test]
+    fn test_block_body_profile_root() {
+        let body = BlockBody::default();
+        // Without validator operations, the Devnet3 fields are absent.
+        assert_eq!(
+            body.tree_hash_root(),
+            stable_container_root(
+                BLOCK_BODY_MAX_FIELDS,
+                &[Some(body.attestations.tree_hash_root()), None, None, None]
+            )
+        );
+        // Profiles whose fields are all required encode like containers.
+        assert_eq!(BlockBody::from_ssz_bytes(&body.as_ssz_bytes()), Ok(body));
+    }
+}
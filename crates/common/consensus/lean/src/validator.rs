@@ -1,8 +1,12 @@
-use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+use ream_post_quantum_crypto::leansig::{public_key::PublicKey, signature::Signature};
 use serde::{Deserialize, Serialize};
 use ssz_derive::{Decode, Encode};
+use tree_hash::TreeHash;
 use tree_hash_derive::TreeHash;
 
+/// The exit slot of a validator which hasn't exited.
+pub const FAR_FUTURE_SLOT: u64 = u64::MAX;
+
 /// Represents a validator entry in the Lean chain.
 #[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
 pub struct Validator {
@@ -11,6 +15,133 @@ pub struct Validator {
     pub index: u64,
 }
 
-pub fn is_proposer(validator_index: u64, slot: u64, validator_count: u64) -> bool {
-    slot % validator_count == validator_index
+/// What the Devnet3 fork tracks of a validator on top of its [Validator] entry, for networks with
+/// a dynamic validator set.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct ValidatorStatus {
+    /// The slot the validator exits at, [FAR_FUTURE_SLOT] while no exit is scheduled.
+    pub exit_slot: u64,
+    /// The key the validator signs with from `next_public_key_slot` on, after a [KeyRotation].
+    pub next_public_key: PublicKey,
+    /// [FAR_FUTURE_SLOT] while no key rotation is pending.
+    pub next_public_key_slot: u64,
+}
+
+impl Default for ValidatorStatus {
+    fn default() -> Self {
+        Self {
+            exit_slot: FAR_FUTURE_SLOT,
+            next_public_key: PublicKey::default(),
+            next_public_key_slot: FAR_FUTURE_SLOT,
+        }
+    }
+}
+
+impl ValidatorStatus {
+    pub fn is_active(&self, slot: u64) -> bool {
+        slot < self.exit_slot
+    }
+
+    pub fn has_scheduled_exit(&self) -> bool {
+        self.exit_slot != FAR_FUTURE_SLOT
+    }
+
+    pub fn has_pending_key_rotation(&self) -> bool {
+        self.next_public_key_slot != FAR_FUTURE_SLOT
+    }
+}
+
+/// Adds a validator with `public_key` to the state, at the next validator index.
+///
+/// Lean blocks don't have deposits yet, so on devnets with a dynamic validator set the proposer
+/// decides which registrations to include.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct ValidatorRegistration {
+    #[serde(rename = "pubkey")]
+    pub public_key: PublicKey,
+    /// The epoch the registration is signed at, before the block including it. The validator
+    /// only has duties after it is registered, so its key never signs a duty at this epoch.
+    pub slot: u64,
+}
+
+/// A [ValidatorRegistration] signed by the key it registers, which proves the registrant holds
+/// the key, so nobody can register a key of someone else.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct SignedValidatorRegistration {
+    pub message: ValidatorRegistration,
+    pub signature: Signature,
+}
+
+impl SignedValidatorRegistration {
+    /// Verifies that the registered key signed the registration.
+    pub fn verify(&self) -> anyhow::Result<bool> {
+        self.signature.verify(
+            &self.message.public_key,
+            self.message.slot as u32,
+            &self.message.tree_hash_root(),
+        )
+    }
+}
+
+/// Exits the validator at `validator_index` from `exit_slot` on. It stops proposing and its
+/// attestations no longer count towards justification from then.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct ValidatorExit {
+    pub validator_index: u64,
+    pub exit_slot: u64,
+}
+
+/// A [ValidatorExit] authorized by the key of the exiting validator.
+///
+/// The key signs at the epoch of `exit_slot`, which it never signs a duty at, as the validator
+/// has no duties from that slot on.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct SignedValidatorExit {
+    pub message: ValidatorExit,
+    pub signature: Signature,
+}
+
+impl SignedValidatorExit {
+    /// Verifies that `public_key`, the key of the validator at `exit_slot`, signed the exit.
+    pub fn verify(&self, public_key: &PublicKey) -> anyhow::Result<bool> {
+        self.signature.verify(
+            public_key,
+            self.message.exit_slot as u32,
+            &self.message.tree_hash_root(),
+        )
+    }
+}
+
+/// Replaces the key of the validator at `validator_index` with `new_public_key` from
+/// `activation_slot` on.
+///
+/// XMSS keys can only sign at the epochs of their activation interval, so a validator rotates to
+/// a new key before its key runs out.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct KeyRotation {
+    pub validator_index: u64,
+    #[serde(rename = "new_pubkey")]
+    pub new_public_key: PublicKey,
+    pub activation_slot: u64,
+}
+
+/// A [KeyRotation] authorized by the key being replaced.
+///
+/// The old key signs at the epoch of `activation_slot`, which it never signs a duty at, as the
+/// new key takes over from that slot on. Signing twice at an epoch would weaken the key.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize, Deserialize, Encode, Decode, TreeHash)]
+pub struct SignedKeyRotation {
+    pub message: KeyRotation,
+    pub signature: Signature,
+}
+
+impl SignedKeyRotation {
+    /// Verifies that `public_key`, the current key of the validator, signed the rotation.
+    pub fn verify(&self, public_key: &PublicKey) -> anyhow::Result<bool> {
+        self.signature.verify(
+            public_key,
+            self.message.activation_slot as u32,
+            &self.message.tree_hash_root(),
+        )
+    }
 }
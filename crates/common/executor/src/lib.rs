@@ -1,28 +1,51 @@
 use std::{future::Future, sync::Arc, thread::sleep, time::Duration};
 
 use anyhow::bail;
-use tokio::{runtime::Runtime, sync::broadcast, task::JoinHandle};
+use tokio::{
+    runtime::Runtime,
+    sync::{broadcast, watch},
+    task::JoinHandle,
+    time::timeout,
+};
 use tracing::warn;
 
 #[derive(Clone)]
 pub struct ReamExecutor {
     runtime: Arc<Runtime>,
     shutdown: broadcast::Sender<()>,
+    graceful_shutdown: watch::Sender<bool>,
+}
+
+/// Observed by services spawned with [ReamExecutor::spawn_graceful], so they can finish their
+/// in-flight work and exit on their own once the node starts shutting down.
+#[derive(Clone)]
+pub struct ShutdownSignal(watch::Receiver<bool>);
+
+impl ShutdownSignal {
+    pub fn is_shutting_down(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Resolves once the node starts shutting down.
+    pub async fn wait(&mut self) {
+        // The sender is only dropped with the executor, which is a shutdown as well.
+        let _ = self.0.wait_for(|shutting_down| *shutting_down).await;
+    }
 }
 
 impl ReamExecutor {
     pub fn new() -> std::io::Result<Self> {
-        let runtime = Arc::new(Runtime::new()?);
-        let (shutdown, _) = broadcast::channel(1);
-        Ok(Self { runtime, shutdown })
+        Ok(Self::with_runtime(Runtime::new()?))
     }
 
     /// Creates a new TaskExecutor with an existing runtime
     pub fn with_runtime(runtime: Runtime) -> Self {
         let (shutdown, _) = broadcast::channel(1);
+        let (graceful_shutdown, _) = watch::channel(false);
         Self {
             runtime: Arc::new(runtime),
             shutdown,
+            graceful_shutdown,
         }
     }
 
@@ -58,6 +81,25 @@ impl ReamExecutor {
         })
     }
 
+    /// Spawns a task which isn't cancelled on shutdown. Instead it is handed a [ShutdownSignal] and
+    /// is expected to clean up and return once it fires, see
+    /// [ReamExecutor::wait_for_graceful_shutdown].
+    pub fn spawn_graceful<F, Fut, T>(&self, future_fn: F) -> JoinHandle<T>
+    where
+        F: FnOnce(ShutdownSignal) -> Fut + Send + 'static,
+        Fut: Future<Output = T> + Send + 'static,
+        T: Send + 'static,
+    {
+        self.runtime
+            .spawn(future_fn(self.graceful_shutdown_signal()))
+    }
+
+    /// Returns a [ShutdownSignal] for a service which is driven by a task the caller spawns. The
+    /// graceful shutdown waits for it to be dropped as well.
+    pub fn graceful_shutdown_signal(&self) -> ShutdownSignal {
+        ShutdownSignal(self.graceful_shutdown.subscribe())
+    }
+
     /// Spawns a blocking task in a dedicated thread pool
     pub fn spawn_blocking<F, R>(&self, task: F) -> JoinHandle<R>
     where
@@ -90,11 +132,20 @@ impl ReamExecutor {
 
     /// Triggers a shutdown signal to all spawned tasks
     pub fn shutdown_signal(&self) {
+        self.graceful_shutdown.send_replace(true);
         if let Err(err) = self.shutdown.send(()) {
             warn!("Failed to send shutdown signal: {err}");
         }
     }
 
+    /// Waits up to `duration` for every task spawned with [ReamExecutor::spawn_graceful] to
+    /// return, which is when the last [ShutdownSignal] is dropped. Returns `false` on timeout.
+    pub async fn wait_for_graceful_shutdown(&self, duration: Duration) -> bool {
+        timeout(duration, self.graceful_shutdown.closed())
+            .await
+            .is_ok()
+    }
+
     pub fn shutdown_runtime(self) {
         sleep(Duration::from_secs(5));
 
@@ -155,6 +206,26 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_graceful_task() {
+        let executor = ReamExecutor::new().unwrap();
+
+        let handle = executor.spawn_graceful(|mut shutdown| async move {
+            shutdown.wait().await;
+            // Work done after the signal still completes.
+            sleep(Duration::from_millis(50)).await;
+            shutdown.is_shutting_down()
+        });
+
+        executor.shutdown_signal();
+        assert!(
+            executor
+                .runtime
+                .block_on(executor.wait_for_graceful_shutdown(Duration::from_secs(1)))
+        );
+        assert!(executor.runtime.block_on(handle).unwrap());
+    }
+
     #[test]
     fn test_spawn_many() {
         let executor = ReamExecutor::new().unwrap();
@@ -0,0 +1,58 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use ream_consensus_lean::attestation::{Attestation, SignedAttestation};
+use ream_fork_choice_lean::store::Store;
+use ream_network_spec::networks::{LeanNetworkSpec, set_lean_network_spec};
+use ream_post_quantum_crypto::leansig::signature::Signature;
+use ream_test_utils::{db_setup, genesis};
+use tokio::runtime::Runtime;
+
+const VALIDATOR_COUNT: usize = 4096;
+
+/// A store where every validator has a known attestation, all of which can be included in the
+/// next block.
+fn sample_store() -> Store {
+    set_lean_network_spec(LeanNetworkSpec::ephemery().into());
+    let (genesis_block, genesis_state) = genesis(VALIDATOR_COUNT);
+    // The proposer of genesis attests to it as head, target and source.
+    let attestation_data = genesis_block.message.proposer_attestation.data.clone();
+    let store = Store::get_forkchoice_store(genesis_block, genesis_state, db_setup(), None)
+        .expect("Failed to create store");
+
+    store
+        .store
+        .latest_known_attestations_provider()
+        .batch_insert((0..VALIDATOR_COUNT as u64).map(|validator_id| {
+            (
+                validator_id,
+                SignedAttestation {
+                    message: Attestation {
+                        validator_id,
+                        data: attestation_data.clone(),
+                    },
+                    signature: Signature::blank(),
+                },
+            )
+        }))
+        .expect("Failed to insert attestations");
+    store
+}
+
+fn bench_attestation_packing(c: &mut Criterion) {
+    let store = sample_store();
+    let runtime = Runtime::new().expect("Failed to build runtime");
+
+    c.bench_function("produce_block_with_4096_attestations", |b| {
+        b.iter(|| {
+            let block_with_signatures = runtime
+                .block_on(store.produce_block_with_signatures(1, 1))
+                .expect("Failed to produce block");
+            assert_eq!(
+                block_with_signatures.block.body.attestations.len(),
+                VALIDATOR_COUNT
+            );
+        })
+    });
+}
+
+criterion_group!(benches, bench_attestation_packing);
+criterion_main!(benches);
@@ -0,0 +1,107 @@
+use std::sync::Arc;
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ream_fork_choice_lean::store::Store;
+use ream_network_spec::networks::{LeanNetworkSpec, set_lean_network_spec};
+use ream_storage::{
+    db::{ReamDB, lean::LeanDB},
+    tables::{field::REDBField, table::REDBTable},
+};
+use ream_test_utils::genesis;
+use tempdir::TempDir;
+use tokio::{runtime::Runtime, sync::Mutex, task::JoinSet};
+
+const READS_PER_TASK: usize = 100;
+const READER_COUNTS: [usize; 4] = [1, 2, 4, 8];
+
+/// A store on disk in `dir`, so the reads and writes contend like on a running node.
+fn sample_store(dir: &TempDir) -> Store {
+    set_lean_network_spec(LeanNetworkSpec::ephemery().into());
+    let (genesis_block, genesis_state) = genesis(10);
+    let lean_db = ReamDB::new(dir.path().to_path_buf())
+        .expect("Failed to open database")
+        .init_lean_db()
+        .expect("Failed to init lean tables");
+    Store::get_forkchoice_store(genesis_block, genesis_state, lean_db, None)
+        .expect("Failed to create store")
+}
+
+/// The reads an RPC handler or gossip validation does for a request.
+fn read_head_block(lean_db: &LeanDB) {
+    let head = lean_db.head_provider().get().expect("Failed to read head");
+    lean_db
+        .block_provider()
+        .get(head)
+        .expect("Failed to read head block")
+        .expect("Head block is missing");
+}
+
+/// The write fork choice does on every interval tick.
+fn advance_time(lean_db: &LeanDB) {
+    let time_provider = lean_db.time_provider();
+    let time = time_provider.get().expect("Failed to read time");
+    time_provider
+        .insert(time + 1)
+        .expect("Failed to write time");
+}
+
+/// Runs `readers` tasks doing head lookups, next to a writer advancing the store time, first
+/// with the database behind a single mutex as it used to be, then with shared handles.
+fn bench_concurrent_reads(c: &mut Criterion) {
+    let dir = TempDir::new("contention_bench").expect("Failed to create temp dir");
+    let store = sample_store(&dir);
+    let locked_db = Arc::new(Mutex::new(store.store.clone()));
+    let runtime = Runtime::new().expect("Failed to build runtime");
+
+    let mut group = c.benchmark_group("concurrent_reads");
+    for readers in READER_COUNTS {
+        group.bench_with_input(
+            BenchmarkId::new("mutex", readers),
+            &readers,
+            |b, &readers| {
+                b.iter(|| {
+                    runtime.block_on(async {
+                        let mut tasks = JoinSet::new();
+                        let writer_db = locked_db.clone();
+                        tasks.spawn(async move { advance_time(&writer_db.lock().await) });
+                        for _ in 0..readers {
+                            let reader_db = locked_db.clone();
+                            tasks.spawn(async move {
+                                for _ in 0..READS_PER_TASK {
+                                    read_head_block(&reader_db.lock().await);
+                                }
+                            });
+                        }
+                        tasks.join_all().await;
+                    })
+                })
+            },
+        );
+        group.bench_with_input(
+            BenchmarkId::new("shared", readers),
+            &readers,
+            |b, &readers| {
+                b.iter(|| {
+                    runtime.block_on(async {
+                        let mut tasks = JoinSet::new();
+                        let writer_db = store.store.clone();
+                        tasks.spawn(async move { advance_time(&writer_db) });
+                        for _ in 0..readers {
+                            let reader_db = store.store.clone();
+                            tasks.spawn(async move {
+                                for _ in 0..READS_PER_TASK {
+                                    read_head_block(&reader_db);
+                                }
+                            });
+                        }
+                        tasks.join_all().await;
+                    })
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_concurrent_reads);
+criterion_main!(benches);
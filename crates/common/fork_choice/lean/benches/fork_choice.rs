@@ -0,0 +1,172 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ream_consensus_lean::{
+    attestation::{Attestation, AttestationData, SignedAttestation},
+    checkpoint::Checkpoint,
+};
+use ream_fork_choice_lean::store::Store;
+use ream_network_spec::networks::{LeanNetworkSpec, set_lean_network_spec};
+use ream_post_quantum_crypto::leansig::signature::Signature;
+use ream_storage::tables::{field::REDBField, table::REDBTable};
+use ream_test_utils::{chain::ChainBuilder, db_setup, genesis};
+use tokio::runtime::Runtime;
+
+const VALIDATOR_COUNT: usize = 4096;
+const BLOCK_COUNTS: [u64; 2] = [1_000, 10_000];
+/// How many of the latest blocks the attestations are spread over.
+const ATTESTED_BLOCKS: u64 = 32;
+/// How far behind the tip the latest justified checkpoint is. Attestations only count towards
+/// the blocks after it.
+const JUSTIFIED_DISTANCE: u64 = 64;
+
+/// An in-memory store with a chain of `block_count` blocks on top of genesis, where every eighth
+/// block has a sibling one slot later nobody attests to. Every validator has a known and a new
+/// attestation for one of the latest blocks, the latest justified checkpoint is
+/// [JUSTIFIED_DISTANCE] blocks behind the tip, and the head is still genesis. Returns the tip too.
+fn sample_store(block_count: u64) -> (Store, Checkpoint) {
+    set_lean_network_spec(LeanNetworkSpec::ephemery().into());
+    let (genesis_block, genesis_state) = genesis(VALIDATOR_COUNT);
+    let genesis_checkpoint = genesis_block.message.proposer_attestation.data.head;
+    let store = Store::get_forkchoice_store(
+        genesis_block.clone(),
+        genesis_state.clone(),
+        db_setup(),
+        None,
+    )
+    .expect("Failed to create store");
+
+    let mut chain_builder = ChainBuilder::new(genesis_block, genesis_state);
+    let mut chain = vec![genesis_checkpoint];
+    for slot in 1..=block_count {
+        let parent_root = chain[slot as usize - 1].root;
+        let root = chain_builder
+            .build_block(parent_root, slot, vec![])
+            .expect("Failed to build block");
+        if slot % 8 == 0 {
+            let sibling_root = chain_builder
+                .build_block(parent_root, slot + 1, vec![])
+                .expect("Failed to build sibling block");
+            chain_builder.forget_state(sibling_root);
+        }
+        // Only the tip is built on, so the states behind it aren't needed anymore.
+        chain_builder.forget_state(parent_root);
+        chain.push(Checkpoint { root, slot });
+    }
+    chain_builder
+        .store_blocks(&store.store)
+        .expect("Failed to insert blocks");
+
+    let attestations = (0..VALIDATOR_COUNT as u64)
+        .map(|validator_id| {
+            let head = chain[(block_count - validator_id % ATTESTED_BLOCKS) as usize];
+            (
+                validator_id,
+                SignedAttestation {
+                    message: Attestation {
+                        validator_id,
+                        data: AttestationData {
+                            slot: head.slot,
+                            head,
+                            target: head,
+                            source: genesis_checkpoint,
+                        },
+                    },
+                    signature: Signature::blank(),
+                },
+            )
+        })
+        .collect::<Vec<_>>();
+    let new_attestations_provider = store.store.latest_new_attestations_provider();
+    for (validator_id, attestation) in &attestations {
+        new_attestations_provider
+            .insert(*validator_id, attestation.clone())
+            .expect("Failed to insert new attestation");
+    }
+    store
+        .store
+        .latest_known_attestations_provider()
+        .batch_insert(attestations)
+        .expect("Failed to insert known attestations");
+    store
+        .store
+        .latest_justified_provider()
+        .insert(chain[(block_count - JUSTIFIED_DISTANCE) as usize])
+        .expect("Failed to set latest justified");
+
+    (store, chain[block_count as usize])
+}
+
+fn bench_compute_head(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Failed to build runtime");
+    let mut group = c.benchmark_group("compute_lmd_ghost_head");
+    group.sample_size(10);
+    for block_count in BLOCK_COUNTS {
+        let (store, _) = sample_store(block_count);
+        group.bench_with_input(
+            BenchmarkId::new("blocks", block_count),
+            &block_count,
+            |b, _| {
+                b.iter(|| {
+                    runtime
+                        .block_on(store.update_head())
+                        .expect("Failed to update head")
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_update_safe_target(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Failed to build runtime");
+    let mut group = c.benchmark_group("update_safe_target");
+    group.sample_size(10);
+    for block_count in BLOCK_COUNTS {
+        let (store, _) = sample_store(block_count);
+        group.bench_with_input(
+            BenchmarkId::new("blocks", block_count),
+            &block_count,
+            |b, _| {
+                b.iter(|| {
+                    runtime
+                        .block_on(store.update_safe_target())
+                        .expect("Failed to update safe target")
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+fn bench_get_attestation_target(c: &mut Criterion) {
+    let runtime = Runtime::new().expect("Failed to build runtime");
+    let mut group = c.benchmark_group("get_attestation_target");
+    group.sample_size(10);
+    for block_count in BLOCK_COUNTS {
+        let (store, tip) = sample_store(block_count);
+        store
+            .store
+            .head_provider()
+            .insert(tip.root)
+            .expect("Failed to set head");
+        group.bench_with_input(
+            BenchmarkId::new("blocks", block_count),
+            &block_count,
+            |b, _| {
+                b.iter(|| {
+                    runtime
+                        .block_on(store.get_attestation_target())
+                        .expect("Failed to get attestation target")
+                })
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_compute_head,
+    bench_update_safe_target,
+    bench_get_attestation_target
+);
+criterion_main!(benches);
@@ -0,0 +1,69 @@
+use std::sync::Arc;
+
+use alloy_primitives::B256;
+use parking_lot::Mutex;
+use ream_consensus_lean::state::LeanState;
+
+/// The post state of a block, advanced to a later slot ahead of time so importing or producing
+/// the block of that slot doesn't process the slot on the critical path.
+///
+/// Only the latest advanced state is kept, handles are cheap to clone and share it.
+#[derive(Debug, Clone, Default)]
+pub struct AdvancedStateCache {
+    inner: Arc<Mutex<Option<AdvancedState>>>,
+}
+
+#[derive(Debug)]
+struct AdvancedState {
+    block_root: B256,
+    state: LeanState,
+}
+
+impl AdvancedStateCache {
+    /// Caches `state`, the post state of the block `block_root` advanced to `state.slot`.
+    pub fn insert(&self, block_root: B256, state: LeanState) {
+        *self.inner.lock() = Some(AdvancedState { block_root, state });
+    }
+
+    /// Returns whether the post state of the block `block_root` advanced to `slot` is cached.
+    pub fn contains(&self, block_root: B256, slot: u64) -> bool {
+        self.inner.lock().as_ref().is_some_and(|advanced| {
+            advanced.block_root == block_root && advanced.state.slot == slot
+        })
+    }
+
+    /// Returns a copy of the post state of the block `block_root` advanced to `slot`, if it's
+    /// cached.
+    pub fn get(&self, block_root: B256, slot: u64) -> Option<LeanState> {
+        self.inner
+            .lock()
+            .as_ref()
+            .filter(|advanced| advanced.block_root == block_root && advanced.state.slot == slot)
+            .map(|advanced| advanced.state.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ream_consensus_lean::state::LeanState;
+    use ream_network_spec::networks::initialize_test_lean_network_spec;
+
+    use super::AdvancedStateCache;
+
+    #[test]
+    fn test_advanced_state_cache() {
+        initialize_test_lean_network_spec();
+        let cache = AdvancedStateCache::default();
+        let block_root = B256::repeat_byte(1);
+        let mut state = LeanState::generate_genesis(0, None);
+        state.process_slots(2).unwrap();
+
+        assert!(cache.get(block_root, 2).is_none());
+        cache.clone().insert(block_root, state.clone());
+        assert!(cache.contains(block_root, 2));
+        assert_eq!(cache.get(block_root, 2), Some(state));
+        assert!(cache.get(block_root, 3).is_none());
+        assert!(cache.get(B256::repeat_byte(2), 2).is_none());
+    }
+}
@@ -0,0 +1,67 @@
+use std::{collections::HashMap, sync::Arc};
+
+use alloy_primitives::B256;
+use parking_lot::RwLock;
+
+/// The slot and parent root of blocks, so walking parent links for ancestor queries doesn't read
+/// and decode every block on the way from the database.
+///
+/// Blocks never change, so an entry stays valid until it is pruned. Handles are cheap to clone
+/// and share the entries.
+#[derive(Debug, Clone, Default)]
+pub struct AncestorCache {
+    links: Arc<RwLock<HashMap<B256, (u64, B256)>>>,
+}
+
+impl AncestorCache {
+    /// Caches the slot and parent root of the block `block_root`.
+    pub fn insert(&self, block_root: B256, slot: u64, parent_root: B256) {
+        self.links.write().insert(block_root, (slot, parent_root));
+    }
+
+    /// Returns the slot and parent root of the block `block_root`, if it's cached.
+    pub fn get(&self, block_root: B256) -> Option<(u64, B256)> {
+        self.links.read().get(&block_root).copied()
+    }
+
+    /// Drops the blocks before `finalized_slot`. Queries reaching past the finalized block are
+    /// rare, and fall back to the database.
+    pub fn prune(&self, finalized_slot: u64) {
+        self.links
+            .write()
+            .retain(|_, (slot, _)| *slot >= finalized_slot);
+    }
+
+    pub fn len(&self) -> usize {
+        self.links.read().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.links.read().is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+
+    use super::AncestorCache;
+
+    #[test]
+    fn test_prune_ancestor_cache() {
+        let cache = AncestorCache::default();
+        cache.insert(B256::repeat_byte(1), 1, B256::ZERO);
+        cache
+            .clone()
+            .insert(B256::repeat_byte(2), 2, B256::repeat_byte(1));
+        assert_eq!(
+            cache.get(B256::repeat_byte(2)),
+            Some((2, B256::repeat_byte(1)))
+        );
+
+        cache.prune(2);
+        assert_eq!(cache.len(), 1);
+        assert!(cache.get(B256::repeat_byte(1)).is_none());
+        assert!(cache.get(B256::repeat_byte(2)).is_some());
+    }
+}
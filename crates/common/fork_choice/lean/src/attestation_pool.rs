@@ -0,0 +1,429 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use parking_lot::Mutex;
+use ream_consensus_lean::attestation::{SignedAggregatedAttestation, SignedAttestation};
+use ream_metrics::{
+    ATTESTATION_POOL_SIZE, ATTESTATIONS_DROPPED_TOTAL, inc_int_counter_vec, inc_int_counter_vec_by,
+    set_int_gauge_vec,
+};
+use ream_storage::{
+    db::{lean::LeanDB, write_batch::WriteBatch},
+    errors::StoreError,
+    tables::table::REDBTable,
+};
+use tracing::info;
+use tree_hash::TreeHash;
+
+use crate::{
+    constants::MAX_ATTESTATION_POOL_SIZE,
+    rejection::{ATTESTATION, RejectReason, record_rejected},
+};
+
+/// The latest attestation of each validator, kept in two pools: the new attestations received
+/// since they were last accepted, and the known attestations fork choice counts.
+///
+/// Each pool holds at most `max_size` attestations, or one for every validator if there are more
+/// validators than that, so the attestations of registered validators are never dropped. Once
+/// full, attestations of validators without one in the pool are dropped, and attestations for
+/// slots before the finalized slot are evicted as they can no longer move the head.
+///
+/// Every write to the pools holds one lock, so a pool can't grow past its limit between checking
+/// its size and inserting.
+///
+/// New attestations from gossip can be buffered in memory and written in batches, see
+/// [AttestationPool::with_batch_size].
+#[derive(Debug, Clone)]
+pub struct AttestationPool {
+    db: LeanDB,
+    max_size: u64,
+    batch_size: usize,
+    validator_count: Arc<AtomicU64>,
+    state: Arc<Mutex<PoolState>>,
+}
+
+#[derive(Debug, Default)]
+struct PoolState {
+    /// New attestations which haven't been written to the database yet.
+    pending: HashMap<u64, SignedAttestation>,
+    /// How many of them are of validators without a new attestation in the database, so they
+    /// grow the pool once written.
+    added: u64,
+    /// How many new and known attestations are in the database. The pools are only written
+    /// under the lock, so they are counted as they change instead of read back after each write.
+    new_count: u64,
+    known_count: u64,
+}
+
+impl AttestationPool {
+    pub fn new(db: LeanDB) -> Self {
+        Self {
+            db,
+            max_size: MAX_ATTESTATION_POOL_SIZE,
+            batch_size: 1,
+            validator_count: Arc::default(),
+            state: Arc::default(),
+        }
+    }
+
+    pub fn with_max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// Buffers up to `batch_size` new attestations from gossip and writes them in a single
+    /// transaction, instead of one durable transaction each. The buffer is also written on every
+    /// interval tick by [AttestationPool::flush_pending], and before anything reads the new
+    /// attestations, so at most one interval or `batch_size` attestations are lost on a crash.
+    /// A `batch_size` of 1, the default, writes every attestation immediately.
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Makes room for the attestations of `validator_count` validators in each pool, for a state
+    /// with that many validators. The pools never shrink again.
+    pub fn observe_validator_count(&self, validator_count: u64) {
+        self.validator_count
+            .fetch_max(validator_count, Ordering::Relaxed);
+    }
+
+    /// The most validators of any state seen, see [AttestationPool::observe_validator_count].
+    pub fn validator_count(&self) -> u64 {
+        self.validator_count.load(Ordering::Relaxed)
+    }
+
+    fn capacity(&self) -> u64 {
+        self.max_size.max(self.validator_count())
+    }
+
+    /// Writes the buffered new attestations to the database in a single transaction.
+    pub fn flush_pending(&self) -> anyhow::Result<()> {
+        let mut state = self.state.lock();
+        self.write_pending(&mut state)?;
+        record_size(&state);
+        Ok(())
+    }
+
+    fn write_pending(&self, state: &mut PoolState) -> anyhow::Result<()> {
+        if state.pending.is_empty() {
+            return Ok(());
+        }
+        self.db.with_write_batch(|batch| {
+            for (validator_id, signed_attestation) in &state.pending {
+                batch.insert_latest_new_attestation(*validator_id, signed_attestation)?;
+            }
+            Ok(())
+        })?;
+        state.pending.clear();
+        state.new_count += state.added;
+        state.added = 0;
+        Ok(())
+    }
+
+    /// Adds an attestation received over gossip to the new attestations, unless the validator
+    /// already has one for the same or a later slot.
+    pub fn insert_new(&self, signed_attestation: SignedAttestation) -> anyhow::Result<()> {
+        let validator_id = signed_attestation.message.validator_id;
+        let mut state = self.state.lock();
+        let latest_new = match state.pending.get(&validator_id) {
+            Some(latest_pending) => Some(latest_pending.clone()),
+            None => self
+                .db
+                .latest_new_attestations_provider()
+                .get(validator_id)?,
+        };
+        match latest_new {
+            Some(latest_new)
+                if latest_new.message.data.slot >= signed_attestation.message.data.slot =>
+            {
+                record_rejected(ATTESTATION, RejectReason::Duplicate);
+                return Ok(());
+            }
+            Some(_) => {}
+            None if state.new_count + state.added >= self.capacity() => {
+                inc_int_counter_vec(&ATTESTATIONS_DROPPED_TOTAL, &["pool_full"]);
+                return Ok(());
+            }
+            None => state.added += 1,
+        }
+        state.pending.insert(validator_id, signed_attestation);
+        if state.pending.len() >= self.batch_size {
+            self.write_pending(&mut state)?;
+        }
+        record_size(&state);
+        Ok(())
+    }
+
+    /// Adds the attestations included in a block to the known attestations in a single
+    /// transaction, except those of validators which already have one for the same or a later
+    /// slot. The new attestations they supersede have been included, so they are removed.
+    pub fn insert_from_block(
+        &self,
+        signed_attestations: Vec<SignedAttestation>,
+    ) -> anyhow::Result<()> {
+        self.insert_from_block_with(signed_attestations, |_| Ok(()))
+    }
+
+    /// Like [AttestationPool::insert_from_block], but also runs `write` in the same transaction,
+    /// so a block import commits the block together with its attestations. Nothing is written if
+    /// `write` fails.
+    pub fn insert_from_block_with(
+        &self,
+        signed_attestations: Vec<SignedAttestation>,
+        write: impl FnOnce(&WriteBatch) -> Result<(), StoreError>,
+    ) -> anyhow::Result<()> {
+        let mut state = self.state.lock();
+        self.write_pending(&mut state)?;
+        let (known_attestations, new_attestations) = (
+            self.db.latest_known_attestations_provider(),
+            self.db.latest_new_attestations_provider(),
+        );
+
+        // A block can include several attestations of a validator, so the attestations written
+        // by this batch are checked before the database.
+        let mut superseded_new = HashSet::new();
+        let mut latest_known = HashMap::<u64, SignedAttestation>::new();
+        let mut added = 0;
+        for signed_attestation in signed_attestations {
+            let validator_id = signed_attestation.message.validator_id;
+            let attestation_slot = signed_attestation.message.data.slot;
+
+            if !superseded_new.contains(&validator_id)
+                && new_attestations
+                    .get(validator_id)?
+                    .is_some_and(|latest_new| latest_new.message.data.slot <= attestation_slot)
+            {
+                superseded_new.insert(validator_id);
+            }
+
+            let known_slot = match latest_known.get(&validator_id) {
+                Some(latest_known) => Some(latest_known.message.data.slot),
+                None => known_attestations
+                    .get(validator_id)?
+                    .map(|latest_known| latest_known.message.data.slot),
+            };
+            match known_slot {
+                Some(known_slot) if known_slot >= attestation_slot => continue,
+                Some(_) => {}
+                None if state.known_count + added >= self.capacity() => {
+                    inc_int_counter_vec(&ATTESTATIONS_DROPPED_TOTAL, &["pool_full"]);
+                    continue;
+                }
+                None => added += 1,
+            }
+            latest_known.insert(validator_id, signed_attestation);
+        }
+
+        self.db.with_write_batch(|batch| {
+            write(batch)?;
+            for validator_id in &superseded_new {
+                batch.remove_latest_new_attestation(*validator_id)?;
+            }
+            for (validator_id, signed_attestation) in &latest_known {
+                batch.insert_latest_known_attestation(*validator_id, signed_attestation)?;
+            }
+            Ok(())
+        })?;
+        state.new_count = state.new_count.saturating_sub(superseded_new.len() as u64);
+        state.known_count += added;
+        record_size(&state);
+        Ok(())
+    }
+
+    /// Moves the new attestations into the known attestations.
+    pub fn accept_new(&self) -> anyhow::Result<()> {
+        let mut state = self.state.lock();
+        self.write_pending(&mut state)?;
+        let (dropped, known_count) = self.db.move_new_to_known(self.capacity())?;
+        inc_int_counter_vec_by(&ATTESTATIONS_DROPPED_TOTAL, dropped, &["pool_full"]);
+        state.new_count = 0;
+        state.known_count = known_count;
+        record_size(&state);
+        Ok(())
+    }
+
+    /// Checks the pools left by the previous run. New attestations are kept to be accepted at
+    /// the next interval, except those a known attestation of the validator already supersedes.
+    pub fn recover(&self) -> anyhow::Result<()> {
+        let mut state = self.state.lock();
+        let (known_attestations, new_attestations) = (
+            self.db.latest_known_attestations_provider(),
+            self.db.latest_new_attestations_provider(),
+        );
+
+        let mut superseded = 0;
+        for signed_attestation in new_attestations.iter_values()?.collect::<Vec<_>>() {
+            let signed_attestation = signed_attestation?;
+            let validator_id = signed_attestation.message.validator_id;
+            if known_attestations
+                .get(validator_id)?
+                .is_some_and(|latest_known| {
+                    latest_known.message.data.slot >= signed_attestation.message.data.slot
+                })
+            {
+                new_attestations.remove(validator_id)?;
+                superseded += 1;
+            }
+        }
+
+        state.new_count = new_attestations.count()?;
+        state.known_count = known_attestations.count()?;
+        if state.new_count > 0 || superseded > 0 {
+            info!(
+                pending = state.new_count,
+                superseded, "Recovered the new attestations received before the restart"
+            );
+        }
+        record_size(&state);
+        Ok(())
+    }
+
+    /// Aggregates the known attestations with the same data, in slot order.
+    pub fn aggregate_known(&self) -> anyhow::Result<Vec<SignedAggregatedAttestation>> {
+        let mut attestations_by_data = HashMap::<_, Vec<_>>::new();
+        for signed_attestation in self
+            .db
+            .latest_known_attestations_provider()
+            .get_all_attestations()?
+            .into_values()
+        {
+            attestations_by_data
+                .entry(signed_attestation.message.data.tree_hash_root())
+                .or_default()
+                .push(signed_attestation);
+        }
+
+        let mut aggregates = attestations_by_data
+            .values()
+            .map(|signed_attestations| SignedAggregatedAttestation::aggregate(signed_attestations))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        aggregates.sort_by_key(|aggregate| aggregate.message.message.slot);
+        Ok(aggregates)
+    }
+
+    /// Evicts the attestations for slots before `finalized_slot` from both pools.
+    pub fn prune(&self, finalized_slot: u64) -> anyhow::Result<()> {
+        let mut state = self.state.lock();
+        self.write_pending(&mut state)?;
+        let pruned_new = self
+            .db
+            .latest_new_attestations_provider()
+            .remove_before_slot(finalized_slot)?;
+        let pruned_known = self
+            .db
+            .latest_known_attestations_provider()
+            .remove_before_slot(finalized_slot)?;
+        state.new_count = state.new_count.saturating_sub(pruned_new);
+        state.known_count = state.known_count.saturating_sub(pruned_known);
+        inc_int_counter_vec_by(
+            &ATTESTATIONS_DROPPED_TOTAL,
+            pruned_new + pruned_known,
+            &["finalized"],
+        );
+        record_size(&state);
+        Ok(())
+    }
+}
+
+fn record_size(state: &PoolState) {
+    set_int_gauge_vec(
+        &ATTESTATION_POOL_SIZE,
+        (state.new_count + state.added) as i64,
+        &["new"],
+    );
+    set_int_gauge_vec(&ATTESTATION_POOL_SIZE, state.known_count as i64, &["known"]);
+}
+
+#[cfg(test)]
+mod tests {
+    use ream_consensus_lean::{
+        attestation::{Attestation, AttestationData, SignedAttestation},
+        checkpoint::Checkpoint,
+    };
+    use ream_post_quantum_crypto::leansig::signature::Signature;
+    use ream_storage::{db::ReamDB, tables::table::REDBTable};
+
+    use super::AttestationPool;
+
+    fn signed_attestation(validator_id: u64, slot: u64) -> SignedAttestation {
+        SignedAttestation {
+            message: Attestation {
+                validator_id,
+                data: AttestationData {
+                    slot,
+                    head: Checkpoint::default(),
+                    target: Checkpoint::default(),
+                    source: Checkpoint::default(),
+                },
+            },
+            signature: Signature::blank(),
+        }
+    }
+
+    #[test]
+    fn test_aggregate_known() {
+        let attestation_pool =
+            AttestationPool::new(ReamDB::in_memory().unwrap().init_lean_db().unwrap());
+        for (validator_id, slot) in [(0, 2), (1, 1), (2, 2), (3, 2)] {
+            attestation_pool
+                .insert_new(signed_attestation(validator_id, slot))
+                .unwrap();
+        }
+        // Only the known attestations are aggregated.
+        assert!(attestation_pool.aggregate_known().unwrap().is_empty());
+
+        attestation_pool.accept_new().unwrap();
+        let aggregates = attestation_pool.aggregate_known().unwrap();
+        assert_eq!(
+            aggregates
+                .iter()
+                .map(|aggregate| (
+                    aggregate.message.message.slot,
+                    aggregate.message.participants().collect::<Vec<_>>()
+                ))
+                .collect::<Vec<_>>(),
+            vec![(1, vec![1]), (2, vec![0, 2, 3])]
+        );
+        assert_eq!(aggregates[1].signature.len(), 3);
+    }
+
+    #[test]
+    fn test_insert_from_block() {
+        let db = ReamDB::in_memory().unwrap().init_lean_db().unwrap();
+        let attestation_pool = AttestationPool::new(db.clone()).with_max_size(2);
+        attestation_pool
+            .insert_new(signed_attestation(0, 3))
+            .unwrap();
+        attestation_pool
+            .insert_new(signed_attestation(1, 5))
+            .unwrap();
+
+        // The latest attestation of each validator in the block is kept, and the pool only has
+        // room for two validators.
+        attestation_pool
+            .insert_from_block(vec![
+                signed_attestation(0, 4),
+                signed_attestation(0, 2),
+                signed_attestation(1, 4),
+                signed_attestation(2, 4),
+            ])
+            .unwrap();
+        let (known, new) = (
+            db.latest_known_attestations_provider(),
+            db.latest_new_attestations_provider(),
+        );
+        assert_eq!(known.get(0).unwrap().unwrap().message.data.slot, 4);
+        assert_eq!(known.get(1).unwrap().unwrap().message.data.slot, 4);
+        assert!(known.get(2).unwrap().is_none());
+
+        // Only the new attestation included in the block is superseded.
+        assert!(new.get(0).unwrap().is_none());
+        assert_eq!(new.get(1).unwrap().unwrap().message.data.slot, 5);
+    }
+}
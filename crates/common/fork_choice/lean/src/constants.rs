@@ -1 +1,12 @@
 pub const JUSTIFICATION_LOOKBACK_SLOTS: u64 = 3;
+/// Percentage of the validator count added to the weight of a timely block, like the beacon
+/// chain's `PROPOSER_SCORE_BOOST`.
+pub const PROPOSER_SCORE_BOOST: u64 = 40;
+/// How many attestations each of the new and known attestation pools holds at most.
+pub const MAX_ATTESTATION_POOL_SIZE: u64 = 4096;
+/// How many new attestations from gossip a node buffers before writing them in one transaction.
+pub const ATTESTATION_WRITE_BATCH_SIZE: usize = 64;
+/// How many blocks a state is regenerated over at most, from the nearest stored state.
+pub const MAX_STATE_REPLAY_BLOCKS: usize = 64;
+/// How many regenerated states are kept in memory.
+pub const REGENERATED_STATE_CACHE_SIZE: usize = 8;
@@ -1,22 +1,16 @@
-use alloy_primitives::B256;
-use ream_consensus_lean::{
-    block::{Block, BlockBody},
-    state::LeanState,
-    validator::Validator,
-};
+use std::{collections::HashMap, fs, path::Path};
+
+use anyhow::{anyhow, bail, ensure};
+use ream_consensus_lean::{block::Block, state::LeanState, validator::Validator};
+use ream_keystore::lean_keystore::{ValidatorKeysManifest, ValidatorRegistry};
+use ream_network_spec::networks::LeanNetworkSpec;
+use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+use ream_storage::{db::lean::LeanDB, tables::table::REDBTable};
+use tracing::info;
 use tree_hash::TreeHash;
 
-fn genesis_block(state_root: B256) -> Block {
-    Block {
-        slot: 0,
-        proposer_index: 0,
-        parent_root: B256::ZERO,
-        state_root,
-        body: BlockBody {
-            attestations: Default::default(),
-        },
-    }
-}
+/// Path of the keys manifest, relative to the directory of the validator registry.
+pub const VALIDATOR_KEYS_MANIFEST_PATH: &str = "hash-sig-keys/validator-keys-manifest.yaml";
 
 /// Setup the genesis block and state for the Lean chain.
 ///
@@ -24,19 +18,145 @@ fn genesis_block(state_root: B256) -> Block {
 /// <https://github.com/leanEthereum/leanSpec/blob/f869a7934fc4bccf0ba22159c64ecd398c543107/src/lean_spec/subspecs/containers/state/state.py#L65-L108>
 pub fn setup_genesis(genesis_time: u64, validators: Vec<Validator>) -> (Block, LeanState) {
     let genesis_state = LeanState::generate_genesis(genesis_time, Some(validators));
-    let genesis_block = genesis_block(genesis_state.tree_hash_root());
+    let genesis_block = Block::genesis(genesis_state.tree_hash_root());
 
     (genesis_block, genesis_state)
 }
 
+/// Builds the genesis block and state described by `network`, which is parsed from
+/// `config.yaml`.
+///
+/// The validators of the config are checked against the keys manifest next to the validator
+/// registry (`validators.yaml`) at `validator_registry_path`, and every validator the registry
+/// assigns to a node must exist and belong to only one node. If the config has a
+/// `GENESIS_BLOCK_ROOT`, the root of the built genesis block must match it.
+pub fn load_genesis(
+    network: &LeanNetworkSpec,
+    validator_registry_path: &Path,
+) -> anyhow::Result<(Block, LeanState)> {
+    ensure!(
+        network.num_validators == network.validator_public_keys.len() as u64,
+        "Config has NUM_VALIDATORS {} but {} GENESIS_VALIDATORS",
+        network.num_validators,
+        network.validator_public_keys.len()
+    );
+    let validators = network
+        .validator_public_keys
+        .iter()
+        .enumerate()
+        .map(|(index, public_key)| Validator {
+            public_key: PublicKey::new(*public_key),
+            index: index as u64,
+        })
+        .collect::<Vec<_>>();
+
+    let registry_dir = validator_registry_path
+        .parent()
+        .ok_or_else(|| anyhow!("Validator registry path has no parent directory"))?;
+    let manifest_path = registry_dir.join(VALIDATOR_KEYS_MANIFEST_PATH);
+    let manifest = serde_yaml::from_str::<ValidatorKeysManifest>(
+        &fs::read_to_string(&manifest_path).map_err(|err| {
+            anyhow!(
+                "Failed to read keys manifest {}: {err}",
+                manifest_path.display()
+            )
+        })?,
+    )
+    .map_err(|err| anyhow!("Failed to parse keys manifest: {err}"))?;
+    check_manifest(&validators, &manifest)?;
+
+    let registry = serde_yaml::from_str::<ValidatorRegistry>(
+        &fs::read_to_string(validator_registry_path)
+            .map_err(|err| anyhow!("Failed to read validator registry: {err}"))?,
+    )
+    .map_err(|err| anyhow!("Failed to parse validator registry: {err}"))?;
+    check_registry(network.num_validators, &registry)?;
+
+    let (genesis_block, genesis_state) = setup_genesis(network.genesis_time, validators);
+    let genesis_root = genesis_block.tree_hash_root();
+    if let Some(expected_root) = network.genesis_block_root {
+        ensure!(
+            genesis_root == expected_root,
+            "Genesis root {genesis_root} doesn't match GENESIS_BLOCK_ROOT {expected_root}"
+        );
+    }
+
+    info!(
+        genesis_time = network.genesis_time,
+        num_validators = network.num_validators,
+        "Loaded genesis block {genesis_root}"
+    );
+    Ok((genesis_block, genesis_state))
+}
+
+fn check_manifest(
+    validators: &[Validator],
+    manifest: &ValidatorKeysManifest,
+) -> anyhow::Result<()> {
+    ensure!(
+        manifest.num_validators == validators.len() as u64
+            && manifest.validators.len() == validators.len(),
+        "Keys manifest has {} validators but the config has {}",
+        manifest.validators.len(),
+        validators.len()
+    );
+    for (validator, key) in validators.iter().zip(&manifest.validators) {
+        ensure!(
+            key.index == validator.index,
+            "Keys manifest lists validator {} at position {}",
+            key.index,
+            validator.index
+        );
+        ensure!(
+            key.public_key == validator.public_key,
+            "Public key of validator {} differs between the config and the keys manifest",
+            validator.index
+        );
+    }
+    Ok(())
+}
+
+fn check_registry(num_validators: u64, registry: &ValidatorRegistry) -> anyhow::Result<()> {
+    let mut owners = HashMap::new();
+    for (node_id, indices) in &registry.nodes {
+        for index in indices {
+            ensure!(
+                *index < num_validators,
+                "Validator registry assigns unknown validator {index} to {node_id}"
+            );
+            if let Some(owner) = owners.insert(*index, node_id) {
+                bail!("Validator registry assigns validator {index} to both {owner} and {node_id}");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Checks `lean_db` was initialized from `genesis_block`, if it was initialized at all. A fresh
+/// database gets the genesis written by
+/// [Store::get_forkchoice_store](crate::store::Store::get_forkchoice_store), so booting again with
+/// the same config doesn't change anything.
+pub fn check_database_genesis(lean_db: &LeanDB, genesis_block: &Block) -> anyhow::Result<()> {
+    let genesis_root = genesis_block.tree_hash_root();
+    match lean_db.slot_index_provider().get(0)? {
+        Some(stored_root) if stored_root != genesis_root => bail!(
+            "Database was initialized with genesis block {stored_root}, but the config builds \
+             {genesis_root}. Use the config the database was created with, or purge it with \
+             --purge-db"
+        ),
+        _ => Ok(()),
+    }
+}
+
 #[cfg(test)]
 mod test {
     use alloy_primitives::{FixedBytes, hex::ToHexExt};
     use ream_consensus_lean::validator::Validator;
+    use ream_keystore::lean_keystore::ValidatorRegistry;
     use ream_post_quantum_crypto::leansig::public_key::PublicKey;
     use tree_hash::TreeHash;
 
-    use crate::genesis::setup_genesis;
+    use crate::genesis::{check_registry, setup_genesis};
 
     #[test]
     fn test_genesis_block_hash_comparison() {
@@ -77,4 +197,13 @@ mod test {
             "ce48a709189aa2b23b6858800996176dc13eb49c0c95d717c39e60042de1ac91"
         );
     }
+
+    #[test]
+    fn test_registry_rejects_shared_and_unknown_validators() {
+        let registry = |yaml: &str| serde_yaml::from_str::<ValidatorRegistry>(yaml).unwrap();
+
+        assert!(check_registry(3, &registry("ream_0: [0, 1]\nzeam_0: [2]")).is_ok());
+        assert!(check_registry(3, &registry("ream_0: [0, 1]\nzeam_0: [1]")).is_err());
+        assert!(check_registry(3, &registry("ream_0: [0, 3]")).is_err());
+    }
 }
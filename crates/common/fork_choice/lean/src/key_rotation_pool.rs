@@ -0,0 +1,127 @@
+use std::{
+    collections::{BTreeMap, HashSet},
+    sync::Arc,
+};
+
+use parking_lot::Mutex;
+use ream_consensus_lean::{state::LeanState, validator::SignedKeyRotation};
+use ssz_types::typenum::{U16, Unsigned};
+use tracing::debug;
+
+/// The signed key rotations waiting to be included in a block, at most one per validator.
+///
+/// Rotations are checked against the state a block is built on and dropped once they can no
+/// longer be included, because they are on chain or their activation slot has passed.
+#[derive(Debug, Clone, Default)]
+pub struct KeyRotationPool {
+    rotations: Arc<Mutex<BTreeMap<u64, SignedKeyRotation>>>,
+}
+
+impl KeyRotationPool {
+    /// Adds `rotation`, replacing an earlier rotation of the same validator.
+    pub fn insert(&self, rotation: SignedKeyRotation) {
+        self.rotations
+            .lock()
+            .insert(rotation.message.validator_index, rotation);
+    }
+
+    pub fn len(&self) -> usize {
+        self.rotations.lock().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.rotations.lock().is_empty()
+    }
+
+    /// Returns the rotations a block on top of `state`, advanced to the slot of the block, can
+    /// include, and drops the ones which can never be included anymore.
+    pub fn includable(&self, state: &LeanState) -> Vec<SignedKeyRotation> {
+        let mut rotations = self.rotations.lock();
+        rotations.retain(|validator_index, rotation| {
+            let activation_slot = rotation.message.activation_slot;
+            let on_chain = state
+                .validator_status(*validator_index)
+                .is_some_and(|status| {
+                    status.has_pending_key_rotation()
+                        && status.next_public_key == rotation.message.new_public_key
+                });
+            activation_slot > state.slot && !on_chain
+        });
+
+        let mut new_public_keys = HashSet::new();
+        rotations
+            .values()
+            .filter(
+                |rotation| match state.validate_key_rotation(&rotation.message) {
+                    Ok(()) => new_public_keys.insert(rotation.message.new_public_key),
+                    Err(err) => {
+                        debug!(
+                            slot = state.slot,
+                            "Key rotation of validator {} isn't includable: {err:?}",
+                            rotation.message.validator_index
+                        );
+                        false
+                    }
+                },
+            )
+            .take(U16::USIZE)
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ream_consensus_lean::{
+        state::LeanState,
+        utils::generate_default_validators,
+        validator::{KeyRotation, SignedKeyRotation, ValidatorStatus},
+    };
+    use ream_post_quantum_crypto::leansig::{public_key::PublicKey, signature::Signature};
+    use ssz_types::VariableList;
+
+    use super::KeyRotationPool;
+
+    fn rotation(validator_index: u64, key_byte: u8, activation_slot: u64) -> SignedKeyRotation {
+        SignedKeyRotation {
+            message: KeyRotation {
+                validator_index,
+                new_public_key: PublicKey::from(&[key_byte; 52][..]),
+                activation_slot,
+            },
+            signature: Signature::blank(),
+        }
+    }
+
+    #[test]
+    fn test_includable_key_rotations() {
+        let mut state = LeanState::generate_genesis(0, Some(generate_default_validators(3)));
+        let pool = KeyRotationPool::default();
+        pool.insert(rotation(0, 1, 5));
+        // Rotations are only includable from the Devnet3 fork on, but wait in the pool for it.
+        assert!(pool.includable(&state).is_empty());
+        assert_eq!(pool.len(), 1);
+
+        state.validator_statuses =
+            Some(VariableList::try_from(vec![ValidatorStatus::default(); 3]).unwrap());
+        pool.insert(rotation(1, 2, 5));
+        // The same key can't be rotated to twice.
+        pool.insert(rotation(2, 2, 5));
+        assert_eq!(
+            pool.includable(&state),
+            vec![rotation(0, 1, 5), rotation(1, 2, 5)]
+        );
+
+        // A rotation on chain is dropped.
+        state
+            .process_key_rotations(&VariableList::try_from(vec![rotation(0, 1, 5)]).unwrap())
+            .unwrap();
+        assert_eq!(pool.includable(&state), vec![rotation(1, 2, 5)]);
+        assert_eq!(pool.len(), 2);
+
+        // So are rotations activating before the block.
+        state.slot = 5;
+        assert!(pool.includable(&state).is_empty());
+        assert!(pool.is_empty());
+    }
+}
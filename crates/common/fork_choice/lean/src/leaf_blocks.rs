@@ -0,0 +1,76 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use alloy_primitives::B256;
+use parking_lot::RwLock;
+
+/// The blocks no known block builds on, so the number of live forks can be kept up to date as
+/// blocks are imported instead of reading every block.
+///
+/// Handles are cheap to clone and share the leaves.
+#[derive(Debug, Clone, Default)]
+pub struct LeafBlocks {
+    leaves: Arc<RwLock<HashSet<B256>>>,
+}
+
+impl LeafBlocks {
+    /// Starts from the leaves among `blocks`, the slot and parent root of each block by root.
+    pub fn from_blocks(blocks: &HashMap<B256, (u64, B256)>) -> Self {
+        let parents = blocks
+            .values()
+            .map(|(_, parent_root)| *parent_root)
+            .collect::<HashSet<_>>();
+        Self {
+            leaves: Arc::new(RwLock::new(
+                blocks
+                    .keys()
+                    .filter(|block_root| !parents.contains(*block_root))
+                    .copied()
+                    .collect(),
+            )),
+        }
+    }
+
+    /// Records the imported block `block_root`, after which its parent is no longer a leaf.
+    pub fn insert(&self, block_root: B256, parent_root: B256) {
+        let mut leaves = self.leaves.write();
+        leaves.remove(&parent_root);
+        leaves.insert(block_root);
+    }
+
+    /// Returns the number of leaves other than `head`, which are the tips of the branches off the
+    /// canonical chain.
+    pub fn fork_count(&self, head: B256) -> usize {
+        let leaves = self.leaves.read();
+        leaves.len() - leaves.contains(&head) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy_primitives::B256;
+
+    use super::LeafBlocks;
+
+    #[test]
+    fn test_fork_count() {
+        let root = B256::repeat_byte;
+        let leaf_blocks = LeafBlocks::from_blocks(&HashMap::from([
+            (root(1), (1, B256::ZERO)),
+            (root(2), (2, root(1))),
+            (root(3), (3, root(1))),
+        ]));
+        assert_eq!(leaf_blocks.fork_count(root(2)), 1);
+
+        // Extending the fork keeps one fork, a block on the canonical chain adds one.
+        leaf_blocks.clone().insert(root(4), root(3));
+        assert_eq!(leaf_blocks.fork_count(root(2)), 1);
+        leaf_blocks.insert(root(5), root(1));
+        assert_eq!(leaf_blocks.fork_count(root(2)), 2);
+        assert_eq!(leaf_blocks.fork_count(root(4)), 2);
+    }
+}
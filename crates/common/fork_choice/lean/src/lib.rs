@@ -1,4 +1,12 @@
+pub mod advanced_state;
+pub mod ancestors;
+pub mod attestation_pool;
 pub mod constants;
 pub mod genesis;
+pub mod key_rotation_pool;
+pub mod leaf_blocks;
+pub mod light_client;
+pub mod rejection;
+pub mod state_regeneration;
 pub mod store;
 pub mod utils;
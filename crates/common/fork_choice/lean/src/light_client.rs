@@ -0,0 +1,146 @@
+//! Builds the light client data of the lean chain from the blocks and states in the database.
+
+use alloy_primitives::B256;
+use ream_consensus_lean::{
+    block::Block,
+    light_client::{LightClientBootstrap, LightClientFinalityUpdate, LightClientOptimisticUpdate},
+    state::LeanState,
+};
+use ream_storage::{db::lean::LeanDB, tables::table::REDBTable};
+
+/// Most states [light_client_updates] loads for a single request.
+pub const MAX_LIGHT_CLIENT_UPDATE_STATES: usize = 128;
+
+/// Returns the post state of the block at `block_root`, if it is stored.
+async fn post_state(lean_db: &LeanDB, block_root: B256) -> anyhow::Result<Option<LeanState>> {
+    Ok(lean_db
+        .run_blocking(move |lean_db| lean_db.state_provider().get(block_root))
+        .await?)
+}
+
+/// Returns the block at `block_root` with its post state, if both are stored.
+async fn block_and_state(
+    lean_db: &LeanDB,
+    block_root: B256,
+) -> anyhow::Result<Option<(Block, LeanState)>> {
+    Ok(lean_db
+        .run_blocking(move |lean_db| {
+            let Some(block) = lean_db.block_provider().get(block_root)? else {
+                return Ok(None);
+            };
+            Ok(lean_db
+                .state_provider()
+                .get(block_root)?
+                .map(|state| (block.message.block, state)))
+        })
+        .await?)
+}
+
+pub async fn light_client_bootstrap(
+    lean_db: &LeanDB,
+    block_root: B256,
+) -> anyhow::Result<Option<LightClientBootstrap>> {
+    block_and_state(lean_db, block_root)
+        .await?
+        .map(|(block, state)| LightClientBootstrap::new(&block, &state))
+        .transpose()
+}
+
+/// Returns the finality update attested by the block at `block_root`, or `None` if the block
+/// isn't stored or its state hasn't finalized a block after genesis yet.
+pub async fn light_client_finality_update(
+    lean_db: &LeanDB,
+    block_root: B256,
+) -> anyhow::Result<Option<LightClientFinalityUpdate>> {
+    let Some((block, state)) = block_and_state(lean_db, block_root).await? else {
+        return Ok(None);
+    };
+    finality_update(lean_db, &block, &state).await
+}
+
+async fn finality_update(
+    lean_db: &LeanDB,
+    attested_block: &Block,
+    attested_state: &LeanState,
+) -> anyhow::Result<Option<LightClientFinalityUpdate>> {
+    let finalized_root = attested_state.latest_finalized.root;
+    if finalized_root == B256::ZERO {
+        return Ok(None);
+    }
+    let Some(finalized_block) = lean_db
+        .run_blocking(move |lean_db| lean_db.block_provider().get(finalized_root))
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    LightClientFinalityUpdate::new(
+        attested_block,
+        attested_state,
+        &finalized_block.message.block,
+    )
+    .map(Some)
+}
+
+pub async fn light_client_optimistic_update(
+    lean_db: &LeanDB,
+    block_root: B256,
+) -> anyhow::Result<Option<LightClientOptimisticUpdate>> {
+    Ok(lean_db
+        .run_blocking(move |lean_db| lean_db.block_provider().get(block_root))
+        .await?
+        .map(|block| LightClientOptimisticUpdate::from(&block.message.block)))
+}
+
+/// Returns the finality updates of the canonical blocks from `from_slot` to `to_slot` which
+/// finalized a new checkpoint, in slot order.
+///
+/// At most [MAX_LIGHT_CLIENT_UPDATE_STATES] states are loaded, the updates of the blocks after
+/// those are left out, so a caller asks again from the slot after the last update.
+pub async fn light_client_updates(
+    lean_db: &LeanDB,
+    from_slot: u64,
+    to_slot: u64,
+) -> anyhow::Result<Vec<LightClientFinalityUpdate>> {
+    let blocks = lean_db
+        .run_blocking(move |lean_db| {
+            lean_db
+                .canonical_chain_iter(from_slot, to_slot)?
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .await?;
+
+    let mut finalized_slot = None;
+    let mut updates = vec![];
+    let mut state_loads = 0;
+    for (_, block_root, signed_block) in blocks {
+        if state_loads >= MAX_LIGHT_CLIENT_UPDATE_STATES {
+            break;
+        }
+        let block = signed_block.message.block;
+        state_loads += 1;
+        let Some(state) = post_state(lean_db, block_root).await? else {
+            continue;
+        };
+
+        // The first block is compared against the finalized checkpoint of its parent.
+        let parent_finalized_slot = match finalized_slot {
+            Some(finalized_slot) => Some(finalized_slot),
+            None => {
+                state_loads += 1;
+                post_state(lean_db, block.parent_root)
+                    .await?
+                    .map(|parent_state| parent_state.latest_finalized.slot)
+            }
+        };
+        finalized_slot = Some(state.latest_finalized.slot);
+        if parent_finalized_slot.is_some_and(|slot| slot >= state.latest_finalized.slot) {
+            continue;
+        }
+
+        if let Some(update) = finality_update(lean_db, &block, &state).await? {
+            updates.push(update);
+        }
+    }
+    Ok(updates)
+}
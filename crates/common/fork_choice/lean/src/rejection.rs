@@ -0,0 +1,217 @@
+//! Why fork choice rejects or ignores blocks and attestations. Each one is counted by kind and
+//! [RejectReason] in `lean_gossip_rejected_total`, which shows why a node's view of the chain
+//! diverges from its peers'.
+//!
+//! [Store::on_block] and [Store::validate_attestation] return a [RejectedError] for the checks
+//! with a reason of their own, and [reject_reason] recovers it from the returned error. Callers
+//! get a [ForkChoiceError], which tells a message at fault apart from a failure of the node.
+//!
+//! [Store::on_block]: crate::store::Store::on_block
+//! [Store::validate_attestation]: crate::store::Store::validate_attestation
+
+use ream_consensus_lean::errors::StateTransitionError;
+use ream_metrics::{GOSSIP_REJECTED_TOTAL, inc_int_counter_vec};
+use ream_storage::errors::StoreError;
+use thiserror::Error;
+
+use crate::store::FutureBlockError;
+
+pub const BLOCK: &str = "block";
+pub const ATTESTATION: &str = "attestation";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    /// For a slot which hasn't started yet, even allowing for the gossip clock disparity.
+    FutureSlot,
+    /// The parent of the block isn't known.
+    UnknownParent,
+    /// A signature of the block doesn't verify.
+    InvalidSignature,
+    /// The block is already known, or the validator already has an attestation for the same or
+    /// a later slot.
+    Duplicate,
+    /// The attestation votes for a block which isn't known.
+    UnknownBlock,
+    /// The blob sidecars of the block are incomplete.
+    DataUnavailable,
+    /// Any other failed check, like the state transition, or an error of the node itself.
+    Invalid,
+}
+
+impl RejectReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RejectReason::FutureSlot => "future_slot",
+            RejectReason::UnknownParent => "unknown_parent",
+            RejectReason::InvalidSignature => "invalid_signature",
+            RejectReason::Duplicate => "duplicate",
+            RejectReason::UnknownBlock => "unknown_block",
+            RejectReason::DataUnavailable => "data_unavailable",
+            RejectReason::Invalid => "invalid",
+        }
+    }
+}
+
+/// A rejection with the [RejectReason] it is counted under.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[error("{message}")]
+pub struct RejectedError {
+    pub reason: RejectReason,
+    pub message: String,
+}
+
+impl RejectedError {
+    pub fn new(reason: RejectReason, message: impl Into<String>) -> Self {
+        Self {
+            reason,
+            message: message.into(),
+        }
+    }
+}
+
+/// Why fork choice didn't import a block or an attestation.
+#[derive(Debug, Error)]
+pub enum ForkChoiceError {
+    /// The block is for a slot which hasn't started yet, it may still be imported once it has.
+    #[error(transparent)]
+    FutureBlock(#[from] FutureBlockError),
+
+    /// The block or attestation fails a check of fork choice.
+    #[error(transparent)]
+    Rejected(#[from] RejectedError),
+
+    /// The block fails the state transition.
+    #[error("Invalid state transition: {0}")]
+    InvalidStateTransition(#[from] StateTransitionError),
+
+    /// Reading from or writing to the database failed.
+    #[error(transparent)]
+    Storage(#[from] StoreError),
+
+    /// Any other failure of the node itself, which says nothing about the message and may not
+    /// happen again.
+    #[error(transparent)]
+    Internal(anyhow::Error),
+}
+
+impl ForkChoiceError {
+    /// Whether the failure is the node's own rather than the message's.
+    pub fn is_internal(&self) -> bool {
+        match self {
+            ForkChoiceError::InvalidStateTransition(err) => err.is_internal(),
+            ForkChoiceError::Storage(_) | ForkChoiceError::Internal(_) => true,
+            ForkChoiceError::FutureBlock(_) | ForkChoiceError::Rejected(_) => false,
+        }
+    }
+
+    /// The reason the error is counted under, see [reject_reason].
+    pub fn reject_reason(&self) -> RejectReason {
+        match self {
+            ForkChoiceError::FutureBlock(_) => RejectReason::FutureSlot,
+            ForkChoiceError::Rejected(rejected) => rejected.reason,
+            ForkChoiceError::InvalidStateTransition(_)
+            | ForkChoiceError::Storage(_)
+            | ForkChoiceError::Internal(_) => RejectReason::Invalid,
+        }
+    }
+}
+
+/// Sorts the errors fork choice returns internally by what they say about the message. Errors
+/// which aren't one of the typed ones are the node's own.
+impl From<anyhow::Error> for ForkChoiceError {
+    fn from(err: anyhow::Error) -> Self {
+        let err = match err.downcast::<ForkChoiceError>() {
+            Ok(err) => return err,
+            Err(err) => err,
+        };
+        let err = match err.downcast::<FutureBlockError>() {
+            Ok(err) => return ForkChoiceError::FutureBlock(err),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<RejectedError>() {
+            Ok(err) => return ForkChoiceError::Rejected(err),
+            Err(err) => err,
+        };
+        let err = match err.downcast::<StateTransitionError>() {
+            Ok(err) => return ForkChoiceError::InvalidStateTransition(err),
+            Err(err) => err,
+        };
+        match err.downcast::<StoreError>() {
+            Ok(err) => ForkChoiceError::Storage(err),
+            Err(err) => ForkChoiceError::Internal(err),
+        }
+    }
+}
+
+/// The reason an error of fork choice is counted under. Errors without a reason of their own
+/// count as [RejectReason::Invalid].
+pub fn reject_reason(err: &anyhow::Error) -> RejectReason {
+    if err.is::<FutureBlockError>() {
+        return RejectReason::FutureSlot;
+    }
+    err.downcast_ref::<RejectedError>()
+        .map_or(RejectReason::Invalid, |rejected| rejected.reason)
+}
+
+/// Counts a rejected `kind` of message, [BLOCK] or [ATTESTATION].
+pub fn record_rejected(kind: &str, reason: RejectReason) {
+    inc_int_counter_vec(&GOSSIP_REJECTED_TOTAL, &[kind, reason.as_str()]);
+}
+
+#[cfg(test)]
+mod tests {
+    use anyhow::anyhow;
+    use ream_consensus_lean::errors::StateTransitionError;
+
+    use super::{ForkChoiceError, RejectReason, RejectedError, reject_reason};
+    use crate::store::FutureBlockError;
+
+    #[test]
+    fn test_reject_reason() {
+        let err = anyhow::Error::from(RejectedError::new(
+            RejectReason::UnknownParent,
+            "State not found for parent root",
+        ));
+        assert_eq!(reject_reason(&err), RejectReason::UnknownParent);
+        assert_eq!(err.to_string(), "State not found for parent root");
+
+        let err = anyhow::Error::from(FutureBlockError {
+            slot: 2,
+            latest_slot: 1,
+        });
+        assert_eq!(reject_reason(&err), RejectReason::FutureSlot);
+        assert_eq!(
+            reject_reason(&anyhow!("Failed to get attestation")),
+            RejectReason::Invalid
+        );
+    }
+
+    #[test]
+    fn test_fork_choice_error() {
+        let err = ForkChoiceError::from(anyhow::Error::from(RejectedError::new(
+            RejectReason::UnknownParent,
+            "State not found for parent root",
+        )));
+        assert!(matches!(err, ForkChoiceError::Rejected(_)));
+        assert_eq!(err.reject_reason(), RejectReason::UnknownParent);
+
+        let err =
+            ForkChoiceError::from(anyhow::Error::from(StateTransitionError::InvalidStateRoot));
+        assert!(matches!(err, ForkChoiceError::InvalidStateTransition(_)));
+        assert_eq!(err.reject_reason(), RejectReason::Invalid);
+        assert!(!err.is_internal());
+
+        // A typed error passed through an untyped one keeps its type.
+        let err = ForkChoiceError::from(anyhow::Error::from(ForkChoiceError::FutureBlock(
+            FutureBlockError {
+                slot: 2,
+                latest_slot: 1,
+            },
+        )));
+        assert_eq!(err.reject_reason(), RejectReason::FutureSlot);
+
+        let err = ForkChoiceError::from(anyhow!("Failed to read the block table"));
+        assert!(err.is_internal());
+        assert_eq!(err.reject_reason(), RejectReason::Invalid);
+    }
+}
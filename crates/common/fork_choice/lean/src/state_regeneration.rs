@@ -0,0 +1,110 @@
+use std::{num::NonZeroUsize, sync::Arc};
+
+use alloy_primitives::B256;
+use anyhow::{anyhow, bail};
+use lru::LruCache;
+use parking_lot::Mutex;
+use ream_consensus_lean::state::LeanState;
+use ream_storage::{db::lean::LeanDB, tables::table::REDBTable};
+
+use crate::constants::{MAX_STATE_REPLAY_BLOCKS, REGENERATED_STATE_CACHE_SIZE};
+
+/// Regenerates the post states of blocks whose states are no longer stored, by replaying the
+/// blocks since the nearest ancestor with a stored state.
+///
+/// Regenerated states are kept in a small in-memory cache rather than written back, so reading
+/// old states over the API doesn't grow the database. Handles are cheap to clone and share the
+/// cache, so callers don't have to hold the fork choice store while a state is replayed.
+#[derive(Debug, Clone)]
+pub struct StateRegenerator {
+    store: LeanDB,
+    cache: Arc<Mutex<LruCache<B256, LeanState>>>,
+    max_replay_blocks: usize,
+}
+
+impl StateRegenerator {
+    pub fn new(store: LeanDB) -> Self {
+        Self {
+            store,
+            cache: Arc::new(Mutex::new(LruCache::new(
+                NonZeroUsize::new(REGENERATED_STATE_CACHE_SIZE).expect("Invalid cache size"),
+            ))),
+            max_replay_blocks: MAX_STATE_REPLAY_BLOCKS,
+        }
+    }
+
+    /// Refuses to regenerate states more than `max_replay_blocks` blocks away from a stored or
+    /// cached state.
+    pub fn with_max_replay_blocks(mut self, max_replay_blocks: usize) -> Self {
+        self.max_replay_blocks = max_replay_blocks;
+        self
+    }
+
+    /// Returns the post state of the block `block_root`, or `None` if the block, or an ancestor
+    /// needed to replay it, is unknown. Fails if the state is too far from a stored one.
+    pub async fn get_state_at(&self, block_root: B256) -> anyhow::Result<Option<LeanState>> {
+        if let Some(state) = self.cache.lock().get(&block_root) {
+            return Ok(Some(state.clone()));
+        }
+
+        let (cache, max_replay_blocks) = (self.cache.clone(), self.max_replay_blocks);
+        let Some((state, blocks)) = self
+            .store
+            .run_blocking(move |lean_db| {
+                let (block_provider, state_provider) =
+                    (lean_db.block_provider(), lean_db.state_provider());
+                let mut blocks = vec![];
+                let mut current = block_root;
+                loop {
+                    if let Some(state) = state_provider.get(current)? {
+                        return Ok(Some((Some(state), blocks)));
+                    }
+                    if let Some(state) = cache.lock().get(&current) {
+                        return Ok(Some((Some(state.clone()), blocks)));
+                    }
+                    // Without a state within reach the walk stops, rather than reading the chain
+                    // back to genesis.
+                    if blocks.len() == max_replay_blocks {
+                        return Ok(Some((None, blocks)));
+                    }
+                    let Some(block) = block_provider.get(current)? else {
+                        return Ok(None);
+                    };
+                    current = block.message.block.parent_root;
+                    blocks.push(block);
+                }
+            })
+            .await?
+        else {
+            return Ok(None);
+        };
+        let Some(mut state) = state else {
+            bail!(
+                "State of block {block_root} is more than {} blocks away from a stored state",
+                self.max_replay_blocks
+            );
+        };
+        if blocks.is_empty() {
+            return Ok(Some(state));
+        }
+
+        // Replaying blocks hashes the whole state for every slot, keep it off the async workers.
+        let state = tokio::task::spawn_blocking(move || {
+            for block in blocks.iter().rev() {
+                state
+                    .state_transition(&block.message.block, true)
+                    .map_err(|err| {
+                        anyhow!(
+                            "Failed to replay block at slot {} to regenerate state: {err}",
+                            block.message.block.slot
+                        )
+                    })?;
+            }
+            anyhow::Ok(state)
+        })
+        .await??;
+
+        self.cache.lock().put(block_root, state.clone());
+        Ok(Some(state))
+    }
+}
@@ -1,47 +1,128 @@
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+    time::Instant,
+};
 
 use alloy_primitives::B256;
 use anyhow::{anyhow, ensure};
 use ream_consensus_lean::{
     attestation::{Attestation, AttestationData, SignedAttestation},
+    blob_sidecar::BlobSidecar,
     block::{Block, BlockBody, BlockWithSignatures, SignedBlockWithAttestation},
     checkpoint::Checkpoint,
     state::LeanState,
-    validator::is_proposer,
 };
-use ream_consensus_misc::constants::lean::INTERVALS_PER_SLOT;
 use ream_metrics::{
     ATTESTATION_VALIDATION_TIME, ATTESTATIONS_INVALID_TOTAL, ATTESTATIONS_VALID_TOTAL,
-    FINALIZED_SLOT, FORK_CHOICE_BLOCK_PROCESSING_TIME, HEAD_SLOT, JUSTIFIED_SLOT,
-    LATEST_FINALIZED_SLOT, LATEST_JUSTIFIED_SLOT, PROPOSE_BLOCK_TIME, VALIDATORS_COUNT,
-    inc_int_counter_vec, set_int_gauge_vec, start_timer, stop_timer,
+    FINALIZED_SLOT, FORK_CHOICE_BLOCK_PROCESSING_TIME, FORK_CHOICE_REORGS_TOTAL, HEAD_SLOT,
+    JUSTIFIED_SLOT, LATEST_FINALIZED_SLOT, LATEST_JUSTIFIED_SLOT, LIVE_FORK_COUNT,
+    PROPOSE_BLOCK_TIME, VALIDATORS_COUNT, inc_int_counter_vec, observe_histogram_vec,
+    set_int_gauge_vec,
+    slot_report::{record_attestations_processed, record_block_import, record_head_change},
+    start_outcome_timer, stop_outcome_timer,
 };
-use ream_network_spec::networks::lean_network_spec;
+use ream_network_spec::networks::{IntervalDuty, lean_network_spec};
 use ream_network_state_lean::NetworkState;
-use ream_post_quantum_crypto::leansig::signature::Signature;
+use ream_node::diagnostics::debug_invariants;
+use ream_post_quantum_crypto::leansig::{public_key::PublicKey, signature::Signature};
 use ream_storage::{
     db::lean::LeanDB,
     tables::{field::REDBField, table::REDBTable},
 };
 use ream_sync::rwlock::{Reader, Writer};
+use serde::{Deserialize, Serialize};
 use ssz_types::{VariableList, typenum::U4096};
-use tokio::sync::Mutex;
+use thiserror::Error;
+use tracing::{error, info, instrument};
 use tree_hash::TreeHash;
 
 use super::utils::is_justifiable_after;
-use crate::constants::JUSTIFICATION_LOOKBACK_SLOTS;
+use crate::{
+    advanced_state::AdvancedStateCache,
+    ancestors::AncestorCache,
+    attestation_pool::AttestationPool,
+    constants::{JUSTIFICATION_LOOKBACK_SLOTS, PROPOSER_SCORE_BOOST},
+    key_rotation_pool::KeyRotationPool,
+    leaf_blocks::LeafBlocks,
+    rejection::{
+        ATTESTATION, BLOCK, ForkChoiceError, RejectReason, RejectedError, record_rejected,
+    },
+    state_regeneration::StateRegenerator,
+};
 
 pub type LeanStoreWriter = Writer<Store>;
 pub type LeanStoreReader = Reader<Store>;
 
+/// A leaf block that is not part of the canonical chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BranchTip {
+    /// The leaf block of the branch.
+    pub tip: Checkpoint,
+    /// The last canonical block the branch is built on, or `None` if the branch doesn't connect
+    /// to the canonical chain through known blocks.
+    pub fork_point: Option<Checkpoint>,
+    /// The number of non-canonical blocks from the fork point to the tip.
+    pub length: u64,
+}
+
+/// Returned by [Store::on_block], as a [ForkChoiceError::FutureBlock], for a block whose slot
+/// hasn't started yet, even allowing for the maximum gossip clock disparity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("Block from future slot {slot}, the latest slot is {latest_slot}")]
+pub struct FutureBlockError {
+    pub slot: u64,
+    pub latest_slot: u64,
+}
+
+/// Where an attestation passed to [Store::on_attestation] comes from, the `source` label of the
+/// attestation metrics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttestationSource {
+    /// Received from a peer over gossip.
+    Gossip,
+    /// Submitted to this node, through the HTTP API or by one of its validators.
+    Api,
+    /// Included in an imported block.
+    Block,
+    /// The proposer attestation of an imported block. It is counted as coming from the block, but
+    /// like attestations from gossip it only becomes known once new attestations are accepted.
+    Proposer,
+}
+
+impl AttestationSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AttestationSource::Gossip => "gossip",
+            AttestationSource::Api => "api",
+            AttestationSource::Block | AttestationSource::Proposer => "block",
+        }
+    }
+}
+
 /// [Store] represents the state that the Lean node should maintain.
 ///
 /// Most of the fields are based on the Python implementation of [`Staker`](https://github.com/ethereum/research/blob/d225a6775a9b184b5c1fd6c830cc58a375d9535f/3sf-mini/p2p.py#L15-L42),
 /// but doesn't include `validator_id` as a node should manage multiple validators.
 #[derive(Debug, Clone)]
 pub struct Store {
-    pub store: Arc<Mutex<LeanDB>>,
+    /// Handles are cheap to clone and redb runs readers concurrently with the single writer, so
+    /// the database isn't wrapped in a lock.
+    pub store: LeanDB,
     pub network_state: Arc<NetworkState>,
+    pub attestation_pool: AttestationPool,
+    /// Whether [Store::on_block] rejects blocks whose blob sidecars are incomplete.
+    pub data_availability: bool,
+    /// The head state advanced to the next slot by [Store::advance_head_state].
+    pub advanced_state: AdvancedStateCache,
+    /// Parent links read by [Store::ancestor_at_slot] and [Store::is_ancestor].
+    pub ancestors: AncestorCache,
+    /// The tips of every branch, counted for the live fork metric.
+    pub leaf_blocks: LeafBlocks,
+    /// Key rotations [Store::produce_block_with_signatures] includes in blocks.
+    pub key_rotation_pool: KeyRotationPool,
+    /// Regenerates the states [Store::get_state_at] doesn't find in the database.
+    pub state_regenerator: StateRegenerator,
 }
 
 impl Store {
@@ -63,7 +144,7 @@ impl Store {
             slot: anchor_slot,
         };
         db.time_provider()
-            .insert(time.unwrap_or(anchor_slot * lean_network_spec().seconds_per_slot))
+            .insert(time.unwrap_or(anchor_slot * lean_network_spec().intervals_per_slot))
             .expect("Failed to insert anchor slot");
         db.block_provider()
             .insert(anchor_root, anchor_block)
@@ -74,6 +155,12 @@ impl Store {
         db.latest_justified_provider()
             .insert(anchor_checkpoint)
             .expect("Failed to insert latest justified checkpoint");
+        let validator_count = anchor_state.validators.len() as u64;
+        set_int_gauge_vec(
+            &VALIDATORS_COUNT,
+            anchor_state.active_validator_indices().len() as i64,
+            &[],
+        );
         db.state_provider()
             .insert(anchor_root, anchor_state)
             .expect("Failed to insert genesis state");
@@ -83,94 +170,195 @@ impl Store {
         db.safe_target_provider()
             .insert(anchor_root)
             .expect("Failed to insert genesis block hash");
+        db.proposer_boost_root_provider()
+            .insert(B256::ZERO)
+            .expect("Failed to insert proposer boost root");
 
-        set_int_gauge_vec(
-            &VALIDATORS_COUNT,
-            lean_network_spec().num_validators as i64,
-            &[],
-        );
+        let leaf_blocks = LeafBlocks::from_blocks(&db.block_provider().get_parent_map()?);
+        let attestation_pool = AttestationPool::new(db.clone());
+        attestation_pool.observe_validator_count(validator_count);
+        attestation_pool.recover()?;
 
         Ok(Store {
-            store: Arc::new(Mutex::new(db)),
+            attestation_pool,
+            state_regenerator: StateRegenerator::new(db.clone()),
+            store: db,
             network_state: Arc::new(NetworkState::new(anchor_checkpoint, anchor_checkpoint)),
+            data_availability: false,
+            advanced_state: AdvancedStateCache::default(),
+            ancestors: AncestorCache::default(),
+            leaf_blocks,
+            key_rotation_pool: KeyRotationPool::default(),
         })
     }
 
+    /// Caps the new and known attestation pools at `max_size` attestations each, or at one per
+    /// validator if there are more validators.
+    pub fn with_attestation_pool_size(mut self, max_size: u64) -> Self {
+        self.attestation_pool = self.attestation_pool.with_max_size(max_size);
+        self
+    }
+
+    /// Writes new attestations from gossip in batches of up to `batch_size`, see
+    /// [AttestationPool::with_batch_size].
+    pub fn with_attestation_batch_size(mut self, batch_size: usize) -> Self {
+        self.attestation_pool = self.attestation_pool.with_batch_size(batch_size);
+        self
+    }
+
+    /// Rejects blocks in [Store::on_block] until every blob sidecar of theirs has been received.
+    /// Meant for data availability experiments on devnets.
+    pub fn with_data_availability(mut self, enabled: bool) -> Self {
+        self.data_availability = enabled;
+        self
+    }
+
     /// Use LMD GHOST to get the head, given a particular root (usually the
-    /// latest known justified block)
+    /// latest known justified block). Unless `proposer_boost_root` is zero, that block and its
+    /// ancestors get [PROPOSER_SCORE_BOOST] percent of the validators active in the state of the
+    /// root as extra weight.
+    ///
+    /// Every attestation walks the blocks back to the root, so the walk runs on the blocking
+    /// thread pool.
     async fn compute_lmd_ghost_head(
         &self,
-        attestations: impl Iterator<Item = anyhow::Result<SignedAttestation>>,
+        attestations: Vec<SignedAttestation>,
         provided_root: B256,
         min_score: u64,
+        proposer_boost_root: B256,
     ) -> anyhow::Result<B256> {
-        let mut root = provided_root;
-
-        let (slot_index_table, block_provider) = {
-            let db = self.store.lock().await;
-            (db.slot_index_provider(), db.block_provider())
+        // Validators register and exit, so the boost follows the active validators of the state.
+        let boost = if proposer_boost_root == B256::ZERO {
+            0
+        } else {
+            let root_state = self
+                .get_state_at(provided_root)
+                .await?
+                .ok_or_else(|| anyhow!("Failed to get state {provided_root} for proposer boost"))?;
+            root_state.active_validator_indices().len() as u64 * PROPOSER_SCORE_BOOST / 100
         };
 
-        // Start at genesis by default
-        if root == B256::ZERO {
-            root = slot_index_table
-                .get_oldest_root()?
-                .ok_or(anyhow!("No blocks found to calculate fork choice"))?;
-        }
+        let head = self
+            .store
+            .run_blocking(move |lean_db| {
+                let (slot_index_table, block_provider) =
+                    (lean_db.slot_index_provider(), lean_db.block_provider());
+
+                // Start at genesis by default
+                let root = match provided_root {
+                    B256::ZERO => match slot_index_table.get_oldest_root()? {
+                        Some(root) => root,
+                        None => return Ok(None),
+                    },
+                    root => root,
+                };
+
+                let start_slot = block_provider.get(root)?.expect("msg").message.block.slot;
+                // For each block, count the number of votes for that block. A vote
+                // for any descendant of a block also counts as a vote for that block
+                let mut weights = HashMap::<B256, u64>::new();
 
-        let start_slot = block_provider.get(root)?.expect("msg").message.block.slot;
-        // For each block, count the number of votes for that block. A vote
-        // for any descendant of a block also counts as a vote for that block
-        let mut weights = HashMap::<B256, u64>::new();
+                for attestation in attestations {
+                    let mut current_root = attestation.message.data.head.root;
 
-        for attestation in attestations {
-            let attestation = attestation?;
-            let mut current_root = attestation.message.data.head.root;
+                    while let Some(block) = block_provider.get(current_root)? {
+                        let block = block.message.block;
 
-            while let Some(block) = block_provider.get(current_root)? {
-                let block = block.message.block;
+                        if block.slot <= start_slot {
+                            break;
+                        }
 
-                if block.slot <= start_slot {
-                    break;
+                        *weights.entry(current_root).or_insert(0) += 1;
+
+                        current_root = block.parent_root;
+                    }
                 }
 
-                *weights.entry(current_root).or_insert(0) += 1;
+                if proposer_boost_root != B256::ZERO {
+                    let mut current_root = proposer_boost_root;
+                    while let Some(block) = block_provider.get(current_root)? {
+                        let block = block.message.block;
 
-                current_root = block.parent_root;
-            }
+                        if block.slot <= start_slot {
+                            break;
+                        }
+
+                        *weights.entry(current_root).or_insert(0) += boost;
+
+                        current_root = block.parent_root;
+                    }
+                }
+
+                // Identify the children of each block
+                let children_map = block_provider.get_children_map(min_score, &weights)?;
+
+                // Start at the root (latest justified hash or genesis) and repeatedly
+                // choose the child with the most latest votes, tiebreaking by slot then hash
+                let mut head = root;
+
+                while let Some(children) = children_map.get(&head) {
+                    let Some(child) = children.iter().max_by_key(|child_hash| {
+                        let vote_weight = weights.get(*child_hash).unwrap_or(&0);
+                        let slot = block_provider
+                            .get(**child_hash)
+                            .map(|maybe_block| match maybe_block {
+                                Some(block) => block.message.block.slot,
+                                None => 0,
+                            })
+                            .unwrap_or(0);
+                        (*vote_weight, slot, *(*child_hash))
+                    }) else {
+                        break;
+                    };
+                    head = *child;
+                }
+
+                Ok(Some(head))
+            })
+            .await?;
+
+        head.ok_or(anyhow!("No blocks found to calculate fork choice"))
+    }
+
+    /// Returns the slot and parent root of the block `block_root`, or `None` if it's unknown.
+    fn block_link(&self, block_root: B256) -> anyhow::Result<Option<(u64, B256)>> {
+        if let Some(link) = self.ancestors.get(block_root) {
+            return Ok(Some(link));
         }
+        let Some(block) = self.store.block_provider().get(block_root)? else {
+            return Ok(None);
+        };
+        let block = block.message.block;
+        self.ancestors
+            .insert(block_root, block.slot, block.parent_root);
+        Ok(Some((block.slot, block.parent_root)))
+    }
 
-        // Identify the children of each block
-        let children_map = block_provider.get_children_map(min_score, &weights)?;
-
-        // Start at the root (latest justified hash or genesis) and repeatedly
-        // choose the child with the most latest votes, tiebreaking by slot then hash
-        let mut head = root;
-
-        while let Some(children) = children_map.get(&head) {
-            head = *children
-                .iter()
-                .max_by_key(|child_hash| {
-                    let vote_weight = weights.get(*child_hash).unwrap_or(&0);
-                    let slot = block_provider
-                        .get(**child_hash)
-                        .map(|maybe_block| match maybe_block {
-                            Some(block) => block.message.block.slot,
-                            None => 0,
-                        })
-                        .unwrap_or(0);
-                    (*vote_weight, slot, *(*child_hash))
-                })
-                .ok_or_else(|| anyhow!("No children found for current root: {head}"))?;
+    /// Returns the latest block at or before `slot` on the chain of `block_root`, which is
+    /// `block_root` itself if it's at or before `slot`. Returns `None` if the chain leaves the
+    /// known blocks first.
+    pub fn ancestor_at_slot(&self, block_root: B256, slot: u64) -> anyhow::Result<Option<B256>> {
+        let mut current = block_root;
+        while let Some((block_slot, parent_root)) = self.block_link(current)? {
+            if block_slot <= slot {
+                return Ok(Some(current));
+            }
+            current = parent_root;
         }
+        Ok(None)
+    }
 
-        Ok(head)
+    /// Returns whether the block `ancestor` is `descendant` or one of its ancestors. Unknown
+    /// blocks are ancestors of nothing.
+    pub fn is_ancestor(&self, ancestor: B256, descendant: B256) -> anyhow::Result<bool> {
+        let Some((ancestor_slot, _)) = self.block_link(ancestor)? else {
+            return Ok(false);
+        };
+        Ok(self.ancestor_at_slot(descendant, ancestor_slot)? == Some(ancestor))
     }
 
     pub async fn get_block_id_by_slot(&self, slot: u64) -> anyhow::Result<B256> {
         self.store
-            .lock()
-            .await
             .slot_index_provider()
             .get(slot)?
             .ok_or_else(|| anyhow!("Block not found in chain for slot: {slot}"))
@@ -179,37 +367,42 @@ impl Store {
     /// Compute the latest block that the validator is allowed to choose as the target
     /// and update as a safe target.
     pub async fn update_safe_target(&self) -> anyhow::Result<()> {
+        self.attestation_pool.flush_pending()?;
         // 2/3rd majority min voting weight for target selection
         // Note that we use ceiling division here.
-        let (
-            head_provider,
-            state_provider,
-            latest_justified_provider,
-            safe_target_provider,
-            latest_new_attestations_provider,
-        ) = {
-            let db = self.store.lock().await;
+        let (head_provider, latest_justified_provider, safe_target_provider) = {
+            let db = &self.store;
             (
                 db.head_provider(),
-                db.state_provider(),
                 db.latest_justified_provider(),
                 db.safe_target_provider(),
-                db.latest_new_attestations_provider(),
             )
         };
 
-        let head_state = state_provider
-            .get(head_provider.get()?)?
-            .ok_or(anyhow!("Failed to get head state for safe target update"))?;
+        let head_root = head_provider.get()?;
+        let (head_state, new_attestations) = self
+            .store
+            .run_blocking(move |lean_db| {
+                Ok((
+                    lean_db.state_provider().get(head_root)?,
+                    lean_db
+                        .latest_new_attestations_provider()
+                        .get_all_attestations()?,
+                ))
+            })
+            .await?;
+        let head_state =
+            head_state.ok_or(anyhow!("Failed to get head state for safe target update"))?;
 
         let min_target_score = (head_state.validators.len() as u64 * 2).div_ceil(3);
         let latest_justified_root = latest_justified_provider.get()?.root;
 
         safe_target_provider.insert(
             self.compute_lmd_ghost_head(
-                latest_new_attestations_provider.iter_values()?,
+                new_attestations.into_values().collect(),
                 latest_justified_root,
                 min_target_score,
+                B256::ZERO,
             )
             .await?,
         )?;
@@ -220,48 +413,46 @@ impl Store {
     /// Process new attestations that the staker has received. Attestation processing is done
     /// at a particular time, because of safe target and view merge rule
     pub async fn accept_new_attestations(&self) -> anyhow::Result<()> {
-        let latest_known_attestation_provider = {
-            let db = self.store.lock().await;
-            db.latest_known_attestations_provider()
-        };
-
-        latest_known_attestation_provider.batch_insert(
-            self.store
-                .lock()
-                .await
-                .latest_new_attestations_provider()
-                .drain()?
-                .into_iter(),
-        )?;
-
+        self.attestation_pool.accept_new()?;
         self.update_head().await?;
         Ok(())
     }
 
+    /// Advances the store by one interval and runs the fork choice duty of the interval, as set by
+    /// the network spec's interval duties.
     pub async fn tick_interval(&self, has_proposal: bool) -> anyhow::Result<()> {
+        self.attestation_pool.flush_pending()?;
         let current_interval = {
-            let time_provider = self.store.lock().await.time_provider();
+            let time_provider = self.store.time_provider();
             let time = time_provider.get()? + 1;
             time_provider.insert(time)?;
-            time % lean_network_spec().seconds_per_slot % INTERVALS_PER_SLOT
+            time % lean_network_spec().intervals_per_slot
         };
         if current_interval == 0 {
-            if has_proposal {
-                self.accept_new_attestations().await?;
-            }
-        } else if current_interval == 2 {
-            self.update_safe_target().await?;
-        } else if current_interval == 3 {
-            self.accept_new_attestations().await?;
-        };
+            // A block is only boosted during its own slot.
+            self.store
+                .proposer_boost_root_provider()
+                .insert(B256::ZERO)?;
+        }
+        match lean_network_spec().interval_duty(current_interval) {
+            Some(IntervalDuty::Propose) if has_proposal => self.accept_new_attestations().await?,
+            Some(IntervalDuty::SafeTarget) => self.update_safe_target().await?,
+            Some(IntervalDuty::AcceptAttestations) => self.accept_new_attestations().await?,
+            _ => {}
+        }
         Ok(())
     }
 
+    /// Advances the store to `time` seconds since the UNIX epoch. An interval which starts within
+    /// the maximum gossip clock disparity of `time` counts as started, so a clock running slightly
+    /// behind doesn't hold the store back from the rest of the network.
     pub async fn on_tick(&self, time: u64, has_proposal: bool) -> anyhow::Result<()> {
-        let seconds_per_interval = lean_network_spec().seconds_per_slot / INTERVALS_PER_SLOT;
-        let tick_interval_time = (time - lean_network_spec().genesis_time) / seconds_per_interval;
+        let spec = lean_network_spec();
+        let elapsed_millis = time.saturating_sub(spec.genesis_time) * 1000;
+        let tick_interval_time = (elapsed_millis + spec.maximum_gossip_clock_disparity)
+            / spec.interval_duration().as_millis() as u64;
 
-        let time_provider = self.store.lock().await.time_provider();
+        let time_provider = self.store.time_provider();
         while time_provider.get()? < tick_interval_time {
             let should_signal_proposal =
                 has_proposal && (time_provider.get()? + 1) == tick_interval_time;
@@ -271,24 +462,69 @@ impl Store {
         Ok(())
     }
 
+    /// Checks that the head and checkpoints stored in the database agree with each other. Only
+    /// run when debug invariants are enabled, as it reads the head block and state.
+    pub async fn check_invariants(&self) -> anyhow::Result<()> {
+        let (head_provider, block_provider, state_provider, latest_justified, latest_finalized) = {
+            let db = &self.store;
+            (
+                db.head_provider(),
+                db.block_provider(),
+                db.state_provider(),
+                db.latest_justified_provider().get()?,
+                db.latest_finalized_provider().get()?,
+            )
+        };
+
+        ensure!(
+            latest_justified.slot >= latest_finalized.slot,
+            "Latest justified slot {} is behind latest finalized slot {}",
+            latest_justified.slot,
+            latest_finalized.slot
+        );
+
+        let head = head_provider.get()?;
+        let head_block = block_provider
+            .get(head)?
+            .ok_or_else(|| anyhow!("Head block {head} is not stored"))?;
+        ensure!(
+            head_block.message.block.slot >= latest_finalized.slot,
+            "Head slot {} is behind latest finalized slot {}",
+            head_block.message.block.slot,
+            latest_finalized.slot
+        );
+        ensure!(
+            state_provider.get(head)?.is_some(),
+            "State for head block {head} is not stored"
+        );
+        Ok(())
+    }
+
     /// Done upon processing new attestations or a new block
     pub async fn update_head(&self) -> anyhow::Result<()> {
-        let (latest_known_attestations, latest_justified_provider, head_provider, block_provider) = {
-            let db = self.store.lock().await;
+        let (latest_justified_provider, head_provider, block_provider) = {
+            let db = &self.store;
             (
-                db.latest_known_attestations_provider()
-                    .get_all_attestations()?,
                 db.latest_justified_provider(),
                 db.head_provider(),
                 db.block_provider(),
             )
         };
+        let latest_known_attestations = self
+            .store
+            .run_blocking(|lean_db| {
+                lean_db
+                    .latest_known_attestations_provider()
+                    .get_all_attestations()
+            })
+            .await?;
 
         let new_head = self
             .compute_lmd_ghost_head(
-                latest_known_attestations.into_values().map(Ok),
+                latest_known_attestations.into_values().collect(),
                 latest_justified_provider.get()?.root,
                 0,
+                self.store.proposer_boost_root_provider().get()?,
             )
             .await?;
 
@@ -309,77 +545,260 @@ impl Store {
             root: head_block.message.block.tree_hash_root(),
             slot: head_block.message.block.slot,
         };
+        let old_head = head_provider.get()?;
+        if old_head != new_head {
+            record_head_change();
+            if !self.is_ancestor(old_head, new_head)? {
+                info!("Reorg from head {old_head} to {new_head}");
+                inc_int_counter_vec(&FORK_CHOICE_REORGS_TOTAL, &[]);
+            }
+        }
         head_provider.insert(new_head)?;
 
         Ok(())
     }
 
+    /// Returns all leaf blocks that are not on the canonical chain, together with the canonical
+    /// block they forked from and the length of their branch. Sorted by tip slot, newest first.
+    pub async fn branch_tips(&self) -> anyhow::Result<Vec<BranchTip>> {
+        let (lean_db, head_provider) = {
+            let db = &self.store;
+            (db.clone(), db.head_provider())
+        };
+        let blocks = lean_db
+            .run_blocking(|lean_db| lean_db.block_provider().get_parent_map())
+            .await?;
+
+        let mut canonical = HashSet::new();
+        let mut current = head_provider.get()?;
+        while let Some((_, parent_root)) = blocks.get(&current) {
+            canonical.insert(current);
+            current = *parent_root;
+        }
+
+        let parents: HashSet<B256> = blocks
+            .values()
+            .map(|(_, parent_root)| *parent_root)
+            .collect();
+
+        let mut branch_tips = vec![];
+        for (root, (slot, _)) in &blocks {
+            if parents.contains(root) || canonical.contains(root) {
+                continue;
+            }
+
+            let mut length = 0;
+            let mut current = *root;
+            let fork_point = loop {
+                if canonical.contains(&current) {
+                    break blocks.get(&current).map(|(slot, _)| Checkpoint {
+                        root: current,
+                        slot: *slot,
+                    });
+                }
+                match blocks.get(&current) {
+                    Some((_, parent_root)) => {
+                        length += 1;
+                        current = *parent_root;
+                    }
+                    None => break None,
+                }
+            };
+
+            branch_tips.push(BranchTip {
+                tip: Checkpoint {
+                    root: *root,
+                    slot: *slot,
+                },
+                fork_point,
+                length,
+            });
+        }
+        branch_tips.sort_by(|a, b| b.tip.slot.cmp(&a.tip.slot));
+
+        Ok(branch_tips)
+    }
+
+    /// Checks whether a finalized checkpoint reported by a peer can be on the same chain as ours.
+    /// One at or before our finalized slot must be our finalized block or one of its ancestors,
+    /// and a later one we know of must descend from our finalized block. A checkpoint we can't
+    /// place yet, such as a block we haven't seen, is taken to be compatible.
+    pub async fn is_compatible_finalized_checkpoint(
+        &self,
+        checkpoint: Checkpoint,
+    ) -> anyhow::Result<bool> {
+        // The checkpoints of the genesis state stay zeroed until a block justifies one.
+        if checkpoint.root == B256::ZERO {
+            return Ok(checkpoint.slot == 0);
+        }
+
+        let latest_finalized = self.store.latest_finalized_provider().get()?;
+        let (descendant, ancestor) = if checkpoint.slot <= latest_finalized.slot {
+            (latest_finalized, checkpoint)
+        } else {
+            (checkpoint, latest_finalized)
+        };
+        // The walk reads every block missing from the ancestor cache, keep it off the async
+        // workers.
+        let store = self.clone();
+        let ancestor_root = tokio::task::spawn_blocking(move || {
+            store.ancestor_at_slot(descendant.root, ancestor.slot)
+        })
+        .await??;
+        Ok(ancestor_root.is_none_or(|root| root == ancestor.root))
+    }
+
+    /// Returns the post state of the block `block_root`, regenerating it with
+    /// [StateRegenerator] if it's no longer stored. Returns `None` if the block, or an ancestor
+    /// needed to replay it, is unknown.
+    pub async fn get_state_at(&self, block_root: B256) -> anyhow::Result<Option<LeanState>> {
+        self.state_regenerator.get_state_at(block_root).await
+    }
+
+    /// Advances a copy of the head state to `slot` and caches it, so importing or producing the
+    /// block of `slot` on the head skips processing the slots. Does nothing if it's already
+    /// cached or the head is at `slot` or later.
+    pub async fn advance_head_state(&self, slot: u64) -> anyhow::Result<()> {
+        let head_root = self.store.head_provider().get()?;
+        if self.advanced_state.contains(head_root, slot) {
+            return Ok(());
+        }
+        // The head state is always stored, so it's never regenerated here.
+        let Some(mut state) = self
+            .store
+            .run_blocking(move |lean_db| lean_db.state_provider().get(head_root))
+            .await?
+        else {
+            return Err(anyhow!("State not found for head {head_root}"));
+        };
+        if state.slot >= slot {
+            return Ok(());
+        }
+
+        // Processing slots hashes the whole state, keep it off the async workers.
+        let state = tokio::task::spawn_blocking(move || {
+            state.process_slots(slot)?;
+            anyhow::Ok(state)
+        })
+        .await??;
+        self.advanced_state.insert(head_root, state);
+        Ok(())
+    }
+
+    /// Returns the fraction of the active validators of the head state whose latest known
+    /// attestation targets a block on the canonical chain since the latest finalized slot, or 0
+    /// without active validators.
+    pub async fn canonical_participation(&self) -> anyhow::Result<f64> {
+        let active_validators = self.head_state().await?.active_validator_indices();
+        if active_validators.is_empty() {
+            return Ok(0.0);
+        }
+        let attestations = self
+            .store
+            .latest_known_attestations_provider()
+            .get_all_attestations()?;
+        let Some(oldest_target_slot) = attestations
+            .values()
+            .map(|attestation| attestation.message.data.target.slot)
+            .min()
+        else {
+            return Ok(0.0);
+        };
+
+        // Only the canonical chain back to the oldest target is walked, older targets can't be
+        // behind the latest finalized block.
+        let oldest_slot =
+            oldest_target_slot.max(self.store.latest_finalized_provider().get()?.slot);
+        let mut canonical = HashSet::new();
+        let mut current = self.store.head_provider().get()?;
+        while let Some((slot, parent_root)) = self.block_link(current)?
+            && slot >= oldest_slot
+        {
+            canonical.insert(current);
+            current = parent_root;
+        }
+
+        let participating = active_validators
+            .iter()
+            .filter(|validator_index| {
+                attestations
+                    .get(validator_index)
+                    .is_some_and(|attestation| {
+                        canonical.contains(&attestation.message.data.target.root)
+                    })
+            })
+            .count();
+        Ok(participating as f64 / active_validators.len() as f64)
+    }
+
     pub async fn get_attestation_target(&self) -> anyhow::Result<Checkpoint> {
-        let (head_provider, block_provider, safe_target_provider, latest_finalized_provider) = {
-            let db = self.store.lock().await;
+        let (head_provider, safe_target_provider, latest_finalized_provider) = {
+            let db = &self.store;
             (
                 db.head_provider(),
-                db.block_provider(),
                 db.safe_target_provider(),
                 db.latest_finalized_provider(),
             )
         };
+        let target_link = |block_root| {
+            self.block_link(block_root)?
+                .ok_or(anyhow!("Block not found for target block root"))
+        };
 
+        let (safe_target_slot, _) = self
+            .block_link(safe_target_provider.get()?)?
+            .ok_or(anyhow!("Block not found for safe target"))?;
         let mut target_block_root = head_provider.get()?;
+        let (mut target_slot, mut parent_root) = target_link(target_block_root)?;
 
         for _ in 0..JUSTIFICATION_LOOKBACK_SLOTS {
-            if block_provider
-                .get(target_block_root)?
-                .ok_or(anyhow!("Block not found for target block root"))?
-                .message
-                .block
-                .slot
-                > block_provider
-                    .get(safe_target_provider.get()?)?
-                    .ok_or(anyhow!("Block not found for safe target"))?
-                    .message
-                    .block
-                    .slot
-            {
-                target_block_root = block_provider
-                    .get(target_block_root)?
-                    .ok_or(anyhow!("Block not found for target block root"))?
-                    .message
-                    .block
-                    .parent_root;
-            } else {
+            if target_slot <= safe_target_slot {
                 break;
             }
+            target_block_root = parent_root;
+            (target_slot, parent_root) = target_link(target_block_root)?;
         }
 
         let latest_finalized_slot = latest_finalized_provider.get()?.slot;
-        while !is_justifiable_after(
-            block_provider
-                .get(target_block_root)?
-                .ok_or(anyhow!("Block not found for target block root"))?
-                .message
-                .block
-                .slot,
-            latest_finalized_slot,
-        )? {
-            target_block_root = block_provider
-                .get(target_block_root)?
-                .ok_or(anyhow!("Block not found for target block root"))?
-                .message
-                .block
-                .parent_root;
+        while !is_justifiable_after(target_slot, latest_finalized_slot)? {
+            target_block_root = parent_root;
+            (target_slot, parent_root) = target_link(target_block_root)?;
         }
 
-        let target_block = block_provider
-            .get(target_block_root)?
-            .ok_or(anyhow!("Block not found for target block root"))?;
-
         Ok(Checkpoint {
-            root: target_block.message.block.tree_hash_root(),
-            slot: target_block.message.block.slot,
+            root: target_block_root,
+            slot: target_slot,
         })
     }
 
+    /// Returns the post state of the current head.
+    pub async fn head_state(&self) -> anyhow::Result<LeanState> {
+        let head_root = self.store.head_provider().get()?;
+        self.store
+            .run_blocking(move |lean_db| lean_db.state_provider().get(head_root))
+            .await?
+            .ok_or_else(|| anyhow!("State not found for head {head_root}"))
+    }
+
+    /// Returns the index of the validator proposing at `slot` on the current head.
+    pub async fn proposer_index(&self, slot: u64) -> anyhow::Result<u64> {
+        self.head_state().await?.proposer_index_at(slot)
+    }
+
+    /// Returns the key the validator at `validator_index` signs with at `slot` on the current
+    /// head, or `None` for an unknown validator.
+    pub async fn validator_public_key(
+        &self,
+        validator_index: u64,
+        slot: u64,
+    ) -> anyhow::Result<Option<PublicKey>> {
+        Ok(self
+            .head_state()
+            .await?
+            .validator_public_key_at(validator_index, slot)
+            .copied())
+    }
+
     /// Get the head for block proposal at given slot.
     /// Ensures store is up-to-date and processes any pending attestations.
     pub async fn get_proposal_head(&self, slot: u64) -> anyhow::Result<B256> {
@@ -387,42 +806,80 @@ impl Store {
             lean_network_spec().genesis_time + slot * lean_network_spec().seconds_per_slot;
         self.on_tick(slot_time, true).await?;
         self.accept_new_attestations().await?;
-        Ok(self.store.lock().await.head_provider().get()?)
+        Ok(self.store.head_provider().get()?)
     }
 
+    #[instrument(skip(self))]
     pub async fn produce_block_with_signatures(
         &self,
         slot: u64,
         validator_index: u64,
     ) -> anyhow::Result<BlockWithSignatures> {
         let head_root = self.get_proposal_head(slot).await?;
-        let initialize_block_timer = start_timer(&PROPOSE_BLOCK_TIME, &["initialize_block"]);
-        let (state_provider, latest_known_attestation_provider, block_provider) = {
-            let db = self.store.lock().await;
-            (
-                db.state_provider(),
-                db.latest_known_attestations_provider(),
-                db.block_provider(),
-            )
+        let initialize_block_timer =
+            start_outcome_timer(&PROPOSE_BLOCK_TIME, &["initialize_block"]);
+        let (latest_known_attestation_provider, block_provider) = {
+            let db = &self.store;
+            (db.latest_known_attestations_provider(), db.block_provider())
         };
-        let head_state = state_provider
-            .get(head_root)?
-            .ok_or(anyhow!("State not found for head root"))?;
-        stop_timer(initialize_block_timer);
-
-        let num_validators = head_state.validators.len();
+        let base_state = match self.advanced_state.get(head_root, slot) {
+            Some(advanced_state) => advanced_state,
+            None => {
+                let mut head_state = self
+                    .store
+                    .run_blocking(move |lean_db| lean_db.state_provider().get(head_root))
+                    .await?
+                    .ok_or(anyhow!("State not found for head root"))?;
+                head_state.process_slots(slot)?;
+                head_state
+            }
+        };
+        stop_outcome_timer(initialize_block_timer);
 
         ensure!(
-            is_proposer(validator_index, slot, num_validators as u64),
+            base_state.is_proposer(validator_index),
             "Validator {validator_index} is not the proposer for slot {slot}"
         );
 
+        let key_rotations = VariableList::try_from(self.key_rotation_pool.includable(&base_state))
+            .map_err(|err| anyhow!("Could not add key rotations: {err:?}"))?;
+
         let add_attestations_timer =
-            start_timer(&PROPOSE_BLOCK_TIME, &["add_valid_attestations_to_block"]);
+            start_outcome_timer(&PROPOSE_BLOCK_TIME, &["add_valid_attestations_to_block"]);
+
+        // Group the attestations which can be included by their source, in a deterministic order
+        // which keeps attestations with the same data together.
+        let mut attestations_by_source: HashMap<Checkpoint, Vec<SignedAttestation>> =
+            HashMap::new();
+        for signed_attestation in latest_known_attestation_provider
+            .get_all_attestations()?
+            .into_values()
+        {
+            if !block_provider.contains_key(signed_attestation.message.data.head.root) {
+                continue;
+            }
+            attestations_by_source
+                .entry(signed_attestation.message.data.source)
+                .or_default()
+                .push(signed_attestation);
+        }
+        for group in attestations_by_source.values_mut() {
+            group.sort_by_key(|signed_attestation| {
+                let data = &signed_attestation.message.data;
+                (
+                    data.slot,
+                    data.target.slot,
+                    data.head.root,
+                    signed_attestation.message.validator_id,
+                )
+            });
+        }
 
+        // Only attestations whose source is the justified checkpoint count, and including them
+        // can justify a later checkpoint. Each group is added at most once, so the state
+        // transition only runs again when the justified checkpoint moves.
         let mut attestations = VariableList::empty();
         let mut signatures: Vec<Signature> = Vec::new();
-
         let (mut candidate_block, post_state) = loop {
             let candidate_block = Block {
                 slot,
@@ -430,52 +887,30 @@ impl Store {
                 parent_root: head_root,
                 state_root: B256::ZERO,
                 body: BlockBody {
-                    attestations: attestations.clone(),
+                    key_rotations: key_rotations.clone(),
+                    ..BlockBody::new(attestations.clone())
                 },
             };
-            let mut advanced_state = head_state.clone();
-            advanced_state.process_slots(slot)?;
+            let mut advanced_state = base_state.clone();
             advanced_state.process_block(&candidate_block)?;
 
-            let mut new_attestations: VariableList<Attestation, U4096> = VariableList::empty();
-            let mut new_signatures: Vec<Signature> = Vec::new();
-            for signed_attestation in latest_known_attestation_provider
-                .get_all_attestations()?
-                .values()
-            {
-                let data = &signed_attestation.message.data;
-                if !block_provider.contains_key(data.head.root) {
-                    continue;
-                }
-                if data.source != advanced_state.latest_justified {
-                    continue;
-                }
-                if !attestations.contains(&signed_attestation.message) {
-                    new_attestations
-                        .push(signed_attestation.message.clone())
-                        .map_err(|err| anyhow!("Could not append attestation: {err:?}"))?;
-                    new_signatures.push(signed_attestation.signature);
-                }
-            }
-            if new_attestations.is_empty() {
+            let Some(group) = attestations_by_source.remove(&advanced_state.latest_justified)
+            else {
                 break (candidate_block, advanced_state);
-            }
-
-            for attestation in new_attestations {
+            };
+            for signed_attestation in group {
                 attestations
-                    .push(attestation)
+                    .push(signed_attestation.message)
                     .map_err(|err| anyhow!("Could not append attestation: {err:?}"))?;
-            }
-
-            for signature in new_signatures {
-                signatures.push(signature);
+                signatures.push(signed_attestation.signature);
             }
         };
-        stop_timer(add_attestations_timer);
+        stop_outcome_timer(add_attestations_timer);
 
-        let compute_state_root_timer = start_timer(&PROPOSE_BLOCK_TIME, &["compute_state_root"]);
+        let compute_state_root_timer =
+            start_outcome_timer(&PROPOSE_BLOCK_TIME, &["compute_state_root"]);
         candidate_block.state_root = post_state.tree_hash_root();
-        stop_timer(compute_state_root_timer);
+        stop_outcome_timer(compute_state_root_timer);
         Ok(BlockWithSignatures {
             block: candidate_block,
             signatures: VariableList::new(signatures)
@@ -483,17 +918,40 @@ impl Store {
         })
     }
 
+    /// Imports a block, counting it by [RejectReason] if it is rejected. A block from a future
+    /// slot is returned as a [ForkChoiceError::FutureBlock] and left for the caller to count, as
+    /// it may still be queued until its slot starts.
+    #[instrument(skip_all, fields(slot = signed_block_with_attestation.message.block.slot))]
     pub async fn on_block(
         &mut self,
         signed_block_with_attestation: &SignedBlockWithAttestation,
         verify_signatures: bool,
+    ) -> Result<(), ForkChoiceError> {
+        let result = self
+            .import_block(signed_block_with_attestation, verify_signatures)
+            .await
+            .map_err(ForkChoiceError::from);
+        if let Err(err) = &result
+            && !matches!(err, ForkChoiceError::FutureBlock(_))
+        {
+            record_rejected(BLOCK, err.reject_reason());
+        }
+        result
+    }
+
+    async fn import_block(
+        &mut self,
+        signed_block_with_attestation: &SignedBlockWithAttestation,
+        verify_signatures: bool,
     ) -> anyhow::Result<()> {
-        let block_processing_timer = start_timer(&FORK_CHOICE_BLOCK_PROCESSING_TIME, &[]);
+        // Rejected blocks return early and are timed as failures.
+        let block_processing_timer = start_outcome_timer(&FORK_CHOICE_BLOCK_PROCESSING_TIME, &[]);
+        let block_import_start = Instant::now();
 
-        let (state_provider, block_provider, latest_justified_provider, latest_finalized_provider) = {
-            let db = self.store.lock().await;
+        let (lean_db, block_provider, latest_justified_provider, latest_finalized_provider) = {
+            let db = &self.store;
             (
-                db.state_provider(),
+                db.clone(),
                 db.block_provider(),
                 db.latest_justified_provider(),
                 db.latest_finalized_provider(),
@@ -506,16 +964,55 @@ impl Store {
 
         // If the block is already known, ignore it
         if block_provider.get(block_root)?.is_some() {
-            stop_timer(block_processing_timer);
+            block_processing_timer.discard();
+            record_rejected(BLOCK, RejectReason::Duplicate);
             return Ok(());
         }
 
-        let mut parent_state = state_provider
-            .get(block.parent_root)?
-            .ok_or(anyhow!("State not found for parent root"))?;
+        // Allow for the proposer's clock being slightly ahead of ours.
+        let latest_slot = lean_network_spec().latest_gossip_slot(self.store.time_provider().get()?);
+        if block.slot > latest_slot {
+            return Err(FutureBlockError {
+                slot: block.slot,
+                latest_slot,
+            }
+            .into());
+        }
+
+        ensure!(
+            !self.data_availability || self.is_data_available(block_root)?,
+            RejectedError::new(
+                RejectReason::DataUnavailable,
+                format!("Blob sidecars of block {block_root} are incomplete"),
+            )
+        );
+
+        let parent_root = block.parent_root;
+        let mut parent_state = match self.advanced_state.get(parent_root, block.slot) {
+            Some(advanced_state) => advanced_state,
+            None => lean_db
+                .run_blocking(move |lean_db| lean_db.state_provider().get(parent_root))
+                .await?
+                .ok_or_else(|| {
+                    RejectedError::new(
+                        RejectReason::UnknownParent,
+                        "State not found for parent root",
+                    )
+                })?,
+        };
 
-        signed_block_with_attestation.verify_signatures(&parent_state, verify_signatures)?;
-        parent_state.state_transition(block, true)?;
+        signed_block_with_attestation
+            .verify_signatures(&parent_state, verify_signatures)
+            .map_err(|err| {
+                RejectedError::new(RejectReason::InvalidSignature, format!("{err:#}"))
+            })?;
+        if parent_state.slot == block.slot {
+            parent_state.state_transition_from_advanced(block, true)
+        } else {
+            parent_state.state_transition(block, true)
+        }?;
+        self.attestation_pool
+            .observe_validator_count(parent_state.validators.len() as u64);
 
         let latest_justified =
             if parent_state.latest_justified.slot > latest_justified_provider.get()?.slot {
@@ -524,113 +1021,209 @@ impl Store {
                 latest_justified_provider.get()?
             };
 
-        let latest_finalized =
-            if parent_state.latest_finalized.slot > latest_finalized_provider.get()?.slot {
-                parent_state.latest_finalized
-            } else {
-                latest_finalized_provider.get()?
-            };
+        let previous_finalized_slot = latest_finalized_provider.get()?.slot;
+        let latest_finalized = if parent_state.latest_finalized.slot > previous_finalized_slot {
+            parent_state.latest_finalized
+        } else {
+            latest_finalized_provider.get()?
+        };
 
         set_int_gauge_vec(&JUSTIFIED_SLOT, latest_justified.slot as i64, &[]);
         set_int_gauge_vec(&FINALIZED_SLOT, latest_finalized.slot as i64, &[]);
         set_int_gauge_vec(&LATEST_JUSTIFIED_SLOT, latest_justified.slot as i64, &[]);
         set_int_gauge_vec(&LATEST_FINALIZED_SLOT, latest_finalized.slot as i64, &[]);
 
-        block_provider.insert(block_root, signed_block_with_attestation.clone())?;
-        state_provider.insert(block_root, parent_state)?;
-        latest_justified_provider.insert(latest_justified)?;
-        latest_finalized_provider.insert(latest_finalized)?;
-        *self.network_state.finalized_checkpoint.write() = latest_finalized;
-
-        for (attestation, signature) in signed_block_with_attestation
-            .message
-            .block
+        // Every attestation of the block is validated before anything is written, so a block with
+        // an invalid attestation isn't stored.
+        let mut attestations = Vec::with_capacity(block.body.attestations.len());
+        for (attestation, signature) in block
             .body
             .attestations
             .iter()
-            .zip(signed_block_with_attestation.signature.clone())
+            .zip(signatures.iter().copied())
         {
-            self.on_attestation(
-                SignedAttestation {
-                    message: attestation.clone(),
-                    signature,
-                },
-                true,
-            )
-            .await?;
+            let signed_attestation = SignedAttestation {
+                message: attestation.clone(),
+                signature,
+            };
+            self.validate_and_count_attestation(&signed_attestation, AttestationSource::Block)
+                .await?;
+            attestations.push(signed_attestation);
+        }
+
+        // Commit the block, its post state, the new checkpoints and its attestations with a
+        // single fsync.
+        let signed_block = signed_block_with_attestation.clone();
+        let attestation_pool = self.attestation_pool.clone();
+        tokio::task::spawn_blocking(move || {
+            attestation_pool.insert_from_block_with(attestations, |batch| {
+                batch.insert_block(block_root, &signed_block)?;
+                batch.insert_state(block_root, &parent_state)?;
+                batch.set_latest_justified(latest_justified)?;
+                batch.set_latest_finalized(latest_finalized)?;
+                batch.index_finalized_chain(previous_finalized_slot, latest_finalized)
+            })
+        })
+        .await??;
+        *self.network_state.finalized_checkpoint.write() = latest_finalized;
+        self.ancestors
+            .insert(block_root, block.slot, block.parent_root);
+        self.leaf_blocks.insert(block_root, block.parent_root);
+
+        // Boost the first block which arrives in the first interval of its own slot.
+        let time = self.store.time_provider().get()?;
+        let intervals_per_slot = lean_network_spec().intervals_per_slot;
+        let proposer_boost_root_provider = self.store.proposer_boost_root_provider();
+        if block.slot == time / intervals_per_slot
+            && time % intervals_per_slot == 0
+            && proposer_boost_root_provider.get()? == B256::ZERO
+        {
+            proposer_boost_root_provider.insert(block_root)?;
+        }
+
+        // Attestations for slots before the finalized slot can't add weight past the justified
+        // block, which fork choice starts from.
+        if latest_finalized.slot > previous_finalized_slot {
+            self.attestation_pool.prune(latest_finalized.slot)?;
+            self.ancestors.prune(latest_finalized.slot);
         }
 
         self.update_head().await?;
+        set_int_gauge_vec(
+            &LIVE_FORK_COUNT,
+            self.leaf_blocks
+                .fork_count(self.store.head_provider().get()?) as i64,
+            &[],
+        );
+        if debug_invariants()
+            && let Err(err) = self.check_invariants().await
+        {
+            error!("Fork choice invariant violated after importing block {block_root}: {err:?}");
+        }
 
         self.on_attestation(
             SignedAttestation {
                 message: proposer_attestation.clone(),
                 signature: *signatures
                     .get(block.body.attestations.len())
-                    .ok_or(anyhow!("Failed to get attestation"))?,
+                    .ok_or_else(|| {
+                        RejectedError::new(
+                            RejectReason::InvalidSignature,
+                            "Block has no signature for the proposer attestation",
+                        )
+                    })?,
             },
-            false,
+            AttestationSource::Proposer,
         )
         .await?;
 
-        stop_timer(block_processing_timer);
+        stop_outcome_timer(block_processing_timer);
+        record_block_import(block_import_start.elapsed());
+        Ok(())
+    }
+
+    /// Whether every blob of the block at `block_root` has been received. Lean blocks don't commit
+    /// to their blobs, so a block is only available once its sidecars have arrived, a block
+    /// without blobs included, see [BlobSidecar].
+    pub fn is_data_available(&self, block_root: B256) -> anyhow::Result<bool> {
+        let sidecars = self
+            .store
+            .blob_sidecar_provider()
+            .get_block_sidecars(block_root)?;
+        let Some(first) = sidecars.first() else {
+            return Ok(false);
+        };
+        // Sidecars are stored by index, so they're complete once there is one for each index and
+        // all of them agree on the blob count.
+        Ok(sidecars
+            .iter()
+            .all(|sidecar| sidecar.blob_count == first.blob_count)
+            && sidecars.len() as u64 == first.sidecar_count())
+    }
+
+    /// Stores a blob sidecar received from the network, once it agrees with every sidecar already
+    /// known for its block.
+    pub fn on_blob_sidecar(&self, blob_sidecar: BlobSidecar) -> anyhow::Result<()> {
+        blob_sidecar.validate()?;
+        let blob_sidecar_provider = self.store.blob_sidecar_provider();
+        for known in blob_sidecar_provider.get_block_sidecars(blob_sidecar.block_root)? {
+            ensure!(
+                known.blob_count == blob_sidecar.blob_count,
+                "Blob sidecar claims {} blobs for block {}, sidecar {} claims {}",
+                blob_sidecar.blob_count,
+                blob_sidecar.block_root,
+                known.index,
+                known.blob_count
+            );
+        }
+        blob_sidecar_provider.insert(blob_sidecar.identifier(), blob_sidecar)?;
         Ok(())
     }
 
     pub async fn validate_attestation(
         &self,
         signed_attestation: &SignedAttestation,
-    ) -> anyhow::Result<()> {
+    ) -> Result<(), ForkChoiceError> {
         let data = &signed_attestation.message.data;
-        let block_provider = self.store.lock().await.block_provider();
+        let block_provider = self.store.block_provider();
 
-        // Validate attestation targets exist in store
-        ensure!(
-            block_provider.contains_key(data.source.root),
-            "Unknown source block: {}",
-            data.source.root
-        );
-        ensure!(
-            block_provider.contains_key(data.target.root),
-            "Unknown target block: {}",
-            data.target.root
-        );
-        ensure!(
-            block_provider.contains_key(data.head.root),
-            "Unknown head block: {}",
-            data.head.root
-        );
-        ensure!(
-            data.source.slot <= data.target.slot,
-            "Source checkpoint slot must not exceed target"
-        );
+        // Votes of validators no state has are never counted, and would only fill the pools.
+        let validator_id = signed_attestation.message.validator_id;
+        if validator_id >= self.attestation_pool.validator_count() {
+            return Err(invalid_attestation(&format!(
+                "Unknown validator {validator_id}"
+            )));
+        }
 
-        // Validate slot relationships
-        let source_block = block_provider
+        // Validate attestation targets exist in store
+        for (name, root) in [
+            ("source", data.source.root),
+            ("target", data.target.root),
+            ("head", data.head.root),
+        ] {
+            if !block_provider.contains_key(root) {
+                return Err(RejectedError::new(
+                    RejectReason::UnknownBlock,
+                    format!("Unknown {name} block: {root}"),
+                )
+                .into());
+            }
+        }
+        if data.source.slot > data.target.slot {
+            return Err(invalid_attestation(
+                "Source checkpoint slot must not exceed target",
+            ));
+        }
+
+        // Validate slot relationships
+        let source_block = block_provider
             .get(data.source.root)?
             .ok_or(anyhow!("Failed to get source block"))?;
 
         let target_block = block_provider
             .get(data.target.root)?
             .ok_or(anyhow!("Failed to get target block"))?;
-        ensure!(
-            source_block.message.block.slot == data.source.slot,
-            "Source checkpoint slot mismatch"
-        );
+        if source_block.message.block.slot != data.source.slot {
+            return Err(invalid_attestation("Source checkpoint slot mismatch"));
+        }
 
-        ensure!(
-            target_block.message.block.slot == data.target.slot,
-            "Target checkpoint slot mismatch"
-        );
+        if target_block.message.block.slot != data.target.slot {
+            return Err(invalid_attestation("Target checkpoint slot mismatch"));
+        }
 
         let current_slot =
-            self.store.lock().await.time_provider().get()? / lean_network_spec().seconds_per_slot;
-        ensure!(
-            data.slot <= current_slot + 1,
-            "Attestation too far in future expected slot: {} <= {}",
-            data.slot,
-            current_slot + 1,
-        );
+            self.store.time_provider().get()? / lean_network_spec().intervals_per_slot;
+        if data.slot > current_slot + 1 {
+            return Err(RejectedError::new(
+                RejectReason::FutureSlot,
+                format!(
+                    "Attestation too far in future expected slot: {} <= {}",
+                    data.slot,
+                    current_slot + 1,
+                ),
+            )
+            .into());
+        }
 
         Ok(())
     }
@@ -638,69 +1231,70 @@ impl Store {
     pub async fn on_attestation(
         &self,
         signed_attestation: SignedAttestation,
-        is_from_block: bool,
-    ) -> anyhow::Result<()> {
-        let (latest_known_attestations_provider, latest_new_attestations_provider, time_provider) = {
-            let db = self.store.lock().await;
-            (
-                db.latest_known_attestations_provider(),
-                db.latest_new_attestations_provider(),
-                db.time_provider(),
-            )
-        };
-
-        let validate_attestation_timer = start_timer(&ATTESTATION_VALIDATION_TIME, &[]);
+        source: AttestationSource,
+    ) -> Result<(), ForkChoiceError> {
+        self.validate_and_count_attestation(&signed_attestation, source)
+            .await?;
 
-        match self.validate_attestation(&signed_attestation).await {
-            Ok(_) => {
-                inc_int_counter_vec(&ATTESTATIONS_VALID_TOTAL, &[]);
-                stop_timer(validate_attestation_timer);
-            }
-            Err(err) => {
-                inc_int_counter_vec(&ATTESTATIONS_INVALID_TOTAL, &[]);
-                stop_timer(validate_attestation_timer);
-                return Err(err);
+        if source == AttestationSource::Block {
+            self.attestation_pool
+                .insert_from_block(vec![signed_attestation])?;
+        } else {
+            // Allow for the attester's clock being slightly ahead of ours.
+            let attestation_slot = signed_attestation.message.data.slot;
+            let latest_slot =
+                lean_network_spec().latest_gossip_slot(self.store.time_provider().get()?);
+            if attestation_slot > latest_slot {
+                record_rejected(ATTESTATION, RejectReason::FutureSlot);
+                return Err(RejectedError::new(
+                    RejectReason::FutureSlot,
+                    format!("Attestation from future slot {attestation_slot} <= {latest_slot}"),
+                )
+                .into());
             }
+            self.attestation_pool.insert_new(signed_attestation)?;
         }
 
-        let validator_id = signed_attestation.message.validator_id;
-        let attestation_slot = signed_attestation.message.data.slot;
-        if is_from_block {
-            let latest_known = match latest_known_attestations_provider.get(validator_id)? {
-                Some(latest_known) => latest_known.message.data.slot < attestation_slot,
-                None => true,
-            };
-            if latest_known {
-                latest_known_attestations_provider.insert(validator_id, signed_attestation)?;
-            }
-            let remove = match latest_new_attestations_provider.get(validator_id)? {
-                Some(new_new) => new_new.message.data.slot <= attestation_slot,
-                None => false,
-            };
-            if remove {
-                latest_new_attestations_provider.remove(validator_id)?;
-            }
+        Ok(())
+    }
+
+    /// Validates an attestation from `source`, recording the validation time and whether it was
+    /// valid.
+    async fn validate_and_count_attestation(
+        &self,
+        signed_attestation: &SignedAttestation,
+        source: AttestationSource,
+    ) -> Result<(), ForkChoiceError> {
+        let validation_start = Instant::now();
+        let validation = self.validate_attestation(signed_attestation).await;
+        let outcome = if validation.is_ok() {
+            "success"
         } else {
-            let time_slots = time_provider.get()? / lean_network_spec().seconds_per_slot;
-            ensure!(
-                attestation_slot <= time_slots,
-                "Attestation from future slot {attestation_slot} <= {time_slots}",
-            );
-            let latest_new = match latest_new_attestations_provider.get(validator_id)? {
-                Some(latest_new) => latest_new.message.data.slot < attestation_slot,
-                None => true,
-            };
-            if latest_new {
-                latest_new_attestations_provider.insert(validator_id, signed_attestation)?;
+            "failure"
+        };
+        observe_histogram_vec(
+            &ATTESTATION_VALIDATION_TIME,
+            validation_start.elapsed().as_secs_f64(),
+            &[source.as_str(), outcome],
+        );
+
+        match validation {
+            Ok(()) => {
+                inc_int_counter_vec(&ATTESTATIONS_VALID_TOTAL, &[source.as_str()]);
+                record_attestations_processed(1);
+                Ok(())
+            }
+            Err(err) => {
+                inc_int_counter_vec(&ATTESTATIONS_INVALID_TOTAL, &[source.as_str()]);
+                record_rejected(ATTESTATION, err.reject_reason());
+                Err(err)
             }
         }
-
-        Ok(())
     }
 
     pub async fn produce_attestation_data(&self, slot: u64) -> anyhow::Result<AttestationData> {
         let (head_provider, block_provider, latest_justified_provider) = {
-            let db = self.store.lock().await;
+            let db = &self.store;
             (
                 db.head_provider(),
                 db.block_provider(),
@@ -726,54 +1320,36 @@ impl Store {
     }
 }
 
+/// An attestation which fails a check without a [RejectReason] of its own.
+fn invalid_attestation(message: &str) -> ForkChoiceError {
+    RejectedError::new(RejectReason::Invalid, message).into()
+}
+
 #[cfg(test)]
 mod tests {
     use alloy_primitives::B256;
     use ream_consensus_lean::{
         attestation::{Attestation, AttestationData, SignedAttestation},
-        block::{Block, BlockWithAttestation, BlockWithSignatures, SignedBlockWithAttestation},
+        blob_sidecar::BlobSidecar,
+        block::{Block, BlockBody, BlockWithSignatures},
         checkpoint::Checkpoint,
         state::LeanState,
-        utils::generate_default_validators,
     };
-    use ream_network_spec::networks::{LeanNetworkSpec, set_lean_network_spec};
+    use ream_metrics::{ATTESTATIONS_INVALID_TOTAL, ATTESTATIONS_VALID_TOTAL};
+    use ream_network_spec::networks::{LeanNetworkSpec, lean_network_spec, set_lean_network_spec};
     use ream_post_quantum_crypto::leansig::signature::Signature;
-    use ream_storage::{
-        db::{ReamDB, lean::LeanDB},
-        tables::{field::REDBField, table::REDBTable},
+    use ream_storage::tables::{field::REDBField, table::REDBTable};
+    use ream_test_utils::{
+        build_signed_block_with_attestation, chain::ChainBuilder, db_setup, genesis,
     };
-    use ssz_types::{VariableList, typenum::U4096};
-    use tempdir::TempDir;
+    use ssz_types::VariableList;
     use tree_hash::TreeHash;
 
-    use super::Store;
-    use crate::genesis::setup_genesis;
-
-    pub fn db_setup() -> LeanDB {
-        let temp_dir = TempDir::new("lean_test").unwrap();
-        let temp_path = temp_dir.path().to_path_buf();
-        let ream_db = ReamDB::new(temp_path).expect("unable to init Ream Database");
-        ream_db.init_lean_db().unwrap()
-    }
+    use super::{AttestationSource, FutureBlockError, Store};
+    use crate::rejection::{ForkChoiceError, RejectReason};
 
     pub async fn sample_store(no_of_validators: usize) -> (Store, LeanState) {
-        let (genesis_block, genesis_state) =
-            setup_genesis(0, generate_default_validators(no_of_validators));
-
-        let checkpoint = Checkpoint {
-            slot: genesis_block.slot,
-            root: genesis_block.tree_hash_root(),
-        };
-        let signed_genesis_block = build_signed_block_with_attestation(
-            AttestationData {
-                slot: genesis_block.slot,
-                head: checkpoint,
-                target: checkpoint,
-                source: checkpoint,
-            },
-            genesis_block.clone(),
-            VariableList::default(),
-        );
+        let (signed_genesis_block, genesis_state) = genesis(no_of_validators);
 
         set_lean_network_spec(LeanNetworkSpec::ephemery().into());
 
@@ -789,24 +1365,6 @@ mod tests {
         )
     }
 
-    pub fn build_signed_block_with_attestation(
-        attestation_data: AttestationData,
-        block: Block,
-        mut signatures: VariableList<Signature, U4096>,
-    ) -> SignedBlockWithAttestation {
-        signatures.push(Signature::blank()).unwrap();
-        SignedBlockWithAttestation {
-            message: BlockWithAttestation {
-                proposer_attestation: Attestation {
-                    validator_id: block.proposer_index,
-                    data: attestation_data,
-                },
-                block,
-            },
-            signature: signatures,
-        }
-    }
-
     // BLOCK PRODUCTION TESTS
 
     /// Test basic block production by authorized proposer.
@@ -815,10 +1373,10 @@ mod tests {
         let (mut store, mut genesis_state) = sample_store(10).await;
 
         genesis_state.process_slots(1).unwrap();
-        let store_head = store.store.lock().await.head_provider().get().unwrap();
+        let store_head = store.store.head_provider().get().unwrap();
 
         let (block_provider, state_provider) = {
-            let store = store.store.lock().await;
+            let store = &store.store;
             (store.block_provider(), store.state_provider())
         };
 
@@ -836,6 +1394,12 @@ mod tests {
             signatures,
         );
 
+        // Blocks are only imported once their slot has started.
+        store
+            .store
+            .time_provider()
+            .insert(lean_network_spec().intervals_per_slot)
+            .unwrap();
         store
             .on_block(&signed_block_with_attestation, false)
             .await
@@ -859,7 +1423,7 @@ mod tests {
         let (store, _) = sample_store(10).await;
 
         let (head_provider, block_provider, justified_provider, latest_known_attestations) = {
-            let db = store.store.lock().await;
+            let db = &store.store;
             (
                 db.head_provider(),
                 db.block_provider(),
@@ -923,10 +1487,10 @@ mod tests {
     #[tokio::test]
     pub async fn test_produce_block_sequential_slots() {
         let (store, mut genesis_state) = sample_store(10).await;
-        let block_provider = store.store.lock().await.block_provider();
+        let block_provider = store.store.block_provider();
 
         genesis_state.process_slots(1).unwrap();
-        let genesis_hash = store.store.lock().await.head_provider().get().unwrap();
+        let genesis_hash = store.store.head_provider().get().unwrap();
 
         let BlockWithSignatures { block, .. } =
             store.produce_block_with_signatures(1, 1).await.unwrap();
@@ -956,6 +1520,278 @@ mod tests {
         assert!(!block.state_root.is_zero());
     }
 
+    /// Test that blocks are rejected until their slot starts.
+    #[tokio::test]
+    pub async fn test_on_block_future_slot() {
+        let (mut store, _) = sample_store(10).await;
+
+        let BlockWithSignatures { block, signatures } =
+            store.produce_block_with_signatures(1, 1).await.unwrap();
+        let signed_block_with_attestation = build_signed_block_with_attestation(
+            store.produce_attestation_data(1).await.unwrap(),
+            block,
+            signatures,
+        );
+
+        let err = store
+            .on_block(&signed_block_with_attestation, false)
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            ForkChoiceError::FutureBlock(FutureBlockError {
+                slot: 1,
+                latest_slot: 0,
+            })
+        ));
+
+        store
+            .store
+            .time_provider()
+            .insert(lean_network_spec().intervals_per_slot)
+            .unwrap();
+        store
+            .on_block(&signed_block_with_attestation, false)
+            .await
+            .unwrap();
+    }
+
+    /// Test that a block with an invalid attestation is rejected before anything is written.
+    #[tokio::test]
+    pub async fn test_on_block_invalid_attestation() {
+        let (mut store, genesis_state) = sample_store(10).await;
+        let genesis_root = store.store.head_provider().get().unwrap();
+        let block_provider = store.store.block_provider();
+        store
+            .store
+            .time_provider()
+            .insert(lean_network_spec().intervals_per_slot)
+            .unwrap();
+
+        // The state transition skips the vote, but fork choice doesn't know its head.
+        let genesis_checkpoint = Checkpoint {
+            root: genesis_root,
+            slot: 0,
+        };
+        let mut chain = ChainBuilder::new(
+            block_provider.get(genesis_root).unwrap().unwrap(),
+            genesis_state,
+        );
+        let root = chain
+            .build_block(
+                genesis_root,
+                1,
+                vec![Attestation {
+                    validator_id: 0,
+                    data: AttestationData {
+                        slot: 1,
+                        head: Checkpoint {
+                            root: B256::repeat_byte(9),
+                            slot: 1,
+                        },
+                        target: genesis_checkpoint,
+                        source: genesis_checkpoint,
+                    },
+                }],
+            )
+            .unwrap();
+        let mut signed_block_with_attestation = chain.block(root).unwrap().clone();
+        signed_block_with_attestation.signature =
+            VariableList::try_from(vec![Signature::blank(), Signature::blank()]).unwrap();
+
+        assert!(
+            store
+                .on_block(&signed_block_with_attestation, false)
+                .await
+                .is_err()
+        );
+        assert!(block_provider.get(root).unwrap().is_none());
+        assert!(
+            store
+                .store
+                .latest_known_attestations_provider()
+                .get(0)
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    /// Test that blocks with incomplete blob sidecars are rejected once data availability is on.
+    #[tokio::test]
+    pub async fn test_on_block_data_availability() {
+        let (store, _) = sample_store(10).await;
+        let mut store = store.with_data_availability(true);
+        store
+            .store
+            .time_provider()
+            .insert(lean_network_spec().intervals_per_slot)
+            .unwrap();
+
+        let BlockWithSignatures { block, signatures } =
+            store.produce_block_with_signatures(1, 1).await.unwrap();
+        let block_root = block.tree_hash_root();
+        let signed_block_with_attestation = build_signed_block_with_attestation(
+            store.produce_attestation_data(1).await.unwrap(),
+            block,
+            signatures,
+        );
+        let blob_sidecar = |index| BlobSidecar {
+            block_root,
+            index,
+            blob_count: 2,
+            blob: VariableList::empty(),
+        };
+
+        // A block whose sidecars haven't arrived isn't taken to have no blobs.
+        assert!(!store.is_data_available(block_root).unwrap());
+
+        store.on_blob_sidecar(blob_sidecar(0)).unwrap();
+        assert!(
+            store
+                .on_blob_sidecar(BlobSidecar {
+                    blob_count: 3,
+                    ..blob_sidecar(1)
+                })
+                .is_err()
+        );
+        assert!(!store.is_data_available(block_root).unwrap());
+        assert!(
+            store
+                .on_block(&signed_block_with_attestation, false)
+                .await
+                .is_err()
+        );
+
+        store.on_blob_sidecar(blob_sidecar(1)).unwrap();
+        assert!(store.is_data_available(block_root).unwrap());
+        // Replacing a sidecar can't change the blob count of the block.
+        assert!(
+            store
+                .on_blob_sidecar(BlobSidecar {
+                    block_root,
+                    index: 0,
+                    blob_count: 1,
+                    blob: VariableList::empty(),
+                })
+                .is_err()
+        );
+        store
+            .on_block(&signed_block_with_attestation, false)
+            .await
+            .unwrap();
+        assert!(
+            store
+                .store
+                .block_provider()
+                .get(block_root)
+                .unwrap()
+                .is_some()
+        );
+
+        // A block without blobs is available once its empty sidecar is announced.
+        let other_root = B256::repeat_byte(1);
+        let announcement = BlobSidecar {
+            block_root: other_root,
+            index: 0,
+            blob_count: 0,
+            blob: VariableList::empty(),
+        };
+        assert!(!store.is_data_available(other_root).unwrap());
+        store.on_blob_sidecar(announcement).unwrap();
+        assert!(store.is_data_available(other_root).unwrap());
+    }
+
+    /// Test that the block of the slot the head state was advanced to is imported on the cached
+    /// state.
+    #[tokio::test]
+    pub async fn test_advance_head_state() {
+        let (mut store, _) = sample_store(10).await;
+        store
+            .store
+            .time_provider()
+            .insert(lean_network_spec().intervals_per_slot)
+            .unwrap();
+        let head_root = store.store.head_provider().get().unwrap();
+
+        store.advance_head_state(1).await.unwrap();
+        let advanced_state = store.advanced_state.get(head_root, 1).unwrap();
+        assert_eq!(advanced_state.slot, 1);
+
+        let BlockWithSignatures { block, signatures } =
+            store.produce_block_with_signatures(1, 1).await.unwrap();
+        let signed_block_with_attestation = build_signed_block_with_attestation(
+            store.produce_attestation_data(1).await.unwrap(),
+            block,
+            signatures,
+        );
+
+        // A cached state which processing the slot wouldn't give fails the import, so the import
+        // used the cache.
+        let mut wrong_state = advanced_state.clone();
+        wrong_state.latest_justified.slot += 1;
+        store.advanced_state.insert(head_root, wrong_state);
+        store.advance_head_state(1).await.unwrap();
+        assert!(
+            store
+                .on_block(&signed_block_with_attestation, false)
+                .await
+                .is_err()
+        );
+
+        store.advanced_state.insert(head_root, advanced_state);
+        store
+            .on_block(&signed_block_with_attestation, false)
+            .await
+            .unwrap();
+    }
+
+    /// Test that rejected blocks and attestations carry the reason they are counted under.
+    #[tokio::test]
+    pub async fn test_reject_reasons() {
+        let (mut store, _) = sample_store(10).await;
+        store
+            .store
+            .time_provider()
+            .insert(lean_network_spec().intervals_per_slot)
+            .unwrap();
+
+        let BlockWithSignatures { block, signatures } =
+            store.produce_block_with_signatures(1, 1).await.unwrap();
+        let attestation_data = store.produce_attestation_data(1).await.unwrap();
+        let orphan_block = build_signed_block_with_attestation(
+            attestation_data.clone(),
+            Block {
+                parent_root: B256::repeat_byte(1),
+                ..block
+            },
+            signatures,
+        );
+        let err = store.on_block(&orphan_block, false).await.unwrap_err();
+        assert_eq!(err.reject_reason(), RejectReason::UnknownParent);
+        assert!(!err.is_internal());
+
+        let err = store
+            .on_attestation(
+                SignedAttestation {
+                    message: Attestation {
+                        validator_id: 0,
+                        data: AttestationData {
+                            head: Checkpoint {
+                                root: B256::repeat_byte(2),
+                                slot: 1,
+                            },
+                            ..attestation_data
+                        },
+                    },
+                    signature: Signature::blank(),
+                },
+                AttestationSource::Gossip,
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(err.reject_reason(), RejectReason::UnknownBlock);
+    }
+
     /// Test that produced block's state is consistent with block content
     #[tokio::test]
     pub async fn test_produce_block_state_consistency() {
@@ -963,7 +1799,7 @@ mod tests {
 
         let head = store.get_proposal_head(3).await.unwrap();
         let (block_provider, state_provider, latest_known_attestations, latest_justified_provider) = {
-            let store = store.store.lock().await;
+            let store = &store.store;
             (
                 store.block_provider(),
                 store.state_provider(),
@@ -999,6 +1835,11 @@ mod tests {
             signatures,
         );
 
+        store
+            .store
+            .time_provider()
+            .insert(4 * lean_network_spec().intervals_per_slot)
+            .unwrap();
         store
             .on_block(&signed_block_with_attestation, false)
             .await
@@ -1023,13 +1864,7 @@ mod tests {
         let validator_id = 5;
 
         let (store, _) = sample_store(10).await;
-        let latest_justified_checkpoint = store
-            .store
-            .lock()
-            .await
-            .latest_justified_provider()
-            .get()
-            .unwrap();
+        let latest_justified_checkpoint = store.store.latest_justified_provider().get().unwrap();
 
         let attestation = Attestation {
             validator_id,
@@ -1046,7 +1881,7 @@ mod tests {
         let slot = 2;
 
         let (store, _) = sample_store(10).await;
-        let block_provider = store.store.lock().await.block_provider();
+        let block_provider = store.store.block_provider();
 
         let attestation = Attestation {
             validator_id: 8,
@@ -1105,7 +1940,7 @@ mod tests {
         let validator_id = 3;
 
         let (store, _) = sample_store(10).await;
-        let latest_justified_provider = store.store.lock().await.latest_justified_provider();
+        let latest_justified_provider = store.store.latest_justified_provider();
 
         let attestation_1 = Attestation {
             validator_id,
@@ -1130,7 +1965,7 @@ mod tests {
     pub async fn test_produce_attestation_justification_consistency() {
         let (store, _) = sample_store(10).await;
         let (latest_justified_provider, block_provider) = {
-            let db = store.store.lock().await;
+            let db = &store.store;
             (db.latest_justified_provider(), db.block_provider())
         };
 
@@ -1150,4 +1985,535 @@ mod tests {
                 .is_some()
         );
     }
+
+    // FORK TESTS
+
+    /// Test that invariants hold for a fresh store and catch a missing head state.
+    #[tokio::test]
+    pub async fn test_check_invariants() {
+        let (store, _) = sample_store(10).await;
+        store.check_invariants().await.unwrap();
+
+        let (head, state_provider) = {
+            let db = &store.store;
+            (db.head_provider().get().unwrap(), db.state_provider())
+        };
+        state_provider.remove(head).unwrap();
+        assert!(store.check_invariants().await.is_err());
+    }
+
+    /// Test that competing blocks off the canonical chain are reported as branch tips.
+    #[tokio::test]
+    pub async fn test_branch_tips() {
+        let (store, _) = sample_store(10).await;
+        let (head_provider, block_provider) = {
+            let db = &store.store;
+            (db.head_provider(), db.block_provider())
+        };
+        let genesis_root = head_provider.get().unwrap();
+        let genesis_checkpoint = Checkpoint {
+            root: genesis_root,
+            slot: 0,
+        };
+
+        assert!(store.branch_tips().await.unwrap().is_empty());
+
+        let build_block = |slot: u64, parent_root: B256| {
+            build_signed_block_with_attestation(
+                AttestationData {
+                    slot,
+                    head: genesis_checkpoint,
+                    target: genesis_checkpoint,
+                    source: genesis_checkpoint,
+                },
+                Block {
+                    slot,
+                    proposer_index: slot,
+                    parent_root,
+                    state_root: B256::ZERO,
+                    body: BlockBody::default(),
+                },
+                VariableList::default(),
+            )
+        };
+
+        let canonical_block = build_block(1, genesis_root);
+        let canonical_root = canonical_block.message.block.tree_hash_root();
+        block_provider
+            .insert(canonical_root, canonical_block)
+            .unwrap();
+        head_provider.insert(canonical_root).unwrap();
+
+        let fork_block_1 = build_block(2, genesis_root);
+        let fork_root_1 = fork_block_1.message.block.tree_hash_root();
+        block_provider.insert(fork_root_1, fork_block_1).unwrap();
+
+        let fork_block_2 = build_block(3, fork_root_1);
+        let fork_root_2 = fork_block_2.message.block.tree_hash_root();
+        block_provider.insert(fork_root_2, fork_block_2).unwrap();
+
+        let branch_tips = store.branch_tips().await.unwrap();
+        assert_eq!(branch_tips.len(), 1);
+        assert_eq!(
+            branch_tips[0].tip,
+            Checkpoint {
+                root: fork_root_2,
+                slot: 3,
+            }
+        );
+        assert_eq!(branch_tips[0].fork_point, Some(genesis_checkpoint));
+        assert_eq!(branch_tips[0].length, 2);
+    }
+
+    /// Test that peers' finalized checkpoints are checked against our finalized chain.
+    #[tokio::test]
+    pub async fn test_is_compatible_finalized_checkpoint() {
+        let (store, _) = sample_store(10).await;
+        let (block_provider, latest_finalized_provider) = {
+            let db = &store.store;
+            (db.block_provider(), db.latest_finalized_provider())
+        };
+        let genesis_checkpoint = latest_finalized_provider.get().unwrap();
+
+        let build_block = |slot: u64, parent_root: B256| {
+            build_signed_block_with_attestation(
+                AttestationData {
+                    slot,
+                    head: genesis_checkpoint,
+                    target: genesis_checkpoint,
+                    source: genesis_checkpoint,
+                },
+                Block {
+                    slot,
+                    proposer_index: slot,
+                    parent_root,
+                    state_root: B256::ZERO,
+                    body: BlockBody::default(),
+                },
+                VariableList::default(),
+            )
+        };
+        let finalized_block = build_block(2, genesis_checkpoint.root);
+        let finalized_checkpoint = Checkpoint {
+            root: finalized_block.message.block.tree_hash_root(),
+            slot: 2,
+        };
+        block_provider
+            .insert(finalized_checkpoint.root, finalized_block)
+            .unwrap();
+        latest_finalized_provider
+            .insert(finalized_checkpoint)
+            .unwrap();
+        let fork_block = build_block(3, genesis_checkpoint.root);
+        let fork_checkpoint = Checkpoint {
+            root: fork_block.message.block.tree_hash_root(),
+            slot: 3,
+        };
+        block_provider
+            .insert(fork_checkpoint.root, fork_block)
+            .unwrap();
+
+        for (checkpoint, is_compatible) in [
+            (genesis_checkpoint, true),
+            (finalized_checkpoint, true),
+            (Checkpoint::default(), true),
+            // A different genesis.
+            (
+                Checkpoint {
+                    root: B256::repeat_byte(1),
+                    slot: 0,
+                },
+                false,
+            ),
+            // A block we haven't seen after our finalized slot.
+            (
+                Checkpoint {
+                    root: B256::repeat_byte(1),
+                    slot: 5,
+                },
+                true,
+            ),
+            // A block which doesn't descend from our finalized block.
+            (fork_checkpoint, false),
+        ] {
+            assert_eq!(
+                store
+                    .is_compatible_finalized_checkpoint(checkpoint)
+                    .await
+                    .unwrap(),
+                is_compatible,
+                "{checkpoint:?}"
+            );
+        }
+
+        assert!(
+            store
+                .is_ancestor(genesis_checkpoint.root, finalized_checkpoint.root)
+                .unwrap()
+        );
+        assert!(
+            !store
+                .is_ancestor(finalized_checkpoint.root, fork_checkpoint.root)
+                .unwrap()
+        );
+        // Slot 2 is empty on the fork, so its ancestor there is the genesis block.
+        assert_eq!(
+            store.ancestor_at_slot(fork_checkpoint.root, 2).unwrap(),
+            Some(genesis_checkpoint.root)
+        );
+    }
+
+    /// Test that a state missing from the state table is regenerated from an ancestor's state,
+    /// without writing it back, and only within the replay limit.
+    #[tokio::test]
+    pub async fn test_get_state_at() {
+        let (store, genesis_state) = sample_store(10).await;
+        let (block_provider, state_provider) = {
+            let db = &store.store;
+            (db.block_provider(), db.state_provider())
+        };
+
+        let genesis_block = block_provider
+            .get(store.store.head_provider().get().unwrap())
+            .unwrap()
+            .unwrap();
+        let mut chain = ChainBuilder::new(genesis_block, genesis_state);
+        let parent_root = *chain
+            .extend(chain.genesis_root(), 1..=3)
+            .unwrap()
+            .last()
+            .unwrap();
+        chain.store_blocks(&store.store).unwrap();
+        let state = chain.state(parent_root).unwrap().clone();
+
+        assert!(state_provider.get(parent_root).unwrap().is_none());
+        let limited_regenerator = store.state_regenerator.clone().with_max_replay_blocks(2);
+        assert!(limited_regenerator.get_state_at(parent_root).await.is_err());
+
+        assert_eq!(
+            store.get_state_at(parent_root).await.unwrap(),
+            Some(state.clone())
+        );
+        assert!(state_provider.get(parent_root).unwrap().is_none());
+        // The regenerated state is cached, and shared by every handle.
+        assert_eq!(
+            limited_regenerator.get_state_at(parent_root).await.unwrap(),
+            Some(state)
+        );
+        assert_eq!(
+            store.get_state_at(B256::repeat_byte(1)).await.unwrap(),
+            None
+        );
+    }
+
+    /// Test that the proposer boost outweighs the slot tiebreak, and expires with its slot.
+    #[tokio::test]
+    pub async fn test_proposer_boost() {
+        let (store, genesis_state) = sample_store(10).await;
+        let (head_provider, block_provider, state_provider, proposer_boost_root_provider) = {
+            let db = &store.store;
+            (
+                db.head_provider(),
+                db.block_provider(),
+                db.state_provider(),
+                db.proposer_boost_root_provider(),
+            )
+        };
+        let genesis_root = head_provider.get().unwrap();
+        let genesis_checkpoint = Checkpoint {
+            root: genesis_root,
+            slot: 0,
+        };
+
+        let mut roots = vec![];
+        for slot in [1, 2] {
+            let block = build_signed_block_with_attestation(
+                AttestationData {
+                    slot,
+                    head: genesis_checkpoint,
+                    target: genesis_checkpoint,
+                    source: genesis_checkpoint,
+                },
+                Block {
+                    slot,
+                    proposer_index: slot,
+                    parent_root: genesis_root,
+                    state_root: B256::ZERO,
+                    body: BlockBody::default(),
+                },
+                VariableList::default(),
+            );
+            let root = block.message.block.tree_hash_root();
+            block_provider.insert(root, block).unwrap();
+            state_provider.insert(root, genesis_state.clone()).unwrap();
+            roots.push(root);
+        }
+
+        // Without votes, the later block wins the tiebreak.
+        store.update_head().await.unwrap();
+        assert_eq!(head_provider.get().unwrap(), roots[1]);
+
+        proposer_boost_root_provider.insert(roots[0]).unwrap();
+        store.update_head().await.unwrap();
+        assert_eq!(head_provider.get().unwrap(), roots[0]);
+
+        // The boost is cleared at the start of the next slot.
+        for _ in 0..lean_network_spec().intervals_per_slot {
+            store.tick_interval(false).await.unwrap();
+        }
+        assert_eq!(proposer_boost_root_provider.get().unwrap(), B256::ZERO);
+        store.update_head().await.unwrap();
+        assert_eq!(head_provider.get().unwrap(), roots[1]);
+    }
+
+    #[tokio::test]
+    pub async fn test_attestation_sources() {
+        let (store, _) = sample_store(4).await;
+        let genesis = Checkpoint {
+            root: store.store.head_provider().get().unwrap(),
+            slot: 0,
+        };
+        let attestation = |validator_id| SignedAttestation {
+            message: Attestation {
+                validator_id,
+                data: AttestationData {
+                    slot: 0,
+                    head: genesis,
+                    target: genesis,
+                    source: genesis,
+                },
+            },
+            signature: Signature::blank(),
+        };
+        // Other tests count attestations concurrently, so the counters only have to grow.
+        let valid = |source| ATTESTATIONS_VALID_TOTAL.with_label_values(&[source]).get();
+        let invalid = |source| {
+            ATTESTATIONS_INVALID_TOTAL
+                .with_label_values(&[source])
+                .get()
+        };
+        let (valid_api, valid_block, invalid_gossip) =
+            (valid("api"), valid("block"), invalid("gossip"));
+
+        for (validator_id, source) in [
+            (0, AttestationSource::Api),
+            (1, AttestationSource::Block),
+            (2, AttestationSource::Proposer),
+        ] {
+            store
+                .on_attestation(attestation(validator_id), source)
+                .await
+                .unwrap();
+        }
+        assert!(
+            store
+                .on_attestation(attestation(100), AttestationSource::Gossip)
+                .await
+                .is_err()
+        );
+        assert!(valid("api") > valid_api);
+        // Proposer attestations are counted as coming from the block.
+        assert!(valid("block") >= valid_block + 2);
+        assert!(invalid("gossip") > invalid_gossip);
+
+        // Only attestations included in blocks are known right away.
+        store.attestation_pool.flush_pending().unwrap();
+        let (known, new) = (
+            store.store.latest_known_attestations_provider(),
+            store.store.latest_new_attestations_provider(),
+        );
+        assert!(known.get(1).unwrap().is_some());
+        for validator_id in [0, 2] {
+            assert!(known.get(validator_id).unwrap().is_none());
+            assert!(new.get(validator_id).unwrap().is_some());
+        }
+    }
+
+    #[tokio::test]
+    pub async fn test_canonical_participation() {
+        let (store, _) = sample_store(4).await;
+        assert_eq!(store.canonical_participation().await.unwrap(), 0.0);
+
+        let genesis_root = store.store.head_provider().get().unwrap();
+        let attestation = |validator_id, target_root| SignedAttestation {
+            message: Attestation {
+                validator_id,
+                data: AttestationData {
+                    slot: 0,
+                    head: Checkpoint {
+                        root: genesis_root,
+                        slot: 0,
+                    },
+                    target: Checkpoint {
+                        root: target_root,
+                        slot: 0,
+                    },
+                    source: Checkpoint {
+                        root: genesis_root,
+                        slot: 0,
+                    },
+                },
+            },
+            signature: Signature::blank(),
+        };
+        store
+            .store
+            .latest_known_attestations_provider()
+            .batch_insert([
+                (0, attestation(0, genesis_root)),
+                (1, attestation(1, genesis_root)),
+                (2, attestation(2, B256::repeat_byte(1))),
+            ])
+            .unwrap();
+
+        // Targets off the canonical chain don't count, and neither do validators which didn't
+        // attest.
+        assert_eq!(store.canonical_participation().await.unwrap(), 0.5);
+    }
+
+    #[tokio::test]
+    pub async fn test_attestation_pool_limits() {
+        let (store, _) = sample_store(2).await;
+        let store = store.with_attestation_pool_size(2);
+        let (latest_new_attestations, latest_known_attestations) = (
+            store.store.latest_new_attestations_provider(),
+            store.store.latest_known_attestations_provider(),
+        );
+        let attestation = |validator_id, slot| SignedAttestation {
+            message: Attestation {
+                validator_id,
+                data: AttestationData {
+                    slot,
+                    head: Checkpoint::default(),
+                    target: Checkpoint::default(),
+                    source: Checkpoint::default(),
+                },
+            },
+            signature: Signature::blank(),
+        };
+
+        let pool = &store.attestation_pool;
+        pool.insert_new(attestation(0, 1)).unwrap();
+        pool.insert_new(attestation(1, 1)).unwrap();
+        // The pool is full, so a validator without an attestation in it is dropped, while a later
+        // attestation of a validator already in it replaces the earlier one.
+        pool.insert_new(attestation(2, 1)).unwrap();
+        pool.insert_new(attestation(0, 3)).unwrap();
+        assert_eq!(latest_new_attestations.count().unwrap(), 2);
+        assert!(latest_new_attestations.get(2).unwrap().is_none());
+        assert_eq!(
+            latest_new_attestations
+                .get(0)
+                .unwrap()
+                .unwrap()
+                .message
+                .data
+                .slot,
+            3
+        );
+
+        pool.accept_new().unwrap();
+        assert_eq!(latest_new_attestations.count().unwrap(), 0);
+        assert_eq!(latest_known_attestations.count().unwrap(), 2);
+
+        // An attestation included in a block supersedes the new one of the same validator.
+        pool.insert_new(attestation(1, 4)).unwrap();
+        pool.insert_from_block(vec![attestation(1, 4)]).unwrap();
+        assert!(latest_new_attestations.get(1).unwrap().is_none());
+
+        pool.prune(4).unwrap();
+        assert!(latest_known_attestations.get(0).unwrap().is_none());
+        assert_eq!(
+            latest_known_attestations
+                .get(1)
+                .unwrap()
+                .unwrap()
+                .message
+                .data
+                .slot,
+            4
+        );
+
+        // A pool smaller than the validator set grows to hold a vote of every validator.
+        pool.insert_new(attestation(0, 5)).unwrap();
+        pool.insert_new(attestation(1, 5)).unwrap();
+        pool.observe_validator_count(3);
+        pool.insert_new(attestation(2, 5)).unwrap();
+        assert_eq!(latest_new_attestations.count().unwrap(), 3);
+
+        // Votes of validators no state has are rejected before they reach the pools.
+        for (validator_id, reject_reason) in
+            [(2, RejectReason::UnknownBlock), (3, RejectReason::Invalid)]
+        {
+            assert_eq!(
+                store
+                    .validate_attestation(&attestation(validator_id, 5))
+                    .await
+                    .unwrap_err()
+                    .reject_reason(),
+                reject_reason
+            );
+        }
+    }
+
+    #[tokio::test]
+    pub async fn test_attestation_write_batching() {
+        let (store, _) = sample_store(3).await;
+        let store = store
+            .with_attestation_pool_size(3)
+            .with_attestation_batch_size(3);
+        let latest_new_attestations = store.store.latest_new_attestations_provider();
+        let attestation = |validator_id, slot| SignedAttestation {
+            message: Attestation {
+                validator_id,
+                data: AttestationData {
+                    slot,
+                    head: Checkpoint::default(),
+                    target: Checkpoint::default(),
+                    source: Checkpoint::default(),
+                },
+            },
+            signature: Signature::blank(),
+        };
+
+        let pool = &store.attestation_pool;
+        pool.insert_new(attestation(0, 1)).unwrap();
+        pool.insert_new(attestation(1, 1)).unwrap();
+        // Buffered attestations are deduplicated and count towards the pool size before they are
+        // written.
+        pool.insert_new(attestation(0, 0)).unwrap();
+        pool.insert_new(attestation(0, 2)).unwrap();
+        assert_eq!(latest_new_attestations.count().unwrap(), 0);
+
+        // The third validator fills the batch, and the pool with it.
+        pool.insert_new(attestation(2, 1)).unwrap();
+        assert_eq!(latest_new_attestations.count().unwrap(), 3);
+        assert_eq!(
+            latest_new_attestations
+                .get(0)
+                .unwrap()
+                .unwrap()
+                .message
+                .data
+                .slot,
+            2
+        );
+        pool.insert_new(attestation(3, 1)).unwrap();
+        pool.flush_pending().unwrap();
+        assert!(latest_new_attestations.get(3).unwrap().is_none());
+
+        // A tick writes what is still buffered, before its duty may accept it.
+        pool.insert_new(attestation(1, 2)).unwrap();
+        store.tick_interval(false).await.unwrap();
+        let written = match latest_new_attestations.get(1).unwrap() {
+            Some(latest_new) => latest_new,
+            None => store
+                .store
+                .latest_known_attestations_provider()
+                .get(1)
+                .unwrap()
+                .unwrap(),
+        };
+        assert_eq!(written.message.data.slot, 2);
+    }
 }
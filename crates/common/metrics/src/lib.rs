@@ -1,19 +1,24 @@
+pub mod slot_report;
 pub mod timer;
 
+pub use prometheus::TEXT_FORMAT;
+#[cfg(target_os = "linux")]
+use prometheus::process_collector::ProcessCollector;
+use prometheus::{Encoder, TextEncoder};
 use prometheus_exporter::prometheus::{
-    HistogramTimer, HistogramVec, IntCounterVec, IntGaugeVec, default_registry,
-    register_histogram_vec_with_registry, register_int_counter_vec_with_registry,
-    register_int_gauge_vec_with_registry,
+    GaugeVec, HistogramTimer, HistogramVec, IntCounterVec, IntGaugeVec, default_registry,
+    register_gauge_vec_with_registry, register_histogram_vec_with_registry,
+    register_int_counter_vec_with_registry, register_int_gauge_vec_with_registry,
 };
 
-use crate::timer::DiscardOnDropHistogramTimer;
+use crate::timer::{DiscardOnDropHistogramTimer, OutcomeHistogramTimer};
 
 // Provisioning each metrics
 lazy_static::lazy_static! {
     pub static ref PROPOSE_BLOCK_TIME: HistogramVec = register_histogram_vec_with_registry!(
         "lean_propose_block_time",
-        "Duration of the sections it takes to propose a new block",
-        &["section"],
+        "Duration of the sections it takes to propose a new block by outcome",
+        &["section", "outcome"],
         default_registry()
     ).expect("failed to create PROPOSE_BLOCK_TIME histogram vec");
 
@@ -52,6 +57,41 @@ lazy_static::lazy_static! {
         default_registry()
     ).expect("failed to create LATEST_FINALIZED_SLOT int gauge vec");
 
+    pub static ref HEAD_JUSTIFIED_DISTANCE: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_head_justified_distance_slots",
+        "Number of slots the head is ahead of its latest justified checkpoint",
+        &[],
+        default_registry()
+    ).expect("failed to create HEAD_JUSTIFIED_DISTANCE int gauge vec");
+
+    pub static ref HEAD_FINALIZED_DISTANCE: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_head_finalized_distance_slots",
+        "Number of slots the head is ahead of its latest finalized checkpoint",
+        &[],
+        default_registry()
+    ).expect("failed to create HEAD_FINALIZED_DISTANCE int gauge vec");
+
+    pub static ref SLOTS_SINCE_FINALIZATION: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_slots_since_finalization",
+        "Number of slots since the latest finalized checkpoint",
+        &[],
+        default_registry()
+    ).expect("failed to create SLOTS_SINCE_FINALIZATION int gauge vec");
+
+    pub static ref FINALITY_STALLED: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_finality_stalled",
+        "1 if finality hasn't advanced for longer than the stall threshold, 0 otherwise",
+        &[],
+        default_registry()
+    ).expect("failed to create FINALITY_STALLED int gauge vec");
+
+    pub static ref ATTESTATION_PARTICIPATION_RATE: GaugeVec = register_gauge_vec_with_registry!(
+        "lean_attestation_participation_rate",
+        "Fraction of validators whose latest attestation targets the canonical chain",
+        &[],
+        default_registry()
+    ).expect("failed to create ATTESTATION_PARTICIPATION_RATE gauge vec");
+
     pub static ref VALIDATORS_COUNT: IntGaugeVec = register_int_gauge_vec_with_registry!(
         "lean_validators_count",
         "The total number of validators",
@@ -60,46 +100,60 @@ lazy_static::lazy_static! {
     ).expect("failed to create VALIDATORS_COUNT int gauge vec");
 
     // Fork-Choice Metrics
+    pub static ref LIVE_FORK_COUNT: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_live_fork_count",
+        "Number of branch tips that are not on the canonical chain",
+        &[],
+        default_registry()
+    ).expect("failed to create LIVE_FORK_COUNT int gauge vec");
+
     pub static ref FORK_CHOICE_BLOCK_PROCESSING_TIME: HistogramVec = register_histogram_vec_with_registry!(
         "lean_fork_choice_block_processing_time_seconds",
-        "Time taken to process block",
-        &[],
+        "Time taken to process block by outcome",
+        &["outcome"],
         default_registry()
     ).expect("failed to create FORK_CHOICE_BLOCK_PROCESSING_TIME histogram vec");
 
+    pub static ref FORK_CHOICE_REORGS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_fork_choice_reorgs_total",
+        "Total number of head changes to a block which doesn't descend from the previous head",
+        &[],
+        default_registry()
+    ).expect("failed to create FORK_CHOICE_REORGS_TOTAL int counter vec");
+
     pub static ref ATTESTATIONS_VALID_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
         "lean_attestations_valid_total",
-        "Total number of valid attestations",
-        &[],
+        "Total number of valid attestations by source",
+        &["source"],
         default_registry()
     ).expect("failed to create ATTESTATIONS_VALID_TOTAL int counter vec");
 
     pub static ref ATTESTATIONS_INVALID_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
         "lean_attestations_invalid_total",
-        "Total number of invalid attestations",
-        &[],
+        "Total number of invalid attestations by source",
+        &["source"],
         default_registry()
     ).expect("failed to create ATTESTATIONS_INVALID_TOTAL int counter vec");
 
     pub static ref ATTESTATION_VALIDATION_TIME: HistogramVec = register_histogram_vec_with_registry!(
         "lean_attestation_validation_time_seconds",
-        "Time taken to validate attestation",
-        &[],
+        "Time taken to validate attestation by source and outcome",
+        &["source", "outcome"],
         default_registry()
     ).expect("failed to create ATTESTATION_VALIDATION_TIME histogram vec");
 
     // State Transition Metrics
     pub static ref STATE_TRANSITION_TIME: HistogramVec = register_histogram_vec_with_registry!(
         "lean_state_transition_time_seconds",
-        "Time taken to process state transition",
-        &[],
+        "Time taken to process state transition by outcome",
+        &["outcome"],
         default_registry()
     ).expect("failed to create STATE_TRANSITION_TIME histogram vec");
 
     pub static ref STATE_TRANSITION_BLOCK_PROCESSING_TIME: HistogramVec = register_histogram_vec_with_registry!(
         "lean_state_transition_block_processing_time_seconds",
-        "Time taken to process block in state transition",
-        &[],
+        "Time taken to process block in state transition by outcome",
+        &["outcome"],
         default_registry()
     ).expect("failed to create STATE_TRANSITION_BLOCK_PROCESSING_TIME histogram vec");
 
@@ -112,8 +166,8 @@ lazy_static::lazy_static! {
 
     pub static ref STATE_TRANSITION_SLOTS_PROCESSING_TIME: HistogramVec = register_histogram_vec_with_registry!(
         "lean_state_transition_slots_processing_time_seconds",
-        "Time taken to process slots in state transition",
-        &[],
+        "Time taken to process slots in state transition by outcome",
+        &["outcome"],
         default_registry()
     ).expect("failed to create STATE_TRANSITION_SLOTS_PROCESSING_TIME histogram vec");
 
@@ -124,12 +178,134 @@ lazy_static::lazy_static! {
         default_registry()
     ).expect("failed to create STATE_TRANSITION_ATTESTATIONS_PROCESSED_TOTAL int counter vec");
 
+    pub static ref STATE_TRANSITION_ATTESTATIONS_REJECTED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_state_transition_attestations_rejected_total",
+        "Total number of attestations which made their block invalid by reason",
+        &["reason"],
+        default_registry()
+    ).expect("failed to create STATE_TRANSITION_ATTESTATIONS_REJECTED_TOTAL int counter vec");
+
     pub static ref STATE_TRANSITION_ATTESTATIONS_PROCESSING_TIME: HistogramVec = register_histogram_vec_with_registry!(
         "lean_state_transition_attestations_processing_time_seconds",
-        "Time taken to process attestations in state transition",
-        &[],
+        "Time taken to process attestations in state transition by outcome",
+        &["outcome"],
         default_registry()
     ).expect("failed to create STATE_TRANSITION_ATTESTATIONS_PROCESSING_TIME histogram vec");
+
+    // Network Metrics
+    pub static ref PEER_COUNT: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_peer_count",
+        "Number of known peers by connection direction and state",
+        &["direction", "state"],
+        default_registry()
+    ).expect("failed to create PEER_COUNT int gauge vec");
+
+    pub static ref GOSSIP_MESSAGES_RECEIVED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_gossip_messages_received_total",
+        "Total number of gossip messages received per topic",
+        &["topic"],
+        default_registry()
+    ).expect("failed to create GOSSIP_MESSAGES_RECEIVED_TOTAL int counter vec");
+
+    pub static ref GOSSIP_MESSAGES_SENT_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_gossip_messages_sent_total",
+        "Total number of gossip messages published per topic",
+        &["topic"],
+        default_registry()
+    ).expect("failed to create GOSSIP_MESSAGES_SENT_TOTAL int counter vec");
+
+    pub static ref REQ_RESP_REQUESTS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_req_resp_requests_total",
+        "Total number of req/resp requests by protocol and outcome",
+        &["protocol", "outcome"],
+        default_registry()
+    ).expect("failed to create REQ_RESP_REQUESTS_TOTAL int counter vec");
+
+    pub static ref DIAL_FAILURES_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_dial_failures_total",
+        "Total number of failed outgoing dials",
+        &[],
+        default_registry()
+    ).expect("failed to create DIAL_FAILURES_TOTAL int counter vec");
+
+    pub static ref PEERS_DISCONNECTED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_peers_disconnected_total",
+        "Total number of peers disconnected by the node by reason",
+        &["reason"],
+        default_registry()
+    ).expect("failed to create PEERS_DISCONNECTED_TOTAL int counter vec");
+
+    pub static ref INBOUND_CONNECTIONS_REJECTED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_inbound_connections_rejected_total",
+        "Total number of inbound peers refused for the address they connected from, by the limit they exceeded",
+        &["limit"],
+        default_registry()
+    ).expect("failed to create INBOUND_CONNECTIONS_REJECTED_TOTAL int counter vec");
+
+    // Validator Performance Metrics, only recorded with `--validator-performance-metrics` as they
+    // have a series per validator
+    pub static ref VALIDATOR_PROPOSALS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_validator_proposals_total",
+        "Total number of blocks each validator was due to propose, by whether it proposed them",
+        &["validator", "outcome"],
+        default_registry()
+    ).expect("failed to create VALIDATOR_PROPOSALS_TOTAL int counter vec");
+
+    pub static ref VALIDATOR_ATTESTATIONS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_validator_attestations_total",
+        "Total number of slots each validator was due to attest in, by whether its attestation was included",
+        &["validator", "outcome"],
+        default_registry()
+    ).expect("failed to create VALIDATOR_ATTESTATIONS_TOTAL int counter vec");
+
+    pub static ref KEY_EPOCHS_REMAINING: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_validator_key_epochs_remaining",
+        "Number of epochs the XMSS key of each validator of this node can still sign at",
+        &["validator"],
+        default_registry()
+    ).expect("failed to create KEY_EPOCHS_REMAINING int gauge vec");
+
+    pub static ref CLOCK_OFFSET_MILLISECONDS: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_clock_offset_milliseconds",
+        "How far the system clock is ahead of the measured time, negative if it is behind",
+        &[],
+        default_registry()
+    ).expect("failed to create CLOCK_OFFSET_MILLISECONDS int gauge vec");
+
+    pub static ref ATTESTATION_POOL_SIZE: IntGaugeVec = register_int_gauge_vec_with_registry!(
+        "lean_attestation_pool_size",
+        "Number of attestations in the new and known attestation pools",
+        &["pool"],
+        default_registry()
+    ).expect("failed to create ATTESTATION_POOL_SIZE int gauge vec");
+
+    pub static ref ATTESTATIONS_DROPPED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_attestations_dropped_total",
+        "Total number of attestations dropped from the attestation pools by reason",
+        &["reason"],
+        default_registry()
+    ).expect("failed to create ATTESTATIONS_DROPPED_TOTAL int counter vec");
+
+    pub static ref GOSSIP_REJECTED_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_gossip_rejected_total",
+        "Total number of blocks and attestations rejected or ignored by fork choice by kind and reason",
+        &["kind", "reason"],
+        default_registry()
+    ).expect("failed to create GOSSIP_REJECTED_TOTAL int counter vec");
+
+    pub static ref RECENT_BLOCKS_CACHE_LOOKUPS_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_recent_blocks_cache_lookups_total",
+        "Total number of blocks requested by root from the recent blocks cache by result, hit or miss",
+        &["result"],
+        default_registry()
+    ).expect("failed to create RECENT_BLOCKS_CACHE_LOOKUPS_TOTAL int counter vec");
+
+    pub static ref STORAGE_ENCODED_BYTES_TOTAL: IntCounterVec = register_int_counter_vec_with_registry!(
+        "lean_storage_encoded_bytes_total",
+        "Total number of bytes of compressed values written to the database by value type and size, uncompressed or stored",
+        &["type", "size"],
+        default_registry()
+    ).expect("failed to create STORAGE_ENCODED_BYTES_TOTAL int counter vec");
 }
 
 /// Set the value of a gauge metric
@@ -137,6 +313,16 @@ pub fn set_int_gauge_vec(gauge_vec: &IntGaugeVec, value: i64, label_values: &[&s
     gauge_vec.with_label_values(label_values).set(value);
 }
 
+/// Remove the series of a gauge metric, once nothing records it anymore
+pub fn remove_int_gauge_vec(gauge_vec: &IntGaugeVec, label_values: &[&str]) {
+    let _ = gauge_vec.remove_label_values(label_values);
+}
+
+/// Set the value of a floating point gauge metric
+pub fn set_gauge_vec(gauge_vec: &GaugeVec, value: f64, label_values: &[&str]) {
+    gauge_vec.with_label_values(label_values).set(value);
+}
+
 /// Start a timer for a histogram metric
 pub fn start_timer(histogram_vec: &HistogramVec, label_values: &[&str]) -> HistogramTimer {
     histogram_vec.with_label_values(label_values).start_timer()
@@ -159,7 +345,50 @@ pub fn stop_timer_discard_on_drop(timer: DiscardOnDropHistogramTimer) {
     timer.observe_duration()
 }
 
+/// Start a timer for a histogram metric whose last label is `outcome`, which records a success
+/// if stop_outcome_timer is called and a failure if it is dropped before
+pub fn start_outcome_timer(
+    histogram_vec: &HistogramVec,
+    label_values: &[&str],
+) -> OutcomeHistogramTimer {
+    let with_outcome = |outcome| {
+        let mut label_values = label_values.to_vec();
+        label_values.push(outcome);
+        histogram_vec.with_label_values(&label_values)
+    };
+    OutcomeHistogramTimer::new(with_outcome("success"), with_outcome("failure"))
+}
+
+pub fn stop_outcome_timer(timer: OutcomeHistogramTimer) {
+    timer.observe_success()
+}
+
+/// Record an observation of a histogram metric
+pub fn observe_histogram_vec(histogram_vec: &HistogramVec, value: f64, label_values: &[&str]) {
+    histogram_vec.with_label_values(label_values).observe(value);
+}
+
 /// Increment a counter metric
 pub fn inc_int_counter_vec(counter_vec: &IntCounterVec, label_values: &[&str]) {
     counter_vec.with_label_values(label_values).inc();
 }
+
+/// Increase a counter metric by `value`
+pub fn inc_int_counter_vec_by(counter_vec: &IntCounterVec, value: u64, label_values: &[&str]) {
+    counter_vec.with_label_values(label_values).inc_by(value);
+}
+
+/// Registers the CPU time, resident memory and open file descriptors of this process as the
+/// `process_*` metrics. They can only be collected on Linux, elsewhere nothing is registered.
+pub fn register_process_metrics() -> prometheus::Result<()> {
+    #[cfg(target_os = "linux")]
+    default_registry().register(Box::new(ProcessCollector::for_self()))?;
+    Ok(())
+}
+
+/// Encodes every registered metric in the Prometheus text format, served as [TEXT_FORMAT].
+pub fn encode_metrics() -> prometheus::Result<String> {
+    let mut buffer = vec![];
+    TextEncoder::new().encode(&default_registry().gather(), &mut buffer)?;
+    String::from_utf8(buffer).map_err(|err| prometheus::Error::Msg(err.to_string()))
+}
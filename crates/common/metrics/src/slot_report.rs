@@ -0,0 +1,136 @@
+//! Per-slot performance counters.
+//!
+//! Components record what they did while a slot was in progress, and the chain service calls
+//! [finish_slot] at the end of every slot to turn the counters into a [SlotReport]. This makes
+//! regressions visible in the logs and over the API without a Prometheus setup.
+
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+
+/// Summary of the work done by the node during a single slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlotReport {
+    pub slot: u64,
+    pub blocks_imported: u64,
+    pub block_import_time_micros: u64,
+    pub attestations_processed: u64,
+    pub signature_verifications: u64,
+    pub head_changes: u64,
+    pub db_writes: u64,
+}
+
+struct SlotReportCollector {
+    blocks_imported: AtomicU64,
+    block_import_time_micros: AtomicU64,
+    attestations_processed: AtomicU64,
+    signature_verifications: AtomicU64,
+    head_changes: AtomicU64,
+    db_writes: AtomicU64,
+}
+
+static COLLECTOR: SlotReportCollector = SlotReportCollector {
+    blocks_imported: AtomicU64::new(0),
+    block_import_time_micros: AtomicU64::new(0),
+    attestations_processed: AtomicU64::new(0),
+    signature_verifications: AtomicU64::new(0),
+    head_changes: AtomicU64::new(0),
+    db_writes: AtomicU64::new(0),
+};
+
+static LAST_SLOT_REPORT: RwLock<Option<SlotReport>> = RwLock::new(None);
+
+/// Record that a block was imported, and how long the import took.
+pub fn record_block_import(duration: Duration) {
+    COLLECTOR.blocks_imported.fetch_add(1, Ordering::Relaxed);
+    COLLECTOR
+        .block_import_time_micros
+        .fetch_add(duration.as_micros() as u64, Ordering::Relaxed);
+}
+
+/// Record that attestations were processed by fork choice.
+pub fn record_attestations_processed(count: u64) {
+    COLLECTOR
+        .attestations_processed
+        .fetch_add(count, Ordering::Relaxed);
+}
+
+/// Record that signatures were verified.
+pub fn record_signature_verifications(count: u64) {
+    COLLECTOR
+        .signature_verifications
+        .fetch_add(count, Ordering::Relaxed);
+}
+
+/// Record that the head of the chain changed.
+pub fn record_head_change() {
+    COLLECTOR.head_changes.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record that a write transaction was committed to the database.
+pub fn record_db_write() {
+    COLLECTOR.db_writes.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Resets the counters and returns the report for `slot`. The report is also kept around so it
+/// can be served by [last_slot_report].
+pub fn finish_slot(slot: u64) -> SlotReport {
+    let report = SlotReport {
+        slot,
+        blocks_imported: COLLECTOR.blocks_imported.swap(0, Ordering::Relaxed),
+        block_import_time_micros: COLLECTOR
+            .block_import_time_micros
+            .swap(0, Ordering::Relaxed),
+        attestations_processed: COLLECTOR.attestations_processed.swap(0, Ordering::Relaxed),
+        signature_verifications: COLLECTOR.signature_verifications.swap(0, Ordering::Relaxed),
+        head_changes: COLLECTOR.head_changes.swap(0, Ordering::Relaxed),
+        db_writes: COLLECTOR.db_writes.swap(0, Ordering::Relaxed),
+    };
+    *LAST_SLOT_REPORT.write() = Some(report.clone());
+    report
+}
+
+/// Returns the report of the last finished slot, if any.
+pub fn last_slot_report() -> Option<SlotReport> {
+    LAST_SLOT_REPORT.read().clone()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_finish_slot_resets_counters() {
+        record_block_import(Duration::from_micros(1500));
+        record_attestations_processed(3);
+        record_signature_verifications(4);
+        record_head_change();
+        record_db_write();
+        record_db_write();
+
+        let report = finish_slot(7);
+        assert_eq!(report.slot, 7);
+        assert_eq!(report.blocks_imported, 1);
+        assert_eq!(report.block_import_time_micros, 1500);
+        assert_eq!(report.attestations_processed, 3);
+        assert_eq!(report.signature_verifications, 4);
+        assert_eq!(report.head_changes, 1);
+        assert_eq!(report.db_writes, 2);
+        assert_eq!(last_slot_report(), Some(report));
+
+        let report = finish_slot(8);
+        assert_eq!(
+            report,
+            SlotReport {
+                slot: 8,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -73,13 +73,71 @@ impl Drop for DiscardOnDropHistogramTimer {
     }
 }
 
+/// Timer to measure the duration of an event and record it by its outcome.
+///
+/// Stopping the timer records its duration as a success. A timer dropped without being stopped,
+/// such as when the event returns early with an error, records its duration as a failure.
+#[must_use = "Timer should be kept in a variable otherwise it cannot observe duration"]
+#[derive(Debug)]
+pub struct OutcomeHistogramTimer {
+    /// The histogram of successful events.
+    success: Histogram,
+    /// The histogram of failed events.
+    failure: Histogram,
+    /// Whether the timer has already been observed once.
+    observed: bool,
+    /// Starting instant for the timer.
+    start: Instant,
+}
+
+impl OutcomeHistogramTimer {
+    pub fn new(success: Histogram, failure: Histogram) -> Self {
+        Self {
+            success,
+            failure,
+            observed: false,
+            start: Instant::now(),
+        }
+    }
+
+    /// Observe and record timer duration (in seconds) as a success.
+    pub fn observe_success(self) {
+        let mut timer = self;
+        timer.observe(true);
+    }
+
+    /// Stop the timer without recording, for an event which neither succeeded nor failed.
+    pub fn discard(self) {
+        let mut timer = self;
+        timer.observed = true;
+    }
+
+    fn observe(&mut self, success: bool) {
+        self.observed = true;
+        let histogram = if success {
+            &self.success
+        } else {
+            &self.failure
+        };
+        histogram.observe(self.start.elapsed().as_secs_f64());
+    }
+}
+
+impl Drop for OutcomeHistogramTimer {
+    fn drop(&mut self) {
+        if !self.observed {
+            self.observe(false);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use std::{thread, time::Duration};
 
     use prometheus_exporter::prometheus::{Histogram, HistogramOpts, core::Collector};
 
-    use crate::timer::DiscardOnDropHistogramTimer;
+    use crate::timer::{DiscardOnDropHistogramTimer, OutcomeHistogramTimer};
 
     #[test]
     fn test_observe_duration() {
@@ -263,4 +321,18 @@ mod tests {
         assert_eq!(proto_histogram.get_sample_count(), 0);
         assert!(proto_histogram.get_sample_sum() >= 0.0);
     }
+
+    #[test]
+    fn test_outcome_timer() {
+        let success = Histogram::with_opts(HistogramOpts::new("test_success", "testing")).unwrap();
+        let failure = Histogram::with_opts(HistogramOpts::new("test_failure", "testing")).unwrap();
+
+        OutcomeHistogramTimer::new(success.clone(), failure.clone()).observe_success();
+        drop(OutcomeHistogramTimer::new(success.clone(), failure.clone()));
+        drop(OutcomeHistogramTimer::new(success.clone(), failure.clone()));
+        OutcomeHistogramTimer::new(success.clone(), failure.clone()).discard();
+
+        assert_eq!(success.get_sample_count(), 1);
+        assert_eq!(failure.get_sample_count(), 2);
+    }
 }
@@ -1,9 +1,10 @@
-use std::{fs, sync::Arc};
+use std::{fs, path::Path, sync::Arc};
 
 use serde::de::DeserializeOwned;
 
 use crate::networks::{
-    BeaconNetworkSpec, DEV, Devnet, HOLESKY, HOODI, LeanNetworkSpec, MAINNET, SEPOLIA,
+    BeaconNetworkSpec, DEV, Devnet, HOLESKY, HOODI, LEAN_NETWORK_NAMES, LeanNetworkSpec, MAINNET,
+    SEPOLIA,
 };
 
 pub fn beacon_network_parser(network_string: &str) -> Result<Arc<BeaconNetworkSpec>, String> {
@@ -17,11 +18,33 @@ pub fn beacon_network_parser(network_string: &str) -> Result<Arc<BeaconNetworkSp
     }
 }
 
+/// Parses `--network` as the name of a built-in network, or as the path to a YAML or, with a
+/// `.toml` extension, TOML config file.
 pub fn lean_network_parser(network_string: &str) -> Result<LeanNetworkSpec, String> {
-    match network_string {
-        "ephemery" => Ok(LeanNetworkSpec::ephemery()),
-        path => read_network_spec(path),
+    if let Some(network_spec) = LeanNetworkSpec::named(network_string) {
+        return Ok(network_spec);
+    }
+
+    let path = Path::new(network_string);
+    if !path.exists() {
+        return Err(format!(
+            "{network_string} is neither a config file nor a built-in network, which are: {}",
+            LEAN_NETWORK_NAMES.join(", ")
+        ));
     }
+    let network_spec = match path.extension().and_then(|extension| extension.to_str()) {
+        Some("toml") => {
+            let contents =
+                fs::read_to_string(path).map_err(|err| format!("Failed to read file: {err}"))?;
+            toml::from_str::<LeanNetworkSpec>(&contents)
+                .map_err(|err| format!("Failed to parse TOML from: {err}"))?
+        }
+        _ => read_network_spec::<LeanNetworkSpec>(network_string)?,
+    };
+    network_spec
+        .validate()
+        .map_err(|err| format!("Invalid network config {network_string}: {err}"))?;
+    Ok(network_spec)
 }
 
 pub fn lean_devnet_parser(devnet_string: &str) -> Result<Devnet, String> {
@@ -1,15 +1,23 @@
 use std::{
     fmt::{self, Display},
     sync::{Arc, Once, OnceLock},
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 
-use alloy_primitives::FixedBytes;
-use serde::{Deserialize, Deserializer};
+use alloy_primitives::{B256, FixedBytes, aliases::B32, hex};
+use ream_consensus_misc::fork_data::ForkData;
+use serde::{Deserialize, Deserializer, de};
 use tracing::warn;
 
 static HAS_NETWORK_SPEC_BEEN_INITIALIZED: Once = Once::new();
 
+/// Names of the built-in networks which `--network` accepts besides a config file. They are local
+/// networks of the bundled validators, starting shortly after the node.
+pub const LEAN_NETWORK_NAMES: [&str; 3] = ["ephemery", "devnet1", "devnet2"];
+
+/// The fork segment of the gossip topics until the first scheduled fork, as leanSpec names them.
+pub const GENESIS_TOPIC_FORK: &str = "devnet0";
+
 /// Static specification of the Lean Chain network.
 pub static LEAN_NETWORK_SPEC: OnceLock<Arc<LeanNetworkSpec>> = OnceLock::new();
 
@@ -31,6 +39,14 @@ pub fn set_lean_network_spec(network_spec: Arc<LeanNetworkSpec>) {
     });
 }
 
+/// Sets up the ephemery network, which has no scheduled forks, for tests. Once a network is set
+/// up, later calls do nothing.
+pub fn initialize_test_lean_network_spec() {
+    HAS_NETWORK_SPEC_BEEN_INITIALIZED.call_once(|| {
+        let _ = LEAN_NETWORK_SPEC.set(LeanNetworkSpec::ephemery().into());
+    });
+}
+
 /// Returns the static [LeanNetworkSpec] initialized by [set_lean_network_spec].
 ///
 /// # Panics
@@ -50,6 +66,21 @@ pub enum Devnet {
     Two,
 }
 
+impl<'de> Deserialize<'de> for Devnet {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match u64::deserialize(deserializer)? {
+            1 => Ok(Devnet::One),
+            2 => Ok(Devnet::Two),
+            devnet => Err(de::Error::custom(format!(
+                "Expected devnet 1 or 2, but got: {devnet}"
+            ))),
+        }
+    }
+}
+
 impl Display for Devnet {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -59,6 +90,47 @@ impl Display for Devnet {
     }
 }
 
+/// The forks of the Lean chain, in the order they activate. The state transition dispatches on
+/// the fork active at the slot it processes, so new rules start at a slot boundary.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeanFork {
+    #[default]
+    Genesis,
+    Devnet2,
+    /// Adds validator registrations, exits and key rotations to blocks, for devnets with a dynamic
+    /// validator set.
+    Devnet3,
+}
+
+impl Display for LeanFork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LeanFork::Genesis => write!(f, "Genesis"),
+            LeanFork::Devnet2 => write!(f, "Devnet2"),
+            LeanFork::Devnet3 => write!(f, "Devnet3"),
+        }
+    }
+}
+
+/// A fork of [LeanNetworkSpec::fork_schedule], active from `slot` onwards.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct ScheduledLeanFork {
+    pub fork: LeanFork,
+    #[serde(with = "crate::b32_hex")]
+    pub version: B32,
+    pub slot: u64,
+}
+
+/// Returns the fork active at `slot`, or `None` if [set_lean_network_spec] wasn't called. The
+/// rules of a slot depend on the network's fork schedule, so there is no fork to fall back to.
+pub fn lean_fork_at_slot(slot: u64) -> Option<LeanFork> {
+    LEAN_NETWORK_SPEC
+        .get()
+        .map(|network_spec| network_spec.fork_at_slot(slot))
+}
+
 /// Use 3 as the default justification lookback slots if not specified.
 fn default_justification_lookback_slots() -> u64 {
     3
@@ -69,6 +141,63 @@ fn default_seconds_per_slot() -> u64 {
     4
 }
 
+/// 3SF-mini divides a slot into 4 intervals.
+/// Reference: https://github.com/ethereum/research/blob/d225a6775a9b184b5c1fd6c830cc58a375d9535f/3sf-mini/p2p.py#L77-L98
+fn default_intervals_per_slot() -> u64 {
+    4
+}
+
+/// Use 500 milliseconds as the default maximum gossip clock disparity if not specified, the same
+/// as the beacon chain.
+fn default_maximum_gossip_clock_disparity() -> u64 {
+    500
+}
+
+/// What a node does at an interval of a slot.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum IntervalDuty {
+    /// The proposer builds and publishes its block.
+    Propose,
+    /// Validators attest to the head.
+    Attest,
+    /// The safe target is computed from the attestations received so far.
+    SafeTarget,
+    /// Attestations received so far are counted by fork choice.
+    AcceptAttestations,
+}
+
+/// The interval of a slot each [IntervalDuty] runs at, counting from 0.
+#[derive(Clone, Debug, Eq, PartialEq, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub struct IntervalDuties {
+    pub propose: u64,
+    pub attest: u64,
+    pub safe_target: u64,
+    pub accept_attestations: u64,
+}
+
+impl Default for IntervalDuties {
+    fn default() -> Self {
+        Self {
+            propose: 0,
+            attest: 1,
+            safe_target: 2,
+            accept_attestations: 3,
+        }
+    }
+}
+
+impl IntervalDuties {
+    fn duties(&self) -> [(u64, IntervalDuty); 4] {
+        [
+            (self.propose, IntervalDuty::Propose),
+            (self.attest, IntervalDuty::Attest),
+            (self.safe_target, IntervalDuty::SafeTarget),
+            (self.accept_attestations, IntervalDuty::AcceptAttestations),
+        ]
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, Deserialize, Default)]
 #[serde(rename_all = "UPPERCASE")]
 pub struct LeanNetworkSpec {
@@ -83,9 +212,27 @@ pub struct LeanNetworkSpec {
     pub justification_lookback_slots: u64,
     #[serde(default = "default_seconds_per_slot")]
     pub seconds_per_slot: u64,
+    #[serde(default = "default_intervals_per_slot")]
+    pub intervals_per_slot: u64,
+    #[serde(default)]
+    pub interval_duties: IntervalDuties,
+    /// How many milliseconds a message may be early by, to allow for clocks drifting apart.
+    #[serde(default = "default_maximum_gossip_clock_disparity")]
+    pub maximum_gossip_clock_disparity: u64,
+
+    /// Root of the genesis block, checked against the genesis built from this config if set.
+    #[serde(default, rename = "GENESIS_BLOCK_ROOT")]
+    pub genesis_block_root: Option<B256>,
 
-    /// Skipped in YAML, defaults to Devnet::One
-    #[serde(skip)]
+    /// Version of the fork the network starts at, part of the fork digest of the scheduled forks.
+    #[serde(default, with = "crate::b32_hex")]
+    pub genesis_fork_version: B32,
+    /// The forks the network upgrades to after genesis, in the order they activate.
+    #[serde(default)]
+    pub fork_schedule: Vec<ScheduledLeanFork>,
+
+    /// Defaults to Devnet::One, the `--devnet` flag overrides it.
+    #[serde(default)]
     pub devnet: Devnet,
 
     /// Capture any extra fields we aren't interested in
@@ -94,6 +241,18 @@ pub struct LeanNetworkSpec {
 }
 
 impl LeanNetworkSpec {
+    /// Returns the built-in network called `name`, one of [LEAN_NETWORK_NAMES].
+    pub fn named(name: &str) -> Option<Self> {
+        match name {
+            "ephemery" | "devnet1" => Some(Self::ephemery()),
+            "devnet2" => Some(Self {
+                devnet: Devnet::Two,
+                ..Self::ephemery()
+            }),
+            _ => None,
+        }
+    }
+
     /// Creates a new instance of `LeanNetworkSpec` for the Ephemery network
     /// that starts 3 seconds after the current system time,
     pub fn ephemery() -> Self {
@@ -110,13 +269,172 @@ impl LeanNetworkSpec {
             genesis_time: current_timestamp + 10,
             justification_lookback_slots: 3,
             seconds_per_slot: 4,
+            intervals_per_slot: default_intervals_per_slot(),
+            interval_duties: IntervalDuties::default(),
+            maximum_gossip_clock_disparity: default_maximum_gossip_clock_disparity(),
             num_validators: config.num_validators,
             validator_public_keys: config.validator_public_keys,
+            // The genesis time differs on every run, so there is no fixed root to check.
+            genesis_block_root: None,
+            genesis_fork_version: B32::ZERO,
+            fork_schedule: vec![],
             devnet: Devnet::One,
             discarded_values: DiscardUnknown,
         }
     }
 
+    /// Checks that the spec describes a network a node can run, so a bad config file fails at
+    /// startup rather than at the first slot.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.seconds_per_slot == 0 {
+            return Err("SECONDS_PER_SLOT must be at least 1".to_string());
+        }
+        if self.justification_lookback_slots == 0 {
+            return Err("JUSTIFICATION_LOOKBACK_SLOTS must be at least 1".to_string());
+        }
+        if self.num_validators == 0 {
+            return Err("NUM_VALIDATORS must be at least 1".to_string());
+        }
+        if self.num_validators != self.validator_public_keys.len() as u64 {
+            return Err(format!(
+                "NUM_VALIDATORS is {}, but there are {} GENESIS_VALIDATORS",
+                self.num_validators,
+                self.validator_public_keys.len()
+            ));
+        }
+        self.validate_fork_schedule()?;
+        self.validate_intervals()
+    }
+
+    /// Checks that the scheduled forks come after genesis and each other, both in slot and in
+    /// [LeanFork] order, and that every fork has its own version.
+    fn validate_fork_schedule(&self) -> Result<(), String> {
+        let mut previous = (LeanFork::Genesis, 0, self.genesis_fork_version);
+        for scheduled in &self.fork_schedule {
+            let (fork, slot, version) = previous;
+            if scheduled.fork <= fork {
+                return Err(format!("{} is scheduled after {fork}", scheduled.fork));
+            }
+            if scheduled.slot <= slot {
+                return Err(format!(
+                    "{} activates at slot {}, which isn't after {fork} at slot {slot}",
+                    scheduled.fork, scheduled.slot
+                ));
+            }
+            if scheduled.version == version {
+                return Err(format!("{} has the same version as {fork}", scheduled.fork));
+            }
+            previous = (scheduled.fork, scheduled.slot, scheduled.version);
+        }
+        Ok(())
+    }
+
+    /// Checks that every duty runs at its own interval of the slot and that the slot splits into
+    /// its intervals evenly, down to milliseconds.
+    fn validate_intervals(&self) -> Result<(), String> {
+        if self.intervals_per_slot == 0 {
+            return Err("INTERVALS_PER_SLOT must be at least 1".to_string());
+        }
+        if (self.seconds_per_slot * 1000) % self.intervals_per_slot != 0 {
+            return Err(format!(
+                "A slot of {} seconds can't be split into {} intervals of whole milliseconds",
+                self.seconds_per_slot, self.intervals_per_slot
+            ));
+        }
+        let duties = self.interval_duties.duties();
+        for (index, (interval, duty)) in duties.iter().enumerate() {
+            if *interval >= self.intervals_per_slot {
+                return Err(format!(
+                    "{duty:?} runs at interval {interval}, but a slot only has {} intervals",
+                    self.intervals_per_slot
+                ));
+            }
+            if let Some((_, other_duty)) = duties[..index]
+                .iter()
+                .find(|(other_interval, _)| other_interval == interval)
+            {
+                return Err(format!(
+                    "{other_duty:?} and {duty:?} both run at interval {interval}"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the duty which runs at `interval` of a slot, if any.
+    pub fn interval_duty(&self, interval: u64) -> Option<IntervalDuty> {
+        self.interval_duties
+            .duties()
+            .into_iter()
+            .find(|(duty_interval, _)| *duty_interval == interval)
+            .map(|(_, duty)| duty)
+    }
+
+    pub fn interval_duration(&self) -> Duration {
+        Duration::from_millis(self.seconds_per_slot * 1000 / self.intervals_per_slot)
+    }
+
+    /// Returns the latest slot a message may be for at `time` intervals since genesis, allowing
+    /// for the sender's clock being up to [Self::maximum_gossip_clock_disparity] ahead.
+    pub fn latest_gossip_slot(&self, time: u64) -> u64 {
+        let interval_millis = self.interval_duration().as_millis() as u64;
+        (time * interval_millis + self.maximum_gossip_clock_disparity)
+            / (self.seconds_per_slot * 1000)
+    }
+
+    /// Returns the slot at `unix_time` seconds, or 0 before genesis.
+    pub fn slot_at(&self, unix_time: u64) -> u64 {
+        unix_time.saturating_sub(self.genesis_time) / self.seconds_per_slot
+    }
+
+    pub fn current_slot(&self) -> u64 {
+        self.slot_at(
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .expect("System time is before UNIX epoch")
+                .as_secs(),
+        )
+    }
+
+    fn scheduled_fork_at_slot(&self, slot: u64) -> Option<&ScheduledLeanFork> {
+        self.fork_schedule
+            .iter()
+            .rev()
+            .find(|scheduled| slot >= scheduled.slot)
+    }
+
+    /// Returns the fork active at `slot`.
+    pub fn fork_at_slot(&self, slot: u64) -> LeanFork {
+        self.scheduled_fork_at_slot(slot)
+            .map_or(LeanFork::Genesis, |scheduled| scheduled.fork)
+    }
+
+    /// Returns the version of the fork active at `slot`.
+    pub fn fork_version_at_slot(&self, slot: u64) -> B32 {
+        self.scheduled_fork_at_slot(slot)
+            .map_or(self.genesis_fork_version, |scheduled| scheduled.version)
+    }
+
+    /// Returns the digest of the fork active at `slot` on the chain starting at the genesis block
+    /// `genesis_root`.
+    pub fn fork_digest(&self, genesis_root: B256, slot: u64) -> B32 {
+        ForkData {
+            current_version: self.fork_version_at_slot(slot),
+            genesis_validators_root: genesis_root,
+        }
+        .compute_fork_digest()
+    }
+
+    /// Returns the fork segment of the gossip topics at `slot`. Until the first scheduled fork the
+    /// topics are named [GENESIS_TOPIC_FORK] like leanSpec names them, after it by the fork digest
+    /// of the active fork, so nodes on either side of a fork don't gossip together.
+    pub fn topic_fork(&self, genesis_root: B256, slot: u64) -> String {
+        match self.scheduled_fork_at_slot(slot) {
+            Some(_) => hex::encode(self.fork_digest(genesis_root, slot)),
+            None => GENESIS_TOPIC_FORK.to_string(),
+        }
+    }
+
     pub fn is_devnet_enabled(&self, target: Devnet) -> bool {
         match self.devnet {
             Devnet::Two => true,
@@ -138,3 +456,115 @@ impl<'de> Deserialize<'de> for DiscardUnknown {
         Ok(DiscardUnknown)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{B256, aliases::B32, hex};
+
+    use super::{
+        GENESIS_TOPIC_FORK, LeanFork, LeanNetworkSpec, ScheduledLeanFork, lean_fork_at_slot,
+    };
+
+    fn spec_with_forks(fork_schedule: Vec<ScheduledLeanFork>) -> LeanNetworkSpec {
+        LeanNetworkSpec {
+            genesis_fork_version: B32::repeat_byte(1),
+            fork_schedule,
+            ..LeanNetworkSpec::ephemery()
+        }
+    }
+
+    fn devnet2_at(slot: u64, version: B32) -> ScheduledLeanFork {
+        ScheduledLeanFork {
+            fork: LeanFork::Devnet2,
+            version,
+            slot,
+        }
+    }
+
+    #[test]
+    fn test_fork_at_slot() {
+        let spec = spec_with_forks(vec![devnet2_at(10, B32::repeat_byte(2))]);
+        assert!(spec.validate().is_ok());
+
+        assert_eq!(spec.fork_at_slot(0), LeanFork::Genesis);
+        assert_eq!(spec.fork_at_slot(9), LeanFork::Genesis);
+        assert_eq!(spec.fork_at_slot(10), LeanFork::Devnet2);
+        assert_eq!(spec.fork_at_slot(u64::MAX), LeanFork::Devnet2);
+
+        assert_eq!(spec.fork_version_at_slot(9), B32::repeat_byte(1));
+        assert_eq!(spec.fork_version_at_slot(10), B32::repeat_byte(2));
+        assert_ne!(
+            spec.fork_digest(Default::default(), 9),
+            spec.fork_digest(Default::default(), 10)
+        );
+    }
+
+    #[test]
+    fn test_topic_fork() {
+        // The genesis fork keeps the topics of leanSpec.
+        assert_eq!(
+            spec_with_forks(vec![]).topic_fork(Default::default(), u64::MAX),
+            GENESIS_TOPIC_FORK
+        );
+
+        let spec = spec_with_forks(vec![devnet2_at(10, B32::repeat_byte(2))]);
+        assert_eq!(spec.topic_fork(Default::default(), 9), GENESIS_TOPIC_FORK);
+        assert_eq!(
+            spec.topic_fork(Default::default(), 10),
+            hex::encode(spec.fork_digest(Default::default(), 10))
+        );
+        assert_ne!(
+            spec.topic_fork(Default::default(), 10),
+            spec.topic_fork(B256::repeat_byte(1), 10)
+        );
+    }
+
+    #[test]
+    fn test_latest_gossip_slot() {
+        // Slots of 4 seconds with 4 intervals, and a disparity of 500 milliseconds.
+        let spec = LeanNetworkSpec::ephemery();
+        assert_eq!(spec.maximum_gossip_clock_disparity, 500);
+        assert_eq!(spec.latest_gossip_slot(0), 0);
+        // A message for the next slot is accepted once its start is within the disparity.
+        assert_eq!(spec.latest_gossip_slot(3), 0);
+        let spec = LeanNetworkSpec {
+            maximum_gossip_clock_disparity: 1_000,
+            ..spec
+        };
+        assert_eq!(spec.latest_gossip_slot(3), 1);
+        assert_eq!(spec.latest_gossip_slot(4), 1);
+    }
+
+    #[test]
+    fn test_validate_fork_schedule() {
+        assert!(spec_with_forks(vec![]).validate().is_ok());
+
+        // A fork can't activate at genesis, as genesis runs the genesis rules.
+        assert!(
+            spec_with_forks(vec![devnet2_at(0, B32::repeat_byte(2))])
+                .validate()
+                .is_err()
+        );
+        // Every fork needs its own version, or nodes on either side of it would gossip together.
+        assert!(
+            spec_with_forks(vec![devnet2_at(10, B32::repeat_byte(1))])
+                .validate()
+                .is_err()
+        );
+        // A fork can't be scheduled twice.
+        assert!(
+            spec_with_forks(vec![
+                devnet2_at(10, B32::repeat_byte(2)),
+                devnet2_at(20, B32::repeat_byte(3)),
+            ])
+            .validate()
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_lean_fork_at_slot_needs_network_spec() {
+        // No test of this crate sets the network spec, and there is no fork to assume without it.
+        assert_eq!(lean_fork_at_slot(0), None);
+    }
+}
@@ -0,0 +1,322 @@
+//! Runtime controls for diagnostics which operators can change without restarting the node.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{
+        OnceLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+use anyhow::bail;
+use parking_lot::Mutex;
+use tracing_subscriber::{EnvFilter, Registry, filter::LevelFilter, reload::Handle};
+
+/// Filter switched to by SIGHUP when the node runs with its startup filter.
+pub const VERBOSE_LOG_FILTER: &str = "debug";
+
+static DEBUG_INVARIANTS: AtomicBool = AtomicBool::new(false);
+
+static LOG_LEVEL_CONTROLLER: OnceLock<LogLevelController> = OnceLock::new();
+
+/// Whether expensive internal consistency checks should run.
+pub fn debug_invariants() -> bool {
+    DEBUG_INVARIANTS.load(Ordering::Relaxed)
+}
+
+pub fn set_debug_invariants(enabled: bool) {
+    DEBUG_INVARIANTS.store(enabled, Ordering::Relaxed);
+}
+
+/// The target of a filter directive, empty for a bare level like `info`.
+fn directive_target(directive: &str) -> &str {
+    match directive.split_once('=') {
+        Some((target, _)) => target,
+        None if directive.parse::<LevelFilter>().is_ok() => "",
+        None => directive,
+    }
+}
+
+/// Applies the directives of `overrides` on top of `base`, replacing the directives of `base`
+/// for the same targets, e.g. `info,discv5=error` with `discv5=debug,libp2p=warn` is
+/// `info,discv5=debug,libp2p=warn`.
+pub fn merge_directives(base: &str, overrides: &str) -> String {
+    let overrides = overrides
+        .split(',')
+        .map(str::trim)
+        .filter(|directive| !directive.is_empty())
+        .collect::<Vec<_>>();
+    base.split(',')
+        .map(str::trim)
+        .filter(|directive| {
+            !directive.is_empty()
+                && !overrides
+                    .iter()
+                    .any(|other| directive_target(other) == directive_target(directive))
+        })
+        .chain(overrides.iter().copied())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Reads the filter written by [LogLevelController::persist_filter], if there is one.
+pub fn load_persisted_filter(path: &Path) -> Option<String> {
+    let filter = fs::read_to_string(path).ok()?.trim().to_string();
+    (!filter.is_empty()).then_some(filter)
+}
+
+/// Returns the filter the node starts with, and the default filter which
+/// [LogLevelController::clear_persisted_filter] returns to.
+///
+/// A non-empty `rust_log` is used as is. Otherwise the default is the `verbosity` filter, which
+/// the filter persisted at `persist_path` replaces unless the verbosity was passed explicitly,
+/// and `log_levels` apply on top of either.
+pub fn startup_filters(
+    rust_log: &str,
+    verbosity: String,
+    verbosity_passed: bool,
+    log_levels: Option<&str>,
+    persist_path: &Path,
+) -> (String, String) {
+    if !rust_log.is_empty() {
+        return (rust_log.to_string(), rust_log.to_string());
+    }
+    let with_log_levels = |filter: String| match log_levels {
+        Some(log_levels) => merge_directives(&filter, log_levels),
+        None => filter,
+    };
+    let startup_filter = match verbosity_passed {
+        true => None,
+        false => load_persisted_filter(persist_path),
+    };
+    (
+        with_log_levels(startup_filter.unwrap_or_else(|| verbosity.clone())),
+        with_log_levels(verbosity),
+    )
+}
+
+/// Swaps the tracing filter of the global subscriber at runtime.
+pub struct LogLevelController {
+    handle: Handle<EnvFilter, Registry>,
+    startup_filter: String,
+    current_filter: Mutex<String>,
+    persist_path: Option<PathBuf>,
+    default_filter: String,
+}
+
+impl LogLevelController {
+    pub fn new(handle: Handle<EnvFilter, Registry>, startup_filter: String) -> Self {
+        Self {
+            handle,
+            current_filter: Mutex::new(startup_filter.clone()),
+            default_filter: startup_filter.clone(),
+            startup_filter,
+            persist_path: None,
+        }
+    }
+
+    /// Sets the file [Self::persist_filter] writes the filter to, and the filter
+    /// [Self::clear_persisted_filter] returns to.
+    pub fn with_persist_path(mut self, persist_path: PathBuf, default_filter: String) -> Self {
+        self.persist_path = Some(persist_path);
+        self.default_filter = default_filter;
+        self
+    }
+
+    pub fn current_filter(&self) -> String {
+        self.current_filter.lock().clone()
+    }
+
+    /// Replaces the filter with `directives`, which use the `RUST_LOG` syntax.
+    pub fn set_filter(&self, directives: &str) -> anyhow::Result<()> {
+        let filter = EnvFilter::builder().parse(directives)?;
+        self.handle.reload(filter)?;
+        *self.current_filter.lock() = directives.to_string();
+        Ok(())
+    }
+
+    /// Changes the levels of the targets in `levels`, e.g. `ream_fork_choice=debug,libp2p=warn`,
+    /// and keeps the rest of the filter.
+    pub fn set_levels(&self, levels: &str) -> anyhow::Result<()> {
+        self.set_filter(&merge_directives(&self.current_filter(), levels))
+    }
+
+    /// Returns the filter after replacing it with `filter` and changing the levels in `levels`,
+    /// without applying it. Fails if either doesn't parse, so a change can be checked as a whole
+    /// before any of it is applied.
+    pub fn updated_filter(
+        &self,
+        filter: Option<&str>,
+        levels: Option<&str>,
+    ) -> anyhow::Result<String> {
+        let filter = match filter {
+            Some(filter) => {
+                EnvFilter::builder().parse(filter)?;
+                filter.to_string()
+            }
+            None => self.current_filter(),
+        };
+        let filter = match levels {
+            Some(levels) => merge_directives(&filter, levels),
+            None => filter,
+        };
+        EnvFilter::builder().parse(&filter)?;
+        Ok(filter)
+    }
+
+    /// Writes `filter` to the persist path, so the node starts with it next time.
+    pub fn persist_filter(&self, filter: &str) -> anyhow::Result<()> {
+        let Some(path) = &self.persist_path else {
+            bail!("No path to persist the log filter to");
+        };
+        fs::write(path, filter)?;
+        Ok(())
+    }
+
+    /// Removes the persisted filter and switches back to the default filter, which is returned.
+    pub fn clear_persisted_filter(&self) -> anyhow::Result<String> {
+        if let Some(path) = &self.persist_path
+            && let Err(err) = fs::remove_file(path)
+            && err.kind() != io::ErrorKind::NotFound
+        {
+            return Err(err.into());
+        }
+        self.set_filter(&self.default_filter)?;
+        Ok(self.default_filter.clone())
+    }
+
+    /// Switches between the startup filter and [VERBOSE_LOG_FILTER], enabling debug invariants
+    /// together with verbose logs. Returns the filter now in use.
+    pub fn toggle_verbose(&self) -> anyhow::Result<String> {
+        let verbose = self.current_filter() == self.startup_filter;
+        let filter = match verbose {
+            true => VERBOSE_LOG_FILTER.to_string(),
+            false => self.startup_filter.clone(),
+        };
+        self.set_filter(&filter)?;
+        set_debug_invariants(verbose);
+        Ok(filter)
+    }
+}
+
+/// Installs the controller used by the admin API and the SIGHUP handler. Only the first call has
+/// an effect.
+pub fn set_log_level_controller(controller: LogLevelController) {
+    let _ = LOG_LEVEL_CONTROLLER.set(controller);
+}
+
+pub fn log_level_controller() -> Option<&'static LogLevelController> {
+    LOG_LEVEL_CONTROLLER.get()
+}
+
+#[cfg(test)]
+mod tests {
+    use tracing_subscriber::reload::Layer;
+
+    use super::*;
+
+    #[test]
+    fn test_toggle_verbose() {
+        let (_layer, handle) = Layer::<EnvFilter, Registry>::new(EnvFilter::new("info"));
+        let controller = LogLevelController::new(handle, "info".to_string());
+
+        assert_eq!(controller.toggle_verbose().unwrap(), VERBOSE_LOG_FILTER);
+        assert!(debug_invariants());
+
+        assert_eq!(controller.toggle_verbose().unwrap(), "info");
+        assert!(!debug_invariants());
+
+        assert!(controller.set_filter("ream=not_a_level").is_err());
+        assert_eq!(controller.current_filter(), "info");
+
+        controller.set_levels("libp2p=warn").unwrap();
+        assert_eq!(controller.current_filter(), "info,libp2p=warn");
+        assert!(controller.persist_filter("info").is_err());
+    }
+
+    #[test]
+    fn test_updated_filter() {
+        let (_layer, handle) = Layer::<EnvFilter, Registry>::new(EnvFilter::new("info"));
+        let controller = LogLevelController::new(handle, "info".to_string());
+
+        assert_eq!(
+            controller
+                .updated_filter(Some("warn"), Some("libp2p=debug"))
+                .unwrap(),
+            "warn,libp2p=debug"
+        );
+        assert_eq!(
+            controller
+                .updated_filter(None, Some("libp2p=debug"))
+                .unwrap(),
+            "info,libp2p=debug"
+        );
+        // A valid filter with invalid levels is refused as a whole.
+        assert!(
+            controller
+                .updated_filter(Some("warn"), Some("libp2p=not_a_level"))
+                .is_err()
+        );
+        assert!(
+            controller
+                .updated_filter(Some("ream=not_a_level"), None)
+                .is_err()
+        );
+        assert_eq!(controller.current_filter(), "info");
+    }
+
+    #[test]
+    fn test_persisted_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("log_filter.txt");
+        let (_layer, handle) = Layer::<EnvFilter, Registry>::new(EnvFilter::new("info"));
+        let controller = LogLevelController::new(handle, "info".to_string())
+            .with_persist_path(path.clone(), "info".to_string());
+
+        controller.persist_filter("debug,libp2p=warn").unwrap();
+        assert_eq!(
+            startup_filters("", "info".to_string(), false, Some("discv5=error"), &path),
+            (
+                "debug,libp2p=warn,discv5=error".to_string(),
+                "info,discv5=error".to_string()
+            )
+        );
+        // An explicitly passed verbosity, and RUST_LOG, win over the persisted filter.
+        assert_eq!(
+            startup_filters("", "trace".to_string(), true, None, &path),
+            ("trace".to_string(), "trace".to_string())
+        );
+        assert_eq!(
+            startup_filters("warn", "info".to_string(), false, None, &path),
+            ("warn".to_string(), "warn".to_string())
+        );
+
+        controller.set_filter("debug").unwrap();
+        assert_eq!(controller.clear_persisted_filter().unwrap(), "info");
+        assert_eq!(controller.current_filter(), "info");
+        assert!(!path.exists());
+        assert_eq!(
+            startup_filters("", "info".to_string(), false, None, &path),
+            ("info".to_string(), "info".to_string())
+        );
+        // Clearing twice is fine.
+        controller.clear_persisted_filter().unwrap();
+    }
+
+    #[test]
+    fn test_merge_directives() {
+        assert_eq!(
+            merge_directives(
+                "info,actix_server=warn,discv5=error",
+                "discv5=debug,libp2p=warn"
+            ),
+            "info,actix_server=warn,discv5=debug,libp2p=warn"
+        );
+        assert_eq!(
+            merge_directives("info,discv5=error", "debug"),
+            "discv5=error,debug"
+        );
+        assert_eq!(merge_directives("", "ream=trace"), "ream=trace");
+    }
+}
@@ -1 +1,3 @@
+pub mod diagnostics;
+pub mod secret_file;
 pub mod version;
@@ -0,0 +1,62 @@
+//! Writing files which hold keys or secrets.
+
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+};
+
+/// Writes `contents` to `path`, readable and writable by the owner only.
+///
+/// The contents go to a temporary file next to `path` first, which then replaces `path`, so a
+/// crash never leaves a truncated secret behind, and the secret is never readable by others, not
+/// even while it is written.
+pub fn write_secret_file<P: AsRef<Path>>(path: P, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    let mut temporary_path = path.as_os_str().to_owned();
+    temporary_path.push(".tmp");
+    let temporary_path = PathBuf::from(temporary_path);
+
+    // The mode only applies to new files, so a leftover temporary file is never reused.
+    match fs::remove_file(&temporary_path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    let mut file = options.open(&temporary_path)?;
+    file.write_all(contents.as_ref())?;
+    file.sync_all()?;
+    fs::rename(&temporary_path, path)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use tempfile::TempDir;
+
+    use super::write_secret_file;
+
+    #[test]
+    fn test_write_secret_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("secret");
+        fs::write(&path, "old").unwrap();
+        fs::write(dir.path().join("secret.tmp"), "leftover").unwrap();
+
+        write_secret_file(&path, "new").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "new");
+        assert!(!dir.path().join("secret.tmp").exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
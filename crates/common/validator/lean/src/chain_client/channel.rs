@@ -0,0 +1,99 @@
+use anyhow::anyhow;
+use async_trait::async_trait;
+use ream_chain_lean::messages::LeanChainServiceMessage;
+use ream_consensus_lean::{
+    attestation::{AttestationData, SignedAttestation},
+    block::{BlockWithSignatures, SignedBlockWithAttestation},
+};
+use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::chain_client::LeanChainClient;
+
+/// Talks to a [LeanChainService] running in the same process over its message channel.
+pub struct ChannelChainClient {
+    chain_sender: mpsc::UnboundedSender<LeanChainServiceMessage>,
+}
+
+impl ChannelChainClient {
+    pub fn new(chain_sender: mpsc::UnboundedSender<LeanChainServiceMessage>) -> Self {
+        Self { chain_sender }
+    }
+
+    fn send(&self, message: LeanChainServiceMessage) -> anyhow::Result<()> {
+        self.chain_sender
+            .send(message)
+            .map_err(|err| anyhow!("Failed to send message to LeanChainService: {err:?}"))
+    }
+}
+
+#[async_trait]
+impl LeanChainClient for ChannelChainClient {
+    fn name(&self) -> String {
+        "in-process chain service".to_string()
+    }
+
+    async fn proposer_index(&self, slot: u64) -> anyhow::Result<u64> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(LeanChainServiceMessage::GetProposerIndex { slot, sender })?;
+        receiver.await.map_err(|err| {
+            anyhow!("Failed to receive proposer index from LeanChainService: {err:?}")
+        })?
+    }
+
+    async fn validator_public_key(
+        &self,
+        validator_index: u64,
+        slot: u64,
+    ) -> anyhow::Result<Option<PublicKey>> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(LeanChainServiceMessage::GetValidatorPublicKey {
+            validator_index,
+            slot,
+            sender,
+        })?;
+        receiver.await.map_err(|err| {
+            anyhow!("Failed to receive validator public key from LeanChainService: {err:?}")
+        })?
+    }
+
+    async fn produce_block(&self, slot: u64) -> anyhow::Result<BlockWithSignatures> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(LeanChainServiceMessage::ProduceBlock { slot, sender })?;
+        receiver
+            .await
+            .map_err(|err| anyhow!("Failed to receive block from LeanChainService: {err:?}"))
+    }
+
+    async fn attestation_data(&self, slot: u64) -> anyhow::Result<AttestationData> {
+        let (sender, receiver) = oneshot::channel();
+        self.send(LeanChainServiceMessage::BuildAttestationData { slot, sender })?;
+        receiver.await.map_err(|err| {
+            anyhow!("Failed to receive attestation data from LeanChainService: {err:?}")
+        })
+    }
+
+    async fn submit_block(
+        &self,
+        signed_block_with_attestation: SignedBlockWithAttestation,
+    ) -> anyhow::Result<()> {
+        self.send(LeanChainServiceMessage::ProcessBlock {
+            signed_block_with_attestation: Box::new(signed_block_with_attestation),
+            need_gossip: true,
+            peer_id: None,
+            validation: None,
+        })
+    }
+
+    async fn submit_attestation(
+        &self,
+        signed_attestation: SignedAttestation,
+    ) -> anyhow::Result<()> {
+        self.send(LeanChainServiceMessage::ProcessAttestation {
+            signed_attestation: Box::new(signed_attestation),
+            need_gossip: true,
+            from_peer: false,
+            validation: None,
+        })
+    }
+}
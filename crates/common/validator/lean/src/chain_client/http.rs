@@ -0,0 +1,304 @@
+use std::{fs, path::Path, time::Duration};
+
+use alloy_primitives::hex;
+use anyhow::anyhow;
+use async_trait::async_trait;
+use jsonwebtoken::{EncodingKey, Header, encode, get_current_timestamp};
+use ream_api_types_lean::duties::ProposerDuty;
+use ream_consensus_lean::{
+    attestation::{AttestationData, SignedAttestation},
+    block::{BlockWithSignatures, SignedBlockWithAttestation},
+    state::LeanState,
+};
+use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+use reqwest::{Client, RequestBuilder, Response};
+use serde::{Serialize, de::DeserializeOwned};
+use url::Url;
+
+use crate::chain_client::LeanChainClient;
+
+/// Talks to a lean node over the lean API.
+///
+/// The node is expected to serve the validator duties, block production and attestation data
+/// endpoints under `/lean/v0/validator`, and to accept JSON encoded blocks and attestations on
+/// `POST /lean/v0/blocks` and `POST /lean/v0/attestations`. Every request carries a JWT
+/// signed with the admin secret of the node.
+pub struct HttpChainClient {
+    client: Client,
+    endpoint: Url,
+    jwt_encoding_key: EncodingKey,
+}
+
+#[derive(Serialize)]
+struct Claims {
+    iat: u64,
+}
+
+impl HttpChainClient {
+    /// Creates a client for the node at `endpoint`, authenticating with the hex encoded admin
+    /// secret at `admin_secret_path`.
+    pub fn new(endpoint: Url, timeout: Duration, admin_secret_path: &Path) -> anyhow::Result<Self> {
+        let contents = fs::read_to_string(admin_secret_path).map_err(|err| {
+            anyhow!(
+                "Failed to read admin secret {}: {err}",
+                admin_secret_path.display()
+            )
+        })?;
+        let secret = hex::decode(contents.trim()).map_err(|err| {
+            anyhow!(
+                "Admin secret {} is not hex: {err}",
+                admin_secret_path.display()
+            )
+        })?;
+        Ok(Self {
+            client: Client::builder()
+                .timeout(timeout)
+                .build()
+                .map_err(|err| anyhow!("Failed to build lean node HTTP client: {err:?}"))?,
+            endpoint,
+            jwt_encoding_key: EncodingKey::from_secret(&secret),
+        })
+    }
+
+    /// Adds a freshly issued JWT to `request`, as the node only accepts recent ones.
+    fn authenticated(&self, request: RequestBuilder) -> anyhow::Result<RequestBuilder> {
+        let claims = Claims {
+            iat: get_current_timestamp(),
+        };
+        let token = encode(&Header::default(), &claims, &self.jwt_encoding_key)
+            .map_err(|err| anyhow!("Could not encode admin token: {err:?}"))?;
+        Ok(request.bearer_auth(token))
+    }
+
+    fn url(&self, path: &str) -> anyhow::Result<Url> {
+        self.endpoint
+            .join(path)
+            .map_err(|err| anyhow!("Failed to build lean node URL for {path}: {err:?}"))
+    }
+
+    async fn get<T: DeserializeOwned>(&self, path: &str) -> anyhow::Result<T> {
+        let request = self.authenticated(self.client.get(self.url(path)?))?;
+        let response = ensure_success(request.send().await?).await?;
+        Ok(response.json::<T>().await?)
+    }
+}
+
+async fn ensure_success(response: Response) -> anyhow::Result<Response> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(anyhow!(
+        "Lean node returned unexpected status {status:?}: {body}"
+    ))
+}
+
+#[async_trait]
+impl LeanChainClient for HttpChainClient {
+    fn name(&self) -> String {
+        self.endpoint.to_string()
+    }
+
+    async fn proposer_index(&self, slot: u64) -> anyhow::Result<u64> {
+        self.get::<Vec<ProposerDuty>>(&format!("/lean/v0/validator/duties/proposer/{slot}"))
+            .await?
+            .into_iter()
+            .find(|duty| duty.slot == slot)
+            .map(|duty| duty.validator_index)
+            .ok_or_else(|| anyhow!("Lean node returned no proposer for slot {slot}"))
+    }
+
+    async fn validator_public_key(
+        &self,
+        validator_index: u64,
+        slot: u64,
+    ) -> anyhow::Result<Option<PublicKey>> {
+        // The key switches at the activation slot of a key rotation, which is tracked in the
+        // validator statuses of the state.
+        Ok(self
+            .get::<LeanState>("/lean/v0/states/head")
+            .await?
+            .validator_public_key_at(validator_index, slot)
+            .copied())
+    }
+
+    async fn produce_block(&self, slot: u64) -> anyhow::Result<BlockWithSignatures> {
+        self.get(&format!("/lean/v0/validator/blocks/{slot}")).await
+    }
+
+    async fn attestation_data(&self, slot: u64) -> anyhow::Result<AttestationData> {
+        self.get(&format!("/lean/v0/validator/attestation_data/{slot}"))
+            .await
+    }
+
+    async fn submit_block(
+        &self,
+        signed_block_with_attestation: SignedBlockWithAttestation,
+    ) -> anyhow::Result<()> {
+        let request = self
+            .authenticated(self.client.post(self.url("/lean/v0/blocks")?))?
+            .json(&signed_block_with_attestation);
+        ensure_success(request.send().await?).await?;
+        Ok(())
+    }
+
+    async fn submit_attestation(
+        &self,
+        signed_attestation: SignedAttestation,
+    ) -> anyhow::Result<()> {
+        let request = self
+            .authenticated(self.client.post(self.url("/lean/v0/attestations")?))?
+            .json(&signed_attestation);
+        ensure_success(request.send().await?).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, time::Duration};
+
+    use actix_web::{
+        App, HttpRequest, HttpResponse, HttpServer,
+        http::header::AUTHORIZATION,
+        web::{self, Json},
+    };
+    use alloy_primitives::hex;
+    use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode};
+    use ream_api_types_lean::duties::ProposerDuty;
+    use ream_consensus_lean::attestation::{Attestation, AttestationData, SignedAttestation};
+    use ream_post_quantum_crypto::leansig::signature::Signature;
+    use url::Url;
+
+    use super::HttpChainClient;
+    use crate::chain_client::LeanChainClient;
+
+    const SECRET: [u8; 32] = [7; 32];
+
+    fn is_authorized(request: &HttpRequest) -> bool {
+        request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .is_some_and(|token| {
+                let mut validation = Validation::new(Algorithm::HS256);
+                validation.validate_exp = false;
+                validation.set_required_spec_claims(&["iat"]);
+                decode::<serde_json::Value>(token, &DecodingKey::from_secret(&SECRET), &validation)
+                    .is_ok()
+            })
+    }
+
+    async fn proposer_duties(request: HttpRequest, slot: web::Path<u64>) -> HttpResponse {
+        if !is_authorized(&request) {
+            return HttpResponse::Unauthorized().finish();
+        }
+        HttpResponse::Ok().json(vec![ProposerDuty {
+            slot: slot.into_inner(),
+            validator_index: 3,
+            is_local: false,
+        }])
+    }
+
+    async fn attestations(
+        request: HttpRequest,
+        attestation: Json<SignedAttestation>,
+    ) -> HttpResponse {
+        if !is_authorized(&request) {
+            return HttpResponse::Unauthorized().finish();
+        }
+        if attestation.message.validator_id == 0 {
+            return HttpResponse::BadRequest().body("Unknown validator 0");
+        }
+        HttpResponse::Accepted().finish()
+    }
+
+    /// Serves the endpoints the tests use on a free port, returning its URL.
+    fn start_node() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = Url::parse(&format!("http://{}", listener.local_addr().unwrap())).unwrap();
+        let server = HttpServer::new(|| {
+            App::new()
+                .route(
+                    "/lean/v0/validator/duties/proposer/{slot}",
+                    web::get().to(proposer_duties),
+                )
+                .route("/lean/v0/attestations", web::post().to(attestations))
+        })
+        .workers(1)
+        .listen(listener)
+        .unwrap()
+        .run();
+        actix_web::rt::spawn(server);
+        url
+    }
+
+    fn client(endpoint: Url, secret: [u8; 32]) -> HttpChainClient {
+        let secret_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(secret_file.path(), hex::encode(secret)).unwrap();
+        HttpChainClient::new(endpoint, Duration::from_secs(5), secret_file.path()).unwrap()
+    }
+
+    fn attestation(validator_id: u64) -> SignedAttestation {
+        SignedAttestation {
+            message: Attestation {
+                validator_id,
+                data: AttestationData {
+                    slot: 1,
+                    ..Default::default()
+                },
+            },
+            signature: Signature::blank(),
+        }
+    }
+
+    #[actix_web::test]
+    async fn test_http_chain_client() {
+        let endpoint = start_node();
+        let chain_client = client(endpoint.clone(), SECRET);
+
+        assert_eq!(chain_client.proposer_index(5).await.unwrap(), 3);
+        chain_client
+            .submit_attestation(attestation(1))
+            .await
+            .unwrap();
+
+        // Errors of the node are returned along with their body.
+        let err = chain_client
+            .submit_attestation(attestation(0))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown validator 0"), "{err}");
+
+        // The node rejects tokens signed with another secret.
+        let other_client = client(endpoint, [8; 32]);
+        assert!(other_client.proposer_index(5).await.is_err());
+        assert!(
+            other_client
+                .submit_attestation(attestation(1))
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_http_chain_client_requires_hex_secret() {
+        let secret_file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(secret_file.path(), "not hex").unwrap();
+        let endpoint = Url::parse("http://127.0.0.1:5052").unwrap();
+        assert!(
+            HttpChainClient::new(endpoint.clone(), Duration::from_secs(5), secret_file.path())
+                .is_err()
+        );
+        assert!(
+            HttpChainClient::new(
+                endpoint,
+                Duration::from_secs(5),
+                &secret_file.path().with_extension("missing"),
+            )
+            .is_err()
+        );
+    }
+}
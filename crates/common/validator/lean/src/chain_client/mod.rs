@@ -0,0 +1,48 @@
+pub mod channel;
+pub mod http;
+
+use async_trait::async_trait;
+use ream_consensus_lean::{
+    attestation::{AttestationData, SignedAttestation},
+    block::{BlockWithSignatures, SignedBlockWithAttestation},
+};
+use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+
+/// The node a [ValidatorService](crate::service::ValidatorService) learns its duties from, gets
+/// blocks and attestation data to sign from, and submits signed messages to.
+///
+/// [channel::ChannelChainClient] talks to a [LeanChainService] running in the same process,
+/// [http::HttpChainClient] talks to a lean node over the lean API, so the validator can run as a
+/// separate process.
+#[async_trait]
+pub trait LeanChainClient: Send + Sync {
+    /// Human readable identifier used in logs.
+    fn name(&self) -> String;
+
+    /// Returns the index of the validator proposing at `slot`.
+    async fn proposer_index(&self, slot: u64) -> anyhow::Result<u64>;
+
+    /// Returns the key the chain expects the validator at `validator_index` to sign with at
+    /// `slot`, or `None` for an unknown validator.
+    async fn validator_public_key(
+        &self,
+        validator_index: u64,
+        slot: u64,
+    ) -> anyhow::Result<Option<PublicKey>>;
+
+    /// Requests an unsigned block for `slot` along with the signatures of its attestations.
+    async fn produce_block(&self, slot: u64) -> anyhow::Result<BlockWithSignatures>;
+
+    /// Requests the data to attest to at `slot`.
+    async fn attestation_data(&self, slot: u64) -> anyhow::Result<AttestationData>;
+
+    /// Imports and gossips a signed block.
+    async fn submit_block(
+        &self,
+        signed_block_with_attestation: SignedBlockWithAttestation,
+    ) -> anyhow::Result<()>;
+
+    /// Imports and gossips a signed attestation.
+    async fn submit_attestation(&self, signed_attestation: SignedAttestation)
+    -> anyhow::Result<()>;
+}
@@ -0,0 +1,174 @@
+use std::{fs, io::ErrorKind, path::PathBuf};
+
+use alloy_primitives::{FixedBytes, hex::ToHexExt};
+use anyhow::anyhow;
+use ream_api_types_lean::keymanager::{
+    DeleteKeystoresResponse, ImportKeystoresRequest, ImportStatus, KeystoreInfo, KeystoreStatus,
+};
+use ream_keystore::lean_keystore::{LeanEncryptedKeystore, ValidatorKeystore};
+use ream_network_spec::networks::lean_network_spec;
+use ream_node::secret_file::write_secret_file;
+use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+use tokio::sync::oneshot;
+
+const KEYSTORE_EXTENSION: &str = "json";
+const PASSWORD_EXTENSION: &str = "password";
+
+/// Requests the keymanager API makes to the [ValidatorService].
+#[derive(Debug)]
+pub enum KeymanagerMessage {
+    ListKeystores {
+        sender: oneshot::Sender<Vec<KeystoreInfo>>,
+    },
+    ImportKeystores {
+        request: ImportKeystoresRequest,
+        sender: oneshot::Sender<anyhow::Result<Vec<KeystoreStatus<ImportStatus>>>>,
+    },
+    DeleteKeystores {
+        public_keys: Vec<FixedBytes<52>>,
+        sender: oneshot::Sender<anyhow::Result<DeleteKeystoresResponse>>,
+    },
+}
+
+/// The directory keystores imported through the keymanager API are kept in, so they are loaded
+/// again on restart. Each is kept as `<pubkey>.json` next to its password in `<pubkey>.password`.
+#[derive(Debug, Clone)]
+pub struct ImportedKeystores {
+    dir: PathBuf,
+}
+
+impl ImportedKeystores {
+    pub fn new(dir: PathBuf) -> anyhow::Result<Self> {
+        fs::create_dir_all(&dir).map_err(|err| {
+            anyhow!(
+                "Failed to create keystore directory {}: {err}",
+                dir.display()
+            )
+        })?;
+        Ok(Self { dir })
+    }
+
+    /// Decrypts every keystore in the directory.
+    pub fn load(&self) -> anyhow::Result<Vec<ValidatorKeystore>> {
+        let entries = fs::read_dir(&self.dir).map_err(|err| {
+            anyhow!(
+                "Failed to read keystore directory {}: {err}",
+                self.dir.display()
+            )
+        })?;
+
+        let mut keystores = vec![];
+        for entry in entries {
+            let path = entry?.path();
+            if path
+                .extension()
+                .is_none_or(|extension| extension != KEYSTORE_EXTENSION)
+            {
+                continue;
+            }
+            let keystore = fs::read_to_string(&path)
+                .map_err(|err| anyhow!("Failed to read keystore {}: {err}", path.display()))?;
+            let password = fs::read_to_string(path.with_extension(PASSWORD_EXTENSION))
+                .map_err(|err| anyhow!("Failed to read password of {}: {err}", path.display()))?;
+            keystores.push(
+                decrypt_keystore(&keystore, &password)
+                    .map_err(|err| anyhow!("Failed to load keystore {}: {err}", path.display()))?,
+            );
+        }
+        Ok(keystores)
+    }
+
+    /// Keeps `keystore` and its `password`, both readable by the owner only.
+    pub fn save(
+        &self,
+        public_key: &PublicKey,
+        keystore: &str,
+        password: &str,
+    ) -> anyhow::Result<()> {
+        let path = self.keystore_path(public_key);
+        write_secret_file(path.with_extension(PASSWORD_EXTENSION), password)
+            .map_err(|err| anyhow!("Failed to write password of {}: {err}", path.display()))?;
+        write_secret_file(&path, keystore)
+            .map_err(|err| anyhow!("Failed to write keystore {}: {err}", path.display()))?;
+        Ok(())
+    }
+
+    /// Removes the keystore of `public_key`, returning whether there was one.
+    pub fn remove(&self, public_key: &PublicKey) -> anyhow::Result<bool> {
+        let path = self.keystore_path(public_key);
+        match fs::remove_file(&path) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(false),
+            Err(err) => {
+                return Err(anyhow!(
+                    "Failed to remove keystore {}: {err}",
+                    path.display()
+                ));
+            }
+        }
+        fs::remove_file(path.with_extension(PASSWORD_EXTENSION))
+            .map_err(|err| anyhow!("Failed to remove password of {}: {err}", path.display()))?;
+        Ok(true)
+    }
+
+    fn keystore_path(&self, public_key: &PublicKey) -> PathBuf {
+        self.dir
+            .join(public_key.inner.encode_hex())
+            .with_extension(KEYSTORE_EXTENSION)
+    }
+}
+
+/// Decrypts a JSON encoded keystore, and looks up the index of its validator in the network spec.
+pub fn decrypt_keystore(keystore: &str, password: &str) -> anyhow::Result<ValidatorKeystore> {
+    let keystore = serde_json::from_str::<LeanEncryptedKeystore>(keystore)
+        .map_err(|err| anyhow!("Failed to parse keystore: {err}"))?;
+    let index = validator_index(&keystore.public_key)?;
+    let private_key = keystore.decrypt(password.as_bytes())?;
+    Ok(ValidatorKeystore {
+        index,
+        public_key: keystore.public_key,
+        private_key,
+    })
+}
+
+pub fn validator_index(public_key: &PublicKey) -> anyhow::Result<u64> {
+    lean_network_spec()
+        .validator_public_keys
+        .iter()
+        .position(|validator_public_key| *validator_public_key == public_key.inner)
+        .map(|index| index as u64)
+        .ok_or_else(|| anyhow!("{} is not a validator of this network", public_key.inner))
+}
+
+#[cfg(test)]
+mod tests {
+    use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+    use tempfile::TempDir;
+
+    use super::ImportedKeystores;
+
+    #[test]
+    fn test_save_and_remove_keystore() {
+        let dir = TempDir::new().unwrap();
+        let imported_keystores = ImportedKeystores::new(dir.path().join("keystores")).unwrap();
+        let public_key = PublicKey::from(&[1_u8; 52][..]);
+
+        imported_keystores
+            .save(&public_key, "{}", "password")
+            .unwrap();
+        let path = imported_keystores.keystore_path(&public_key);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            for path in [path.clone(), path.with_extension(super::PASSWORD_EXTENSION)] {
+                let mode = std::fs::metadata(path).unwrap().permissions().mode();
+                assert_eq!(mode & 0o777, 0o600);
+            }
+        }
+
+        assert!(imported_keystores.remove(&public_key).unwrap());
+        assert!(!path.exists());
+        assert!(!imported_keystores.remove(&public_key).unwrap());
+    }
+}
@@ -1,2 +1,6 @@
+pub mod chain_client;
+pub mod keymanager;
 pub mod registry;
 pub mod service;
+pub mod signer;
+pub mod slashing_protection;
@@ -1,8 +1,20 @@
-use std::{fs, path::Path};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-use anyhow::anyhow;
-use ream_keystore::lean_keystore::{ValidatorKeysManifest, ValidatorKeystore, ValidatorRegistry};
-use ream_post_quantum_crypto::leansig::private_key::{LeanSigPrivateKey, PrivateKey};
+use anyhow::{anyhow, ensure};
+use ream_keystore::{
+    lean_keystore::{RotatedKeystore, ValidatorKeysManifest, ValidatorKeystore, ValidatorRegistry},
+    secret::SecretBytes,
+};
+use ream_post_quantum_crypto::leansig::{
+    LEAN_SIG_LIFETIME, LEAN_SIG_SCHEME_NAME,
+    private_key::{LeanSigPrivateKey, PrivateKey},
+};
+
+pub const MANIFEST_FILE: &str = "validator-keys-manifest.yaml";
 
 /// Load validator registry from YAML file for a specific node
 ///
@@ -13,45 +25,182 @@ pub fn load_validator_registry<P: AsRef<Path> + std::fmt::Debug>(
     path: P,
     node_id: &str,
 ) -> anyhow::Result<Vec<ValidatorKeystore>> {
-    let mut path = path.as_ref().to_path_buf();
-    let validator_registry_yaml = fs::read_to_string(&path)
+    let validator_indices = load_validator_indices(&path, node_id)?;
+    load_validator_keystores(path, &validator_indices)
+}
+
+/// Returns the indices of the validators the registry at `path` assigns to `node_id`.
+pub fn load_validator_indices<P: AsRef<Path>>(path: P, node_id: &str) -> anyhow::Result<Vec<u64>> {
+    let validator_registry_yaml = fs::read_to_string(path.as_ref())
         .map_err(|err| anyhow!("Failed to read validator registry file {err}"))?;
     let validator_registry = serde_yaml::from_str::<ValidatorRegistry>(&validator_registry_yaml)
         .map_err(|err| anyhow!("Failed to parse validator registry YAML: {err}"))?;
 
-    path.pop();
-    path.push("hash-sig-keys/");
-    let mut validator_keystores = vec![];
-    for ream_validator_index in validator_registry.nodes.get(node_id).expect("") {
-        path.push("validator-keys-manifest.yaml");
-
-        let validator_keys_manifest_yaml = fs::read_to_string(&path)
-            .map_err(|err| anyhow!("Failed to read validator keys manifest yaml file {err}",))?;
+    validator_registry
+        .nodes
+        .get(node_id)
+        .cloned()
+        .ok_or_else(|| anyhow!("Validator registry has no validators for node {node_id}"))
+}
 
-        let validator_keys_manifest =
-            serde_yaml::from_str::<ValidatorKeysManifest>(&validator_keys_manifest_yaml)
-                .map_err(|err| anyhow!("Failed to parse validator keys manifest yaml: {err}"))?;
+/// Loads the keystores of `validator_indices` from the keys manifest next to the registry at
+/// `path`.
+pub fn load_validator_keystores<P: AsRef<Path>>(
+    path: P,
+    validator_indices: &[u64],
+) -> anyhow::Result<Vec<ValidatorKeystore>> {
+    let keys_dir = keys_dir(path.as_ref());
+    let validator_keys_manifest = load_keys_manifest(&keys_dir)?;
 
+    let mut validator_keystores = vec![];
+    for validator_index in validator_indices {
         let validator = validator_keys_manifest
             .validators
-            .get(*ream_validator_index as usize)
-            .expect("Failed to get ream validator index");
-
-        path.pop();
-        path.push(validator.privkey_file.clone());
-        let validator_private_key_json = fs::read_to_string(&path)
-            .map_err(|err| anyhow!("Failed to read validator private key json file {err}",))?;
-        let hash_sig_private_key =
-            serde_json::from_str::<LeanSigPrivateKey>(&validator_private_key_json)
-                .map_err(|err| anyhow!("Failed to parse validator private key json: {err}"))?;
-        let private_key = PrivateKey::new(hash_sig_private_key);
+            .get(*validator_index as usize)
+            .ok_or_else(|| anyhow!("Validator {validator_index} isn't in the keys manifest"))?;
 
         validator_keystores.push(ValidatorKeystore {
-            index: *ream_validator_index,
+            index: *validator_index,
             public_key: validator.public_key,
-            private_key,
+            private_key: load_private_key(
+                &keys_dir,
+                &validator_keys_manifest,
+                &validator.privkey_file,
+            )?,
         });
-        path.pop();
     }
     Ok(validator_keystores)
 }
+
+/// Loads the keys which the validators of `validator_indices` rotate to, from the keys manifest
+/// next to the registry at `path`.
+pub fn load_key_rotations<P: AsRef<Path>>(
+    path: P,
+    validator_indices: &[u64],
+) -> anyhow::Result<Vec<RotatedKeystore>> {
+    let keys_dir = keys_dir(path.as_ref());
+    let validator_keys_manifest = load_keys_manifest(&keys_dir)?;
+
+    let mut rotated_keystores = vec![];
+    for validator in validator_keys_manifest
+        .validators
+        .iter()
+        .filter(|validator| validator_indices.contains(&validator.index))
+    {
+        for rotation in &validator.rotations {
+            rotated_keystores.push(RotatedKeystore {
+                activation_slot: rotation.activation_slot,
+                keystore: ValidatorKeystore {
+                    index: validator.index,
+                    public_key: rotation.public_key,
+                    private_key: load_private_key(
+                        &keys_dir,
+                        &validator_keys_manifest,
+                        &rotation.privkey_file,
+                    )?,
+                },
+            });
+        }
+    }
+    Ok(rotated_keystores)
+}
+
+pub fn load_keys_manifest(keys_dir: &Path) -> anyhow::Result<ValidatorKeysManifest> {
+    let validator_keys_manifest_yaml = fs::read_to_string(keys_dir.join(MANIFEST_FILE))
+        .map_err(|err| anyhow!("Failed to read validator keys manifest yaml file {err}",))?;
+    serde_yaml::from_str::<ValidatorKeysManifest>(&validator_keys_manifest_yaml)
+        .map_err(|err| anyhow!("Failed to parse validator keys manifest yaml: {err}"))
+}
+
+/// Loads a private key of `manifest`. Keys of another scheme or lifetime than the one validators
+/// sign with are refused, as they might parse but sign with the wrong parameters.
+fn load_private_key(
+    keys_dir: &Path,
+    manifest: &ValidatorKeysManifest,
+    privkey_file: &str,
+) -> anyhow::Result<PrivateKey> {
+    ensure!(
+        manifest.key_scheme == LEAN_SIG_SCHEME_NAME && manifest.lifetime == LEAN_SIG_LIFETIME,
+        "Keys manifest has {} keys with a lifetime of {} epochs, but validators sign with \
+         {LEAN_SIG_SCHEME_NAME} keys with a lifetime of {LEAN_SIG_LIFETIME} epochs",
+        manifest.key_scheme,
+        manifest.lifetime
+    );
+    let validator_private_key_json = SecretBytes::new(
+        fs::read(keys_dir.join(privkey_file))
+            .map_err(|err| anyhow!("Failed to read validator private key json file {err}",))?,
+    );
+    let hash_sig_private_key =
+        serde_json::from_slice::<LeanSigPrivateKey>(validator_private_key_json.expose_secret())
+            .map_err(|err| anyhow!("Failed to parse validator private key json: {err}"))?;
+    Ok(PrivateKey::new(hash_sig_private_key))
+}
+
+/// Returns when the registry at `path` or its keys manifest was last changed, to tell whether
+/// the validators have to be reloaded.
+pub fn registry_modified<P: AsRef<Path>>(path: P) -> anyhow::Result<SystemTime> {
+    let registry_modified = fs::metadata(path.as_ref())?.modified()?;
+    let manifest_modified =
+        fs::metadata(keys_dir(path.as_ref()).join(MANIFEST_FILE))?.modified()?;
+    Ok(registry_modified.max(manifest_modified))
+}
+
+/// Returns the directory of the keys of the registry at `registry_path`.
+pub fn keys_dir(registry_path: &Path) -> PathBuf {
+    registry_path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join("hash-sig-keys")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use ream_keystore::lean_keystore::{ValidatorKeysManifest, ValidatorKeystoreRaw};
+    use ream_post_quantum_crypto::leansig::{LEAN_SIG_LIFETIME, LEAN_SIG_SCHEME_NAME};
+    use tempfile::TempDir;
+
+    use super::{MANIFEST_FILE, keys_dir, load_validator_keystores};
+
+    #[test]
+    fn test_load_keys_of_other_scheme() {
+        let dir = TempDir::new().unwrap();
+        let registry_path = dir.path().join("validators.yaml");
+        let keys_dir = keys_dir(&registry_path);
+        fs::create_dir_all(&keys_dir).unwrap();
+        fs::write(keys_dir.join("validator_0_sk.json"), "{}").unwrap();
+        let write_manifest = |key_scheme: &str, lifetime| {
+            let manifest = ValidatorKeysManifest {
+                key_scheme: key_scheme.to_string(),
+                hash_function: "Poseidon2".to_string(),
+                encoding: "TargetSum".to_string(),
+                lifetime,
+                log_num_active_epochs: 3,
+                num_active_epochs: 8,
+                num_validators: 1,
+                validators: vec![ValidatorKeystoreRaw {
+                    index: 0,
+                    public_key: Default::default(),
+                    privkey_file: "validator_0_sk.json".to_string(),
+                    rotations: vec![],
+                }],
+            };
+            fs::write(
+                keys_dir.join(MANIFEST_FILE),
+                serde_yaml::to_string(&manifest).unwrap(),
+            )
+            .unwrap();
+        };
+
+        // Keys of the scheme validators sign with are read, this one isn't a key.
+        write_manifest(LEAN_SIG_SCHEME_NAME, LEAN_SIG_LIFETIME);
+        let err = load_validator_keystores(&registry_path, &[0]).unwrap_err();
+        assert!(err.to_string().contains("Failed to parse"));
+
+        // Keys of another scheme are refused before being read.
+        write_manifest("SIGTopLevelTargetSumLifetime8Dim64Base8", 1 << 8);
+        let err = load_validator_keystores(&registry_path, &[0]).unwrap_err();
+        assert!(err.to_string().contains("validators sign with"));
+    }
+}
@@ -1,42 +1,153 @@
-use anyhow::anyhow;
-use ream_chain_lean::{clock::create_lean_clock_interval, messages::LeanChainServiceMessage};
+use std::{collections::HashSet, mem, path::PathBuf, sync::Arc, time::SystemTime};
+
+use alloy_primitives::FixedBytes;
+use anyhow::{anyhow, ensure};
+use ream_api_types_lean::keymanager::{
+    DeleteKeystoresResponse, DeleteStatus, ImportKeystoresRequest, ImportStatus, KeystoreInfo,
+    KeystoreStatus,
+};
+use ream_chain_lean::clock::create_lean_clock_interval;
 use ream_consensus_lean::{
     attestation::{Attestation, SignedAttestation},
     block::{BlockWithAttestation, BlockWithSignatures, SignedBlockWithAttestation},
 };
-use ream_keystore::lean_keystore::ValidatorKeystore;
-use ream_network_spec::networks::lean_network_spec;
-use tokio::sync::{mpsc, oneshot};
-use tracing::{Level, debug, enabled, info};
+use ream_executor::ShutdownSignal;
+use ream_keystore::lean_keystore::{RotatedKeystore, ValidatorKeystore};
+use ream_metrics::{KEY_EPOCHS_REMAINING, remove_int_gauge_vec, set_int_gauge_vec};
+use ream_network_spec::networks::{IntervalDuty, lean_network_spec};
+use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+use tokio::{sync::mpsc, task::spawn_blocking};
+use tracing::{Level, debug, enabled, error, info, warn};
 use tree_hash::TreeHash;
 
+use crate::{
+    chain_client::LeanChainClient,
+    keymanager::{ImportedKeystores, KeymanagerMessage, decrypt_keystore, validator_index},
+    registry::{
+        load_key_rotations, load_validator_indices, load_validator_keystores, registry_modified,
+    },
+    signer::Signings,
+    slashing_protection::{SlashingProtection, SlashingProtectionRecords},
+};
+
+/// Keys which can sign at fewer epochs than this are warned about, a day of 4 second slots.
+const LOW_KEY_EPOCHS: u64 = 21_600;
+
+/// How many slots apart a key running low on epochs is warned about.
+const LOW_KEY_EPOCHS_WARNING_INTERVAL: u64 = 900;
+
 /// ValidatorService is responsible for managing validator operations
 /// such as proposing blocks and submitting attestations on them. This service also holds the
 /// keystores for its validators, which are used to sign.
 ///
-/// Every first tick (t=0) it proposes a block if it's the validator's turn.
-/// Every second tick (t=1/4) it attestations on the proposed block.
+/// At the propose interval of the network spec it proposes a block if it's the validator's turn,
+/// and at the attest interval it attests to the proposed block.
+///
+/// Blocks and attestation data come from, and signed messages go to, a [LeanChainClient], which
+/// is either the [LeanChainService] of the same process or a lean node reached over HTTP.
+///
+/// Every message is checked against [SlashingProtection] before it is signed. Signing runs on
+/// blocking threads, see [Signings], and has to finish within an interval. As XMSS keys can only
+/// sign at a bounded number of epochs, the number left to each key is tracked as well, and with
+/// [ValidatorService::with_key_rotations] a validator switches to its next key at the activation
+/// slot of its key rotation. With
+/// [ValidatorService::with_keystore_reload], the safe target interval also picks up the validators
+/// added to or removed from the validator registry. With [ValidatorService::with_keymanager], keys
+/// can also be listed, imported and deleted through the keymanager API.
 ///
 /// NOTE: Other ticks should be handled by the other services, such as [LeanChainService].
 pub struct ValidatorService {
-    keystores: Vec<ValidatorKeystore>,
-    chain_sender: mpsc::UnboundedSender<LeanChainServiceMessage>,
+    keystores: Vec<Arc<ValidatorKeystore>>,
+    chain_client: Box<dyn LeanChainClient>,
+    slashing_protection: SlashingProtection,
+    keystore_reload: Option<KeystoreReload>,
+    keymanager: Option<Keymanager>,
+    /// The keys the validators switch to, which aren't active yet.
+    key_rotations: Vec<RotatedKeystore>,
+    /// The validators whose key can't sign anymore, which were reported already.
+    exhausted_keys: HashSet<u64>,
+}
+
+/// The validator registry the keystores were loaded from, and when it last changed.
+struct KeystoreReload {
+    registry_path: PathBuf,
+    node_id: String,
+    modified: Option<SystemTime>,
+}
+
+/// Where the keymanager API's requests come from, and where the keys it imports are kept.
+struct Keymanager {
+    receiver: mpsc::UnboundedReceiver<KeymanagerMessage>,
+    imported_keystores: ImportedKeystores,
+    /// Validators whose keys were imported, rather than loaded from the validator registry.
+    imported_indices: HashSet<u64>,
 }
 
 impl ValidatorService {
     pub async fn new(
         keystores: Vec<ValidatorKeystore>,
-        chain_sender: mpsc::UnboundedSender<LeanChainServiceMessage>,
+        chain_client: Box<dyn LeanChainClient>,
+        slashing_protection: SlashingProtection,
     ) -> Self {
         ValidatorService {
-            keystores,
-            chain_sender,
+            keystores: keystores.into_iter().map(Arc::new).collect(),
+            chain_client,
+            slashing_protection,
+            keystore_reload: None,
+            keymanager: None,
+            key_rotations: vec![],
+            exhausted_keys: HashSet::new(),
+        }
+    }
+
+    /// Reloads the validators of `node_id` whenever the registry at `registry_path`, or its keys
+    /// manifest, changes.
+    pub fn with_keystore_reload(mut self, registry_path: PathBuf, node_id: String) -> Self {
+        self.keystore_reload = Some(KeystoreReload {
+            modified: registry_modified(&registry_path).ok(),
+            registry_path,
+            node_id,
+        });
+        self
+    }
+
+    /// Signs with the keys of `key_rotations` from their activation slots on. The rotations have
+    /// to be on chain by then, see `ream keys rotate`.
+    pub fn with_key_rotations(mut self, key_rotations: Vec<RotatedKeystore>) -> Self {
+        self.key_rotations = key_rotations;
+        self
+    }
+
+    /// Serves the keymanager API's requests from `receiver`, and signs with the keys which were
+    /// imported through it before. A key in the validator registry as well is treated as a
+    /// registry key.
+    pub fn with_keymanager(
+        mut self,
+        receiver: mpsc::UnboundedReceiver<KeymanagerMessage>,
+        imported_keystores: ImportedKeystores,
+    ) -> anyhow::Result<Self> {
+        let mut imported_indices = HashSet::new();
+        for keystore in imported_keystores.load()? {
+            if self.is_active(keystore.index) {
+                continue;
+            }
+            imported_indices.insert(keystore.index);
+            self.keystores.push(Arc::new(keystore));
         }
+        self.keymanager = Some(Keymanager {
+            receiver,
+            imported_keystores,
+            imported_indices,
+        });
+        Ok(self)
     }
 
-    pub async fn start(self) -> anyhow::Result<()> {
+    /// Runs until `shutdown` fires. A duty in progress is finished, so a block or attestation
+    /// which has been signed is always submitted.
+    pub async fn start(mut self, mut shutdown: ShutdownSignal) -> anyhow::Result<()> {
         info!(
             genesis_time = lean_network_spec().genesis_time,
+            chain = self.chain_client.name(),
             "ValidatorService started with {} validator(s)",
             self.keystores.len()
         );
@@ -49,119 +160,816 @@ impl ValidatorService {
         loop {
             tokio::select! {
                 _ = interval.tick() => {
-                    let slot = tick_count / 4;
-                    match tick_count % 4 {
-                        0 => {
-                            // First tick (t=0): Propose a block.
-                            if slot > 0 && let Some(keystore) = self.is_proposer(slot) {
-                                info!(slot, tick = tick_count, "Proposing block by Validator {}", keystore.index);
-                                let (tx, rx) = oneshot::channel();
-
-                                self.chain_sender
-                                    .send(LeanChainServiceMessage::ProduceBlock { slot, sender: tx })
-                                    .expect("Failed to send produce block to LeanChainService");
-
-                                // Wait for the block to be produced.
-                                let BlockWithSignatures { block, mut signatures } = rx.await.expect("Failed to receive block from LeanChainService");
-
-                                info!(
-                                    slot = block.slot,
-                                    block_root = ?block.tree_hash_root(),
-                                    "Building block finished by Validator {}",
-                                    keystore.index,
-                                );
-
-                            let (tx, rx) = oneshot::channel();
-                            self.chain_sender
-                                .send(LeanChainServiceMessage::BuildAttestationData { slot, sender: tx })
-                                .expect("Failed to send attestation to LeanChainService");
-
-                            let attestation_data = rx.await.expect("Failed to receive attestation data from LeanChainService");
-                                let message = Attestation { validator_id: keystore.index, data: attestation_data };
-                                signatures.push(keystore.private_key.sign(&message.tree_hash_root(), slot as u32)?).map_err(|err| anyhow!("Failed to push signature {err:?}"))?;
-                                let signed_block_with_attestation = SignedBlockWithAttestation {
-                                    message: BlockWithAttestation {
-                                        block: block.clone(),
-                                        proposer_attestation: message,
-                                    },
-                                    signature: signatures,
-                                };
-
-                                // Send block to the LeanChainService.
-                                self.chain_sender
-                                    .send(LeanChainServiceMessage::ProcessBlock { signed_block_with_attestation: Box::new(signed_block_with_attestation), need_gossip: true })
-                                    .map_err(|err| anyhow!("Failed to send block to LeanChainService: {err:?}"))?;
-                            } else {
-
-                                let proposer_index = slot % lean_network_spec().num_validators;
-                                info!("Not proposer for slot {slot} (proposer is validator {proposer_index}), skipping");
-
+                    let spec = lean_network_spec();
+                    let slot = tick_count / spec.intervals_per_slot;
+                    if tick_count % spec.intervals_per_slot == 0 {
+                        self.apply_key_rotations(slot).await;
+                    }
+                    match spec.interval_duty(tick_count % spec.intervals_per_slot) {
+                        Some(IntervalDuty::Propose) => {
+                            if slot > 0 && let Err(err) = self.propose_block(slot, tick_count).await {
+                                error!(slot, "Failed to propose block: {err:?}");
                             }
                         }
-                        1 => {
-                            // Second tick (t=1/4): Attestation.
-                            info!(slot, tick = tick_count, "Starting attestation phase: {} validator(s) voting", self.keystores.len());
-
-                            let (tx, rx) = oneshot::channel();
-                            self.chain_sender
-                                .send(LeanChainServiceMessage::BuildAttestationData { slot, sender: tx })
-                                .expect("Failed to send attestation to LeanChainService");
-
-                            let attestation_data = rx.await.expect("Failed to receive attestation data from LeanChainService");
-
-                            if enabled!(Level::DEBUG) {
-                                debug!(
-                                    slot = attestation_data.slot,
-                                    head = ?attestation_data.head,
-                                    source = ?attestation_data.source,
-                                    target = ?attestation_data.target,
-                                    "Building attestation data finished",
-                                );
-                            } else {
-                                info!(
-                                    slot = attestation_data.slot,
-                                    head_slot = attestation_data.head.slot,
-                                    source_slot = attestation_data.source.slot,
-                                    target_slot = attestation_data.target.slot,
-                                    "Building attestation data finished",
-                                );
-                            }
-
-                            // TODO: Sign the attestation with the keystore.
-                            let mut signed_attestations = vec![];
-                            for (_, keystore) in self.keystores.iter().enumerate().filter(|(index, _)| *index as u64 != slot % lean_network_spec().num_validators) {
-                                let message = Attestation {
-                                        validator_id: keystore.index,
-                                        data: attestation_data.clone()
-                                    };
-                                    signed_attestations.push(SignedAttestation {
-                                    signature: keystore.private_key.sign(&message.tree_hash_root(), slot as u32)?,
-                                    message,
-                                });
+                        Some(IntervalDuty::Attest) => {
+                            if let Err(err) = self.attest(slot, tick_count).await {
+                                error!(slot, "Failed to attest: {err:?}");
                             }
-
-                            for signed_attestation in signed_attestations {
-                                self.chain_sender
-                                    .send(LeanChainServiceMessage::ProcessAttestation { signed_attestation: Box::new(signed_attestation), need_gossip: true })
-                                    .map_err(|err| anyhow!("Failed to send attestation to LeanChainService: {err:?}"))?;
+                        }
+                        Some(IntervalDuty::SafeTarget) => {
+                            // Validators have nothing to sign, pick up changes to the validator
+                            // registry.
+                            if let Err(err) = self.reload_keystores(slot).await {
+                                warn!(slot, "Failed to reload keystores: {err:?}");
                             }
+                            self.check_key_epochs(slot);
                         }
                         _ => {
-                            // Other ticks (t=2/4, t=3/4): Do nothing.
+                            // Other intervals are handled by the LeanChainService.
                         }
                     }
                     tick_count += 1;
                 }
+                Some(message) = next_keymanager_message(&mut self.keymanager) => {
+                    self.handle_keymanager_message(message).await;
+                }
+                _ = shutdown.wait() => {
+                    info!("ValidatorService stopped");
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    async fn propose_block(&mut self, slot: u64, tick_count: u64) -> anyhow::Result<()> {
+        let proposer_index = self.chain_client.proposer_index(slot).await?;
+        let Some(keystore) = self
+            .keystores
+            .iter()
+            .find(|keystore| keystore.index == proposer_index)
+            .cloned()
+        else {
+            info!(
+                "Not proposer for slot {slot} (proposer is validator {proposer_index}), skipping"
+            );
+            return Ok(());
+        };
+
+        info!(
+            slot,
+            tick = tick_count,
+            "Proposing block by Validator {}",
+            keystore.index
+        );
+
+        // Wait for the block to be produced.
+        let BlockWithSignatures {
+            block,
+            mut signatures,
+        } = self.chain_client.produce_block(slot).await?;
+
+        info!(
+            slot = block.slot,
+            block_root = ?block.tree_hash_root(),
+            "Building block finished by Validator {}",
+            keystore.index,
+        );
+
+        let attestation_data = self.chain_client.attestation_data(slot).await?;
+        let message = Attestation {
+            validator_id: keystore.index,
+            data: attestation_data,
+        };
+        self.slashing_protection
+            .check_and_record(keystore.index, slot)?;
+        let (_, signature) = Signings::start(
+            [(keystore, message.tree_hash_root(), ())],
+            slot as u32,
+            lean_network_spec().interval_duration(),
+        )
+        .next()
+        .await
+        .ok_or_else(|| anyhow!("Proposer attestation wasn't signed in time"))?;
+        signatures
+            .push(signature?)
+            .map_err(|err| anyhow!("Failed to push signature {err:?}"))?;
+
+        self.chain_client
+            .submit_block(SignedBlockWithAttestation {
+                message: BlockWithAttestation {
+                    block,
+                    proposer_attestation: message,
+                },
+                signature: signatures,
+            })
+            .await
+    }
+
+    async fn attest(&mut self, slot: u64, tick_count: u64) -> anyhow::Result<()> {
+        info!(
+            slot,
+            tick = tick_count,
+            "Starting attestation phase: {} validator(s) voting",
+            self.keystores.len()
+        );
+
+        let attestation_data = self.chain_client.attestation_data(slot).await?;
+
+        if enabled!(Level::DEBUG) {
+            debug!(
+                slot = attestation_data.slot,
+                head = ?attestation_data.head,
+                source = ?attestation_data.source,
+                target = ?attestation_data.target,
+                "Building attestation data finished",
+            );
+        } else {
+            info!(
+                slot = attestation_data.slot,
+                head_slot = attestation_data.head.slot,
+                source_slot = attestation_data.source.slot,
+                target_slot = attestation_data.target.slot,
+                "Building attestation data finished",
+            );
+        }
+
+        // The proposer already attested in its block.
+        let proposer_index = self.chain_client.proposer_index(slot).await?;
+        let mut jobs = vec![];
+        for keystore in self
+            .keystores
+            .iter()
+            .filter(|keystore| keystore.index != proposer_index)
+        {
+            if let Err(err) = self
+                .slashing_protection
+                .check_and_record(keystore.index, slot)
+            {
+                warn!(slot, "Not attesting: {err:?}");
+                continue;
+            }
+            let message = Attestation {
+                validator_id: keystore.index,
+                data: attestation_data.clone(),
+            };
+            jobs.push((keystore.clone(), message.tree_hash_root(), message));
+        }
+
+        // Each attestation is submitted as soon as it is signed.
+        let mut signings =
+            Signings::start(jobs, slot as u32, lean_network_spec().interval_duration());
+        while let Some((message, signature)) = signings.next().await {
+            let signature = match signature {
+                Ok(signature) => signature,
+                Err(err) => {
+                    warn!(slot, "Not attesting: {err:?}");
+                    continue;
+                }
+            };
+            let validator_id = message.validator_id;
+            if let Err(err) = self
+                .chain_client
+                .submit_attestation(SignedAttestation { message, signature })
+                .await
+            {
+                warn!(slot, validator_id, "Failed to submit attestation: {err:?}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Adds the validators which were added to the registry and drops the ones which were removed
+    /// from it, if it changed since the last reload. Keys of added validators are loaded on a
+    /// blocking thread, as they are large.
+    async fn reload_keystores(&mut self, slot: u64) -> anyhow::Result<()> {
+        let Some(keystore_reload) = &self.keystore_reload else {
+            return Ok(());
+        };
+        let registry_path = keystore_reload.registry_path.clone();
+        let modified = registry_modified(&registry_path)?;
+        if keystore_reload.modified == Some(modified) {
+            return Ok(());
+        }
+
+        let validator_indices = load_validator_indices(&registry_path, &keystore_reload.node_id)?;
+        let added_indices = validator_indices
+            .iter()
+            .filter(|index| {
+                !self
+                    .keystores
+                    .iter()
+                    .any(|keystore| keystore.index == **index)
+            })
+            .copied()
+            .collect::<Vec<_>>();
+        let added_keystores = tokio::task::spawn_blocking({
+            let registry_path = registry_path.clone();
+            move || load_validator_keystores(registry_path, &added_indices)
+        })
+        .await??;
+
+        let imported_indices = self
+            .keymanager
+            .as_ref()
+            .map(|keymanager| keymanager.imported_indices.clone())
+            .unwrap_or_default();
+        self.keystores.retain(|keystore| {
+            let keep = validator_indices.contains(&keystore.index)
+                || imported_indices.contains(&keystore.index);
+            if !keep {
+                info!(slot, "Validator {} deactivated", keystore.index);
+                remove_int_gauge_vec(&KEY_EPOCHS_REMAINING, &[&keystore.index.to_string()]);
+            }
+            keep
+        });
+        for keystore in added_keystores {
+            // The first slot this validator may sign at, it never signs twice for a slot.
+            let activation_slot = self
+                .slashing_protection
+                .last_signed_slot(keystore.index)
+                .map_or(slot + 1, |last_signed_slot| {
+                    (slot + 1).max(last_signed_slot + 1)
+                });
+            info!(
+                activation_slot,
+                "Validator {} activated, running {} validator(s)",
+                keystore.index,
+                self.keystores.len() + 1
+            );
+            self.keystores.push(Arc::new(keystore));
+        }
+
+        // Key rotations may have been added to the keys manifest as well.
+        let active_indices = self
+            .keystores
+            .iter()
+            .map(|keystore| keystore.index)
+            .collect::<Vec<_>>();
+        self.key_rotations =
+            tokio::task::spawn_blocking(move || load_key_rotations(registry_path, &active_indices))
+                .await??;
+
+        if let Some(keystore_reload) = &mut self.keystore_reload {
+            keystore_reload.modified = Some(modified);
+        }
+        Ok(())
+    }
+
+    /// Switches the validators whose key rotation activates by `slot` to their next key, once the
+    /// chain expects them to sign with it. Until the rotation is on chain, the validator keeps
+    /// signing with its current key, which the chain still accepts.
+    async fn apply_key_rotations(&mut self, slot: u64) {
+        if self
+            .key_rotations
+            .iter()
+            .all(|rotation| rotation.activation_slot > slot)
+        {
+            return;
+        }
+
+        let (active, pending) = mem::take(&mut self.key_rotations)
+            .into_iter()
+            .partition::<Vec<_>, _>(|rotation| rotation.activation_slot <= slot);
+        self.key_rotations = pending;
+        for rotation in active {
+            let index = rotation.keystore.index;
+            match self.chain_client.validator_public_key(index, slot).await {
+                Ok(Some(public_key)) if public_key == rotation.keystore.public_key => {}
+                Ok(_) => {
+                    if slot == rotation.activation_slot {
+                        warn!(
+                            slot,
+                            "Key rotation of validator {index} isn't on chain, it keeps signing \
+                             with its current key"
+                        );
+                    }
+                    self.key_rotations.push(rotation);
+                    continue;
+                }
+                Err(err) => {
+                    warn!(
+                        slot,
+                        "Failed to check the key rotation of validator {index}: {err:?}"
+                    );
+                    self.key_rotations.push(rotation);
+                    continue;
+                }
+            }
+
+            let Some(current) = self
+                .keystores
+                .iter_mut()
+                .find(|current| current.index == index)
+            else {
+                continue;
+            };
+            if current.public_key != rotation.keystore.public_key {
+                info!(slot, "Validator {index} switched to its rotated key");
+                *current = Arc::new(rotation.keystore);
             }
         }
     }
 
-    /// Determine if one of the keystores is the proposer for the current slot.
-    fn is_proposer(&self, slot: u64) -> Option<&ValidatorKeystore> {
-        let proposer_index = slot % lean_network_spec().num_validators;
+    /// Records how many epochs each key can still sign at from `slot` on, and warns about the keys
+    /// running out of them. A key which can't sign anymore is only reported once, until the
+    /// validator switches to another key.
+    fn check_key_epochs(&mut self, slot: u64) {
+        let keystores = &self.keystores;
+        self.exhausted_keys
+            .retain(|index| keystores.iter().any(|keystore| keystore.index == *index));
+        for keystore in keystores {
+            let remaining_epochs = keystore.private_key.remaining_epochs(slot);
+            set_int_gauge_vec(
+                &KEY_EPOCHS_REMAINING,
+                remaining_epochs as i64,
+                &[&keystore.index.to_string()],
+            );
+            if remaining_epochs == 0 {
+                if self.exhausted_keys.insert(keystore.index) {
+                    error!(
+                        slot,
+                        "Key of validator {} can't sign anymore, its activation interval is over",
+                        keystore.index
+                    );
+                }
+                continue;
+            }
+            self.exhausted_keys.remove(&keystore.index);
+            if remaining_epochs < LOW_KEY_EPOCHS
+                && remaining_epochs % LOW_KEY_EPOCHS_WARNING_INTERVAL == 0
+            {
+                warn!(
+                    slot,
+                    "Key of validator {} can only sign at {remaining_epochs} more epochs",
+                    keystore.index
+                );
+            }
+        }
+    }
 
+    fn is_active(&self, validator_index: u64) -> bool {
         self.keystores
             .iter()
-            .find(|keystore| keystore.index == proposer_index as u64)
+            .any(|keystore| keystore.index == validator_index)
+    }
+
+    async fn handle_keymanager_message(&mut self, message: KeymanagerMessage) {
+        match message {
+            KeymanagerMessage::ListKeystores { sender } => {
+                let keystores = self
+                    .keystores
+                    .iter()
+                    .map(|keystore| KeystoreInfo {
+                        validating_pubkey: keystore.public_key.inner,
+                        validator_index: keystore.index,
+                        readonly: !self.keymanager.as_ref().is_some_and(|keymanager| {
+                            keymanager.imported_indices.contains(&keystore.index)
+                        }),
+                    })
+                    .collect();
+                if sender.send(keystores).is_err() {
+                    warn!("Failed to send keystores, the request was dropped");
+                }
+            }
+            KeymanagerMessage::ImportKeystores { request, sender } => {
+                if sender.send(self.import_keystores(request).await).is_err() {
+                    warn!("Failed to send import result, the request was dropped");
+                }
+            }
+            KeymanagerMessage::DeleteKeystores {
+                public_keys,
+                sender,
+            } => {
+                if sender.send(self.delete_keystores(public_keys)).is_err() {
+                    warn!("Failed to send delete result, the request was dropped");
+                }
+            }
+        }
+    }
+
+    /// Imports the slashing protection records first, so an imported key never signs at a slot it
+    /// already signed at elsewhere.
+    async fn import_keystores(
+        &mut self,
+        request: ImportKeystoresRequest,
+    ) -> anyhow::Result<Vec<KeystoreStatus<ImportStatus>>> {
+        let ImportKeystoresRequest {
+            keystores,
+            passwords,
+            slashing_protection,
+        } = request;
+        ensure!(
+            keystores.len() == passwords.len(),
+            "Got {} keystores but {} passwords",
+            keystores.len(),
+            passwords.len()
+        );
+        if let Some(slashing_protection) = slashing_protection {
+            let records = serde_json::from_str::<SlashingProtectionRecords>(&slashing_protection)
+                .map_err(|err| anyhow!("Failed to parse slashing protection: {err}"))?;
+            self.slashing_protection.import(&records)?;
+        }
+
+        // Decrypting runs the key derivation function and parses large keys, keep it off the
+        // async workers.
+        let decrypted = spawn_blocking(move || {
+            keystores
+                .into_iter()
+                .zip(passwords)
+                .map(|(keystore, password)| {
+                    let decrypted = decrypt_keystore(&keystore, &password);
+                    (keystore, password, decrypted)
+                })
+                .collect::<Vec<_>>()
+        })
+        .await?;
+
+        let mut statuses = vec![];
+        for (keystore_json, password, decrypted) in decrypted {
+            let keystore = match decrypted {
+                Ok(keystore) => keystore,
+                Err(err) => {
+                    statuses.push(KeystoreStatus::with_message(
+                        ImportStatus::Error,
+                        err.to_string(),
+                    ));
+                    continue;
+                }
+            };
+            if self.is_active(keystore.index) {
+                statuses.push(KeystoreStatus::new(ImportStatus::Duplicate));
+                continue;
+            }
+
+            let keymanager = self
+                .keymanager
+                .as_mut()
+                .ok_or_else(|| anyhow!("Keymanager is not enabled"))?;
+            if let Err(err) =
+                keymanager
+                    .imported_keystores
+                    .save(&keystore.public_key, &keystore_json, &password)
+            {
+                statuses.push(KeystoreStatus::with_message(
+                    ImportStatus::Error,
+                    err.to_string(),
+                ));
+                continue;
+            }
+            keymanager.imported_indices.insert(keystore.index);
+            info!(
+                "Validator {} imported, running {} validator(s)",
+                keystore.index,
+                self.keystores.len() + 1
+            );
+            self.keystores.push(Arc::new(keystore));
+            statuses.push(KeystoreStatus::new(ImportStatus::Imported));
+        }
+        Ok(statuses)
+    }
+
+    /// Stops signing with the given keys and exports their slashing protection records. Keys
+    /// loaded from the validator registry can't be deleted.
+    fn delete_keystores(
+        &mut self,
+        public_keys: Vec<FixedBytes<52>>,
+    ) -> anyhow::Result<DeleteKeystoresResponse> {
+        let keymanager = self
+            .keymanager
+            .as_mut()
+            .ok_or_else(|| anyhow!("Keymanager is not enabled"))?;
+
+        let mut statuses = vec![];
+        let mut exported_indices = vec![];
+        for public_key in public_keys {
+            let public_key = PublicKey::new(public_key);
+            let Some(position) = self
+                .keystores
+                .iter()
+                .position(|keystore| keystore.public_key == public_key)
+            else {
+                // The key may have signed before it was deleted, or moved here, hand out its
+                // records anyway.
+                match validator_index(&public_key) {
+                    Ok(index) if self.slashing_protection.last_signed_slot(index).is_some() => {
+                        exported_indices.push(index);
+                        statuses.push(KeystoreStatus::new(DeleteStatus::NotActive));
+                    }
+                    _ => statuses.push(KeystoreStatus::new(DeleteStatus::NotFound)),
+                }
+                continue;
+            };
+
+            let index = self.keystores[position].index;
+            if !keymanager.imported_indices.contains(&index) {
+                statuses.push(KeystoreStatus::with_message(
+                    DeleteStatus::Error,
+                    "Keys loaded from the validator registry are read-only",
+                ));
+                continue;
+            }
+            if let Err(err) = keymanager.imported_keystores.remove(&public_key) {
+                statuses.push(KeystoreStatus::with_message(
+                    DeleteStatus::Error,
+                    err.to_string(),
+                ));
+                continue;
+            }
+            self.keystores.remove(position);
+            keymanager.imported_indices.remove(&index);
+            remove_int_gauge_vec(&KEY_EPOCHS_REMAINING, &[&index.to_string()]);
+            exported_indices.push(index);
+            info!(
+                "Validator {index} deleted, running {} validator(s)",
+                self.keystores.len()
+            );
+            statuses.push(KeystoreStatus::new(DeleteStatus::Deleted));
+        }
+
+        Ok(DeleteKeystoresResponse {
+            data: statuses,
+            slashing_protection: serde_json::to_string(
+                &self.slashing_protection.export(&exported_indices),
+            )?,
+        })
+    }
+}
+
+async fn next_keymanager_message(keymanager: &mut Option<Keymanager>) -> Option<KeymanagerMessage> {
+    match keymanager {
+        Some(keymanager) => keymanager.receiver.recv().await,
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        collections::{HashMap, HashSet},
+        fs::{self, File},
+        path::Path,
+        sync::{Arc, Mutex},
+        time::{Duration, SystemTime},
+    };
+
+    use anyhow::bail;
+    use async_trait::async_trait;
+    use rand::rng;
+    use ream_consensus_lean::{
+        attestation::{AttestationData, SignedAttestation},
+        block::{BlockWithSignatures, SignedBlockWithAttestation},
+    };
+    use ream_keystore::lean_keystore::{
+        RotatedKeystore, ValidatorKeysManifest, ValidatorKeystore, ValidatorKeystoreRaw,
+        ValidatorRegistry,
+    };
+    use ream_metrics::KEY_EPOCHS_REMAINING;
+    use ream_post_quantum_crypto::leansig::{
+        LEAN_SIG_LIFETIME, LEAN_SIG_SCHEME_NAME, private_key::PrivateKey, public_key::PublicKey,
+    };
+    use tempfile::TempDir;
+
+    use super::ValidatorService;
+    use crate::{
+        chain_client::LeanChainClient,
+        registry::{MANIFEST_FILE, keys_dir, load_validator_registry},
+        slashing_protection::SlashingProtection,
+    };
+
+    /// A chain which only knows the keys of its validators.
+    #[derive(Clone, Default)]
+    struct MockChainClient {
+        public_keys: Arc<Mutex<HashMap<u64, PublicKey>>>,
+    }
+
+    #[async_trait]
+    impl LeanChainClient for MockChainClient {
+        fn name(&self) -> String {
+            "mock".to_string()
+        }
+
+        async fn proposer_index(&self, _slot: u64) -> anyhow::Result<u64> {
+            bail!("Not used by the tests")
+        }
+
+        async fn validator_public_key(
+            &self,
+            validator_index: u64,
+            _slot: u64,
+        ) -> anyhow::Result<Option<PublicKey>> {
+            Ok(self
+                .public_keys
+                .lock()
+                .expect("Public keys lock is poisoned")
+                .get(&validator_index)
+                .copied())
+        }
+
+        async fn produce_block(&self, _slot: u64) -> anyhow::Result<BlockWithSignatures> {
+            bail!("Not used by the tests")
+        }
+
+        async fn attestation_data(&self, _slot: u64) -> anyhow::Result<AttestationData> {
+            bail!("Not used by the tests")
+        }
+
+        async fn submit_block(
+            &self,
+            _signed_block_with_attestation: SignedBlockWithAttestation,
+        ) -> anyhow::Result<()> {
+            bail!("Not used by the tests")
+        }
+
+        async fn submit_attestation(
+            &self,
+            _signed_attestation: SignedAttestation,
+        ) -> anyhow::Result<()> {
+            bail!("Not used by the tests")
+        }
+    }
+
+    fn keystore(index: u64) -> ValidatorKeystore {
+        let (public_key, private_key) = PrivateKey::generate_key_pair(&mut rng(), 0, 10);
+        ValidatorKeystore {
+            index,
+            public_key,
+            private_key,
+        }
+    }
+
+    /// Assigns `validator_indices` to the node `ream_0` in the registry at `registry_path`, marking
+    /// it as changed even within the resolution of file times.
+    fn write_registry(registry_path: &Path, validator_indices: Vec<u64>) {
+        let registry = ValidatorRegistry {
+            nodes: HashMap::from([("ream_0".to_string(), validator_indices)]),
+        };
+        fs::write(registry_path, serde_yaml::to_string(&registry).unwrap()).unwrap();
+        File::options()
+            .write(true)
+            .open(registry_path)
+            .unwrap()
+            .set_modified(SystemTime::now() + Duration::from_secs(60))
+            .unwrap();
+    }
+
+    /// Writes the keys of `count` validators to the keys manifest next to `registry_path`.
+    fn write_keys(registry_path: &Path, count: u64) {
+        let keys_dir = keys_dir(registry_path);
+        fs::create_dir_all(&keys_dir).unwrap();
+        let mut validators = vec![];
+        for index in 0..count {
+            let (public_key, private_key) = PrivateKey::generate_key_pair(&mut rng(), 0, 8);
+            let privkey_file = format!("validator_{index}_sk.json");
+            fs::write(
+                keys_dir.join(&privkey_file),
+                serde_json::to_string(&private_key.inner).unwrap(),
+            )
+            .unwrap();
+            validators.push(ValidatorKeystoreRaw {
+                index,
+                public_key,
+                privkey_file,
+                rotations: vec![],
+            });
+        }
+        let manifest = ValidatorKeysManifest {
+            key_scheme: LEAN_SIG_SCHEME_NAME.to_string(),
+            hash_function: "Poseidon2".to_string(),
+            encoding: "TargetSum".to_string(),
+            lifetime: LEAN_SIG_LIFETIME,
+            log_num_active_epochs: 3,
+            num_active_epochs: 8,
+            num_validators: count,
+            validators,
+        };
+        fs::write(
+            keys_dir.join(MANIFEST_FILE),
+            serde_yaml::to_string(&manifest).unwrap(),
+        )
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_reload_keystores() {
+        let dir = TempDir::new().unwrap();
+        let registry_path = dir.path().join("validators.yaml");
+        write_keys(&registry_path, 2);
+        write_registry(&registry_path, vec![0]);
+
+        let mut slashing_protection =
+            SlashingProtection::load(dir.path().join("slashing_protection.json"), 0).unwrap();
+        slashing_protection.check_and_record(1, 7).unwrap();
+        let mut service = ValidatorService::new(
+            load_validator_registry(&registry_path, "ream_0").unwrap(),
+            Box::new(MockChainClient::default()),
+            slashing_protection,
+        )
+        .await
+        .with_keystore_reload(registry_path.clone(), "ream_0".to_string());
+        let indices = |service: &ValidatorService| {
+            service
+                .keystores
+                .iter()
+                .map(|keystore| keystore.index)
+                .collect::<Vec<_>>()
+        };
+
+        // Nothing changed since the keystores were loaded.
+        service.reload_keystores(5).await.unwrap();
+        assert_eq!(indices(&service), vec![0]);
+
+        // Validators moved to the registry of the node are picked up, their slashing protection
+        // still applies, and the ones moved away are dropped.
+        write_registry(&registry_path, vec![1]);
+        service.reload_keystores(5).await.unwrap();
+        assert_eq!(indices(&service), vec![1]);
+        assert!(service.slashing_protection.check_and_record(1, 7).is_err());
+
+        write_registry(&registry_path, vec![0, 1]);
+        service.reload_keystores(6).await.unwrap();
+        assert_eq!(indices(&service), vec![1, 0]);
+
+        // A registry which can't be read leaves the validators as they are.
+        fs::write(&registry_path, "nodes: [").unwrap();
+        assert!(service.reload_keystores(7).await.is_err());
+        assert_eq!(indices(&service), vec![1, 0]);
+    }
+
+    #[tokio::test]
+    async fn test_apply_key_rotations() {
+        let dir = TempDir::new().unwrap();
+        let current = keystore(0);
+        let rotated = keystore(0);
+        let (current_public_key, rotated_public_key) = (current.public_key, rotated.public_key);
+
+        let chain_client = MockChainClient::default();
+        chain_client
+            .public_keys
+            .lock()
+            .unwrap()
+            .insert(0, current_public_key);
+        let mut service = ValidatorService::new(
+            vec![current],
+            Box::new(chain_client.clone()),
+            SlashingProtection::load(dir.path().join("slashing_protection.json"), 0).unwrap(),
+        )
+        .await
+        .with_key_rotations(vec![RotatedKeystore {
+            activation_slot: 5,
+            keystore: rotated,
+        }]);
+
+        service.apply_key_rotations(4).await;
+        assert_eq!(service.keystores[0].public_key, current_public_key);
+
+        // The chain doesn't know the rotation, so the validator keeps its key.
+        service.apply_key_rotations(5).await;
+        assert_eq!(service.keystores[0].public_key, current_public_key);
+        assert_eq!(service.key_rotations.len(), 1);
+
+        chain_client
+            .public_keys
+            .lock()
+            .unwrap()
+            .insert(0, rotated_public_key);
+        service.apply_key_rotations(6).await;
+        assert_eq!(service.keystores[0].public_key, rotated_public_key);
+        assert!(service.key_rotations.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_check_key_epochs() {
+        let dir = TempDir::new().unwrap();
+        let mut service = ValidatorService::new(
+            vec![keystore(0), keystore(1)],
+            Box::new(MockChainClient::default()),
+            SlashingProtection::load(dir.path().join("slashing_protection.json"), 0).unwrap(),
+        )
+        .await;
+        let remaining = |index: u64| {
+            KEY_EPOCHS_REMAINING
+                .with_label_values(&[&index.to_string()])
+                .get()
+        };
+
+        service.check_key_epochs(0);
+        assert!(service.exhausted_keys.is_empty());
+        assert!(remaining(0) > 0);
+
+        // The keys are reported once they run out, and stay reported on the next slots.
+        service.check_key_epochs(10_000);
+        assert_eq!(service.exhausted_keys, HashSet::from([0, 1]));
+        assert_eq!(remaining(0), 0);
+        service.check_key_epochs(10_001);
+        assert_eq!(service.exhausted_keys, HashSet::from([0, 1]));
+
+        // Dropped validators are forgotten, and a key which can sign again, as after a rotation,
+        // is reported again once it runs out.
+        service.keystores.truncate(1);
+        service.check_key_epochs(10_002);
+        assert_eq!(service.exhausted_keys, HashSet::from([0]));
+        service.check_key_epochs(0);
+        assert!(service.exhausted_keys.is_empty());
     }
 }
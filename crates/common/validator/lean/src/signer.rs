@@ -0,0 +1,138 @@
+use std::{sync::Arc, time::Duration};
+
+use alloy_primitives::B256;
+use anyhow::anyhow;
+use ream_keystore::lean_keystore::ValidatorKeystore;
+use ream_post_quantum_crypto::leansig::signature::Signature;
+use tokio::{
+    sync::mpsc,
+    task::spawn_blocking,
+    time::{Instant, timeout_at},
+};
+use tracing::warn;
+
+/// Signatures being made on blocking threads, handed out in the order they finish.
+///
+/// XMSS signing takes long enough to stall the async workers, and validators would otherwise
+/// sign one after the other, so every key signs on its own blocking thread. Signatures which
+/// aren't done by the deadline are given up on, so one slow key doesn't hold back the messages of
+/// the others.
+pub struct Signings<T> {
+    receiver: mpsc::UnboundedReceiver<(T, anyhow::Result<Signature>)>,
+    deadline: Instant,
+    pending: usize,
+}
+
+impl<T: Send + 'static> Signings<T> {
+    /// Starts signing the message of every job at `epoch` with the key of its keystore. The jobs'
+    /// `T` is handed back with each signature.
+    pub fn start(
+        jobs: impl IntoIterator<Item = (Arc<ValidatorKeystore>, B256, T)>,
+        epoch: u32,
+        deadline: Duration,
+    ) -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let mut pending = 0;
+        for (keystore, message, job) in jobs {
+            let sender = sender.clone();
+            spawn_blocking(move || {
+                let signature = keystore
+                    .private_key
+                    .sign(&message, epoch)
+                    .map_err(|err| anyhow!("Validator {} failed to sign: {err:?}", keystore.index));
+                // The receiver is gone once the deadline passed, the signature is dropped then.
+                let _ = sender.send((job, signature));
+            });
+            pending += 1;
+        }
+
+        Self {
+            receiver,
+            deadline: Instant::now() + deadline,
+            pending,
+        }
+    }
+
+    /// Returns the next finished signature, or `None` once all of them are handed out or the
+    /// deadline passed.
+    pub async fn next(&mut self) -> Option<(T, anyhow::Result<Signature>)> {
+        if self.pending == 0 {
+            return None;
+        }
+        match timeout_at(self.deadline, self.receiver.recv()).await {
+            Ok(Some(signed)) => {
+                self.pending -= 1;
+                Some(signed)
+            }
+            // A signing thread panicked, which drops its sender without sending.
+            Ok(None) => {
+                warn!("{} signature(s) failed without a result", self.pending);
+                self.pending = 0;
+                None
+            }
+            Err(_) => {
+                warn!("{} signature(s) missed the signing deadline", self.pending);
+                self.pending = 0;
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use alloy_primitives::B256;
+    use rand::rng;
+    use ream_keystore::lean_keystore::ValidatorKeystore;
+    use ream_post_quantum_crypto::leansig::{private_key::PrivateKey, signature::Signature};
+    use tokio::{sync::mpsc, time::Instant};
+
+    use super::Signings;
+
+    fn keystore(index: u64) -> Arc<ValidatorKeystore> {
+        let (public_key, private_key) = PrivateKey::generate_key_pair(&mut rng(), 0, 10);
+        Arc::new(ValidatorKeystore {
+            index,
+            public_key,
+            private_key,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_signings() {
+        let keystores = [keystore(0), keystore(1)];
+        let message = B256::repeat_byte(1);
+
+        let mut signings = Signings::start(
+            keystores
+                .iter()
+                .map(|keystore| (keystore.clone(), message, keystore.index)),
+            3,
+            Duration::from_secs(60),
+        );
+        let mut signed = vec![];
+        while let Some((index, signature)) = signings.next().await {
+            let signature = signature.unwrap();
+            assert!(
+                signature
+                    .verify(&keystores[index as usize].public_key, 3, &message)
+                    .unwrap()
+            );
+            signed.push(index);
+        }
+        signed.sort();
+        assert_eq!(signed, vec![0, 1]);
+
+        // Nothing is handed out once the deadline passed, however long a job keeps signing.
+        let (_sender, receiver) = mpsc::unbounded_channel::<(u64, anyhow::Result<Signature>)>();
+        let mut signings = Signings {
+            receiver,
+            deadline: Instant::now() - Duration::from_secs(1),
+            pending: 1,
+        };
+        assert!(signings.next().await.is_none());
+        assert_eq!(signings.pending, 0);
+    }
+}
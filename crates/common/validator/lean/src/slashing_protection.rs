@@ -0,0 +1,200 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File},
+    io::{ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, ensure};
+use serde::{Deserialize, Serialize};
+
+/// Records the last slot each validator signed at, and refuses to sign at or before it again.
+///
+/// A lean key is a one-time signature scheme which signs with the slot as its epoch, so two
+/// messages signed for the same slot would leak the key besides being slashable. The records are
+/// written to disk before the signature is made, so they survive restarts and keys being moved
+/// to another validator client.
+///
+/// The records belong to the chain of their genesis, slots of another chain say nothing about
+/// what was signed on this one.
+#[derive(Debug)]
+pub struct SlashingProtection {
+    path: PathBuf,
+    records: SlashingProtectionRecords,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SlashingProtectionRecords {
+    pub genesis_time: u64,
+    pub last_signed_slots: BTreeMap<u64, u64>,
+}
+
+impl SlashingProtection {
+    /// Loads the records at `path` of the chain starting at `genesis_time`, starting without any
+    /// if the file doesn't exist yet. Fails if the records are of another chain.
+    pub fn load(path: PathBuf, genesis_time: u64) -> anyhow::Result<Self> {
+        let records = match fs::read_to_string(&path) {
+            Ok(records) => serde_json::from_str(&records).map_err(|err| {
+                anyhow!(
+                    "Failed to parse slashing protection {}: {err}",
+                    path.display()
+                )
+            })?,
+            Err(err) if err.kind() == ErrorKind::NotFound => SlashingProtectionRecords {
+                genesis_time,
+                ..Default::default()
+            },
+            Err(err) => {
+                return Err(anyhow!(
+                    "Failed to read slashing protection {}: {err}",
+                    path.display()
+                ));
+            }
+        };
+        ensure!(
+            records.genesis_time == genesis_time,
+            "Slashing protection {} is of the chain with genesis time {}, not {genesis_time}, \
+             restart with --purge-db to start over",
+            path.display(),
+            records.genesis_time
+        );
+        Ok(Self { path, records })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn records(&self) -> &SlashingProtectionRecords {
+        &self.records
+    }
+
+    pub fn last_signed_slot(&self, validator_index: u64) -> Option<u64> {
+        self.records
+            .last_signed_slots
+            .get(&validator_index)
+            .copied()
+    }
+
+    /// Records that `validator_index` signs at `slot`, failing if it already signed at this slot
+    /// or a later one.
+    pub fn check_and_record(&mut self, validator_index: u64, slot: u64) -> anyhow::Result<()> {
+        if let Some(last_signed_slot) = self.last_signed_slot(validator_index) {
+            ensure!(
+                slot > last_signed_slot,
+                "Validator {validator_index} already signed at slot {last_signed_slot} >= {slot}"
+            );
+        }
+
+        let mut records = self.records.clone();
+        records.last_signed_slots.insert(validator_index, slot);
+        write_records(&self.path, &records)?;
+        self.records = records;
+        Ok(())
+    }
+
+    /// Returns the records of `validator_indices`, to move them along with their keys.
+    pub fn export(&self, validator_indices: &[u64]) -> SlashingProtectionRecords {
+        SlashingProtectionRecords {
+            genesis_time: self.records.genesis_time,
+            last_signed_slots: self
+                .records
+                .last_signed_slots
+                .iter()
+                .filter(|(validator_index, _)| validator_indices.contains(validator_index))
+                .map(|(validator_index, slot)| (*validator_index, *slot))
+                .collect(),
+        }
+    }
+
+    /// Merges records exported by another validator client, keeping the later slot of each
+    /// validator. Fails if the records are of another chain.
+    pub fn import(&mut self, imported: &SlashingProtectionRecords) -> anyhow::Result<()> {
+        ensure!(
+            imported.genesis_time == self.records.genesis_time,
+            "Slashing protection is of the chain with genesis time {}, not {}",
+            imported.genesis_time,
+            self.records.genesis_time
+        );
+        let mut records = self.records.clone();
+        for (validator_index, slot) in &imported.last_signed_slots {
+            let last_signed_slot = records
+                .last_signed_slots
+                .entry(*validator_index)
+                .or_insert(*slot);
+            *last_signed_slot = (*last_signed_slot).max(*slot);
+        }
+        write_records(&self.path, &records)?;
+        self.records = records;
+        Ok(())
+    }
+}
+
+/// Writes to a temporary file first, so a crash never leaves a truncated file behind. The file
+/// and the rename are synced before returning, as the signature is made right after.
+fn write_records(path: &Path, records: &SlashingProtectionRecords) -> anyhow::Result<()> {
+    let write = || -> std::io::Result<()> {
+        let temp_path = path.with_extension("tmp");
+        let mut file = File::create(&temp_path)?;
+        file.write_all(&serde_json::to_vec(records)?)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)?;
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            File::open(dir)?.sync_all()?;
+        }
+        Ok(())
+    };
+    write().map_err(|err| anyhow!("Failed to write slashing protection: {err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn test_refuses_to_sign_twice() {
+        let tmp_dir = TempDir::new().unwrap();
+        let path = tmp_dir.path().join("slashing_protection.json");
+
+        let mut slashing_protection = SlashingProtection::load(path.clone(), 0).unwrap();
+        slashing_protection.check_and_record(0, 5).unwrap();
+        assert!(slashing_protection.check_and_record(0, 5).is_err());
+        assert!(slashing_protection.check_and_record(0, 4).is_err());
+        slashing_protection.check_and_record(1, 5).unwrap();
+
+        // The records survive a restart.
+        let mut slashing_protection = SlashingProtection::load(path.clone(), 0).unwrap();
+        assert_eq!(slashing_protection.last_signed_slot(0), Some(5));
+        assert!(slashing_protection.check_and_record(1, 5).is_err());
+        slashing_protection.check_and_record(0, 6).unwrap();
+        assert!(!path.with_extension("tmp").exists());
+
+        // The records of another chain aren't used.
+        assert!(SlashingProtection::load(path, 1).is_err());
+    }
+
+    #[test]
+    fn test_import_keeps_later_slot() {
+        let tmp_dir = TempDir::new().unwrap();
+        let mut slashing_protection =
+            SlashingProtection::load(tmp_dir.path().join("slashing_protection.json"), 0).unwrap();
+        slashing_protection.check_and_record(0, 5).unwrap();
+        slashing_protection.check_and_record(1, 5).unwrap();
+
+        let exported = slashing_protection.export(&[1]);
+        assert_eq!(exported.last_signed_slots, BTreeMap::from([(1, 5)]));
+
+        let mut imported = SlashingProtectionRecords {
+            genesis_time: 1,
+            last_signed_slots: BTreeMap::from([(0, 3), (1, 7), (2, 1)]),
+        };
+        assert!(slashing_protection.import(&imported).is_err());
+        imported.genesis_time = 0;
+        slashing_protection.import(&imported).unwrap();
+        assert_eq!(slashing_protection.last_signed_slot(0), Some(5));
+        assert_eq!(slashing_protection.last_signed_slot(1), Some(7));
+        assert_eq!(slashing_protection.last_signed_slot(2), Some(1));
+    }
+}
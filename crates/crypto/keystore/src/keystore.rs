@@ -1,14 +1,15 @@
 use std::fs;
 
 use alloy_primitives::B256;
-use anyhow::{Result, anyhow, ensure};
+use anyhow::{Result, anyhow, bail, ensure};
 use rand;
 use ream_bls::{PrivateKey, PublicKey};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use uuid::Uuid;
+use zeroize::Zeroizing;
 
-use crate::{decrypt::aes128_ctr, hex_serde, pbkdf2::pbkdf2, scrypt::scrypt};
+use crate::{decrypt::aes128_ctr, hex_serde, pbkdf2::pbkdf2, scrypt::scrypt, secret::SecretBytes};
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
 pub struct EncryptedKeystore<P = PublicKey, C = CryptoV4> {
@@ -52,18 +53,31 @@ impl EncryptedKeystore {
     }
 
     pub fn decrypt(&self, password: &[u8]) -> anyhow::Result<Keystore> {
-        let derived_key = self.crypto.kdf.params.derive_key(password)?;
+        let secret = self.decrypt_secret(password)?;
+        let private_key = PrivateKey {
+            inner: B256::try_from(secret.expose_secret())
+                .map_err(|err| anyhow!("Decrypted secret is not a BLS private key: {err:?}"))?,
+        };
+        Ok(Keystore {
+            public_key: self.public_key.clone(),
+            private_key,
+        })
+    }
+}
+
+impl<P> EncryptedKeystore<P, CryptoV4> {
+    /// Checks `password` against the checksum and returns the decrypted cipher message.
+    pub fn decrypt_secret(&self, password: &[u8]) -> anyhow::Result<SecretBytes> {
+        let derived_key = Zeroizing::new(self.crypto.kdf.params.derive_key(password)?);
         let derived_key_slice = &derived_key[16..32];
-        let pre_image = [derived_key_slice, &self.crypto.cipher.message].concat();
+        let pre_image = Zeroizing::new([derived_key_slice, &self.crypto.cipher.message].concat());
         let checksum = Sha256::digest(&pre_image);
         ensure!(
             checksum.as_slice() == self.crypto.checksum.message.as_slice(),
             "Password provided is invalid!"
         );
 
-        let mut private_key = PrivateKey {
-            inner: B256::from_slice(self.crypto.cipher.message.as_slice()),
-        };
+        let mut secret = Zeroizing::new(self.crypto.cipher.message.clone());
         match &self.crypto.cipher.params {
             CipherParams::Aes128Ctr { iv } => {
                 let key_param: [u8; 16] = derived_key[0..16].try_into().map_err(|err| {
@@ -72,14 +86,11 @@ impl EncryptedKeystore {
                 let iv_param: &[u8; 16] = iv.as_slice().try_into().map_err(|err| {
                     anyhow!("Failed to convert derived key into 16 byte array: {err:?}")
                 })?;
-                aes128_ctr(private_key.inner.as_mut_slice(), key_param, iv_param);
+                aes128_ctr(&mut secret, key_param, iv_param);
             }
-            CipherParams::Aes256Gcm { .. } => todo!(),
+            CipherParams::Aes256Gcm { .. } => bail!("The aes-256-gcm cipher is not supported yet"),
         };
-        Ok(Keystore {
-            public_key: self.public_key.clone(),
-            private_key,
-        })
+        Ok(SecretBytes::from(secret))
     }
 }
 
@@ -194,7 +205,7 @@ impl KdfParams {
                 dklen,
                 salt,
             } => scrypt(password, salt, *n, *p, *r, *dklen),
-            KdfParams::Argon2Id { .. } => todo!(),
+            KdfParams::Argon2Id { .. } => bail!("The argon2id KDF is not supported yet"),
         }
     }
 }
@@ -475,6 +486,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn decrypt_unsupported_functions() {
+        let password = hex!("7465737470617373776f7264f09f9491");
+        let mut keystore: EncryptedKeystore =
+            EncryptedKeystore::load_from_file("./assets/Pbkdf2TestKeystore.json").unwrap();
+        keystore.crypto.cipher.params = CipherParams::Aes256Gcm {
+            iv: vec![0; 12],
+            tag: vec![0; 16],
+        };
+        assert!(keystore.decrypt(&password).is_err());
+
+        keystore.crypto.kdf.params = KdfParams::Argon2Id {
+            m: 65536,
+            t: 3,
+            p: 4,
+            salt: vec![0; 32],
+        };
+        assert!(keystore.validate_password(&password).is_err());
+        assert!(keystore.decrypt(&password).is_err());
+    }
+
     #[test]
     fn decrypt_scrypt() {
         let keystore =
@@ -1,8 +1,14 @@
 use std::collections::HashMap;
 
-use ream_post_quantum_crypto::leansig::{private_key::PrivateKey, public_key::PublicKey};
+use anyhow::anyhow;
+use ream_post_quantum_crypto::leansig::{
+    private_key::{LeanSigPrivateKey, PrivateKey},
+    public_key::PublicKey,
+};
 use serde::{Deserialize, Serialize};
 
+use crate::keystore::{CryptoV4, EncryptedKeystore};
+
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 #[serde(rename_all = "snake_case")]
 pub struct ValidatorKeysManifest {
@@ -23,6 +29,19 @@ pub struct ValidatorKeystoreRaw {
     #[serde(rename = "pubkey_hex")]
     pub public_key: PublicKey,
     pub privkey_file: String,
+    /// The keys the validator rotates to, by activation slot.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rotations: Vec<KeyRotationRaw>,
+}
+
+/// A key a validator signs with from `activation_slot` on, once its key rotation is on chain.
+#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub struct KeyRotationRaw {
+    pub activation_slot: u64,
+    #[serde(rename = "pubkey_hex")]
+    pub public_key: PublicKey,
+    pub privkey_file: String,
 }
 
 #[derive(Debug, PartialEq)]
@@ -32,6 +51,26 @@ pub struct ValidatorKeystore {
     pub private_key: PrivateKey,
 }
 
+/// The keystore a validator switches to at `activation_slot`.
+#[derive(Debug, PartialEq)]
+pub struct RotatedKeystore {
+    pub activation_slot: u64,
+    pub keystore: ValidatorKeystore,
+}
+
+/// An EIP-2335 keystore whose cipher message is a lean private key, in the JSON format of the
+/// hash-sig key files.
+pub type LeanEncryptedKeystore = EncryptedKeystore<PublicKey, CryptoV4>;
+
+impl LeanEncryptedKeystore {
+    pub fn decrypt(&self, password: &[u8]) -> anyhow::Result<PrivateKey> {
+        let secret = self.decrypt_secret(password)?;
+        let private_key = serde_json::from_slice::<LeanSigPrivateKey>(secret.expose_secret())
+            .map_err(|err| anyhow!("Decrypted secret is not a lean private key: {err}"))?;
+        Ok(PrivateKey::new(private_key))
+    }
+}
+
 /// YAML structure for node-based validator mapping
 #[derive(Debug, Deserialize, Serialize, PartialEq)]
 pub struct ValidatorRegistry {
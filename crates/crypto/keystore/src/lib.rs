@@ -6,3 +6,4 @@ pub mod lean_keystore;
 pub mod pbkdf2;
 pub mod salsa;
 pub mod scrypt;
+pub mod secret;
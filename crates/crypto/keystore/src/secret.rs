@@ -0,0 +1,49 @@
+use std::fmt;
+
+use zeroize::Zeroizing;
+
+/// Decrypted key material, wiped from memory when dropped and never printed.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SecretBytes(Zeroizing<Vec<u8>>);
+
+impl SecretBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self(Zeroizing::new(bytes))
+    }
+
+    /// Returns the secret itself. Callers shouldn't copy it anywhere that outlives the call.
+    pub fn expose_secret(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for SecretBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self::new(bytes)
+    }
+}
+
+impl From<Zeroizing<Vec<u8>>> for SecretBytes {
+    fn from(bytes: Zeroizing<Vec<u8>>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "SecretBytes({} bytes)", self.0.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SecretBytes;
+
+    #[test]
+    fn test_debug_hides_secret() {
+        let secret = SecretBytes::new(vec![0xab; 4]);
+
+        assert_eq!(format!("{secret:?}"), "SecretBytes(4 bytes)");
+        assert_eq!(secret.expose_secret(), &[0xab; 4]);
+    }
+}
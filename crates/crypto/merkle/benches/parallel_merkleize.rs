@@ -0,0 +1,26 @@
+use alloy_primitives::B256;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ream_merkle::parallel::list_root_parallel;
+use ssz_types::{VariableList, typenum::U262144};
+use tree_hash::TreeHash;
+
+fn bench_list_root(c: &mut Criterion) {
+    let mut group = c.benchmark_group("list_root");
+    for count in [4096, 65536, 262144] {
+        let leaves: Vec<B256> = (0..count)
+            .map(|i: u64| B256::left_padding_from(&i.to_be_bytes()))
+            .collect();
+        let list = VariableList::<B256, U262144>::new(leaves.clone()).expect("within limit");
+
+        group.bench_with_input(BenchmarkId::new("serial", count), &list, |b, list| {
+            b.iter(|| list.tree_hash_root())
+        });
+        group.bench_with_input(BenchmarkId::new("parallel", count), &leaves, |b, leaves| {
+            b.iter(|| list_root_parallel(leaves, 262144).expect("within limit"))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_list_root);
+criterion_main!(benches);
@@ -1,6 +1,8 @@
 //! https://ethereum.github.io/consensus-specs/ssz/merkle-proofs
 
 pub mod multiproof;
+#[cfg(feature = "parallel")]
+pub mod parallel;
 
 use alloy_primitives::B256;
 use anyhow::ensure;
@@ -0,0 +1,180 @@
+//! Parallel Merkleization of large SSZ lists.
+//!
+//! Lists such as `historical_block_hashes` can hold hundreds of thousands of leaves, which makes
+//! single-threaded tree hashing the bottleneck when computing state roots. The leaves are split
+//! into fixed-size subtrees that are hashed on the rayon thread pool, after which the remaining
+//! upper levels are hashed serially. The produced roots are identical to the serial [tree_hash]
+//! implementation.
+//!
+//! [tree_hash]: https://docs.rs/tree_hash
+
+use alloy_primitives::B256;
+use anyhow::ensure;
+use rayon::prelude::*;
+
+use crate::hash::hash_concat;
+
+/// Lists with fewer leaves than this are hashed serially, as the overhead of spawning rayon tasks
+/// outweighs the gain.
+pub const PARALLEL_THRESHOLD: usize = 1 << 12;
+
+/// Depth of the subtrees hashed by a single rayon task (1024 leaves).
+const SUBTREE_DEPTH: u32 = 10;
+
+const BYTES_PER_CHUNK: usize = 32;
+
+/// Returns the roots of empty subtrees, where `zero_hashes[i]` is the root of a subtree of depth
+/// `i` with all leaves set to zero.
+fn zero_hashes(depth: u32) -> Vec<B256> {
+    let mut zero_hashes = vec![B256::ZERO];
+    for i in 0..depth as usize {
+        zero_hashes.push(hash_concat(
+            zero_hashes[i].as_slice(),
+            zero_hashes[i].as_slice(),
+        ));
+    }
+    zero_hashes
+}
+
+/// Hashes `nodes` at `start_level` up to `end_level`, padding odd layers with zero hashes.
+fn hash_levels(mut nodes: Vec<B256>, start_level: u32, end_level: u32, zero: &[B256]) -> B256 {
+    for level in start_level..end_level {
+        if nodes.len() % 2 == 1 {
+            nodes.push(zero[level as usize]);
+        }
+        nodes = nodes
+            .chunks_exact(2)
+            .map(|pair| hash_concat(pair[0].as_slice(), pair[1].as_slice()))
+            .collect();
+    }
+    nodes.first().copied().unwrap_or(zero[end_level as usize])
+}
+
+/// Computes the root of a binary Merkle tree over `leaves`, padded with zero leaves up to
+/// `limit` rounded up to the next power of two.
+pub fn merkleize_parallel(leaves: &[B256], limit: usize) -> anyhow::Result<B256> {
+    ensure!(
+        leaves.len() <= limit.max(1),
+        "Number of leaves {} is greater than the limit {limit}",
+        leaves.len()
+    );
+
+    let depth = limit.max(1).next_power_of_two().trailing_zeros();
+    let zero = zero_hashes(depth);
+    if leaves.is_empty() {
+        return Ok(zero[depth as usize]);
+    }
+
+    let subtree_depth = SUBTREE_DEPTH.min(depth);
+    let subtree_size = 1 << subtree_depth;
+    let subtree_root = |chunk: &[B256]| hash_levels(chunk.to_vec(), 0, subtree_depth, &zero);
+
+    let subtree_roots: Vec<B256> = if leaves.len() >= PARALLEL_THRESHOLD {
+        leaves.par_chunks(subtree_size).map(subtree_root).collect()
+    } else {
+        leaves.chunks(subtree_size).map(subtree_root).collect()
+    };
+
+    Ok(hash_levels(subtree_roots, subtree_depth, depth, &zero))
+}
+
+/// Mixes the length of a list into its Merkle root, as done for SSZ lists and bitlists.
+pub fn mix_in_length(root: B256, length: usize) -> B256 {
+    let mut length_bytes = [0u8; BYTES_PER_CHUNK];
+    length_bytes[..8].copy_from_slice(&(length as u64).to_le_bytes());
+    hash_concat(root.as_slice(), &length_bytes)
+}
+
+/// Computes the SSZ tree hash root of a list of 32-byte elements (e.g. `List[Bytes32, N]`).
+pub fn list_root_parallel(leaves: &[B256], max_len: usize) -> anyhow::Result<B256> {
+    Ok(mix_in_length(
+        merkleize_parallel(leaves, max_len)?,
+        leaves.len(),
+    ))
+}
+
+/// Computes the SSZ tree hash root of a bitlist from its raw bytes (i.e. without the length
+/// delimiting bit), its length in bits and its maximum length in bits.
+pub fn bitlist_root_parallel(
+    bytes: &[u8],
+    num_bits: usize,
+    max_bits: usize,
+) -> anyhow::Result<B256> {
+    let chunks: Vec<B256> = bytes
+        .chunks(BYTES_PER_CHUNK)
+        .map(|chunk| {
+            let mut leaf = B256::ZERO;
+            leaf[..chunk.len()].copy_from_slice(chunk);
+            leaf
+        })
+        .collect();
+
+    Ok(mix_in_length(
+        merkleize_parallel(&chunks, max_bits.div_ceil(256))?,
+        num_bits,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ssz_types::{
+        BitList, VariableList,
+        typenum::{U1024, U262144, U1073741824},
+    };
+    use tree_hash::TreeHash;
+
+    use super::{bitlist_root_parallel, list_root_parallel};
+
+    fn leaves(count: usize) -> Vec<B256> {
+        (0..count)
+            .map(|i| B256::left_padding_from(&(i as u64 + 1).to_be_bytes()))
+            .collect()
+    }
+
+    #[test]
+    fn test_list_root_matches_serial() {
+        for count in [0, 1, 2, 3, 1000, 1024, 1025, 5000, 70_000] {
+            let leaves = leaves(count);
+            let list = VariableList::<B256, U262144>::new(leaves.clone()).unwrap();
+
+            assert_eq!(
+                list_root_parallel(&leaves, 262144).unwrap(),
+                list.tree_hash_root(),
+                "root mismatch for {count} leaves"
+            );
+        }
+    }
+
+    #[test]
+    fn test_small_list_root_matches_serial() {
+        let leaves = leaves(700);
+        let list = VariableList::<B256, U1024>::new(leaves.clone()).unwrap();
+
+        assert_eq!(
+            list_root_parallel(&leaves, 1024).unwrap(),
+            list.tree_hash_root()
+        );
+    }
+
+    #[test]
+    fn test_list_root_rejects_too_many_leaves() {
+        assert!(list_root_parallel(&leaves(5), 4).is_err());
+    }
+
+    #[test]
+    fn test_bitlist_root_matches_serial() {
+        for num_bits in [0, 1, 255, 256, 257, 10_000, 2_000_000] {
+            let mut bitlist = BitList::<U1073741824>::with_capacity(num_bits).unwrap();
+            for i in (0..num_bits).filter(|i| i % 3 == 0) {
+                bitlist.set(i, true).unwrap();
+            }
+
+            assert_eq!(
+                bitlist_root_parallel(bitlist.as_slice(), bitlist.len(), 1073741824).unwrap(),
+                bitlist.tree_hash_root(),
+                "root mismatch for {num_bits} bits"
+            );
+        }
+    }
+}
@@ -0,0 +1,44 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use leansig::signature::SignatureScheme;
+use rand::rng;
+use ream_post_quantum_crypto::leansig::{LeanSigScheme, private_key::PrivateKey};
+
+/// Compares verifying the signatures of a block, which are checked against the keys of the same
+/// validators slot after slot, with and without the decoded public keys cached.
+fn bench_verify(c: &mut Criterion) {
+    let (public_key, private_key) = PrivateKey::generate_key_pair(&mut rng(), 0, 16);
+    let message = [7; 32];
+    let signature = private_key.sign(&message, 3).expect("epoch is active");
+
+    let mut group = c.benchmark_group("verify");
+    group.bench_function("cached_public_key", |b| {
+        b.iter(|| {
+            signature
+                .verify(&public_key, 3, &message)
+                .expect("key decodes")
+        })
+    });
+    group.bench_function("decoded_public_key", |b| {
+        b.iter(|| {
+            <LeanSigScheme as SignatureScheme>::verify(
+                &public_key.decode().expect("key decodes"),
+                3,
+                &message,
+                &signature.as_lean_sig().expect("signature decodes"),
+            )
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("public_key");
+    group.bench_function("cached", |b| {
+        b.iter(|| public_key.as_lean_sig().expect("key decodes"))
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| public_key.decode().expect("key decodes"))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_verify);
+criterion_main!(benches);
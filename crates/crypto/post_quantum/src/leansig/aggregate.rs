@@ -0,0 +1,130 @@
+use anyhow::ensure;
+use leansig::MESSAGE_LENGTH;
+use serde::{Deserialize, Serialize};
+use ssz_derive::{Decode, Encode};
+use ssz_types::{VariableList, typenum::U4096};
+use tree_hash_derive::TreeHash;
+
+use crate::leansig::{errors::LeanSigError, public_key::PublicKey, signature::Signature};
+
+/// Signatures of several signers at the same epoch, in signer order.
+///
+/// XMSS signatures don't combine, so until the lean spec settles on a succinct proof of many
+/// signatures, aggregating keeps every signature and verifying checks each of them. The type keeps
+/// callers independent of that choice.
+#[derive(
+    Debug, PartialEq, Eq, Clone, Default, Serialize, Deserialize, Encode, Decode, TreeHash,
+)]
+pub struct AggregateSignature {
+    /// U4096 = VALIDATOR_REGISTRY_LIMIT
+    pub signatures: VariableList<Signature, U4096>,
+}
+
+impl AggregateSignature {
+    pub fn aggregate(
+        signatures: impl IntoIterator<Item = Signature>,
+    ) -> Result<Self, LeanSigError> {
+        let signatures = signatures.into_iter().collect::<Vec<_>>();
+        let count = signatures.len();
+        Ok(Self {
+            signatures: VariableList::new(signatures)
+                .map_err(|err| LeanSigError::TooManySignatures(count, err))?,
+        })
+    }
+
+    pub fn len(&self) -> usize {
+        self.signatures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.signatures.is_empty()
+    }
+
+    /// Verifies that the signers, each with its public key and message, signed at `epoch`, in the
+    /// order their signatures were aggregated.
+    pub fn verify(
+        &self,
+        signers: &[(PublicKey, [u8; MESSAGE_LENGTH])],
+        epoch: u32,
+    ) -> anyhow::Result<bool> {
+        ensure!(
+            signers.len() == self.signatures.len(),
+            "Aggregate of {} signatures has {} signers",
+            self.signatures.len(),
+            signers.len()
+        );
+        for ((public_key, message), signature) in signers.iter().zip(self.signatures.iter()) {
+            if !signature.verify(public_key, epoch, message)? {
+                return Ok(false);
+            }
+        }
+        Ok(true)
+    }
+
+    /// Verifies that every key of `public_keys` signed `message` at `epoch`.
+    pub fn verify_same_message(
+        &self,
+        public_keys: &[PublicKey],
+        epoch: u32,
+        message: &[u8; MESSAGE_LENGTH],
+    ) -> anyhow::Result<bool> {
+        self.verify(
+            &public_keys
+                .iter()
+                .map(|public_key| (*public_key, *message))
+                .collect::<Vec<_>>(),
+            epoch,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rng;
+
+    use super::AggregateSignature;
+    use crate::leansig::private_key::PrivateKey;
+
+    #[test]
+    fn test_verify_aggregate() {
+        let key_pairs = (0..3)
+            .map(|_| PrivateKey::generate_key_pair(&mut rng(), 0, 10))
+            .collect::<Vec<_>>();
+        let public_keys = key_pairs
+            .iter()
+            .map(|(public_key, _)| *public_key)
+            .collect::<Vec<_>>();
+        let message = [1; 32];
+
+        let aggregate = AggregateSignature::aggregate(
+            key_pairs
+                .iter()
+                .map(|(_, private_key)| private_key.sign(&message, 4).unwrap()),
+        )
+        .unwrap();
+        assert!(
+            aggregate
+                .verify_same_message(&public_keys, 4, &message)
+                .unwrap()
+        );
+        assert!(
+            !aggregate
+                .verify_same_message(&public_keys, 4, &[2; 32])
+                .unwrap()
+        );
+
+        // Signatures are checked against the signers in order.
+        let mut swapped = public_keys.clone();
+        swapped.swap(0, 1);
+        assert!(
+            !aggregate
+                .verify_same_message(&swapped, 4, &message)
+                .unwrap()
+        );
+        assert!(
+            aggregate
+                .verify_same_message(&public_keys[..2], 4, &message)
+                .is_err()
+        );
+    }
+}
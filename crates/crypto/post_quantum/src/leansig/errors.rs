@@ -1,3 +1,5 @@
+use std::ops::Range;
+
 #[derive(Debug, thiserror::Error)]
 pub enum LeanSigError {
     #[error("Signing failed: {0:?}")]
@@ -8,6 +10,15 @@ pub enum LeanSigError {
 
     #[error("Invalid signature length: {0}")]
     InvalidSignatureLength(usize),
+
+    #[error("Too many signatures to aggregate: {0}: {1:?}")]
+    TooManySignatures(usize, ssz_types::Error),
+
+    #[error("Epoch {epoch} is outside the activation interval {activation_interval:?} of the key")]
+    EpochOutsideActivationInterval {
+        epoch: u32,
+        activation_interval: Range<u64>,
+    },
 }
 
 impl From<core::array::TryFromSliceError> for LeanSigError {
@@ -1,6 +1,13 @@
+pub mod aggregate;
 pub mod errors;
 pub mod private_key;
 pub mod public_key;
 pub mod signature;
 
+/// The name keys manifests give [LeanSigScheme].
+pub const LEAN_SIG_SCHEME_NAME: &str = "SIGTopLevelTargetSumLifetime32Dim64Base8";
+
+/// The number of epochs a key of [LeanSigScheme] can be active for at most.
+pub const LEAN_SIG_LIFETIME: u64 = 1 << 32;
+
 pub type LeanSigScheme = leansig::signature::generalized_xmss::instantiations_poseidon_top_level::lifetime_2_to_the_32::hashing_optimized::SIGTopLevelTargetSumLifetime32Dim64Base8;
@@ -1,18 +1,23 @@
 use std::{fmt, fmt::Debug, ops::Range};
 
-use alloy_primitives::hex::ToHexExt;
 use leansig::{
     MESSAGE_LENGTH,
     serialization::Serializable,
     signature::{SignatureScheme, SignatureSchemeSecretKey},
 };
 use rand::Rng;
+use zeroize::Zeroizing;
 
 use super::errors::LeanSigError;
 use crate::leansig::{LeanSigScheme, public_key::PublicKey, signature::Signature};
 
 pub type LeanSigPrivateKey = <LeanSigScheme as SignatureScheme>::SecretKey;
 
+/// A lean private key.
+///
+/// The key material is never printed, and every serialized copy the wrapper makes is wiped once
+/// it is dropped. The leansig key itself doesn't support zeroization, so the memory of `inner`
+/// is only as protected as leansig leaves it.
 pub struct PrivateKey {
     pub inner: LeanSigPrivateKey,
 }
@@ -42,6 +47,14 @@ impl PrivateKey {
         self.inner.get_activation_interval()
     }
 
+    /// Returns how many epochs from `epoch` on, including it, the key can still sign at.
+    pub fn remaining_epochs(&self, epoch: u64) -> u64 {
+        let activation_interval = self.get_activation_interval();
+        activation_interval
+            .end
+            .saturating_sub(epoch.max(activation_interval.start))
+    }
+
     /// Returns the sub-interval for which the key is currently prepared to sign messages.
     pub fn get_prepared_interval(&self) -> Range<u64> {
         self.inner.get_prepared_interval()
@@ -55,11 +68,7 @@ impl PrivateKey {
         self.inner.advance_preparation()
     }
 
-    /// Signs a message for a given epoch.
-    ///
-    /// # Panics
-    ///
-    /// Panics if the epoch is not within the activation interval
+    /// Signs a message for a given epoch, which has to be within the activation interval.
     pub fn sign(
         &self,
         message: &[u8; MESSAGE_LENGTH],
@@ -67,10 +76,12 @@ impl PrivateKey {
     ) -> anyhow::Result<Signature, LeanSigError> {
         let activation_interval = self.get_activation_interval();
 
-        assert!(
-            activation_interval.contains(&(epoch as u64)),
-            "Epoch {epoch} is outside the activation interval {activation_interval:?}",
-        );
+        if !activation_interval.contains(&(epoch as u64)) {
+            return Err(LeanSigError::EpochOutsideActivationInterval {
+                epoch,
+                activation_interval,
+            });
+        }
 
         let signature = <LeanSigScheme as SignatureScheme>::sign(&self.inner, epoch, message)
             .map_err(LeanSigError::SigningFailed)?;
@@ -81,13 +92,15 @@ impl PrivateKey {
 
 impl Debug for PrivateKey {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.inner.to_bytes().encode_hex())
+        f.debug_struct("PrivateKey")
+            .field("activation_interval", &self.get_activation_interval())
+            .finish_non_exhaustive()
     }
 }
 
 impl PartialEq for PrivateKey {
     fn eq(&self, other: &Self) -> bool {
-        self.inner.to_bytes() == other.inner.to_bytes()
+        Zeroizing::new(self.inner.to_bytes()) == Zeroizing::new(other.inner.to_bytes())
     }
 }
 
@@ -95,7 +108,7 @@ impl PartialEq for PrivateKey {
 mod tests {
     use rand::rng;
 
-    use crate::leansig::private_key::PrivateKey;
+    use crate::leansig::{errors::LeanSigError, private_key::PrivateKey};
 
     #[test]
     fn test_sign_and_verify() {
@@ -123,4 +136,30 @@ mod tests {
         assert!(verify_result.is_ok(), "Verification should succeed");
         assert!(verify_result.unwrap(), "Signature should be valid");
     }
+
+    #[test]
+    fn test_sign_outside_activation_interval() {
+        let (_, private_key) = PrivateKey::generate_key_pair(&mut rng(), 0, 10);
+        let activation_interval = private_key.get_activation_interval();
+
+        assert_eq!(private_key.remaining_epochs(activation_interval.end - 1), 1);
+        assert_eq!(private_key.remaining_epochs(activation_interval.end), 0);
+        assert!(matches!(
+            private_key.sign(&[0; 32], activation_interval.end as u32),
+            Err(LeanSigError::EpochOutsideActivationInterval { .. })
+        ));
+    }
+
+    #[test]
+    fn test_debug_hides_key_material() {
+        let (_, private_key) = PrivateKey::generate_key_pair(&mut rng(), 0, 10);
+
+        assert_eq!(
+            format!("{private_key:?}"),
+            format!(
+                "PrivateKey {{ activation_interval: {:?}, .. }}",
+                private_key.get_activation_interval()
+            )
+        );
+    }
 }
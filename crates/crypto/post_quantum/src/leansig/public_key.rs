@@ -1,9 +1,15 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, LazyLock},
+};
+
 use alloy_primitives::{
     FixedBytes,
     hex::{self, ToHexExt},
 };
 use anyhow::anyhow;
 use leansig::{serialization::Serializable, signature::SignatureScheme};
+use parking_lot::RwLock;
 use serde::{Deserialize, Deserializer, Serialize};
 use ssz_derive::{Decode, Encode};
 use tree_hash_derive::TreeHash;
@@ -12,6 +18,14 @@ use crate::leansig::{LeanSigScheme, errors::LeanSigError};
 
 pub type LeanSigPublicKey = <LeanSigScheme as SignatureScheme>::PublicKey;
 
+/// The cache is cleared once it holds this many keys, far more than there are validators.
+const MAX_DECODED_PUBLIC_KEYS: usize = 1 << 16;
+
+/// Decoded public keys by their encoding. Validators keep their keys, so every signature of a
+/// validator is verified against the same key, which is decoded only once.
+static DECODED_PUBLIC_KEYS: LazyLock<RwLock<HashMap<FixedBytes<52>, Arc<LeanSigPublicKey>>>> =
+    LazyLock::new(Default::default);
+
 /// Wrapper around the `GeneralizedXMSSPublicKey` from the leansig crate.
 ///
 /// With current signature parameters, the serialized public key is 52 bytes:
@@ -26,8 +40,8 @@ pub type LeanSigPublicKey = <LeanSigScheme as SignatureScheme>::PublicKey;
 /// NOTE: [SignatureScheme::PublicKey] is a Rust trait that only implements [serde::Serialize] and
 /// [serde::Deserialize]. So it's impossible to implement [From] or [Into] traits for it.
 ///
-/// NOTE 2: We might use caching here (e.g., `OnceCell`) if serialization/deserialization becomes a
-/// bottleneck.
+/// NOTE 2: A `OnceCell` would make the type neither [Copy] nor SSZ derivable, so decoded keys are
+/// cached process-wide instead, see [PublicKey::as_lean_sig].
 #[derive(Debug, PartialEq, Clone, Encode, Decode, TreeHash, Default, Eq, Hash, Copy)]
 pub struct PublicKey {
     pub inner: FixedBytes<52>,
@@ -52,7 +66,24 @@ impl PublicKey {
         })
     }
 
-    pub fn as_lean_sig(&self) -> anyhow::Result<LeanSigPublicKey> {
+    /// Returns the decoded key, which is cached so verifying signatures of the same validator
+    /// decodes its key only once.
+    pub fn as_lean_sig(&self) -> anyhow::Result<Arc<LeanSigPublicKey>> {
+        if let Some(public_key) = DECODED_PUBLIC_KEYS.read().get(&self.inner) {
+            return Ok(public_key.clone());
+        }
+
+        let public_key = Arc::new(self.decode()?);
+        let mut decoded_public_keys = DECODED_PUBLIC_KEYS.write();
+        if decoded_public_keys.len() >= MAX_DECODED_PUBLIC_KEYS {
+            decoded_public_keys.clear();
+        }
+        decoded_public_keys.insert(self.inner, public_key.clone());
+        Ok(public_key)
+    }
+
+    /// Decodes the key without going through the cache.
+    pub fn decode(&self) -> anyhow::Result<LeanSigPublicKey> {
         LeanSigPublicKey::from_bytes(self.inner.as_slice())
             .map_err(|err| anyhow!("Failed to decode LeanSigPublicKey from SSZ: {err:?}"))
     }
@@ -88,3 +119,22 @@ impl<'de> Deserialize<'de> for PublicKey {
         .map_err(serde::de::Error::custom)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use leansig::serialization::Serializable;
+    use rand::rng;
+
+    use crate::leansig::private_key::PrivateKey;
+
+    #[test]
+    fn test_decoded_public_key_is_cached() {
+        let (public_key, _) = PrivateKey::generate_key_pair(&mut rng(), 0, 10);
+
+        let decoded = public_key.as_lean_sig().unwrap();
+        assert!(Arc::ptr_eq(&decoded, &public_key.as_lean_sig().unwrap()));
+        assert_eq!(decoded.to_bytes(), public_key.decode().unwrap().to_bytes());
+    }
+}
@@ -62,8 +62,10 @@ impl Signature {
 
 #[cfg(test)]
 mod tests {
+    use leansig::serialization::Serializable;
     use rand::rng;
 
+    use super::SIGNATURE_SIZE;
     use crate::leansig::{private_key::PrivateKey, signature::Signature};
 
     #[test]
@@ -95,4 +97,20 @@ mod tests {
         // verify roundtrip
         assert_eq!(signature, signature_returned);
     }
+
+    #[test]
+    fn test_serialized_sizes_are_constant() {
+        // The wrappers store keys and signatures in fixed-size arrays, so every key and signature
+        // leansig produces has to serialize to exactly that size.
+        for epoch in [0, 5, 9] {
+            let (public_key, private_key) = PrivateKey::generate_key_pair(&mut rng(), 0, 10);
+            assert_eq!(public_key.decode().unwrap().to_bytes().len(), 52);
+
+            let signature = private_key.sign(&[epoch as u8; 32], epoch).unwrap();
+            assert_eq!(
+                signature.as_lean_sig().unwrap().to_bytes().len(),
+                SIGNATURE_SIZE
+            );
+        }
+    }
 }
@@ -25,6 +25,12 @@ pub struct CachedPeer {
 
     pub head_checkpoint: Option<Checkpoint>,
     pub finalized_checkpoint: Option<Checkpoint>,
+
+    /// Starts at 0 and goes down every time the peer misbehaves.
+    pub score: i32,
+
+    /// Whether the peer was configured as trusted, which makes it the preferred sync source.
+    pub trusted: bool,
 }
 
 impl CachedPeer {
@@ -42,6 +48,8 @@ impl CachedPeer {
             last_seen: Instant::now(),
             head_checkpoint: None,
             finalized_checkpoint: None,
+            score: 0,
+            trusted: false,
         }
     }
 
@@ -1,19 +1,32 @@
 pub mod cached_peer;
+pub mod local_node;
 
-use std::{collections::HashMap, sync::Arc};
+use std::{
+    cmp::Reverse,
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
 
 use libp2p::{Multiaddr, PeerId};
 use parking_lot::{Mutex, RwLock};
 use ream_consensus_lean::checkpoint::Checkpoint;
+use ream_metrics::{PEER_COUNT, set_int_gauge_vec};
 use ream_peer::{ConnectionState, Direction};
 
-use crate::cached_peer::CachedPeer;
+use crate::{cached_peer::CachedPeer, local_node::LocalNode};
+
+/// How many of the latest block arrival offsets are kept, see
+/// [NetworkState::record_block_arrival_offset].
+const MAX_BLOCK_ARRIVAL_OFFSETS: usize = 64;
 
 #[derive(Debug)]
 pub struct NetworkState {
     pub peer_table: Arc<Mutex<HashMap<PeerId, CachedPeer>>>,
     pub head_checkpoint: RwLock<Checkpoint>,
     pub finalized_checkpoint: RwLock<Checkpoint>,
+    /// Set by the network service once the swarm is listening.
+    pub local_node: RwLock<Option<LocalNode>>,
+    block_arrival_offsets: Mutex<VecDeque<i64>>,
 }
 
 impl NetworkState {
@@ -22,6 +35,8 @@ impl NetworkState {
             peer_table: Arc::new(Mutex::new(HashMap::new())),
             head_checkpoint: RwLock::new(head_checkpoint),
             finalized_checkpoint: RwLock::new(finalized_checkpoint),
+            local_node: RwLock::new(None),
+            block_arrival_offsets: Mutex::new(VecDeque::new()),
         }
     }
 
@@ -32,8 +47,8 @@ impl NetworkState {
         state: ConnectionState,
         direction: Direction,
     ) {
-        self.peer_table
-            .lock()
+        let mut peer_table = self.peer_table.lock();
+        peer_table
             .entry(peer_id)
             .and_modify(|cached_peer| {
                 if let Some(address_ref) = &address {
@@ -43,6 +58,8 @@ impl NetworkState {
                 cached_peer.direction = direction;
             })
             .or_insert(CachedPeer::new(peer_id, address, state, direction));
+
+        update_peer_count_metrics(&peer_table);
     }
 
     pub fn connected_peers(&self) -> usize {
@@ -53,8 +70,99 @@ impl NetworkState {
             .count()
     }
 
+    /// Records the head and finalized checkpoints a peer reported in its status.
+    pub fn update_peer_status(
+        &self,
+        peer_id: &PeerId,
+        head_checkpoint: Checkpoint,
+        finalized_checkpoint: Checkpoint,
+    ) {
+        if let Some(cached_peer) = self.peer_table.lock().get_mut(peer_id) {
+            cached_peer.head_checkpoint = Some(head_checkpoint);
+            cached_peer.finalized_checkpoint = Some(finalized_checkpoint);
+            cached_peer.update_last_seen();
+        }
+    }
+
+    /// Lowers the score of a peer by `penalty`, returning its new score if the peer is known.
+    pub fn penalize_peer(&self, peer_id: &PeerId, penalty: i32) -> Option<i32> {
+        let mut peer_table = self.peer_table.lock();
+        let cached_peer = peer_table.get_mut(peer_id)?;
+        cached_peer.score = cached_peer.score.saturating_sub(penalty);
+        Some(cached_peer.score)
+    }
+
+    pub fn set_peer_trusted(&self, peer_id: &PeerId, trusted: bool) {
+        if let Some(cached_peer) = self.peer_table.lock().get_mut(peer_id) {
+            cached_peer.trusted = trusted;
+        }
+    }
+
+    /// Returns the connected peers to sync from in order of preference: trusted peers first,
+    /// then the peers with the highest head.
+    pub fn sync_peers(&self) -> Vec<CachedPeer> {
+        let mut peers = self
+            .peer_table
+            .lock()
+            .values()
+            .filter(|peer| matches!(peer.state, ConnectionState::Connected))
+            .cloned()
+            .collect::<Vec<_>>();
+        peers.sort_by_key(|peer| {
+            (
+                Reverse(peer.trusted),
+                Reverse(peer.head_checkpoint.map(|checkpoint| checkpoint.slot)),
+            )
+        });
+        peers
+    }
+
+    /// Returns the highest head slot reported by a connected peer.
+    pub fn highest_peer_head_slot(&self) -> Option<u64> {
+        self.peer_table
+            .lock()
+            .values()
+            .filter(|peer| matches!(peer.state, ConnectionState::Connected))
+            .filter_map(|peer| peer.head_checkpoint.map(|checkpoint| checkpoint.slot))
+            .max()
+    }
+
+    /// Records how many milliseconds after the start of its slot, by the local clock, a valid block
+    /// arrived over gossip. Proposers send blocks at the start of the slot, so the offsets show
+    /// how far the local clock is off from theirs, plus the propagation delay.
+    pub fn record_block_arrival_offset(&self, offset_millis: i64) {
+        let mut block_arrival_offsets = self.block_arrival_offsets.lock();
+        if block_arrival_offsets.len() == MAX_BLOCK_ARRIVAL_OFFSETS {
+            block_arrival_offsets.pop_front();
+        }
+        block_arrival_offsets.push_back(offset_millis);
+    }
+
+    /// Returns the latest block arrival offsets, oldest first.
+    pub fn block_arrival_offsets(&self) -> Vec<i64> {
+        self.block_arrival_offsets.lock().iter().copied().collect()
+    }
+
     /// Returns the cached peer from the peer table.
     pub fn cached_peer(&self, id: &PeerId) -> Option<CachedPeer> {
         self.peer_table.lock().get(id).cloned()
     }
 }
+
+/// Recomputes the [PEER_COUNT] gauge for every direction and connection state combination, so
+/// that buckets which become empty are reset to zero.
+fn update_peer_count_metrics(peer_table: &HashMap<PeerId, CachedPeer>) {
+    for direction in Direction::ALL {
+        for state in ConnectionState::ALL {
+            let count = peer_table
+                .values()
+                .filter(|peer| peer.direction == direction && peer.state == state)
+                .count();
+            set_int_gauge_vec(
+                &PEER_COUNT,
+                count as i64,
+                &[&direction.to_string(), &state.to_string()],
+            );
+        }
+    }
+}
@@ -0,0 +1,20 @@
+use libp2p::{Multiaddr, PeerId};
+use serde::{Deserialize, Serialize};
+
+/// How this node is reachable by its peers.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+pub struct LocalNode {
+    /// libp2p peer ID
+    pub peer_id: PeerId,
+
+    /// Base64 encoded ENR advertising the QUIC port, as used in bootnode lists
+    pub enr: String,
+
+    /// Multiaddresses the swarm is listening on
+    pub listen_addresses: Vec<Multiaddr>,
+
+    /// Multiaddresses peers can reach us at from outside our network, mapped by UPnP or observed
+    /// by peers
+    #[serde(default)]
+    pub external_addresses: Vec<Multiaddr>,
+}
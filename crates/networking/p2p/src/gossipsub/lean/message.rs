@@ -1,5 +1,10 @@
 use libp2p::gossipsub::TopicHash;
-use ream_consensus_lean::{attestation::SignedAttestation, block::SignedBlockWithAttestation};
+use ream_consensus_lean::{
+    attestation::SignedAttestation,
+    blob_sidecar::BlobSidecar,
+    block::SignedBlockWithAttestation,
+    light_client::{LightClientFinalityUpdate, LightClientOptimisticUpdate},
+};
 use ssz::Decode;
 
 use super::topics::{LeanGossipTopic, LeanGossipTopicKind};
@@ -9,6 +14,9 @@ use crate::gossipsub::error::GossipsubError;
 pub enum LeanGossipsubMessage {
     Block(Box<SignedBlockWithAttestation>),
     Attestation(Box<SignedAttestation>),
+    BlobSidecar(Box<BlobSidecar>),
+    LightClientFinalityUpdate(Box<LightClientFinalityUpdate>),
+    LightClientOptimisticUpdate(Box<LightClientOptimisticUpdate>),
 }
 
 impl LeanGossipsubMessage {
@@ -20,6 +28,17 @@ impl LeanGossipsubMessage {
             LeanGossipTopicKind::Attestation => Ok(Self::Attestation(Box::new(
                 SignedAttestation::from_ssz_bytes(data)?,
             ))),
+            LeanGossipTopicKind::BlobSidecar => Ok(Self::BlobSidecar(Box::new(
+                BlobSidecar::from_ssz_bytes(data)?,
+            ))),
+            LeanGossipTopicKind::LightClientFinalityUpdate => Ok(Self::LightClientFinalityUpdate(
+                Box::new(LightClientFinalityUpdate::from_ssz_bytes(data)?),
+            )),
+            LeanGossipTopicKind::LightClientOptimisticUpdate => {
+                Ok(Self::LightClientOptimisticUpdate(Box::new(
+                    LightClientOptimisticUpdate::from_ssz_bytes(data)?,
+                )))
+            }
         }
     }
 }
@@ -1,5 +1,7 @@
 use LeanGossipTopicKind::*;
+use alloy_primitives::B256;
 use libp2p::gossipsub::{IdentTopic as Topic, TopicHash};
+use ream_network_spec::networks::LeanNetworkSpec;
 
 use crate::gossipsub::error::GossipsubError;
 
@@ -7,9 +9,17 @@ pub const TOPIC_PREFIX: &str = "leanconsensus";
 pub const ENCODING_POSTFIX: &str = "ssz_snappy";
 pub const LEAN_BLOCK_TOPIC: &str = "block";
 pub const LEAN_ATTESTATION_TOPIC: &str = "attestation";
+pub const LEAN_BLOB_SIDECAR_TOPIC: &str = "blob_sidecar";
+pub const LEAN_LIGHT_CLIENT_FINALITY_UPDATE_TOPIC: &str = "light_client_finality_update";
+pub const LEAN_LIGHT_CLIENT_OPTIMISTIC_UPDATE_TOPIC: &str = "light_client_optimistic_update";
+
+/// The topics of a fork are subscribed to this many slots before it activates and kept as long
+/// after, so messages of nodes whose clocks are slightly off still arrive around the fork slot.
+pub const FORK_TOPIC_OVERLAP_SLOTS: u64 = 2;
 
 #[derive(Debug, Clone, Hash, PartialEq, Eq)]
 pub struct LeanGossipTopic {
+    /// The fork segment of the topic, see [LeanNetworkSpec::topic_fork].
     pub fork: String,
     pub kind: LeanGossipTopicKind,
 }
@@ -31,6 +41,13 @@ impl LeanGossipTopic {
         let kind = match topic_parts[2] {
             LEAN_BLOCK_TOPIC => LeanGossipTopicKind::Block,
             LEAN_ATTESTATION_TOPIC => LeanGossipTopicKind::Attestation,
+            LEAN_BLOB_SIDECAR_TOPIC => LeanGossipTopicKind::BlobSidecar,
+            LEAN_LIGHT_CLIENT_FINALITY_UPDATE_TOPIC => {
+                LeanGossipTopicKind::LightClientFinalityUpdate
+            }
+            LEAN_LIGHT_CLIENT_OPTIMISTIC_UPDATE_TOPIC => {
+                LeanGossipTopicKind::LightClientOptimisticUpdate
+            }
             other => {
                 return Err(GossipsubError::InvalidTopic(format!(
                     "Invalid topic: {other:?}"
@@ -69,6 +86,9 @@ impl From<LeanGossipTopic> for TopicHash {
         let kind_str = match &val.kind {
             Block => LEAN_BLOCK_TOPIC,
             Attestation => LEAN_ATTESTATION_TOPIC,
+            BlobSidecar => LEAN_BLOB_SIDECAR_TOPIC,
+            LightClientFinalityUpdate => LEAN_LIGHT_CLIENT_FINALITY_UPDATE_TOPIC,
+            LightClientOptimisticUpdate => LEAN_LIGHT_CLIENT_OPTIMISTIC_UPDATE_TOPIC,
         };
         TopicHash::from_raw(format!(
             "/{TOPIC_PREFIX}/{}/{kind_str}/{ENCODING_POSTFIX}",
@@ -77,10 +97,35 @@ impl From<LeanGossipTopic> for TopicHash {
     }
 }
 
+/// Returns the forks whose topics are subscribed to at `slot`, the one messages are published on
+/// first. Around a fork slot the topics of both sides of the fork are subscribed to, see
+/// [FORK_TOPIC_OVERLAP_SLOTS].
+pub fn subscribed_topic_forks(
+    network_spec: &LeanNetworkSpec,
+    genesis_root: B256,
+    slot: u64,
+) -> Vec<String> {
+    let mut forks = vec![];
+    for slot in [
+        slot,
+        slot.saturating_sub(FORK_TOPIC_OVERLAP_SLOTS),
+        slot.saturating_add(FORK_TOPIC_OVERLAP_SLOTS),
+    ] {
+        let fork = network_spec.topic_fork(genesis_root, slot);
+        if !forks.contains(&fork) {
+            forks.push(fork);
+        }
+    }
+    forks
+}
+
 #[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
 pub enum LeanGossipTopicKind {
     Block,
     Attestation,
+    BlobSidecar,
+    LightClientFinalityUpdate,
+    LightClientOptimisticUpdate,
 }
 
 impl std::fmt::Display for LeanGossipTopicKind {
@@ -88,6 +133,82 @@ impl std::fmt::Display for LeanGossipTopicKind {
         match self {
             LeanGossipTopicKind::Block => write!(f, "{LEAN_BLOCK_TOPIC}"),
             LeanGossipTopicKind::Attestation => write!(f, "{LEAN_ATTESTATION_TOPIC}"),
+            LeanGossipTopicKind::BlobSidecar => write!(f, "{LEAN_BLOB_SIDECAR_TOPIC}"),
+            LeanGossipTopicKind::LightClientFinalityUpdate => {
+                write!(f, "{LEAN_LIGHT_CLIENT_FINALITY_UPDATE_TOPIC}")
+            }
+            LeanGossipTopicKind::LightClientOptimisticUpdate => {
+                write!(f, "{LEAN_LIGHT_CLIENT_OPTIMISTIC_UPDATE_TOPIC}")
+            }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::{B256, aliases::B32};
+    use libp2p::gossipsub::TopicHash;
+    use ream_network_spec::networks::{
+        GENESIS_TOPIC_FORK, LeanFork, LeanNetworkSpec, ScheduledLeanFork,
+    };
+
+    use super::{
+        FORK_TOPIC_OVERLAP_SLOTS, LeanGossipTopic, LeanGossipTopicKind, subscribed_topic_forks,
+    };
+
+    #[test]
+    fn test_topic_round_trip() {
+        let topic = LeanGossipTopic {
+            fork: GENESIS_TOPIC_FORK.to_string(),
+            kind: LeanGossipTopicKind::Block,
+        };
+        let topic_hash = TopicHash::from(topic.clone());
+
+        assert_eq!(
+            topic_hash.as_str(),
+            "/leanconsensus/devnet0/block/ssz_snappy"
+        );
+        assert_eq!(
+            LeanGossipTopic::from_topic_hash(&topic_hash).unwrap(),
+            topic
+        );
+        assert!(
+            LeanGossipTopic::from_topic_hash(&TopicHash::from_raw(
+                "/leanconsensus/devnet0/unknown/ssz_snappy"
+            ))
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_subscribed_topic_forks() {
+        let fork_slot = 10;
+        let network_spec = LeanNetworkSpec {
+            genesis_fork_version: B32::repeat_byte(1),
+            fork_schedule: vec![ScheduledLeanFork {
+                fork: LeanFork::Devnet2,
+                version: B32::repeat_byte(2),
+                slot: fork_slot,
+            }],
+            ..LeanNetworkSpec::ephemery()
+        };
+        let forks = |slot| subscribed_topic_forks(&network_spec, B256::ZERO, slot);
+        let next_fork = network_spec.topic_fork(B256::ZERO, fork_slot);
+
+        assert_eq!(forks(0), vec![GENESIS_TOPIC_FORK]);
+        // The topics of the next fork are subscribed to shortly before it, while messages are
+        // still published on the topics of the genesis fork.
+        assert_eq!(
+            forks(fork_slot - FORK_TOPIC_OVERLAP_SLOTS),
+            vec![GENESIS_TOPIC_FORK.to_string(), next_fork.clone()]
+        );
+        assert_eq!(
+            forks(fork_slot),
+            vec![next_fork.clone(), GENESIS_TOPIC_FORK.to_string()]
+        );
+        assert_eq!(
+            forks(fork_slot + FORK_TOPIC_OVERLAP_SLOTS),
+            vec![next_fork.clone()]
+        );
+    }
+}
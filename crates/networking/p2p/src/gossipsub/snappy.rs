@@ -1,6 +1,8 @@
 use libp2p::gossipsub::{DataTransform, Message, RawMessage, TopicHash};
 use snap::raw::{Decoder, Encoder, decompress_len};
 
+use crate::utils::max_compressed_len;
+
 pub struct SnappyTransform {
     max_size_per_message: usize,
 }
@@ -15,6 +17,19 @@ impl SnappyTransform {
 
 impl DataTransform for SnappyTransform {
     fn inbound_transform(&self, raw_message: RawMessage) -> Result<Message, std::io::Error> {
+        // Both sizes are checked before decompressing, so a small message can't expand into a
+        // huge one.
+        if raw_message.data.len() as u64 > max_compressed_len(self.max_size_per_message as u64) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "Compressed message size ({}) exceeds max gossip size per message ({})",
+                    raw_message.data.len(),
+                    self.max_size_per_message
+                ),
+            ));
+        }
+
         let len = decompress_len(&raw_message.data)?;
 
         if len > self.max_size_per_message {
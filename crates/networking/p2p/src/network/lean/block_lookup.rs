@@ -0,0 +1,219 @@
+//! Lookups of blocks the chain service is missing, like the parent of a block whose parent isn't
+//! known. Each root is requested with BlocksByRoot from one peer at a time, and a request which
+//! fails, times out or ends without the block is retried against a peer which wasn't asked yet,
+//! after an exponential backoff.
+
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
+
+use alloy_primitives::B256;
+use delay_map::HashMapDelay;
+use libp2p_identity::PeerId;
+
+/// Lookups are given up after this many failed attempts.
+pub const MAX_LOOKUP_ATTEMPTS: u32 = 5;
+
+/// The backoff after the first failed attempt, doubled with every further one.
+const LOOKUP_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_LOOKUP_BACKOFF: Duration = Duration::from_secs(16);
+
+/// Peers are only downscored once they failed this many lookups in a row, as a single failure
+/// usually just means the peer doesn't have the block yet.
+const REPEATED_FAILURES: u32 = 2;
+
+#[derive(Debug, Default)]
+struct BlockLookup {
+    attempts: u32,
+    tried_peers: HashSet<PeerId>,
+    /// The request in flight and the peer it was sent to.
+    request: Option<(u64, PeerId)>,
+}
+
+/// What is left to do after a lookup request failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LookupFailure {
+    pub root: B256,
+    pub peer_id: PeerId,
+    /// Whether the peer failed enough lookups in a row to be downscored.
+    pub penalize: bool,
+    /// How long until the lookup is retried, `None` if it was given up.
+    pub retry_in: Option<Duration>,
+}
+
+#[derive(Debug)]
+pub struct BlockLookups {
+    lookups: HashMap<B256, BlockLookup>,
+    /// The root each request in flight looks up.
+    requests: HashMap<u64, B256>,
+    /// How many lookups each peer failed in a row.
+    peer_failures: HashMap<PeerId, u32>,
+    /// The lookups waiting for their backoff to pass before they are retried.
+    pub retries: HashMapDelay<B256, ()>,
+}
+
+impl Default for BlockLookups {
+    fn default() -> Self {
+        Self {
+            lookups: HashMap::new(),
+            requests: HashMap::new(),
+            peer_failures: HashMap::new(),
+            retries: HashMapDelay::new(LOOKUP_BACKOFF),
+        }
+    }
+}
+
+impl BlockLookups {
+    /// Starts looking up `root`, returning false if it is already being looked up.
+    pub fn start(&mut self, root: B256) -> bool {
+        if self.lookups.contains_key(&root) {
+            return false;
+        }
+        self.lookups.insert(root, BlockLookup::default());
+        true
+    }
+
+    /// Picks the peer for the next attempt of the lookup of `root` from `peers`, in order of
+    /// preference. Peers which weren't asked yet come first.
+    pub fn next_peer(&self, root: B256, peers: &[PeerId]) -> Option<PeerId> {
+        let lookup = self.lookups.get(&root)?;
+        if lookup.request.is_some() {
+            return None;
+        }
+        peers
+            .iter()
+            .find(|peer_id| !lookup.tried_peers.contains(peer_id))
+            .or_else(|| peers.first())
+            .copied()
+    }
+
+    /// Records that the lookup of `root` was requested from `peer_id` with `request_id`.
+    pub fn on_request_sent(&mut self, root: B256, peer_id: PeerId, request_id: u64) {
+        if let Some(lookup) = self.lookups.get_mut(&root) {
+            lookup.tried_peers.insert(peer_id);
+            lookup.request = Some((request_id, peer_id));
+            self.requests.insert(request_id, root);
+        }
+    }
+
+    /// Completes the lookup of `root` if `request_id` asked for it, returning whether it did.
+    pub fn on_block(&mut self, request_id: u64, root: B256) -> bool {
+        if self.requests.get(&request_id) != Some(&root) {
+            return false;
+        }
+        self.requests.remove(&request_id);
+        if let Some((_, peer_id)) = self.lookups.remove(&root).and_then(|lookup| lookup.request) {
+            self.peer_failures.remove(&peer_id);
+        }
+        self.retries.remove(&root);
+        true
+    }
+
+    /// Fails the attempt of `request_id`, if it is a lookup request which is still in flight, and
+    /// schedules the next one.
+    pub fn on_request_failed(&mut self, request_id: u64) -> Option<LookupFailure> {
+        let root = self.requests.remove(&request_id)?;
+        let (_, peer_id) = self.lookups.get_mut(&root)?.request.take()?;
+
+        let failures = self.peer_failures.entry(peer_id).or_default();
+        *failures += 1;
+        let penalize = *failures >= REPEATED_FAILURES;
+
+        Some(LookupFailure {
+            root,
+            peer_id,
+            penalize,
+            retry_in: self.schedule_retry(root),
+        })
+    }
+
+    /// Fails an attempt of the lookup of `root` which couldn't be sent, as no peer was
+    /// connected, returning when it is retried.
+    pub fn on_no_peer(&mut self, root: B256) -> Option<Duration> {
+        self.schedule_retry(root)
+    }
+
+    fn schedule_retry(&mut self, root: B256) -> Option<Duration> {
+        let lookup = self.lookups.get_mut(&root)?;
+        lookup.attempts += 1;
+        if lookup.attempts >= MAX_LOOKUP_ATTEMPTS {
+            self.lookups.remove(&root);
+            return None;
+        }
+
+        let backoff = LOOKUP_BACKOFF
+            .saturating_mul(1 << (lookup.attempts - 1))
+            .min(MAX_LOOKUP_BACKOFF);
+        self.retries.insert_at(root, (), backoff);
+        Some(backoff)
+    }
+
+    pub fn on_peer_disconnected(&mut self, peer_id: &PeerId) {
+        self.peer_failures.remove(peer_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy_primitives::B256;
+    use libp2p_identity::PeerId;
+
+    use super::{BlockLookups, MAX_LOOKUP_ATTEMPTS};
+
+    #[tokio::test]
+    async fn test_lookup_retries_other_peers() {
+        let mut lookups = BlockLookups::default();
+        let root = B256::repeat_byte(1);
+        let peers = [PeerId::random(), PeerId::random()];
+
+        assert!(lookups.start(root));
+        // The same root isn't looked up twice at once.
+        assert!(!lookups.start(root));
+
+        let peer_id = lookups.next_peer(root, &peers).unwrap();
+        assert_eq!(peer_id, peers[0]);
+        lookups.on_request_sent(root, peer_id, 1);
+        assert!(lookups.next_peer(root, &peers).is_none());
+
+        let failure = lookups.on_request_failed(1).unwrap();
+        assert_eq!(failure.peer_id, peers[0]);
+        assert!(!failure.penalize);
+        assert_eq!(failure.retry_in, Some(Duration::from_secs(1)));
+        assert!(lookups.on_request_failed(1).is_none());
+
+        // The retry goes to the peer which wasn't asked yet.
+        let peer_id = lookups.next_peer(root, &peers).unwrap();
+        assert_eq!(peer_id, peers[1]);
+        lookups.on_request_sent(root, peer_id, 2);
+        assert_eq!(
+            lookups.on_request_failed(2).unwrap().retry_in,
+            Some(Duration::from_secs(2))
+        );
+
+        lookups.on_request_sent(root, peers[0], 3);
+        assert!(lookups.on_request_failed(3).unwrap().penalize);
+
+        lookups.on_request_sent(root, peers[1], 4);
+        assert!(!lookups.on_block(4, B256::repeat_byte(2)));
+        assert!(lookups.on_block(4, root));
+        assert!(lookups.lookups.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_lookup_gives_up() {
+        let mut lookups = BlockLookups::default();
+        let root = B256::repeat_byte(1);
+        lookups.start(root);
+
+        for _ in 1..MAX_LOOKUP_ATTEMPTS {
+            assert!(lookups.on_no_peer(root).is_some());
+        }
+        assert!(lookups.on_no_peer(root).is_none());
+        assert!(lookups.lookups.is_empty());
+        assert!(lookups.start(root));
+    }
+}
@@ -0,0 +1,91 @@
+//! Drops and delays the messages of peers on purpose, to test how fork choice and sync cope with
+//! a partitioned or slow network. Only built with the `testing` feature.
+
+use std::{collections::HashMap, time::Duration};
+
+use libp2p_identity::PeerId;
+use rand::Rng;
+use ream_chain_lean::p2p_request::NetworkFaults;
+
+/// What to do with a message received from a peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Deliver,
+    Drop,
+    Delay(Duration),
+}
+
+#[derive(Debug, Default)]
+pub struct FaultInjector {
+    /// The faults of every peer without faults of its own.
+    default_faults: NetworkFaults,
+    peer_faults: HashMap<PeerId, NetworkFaults>,
+}
+
+impl FaultInjector {
+    /// Applies `faults` to the messages of `peer_id`. Without a peer, they apply to every peer and
+    /// replace the faults set for single peers.
+    pub fn set(&mut self, peer_id: Option<PeerId>, faults: NetworkFaults) {
+        match peer_id {
+            Some(peer_id) => {
+                self.peer_faults.insert(peer_id, faults);
+            }
+            None => {
+                self.peer_faults.clear();
+                self.default_faults = faults;
+            }
+        }
+    }
+
+    pub fn verdict(&self, peer_id: &PeerId) -> Verdict {
+        let faults = self
+            .peer_faults
+            .get(peer_id)
+            .unwrap_or(&self.default_faults);
+        let mut rng = rand::rng();
+        if rng.random_range(0..100) < faults.drop_percentage {
+            Verdict::Drop
+        } else if rng.random_range(0..100) < faults.delay_percentage {
+            Verdict::Delay(Duration::from_millis(faults.delay_ms))
+        } else {
+            Verdict::Deliver
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verdict() {
+        let mut fault_injector = FaultInjector::default();
+        let (peer_1, peer_2) = (PeerId::random(), PeerId::random());
+        assert_eq!(fault_injector.verdict(&peer_1), Verdict::Deliver);
+
+        fault_injector.set(
+            None,
+            NetworkFaults {
+                drop_percentage: 100,
+                ..Default::default()
+            },
+        );
+        fault_injector.set(
+            Some(peer_2),
+            NetworkFaults {
+                delay_percentage: 100,
+                delay_ms: 500,
+                ..Default::default()
+            },
+        );
+        assert_eq!(fault_injector.verdict(&peer_1), Verdict::Drop);
+        assert_eq!(
+            fault_injector.verdict(&peer_2),
+            Verdict::Delay(Duration::from_millis(500))
+        );
+
+        // Faults for every peer replace those of single peers.
+        fault_injector.set(None, NetworkFaults::default());
+        assert_eq!(fault_injector.verdict(&peer_2), Verdict::Deliver);
+    }
+}
@@ -1,62 +1,147 @@
+pub mod block_lookup;
+#[cfg(feature = "testing")]
+pub mod fault_injection;
+pub mod observed_addresses;
+pub mod peer_limits;
+
 use std::{
+    collections::{HashMap, HashSet},
     fs,
     net::IpAddr,
     num::{NonZeroU8, NonZeroUsize},
+    path::Path,
     sync::{
         Arc,
         atomic::{AtomicU64, Ordering},
     },
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use alloy_primitives::hex;
+use alloy_primitives::{B256, hex};
 use anyhow::anyhow;
 use delay_map::HashMapDelay;
-use discv5::multiaddr::Protocol;
-use futures::{StreamExt, stream::FuturesUnordered};
+use discv5::{
+    Enr,
+    enr::{CombinedKey, k256::ecdsa::SigningKey},
+    multiaddr::Protocol,
+};
+use futures::{FutureExt, StreamExt, future::BoxFuture, stream::FuturesUnordered};
 use libp2p::{
     Multiaddr, SwarmBuilder,
     connection_limits::{self, ConnectionLimits},
     core::ConnectedPoint,
-    gossipsub::{Event as GossipsubEvent, IdentTopic, MessageAuthenticity},
+    gossipsub::{
+        Event as GossipsubEvent, IdentTopic, MessageAcceptance, MessageAuthenticity, MessageId,
+    },
     identify,
-    swarm::{Config, ConnectionId, NetworkBehaviour, Swarm, SwarmEvent},
+    swarm::{Config, ConnectionId, NetworkBehaviour, Swarm, SwarmEvent, behaviour::toggle::Toggle},
+    upnp,
 };
 use libp2p_identity::{Keypair, PeerId, secp256k1};
 use ream_chain_lean::{messages::LeanChainServiceMessage, p2p_request::LeanP2PRequest};
-use ream_executor::ReamExecutor;
+use ream_consensus_lean::{block::SignedBlockWithAttestation, checkpoint::Checkpoint};
+use ream_executor::{ReamExecutor, ShutdownSignal};
+use ream_metrics::{
+    DIAL_FAILURES_TOTAL, GOSSIP_MESSAGES_RECEIVED_TOTAL, GOSSIP_MESSAGES_SENT_TOTAL,
+    INBOUND_CONNECTIONS_REJECTED_TOTAL, PEERS_DISCONNECTED_TOTAL, REQ_RESP_REQUESTS_TOTAL,
+    inc_int_counter_vec,
+};
 use ream_network_spec::networks::{Devnet, lean_network_spec};
-use ream_network_state_lean::{NetworkState, cached_peer::CachedPeer};
+use ream_network_state_lean::{NetworkState, cached_peer::CachedPeer, local_node::LocalNode};
+use ream_node::secret_file::write_secret_file;
 use ream_peer::{ConnectionState, Direction};
 use ssz::Encode;
 use tokio::{
     sync::{
         mpsc::{UnboundedReceiver, UnboundedSender},
-        oneshot,
+        oneshot::{self, error::RecvError},
     },
-    time::Duration,
+    time::{Duration, interval, timeout},
 };
-use tracing::{info, trace, warn};
+use tracing::{debug, info, trace, warn};
+use tree_hash::TreeHash;
 
 use crate::{
     bootnodes::Bootnodes,
+    constants::QUIC_ENR_KEY,
     gossipsub::{
         GossipsubBehaviour,
         lean::{
-            configurations::LeanGossipsubConfig, message::LeanGossipsubMessage,
-            topics::LeanGossipTopicKind,
+            configurations::LeanGossipsubConfig,
+            message::LeanGossipsubMessage,
+            topics::{LeanGossipTopic, LeanGossipTopicKind, subscribed_topic_forks},
         },
         snappy::SnappyTransform,
     },
-    network::misc::Executor,
+    network::{
+        lean::{
+            block_lookup::{BlockLookups, LookupFailure, MAX_LOOKUP_ATTEMPTS},
+            observed_addresses::ObservedAddresses,
+            peer_limits::{AddressLimit, PeerLimits},
+        },
+        misc::Executor,
+    },
     req_resp::{
         Chain, ReqResp, ReqRespMessage,
-        handler::{ReqRespMessageReceived, RespMessage},
-        lean::messages::{LeanRequestMessage, LeanResponseMessage, status::Status},
+        error::ReqRespError,
+        handler::{ReqRespMessageError, ReqRespMessageReceived, RespMessage},
+        inbound_protocol::ResponseCode,
+        lean::messages::{
+            LeanRequestMessage, LeanResponseMessage, blobs::BlobsByRootV1Request,
+            blocks::BlocksByRootV1Request, status::Status,
+        },
         messages::{RequestMessage, ResponseMessage},
     },
 };
 
 const BOOTNODE_RETRY_TIMEOUT: Duration = Duration::from_secs(30);
+const PEER_PRUNE_INTERVAL: Duration = Duration::from_secs(30);
+const TOPIC_FORK_CHECK_INTERVAL: Duration = Duration::from_secs(1);
+/// How long the shutdown waits for the chain service to stop, and then for peers to disconnect.
+const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long the swarm keeps running on shutdown so the gossip published last reaches peers.
+const SHUTDOWN_GOSSIP_FLUSH_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How much a peer's score drops each time it sends a request or response over the protocol
+/// limits.
+const LIMIT_EXCEEDED_PENALTY: i32 = 25;
+
+/// How much a peer's score drops each time it answers a request with a response code which isn't
+/// defined.
+const INVALID_RESPONSE_CODE_PENALTY: i32 = 10;
+
+/// How much a peer's score drops each time it fails another block lookup after failing the
+/// previous one.
+const LOOKUP_FAILURE_PENALTY: i32 = 5;
+
+/// How much a peer's score drops each time it forwards a gossip message which breaks a consensus
+/// rule or can't be decoded.
+const INVALID_GOSSIP_PENALTY: i32 = 10;
+
+/// Peers are disconnected once their score drops to this.
+const MIN_PEER_SCORE: i32 = -100;
+
+/// The head of a peer at most this many slots ahead of ours is fetched as soon as its status
+/// arrives, a larger gap is left to sync.
+const MAX_HEAD_CATCH_UP_SLOTS: u64 = 4;
+
+/// The blocks or blob sidecars the chain service found for a BlocksByRoot or BlobsByRoot request,
+/// with the stream to answer it on.
+type ByRootResponse = (
+    PeerId,
+    ConnectionId,
+    u64,
+    Result<Vec<LeanResponseMessage>, RecvError>,
+);
+
+/// What the chain service made of a gossip message, with the message, the peer it came from and,
+/// for blocks, how many milliseconds after the start of their slot they arrived.
+type GossipValidation = (
+    MessageId,
+    PeerId,
+    Option<i64>,
+    Result<MessageAcceptance, RecvError>,
+);
 
 #[derive(NetworkBehaviour)]
 pub(crate) struct ReamBehaviour {
@@ -69,6 +154,9 @@ pub(crate) struct ReamBehaviour {
     pub gossipsub: GossipsubBehaviour,
 
     pub connection_limits: connection_limits::Behaviour,
+
+    /// Maps our ports on the gateway, if enabled
+    pub upnp: Toggle<upnp::tokio::Behaviour>,
 }
 
 #[derive(Debug)]
@@ -91,6 +179,77 @@ pub struct LeanNetworkConfig {
     pub socket_address: IpAddr,
     pub socket_port: u16,
     pub private_key_path: Option<std::path::PathBuf>,
+    /// Where the generated key is kept when no `private_key_path` is given, so the peer id and
+    /// ENR stay the same across restarts.
+    pub network_key_path: Option<std::path::PathBuf>,
+    /// Peers dialed before the bootnodes on startup, see [LeanNetworkService::add_trusted_peer].
+    pub trusted_peers: Vec<Multiaddr>,
+    pub peer_limits: PeerLimits,
+    /// Whether to map the QUIC port on the gateway with UPnP.
+    pub enable_upnp: bool,
+    /// Root of the genesis block, the topics of scheduled forks are named by fork digests computed
+    /// from it.
+    pub genesis_root: B256,
+}
+
+/// Reads the hex encoded secp256k1 key at `path`.
+pub fn read_network_key(path: &Path) -> anyhow::Result<Keypair> {
+    let private_key_hex = fs::read_to_string(path)
+        .map_err(|err| anyhow!("failed to read secret key file {}: {err}", path.display()))?;
+    let private_key_bytes = hex::decode(private_key_hex.trim()).map_err(|err| {
+        anyhow!(
+            "failed to decode hex from private key file {}: {err}",
+            path.display()
+        )
+    })?;
+    let private_key = secp256k1::SecretKey::try_from_bytes(private_key_bytes)
+        .map_err(|err| anyhow!("failed to decode secp256k1 secret key from bytes: {err}"))?;
+
+    Ok(Keypair::from(secp256k1::Keypair::from(private_key)))
+}
+
+/// Reads the network key at `path`, generating and writing a new one if the file doesn't exist
+/// yet.
+pub fn load_or_generate_network_key(path: &Path) -> anyhow::Result<Keypair> {
+    if path.exists() {
+        return read_network_key(path);
+    }
+
+    let keypair = secp256k1::Keypair::generate();
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|err| anyhow!("failed to create {}: {err}", parent.display()))?;
+    }
+    write_secret_file(path, hex::encode(keypair.secret().to_bytes()))
+        .map_err(|err| anyhow!("failed to write network key to {}: {err}", path.display()))?;
+    info!("Generated network key at {}", path.display());
+    Ok(Keypair::from(keypair))
+}
+
+/// Receives the requests still queued once the node shuts down, until the chain service says it
+/// stopped, every sender is dropped or `flush_timeout` passes. The API keeps a sender until it
+/// stops as well, so waiting for the channel to close would take the whole timeout.
+async fn drain_outbound_requests(
+    receiver: &mut UnboundedReceiver<LeanP2PRequest>,
+    flush_timeout: Duration,
+) -> Vec<LeanP2PRequest> {
+    let mut requests = vec![];
+    let _ = timeout(flush_timeout, async {
+        while let Some(request) = receiver.recv().await {
+            if let LeanP2PRequest::ChainServiceStopped = request {
+                break;
+            }
+            requests.push(request);
+        }
+    })
+    .await;
+    receiver.close();
+    while let Ok(request) = receiver.try_recv() {
+        if !matches!(request, LeanP2PRequest::ChainServiceStopped) {
+            requests.push(request);
+        }
+    }
+    requests
 }
 
 pub struct LeanNetworkService {
@@ -102,7 +261,26 @@ pub struct LeanNetworkService {
     request_id: AtomicU64,
     pub network_state: Arc<NetworkState>,
     check_canonical_futures: FuturesUnordered<oneshot::Receiver<(PeerId, bool)>>,
+    by_root_futures: FuturesUnordered<BoxFuture<'static, ByRootResponse>>,
+    /// The gossip messages waiting for the chain service to tell whether to forward them.
+    gossip_validations: FuturesUnordered<BoxFuture<'static, GossipValidation>>,
+    /// Messages held back by the injected network faults, see the `testing` feature.
+    delayed_events: FuturesUnordered<BoxFuture<'static, SwarmEvent<ReamBehaviourEvent>>>,
+    #[cfg(feature = "testing")]
+    fault_injector: fault_injection::FaultInjector,
+    /// The blocks the chain service asked us to fetch.
+    block_lookups: BlockLookups,
     pub multi_addr: Multiaddr,
+    trusted_peers: HashMap<PeerId, Multiaddr>,
+    banned_peers: HashSet<PeerId>,
+    enr_key: CombinedKey,
+    local_enr: Enr,
+    /// The addresses of ours peers observed, until one is confirmed.
+    observed_addresses: ObservedAddresses,
+    /// The forks whose topics we are subscribed to, the one we publish on first.
+    topic_forks: Vec<String>,
+    /// Whether the chain service said it stopped, so nothing more is going to be gossiped.
+    chain_service_stopped: bool,
 }
 
 impl LeanNetworkService {
@@ -122,24 +300,13 @@ impl LeanNetworkService {
             connection_limits::Behaviour::new(limits)
         };
 
-        let local_key = if let Some(ref path) = network_config.private_key_path {
-            let private_key_hex = fs::read_to_string(path).map_err(|err| {
-                anyhow!("failed to read secret key file {}: {err}", path.display())
-            })?;
-            let private_key_bytes = hex::decode(private_key_hex.trim()).map_err(|err| {
-                anyhow!(
-                    "failed to decode hex from private key file {}: {err}",
-                    path.display()
-                )
-            })?;
-            let private_key =
-                secp256k1::SecretKey::try_from_bytes(private_key_bytes).map_err(|err| {
-                    anyhow!("failed to decode secp256k1 secret key from bytes: {err}")
-                })?;
-
-            Keypair::from(secp256k1::Keypair::from(private_key))
-        } else {
-            Keypair::generate_secp256k1()
+        let local_key = match (
+            &network_config.private_key_path,
+            &network_config.network_key_path,
+        ) {
+            (Some(path), _) => read_network_key(path)?,
+            (None, Some(path)) => load_or_generate_network_key(path)?,
+            (None, None) => Keypair::generate_secp256k1(),
         };
 
         let gossipsub = {
@@ -169,6 +336,11 @@ impl LeanNetworkService {
                 gossipsub,
                 identify,
                 connection_limits,
+                upnp: Toggle::from(
+                    network_config
+                        .enable_upnp
+                        .then(upnp::tokio::Behaviour::default),
+                ),
             }
         };
 
@@ -192,6 +364,19 @@ impl LeanNetworkService {
         multi_addr.push(Protocol::P2p(local_key.public().to_peer_id()));
         info!("Listening on {multi_addr:?}");
 
+        let enr_key = enr_signing_key(&local_key)?;
+        let local_enr = build_local_enr(
+            &enr_key,
+            network_config.socket_address,
+            network_config.socket_port,
+        )?;
+        *network_state.local_node.write() = Some(LocalNode {
+            peer_id: local_key.public().to_peer_id(),
+            enr: local_enr.to_base64(),
+            listen_addresses: vec![multi_addr.clone()],
+            external_addresses: vec![],
+        });
+
         let mut lean_network_service = LeanNetworkService {
             network_config: network_config.clone(),
             swarm,
@@ -201,7 +386,20 @@ impl LeanNetworkService {
             request_id: AtomicU64::new(1),
             network_state,
             check_canonical_futures: FuturesUnordered::new(),
+            by_root_futures: FuturesUnordered::new(),
+            gossip_validations: FuturesUnordered::new(),
+            delayed_events: FuturesUnordered::new(),
+            #[cfg(feature = "testing")]
+            fault_injector: Default::default(),
+            block_lookups: BlockLookups::default(),
+            chain_service_stopped: false,
             multi_addr: multi_addr.clone(),
+            trusted_peers: HashMap::new(),
+            banned_peers: HashSet::new(),
+            enr_key,
+            local_enr,
+            observed_addresses: ObservedAddresses::default(),
+            topic_forks: current_topic_forks(network_config.genesis_root),
         };
 
         lean_network_service
@@ -211,31 +409,46 @@ impl LeanNetworkService {
                 anyhow!("Failed to start libp2p peer listen on {multi_addr:?}, error: {err:?}")
             })?;
 
-        for topic in &network_config.gossipsub_config.topics {
-            lean_network_service
-                .swarm
-                .behaviour_mut()
-                .gossipsub
-                .subscribe(&IdentTopic::from(topic.clone()))
-                .map_err(|err| anyhow!("subscribe to {topic} failed: {err:?}"))?;
+        for fork in &lean_network_service.topic_forks {
+            for topic in &network_config.gossipsub_config.topics {
+                let topic = LeanGossipTopic {
+                    fork: fork.clone(),
+                    kind: topic.kind,
+                };
+                lean_network_service
+                    .swarm
+                    .behaviour_mut()
+                    .gossipsub
+                    .subscribe(&IdentTopic::from(topic.clone()))
+                    .map_err(|err| anyhow!("subscribe to {topic} failed: {err:?}"))?;
+            }
         }
 
         Ok(lean_network_service)
     }
 
-    pub async fn start(&mut self, bootnodes: Bootnodes) -> anyhow::Result<()> {
+    pub async fn start(
+        &mut self,
+        bootnodes: Bootnodes,
+        mut shutdown: ShutdownSignal,
+    ) -> anyhow::Result<()> {
         info!("LeanNetworkService started");
 
+        for address in self.network_config.trusted_peers.clone() {
+            self.add_trusted_peer(address);
+        }
         self.connect_to_bootnodes(bootnodes.to_multiaddrs_lean())
             .await;
 
+        let mut prune_interval = interval(PEER_PRUNE_INTERVAL);
+        let mut topic_fork_interval = interval(TOPIC_FORK_CHECK_INTERVAL);
         loop {
             tokio::select! {
                 Some(Ok((peer_id, (attempts, addresses)))) = self.bootnode_retry_state.next() => {
                     if matches!(self.network_state.peer_table.lock().get(&peer_id).map(|peer| peer.state), Some(ConnectionState::Connected)) {
                         continue;
                     }
-                    if attempts >= 8 {
+                    if attempts >= 8 && !self.trusted_peers.contains_key(&peer_id) {
                         warn!("giving up on {peer_id:?} after 8 attempts");
                         continue;
                     };
@@ -249,66 +462,29 @@ impl LeanNetworkService {
                     self.bootnode_retry_state.insert(peer_id, (attempts + 1, addresses))
                 }
 
-                Some(item) = self.outbound_p2p_request.recv() => {
-                    match item {
-                        LeanP2PRequest::GossipBlock(signed_block) => {
-                            if let Err(err) = self.swarm
-                                .behaviour_mut()
-                                .gossipsub
-                                .publish(
-                                    self.network_config
-                                        .gossipsub_config
-                                        .topics
-                                        .iter()
-                                        .find(|block_topic| matches!(block_topic.kind, LeanGossipTopicKind::Block))
-                                        .map(|block_topic| IdentTopic::from(block_topic.clone()))
-                                        .expect("LeanBlock topic configured"),
-                                    signed_block.as_ssz_bytes(),
-                                )
-                            {
-                                warn!(
-                                    slot = signed_block.message.block.slot,
-                                    error = ?err,
-                                    "Publish block failed"
-                                );
-                            } else {
-                                info!(
-                                    slot = signed_block.message.block.slot,
-                                    "Broadcasted block"
-                                );
-                            }
-                        }
-                        LeanP2PRequest::GossipAttestation(signed_attestation) => {
-                            if let Err(err) = self.swarm
-                                .behaviour_mut()
-                                .gossipsub
-                                .publish(
-                                    self.network_config
-                                        .gossipsub_config
-                                        .topics
-                                        .iter()
-                                        .find(|attestation_topic| matches!(attestation_topic.kind, LeanGossipTopicKind::Attestation))
-                                        .map(|attestation_topic| IdentTopic::from(attestation_topic.clone()))
-                                        .expect("LeanAttestation topic configured"),
-                                    signed_attestation.as_ssz_bytes(),
-                                )
-                            {
-                                warn!(
-                                    slot = signed_attestation.message.slot(),
-                                    error = ?err,
-                                    "Publish attestation failed"
-                                );
-                            } else {
-                                info!(
-                                    slot = signed_attestation.message.slot(),
-                                    "Broadcasted attestation"
-                                );
-                            }
-                        }
-                    }
+                Some(item) = self.outbound_p2p_request.recv() => self.handle_p2p_request(item),
+
+                Some(Ok((root, ()))) = self.block_lookups.retries.next() => self.send_block_lookup(root),
+
+                _ = prune_interval.tick() => self.prune_peers(),
+
+                _ = topic_fork_interval.tick() => self.update_topic_forks(),
+
+                _ = shutdown.wait() => {
+                    self.shutdown().await;
+                    return Ok(());
                 }
 
                 Some(event) = self.swarm.next() => {
+                    #[cfg(feature = "testing")]
+                    let Some(event) = self.inject_faults(event) else {
+                        continue;
+                    };
+                    if let Some(event) = self.parse_swarm_event(event).await {
+                        info!("Swarm event: {event:?}");
+                    }
+                }
+                Some(event) = self.delayed_events.next() => {
                     if let Some(event) = self.parse_swarm_event(event).await {
                         info!("Swarm event: {event:?}");
                     }
@@ -336,6 +512,33 @@ impl LeanNetworkService {
                         }
                     }
                 }
+                Some((peer_id, connection_id, stream_id, result)) = self.by_root_futures.next() => {
+                    match result {
+                        Ok(responses) => {
+                            for response in responses {
+                                self.send_response(peer_id, connection_id, stream_id, response);
+                            }
+                            self.send_end_of_stream(peer_id, connection_id, stream_id);
+                        }
+                        Err(err) => {
+                            warn!(?peer_id, "Failed to receive by root lookup result: {err:?}");
+                            self.send_error_response(peer_id, connection_id, stream_id, ResponseCode::ServerError, "Failed to look up the requested items");
+                        }
+                    }
+                }
+                Some((message_id, propagation_source, arrival_offset, result)) = self.gossip_validations.next() => {
+                    let acceptance = result.unwrap_or_else(|err| {
+                        warn!(?message_id, "Failed to receive gossip validation result: {err:?}");
+                        MessageAcceptance::Ignore
+                    });
+                    // Only valid blocks were sent by their proposer at the start of their slot.
+                    if let Some(arrival_offset) = arrival_offset
+                        && matches!(acceptance, MessageAcceptance::Accept)
+                    {
+                        self.network_state.record_block_arrival_offset(arrival_offset);
+                    }
+                    self.report_gossip_validation(message_id, propagation_source, acceptance);
+                }
             }
         }
     }
@@ -351,30 +554,88 @@ impl LeanNetworkService {
             SwarmEvent::Behaviour(ReamBehaviourEvent::ReqResp(req_resp_event)) => {
                 self.handle_request_response_event(req_resp_event).await
             }
+            SwarmEvent::Behaviour(ReamBehaviourEvent::Identify(identify::Event::Received {
+                peer_id,
+                info,
+                ..
+            })) => {
+                self.handle_observed_address(peer_id, info.observed_addr);
+                None
+            }
+            SwarmEvent::Behaviour(ReamBehaviourEvent::Upnp(upnp_event)) => {
+                match upnp_event {
+                    upnp::Event::NewExternalAddr(address) => {
+                        info!("UPnP mapped external address {address}");
+                        self.add_external_address(address);
+                    }
+                    upnp::Event::ExpiredExternalAddr(address) => {
+                        warn!("UPnP mapping of external address {address} expired");
+                        self.remove_external_address(&address);
+                    }
+                    upnp::Event::GatewayNotFound => warn!("UPnP gateway not found"),
+                    upnp::Event::NonRoutableGateway => {
+                        warn!("UPnP gateway is not exposed to the public network")
+                    }
+                }
+                None
+            }
             SwarmEvent::ConnectionEstablished {
                 peer_id, endpoint, ..
             } => {
+                if self.banned_peers.contains(&peer_id) {
+                    if let Err(err) = self.swarm.disconnect_peer_id(peer_id) {
+                        warn!("Failed to disconnect banned peer: {err:?}");
+                    }
+                    return None;
+                }
+
                 let (address, direction) = match endpoint {
                     ConnectedPoint::Dialer { address, .. } => {
                         self.bootnode_retry_state.remove(&peer_id);
-
-                        if lean_network_spec().is_devnet_enabled(Devnet::Two) {
-                            // send status request to the peer
-                            let status_message = LeanRequestMessage::Status(self.our_status());
-                            self.send_request(peer_id, status_message);
-                        }
                         (address, Direction::Outbound)
                     }
                     ConnectedPoint::Listener { send_back_addr, .. } => {
                         (send_back_addr, Direction::Inbound)
                     }
                 };
+                if self.is_over_peer_limit(peer_id, direction) {
+                    inc_int_counter_vec(
+                        &PEERS_DISCONNECTED_TOTAL,
+                        &[&format!("{direction}_limit")],
+                    );
+                    if let Err(err) = self.swarm.disconnect_peer_id(peer_id) {
+                        warn!("Failed to disconnect peer over the peer limit: {err:?}");
+                    }
+                    return None;
+                }
+                if direction == Direction::Inbound
+                    && let Some(limit) = self.exceeded_address_limit(peer_id, &address)
+                {
+                    inc_int_counter_vec(&INBOUND_CONNECTIONS_REJECTED_TOTAL, &[&limit.to_string()]);
+                    debug!(
+                        ?peer_id,
+                        "Refusing inbound peer from {address} over the {limit} limit"
+                    );
+                    if let Err(err) = self.swarm.disconnect_peer_id(peer_id) {
+                        warn!("Failed to disconnect peer over the {limit} limit: {err:?}");
+                    }
+                    return None;
+                }
                 self.network_state.upsert_peer(
                     peer_id,
                     Some(address),
                     ConnectionState::Connected,
                     direction,
                 );
+                self.network_state
+                    .set_peer_trusted(&peer_id, self.trusted_peers.contains_key(&peer_id));
+
+                // Exchange statuses with every new peer, whichever side dialed, so a peer which
+                // connects mid-slot learns our head right away and can fetch it with BlocksByRoot.
+                if lean_network_spec().is_devnet_enabled(Devnet::Two) {
+                    let status_message = LeanRequestMessage::Status(self.our_status());
+                    self.send_request(peer_id, status_message);
+                }
 
                 info!(
                     "Connected to peer: {peer_id:?} {:?}",
@@ -396,9 +657,24 @@ impl LeanNetworkService {
                     direction,
                 );
 
+                if let Some(address) = self.trusted_peers.get(&peer_id) {
+                    self.bootnode_retry_state
+                        .insert(peer_id, (0, vec![address.clone()]));
+                }
+
+                self.block_lookups.on_peer_disconnected(&peer_id);
+
                 info!("Disconnected from peer: {peer_id:?}");
                 Some(ReamNetworkEvent::PeerDisconnected(peer_id))
             }
+            SwarmEvent::NewListenAddr { address, .. } => {
+                if let Some(local_node) = self.network_state.local_node.write().as_mut()
+                    && !local_node.listen_addresses.contains(&address)
+                {
+                    local_node.listen_addresses.push(address);
+                }
+                None
+            }
             SwarmEvent::IncomingConnection { local_addr, .. } => {
                 info!("Incoming connection from {local_addr:?}");
                 None
@@ -408,6 +684,7 @@ impl LeanNetworkService {
                 Some(ReamNetworkEvent::PeerConnectedOutgoing(peer_id?))
             }
             SwarmEvent::OutgoingConnectionError { peer_id, error, .. } => {
+                inc_int_counter_vec(&DIAL_FAILURES_TOTAL, &[]);
                 warn!("Failed to connect to {peer_id:?}: {error:?}");
                 None
             }
@@ -416,39 +693,138 @@ impl LeanNetworkService {
     }
 
     fn handle_gossipsub_event(&mut self, event: GossipsubEvent) -> Option<ReamNetworkEvent> {
-        if let GossipsubEvent::Message { message, .. } = event {
-            match LeanGossipsubMessage::decode(&message.topic, &message.data) {
+        if let GossipsubEvent::Message {
+            propagation_source,
+            message_id,
+            message,
+        } = event
+        {
+            let topic = LeanGossipTopic::from_topic_hash(&message.topic)
+                .map(|topic| topic.kind.to_string())
+                .unwrap_or_else(|_| "unknown".to_string());
+            inc_int_counter_vec(&GOSSIP_MESSAGES_RECEIVED_TOTAL, &[&topic]);
+
+            // Gossipsub forwards the message once the chain service accepts it, so it isn't
+            // gossiped again.
+            let (validation, receiver) = oneshot::channel();
+            let mut arrival_offset = None;
+            let acceptance = match LeanGossipsubMessage::decode(&message.topic, &message.data) {
                 Ok(LeanGossipsubMessage::Block(signed_block_with_attestation)) => {
                     let slot = signed_block_with_attestation.message.block.slot;
-
-                    if let Err(err) =
-                        self.chain_message_sender
-                            .send(LeanChainServiceMessage::ProcessBlock {
-                                signed_block_with_attestation,
-                                need_gossip: true,
-                            })
-                    {
-                        warn!("failed to send block for slot {slot} item to chain: {err:?}");
+                    arrival_offset = block_arrival_offset(slot);
+
+                    match self
+                        .chain_message_sender
+                        .send(LeanChainServiceMessage::ProcessBlock {
+                            signed_block_with_attestation,
+                            need_gossip: false,
+                            peer_id: Some(propagation_source),
+                            validation: Some(validation),
+                        }) {
+                        Ok(()) => None,
+                        Err(err) => {
+                            warn!("failed to send block for slot {slot} item to chain: {err:?}");
+                            Some(MessageAcceptance::Ignore)
+                        }
                     }
                 }
                 Ok(LeanGossipsubMessage::Attestation(signed_attestation)) => {
                     let slot = signed_attestation.message.slot();
 
-                    if let Err(err) = self.chain_message_sender.send(
+                    match self.chain_message_sender.send(
                         LeanChainServiceMessage::ProcessAttestation {
                             signed_attestation,
-                            need_gossip: true,
+                            need_gossip: false,
+                            from_peer: true,
+                            validation: Some(validation),
+                        },
+                    ) {
+                        Ok(()) => None,
+                        Err(err) => {
+                            warn!("failed to send attestation for slot {slot} to chain: {err:?}");
+                            Some(MessageAcceptance::Ignore)
+                        }
+                    }
+                }
+                Ok(LeanGossipsubMessage::BlobSidecar(blob_sidecar)) => {
+                    let block_root = blob_sidecar.block_root;
+
+                    match self.chain_message_sender.send(
+                        LeanChainServiceMessage::ProcessBlobSidecar {
+                            blob_sidecar,
+                            need_gossip: false,
+                            validation: Some(validation),
                         },
                     ) {
-                        warn!("failed to send attestation for slot {slot} to chain: {err:?}");
+                        Ok(()) => None,
+                        Err(err) => {
+                            warn!(
+                                "failed to send blob sidecar of block {block_root} to chain: {err:?}"
+                            );
+                            Some(MessageAcceptance::Ignore)
+                        }
                     }
                 }
-                Err(err) => warn!("Failed to decode {:?} gossip topic: {err:?}", message.topic),
+                // Light client updates aren't signed, so nothing shows that a peer's update is the
+                // one of the canonical chain. They are served over the API only, and never relayed.
+                Ok(LeanGossipsubMessage::LightClientFinalityUpdate(update)) => {
+                    debug!(
+                        slot = update.attested_header.slot,
+                        "Ignoring light client finality update"
+                    );
+                    Some(MessageAcceptance::Ignore)
+                }
+                Ok(LeanGossipsubMessage::LightClientOptimisticUpdate(update)) => {
+                    debug!(
+                        slot = update.attested_header.slot,
+                        "Ignoring light client optimistic update"
+                    );
+                    Some(MessageAcceptance::Ignore)
+                }
+                Err(err) => {
+                    warn!("Failed to decode {:?} gossip topic: {err:?}", message.topic);
+                    Some(MessageAcceptance::Reject)
+                }
+            };
+
+            match acceptance {
+                Some(acceptance) => {
+                    self.report_gossip_validation(message_id, propagation_source, acceptance)
+                }
+                None => self.gossip_validations.push(
+                    receiver
+                        .map(move |result| (message_id, propagation_source, arrival_offset, result))
+                        .boxed(),
+                ),
             }
         }
         None
     }
 
+    /// Tells gossipsub whether to forward a message received from `propagation_source`, and
+    /// penalizes the peer if the message is rejected.
+    fn report_gossip_validation(
+        &mut self,
+        message_id: MessageId,
+        propagation_source: PeerId,
+        acceptance: MessageAcceptance,
+    ) {
+        if matches!(acceptance, MessageAcceptance::Reject) {
+            self.penalize_peer(propagation_source, INVALID_GOSSIP_PENALTY);
+        }
+        if !self
+            .swarm
+            .behaviour_mut()
+            .gossipsub
+            .report_message_validation_result(&message_id, &propagation_source, acceptance)
+        {
+            debug!(
+                ?message_id,
+                "Gossip message is no longer awaiting validation"
+            );
+        }
+    }
+
     async fn handle_request_response_event(
         &mut self,
         message: ReqRespMessage,
@@ -471,11 +847,39 @@ impl LeanNetworkService {
         let message = match message {
             Ok(message) => message,
             Err(err) => {
+                inc_int_counter_vec(&REQ_RESP_REQUESTS_TOTAL, &["unknown", "error"]);
                 warn!(
                     ?peer_id,
                     ?connection_id,
                     "Failed to parse req/resp message from peer: {err:?}"
                 );
+                if let ReqRespMessageError::Outbound { request_id, .. } = &err {
+                    self.handle_block_lookup_failure(*request_id);
+                }
+                match err {
+                    ReqRespMessageError::Inbound {
+                        err: ReqRespError::LimitExceeded(_),
+                        ..
+                    }
+                    | ReqRespMessageError::Outbound {
+                        err: ReqRespError::LimitExceeded(_),
+                        ..
+                    } => self.penalize_peer(peer_id, LIMIT_EXCEEDED_PENALTY),
+                    ReqRespMessageError::Outbound {
+                        request_id,
+                        err: ReqRespError::ErrorResponse { code, .. },
+                    } => {
+                        if code.is_retryable() {
+                            debug!(?peer_id, request_id, ?code, "Request can be retried later");
+                        } else if matches!(
+                            code,
+                            ResponseCode::ReservedCode(_) | ResponseCode::ErroneousCode(_)
+                        ) {
+                            self.penalize_peer(peer_id, INVALID_RESPONSE_CODE_PENALTY);
+                        }
+                    }
+                    _ => {}
+                }
                 return None;
             }
         };
@@ -483,6 +887,10 @@ impl LeanNetworkService {
         match message {
             ReqRespMessageReceived::Request { stream_id, message } => {
                 if let RequestMessage::Lean(message) = *message {
+                    inc_int_counter_vec(
+                        &REQ_RESP_REQUESTS_TOTAL,
+                        &[message.protocol().message_name(), "received"],
+                    );
                     match message {
                         LeanRequestMessage::Status(status) => {
                             trace!(
@@ -510,6 +918,52 @@ impl LeanNetworkService {
                                 message: LeanRequestMessage::Status(status),
                             })
                         }
+                        LeanRequestMessage::BlocksByRoot(request) => {
+                            trace!(
+                                ?peer_id,
+                                ?stream_id,
+                                ?connection_id,
+                                roots = request.inner.len(),
+                                "Received BlocksByRoot request"
+                            );
+
+                            self.handle_blocks_by_root_request(
+                                peer_id,
+                                connection_id,
+                                stream_id,
+                                &request,
+                            );
+
+                            Some(ReamNetworkEvent::RequestMessage {
+                                peer_id,
+                                stream_id,
+                                connection_id,
+                                message: LeanRequestMessage::BlocksByRoot(request),
+                            })
+                        }
+                        LeanRequestMessage::BlobsByRoot(request) => {
+                            trace!(
+                                ?peer_id,
+                                ?stream_id,
+                                ?connection_id,
+                                identifiers = request.inner.len(),
+                                "Received BlobsByRoot request"
+                            );
+
+                            self.handle_blobs_by_root_request(
+                                peer_id,
+                                connection_id,
+                                stream_id,
+                                &request,
+                            );
+
+                            Some(ReamNetworkEvent::RequestMessage {
+                                peer_id,
+                                stream_id,
+                                connection_id,
+                                message: LeanRequestMessage::BlobsByRoot(request),
+                            })
+                        }
                         _ => Some(ReamNetworkEvent::RequestMessage {
                             peer_id,
                             stream_id,
@@ -530,6 +984,10 @@ impl LeanNetworkService {
                 message,
             } => {
                 if let ResponseMessage::Lean(response_message) = *message {
+                    inc_int_counter_vec(
+                        &REQ_RESP_REQUESTS_TOTAL,
+                        &[response_message.protocol().message_name(), "success"],
+                    );
                     if let LeanResponseMessage::Status(status) = *response_message {
                         trace!(
                             ?peer_id,
@@ -540,6 +998,8 @@ impl LeanNetworkService {
                         );
 
                         self.handle_status_response(peer_id, status);
+                    } else if let LeanResponseMessage::BlocksByRoot(block) = &*response_message {
+                        self.handle_block_lookup_response(peer_id, request_id, block.clone());
                     }
                 } else {
                     warn!(
@@ -550,8 +1010,173 @@ impl LeanNetworkService {
 
                 None
             }
-            ReqRespMessageReceived::EndOfStream { .. } => None,
+            ReqRespMessageReceived::EndOfStream { request_id } => {
+                // A lookup still in flight when its stream ends didn't get the block.
+                self.handle_block_lookup_failure(request_id);
+                None
+            }
+        }
+    }
+
+    fn handle_p2p_request(&mut self, request: LeanP2PRequest) {
+        match request {
+            LeanP2PRequest::GossipBlock(signed_block) => {
+                let slot = signed_block.message.block.slot;
+                match self.publish(LeanGossipTopicKind::Block, signed_block.as_ssz_bytes()) {
+                    Ok(()) => info!(slot, "Broadcasted block"),
+                    Err(err) => warn!(slot, error = ?err, "Publish block failed"),
+                }
+            }
+            LeanP2PRequest::GossipAttestation(signed_attestation) => {
+                let slot = signed_attestation.message.slot();
+                match self.publish(
+                    LeanGossipTopicKind::Attestation,
+                    signed_attestation.as_ssz_bytes(),
+                ) {
+                    Ok(()) => info!(slot, "Broadcasted attestation"),
+                    Err(err) => warn!(slot, error = ?err, "Publish attestation failed"),
+                }
+            }
+            LeanP2PRequest::GossipBlobSidecar(blob_sidecar) => {
+                let block_root = blob_sidecar.block_root;
+                let index = blob_sidecar.index;
+                match self.publish(
+                    LeanGossipTopicKind::BlobSidecar,
+                    blob_sidecar.as_ssz_bytes(),
+                ) {
+                    Ok(()) => info!(?block_root, index, "Broadcasted blob sidecar"),
+                    Err(err) => {
+                        warn!(?block_root, index, error = ?err, "Publish blob sidecar failed")
+                    }
+                }
+            }
+            LeanP2PRequest::AddTrustedPeer(address) => self.add_trusted_peer(address),
+            LeanP2PRequest::RemoveTrustedPeer(peer_id) => {
+                if self.trusted_peers.remove(&peer_id).is_some() {
+                    self.bootnode_retry_state.remove(&peer_id);
+                    self.network_state.set_peer_trusted(&peer_id, false);
+                    info!(?peer_id, "Removed trusted peer");
+                }
+            }
+            LeanP2PRequest::BanPeer(peer_id) => self.ban_peer(peer_id),
+            LeanP2PRequest::ChainServiceStopped => self.chain_service_stopped = true,
+            LeanP2PRequest::RequestBlock(root) => {
+                if self.block_lookups.start(root) {
+                    self.send_block_lookup(root);
+                }
+            }
+            #[cfg(feature = "testing")]
+            LeanP2PRequest::SetNetworkFaults { peer_id, faults } => {
+                warn!(?peer_id, ?faults, "Injecting network faults");
+                self.fault_injector.set(peer_id, faults);
+            }
+            #[cfg(not(feature = "testing"))]
+            LeanP2PRequest::SetNetworkFaults { .. } => {
+                warn!("Ignoring network faults, the node isn't built with the testing feature");
+            }
+        }
+    }
+
+    /// Drops or holds back the gossip and req/resp messages of peers as the injected network
+    /// faults say, and returns the events to handle right away.
+    #[cfg(feature = "testing")]
+    fn inject_faults(
+        &mut self,
+        event: SwarmEvent<ReamBehaviourEvent>,
+    ) -> Option<SwarmEvent<ReamBehaviourEvent>> {
+        let peer_id = match &event {
+            SwarmEvent::Behaviour(ReamBehaviourEvent::Gossipsub(GossipsubEvent::Message {
+                propagation_source,
+                ..
+            })) => *propagation_source,
+            SwarmEvent::Behaviour(ReamBehaviourEvent::ReqResp(message)) => message.peer_id,
+            _ => return Some(event),
+        };
+        match self.fault_injector.verdict(&peer_id) {
+            fault_injection::Verdict::Deliver => Some(event),
+            fault_injection::Verdict::Drop => {
+                trace!(?peer_id, "Dropped message by the injected network faults");
+                None
+            }
+            fault_injection::Verdict::Delay(delay) => {
+                self.delayed_events
+                    .push(Box::pin(tokio::time::sleep(delay).map(move |()| event)));
+                None
+            }
+        }
+    }
+
+    fn publish(&mut self, kind: LeanGossipTopicKind, data: Vec<u8>) -> anyhow::Result<()> {
+        let topic = self
+            .network_config
+            .gossipsub_config
+            .topics
+            .iter()
+            .find(|topic| topic.kind == kind)
+            .map(|topic| {
+                IdentTopic::from(LeanGossipTopic {
+                    fork: self.topic_forks[0].clone(),
+                    kind: topic.kind,
+                })
+            })
+            .ok_or_else(|| anyhow!("{kind} topic isn't configured"))?;
+        self.swarm
+            .behaviour_mut()
+            .gossipsub
+            .publish(topic, data)
+            .map_err(|err| anyhow!("{err:?}"))?;
+        inc_int_counter_vec(&GOSSIP_MESSAGES_SENT_TOTAL, &[&kind.to_string()]);
+        Ok(())
+    }
+
+    /// Publishes the gossip still queued by the chain service, then disconnects every peer and
+    /// keeps driving the swarm until they are gone, or [SHUTDOWN_FLUSH_TIMEOUT] passes.
+    async fn shutdown(&mut self) {
+        info!("LeanNetworkService shutting down");
+
+        let flush_timeout = match self.chain_service_stopped {
+            true => Duration::ZERO,
+            false => SHUTDOWN_FLUSH_TIMEOUT,
+        };
+        let requests = drain_outbound_requests(&mut self.outbound_p2p_request, flush_timeout).await;
+        let published = requests.iter().any(|request| {
+            matches!(
+                request,
+                LeanP2PRequest::GossipBlock(_)
+                    | LeanP2PRequest::GossipAttestation(_)
+                    | LeanP2PRequest::GossipBlobSidecar(_)
+            )
+        });
+        for request in requests {
+            self.handle_p2p_request(request);
+        }
+        // Gossipsub only writes to the connections while the swarm is driven.
+        if published && self.swarm.connected_peers().next().is_some() {
+            let _ = timeout(SHUTDOWN_GOSSIP_FLUSH_TIMEOUT, async {
+                loop {
+                    if let Some(event) = self.swarm.next().await {
+                        self.parse_swarm_event(event).await;
+                    }
+                }
+            })
+            .await;
+        }
+
+        let connected_peers = self.swarm.connected_peers().copied().collect::<Vec<_>>();
+        for peer_id in connected_peers {
+            if let Err(err) = self.swarm.disconnect_peer_id(peer_id) {
+                warn!("Failed to disconnect peer on shutdown: {err:?}");
+            }
         }
+        let _ = timeout(SHUTDOWN_FLUSH_TIMEOUT, async {
+            while self.swarm.connected_peers().next().is_some() {
+                if let Some(event) = self.swarm.next().await {
+                    self.parse_swarm_event(event).await;
+                }
+            }
+        })
+        .await;
+        info!("LeanNetworkService stopped");
     }
 
     async fn connect_to_bootnodes(&mut self, peers: Vec<Multiaddr>) {
@@ -561,35 +1186,292 @@ impl LeanNetworkService {
                 .iter()
                 .find(|protocol| matches!(protocol, Protocol::P2p(_)))
                 && peer_id != self.local_peer_id()
+                && !self.banned_peers.contains(&peer_id)
             {
-                match self.bootnode_retry_state.remove(&peer_id) {
-                    Some((attempts, mut addresses)) => {
-                        addresses.push(peer.clone());
-                        self.bootnode_retry_state
-                            .insert(peer_id, (attempts, addresses));
-                    }
-                    None => self
-                        .bootnode_retry_state
-                        .insert(peer_id, (0, vec![peer.clone()])),
-                };
+                self.connect_to_peer(peer_id, peer);
+            }
+        }
+    }
 
-                if let Err(err) = self.dial_peer(peer.clone()) {
-                    warn!("Failed to dial peer: {err:?}");
-                    continue;
-                }
+    /// Trusted peers are redialed whenever they disconnect, for as long as it takes, and are
+    /// never banned or disconnected for their score.
+    fn add_trusted_peer(&mut self, address: Multiaddr) {
+        let Some(Protocol::P2p(peer_id)) = address
+            .iter()
+            .find(|protocol| matches!(protocol, Protocol::P2p(_)))
+        else {
+            warn!("Trusted peer address {address} has no peer id");
+            return;
+        };
+        if self.banned_peers.contains(&peer_id) {
+            warn!(?peer_id, "Refusing to trust a banned peer");
+            return;
+        }
 
-                info!("Dialing peer: {peer_id:?}");
-                self.network_state.upsert_peer(
-                    peer_id,
-                    Some(peer),
-                    ConnectionState::Connecting,
-                    Direction::Outbound,
-                );
+        info!(?peer_id, "Added trusted peer {address}");
+        self.trusted_peers.insert(peer_id, address.clone());
+        if !self.swarm.is_connected(&peer_id) {
+            self.connect_to_peer(peer_id, address);
+        }
+        self.network_state.set_peer_trusted(&peer_id, true);
+    }
+
+    fn ban_peer(&mut self, peer_id: PeerId) {
+        if self.trusted_peers.contains_key(&peer_id) {
+            warn!(
+                ?peer_id,
+                "Refusing to ban a trusted peer, remove it from the trusted peers first"
+            );
+            return;
+        }
+
+        info!(?peer_id, "Banned peer");
+        self.banned_peers.insert(peer_id);
+        self.bootnode_retry_state.remove(&peer_id);
+        if self.swarm.is_connected(&peer_id)
+            && let Err(err) = self.swarm.disconnect_peer_id(peer_id)
+        {
+            warn!("Failed to disconnect banned peer: {err:?}");
+        }
+    }
+
+    /// Counts the address a peer saw us connect from, and advertises it once peers in enough
+    /// networks agree on it. Peers behind the same NAT as us see a private address, so only
+    /// public ones count.
+    fn handle_observed_address(&mut self, peer_id: PeerId, address: Multiaddr) {
+        let Some((ip, _)) = ip_and_udp_port(&address) else {
+            return;
+        };
+        let Some((observer_ip, _)) = self
+            .network_state
+            .cached_peer(&peer_id)
+            .and_then(|peer| peer.last_seen_p2p_address)
+            .as_ref()
+            .and_then(ip_and_udp_port)
+        else {
+            return;
+        };
+        if !is_public_ip(ip)
+            || self
+                .network_state
+                .local_node
+                .read()
+                .as_ref()
+                .is_some_and(|local_node| local_node.external_addresses.contains(&address))
+        {
+            return;
+        }
+
+        if self
+            .observed_addresses
+            .observe(address.clone(), observer_ip)
+        {
+            info!("Peers observed external address {address}");
+            self.add_external_address(address);
+        }
+    }
+
+    /// Advertises `address` to peers through identify and in our ENR.
+    fn add_external_address(&mut self, address: Multiaddr) {
+        self.swarm.add_external_address(address.clone());
+        if let Some(local_node) = self.network_state.local_node.write().as_mut()
+            && !local_node.external_addresses.contains(&address)
+        {
+            local_node.external_addresses.push(address.clone());
+        }
+        self.update_local_enr(&address);
+    }
+
+    /// Stops advertising `address`, pointing our ENR back at the latest external address left,
+    /// or at the address we listen on.
+    fn remove_external_address(&mut self, address: &Multiaddr) {
+        self.swarm.remove_external_address(address);
+        let fallback = match self.network_state.local_node.write().as_mut() {
+            Some(local_node) => {
+                local_node
+                    .external_addresses
+                    .retain(|external_address| external_address != address);
+                local_node.external_addresses.last().cloned()
+            }
+            None => None,
+        };
+        self.update_local_enr(&fallback.unwrap_or_else(|| self.multi_addr.clone()));
+    }
+
+    /// Points our ENR at `address`, bumping its sequence number so peers replace the old one.
+    fn update_local_enr(&mut self, address: &Multiaddr) {
+        let Some((ip, port)) = ip_and_udp_port(address) else {
+            return;
+        };
+        let enr_ip = match ip {
+            IpAddr::V4(_) => self.local_enr.ip4().map(IpAddr::V4),
+            IpAddr::V6(_) => self.local_enr.ip6().map(IpAddr::V6),
+        };
+        if enr_ip == Some(ip)
+            && self
+                .local_enr
+                .get_decodable::<u16>(QUIC_ENR_KEY)
+                .and_then(Result::ok)
+                == Some(port)
+        {
+            return;
+        }
+
+        if let Err(err) = self.local_enr.set_ip(ip, &self.enr_key) {
+            warn!("Failed to set the IP address of our ENR: {err:?}");
+            return;
+        }
+        if let Err(err) = self.local_enr.insert(QUIC_ENR_KEY, &port, &self.enr_key) {
+            warn!("Failed to set the QUIC port of our ENR: {err:?}");
+            return;
+        }
+        info!(seq = self.local_enr.seq(), "Updated ENR to {address}");
+        if let Some(local_node) = self.network_state.local_node.write().as_mut() {
+            local_node.enr = self.local_enr.to_base64();
+        }
+    }
+
+    /// Lowers the score of a misbehaving peer, disconnecting it once the score drops to
+    /// [MIN_PEER_SCORE].
+    fn penalize_peer(&mut self, peer_id: PeerId, penalty: i32) {
+        let Some(score) = self.network_state.penalize_peer(&peer_id, penalty) else {
+            return;
+        };
+        warn!(?peer_id, score, "Penalized peer");
+        if score <= MIN_PEER_SCORE
+            && !self.trusted_peers.contains_key(&peer_id)
+            && self.swarm.is_connected(&peer_id)
+        {
+            inc_int_counter_vec(&PEERS_DISCONNECTED_TOTAL, &["score"]);
+            if let Err(err) = self.swarm.disconnect_peer_id(peer_id) {
+                warn!("Failed to disconnect peer with a low score: {err:?}");
+            }
+        }
+    }
+
+    /// Whether a newly connected peer would take us over the peer limit of its direction. Trusted
+    /// peers and further connections of an already connected peer are always let through.
+    fn is_over_peer_limit(&self, peer_id: PeerId, direction: Direction) -> bool {
+        let Some(max_peers) = self.network_config.peer_limits.max_peers(direction) else {
+            return false;
+        };
+        if self.trusted_peers.contains_key(&peer_id) {
+            return false;
+        }
+
+        let peer_table = self.network_state.peer_table.lock();
+        if peer_table
+            .get(&peer_id)
+            .is_some_and(|peer| peer.state == ConnectionState::Connected)
+        {
+            return false;
+        }
+        peer_table
+            .values()
+            .filter(|peer| peer.state == ConnectionState::Connected && peer.direction == direction)
+            .count()
+            >= max_peers
+    }
+
+    /// Which limit on the addresses of inbound peers a newly connected inbound peer from `address`
+    /// would exceed. Only public addresses are limited, so local devnets and hosts sharing a
+    /// private network aren't refused. Trusted peers and further connections of an already
+    /// connected peer are always let through.
+    fn exceeded_address_limit(&self, peer_id: PeerId, address: &Multiaddr) -> Option<AddressLimit> {
+        let (ip, _) = ip_and_udp_port(address)?;
+        if !is_public_ip(ip) || self.trusted_peers.contains_key(&peer_id) {
+            return None;
+        }
+
+        let peer_table = self.network_state.peer_table.lock();
+        if peer_table
+            .get(&peer_id)
+            .is_some_and(|peer| peer.state == ConnectionState::Connected)
+        {
+            return None;
+        }
+        self.network_config.peer_limits.exceeded_address_limit(
+            ip,
+            peer_table
+                .values()
+                .filter(|peer| {
+                    peer.state == ConnectionState::Connected && peer.direction == Direction::Inbound
+                })
+                .filter_map(|peer| peer.last_seen_p2p_address.as_ref())
+                .filter_map(ip_and_udp_port)
+                .map(|(ip, _)| ip),
+        )
+    }
+
+    /// Disconnects the worst peers once more than the target number of peers are connected, see
+    /// [PeerLimits::peers_to_prune].
+    fn prune_peers(&mut self) {
+        let peers = self
+            .network_state
+            .peer_table
+            .lock()
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        let head_slot = self.network_state.head_checkpoint.read().slot;
+        for peer_id in self
+            .network_config
+            .peer_limits
+            .peers_to_prune(&peers, head_slot)
+        {
+            info!(?peer_id, "Pruning peer");
+            inc_int_counter_vec(&PEERS_DISCONNECTED_TOTAL, &["pruned"]);
+            if let Err(err) = self.swarm.disconnect_peer_id(peer_id) {
+                warn!("Failed to disconnect pruned peer: {err:?}");
             }
         }
     }
 
+    /// Dials `address` and redials it through the bootnode retry queue if that fails.
+    fn connect_to_peer(&mut self, peer_id: PeerId, address: Multiaddr) {
+        match self.bootnode_retry_state.remove(&peer_id) {
+            Some((attempts, mut addresses)) => {
+                addresses.push(address.clone());
+                self.bootnode_retry_state
+                    .insert(peer_id, (attempts, addresses));
+            }
+            None => self
+                .bootnode_retry_state
+                .insert(peer_id, (0, vec![address.clone()])),
+        };
+
+        if let Err(err) = self.dial_peer(address.clone()) {
+            warn!("Failed to dial peer: {err:?}");
+            return;
+        }
+
+        info!("Dialing peer: {peer_id:?}");
+        self.network_state.upsert_peer(
+            peer_id,
+            Some(address),
+            ConnectionState::Connecting,
+            Direction::Outbound,
+        );
+    }
+
     pub fn handle_status_response(&mut self, peer_id: PeerId, status: Status) {
+        self.network_state
+            .update_peer_status(&peer_id, status.head, status.finalized);
+
+        // Both sides send their status on connecting, so whichever is behind fetches the head of
+        // the other one here, which serves it with BlocksByRoot. Gossipsub wouldn't deliver the
+        // head block again, it drops a message it has already seen.
+        if is_head_catch_up(*self.network_state.head_checkpoint.read(), status.head)
+            && self.block_lookups.start(status.head.root)
+        {
+            debug!(
+                ?peer_id,
+                head_slot = status.head.slot,
+                "Fetching the head of a peer a few slots ahead"
+            );
+            self.send_block_lookup(status.head.root);
+        }
+
         if !lean_network_spec().is_devnet_enabled(Devnet::Two) {
             return;
         }
@@ -618,18 +1500,213 @@ impl LeanNetworkService {
         }
     }
 
+    /// Asks the chain service for the requested blocks, which are sent to the peer once they
+    /// arrive, skipping the ones we don't have.
+    ///
+    /// This is how a peer which is a few slots behind catches up on our head, see
+    /// [Self::handle_status_response].
+    fn handle_blocks_by_root_request(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        stream_id: u64,
+        request: &BlocksByRootV1Request,
+    ) {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(err) = self
+            .chain_message_sender
+            .send(LeanChainServiceMessage::GetBlocksByRoot {
+                roots: request.inner.to_vec(),
+                sender,
+            })
+        {
+            warn!(?peer_id, "Failed to send GetBlocksByRoot request: {err:?}");
+            self.send_error_response(
+                peer_id,
+                connection_id,
+                stream_id,
+                ResponseCode::ServerError,
+                "Chain service is unavailable",
+            );
+            return;
+        }
+        self.by_root_futures.push(
+            receiver
+                .map(move |result| {
+                    let responses = result.map(|blocks| {
+                        blocks
+                            .into_iter()
+                            .map(LeanResponseMessage::BlocksByRoot)
+                            .collect()
+                    });
+                    (peer_id, connection_id, stream_id, responses)
+                })
+                .boxed(),
+        );
+    }
+
+    /// Asks the chain service for the requested blob sidecars, which are sent to the peer once
+    /// they arrive, skipping the ones we don't have.
+    fn handle_blobs_by_root_request(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        stream_id: u64,
+        request: &BlobsByRootV1Request,
+    ) {
+        let (sender, receiver) = oneshot::channel();
+        if let Err(err) =
+            self.chain_message_sender
+                .send(LeanChainServiceMessage::GetBlobSidecarsByRoot {
+                    identifiers: request.inner.to_vec(),
+                    sender,
+                })
+        {
+            warn!(
+                ?peer_id,
+                "Failed to send GetBlobSidecarsByRoot request: {err:?}"
+            );
+            self.send_error_response(
+                peer_id,
+                connection_id,
+                stream_id,
+                ResponseCode::ServerError,
+                "Chain service is unavailable",
+            );
+            return;
+        }
+        self.by_root_futures.push(
+            receiver
+                .map(move |result| {
+                    let responses = result.map(|blob_sidecars| {
+                        blob_sidecars
+                            .into_iter()
+                            .map(LeanResponseMessage::BlobsByRoot)
+                            .collect()
+                    });
+                    (peer_id, connection_id, stream_id, responses)
+                })
+                .boxed(),
+        );
+    }
+
+    /// Sends the next attempt of the lookup of `root`, preferring the peers we would sync from.
+    fn send_block_lookup(&mut self, root: B256) {
+        let peers = self
+            .network_state
+            .sync_peers()
+            .into_iter()
+            .map(|peer| peer.peer_id)
+            .collect::<Vec<_>>();
+        if let Some(peer_id) = self.block_lookups.next_peer(root, &peers)
+            && let RequestResult::Success(request_id) = self.send_request(
+                peer_id,
+                LeanRequestMessage::BlocksByRoot(BlocksByRootV1Request::new(vec![root])),
+            )
+        {
+            debug!(?peer_id, ?root, request_id, "Looking up block");
+            self.block_lookups
+                .on_request_sent(root, peer_id, request_id);
+            return;
+        }
+
+        match self.block_lookups.on_no_peer(root) {
+            Some(retry_in) => debug!(?root, ?retry_in, "No peer to look up block from"),
+            None => {
+                warn!(
+                    ?root,
+                    "Giving up looking up block, no peer to look it up from"
+                );
+                self.give_up_block_lookup(root);
+            }
+        }
+    }
+
+    fn handle_block_lookup_response(
+        &mut self,
+        peer_id: PeerId,
+        request_id: u64,
+        signed_block_with_attestation: Arc<SignedBlockWithAttestation>,
+    ) {
+        let root = signed_block_with_attestation.message.block.tree_hash_root();
+        if !self.block_lookups.on_block(request_id, root) {
+            return;
+        }
+
+        if let Err(err) = self
+            .chain_message_sender
+            .send(LeanChainServiceMessage::ProcessBlock {
+                signed_block_with_attestation: Box::new(Arc::unwrap_or_clone(
+                    signed_block_with_attestation,
+                )),
+                need_gossip: false,
+                peer_id: Some(peer_id),
+                validation: None,
+            })
+        {
+            warn!(?root, "Failed to send looked up block to chain: {err:?}");
+        }
+    }
+
+    /// Retries the lookup of `request_id`, if it was one, or gives it up after too many attempts.
+    fn handle_block_lookup_failure(&mut self, request_id: u64) {
+        let Some(LookupFailure {
+            root,
+            peer_id,
+            penalize,
+            retry_in,
+        }) = self.block_lookups.on_request_failed(request_id)
+        else {
+            return;
+        };
+
+        if penalize {
+            self.penalize_peer(peer_id, LOOKUP_FAILURE_PENALTY);
+        }
+        match retry_in {
+            Some(retry_in) => debug!(?peer_id, ?root, ?retry_in, "Block lookup failed, retrying"),
+            None => {
+                warn!(
+                    ?root,
+                    "Giving up looking up block after {MAX_LOOKUP_ATTEMPTS} attempts"
+                );
+                self.give_up_block_lookup(root);
+            }
+        }
+    }
+
+    /// Tells the chain service that `root` won't be fetched, so the blocks waiting for it are
+    /// dropped and a later block building on it starts a new lookup.
+    fn give_up_block_lookup(&mut self, root: B256) {
+        if let Err(err) = self
+            .chain_message_sender
+            .send(LeanChainServiceMessage::BlockLookupFailed { root })
+        {
+            warn!(
+                ?root,
+                "Failed to send failed block lookup to chain: {err:?}"
+            );
+        }
+    }
+
     pub fn local_peer_id(&self) -> PeerId {
         *self.swarm.local_peer_id()
     }
 
     fn dial_peer(&mut self, peer_addr: Multiaddr) -> anyhow::Result<()> {
-        self.swarm
-            .dial(peer_addr.clone())
-            .map_err(|err| anyhow!("Failed to dial peer at address {peer_addr:?}, error: {err:?}"))
+        self.swarm.dial(peer_addr.clone()).map_err(|err| {
+            inc_int_counter_vec(&DIAL_FAILURES_TOTAL, &[]);
+            anyhow!("Failed to dial peer at address {peer_addr:?}, error: {err:?}")
+        })
     }
 
     fn send_request(&mut self, peer_id: PeerId, message: LeanRequestMessage) -> RequestResult<u64> {
+        let protocol = message.protocol();
         if !self.swarm.is_connected(&peer_id) {
+            inc_int_counter_vec(
+                &REQ_RESP_REQUESTS_TOTAL,
+                &[protocol.message_name(), "not_connected"],
+            );
             return RequestResult::NotConnected;
         }
 
@@ -639,6 +1716,7 @@ impl LeanNetworkService {
             request_id,
             RequestMessage::Lean(message),
         );
+        inc_int_counter_vec(&REQ_RESP_REQUESTS_TOTAL, &[protocol.message_name(), "sent"]);
 
         RequestResult::Success(request_id)
     }
@@ -662,6 +1740,73 @@ impl LeanNetworkService {
         );
     }
 
+    /// Answers a request with an error response, which also closes its stream.
+    fn send_error_response(
+        &mut self,
+        peer_id: PeerId,
+        connection_id: ConnectionId,
+        stream_id: u64,
+        code: ResponseCode,
+        message: &str,
+    ) {
+        self.swarm.behaviour_mut().req_resp.send_response(
+            peer_id,
+            connection_id,
+            stream_id,
+            RespMessage::Error(ReqRespError::error_response(code, message)),
+        );
+    }
+
+    fn send_end_of_stream(&mut self, peer_id: PeerId, connection_id: ConnectionId, stream_id: u64) {
+        self.swarm.behaviour_mut().req_resp.send_response(
+            peer_id,
+            connection_id,
+            stream_id,
+            RespMessage::EndOfStream,
+        );
+    }
+
+    /// Subscribes to the topics of a scheduled fork shortly before it activates and unsubscribes
+    /// from the topics of the previous fork shortly after, see [subscribed_topic_forks].
+    fn update_topic_forks(&mut self) {
+        let topic_forks = current_topic_forks(self.network_config.genesis_root);
+        if topic_forks == self.topic_forks {
+            return;
+        }
+
+        info!(
+            old_topic_forks = ?self.topic_forks,
+            new_topic_forks = ?topic_forks,
+            "Switching gossip topics around a fork"
+        );
+        let gossipsub = &mut self.swarm.behaviour_mut().gossipsub;
+        for topic in &self.network_config.gossipsub_config.topics {
+            for fork in topic_forks
+                .iter()
+                .filter(|fork| !self.topic_forks.contains(fork))
+            {
+                let new_topic = LeanGossipTopic {
+                    fork: fork.clone(),
+                    kind: topic.kind,
+                };
+                if let Err(err) = gossipsub.subscribe(&IdentTopic::from(new_topic.clone())) {
+                    warn!("subscribe to {new_topic} failed: {err:?}");
+                }
+            }
+            for fork in self
+                .topic_forks
+                .iter()
+                .filter(|fork| !topic_forks.contains(fork))
+            {
+                gossipsub.unsubscribe(&IdentTopic::from(LeanGossipTopic {
+                    fork: fork.clone(),
+                    kind: topic.kind,
+                }));
+            }
+        }
+        self.topic_forks = topic_forks;
+    }
+
     fn our_status(&self) -> Status {
         Status {
             finalized: *self.network_state.finalized_checkpoint.read(),
@@ -675,24 +1820,126 @@ impl LeanNetworkService {
     }
 }
 
+/// Whether `peer_head` is few enough slots ahead of our `head` to be fetched right away.
+fn is_head_catch_up(head: Checkpoint, peer_head: Checkpoint) -> bool {
+    peer_head.slot > head.slot && peer_head.slot - head.slot <= MAX_HEAD_CATCH_UP_SLOTS
+}
+
+/// The forks whose topics are subscribed to at the current slot on the chain starting at
+/// `genesis_root`.
+fn current_topic_forks(genesis_root: B256) -> Vec<String> {
+    let network_spec = lean_network_spec();
+    subscribed_topic_forks(&network_spec, genesis_root, network_spec.current_slot())
+}
+
+/// How many milliseconds after the start of `slot`, by the local clock, a block of it arrives
+/// now, or `None` if that's a slot or more away, as for old blocks.
+fn block_arrival_offset(slot: u64) -> Option<i64> {
+    let network_spec = lean_network_spec();
+    let slot_millis = network_spec.seconds_per_slot * 1000;
+    let slot_start_millis = network_spec
+        .genesis_time
+        .checked_mul(1000)?
+        .checked_add(slot.checked_mul(slot_millis)?)?;
+    let now_millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_millis() as u64;
+    let offset_millis = now_millis as i64 - slot_start_millis as i64;
+    (offset_millis.unsigned_abs() < slot_millis).then_some(offset_millis)
+}
+
 enum RequestResult<T> {
     Success(T),
     NotConnected,
 }
 
+/// The libp2p identity key as the key signing our ENR.
+pub fn enr_signing_key(local_key: &Keypair) -> anyhow::Result<CombinedKey> {
+    let secp256k1_key = local_key
+        .clone()
+        .try_into_secp256k1()
+        .map_err(|err| anyhow!("Failed to get secp256k1 keypair: {err:?}"))?;
+    let signing_key = SigningKey::from_slice(&secp256k1_key.secret().to_bytes())
+        .map_err(|err| anyhow!("Failed to convert keypair to SigningKey: {err:?}"))?;
+    Ok(CombinedKey::Secp256k1(signing_key))
+}
+
+/// Builds the ENR other lean clients expect in their bootnode lists: our IP address and the QUIC
+/// port, signed with the libp2p identity key.
+pub fn build_local_enr(enr_key: &CombinedKey, ip: IpAddr, quic_port: u16) -> anyhow::Result<Enr> {
+    Enr::builder()
+        .ip(ip)
+        .add_value(QUIC_ENR_KEY, &quic_port)
+        .build(enr_key)
+        .map_err(|err| anyhow!("Failed to build ENR: {err}"))
+}
+
+/// The IP address and UDP port of a QUIC multiaddr.
+fn ip_and_udp_port(address: &Multiaddr) -> Option<(IpAddr, u16)> {
+    let mut ip = None;
+    let mut port = None;
+    for protocol in address.iter() {
+        match protocol {
+            Protocol::Ip4(ip4) => ip = Some(IpAddr::V4(ip4)),
+            Protocol::Ip6(ip6) => ip = Some(IpAddr::V6(ip6)),
+            Protocol::Udp(udp_port) => port = Some(udp_port),
+            _ => {}
+        }
+    }
+    Some((ip?, port?))
+}
+
+fn is_public_ip(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip4) => {
+            !(ip4.is_private()
+                || ip4.is_loopback()
+                || ip4.is_link_local()
+                || ip4.is_unspecified()
+                || ip4.is_broadcast()
+                || ip4.is_documentation())
+        }
+        IpAddr::V6(ip6) => {
+            !(ip6.is_loopback()
+                || ip6.is_unspecified()
+                || ip6.is_unique_local()
+                || ip6.is_unicast_link_local())
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use std::{net::Ipv4Addr, time::Duration};
+    use std::{
+        net::Ipv4Addr,
+        time::{Duration, Instant},
+    };
 
     use ream_network_spec::networks::{LeanNetworkSpec, set_lean_network_spec};
     use ream_peer::Direction;
+    use tempfile::TempDir;
     use tokio::sync::mpsc;
     use tracing_test::traced_test;
 
     use super::*;
     use crate::bootnodes::Bootnodes;
 
-    pub async fn setup_lean_node(socket_port: u16) -> anyhow::Result<LeanNetworkService> {
+    pub async fn setup_lean_node(
+        socket_port: u16,
+    ) -> anyhow::Result<(LeanNetworkService, ShutdownSignal)> {
+        let (node, shutdown, _chain_receiver) = setup_lean_node_with_chain(socket_port).await?;
+        Ok((node, shutdown))
+    }
+
+    /// Sets up a node along with the receiver of the messages it sends the chain service.
+    pub async fn setup_lean_node_with_chain(
+        socket_port: u16,
+    ) -> anyhow::Result<(
+        LeanNetworkService,
+        ShutdownSignal,
+        mpsc::UnboundedReceiver<LeanChainServiceMessage>,
+    )> {
         set_lean_network_spec(LeanNetworkSpec::ephemery().into());
 
         let executor = ReamExecutor::new().expect("Failed to create executor");
@@ -701,10 +1948,16 @@ mod tests {
             socket_address: Ipv4Addr::new(127, 0, 0, 1).into(),
             socket_port,
             private_key_path: None,
+            network_key_path: None,
+            trusted_peers: vec![],
+            peer_limits: PeerLimits::default(),
+            enable_upnp: false,
+            genesis_root: B256::ZERO,
         });
-        let (sender, _receiver) = mpsc::unbounded_channel::<LeanChainServiceMessage>();
+        let (sender, receiver) = mpsc::unbounded_channel::<LeanChainServiceMessage>();
         let (_outbound_request_sender_unused, outbound_request_receiver) =
             mpsc::unbounded_channel::<LeanP2PRequest>();
+        let shutdown = executor.graceful_shutdown_signal();
         let node = LeanNetworkService::new(
             config.clone(),
             executor,
@@ -713,7 +1966,7 @@ mod tests {
             Arc::new(NetworkState::new(Default::default(), Default::default())),
         )
         .await?;
-        Ok(node)
+        Ok((node, shutdown, receiver))
     }
 
     #[tokio::test]
@@ -722,8 +1975,8 @@ mod tests {
         let socket_port1 = 9000;
         let socket_port2 = 9001;
 
-        let mut node_1 = setup_lean_node(socket_port1).await?;
-        let mut node_2 = setup_lean_node(socket_port2).await?;
+        let (mut node_1, shutdown_1) = setup_lean_node(socket_port1).await?;
+        let (mut node_2, shutdown_2) = setup_lean_node(socket_port2).await?;
 
         let peer_id_network_1 = node_1.local_peer_id();
         let peer_id_network_2 = node_2.local_peer_id();
@@ -735,14 +1988,14 @@ mod tests {
 
         let node_1_handle = tokio::spawn(async move {
             let bootnodes = Bootnodes::Default;
-            node_1.start(bootnodes).await.unwrap();
+            node_1.start(bootnodes, shutdown_1).await.unwrap();
         });
 
         tokio::time::sleep(Duration::from_millis(100)).await;
 
         let node_2_handle = tokio::spawn(async move {
             let bootnodes = Bootnodes::Multiaddr(vec![node_1_addr]);
-            node_2.start(bootnodes).await.unwrap();
+            node_2.start(bootnodes, shutdown_2).await.unwrap();
         });
 
         tokio::time::sleep(Duration::from_secs(2)).await;
@@ -765,4 +2018,247 @@ mod tests {
 
         Ok(())
     }
+
+    #[tokio::test]
+    async fn test_report_gossip_validation() -> anyhow::Result<()> {
+        let (mut node, _shutdown) = setup_lean_node(9002).await?;
+        let peer_id = PeerId::random();
+        node.network_state.upsert_peer(
+            peer_id,
+            None,
+            ConnectionState::Connected,
+            Direction::Inbound,
+        );
+        let score = |node: &LeanNetworkService| {
+            node.network_state
+                .cached_peer(&peer_id)
+                .expect("peer exists")
+                .score
+        };
+
+        // Messages which may still turn out valid don't count against the peer.
+        let message_id = MessageId::new(b"message");
+        node.report_gossip_validation(message_id.clone(), peer_id, MessageAcceptance::Accept);
+        node.report_gossip_validation(message_id.clone(), peer_id, MessageAcceptance::Ignore);
+        assert_eq!(score(&node), 0);
+
+        node.report_gossip_validation(message_id.clone(), peer_id, MessageAcceptance::Reject);
+        node.report_gossip_validation(message_id, peer_id, MessageAcceptance::Reject);
+        assert_eq!(score(&node), -2 * INVALID_GOSSIP_PENALTY);
+
+        // Unknown peers are left alone.
+        node.report_gossip_validation(
+            MessageId::new(b"other"),
+            PeerId::random(),
+            MessageAcceptance::Reject,
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_observed_addresses_update_enr() -> anyhow::Result<()> {
+        let (mut node, _shutdown) = setup_lean_node(9003).await?;
+        let enr_address = |node: &LeanNetworkService| {
+            (
+                node.local_enr.ip4(),
+                node.local_enr
+                    .get_decodable::<u16>(QUIC_ENR_KEY)
+                    .and_then(Result::ok),
+            )
+        };
+        let observe = |node: &mut LeanNetworkService, observer: &str, address: &Multiaddr| {
+            let peer_id = PeerId::random();
+            node.network_state.upsert_peer(
+                peer_id,
+                Some(format!("/ip4/{observer}/udp/9000/quic-v1").parse().unwrap()),
+                ConnectionState::Connected,
+                Direction::Inbound,
+            );
+            node.handle_observed_address(peer_id, address.clone());
+        };
+        let first: Multiaddr = "/ip4/203.0.113.1/udp/9100/quic-v1".parse()?;
+        let second: Multiaddr = "/ip4/203.0.113.2/udp/9200/quic-v1".parse()?;
+
+        // Peers in a single network can't decide our address.
+        for observer in ["198.51.100.1", "198.51.100.2", "198.51.100.3"] {
+            observe(&mut node, observer, &first);
+        }
+        assert_eq!(
+            enr_address(&node),
+            (Some(Ipv4Addr::new(127, 0, 0, 1)), Some(9003))
+        );
+        for observer in ["198.51.101.1", "198.51.102.1"] {
+            observe(&mut node, observer, &first);
+        }
+        assert_eq!(
+            enr_address(&node),
+            (Some(Ipv4Addr::new(203, 0, 113, 1)), Some(9100))
+        );
+
+        // Once a mapping expires, the ENR goes back to the address left, and then to the one we
+        // listen on.
+        node.add_external_address(second.clone());
+        assert_eq!(
+            enr_address(&node),
+            (Some(Ipv4Addr::new(203, 0, 113, 2)), Some(9200))
+        );
+        node.remove_external_address(&second);
+        assert_eq!(
+            enr_address(&node),
+            (Some(Ipv4Addr::new(203, 0, 113, 1)), Some(9100))
+        );
+        node.remove_external_address(&first);
+        assert_eq!(
+            enr_address(&node),
+            (Some(Ipv4Addr::new(127, 0, 0, 1)), Some(9003))
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_head_catch_up() {
+        let checkpoint = |slot| Checkpoint {
+            root: B256::with_last_byte(slot as u8),
+            slot,
+        };
+        assert!(is_head_catch_up(checkpoint(10), checkpoint(11)));
+        assert!(is_head_catch_up(
+            checkpoint(10),
+            checkpoint(10 + MAX_HEAD_CATCH_UP_SLOTS)
+        ));
+        // Peers behind fetch our head themselves, peers far ahead are synced from.
+        assert!(!is_head_catch_up(checkpoint(10), checkpoint(10)));
+        assert!(!is_head_catch_up(checkpoint(10), checkpoint(9)));
+        assert!(!is_head_catch_up(
+            checkpoint(10),
+            checkpoint(11 + MAX_HEAD_CATCH_UP_SLOTS)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_status_fetches_peer_head() -> anyhow::Result<()> {
+        let (mut node, _shutdown) = setup_lean_node(9004).await?;
+        *node.network_state.head_checkpoint.write() = Checkpoint {
+            root: B256::repeat_byte(10),
+            slot: 10,
+        };
+        let status = |slot: u64| Status {
+            finalized: Checkpoint::default(),
+            head: Checkpoint {
+                root: B256::repeat_byte(slot as u8),
+                slot,
+            },
+        };
+
+        // The lookup of the head is started, so it can't be started again.
+        node.handle_status_response(PeerId::random(), status(12));
+        assert!(!node.block_lookups.start(B256::repeat_byte(12)));
+
+        for slot in [8, 10, 11 + MAX_HEAD_CATCH_UP_SLOTS] {
+            node.handle_status_response(PeerId::random(), status(slot));
+            assert!(node.block_lookups.start(B256::repeat_byte(slot as u8)));
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_blocks_by_root_request() -> anyhow::Result<()> {
+        let (mut node, _shutdown, mut chain_receiver) = setup_lean_node_with_chain(9005).await?;
+        let peer_id = PeerId::random();
+        let roots = vec![B256::repeat_byte(1), B256::repeat_byte(2)];
+        let request = |node: &mut LeanNetworkService, stream_id| {
+            node.handle_blocks_by_root_request(
+                peer_id,
+                ConnectionId::new_unchecked(0),
+                stream_id,
+                &BlocksByRootV1Request::new(roots.clone()),
+            )
+        };
+
+        // The chain service is asked for the blocks, and its answer goes to the stream.
+        request(&mut node, 1);
+        let Some(LeanChainServiceMessage::GetBlocksByRoot {
+            roots: requested,
+            sender,
+        }) = chain_receiver.recv().await
+        else {
+            panic!("Expected a GetBlocksByRoot message");
+        };
+        assert_eq!(requested, roots);
+        sender.send(vec![]).expect("receiver is alive");
+        let (served_peer_id, _, stream_id, responses) = node
+            .by_root_futures
+            .next()
+            .await
+            .expect("response is queued");
+        assert_eq!((served_peer_id, stream_id), (peer_id, 1));
+        assert!(responses.expect("chain service answered").is_empty());
+
+        // A request the chain service drops fails, so the peer gets an error response.
+        request(&mut node, 2);
+        drop(chain_receiver.recv().await);
+        let (_, _, stream_id, responses) = node
+            .by_root_futures
+            .next()
+            .await
+            .expect("response is queued");
+        assert_eq!(stream_id, 2);
+        assert!(responses.is_err());
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_drain_outbound_requests() {
+        let (sender, mut receiver) = mpsc::unbounded_channel();
+        sender
+            .send(LeanP2PRequest::RequestBlock(B256::repeat_byte(1)))
+            .unwrap();
+        sender.send(LeanP2PRequest::ChainServiceStopped).unwrap();
+        sender
+            .send(LeanP2PRequest::RequestBlock(B256::repeat_byte(2)))
+            .unwrap();
+
+        // The sender is still alive, like the API's, but the chain service stopped, so the drain
+        // doesn't wait for the timeout. Requests queued after the stop are still taken.
+        let started = Instant::now();
+        let requests = drain_outbound_requests(&mut receiver, Duration::from_secs(10)).await;
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert!(matches!(
+            requests.as_slice(),
+            [
+                LeanP2PRequest::RequestBlock(first),
+                LeanP2PRequest::RequestBlock(second),
+            ] if *first == B256::repeat_byte(1) && *second == B256::repeat_byte(2)
+        ));
+        assert!(sender.send(LeanP2PRequest::ChainServiceStopped).is_err());
+
+        // Without the chain service saying it stopped, the drain gives up after the timeout.
+        let (_sender, mut receiver) = mpsc::unbounded_channel();
+        assert!(
+            drain_outbound_requests(&mut receiver, Duration::from_millis(10))
+                .await
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_network_key_is_reused() -> anyhow::Result<()> {
+        let dir = TempDir::new()?;
+        let path = dir.path().join("network").join("key");
+
+        let generated = load_or_generate_network_key(&path)?;
+        let loaded = load_or_generate_network_key(&path)?;
+        assert_eq!(generated.public(), loaded.public());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+        }
+
+        fs::remove_file(&path)?;
+        let regenerated = load_or_generate_network_key(&path)?;
+        assert_ne!(generated.public(), regenerated.public());
+        Ok(())
+    }
 }
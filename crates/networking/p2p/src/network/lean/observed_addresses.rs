@@ -0,0 +1,134 @@
+//! The addresses peers report seeing us at through identify, until enough of them agree on one
+//! to advertise it.
+//!
+//! Observations are counted per network of the observing peer rather than per peer, so a few
+//! colluding peers running in the same network can't decide the address we advertise.
+
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use libp2p::Multiaddr;
+
+/// How many peers in different networks must observe the same address of ours before it is
+/// advertised.
+pub const OBSERVED_ADDRESS_CONFIRMATIONS: usize = 3;
+
+/// Once this many different addresses are tracked, the least observed one is forgotten for a new
+/// one, so peers reporting junk can't grow the table without bound.
+pub const MAX_OBSERVED_ADDRESSES: usize = 32;
+
+/// The network a peer at `ip` is counted in: its /24 for IPv4 and its /48 for IPv6.
+fn network_of(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip4) => IpAddr::V4(Ipv4Addr::from(ip4.to_bits() & !0xff)),
+        IpAddr::V6(ip6) => IpAddr::V6(Ipv6Addr::from(ip6.to_bits() & !((1u128 << 80) - 1))),
+    }
+}
+
+#[derive(Debug)]
+struct Observation {
+    /// The networks of the peers which observed the address.
+    networks: HashSet<IpAddr>,
+    /// When the address was first observed, in observations since the start.
+    first_observed: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ObservedAddresses {
+    addresses: HashMap<Multiaddr, Observation>,
+    observations: u64,
+}
+
+impl ObservedAddresses {
+    /// Counts that a peer at `observer_ip` saw us at `address`. Returns true once peers in
+    /// [OBSERVED_ADDRESS_CONFIRMATIONS] different networks did, and forgets the address then.
+    pub fn observe(&mut self, address: Multiaddr, observer_ip: IpAddr) -> bool {
+        self.observations += 1;
+        if !self.addresses.contains_key(&address)
+            && self.addresses.len() >= MAX_OBSERVED_ADDRESSES
+            && let Some(least_observed) = self
+                .addresses
+                .iter()
+                .min_by_key(|(_, observation)| {
+                    (observation.networks.len(), observation.first_observed)
+                })
+                .map(|(address, _)| address.clone())
+        {
+            self.addresses.remove(&least_observed);
+        }
+
+        let first_observed = self.observations;
+        let observation = self
+            .addresses
+            .entry(address.clone())
+            .or_insert_with(|| Observation {
+                networks: HashSet::new(),
+                first_observed,
+            });
+        observation.networks.insert(network_of(observer_ip));
+        if observation.networks.len() < OBSERVED_ADDRESS_CONFIRMATIONS {
+            return false;
+        }
+        self.addresses.remove(&address);
+        true
+    }
+
+    pub fn len(&self) -> usize {
+        self.addresses.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.addresses.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use libp2p::Multiaddr;
+
+    use super::{MAX_OBSERVED_ADDRESSES, OBSERVED_ADDRESS_CONFIRMATIONS, ObservedAddresses};
+
+    fn address(last_byte: u8) -> Multiaddr {
+        format!("/ip4/203.0.113.{last_byte}/udp/9000/quic-v1")
+            .parse()
+            .expect("valid multiaddr")
+    }
+
+    fn observer(network: u8, host: u8) -> IpAddr {
+        IpAddr::V4(Ipv4Addr::new(198, 51, network, host))
+    }
+
+    #[test]
+    fn test_confirmations_need_different_networks() {
+        let mut observed_addresses = ObservedAddresses::default();
+
+        // Any number of peers in the same network count once.
+        for host in 1..=10 {
+            assert!(!observed_addresses.observe(address(1), observer(1, host)));
+        }
+        for network in 2..OBSERVED_ADDRESS_CONFIRMATIONS as u8 {
+            assert!(!observed_addresses.observe(address(1), observer(network, 1)));
+        }
+        assert!(observed_addresses.observe(address(1), observer(100, 1)));
+        // A confirmed address is forgotten.
+        assert!(observed_addresses.is_empty());
+    }
+
+    #[test]
+    fn test_least_observed_address_is_evicted() {
+        let mut observed_addresses = ObservedAddresses::default();
+        observed_addresses.observe(address(0), observer(1, 1));
+        observed_addresses.observe(address(0), observer(2, 1));
+        for last_byte in 1..MAX_OBSERVED_ADDRESSES as u8 + 10 {
+            observed_addresses.observe(address(last_byte), observer(3, 1));
+        }
+        assert_eq!(observed_addresses.len(), MAX_OBSERVED_ADDRESSES);
+
+        // The address more peers observed survives the junk and is still confirmed.
+        assert!(observed_addresses.observe(address(0), observer(4, 1)));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use libp2p_identity::PeerId;
+use ream_network_state_lean::cached_peer::CachedPeer;
+use ream_peer::{ConnectionState, Direction};
+
+use crate::constants::TARGET_PEER_COUNT;
+
+pub const DEFAULT_MAX_INBOUND_PEERS: usize = 40;
+pub const DEFAULT_MAX_OUTBOUND_PEERS: usize = 20;
+pub const DEFAULT_MAX_INBOUND_PEERS_PER_IP: usize = 2;
+pub const DEFAULT_MAX_INBOUND_PEERS_PER_SUBNET: usize = 8;
+
+/// How many peers the node keeps connected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PeerLimits {
+    /// Pruning disconnects peers until this many are left.
+    pub target_peers: usize,
+    /// Peers connecting to us once this many inbound peers are connected are refused.
+    pub max_inbound_peers: usize,
+    /// Peers we dial once this many outbound peers are connected are refused.
+    pub max_outbound_peers: usize,
+    /// Peers connecting to us from an IP address this many inbound peers are connected from are
+    /// refused, so a single host can't take all of our inbound slots.
+    pub max_inbound_peers_per_ip: usize,
+    /// Like [Self::max_inbound_peers_per_ip], for the /24 of IPv4 addresses and the /64 of IPv6
+    /// addresses.
+    pub max_inbound_peers_per_subnet: usize,
+}
+
+/// The limit on the addresses of inbound peers a new inbound peer exceeds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AddressLimit {
+    Ip,
+    Subnet,
+}
+
+impl fmt::Display for AddressLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ip => write!(f, "ip"),
+            Self::Subnet => write!(f, "subnet"),
+        }
+    }
+}
+
+impl Default for PeerLimits {
+    fn default() -> Self {
+        Self {
+            target_peers: TARGET_PEER_COUNT,
+            max_inbound_peers: DEFAULT_MAX_INBOUND_PEERS,
+            max_outbound_peers: DEFAULT_MAX_OUTBOUND_PEERS,
+            max_inbound_peers_per_ip: DEFAULT_MAX_INBOUND_PEERS_PER_IP,
+            max_inbound_peers_per_subnet: DEFAULT_MAX_INBOUND_PEERS_PER_SUBNET,
+        }
+    }
+}
+
+impl PeerLimits {
+    pub fn max_peers(&self, direction: Direction) -> Option<usize> {
+        match direction {
+            Direction::Inbound => Some(self.max_inbound_peers),
+            Direction::Outbound => Some(self.max_outbound_peers),
+            Direction::Unknown => None,
+        }
+    }
+
+    /// Returns the limit a new inbound peer connecting from `ip` exceeds, given the IP addresses
+    /// the inbound peers are connected from.
+    pub fn exceeded_address_limit(
+        &self,
+        ip: IpAddr,
+        inbound_ips: impl IntoIterator<Item = IpAddr>,
+    ) -> Option<AddressLimit> {
+        let (mut same_ip, mut same_subnet) = (0, 0);
+        for inbound_ip in inbound_ips {
+            if inbound_ip == ip {
+                same_ip += 1;
+            }
+            if subnet(inbound_ip) == subnet(ip) {
+                same_subnet += 1;
+            }
+        }
+
+        if same_ip >= self.max_inbound_peers_per_ip {
+            Some(AddressLimit::Ip)
+        } else if same_subnet >= self.max_inbound_peers_per_subnet {
+            Some(AddressLimit::Subnet)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the connected peers to disconnect to get down to [Self::target_peers], the lowest
+    /// scoring first and the longest silent among equal scores. Trusted peers and peers whose
+    /// head is ahead of `head_slot`, which we may need to sync from, are never pruned.
+    pub fn peers_to_prune(&self, peers: &[CachedPeer], head_slot: u64) -> Vec<PeerId> {
+        let connected_peers = peers
+            .iter()
+            .filter(|peer| peer.state == ConnectionState::Connected)
+            .collect::<Vec<_>>();
+        let Some(excess) = connected_peers.len().checked_sub(self.target_peers) else {
+            return vec![];
+        };
+
+        let mut candidates = connected_peers
+            .into_iter()
+            .filter(|peer| {
+                !peer.trusted
+                    && peer
+                        .head_checkpoint
+                        .is_none_or(|checkpoint| checkpoint.slot <= head_slot)
+            })
+            .collect::<Vec<_>>();
+        candidates.sort_by_key(|peer| (peer.score, peer.last_seen));
+        candidates
+            .into_iter()
+            .take(excess)
+            .map(|peer| peer.peer_id)
+            .collect()
+    }
+}
+
+/// The /24 of an IPv4 address or the /64 of an IPv6 address.
+fn subnet(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip4) => IpAddr::V4(Ipv4Addr::from(ip4.to_bits() & !0xff)),
+        IpAddr::V6(ip6) => IpAddr::V6(Ipv6Addr::from(ip6.to_bits() & !(u64::MAX as u128))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ream_consensus_lean::checkpoint::Checkpoint;
+
+    use super::*;
+
+    #[test]
+    fn test_peers_to_prune() {
+        let peer = |score: i32, trusted: bool, head_slot: u64| {
+            let mut peer = CachedPeer::new(
+                PeerId::random(),
+                None,
+                ConnectionState::Connected,
+                Direction::Inbound,
+            );
+            peer.score = score;
+            peer.trusted = trusted;
+            peer.head_checkpoint = Some(Checkpoint {
+                slot: head_slot,
+                ..Default::default()
+            });
+            peer
+        };
+        let peers = vec![
+            peer(-50, true, 10),
+            peer(-40, false, 20),
+            peer(-30, false, 10),
+            peer(-20, false, 10),
+            peer(0, false, 10),
+        ];
+        let limits = PeerLimits {
+            target_peers: 3,
+            ..Default::default()
+        };
+
+        // The trusted peer and the peer ahead of us are kept although they score the lowest.
+        assert_eq!(
+            limits.peers_to_prune(&peers, 10),
+            vec![peers[2].peer_id, peers[3].peer_id]
+        );
+        assert!(PeerLimits::default().peers_to_prune(&peers, 10).is_empty());
+    }
+
+    #[test]
+    fn test_exceeded_address_limit() {
+        let ip = |last_octet: u8| IpAddr::V4(Ipv4Addr::new(1, 2, 3, last_octet));
+        let limits = PeerLimits {
+            max_inbound_peers_per_ip: 2,
+            max_inbound_peers_per_subnet: 3,
+            ..Default::default()
+        };
+
+        assert_eq!(limits.exceeded_address_limit(ip(1), [ip(1)]), None);
+        assert_eq!(
+            limits.exceeded_address_limit(ip(1), [ip(1), ip(1)]),
+            Some(AddressLimit::Ip)
+        );
+        assert_eq!(
+            limits.exceeded_address_limit(ip(1), [ip(1), ip(2), ip(3)]),
+            Some(AddressLimit::Subnet)
+        );
+        // Peers from other subnets don't count.
+        assert_eq!(
+            limits.exceeded_address_limit(
+                ip(1),
+                [ip(1), ip(2), IpAddr::V4(Ipv4Addr::new(1, 2, 4, 1))]
+            ),
+            None
+        );
+    }
+}
@@ -2,6 +2,8 @@ use std::io::{self};
 
 use ssz_types::{VariableList, typenum::U256};
 
+use super::inbound_protocol::ResponseCode;
+
 #[derive(thiserror::Error, Debug)]
 pub enum ReqRespError {
     #[error("IO error: {0}")]
@@ -13,6 +15,10 @@ pub enum ReqRespError {
     #[error("Invalid data {0}")]
     InvalidData(String),
 
+    /// The peer sent more than a protocol allows, in size or in number of chunks.
+    #[error("Limit exceeded {0}")]
+    LimitExceeded(String),
+
     #[error("Incomplete stream")]
     IncompleteStream,
 
@@ -27,17 +33,38 @@ pub enum ReqRespError {
 
     #[error("Raw error message {0}")]
     RawError(String),
+
+    /// An error response with an explicit code, answered by a peer or to answer a peer's request
+    /// with. The message is sent as is, without the code.
+    #[error("Error response {code:?}: {}", .message.as_deref().unwrap_or("no message"))]
+    ErrorResponse {
+        code: ResponseCode,
+        message: Option<String>,
+    },
 }
 
-impl From<ssz::DecodeError> for ReqRespError {
-    fn from(err: ssz::DecodeError) -> Self {
-        ReqRespError::InvalidData(format!("Failed to decode ssz: {err:?}"))
+impl ReqRespError {
+    pub fn error_response(code: ResponseCode, message: impl Into<String>) -> Self {
+        ReqRespError::ErrorResponse {
+            code,
+            message: Some(message.into()),
+        }
+    }
+
+    /// Builds the error a peer answered with `code` and the error message `message`. A message
+    /// which is empty or isn't valid UTF-8 is dropped.
+    pub fn from_peer_response(code: ResponseCode, message: VariableList<u8, U256>) -> Self {
+        ReqRespError::ErrorResponse {
+            code,
+            message: String::from_utf8(Vec::from(message))
+                .ok()
+                .filter(|message| !message.is_empty()),
+        }
     }
 }
 
-impl From<VariableList<u8, U256>> for ReqRespError {
-    fn from(err: VariableList<u8, U256>) -> Self {
-        let err = String::from_utf8(Vec::from(err)).unwrap_or("Invalid UTF-8".to_string());
-        ReqRespError::InvalidData(format!("ReqResp error message from peer: {err:?}"))
+impl From<ssz::DecodeError> for ReqRespError {
+    fn from(err: ssz::DecodeError) -> Self {
+        ReqRespError::InvalidData(format!("Failed to decode ssz: {err:?}"))
     }
 }
@@ -15,6 +15,7 @@ use libp2p::{
         ConnectionHandler, ConnectionHandlerEvent, StreamUpgradeError, SubstreamProtocol,
         handler::{
             ConnectionEvent, DialUpgradeError, FullyNegotiatedInbound, FullyNegotiatedOutbound,
+            ListenUpgradeError,
         },
     },
 };
@@ -64,10 +65,13 @@ impl RespMessage {
                 | ReqRespError::IncompleteStream
                 | ReqRespError::Anyhow(_)
                 | ReqRespError::IoError(_) => Some(ResponseCode::ServerError),
-                ReqRespError::InvalidData(_) => Some(ResponseCode::InvalidRequest),
+                ReqRespError::InvalidData(_) | ReqRespError::LimitExceeded(_) => {
+                    Some(ResponseCode::InvalidRequest)
+                }
                 ReqRespError::Disconnected
                 | ReqRespError::StreamTimedOut
                 | ReqRespError::TokioTimedOut(_) => Some(ResponseCode::ResourceUnavailable),
+                ReqRespError::ErrorResponse { code, .. } => Some(*code),
             },
             RespMessage::EndOfStream => None,
         }
@@ -203,6 +207,18 @@ impl ReqRespConnectionHandler {
         trace!("REQRESP: Dial upgrade error: {:?}", error);
     }
 
+    /// Reports requests which failed to decode, so the behaviour can tell peers sending invalid
+    /// requests apart.
+    fn on_listen_upgrade_error(&mut self, error: ReqRespError) {
+        trace!("REQRESP: Listen upgrade error: {:?}", error);
+        self.behaviour_events
+            .push(HandlerEvent::Err(ReqRespMessageError::Inbound {
+                stream_id: self.inbound_stream_id,
+                err: error,
+            }));
+        self.inbound_stream_id += 1;
+    }
+
     fn request(&mut self, request_id: u64, message: RequestMessage) {
         if let ConnectionState::Live = self.connection_state {
             self.pending_outbound_streams.push(OutboundOpenInfo {
@@ -570,6 +586,9 @@ impl ConnectionHandler for ReqRespConnectionHandler {
             ConnectionEvent::DialUpgradeError(DialUpgradeError { error, info }) => {
                 self.on_dial_upgrade_error(error, info);
             }
+            ConnectionEvent::ListenUpgradeError(ListenUpgradeError { error, .. }) => {
+                self.on_listen_upgrade_error(error);
+            }
             // ConnectionEvent is not exhaustive so we have to account for the default case
             _ => (),
         }
@@ -1,13 +1,13 @@
 use std::{
     future::Future,
-    io::{Cursor, Read, Write},
+    io::{Cursor, ErrorKind, Read, Write},
     pin::Pin,
     time::Duration,
 };
 
 use asynchronous_codec::BytesMut;
 use futures::{
-    FutureExt, StreamExt,
+    FutureExt, SinkExt, StreamExt,
     prelude::{AsyncRead, AsyncWrite},
 };
 use libp2p::{
@@ -19,7 +19,10 @@ use ream_consensus_misc::constants::beacon::genesis_validators_root;
 use ream_network_spec::networks::beacon_network_spec;
 use snap::{read::FrameDecoder, write::FrameEncoder};
 use ssz::{Decode, Encode};
-use ssz_types::{VariableList, typenum::U256};
+use ssz_types::{
+    VariableList,
+    typenum::{U256, Unsigned},
+};
 use tokio::time::timeout;
 use tokio_io_timeout::TimeoutStream;
 use tokio_util::{
@@ -37,6 +40,7 @@ use super::{
     handler::RespMessage,
 };
 use crate::{
+    constants::MAX_PAYLOAD_SIZE,
     req_resp::{
         Chain,
         beacon::messages::{
@@ -49,7 +53,8 @@ use crate::{
         error::ReqRespError,
         lean::{
             messages::{
-                LeanRequestMessage, blocks::BlocksByRootV1Request as LeanBlocksByRootV1Request,
+                LeanRequestMessage, blobs::BlobsByRootV1Request as LeanBlobsByRootV1Request,
+                blocks::BlocksByRootV1Request as LeanBlocksByRootV1Request,
                 status::Status as LeanStatus,
             },
             protocol_id::LeanSupportedProtocol,
@@ -57,7 +62,7 @@ use crate::{
         messages::RequestMessage,
         protocol_id::{ProtocolId, SupportedProtocol},
     },
-    utils::max_message_size,
+    utils::max_framed_compressed_len,
 };
 
 #[derive(Debug, Clone)]
@@ -89,6 +94,7 @@ where
                 Box::pin(timed_socket),
                 InboundSSZSnappyCodec {
                     protocol: info.clone(),
+                    length: None,
                 },
             );
 
@@ -101,6 +107,17 @@ where
                 )),
                 _ => match timeout(Duration::from_secs(15), socket.into_future()).await {
                     Ok((Some(Ok(message)), stream)) => Ok((message, stream)),
+                    // Tell the peer why its request was refused before closing the stream.
+                    Ok((Some(Err(err @ ReqRespError::LimitExceeded(_))), mut stream)) => {
+                        let message = RespMessage::Error(ReqRespError::LimitExceeded(
+                            "Request exceeds the protocol limits".to_string(),
+                        ));
+                        if let Err(send_err) = stream.send(message).await {
+                            debug!("Failed to send error response: {send_err:?}");
+                        }
+                        let _ = stream.close().await;
+                        Err(err)
+                    }
                     Ok((Some(Err(err)), _)) => Err(err),
                     Ok((None, _)) => Err(ReqRespError::IncompleteStream),
                     Err(err) => Err(ReqRespError::from(err)),
@@ -124,6 +141,7 @@ impl UpgradeInfo for InboundReqRespProtocol {
 #[derive(Debug)]
 pub struct InboundSSZSnappyCodec {
     protocol: ProtocolId,
+    length: Option<usize>,
 }
 
 impl Encoder<RespMessage> for InboundSSZSnappyCodec {
@@ -137,7 +155,11 @@ impl Encoder<RespMessage> for InboundSSZSnappyCodec {
         let bytes = match item {
             RespMessage::Response(messages) => messages.as_ssz_bytes(),
             RespMessage::Error(req_resp_error) => {
-                VariableList::<u8, U256>::try_from(req_resp_error.to_string().as_bytes().to_vec())
+                let message = match req_resp_error {
+                    ReqRespError::ErrorResponse { message, .. } => message.unwrap_or_default(),
+                    req_resp_error => req_resp_error.to_string(),
+                };
+                VariableList::<u8, U256>::try_from(error_message_bytes(&message))
                     .map_err(|err| {
                         ReqRespError::InvalidData(format!(
                             "Failed to convert error code to variable list {err:?}",
@@ -150,11 +172,10 @@ impl Encoder<RespMessage> for InboundSSZSnappyCodec {
 
         // The length-prefix is within the expected size bounds derived from the payload SSZ type or
         // MAX_PAYLOAD_SIZE, whichever is smaller.
-        if bytes.len() > max_message_size() as usize {
+        if bytes.len() as u64 > MAX_PAYLOAD_SIZE {
             return Err(ReqRespError::Anyhow(anyhow::anyhow!(
-                "Message size exceeds maximum: {} > {}",
-                bytes.len(),
-                max_message_size()
+                "Message size exceeds maximum: {} > {MAX_PAYLOAD_SIZE}",
+                bytes.len()
             )));
         }
 
@@ -186,16 +207,33 @@ impl Decoder for InboundSSZSnappyCodec {
             )));
         }
 
-        let length = match Uvi::<usize>::default().decode(src)? {
-            Some(length) => length,
-            None => return Ok(None),
+        let length = match self.length {
+            Some(cached_length) => cached_length,
+            None => {
+                let decoded_length = match Uvi::<usize>::default().decode(src)? {
+                    Some(decoded_length) => decoded_length,
+                    None => return Ok(None),
+                };
+                *self.length.get_or_insert(decoded_length)
+            }
         };
 
+        // The length-prefix is within the expected size bounds derived from the payload SSZ
+        // type or MAX_PAYLOAD_SIZE, whichever is smaller. It is checked before allocating the
+        // buffer or decompressing anything, so a peer can't make us do either for a huge payload.
+        let max_length = self.protocol.protocol.max_request_size();
+        if length as u64 > max_length {
+            return Err(ReqRespError::LimitExceeded(format!(
+                "Request size exceeds maximum: {length} > {max_length}"
+            )));
+        }
+
         let mut decoder = FrameDecoder::new(Cursor::new(&src));
         let mut buf: Vec<u8> = vec![0; length];
         let result = match decoder.read_exact(&mut buf) {
             Ok(_) => {
                 src.advance(decoder.get_ref().position() as usize);
+                self.length = None;
                 match self.protocol.protocol {
                     SupportedProtocol::Beacon(beacon_supported_protocol) => {
                         let request_message = match beacon_supported_protocol {
@@ -251,11 +289,28 @@ impl Decoder for InboundSSZSnappyCodec {
                                         .map_err(ReqRespError::from)?,
                                 )
                             }
+                            LeanSupportedProtocol::BlobsByRootV1 => {
+                                LeanRequestMessage::BlobsByRoot(
+                                    LeanBlobsByRootV1Request::from_ssz_bytes(&buf)
+                                        .map_err(ReqRespError::from)?,
+                                )
+                            }
                         };
                         Ok(Some(RequestMessage::Lean(request_message)))
                     }
                 }
             }
+            // Wait for the rest of the request, unless the peer already sent more compressed bytes
+            // than a payload of this length can take.
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => {
+                if decoder.get_ref().position() <= max_framed_compressed_len(length as u64) {
+                    Ok(None)
+                } else {
+                    Err(ReqRespError::LimitExceeded(format!(
+                        "Compressed request is bigger than its length allows: {err:?}"
+                    )))
+                }
+            }
             Err(err) => Err(ReqRespError::from(err)),
         };
 
@@ -267,16 +322,40 @@ impl Decoder for InboundSSZSnappyCodec {
     }
 }
 
+/// Truncates an error message to the most bytes an error response carries, on a character
+/// boundary so it stays valid UTF-8.
+fn error_message_bytes(message: &str) -> Vec<u8> {
+    let mut length = message.len().min(U256::to_usize());
+    while !message.is_char_boundary(length) {
+        length -= 1;
+    }
+    message.as_bytes()[..length].to_vec()
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ResponseCode {
     Success,
     InvalidRequest,
     ServerError,
     ResourceUnavailable,
+    /// The peer refused the request because we sent too many, the code libp2p clients use for it.
+    RateLimited,
     ReservedCode(u8),
     ErroneousCode(u8),
 }
 
+impl ResponseCode {
+    /// Whether the request may still succeed if it is sent again later, or to another peer.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ResponseCode::ServerError
+                | ResponseCode::ResourceUnavailable
+                | ResponseCode::RateLimited
+        )
+    }
+}
+
 impl From<u8> for ResponseCode {
     fn from(byte: u8) -> Self {
         match byte {
@@ -285,6 +364,7 @@ impl From<u8> for ResponseCode {
             2 => ResponseCode::ServerError,
             3 => ResponseCode::ResourceUnavailable,
             4..=127 => ResponseCode::ReservedCode(byte),
+            139 => ResponseCode::RateLimited,
             _ => ResponseCode::ErroneousCode(byte),
         }
     }
@@ -297,8 +377,103 @@ impl From<ResponseCode> for u8 {
             ResponseCode::InvalidRequest => 1,
             ResponseCode::ServerError => 2,
             ResponseCode::ResourceUnavailable => 3,
+            ResponseCode::RateLimited => 139,
             ResponseCode::ReservedCode(byte) => byte,
             ResponseCode::ErroneousCode(byte) => byte,
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::req_resp::lean::messages::blocks::MAX_REQUEST_BLOCKS;
+
+    #[test]
+    fn test_decode_rejects_requests_over_the_limits() {
+        let mut codec = InboundSSZSnappyCodec {
+            protocol: ProtocolId::new(SupportedProtocol::Lean(
+                LeanSupportedProtocol::BlocksByRootV1,
+            )),
+            length: None,
+        };
+
+        // The length is refused before anything is decompressed.
+        let mut src = BytesMut::new();
+        Uvi::<usize>::default()
+            .encode((MAX_REQUEST_BLOCKS as usize + 1) * 32, &mut src)
+            .expect("Failed to encode length");
+        assert!(matches!(
+            codec.decode(&mut src),
+            Err(ReqRespError::LimitExceeded(_))
+        ));
+
+        // A request within the limits still decodes.
+        let request = LeanBlocksByRootV1Request::new(vec![Default::default(); 2]);
+        let bytes = request.as_ssz_bytes();
+        let mut encoder = FrameEncoder::new(vec![]);
+        encoder
+            .write_all(&bytes)
+            .expect("Failed to compress request");
+        encoder.flush().expect("Failed to compress request");
+        let mut codec = InboundSSZSnappyCodec {
+            protocol: codec.protocol,
+            length: None,
+        };
+        let mut src = BytesMut::new();
+        Uvi::<usize>::default()
+            .encode(bytes.len(), &mut src)
+            .expect("Failed to encode length");
+        src.extend_from_slice(encoder.get_ref());
+        let Ok(Some(RequestMessage::Lean(LeanRequestMessage::BlocksByRoot(decoded)))) =
+            codec.decode(&mut src)
+        else {
+            panic!("Failed to decode the request");
+        };
+        assert_eq!(decoded, request);
+    }
+
+    #[test]
+    fn test_encode_error_response() {
+        let mut codec = InboundSSZSnappyCodec {
+            protocol: ProtocolId::new(SupportedProtocol::Lean(
+                LeanSupportedProtocol::BlocksByRootV1,
+            )),
+            length: None,
+        };
+        let mut dst = BytesMut::new();
+        codec
+            .encode(
+                RespMessage::Error(ReqRespError::error_response(
+                    ResponseCode::RateLimited,
+                    "é".repeat(200),
+                )),
+                &mut dst,
+            )
+            .expect("Failed to encode error response");
+
+        assert_eq!(
+            ResponseCode::from(dst.split_to(1)[0]),
+            ResponseCode::RateLimited
+        );
+        let length = Uvi::<usize>::default()
+            .decode(&mut dst)
+            .expect("Failed to decode length")
+            .expect("Length is missing");
+        let mut bytes = vec![0; length];
+        FrameDecoder::new(dst.as_ref())
+            .read_exact(&mut bytes)
+            .expect("Failed to decompress error message");
+
+        // The message is cut to the most an error response carries, and stays valid UTF-8.
+        let message = VariableList::<u8, U256>::from_ssz_bytes(&bytes).unwrap();
+        let ReqRespError::ErrorResponse { code, message } =
+            ReqRespError::from_peer_response(ResponseCode::RateLimited, message)
+        else {
+            panic!("Expected an error response");
+        };
+        assert!(code.is_retryable());
+        assert_eq!(message, Some("é".repeat(128)));
+        assert!(!ResponseCode::InvalidRequest.is_retryable());
+    }
+}
@@ -0,0 +1,23 @@
+use ream_consensus_lean::blob_sidecar::BlobIdentifier;
+use ssz_derive::{Decode, Encode};
+use ssz_types::{VariableList, typenum::U1024};
+
+/// The most blob sidecars a single BlobsByRoot request may ask for, the length of its list of
+/// identifiers.
+pub const MAX_REQUEST_BLOB_SIDECARS: u64 = 1024;
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode)]
+#[ssz(struct_behaviour = "transparent")]
+pub struct BlobsByRootV1Request {
+    pub inner: VariableList<BlobIdentifier, U1024>,
+}
+
+/// Will panic if over 1024 blob sidecars are requested
+impl BlobsByRootV1Request {
+    pub fn new(blob_identifiers: Vec<BlobIdentifier>) -> Self {
+        Self {
+            inner: VariableList::new(blob_identifiers)
+                .expect("Too many blob identifiers were requested"),
+        }
+    }
+}
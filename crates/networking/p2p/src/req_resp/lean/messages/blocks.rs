@@ -2,6 +2,9 @@ use alloy_primitives::B256;
 use ssz_derive::{Decode, Encode};
 use ssz_types::{VariableList, typenum::U1024};
 
+/// The most blocks a single BlocksByRoot request may ask for, the length of its list of roots.
+pub const MAX_REQUEST_BLOCKS: u64 = 1024;
+
 #[derive(Debug, Default, Clone, PartialEq, Eq, Encode, Decode)]
 #[ssz(struct_behaviour = "transparent")]
 pub struct BlocksByRootV1Request {
@@ -1,14 +1,15 @@
+pub mod blobs;
 pub mod blocks;
 pub mod status;
 
 use std::sync::Arc;
 
-use ream_consensus_lean::block::SignedBlockWithAttestation;
+use ream_consensus_lean::{blob_sidecar::BlobSidecar, block::SignedBlockWithAttestation};
 use ssz_derive::{Decode, Encode};
 
 use super::protocol_id::LeanSupportedProtocol;
 use crate::req_resp::{
-    lean::messages::{blocks::BlocksByRootV1Request, status::Status},
+    lean::messages::{blobs::BlobsByRootV1Request, blocks::BlocksByRootV1Request, status::Status},
     protocol_id::{ProtocolId, SupportedProtocol},
 };
 
@@ -17,9 +18,18 @@ use crate::req_resp::{
 pub enum LeanRequestMessage {
     Status(Status),
     BlocksByRoot(BlocksByRootV1Request),
+    BlobsByRoot(BlobsByRootV1Request),
 }
 
 impl LeanRequestMessage {
+    pub fn protocol(&self) -> LeanSupportedProtocol {
+        match self {
+            LeanRequestMessage::Status(_) => LeanSupportedProtocol::StatusV1,
+            LeanRequestMessage::BlocksByRoot(_) => LeanSupportedProtocol::BlocksByRootV1,
+            LeanRequestMessage::BlobsByRoot(_) => LeanSupportedProtocol::BlobsByRootV1,
+        }
+    }
+
     pub fn supported_protocols(&self) -> Vec<ProtocolId> {
         match self {
             LeanRequestMessage::Status(_) => vec![ProtocolId::new(SupportedProtocol::Lean(
@@ -30,6 +40,20 @@ impl LeanRequestMessage {
                     LeanSupportedProtocol::BlocksByRootV1,
                 ))]
             }
+            LeanRequestMessage::BlobsByRoot(_) => {
+                vec![ProtocolId::new(SupportedProtocol::Lean(
+                    LeanSupportedProtocol::BlobsByRootV1,
+                ))]
+            }
+        }
+    }
+
+    /// The most response chunks a peer may answer this request with.
+    pub fn max_response_chunks(&self) -> u64 {
+        match self {
+            LeanRequestMessage::Status(_) => 1,
+            LeanRequestMessage::BlocksByRoot(request) => request.inner.len() as u64,
+            LeanRequestMessage::BlobsByRoot(request) => request.inner.len() as u64,
         }
     }
 }
@@ -39,4 +63,15 @@ impl LeanRequestMessage {
 pub enum LeanResponseMessage {
     Status(Status),
     BlocksByRoot(Arc<SignedBlockWithAttestation>),
+    BlobsByRoot(Arc<BlobSidecar>),
+}
+
+impl LeanResponseMessage {
+    pub fn protocol(&self) -> LeanSupportedProtocol {
+        match self {
+            LeanResponseMessage::Status(_) => LeanSupportedProtocol::StatusV1,
+            LeanResponseMessage::BlocksByRoot(_) => LeanSupportedProtocol::BlocksByRootV1,
+            LeanResponseMessage::BlobsByRoot(_) => LeanSupportedProtocol::BlobsByRootV1,
+        }
+    }
 }
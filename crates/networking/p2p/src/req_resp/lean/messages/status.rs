@@ -10,3 +10,21 @@ pub struct Status {
     /// The client's current head checkpoint
     pub head: Checkpoint,
 }
+
+#[cfg(test)]
+mod tests {
+    use ream_consensus_lean::checkpoint::Checkpoint;
+    use ssz::Encode;
+
+    use super::Status;
+
+    #[test]
+    fn test_status_layout() {
+        // The finalized and head checkpoints of leanSpec, nothing else.
+        let status = Status {
+            finalized: Checkpoint::default(),
+            head: Checkpoint::default(),
+        };
+        assert_eq!(status.as_ssz_bytes().len(), 2 * 40);
+    }
+}
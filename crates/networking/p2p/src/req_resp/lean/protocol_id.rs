@@ -1,6 +1,14 @@
+use ssz::Encode;
+
+use super::messages::{
+    blobs::MAX_REQUEST_BLOB_SIDECARS, blocks::MAX_REQUEST_BLOCKS, status::Status,
+};
+use crate::constants::MAX_PAYLOAD_SIZE;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LeanSupportedProtocol {
     BlocksByRootV1,
+    BlobsByRootV1,
     StatusV1,
 }
 
@@ -8,6 +16,7 @@ impl LeanSupportedProtocol {
     pub fn message_name(&self) -> &str {
         match self {
             LeanSupportedProtocol::BlocksByRootV1 => "lean_blocks_by_root",
+            LeanSupportedProtocol::BlobsByRootV1 => "lean_blobs_by_root",
             LeanSupportedProtocol::StatusV1 => "status",
         }
     }
@@ -15,6 +24,7 @@ impl LeanSupportedProtocol {
     pub fn schema_version(&self) -> &str {
         match self {
             LeanSupportedProtocol::BlocksByRootV1 => "1",
+            LeanSupportedProtocol::BlobsByRootV1 => "1",
             LeanSupportedProtocol::StatusV1 => "1",
         }
     }
@@ -22,7 +32,27 @@ impl LeanSupportedProtocol {
     pub fn has_context_bytes(&self) -> bool {
         match self {
             LeanSupportedProtocol::BlocksByRootV1 => false,
+            LeanSupportedProtocol::BlobsByRootV1 => false,
             LeanSupportedProtocol::StatusV1 => false,
         }
     }
+
+    /// The largest SSZ encoded request of this protocol.
+    pub fn max_request_size(&self) -> u64 {
+        match self {
+            LeanSupportedProtocol::BlocksByRootV1 => MAX_REQUEST_BLOCKS * 32,
+            // A blob identifier is a block root and an index.
+            LeanSupportedProtocol::BlobsByRootV1 => MAX_REQUEST_BLOB_SIDECARS * 40,
+            LeanSupportedProtocol::StatusV1 => Status::ssz_fixed_len() as u64,
+        }
+    }
+
+    /// The largest SSZ encoded response chunk of this protocol.
+    pub fn max_response_size(&self) -> u64 {
+        match self {
+            LeanSupportedProtocol::BlocksByRootV1 => MAX_PAYLOAD_SIZE,
+            LeanSupportedProtocol::BlobsByRootV1 => MAX_PAYLOAD_SIZE,
+            LeanSupportedProtocol::StatusV1 => Status::ssz_fixed_len() as u64,
+        }
+    }
 }
@@ -22,6 +22,14 @@ impl RequestMessage {
             RequestMessage::Lean(request_message) => request_message.supported_protocols(),
         }
     }
+
+    /// The most response chunks a peer may answer this request with, if it is limited.
+    pub fn max_response_chunks(&self) -> Option<u64> {
+        match self {
+            RequestMessage::Beacon(_) => None,
+            RequestMessage::Lean(request_message) => Some(request_message.max_response_chunks()),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
@@ -14,12 +14,17 @@ use futures::{
 };
 use libp2p::{OutboundUpgrade, bytes::Buf, core::UpgradeInfo};
 use ream_consensus_beacon::{blob_sidecar::BlobSidecar, electra::beacon_block::SignedBeaconBlock};
-use ream_consensus_lean::block::SignedBlockWithAttestation;
+use ream_consensus_lean::{
+    blob_sidecar::BlobSidecar as LeanBlobSidecar, block::SignedBlockWithAttestation,
+};
 use ream_consensus_misc::constants::beacon::genesis_validators_root;
 use ream_network_spec::networks::beacon_network_spec;
 use snap::{read::FrameDecoder, write::FrameEncoder};
 use ssz::{Decode, Encode};
-use ssz_types::{VariableList, typenum::U256};
+use ssz_types::{
+    VariableList,
+    typenum::{U256, Unsigned},
+};
 use tokio_util::{
     codec::{Decoder, Encoder, Framed},
     compat::{Compat, FuturesAsyncReadCompatExt},
@@ -29,6 +34,7 @@ use unsigned_varint::codec::Uvi;
 
 use super::{beacon::messages::BeaconRequestMessage, handler::RespMessage};
 use crate::{
+    constants::MAX_PAYLOAD_SIZE,
     req_resp::{
         beacon::{
             messages::{
@@ -45,7 +51,7 @@ use crate::{
         messages::{RequestMessage, ResponseMessage},
         protocol_id::{ProtocolId, SupportedProtocol},
     },
-    utils::max_message_size,
+    utils::max_framed_compressed_len,
 };
 
 #[derive(Debug, Clone)]
@@ -73,6 +79,8 @@ where
                 current_response_code: None,
                 context_bytes: None,
                 length: None,
+                max_response_chunks: self.request.max_response_chunks(),
+                response_chunks: 0,
             },
         );
 
@@ -101,6 +109,9 @@ pub struct OutboundSSZSnappyCodec {
     current_response_code: Option<ResponseCode>,
     context_bytes: Option<B32>,
     length: Option<usize>,
+    /// The most successful chunks the peer may answer the request with, if it is limited.
+    max_response_chunks: Option<u64>,
+    response_chunks: u64,
 }
 
 impl Encoder<RequestMessage> for OutboundSSZSnappyCodec {
@@ -114,11 +125,10 @@ impl Encoder<RequestMessage> for OutboundSSZSnappyCodec {
 
         // The length-prefix is within the expected size bounds derived from the payload SSZ type or
         // MAX_PAYLOAD_SIZE, whichever is smaller.
-        if bytes.len() > max_message_size() as usize {
+        if bytes.len() as u64 > MAX_PAYLOAD_SIZE {
             return Err(ReqRespError::Anyhow(anyhow::anyhow!(
-                "Message size exceeds maximum: {} > {}",
-                bytes.len(),
-                max_message_size()
+                "Message size exceeds maximum: {} > {MAX_PAYLOAD_SIZE}",
+                bytes.len()
             )));
         }
 
@@ -177,11 +187,16 @@ impl Decoder for OutboundSSZSnappyCodec {
         };
 
         // The length-prefix is within the expected size bounds derived from the payload SSZ
-        // type or MAX_PAYLOAD_SIZE, whichever is smaller.
-        if length > max_message_size() as usize {
-            return Err(ReqRespError::Anyhow(anyhow::anyhow!(
-                "Message size exceeds maximum: {length} > {}",
-                max_message_size()
+        // type or MAX_PAYLOAD_SIZE, whichever is smaller. It is checked before allocating the
+        // buffer or decompressing anything, so a peer can't make us do either for a huge payload.
+        let max_length = if response_code == ResponseCode::Success {
+            self.protocol.protocol.max_response_size()
+        } else {
+            U256::to_u64()
+        };
+        if length as u64 > max_length {
+            return Err(ReqRespError::LimitExceeded(format!(
+                "Response size exceeds maximum: {length} > {max_length}"
             )));
         }
 
@@ -193,6 +208,14 @@ impl Decoder for OutboundSSZSnappyCodec {
                 self.length = None;
                 self.context_bytes = None;
                 if ResponseCode::Success == response_code {
+                    self.response_chunks += 1;
+                    if let Some(max_response_chunks) = self.max_response_chunks
+                        && self.response_chunks > max_response_chunks
+                    {
+                        return Err(ReqRespError::LimitExceeded(format!(
+                            "Peer sent more than the {max_response_chunks} chunks requested"
+                        )));
+                    }
                     match self.protocol.protocol {
                         SupportedProtocol::Beacon(beacon_supported_protocol) => {
                             let response_message = match beacon_supported_protocol {
@@ -256,6 +279,12 @@ impl Decoder for OutboundSSZSnappyCodec {
                                             .map_err(ReqRespError::from)?,
                                     ))
                                 }
+                                LeanSupportedProtocol::BlobsByRootV1 => {
+                                    LeanResponseMessage::BlobsByRoot(Arc::new(
+                                        LeanBlobSidecar::from_ssz_bytes(&buf)
+                                            .map_err(ReqRespError::from)?,
+                                    ))
+                                }
                             };
                             Ok(Some(RespMessage::Response(Box::new(
                                 ResponseMessage::Lean(Arc::new(response_message)),
@@ -264,17 +293,19 @@ impl Decoder for OutboundSSZSnappyCodec {
                     }
                 } else {
                     Ok(Some(RespMessage::Error(
-                        VariableList::<u8, U256>::from_ssz_bytes(&buf).map(ReqRespError::from).map_err(|err| anyhow!("OutboundSSZSnappyCodec::decode: protocol: {:?}, response_code: {response_code:?}, err: {err:?}", self.protocol.protocol))?,
+                        VariableList::<u8, U256>::from_ssz_bytes(&buf).map(|message| ReqRespError::from_peer_response(response_code, message)).map_err(|err| anyhow!("OutboundSSZSnappyCodec::decode: protocol: {:?}, response_code: {response_code:?}, err: {err:?}", self.protocol.protocol))?,
                     )))
                 }
             }
             Err(err) => match err.kind() {
+                // Wait for the rest of the chunk, unless the peer already sent more compressed
+                // bytes than a payload of this length can take.
                 ErrorKind::UnexpectedEof => {
-                    if decoder.get_ref().position() < max_message_size() {
+                    if decoder.get_ref().position() <= max_framed_compressed_len(length as u64) {
                         Ok(None)
                     } else {
-                        Err(ReqRespError::InvalidData(format!(
-                            "Message is bigger then max message size: {err:?}"
+                        Err(ReqRespError::LimitExceeded(format!(
+                            "Compressed message is bigger than its length allows: {err:?}"
                         )))
                     }
                 }
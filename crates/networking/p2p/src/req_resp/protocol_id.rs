@@ -1,6 +1,7 @@
 use super::{
     Chain, beacon::protocol_id::BeaconSupportedProtocol, lean::protocol_id::LeanSupportedProtocol,
 };
+use crate::constants::MAX_PAYLOAD_SIZE;
 
 const BEACON_PROTOCOL_PREFIX: &str = "/eth2/beacon_chain/req";
 const LEAN_PROTOCOL_PREFIX: &str = "/leanconsensus/req";
@@ -71,6 +72,27 @@ impl SupportedProtocol {
         }
     }
 
+    /// The largest SSZ encoded request of this protocol, never more than [MAX_PAYLOAD_SIZE].
+    pub fn max_request_size(&self) -> u64 {
+        match self {
+            SupportedProtocol::Beacon(_) => MAX_PAYLOAD_SIZE,
+            SupportedProtocol::Lean(lean_protocol) => {
+                lean_protocol.max_request_size().min(MAX_PAYLOAD_SIZE)
+            }
+        }
+    }
+
+    /// The largest SSZ encoded response chunk of this protocol, never more than
+    /// [MAX_PAYLOAD_SIZE].
+    pub fn max_response_size(&self) -> u64 {
+        match self {
+            SupportedProtocol::Beacon(_) => MAX_PAYLOAD_SIZE,
+            SupportedProtocol::Lean(lean_protocol) => {
+                lean_protocol.max_response_size().min(MAX_PAYLOAD_SIZE)
+            }
+        }
+    }
+
     pub fn supported_protocols(chain: Chain) -> Vec<ProtocolId> {
         match chain {
             Chain::Beacon => vec![
@@ -89,6 +111,7 @@ impl SupportedProtocol {
             .collect(),
             Chain::Lean => vec![
                 LeanSupportedProtocol::BlocksByRootV1,
+                LeanSupportedProtocol::BlobsByRootV1,
                 LeanSupportedProtocol::StatusV1,
             ]
             .into_iter()
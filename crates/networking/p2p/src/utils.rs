@@ -4,12 +4,23 @@ use discv5::Enr;
 
 use crate::constants::{MAX_PAYLOAD_SIZE, QUIC_ENR_KEY};
 
+/// The most uncompressed bytes a single chunk of a snappy frame holds.
+const SNAPPY_FRAME_CHUNK_SIZE: u64 = 65536;
+
 /// Worst-case compressed length for a given payload of size n when using snappy:
 /// https://github.com/google/snappy/blob/32ded457c0b1fe78ceb8397632c416568d6714a0/snappy.cc#L218C1-L218C47
 pub fn max_compressed_len(n: u64) -> u64 {
     32 + n + n / 6
 }
 
+/// Worst-case length of a payload of size n once snappy framed, as req/resp chunks are. The frame
+/// starts with a 10 byte stream identifier, and the payload is compressed in chunks of at most
+/// 64KiB, each with an 8 byte header and checksum.
+pub fn max_framed_compressed_len(n: u64) -> u64 {
+    let chunks = n.div_ceil(SNAPPY_FRAME_CHUNK_SIZE).max(1);
+    10 + n + n / 6 + chunks * (32 + 8)
+}
+
 /// Allow 1024 bytes for framing and encoding overhead but at least 1MiB in case MAX_PAYLOAD_SIZE is
 /// small.
 pub fn max_message_size() -> u64 {
@@ -20,3 +31,34 @@ pub fn max_message_size() -> u64 {
 pub fn quic_from_enr(enr: &Enr) -> Option<u16> {
     enr.get_decodable(QUIC_ENR_KEY).and_then(Result::ok)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use snap::write::FrameEncoder;
+
+    use super::*;
+
+    #[test]
+    fn test_max_framed_compressed_len() {
+        // Pseudo random bytes barely compress, so they come close to the worst case.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        for n in [0, 1, 1000, 65536, 65537, 300_000] {
+            let payload = (0..n)
+                .map(|_| {
+                    state ^= state << 13;
+                    state ^= state >> 7;
+                    state ^= state << 17;
+                    state as u8
+                })
+                .collect::<Vec<u8>>();
+            let mut encoder = FrameEncoder::new(vec![]);
+            encoder
+                .write_all(&payload)
+                .expect("Failed to compress payload");
+            encoder.flush().expect("Failed to compress payload");
+            assert!(encoder.get_ref().len() as u64 <= max_framed_compressed_len(n));
+        }
+    }
+}
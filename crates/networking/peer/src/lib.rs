@@ -1,3 +1,5 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -9,6 +11,26 @@ pub enum ConnectionState {
     Disconnecting,
 }
 
+impl ConnectionState {
+    pub const ALL: [ConnectionState; 4] = [
+        ConnectionState::Connected,
+        ConnectionState::Connecting,
+        ConnectionState::Disconnected,
+        ConnectionState::Disconnecting,
+    ];
+}
+
+impl fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConnectionState::Connected => write!(f, "connected"),
+            ConnectionState::Connecting => write!(f, "connecting"),
+            ConnectionState::Disconnected => write!(f, "disconnected"),
+            ConnectionState::Disconnecting => write!(f, "disconnecting"),
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 #[serde(rename_all = "lowercase")]
 pub enum Direction {
@@ -17,6 +39,20 @@ pub enum Direction {
     Unknown,
 }
 
+impl Direction {
+    pub const ALL: [Direction; 3] = [Direction::Inbound, Direction::Outbound, Direction::Unknown];
+}
+
+impl fmt::Display for Direction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Direction::Inbound => write!(f, "inbound"),
+            Direction::Outbound => write!(f, "outbound"),
+            Direction::Unknown => write!(f, "unknown"),
+        }
+    }
+}
+
 #[derive(Default, Debug, Clone, Serialize)]
 pub struct PeerCount {
     #[serde(with = "serde_utils::quoted_u64")]
@@ -18,6 +18,8 @@ pub async fn start(
 ) -> Result<()> {
     RpcServerBuilder::new(server_config.http_socket_address)
         .allow_origin(server_config.http_allow_origin)
+        .allowed_origins(server_config.http_allowed_origins)
+        .tls(server_config.tls)
         .with_data(db)
         .with_data(network_state)
         .with_data(operation_pool)
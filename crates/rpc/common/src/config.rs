@@ -1,9 +1,26 @@
-use std::net::{IpAddr, SocketAddr};
+use std::{
+    net::{IpAddr, SocketAddr},
+    path::PathBuf,
+};
+
+/// PEM encoded certificate chain and private key the server terminates TLS with.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+}
 
 #[derive(Debug, Clone)]
 pub struct RpcServerConfig {
     pub http_socket_address: SocketAddr,
     pub http_allow_origin: bool,
+    /// Origins allowed to make cross-origin requests, ignored if `http_allow_origin` is set.
+    pub http_allowed_origins: Vec<String>,
+    pub tls: Option<TlsConfig>,
+    pub enable_debug_api: bool,
+    pub enable_admin_api: bool,
+    /// Whether `GET /metrics` is served, so metrics can be scraped without a separate port.
+    pub enable_metrics_endpoint: bool,
 }
 
 impl RpcServerConfig {
@@ -12,6 +29,32 @@ impl RpcServerConfig {
         Self {
             http_socket_address: SocketAddr::new(http_address, http_port),
             http_allow_origin,
+            http_allowed_origins: vec![],
+            tls: None,
+            enable_debug_api: true,
+            enable_admin_api: true,
+            enable_metrics_endpoint: false,
         }
     }
+
+    pub fn with_allowed_origins(mut self, origins: Vec<String>) -> Self {
+        self.http_allowed_origins = origins;
+        self
+    }
+
+    pub fn with_tls(mut self, tls: Option<TlsConfig>) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    pub fn with_namespaces(mut self, enable_debug_api: bool, enable_admin_api: bool) -> Self {
+        self.enable_debug_api = enable_debug_api;
+        self.enable_admin_api = enable_admin_api;
+        self
+    }
+
+    pub fn with_metrics_endpoint(mut self, enable_metrics_endpoint: bool) -> Self {
+        self.enable_metrics_endpoint = enable_metrics_endpoint;
+        self
+    }
 }
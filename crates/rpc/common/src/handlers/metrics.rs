@@ -0,0 +1,12 @@
+use actix_web::{HttpResponse, Responder, get};
+use ream_api_types_common::error::ApiError;
+use ream_metrics::{TEXT_FORMAT, encode_metrics};
+
+/// Called by `/metrics` to scrape the metrics of the node from the HTTP API, the same metrics the
+/// separate metrics server exports.
+#[get("/metrics")]
+pub async fn get_metrics() -> Result<impl Responder, ApiError> {
+    let metrics = encode_metrics()
+        .map_err(|err| ApiError::InternalError(format!("Failed to encode metrics: {err}")))?;
+    Ok(HttpResponse::Ok().content_type(TEXT_FORMAT).body(metrics))
+}
@@ -1,12 +1,26 @@
-use std::{io::Result, net::SocketAddr, sync::Arc};
+use std::{
+    fs::File,
+    io::{BufReader, Error, ErrorKind, Result},
+    net::SocketAddr,
+    sync::Arc,
+    time::Duration,
+};
 
+use actix_cors::Cors;
 use actix_web::{
     App, HttpServer,
-    middleware::Logger,
+    middleware::{Condition, Logger},
     web::{Data, ServiceConfig},
 };
+use ream_executor::ShutdownSignal;
+use rustls::ServerConfig;
 use tracing::info;
 
+use crate::config::TlsConfig;
+
+/// How long in-flight requests get to finish once a graceful shutdown starts.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(5);
+
 /// A type alias for a function that configures the actix-web ServiceConfig.
 type Configurator = dyn Fn(&mut ServiceConfig) + Send + Sync;
 
@@ -14,6 +28,9 @@ type Configurator = dyn Fn(&mut ServiceConfig) + Send + Sync;
 pub struct RpcServerBuilder {
     http_socket_address: SocketAddr,
     http_allow_origin: bool,
+    http_allowed_origins: Vec<String>,
+    tls: Option<TlsConfig>,
+    shutdown: Option<ShutdownSignal>,
     configurators: Vec<Arc<Configurator>>,
 }
 
@@ -23,6 +40,9 @@ impl RpcServerBuilder {
         Self {
             http_socket_address,
             http_allow_origin: false,
+            http_allowed_origins: Vec::new(),
+            tls: None,
+            shutdown: None,
             configurators: Vec::new(),
         }
     }
@@ -33,6 +53,25 @@ impl RpcServerBuilder {
         self
     }
 
+    /// Set the origins allowed to make cross-origin requests.
+    pub fn allowed_origins(mut self, origins: Vec<String>) -> Self {
+        self.http_allowed_origins = origins;
+        self
+    }
+
+    /// Serve over HTTPS instead of plain HTTP.
+    pub fn tls(mut self, tls: Option<TlsConfig>) -> Self {
+        self.tls = tls;
+        self
+    }
+
+    /// Stop accepting connections and finish the in-flight requests once `shutdown` fires,
+    /// instead of reacting to signals directly.
+    pub fn shutdown_signal(mut self, shutdown: ShutdownSignal) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
     /// Configure actix-web App by providing a closure that takes a mutable ref ServiceConfig.
     pub fn configure<F>(mut self, f: F) -> Self
     where
@@ -63,16 +102,83 @@ impl RpcServerBuilder {
             }
         };
 
-        info!("starting HTTP server on {:?}", self.http_socket_address);
-
+        let allow_any_origin = self.http_allow_origin;
+        let allowed_origins = self.http_allowed_origins.clone();
+        let cors_enabled = allow_any_origin || !allowed_origins.is_empty();
         let server = HttpServer::new(move || {
             App::new()
+                .wrap(Condition::new(
+                    cors_enabled,
+                    cors(allow_any_origin, &allowed_origins),
+                ))
                 .wrap(Logger::default())
                 .configure(configure_all.clone())
-        })
-        .bind(self.http_socket_address)?
-        .run();
+        });
+
+        let server = match self.shutdown {
+            Some(_) => server
+                .disable_signals()
+                .shutdown_timeout(SHUTDOWN_TIMEOUT.as_secs()),
+            None => server,
+        };
+
+        let server = match &self.tls {
+            Some(tls) => {
+                info!("starting HTTPS server on {:?}", self.http_socket_address);
+                server.bind_rustls_0_23(self.http_socket_address, load_tls_config(tls)?)?
+            }
+            None => {
+                info!("starting HTTP server on {:?}", self.http_socket_address);
+                server.bind(self.http_socket_address)?
+            }
+        };
 
-        server.await
+        let mut server = server.run();
+        let Some(mut shutdown) = self.shutdown else {
+            return server.await;
+        };
+        tokio::select! {
+            result = &mut server => result,
+            _ = shutdown.wait() => {
+                info!("Stopping RPC server, waiting for in-flight requests");
+                server.handle().stop(true).await;
+                server.await
+            }
+        }
     }
 }
+
+/// Browsers only get CORS headers for the configured origins. Requests without an `Origin`
+/// header, like the ones from other clients and scripts, are unaffected. Without any configured
+/// origin the middleware isn't installed at all.
+fn cors(allow_any_origin: bool, allowed_origins: &[String]) -> Cors {
+    let cors = Cors::default().allow_any_method().allow_any_header();
+    if allow_any_origin || allowed_origins.iter().any(|origin| origin == "*") {
+        return cors.allow_any_origin();
+    }
+    allowed_origins
+        .iter()
+        .fold(cors, |cors, origin| cors.allowed_origin(origin))
+}
+
+fn load_tls_config(tls: &TlsConfig) -> Result<ServerConfig> {
+    let certs = rustls_pemfile::certs(&mut BufReader::new(File::open(&tls.cert_path)?))
+        .collect::<Result<Vec<_>>>()?;
+    let key = rustls_pemfile::private_key(&mut BufReader::new(File::open(&tls.key_path)?))?
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("No private key found in {}", tls.key_path.display()),
+            )
+        })?;
+
+    ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|err| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("Invalid TLS config: {err}"),
+            )
+        })
+}
@@ -0,0 +1,209 @@
+//! Bearer token authentication for the admin namespace, and for the endpoints validator clients
+//! produce and submit blocks and attestations through.
+//!
+//! Admin requests carry `Authorization: Bearer <token>`, where the token is an HS256 JWT signed
+//! with the shared secret. JWTs must carry an `iat` claim within [ADMIN_TOKEN_MAX_AGE_SECS] of the
+//! node's clock, like the engine API, so a token leaked from a request stops working shortly
+//! after, while the secret itself never goes over the wire.
+
+use std::{fs, path::Path, sync::Arc};
+
+use actix_web::{
+    Error,
+    body::MessageBody,
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    web::Data,
+};
+use alloy_primitives::hex;
+use anyhow::{anyhow, ensure};
+use jsonwebtoken::{Algorithm, DecodingKey, Validation, decode, get_current_timestamp};
+use ream_api_types_common::error::ApiError;
+use ream_node::secret_file::write_secret_file;
+use serde::Deserialize;
+use tracing::{info, warn};
+
+pub const ADMIN_TOKEN_MAX_AGE_SECS: u64 = 60;
+
+const ADMIN_SECRET_LENGTH: usize = 32;
+
+#[derive(Debug, Deserialize)]
+struct AdminClaims {
+    iat: u64,
+}
+
+/// The shared secret admin requests are authenticated with.
+#[derive(Clone)]
+pub struct AdminAuth {
+    secret: Arc<Vec<u8>>,
+}
+
+impl AdminAuth {
+    pub fn new(secret: Vec<u8>) -> Self {
+        Self {
+            secret: Arc::new(secret),
+        }
+    }
+
+    /// Reads the hex encoded secret at `path`, generating and writing a new one if the file
+    /// doesn't exist yet.
+    pub fn load_or_generate(path: &Path) -> anyhow::Result<Self> {
+        if !path.exists() {
+            let secret = rand::random::<[u8; ADMIN_SECRET_LENGTH]>().to_vec();
+            write_secret_file(path, hex::encode(&secret)).map_err(|err| {
+                anyhow!("Failed to write admin secret to {}: {err}", path.display())
+            })?;
+            info!("Generated admin API secret at {}", path.display());
+            return Ok(Self::new(secret));
+        }
+
+        let contents = fs::read_to_string(path)
+            .map_err(|err| anyhow!("Failed to read admin secret {}: {err}", path.display()))?;
+        let secret = hex::decode(contents.trim())
+            .map_err(|err| anyhow!("Admin secret {} is not hex: {err}", path.display()))?;
+        ensure!(
+            secret.len() >= ADMIN_SECRET_LENGTH,
+            "Admin secret {} must be at least {ADMIN_SECRET_LENGTH} bytes",
+            path.display()
+        );
+        Ok(Self::new(secret))
+    }
+
+    /// Checks a bearer token against the secret.
+    pub fn verify(&self, token: &str) -> anyhow::Result<()> {
+        let mut validation = Validation::new(Algorithm::HS256);
+        validation.validate_exp = false;
+        validation.set_required_spec_claims(&["iat"]);
+        let claims =
+            decode::<AdminClaims>(token, &DecodingKey::from_secret(&self.secret), &validation)
+                .map_err(|err| anyhow!("Invalid admin token: {err}"))?
+                .claims;
+
+        ensure!(
+            get_current_timestamp().abs_diff(claims.iat) <= ADMIN_TOKEN_MAX_AGE_SECS,
+            "Admin token issued at {} is stale",
+            claims.iat
+        );
+        Ok(())
+    }
+}
+
+/// Rejects requests without a valid admin bearer token.
+pub async fn require_admin_token(
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    let verified = match request.app_data::<Data<AdminAuth>>() {
+        Some(admin_auth) => request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|header| header.to_str().ok())
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .ok_or_else(|| anyhow!("Missing bearer token"))
+            .and_then(|token| admin_auth.verify(token.trim())),
+        None => Err(anyhow!("Admin API authentication is not configured")),
+    };
+
+    if let Err(err) = verified {
+        warn!(path = request.path(), "Rejected admin request: {err}");
+        return Err(ApiError::Unauthorized.into());
+    }
+    next.call(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App, HttpResponse,
+        http::StatusCode,
+        middleware::from_fn,
+        test::{TestRequest, init_service, try_call_service},
+        web::{get, scope},
+    };
+    use jsonwebtoken::{EncodingKey, Header, encode};
+    use serde::Serialize;
+
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Claims {
+        iat: u64,
+    }
+
+    fn token(secret: &[u8], iat: u64) -> String {
+        encode(
+            &Header::new(Algorithm::HS256),
+            &Claims { iat },
+            &EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_verify_admin_token() {
+        let secret = vec![7u8; ADMIN_SECRET_LENGTH];
+        let admin_auth = AdminAuth::new(secret.clone());
+
+        // The secret itself is never a token.
+        assert!(admin_auth.verify(&hex::encode(&secret)).is_err());
+
+        assert!(
+            admin_auth
+                .verify(&token(&secret, get_current_timestamp()))
+                .is_ok()
+        );
+        assert!(
+            admin_auth
+                .verify(&token(
+                    &secret,
+                    get_current_timestamp() - 2 * ADMIN_TOKEN_MAX_AGE_SECS
+                ))
+                .is_err()
+        );
+        assert!(
+            admin_auth
+                .verify(&token(&[8u8; 32], get_current_timestamp()))
+                .is_err()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_require_admin_token() {
+        let secret = vec![7u8; ADMIN_SECRET_LENGTH];
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(AdminAuth::new(secret.clone())))
+                .service(
+                    scope("/admin")
+                        .wrap(from_fn(require_admin_token))
+                        .route("", get().to(HttpResponse::Ok)),
+                ),
+        )
+        .await;
+
+        let cases = [
+            (
+                Some(token(&secret, get_current_timestamp())),
+                StatusCode::OK,
+            ),
+            (
+                Some(token(&[8u8; 32], get_current_timestamp())),
+                StatusCode::UNAUTHORIZED,
+            ),
+            (Some(hex::encode(&secret)), StatusCode::UNAUTHORIZED),
+            (None, StatusCode::UNAUTHORIZED),
+        ];
+        for (token, expected_status) in cases {
+            let mut request = TestRequest::get().uri("/admin");
+            if let Some(token) = token {
+                request = request.insert_header((AUTHORIZATION, format!("Bearer {token}")));
+            }
+            let status = match try_call_service(&app, request.to_request()).await {
+                Ok(response) => response.status(),
+                Err(err) => err.as_response_error().status_code(),
+            };
+            assert_eq!(status, expected_status);
+        }
+    }
+}
@@ -0,0 +1,100 @@
+//! Encoding of lean API request and response bodies.
+//!
+//! Bodies sent with `Content-Type: application/octet-stream` are decoded as SSZ, anything else
+//! is decoded as JSON. Responses are SSZ encoded when the `Accept` header prefers
+//! `application/octet-stream` over JSON, and JSON encoded otherwise.
+
+use actix_web::{
+    HttpRequest, HttpResponse,
+    http::header::{ACCEPT, CONTENT_TYPE},
+    web::Bytes,
+};
+use ream_api_types_common::error::ApiError;
+use serde::{Serialize, de::DeserializeOwned};
+use ssz::{Decode, Encode};
+
+pub const JSON_CONTENT_TYPE: &str = "application/json";
+pub const SSZ_CONTENT_TYPE: &str = "application/octet-stream";
+
+/// Returns true if the request body is SSZ encoded.
+pub fn is_ssz_request(http_request: &HttpRequest) -> bool {
+    http_request
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|header| header.to_str().ok())
+        .is_some_and(|content_type| content_type.starts_with(SSZ_CONTENT_TYPE))
+}
+
+/// Decodes `body` as SSZ or JSON depending on the `Content-Type` of `http_request`.
+pub fn decode_body<T: Decode + DeserializeOwned>(
+    http_request: &HttpRequest,
+    body: &Bytes,
+) -> Result<T, ApiError> {
+    match is_ssz_request(http_request) {
+        true => T::from_ssz_bytes(body)
+            .map_err(|err| ApiError::BadRequest(format!("Invalid SSZ body: {err:?}"))),
+        false => serde_json::from_slice(body)
+            .map_err(|err| ApiError::BadRequest(format!("Invalid JSON body: {err}"))),
+    }
+}
+
+/// Returns true if `accept` ranks SSZ strictly above JSON. Media ranges without a quality value
+/// have `q=1`, and `*/*` counts as JSON so that generic clients keep receiving JSON.
+pub fn prefers_ssz(accept: &str) -> bool {
+    let mut ssz_quality = 0.0;
+    let mut json_quality = 0.0;
+    for media_range in accept.split(',') {
+        let mut parameters = media_range.split(';').map(str::trim);
+        let media_type = parameters.next().unwrap_or_default();
+        let quality = parameters
+            .find_map(|parameter| parameter.strip_prefix("q="))
+            .and_then(|quality| quality.parse::<f32>().ok())
+            .unwrap_or(1.0);
+        match media_type {
+            SSZ_CONTENT_TYPE => ssz_quality = ssz_quality.max(quality),
+            JSON_CONTENT_TYPE | "application/*" | "*/*" => json_quality = json_quality.max(quality),
+            _ => {}
+        }
+    }
+    ssz_quality > json_quality
+}
+
+/// Encodes `value` as SSZ or JSON depending on the `Accept` header of `http_request`.
+pub fn encode_response<T: Encode + Serialize>(
+    http_request: &HttpRequest,
+    value: &T,
+) -> HttpResponse {
+    let wants_ssz = http_request
+        .headers()
+        .get(ACCEPT)
+        .and_then(|header| header.to_str().ok())
+        .is_some_and(prefers_ssz);
+    match wants_ssz {
+        true => HttpResponse::Ok()
+            .content_type(SSZ_CONTENT_TYPE)
+            .body(value.as_ssz_bytes()),
+        false => HttpResponse::Ok().json(value),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prefers_ssz() {
+        assert!(prefers_ssz("application/octet-stream"));
+        assert!(prefers_ssz(
+            "application/octet-stream;q=1.0,application/json;q=0.9"
+        ));
+        assert!(prefers_ssz(
+            "application/json;q=0.5, application/octet-stream"
+        ));
+
+        assert!(!prefers_ssz("application/json"));
+        assert!(!prefers_ssz("*/*"));
+        assert!(!prefers_ssz("application/octet-stream;q=0.5,*/*"));
+        assert!(!prefers_ssz("application/octet-stream;q=0"));
+        assert!(!prefers_ssz("text/html"));
+    }
+}
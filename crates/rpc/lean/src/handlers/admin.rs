@@ -0,0 +1,271 @@
+use std::{
+    path::{Component, PathBuf},
+    str::FromStr,
+};
+
+use actix_web::{
+    HttpResponse, Responder, delete, post,
+    web::{Data, Json, Path},
+};
+use libp2p::{Multiaddr, PeerId};
+use ream_api_types_common::error::ApiError;
+#[cfg(feature = "testing")]
+use ream_api_types_lean::admin::NetworkFaultsRequest;
+use ream_api_types_lean::admin::{
+    LogLevelRequest, LogLevelResponse, PruneRequest, PruneResponse, SnapshotRequest,
+    SnapshotResponse, TrustedPeerRequest,
+};
+use ream_chain_lean::p2p_request::LeanP2PRequest;
+#[cfg(feature = "testing")]
+use ream_chain_lean::p2p_request::NetworkFaults;
+use ream_fork_choice_lean::store::LeanStoreReader;
+use ream_node::diagnostics::{debug_invariants, log_level_controller, set_debug_invariants};
+use ream_storage::{errors::StoreError, tables::field::REDBField};
+use tokio::{sync::mpsc, task::spawn_blocking};
+use tracing::info;
+
+fn parse_peer_id(peer_id: &str) -> Result<PeerId, ApiError> {
+    PeerId::from_str(peer_id)
+        .map_err(|err| ApiError::BadRequest(format!("Invalid PeerId format: {peer_id}, {err:?}")))
+}
+
+fn send_p2p_request(
+    p2p_sender: &mpsc::UnboundedSender<LeanP2PRequest>,
+    request: LeanP2PRequest,
+) -> Result<(), ApiError> {
+    p2p_sender
+        .send(request)
+        .map_err(|err| ApiError::InternalError(format!("Network service is unavailable: {err}")))
+}
+
+// POST /lean/v0/admin/log_level
+#[post("/log_level")]
+pub async fn post_log_level(request: Json<LogLevelRequest>) -> Result<impl Responder, ApiError> {
+    let controller = log_level_controller().ok_or_else(|| {
+        ApiError::InternalError("Log level controller is not installed".to_string())
+    })?;
+    let LogLevelRequest {
+        filter,
+        levels,
+        debug_invariants: enable_debug_invariants,
+        persist,
+    } = request.into_inner();
+
+    // The whole change is checked and persisted before any of it is applied, so a failed
+    // request leaves the diagnostics as they were.
+    let new_filter = controller
+        .updated_filter(filter.as_deref(), levels.as_deref())
+        .map_err(|err| {
+            ApiError::BadRequest(format!(
+                "Invalid log filter {filter:?} or levels {levels:?}: {err}"
+            ))
+        })?;
+    if persist {
+        controller.persist_filter(&new_filter).map_err(|err| {
+            ApiError::InternalError(format!("Could not persist the log filter: {err}"))
+        })?;
+    }
+    if filter.is_some() || levels.is_some() {
+        controller.set_filter(&new_filter).map_err(|err| {
+            ApiError::InternalError(format!("Could not apply the log filter: {err}"))
+        })?;
+    }
+    if let Some(enabled) = enable_debug_invariants {
+        set_debug_invariants(enabled);
+    }
+
+    let response = LogLevelResponse {
+        filter: controller.current_filter(),
+        debug_invariants: debug_invariants(),
+    };
+    info!(
+        "Diagnostics updated: log filter {:?}, debug invariants {}",
+        response.filter, response.debug_invariants
+    );
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// DELETE /lean/v0/admin/log_level
+#[delete("/log_level")]
+pub async fn delete_log_level() -> Result<impl Responder, ApiError> {
+    let controller = log_level_controller().ok_or_else(|| {
+        ApiError::InternalError("Log level controller is not installed".to_string())
+    })?;
+    let filter = controller.clear_persisted_filter().map_err(|err| {
+        ApiError::InternalError(format!("Could not clear the persisted log filter: {err}"))
+    })?;
+    info!("Persisted log filter cleared, log filter is now {filter:?}");
+    Ok(HttpResponse::Ok().json(LogLevelResponse {
+        filter,
+        debug_invariants: debug_invariants(),
+    }))
+}
+
+/// The directory API snapshots are written into, inside the data directory.
+#[derive(Debug, Clone)]
+pub struct SnapshotDir(pub PathBuf);
+
+impl SnapshotDir {
+    /// Returns where the snapshot named `destination` goes. Only plain relative paths are
+    /// accepted, so a snapshot can't be written outside of the directory.
+    fn resolve(&self, destination: &std::path::Path) -> Result<PathBuf, ApiError> {
+        let mut components = destination.components().peekable();
+        if components.peek().is_none()
+            || !components.all(|component| matches!(component, Component::Normal(_)))
+        {
+            return Err(ApiError::BadRequest(format!(
+                "Snapshot destination {destination:?} must be a relative path without `..`"
+            )));
+        }
+        Ok(self.0.join(destination))
+    }
+}
+
+// POST /lean/v0/admin/snapshot
+#[post("/snapshot")]
+pub async fn post_snapshot(
+    request: Json<SnapshotRequest>,
+    lean_chain: Data<LeanStoreReader>,
+    snapshot_dir: Data<SnapshotDir>,
+) -> Result<impl Responder, ApiError> {
+    let destination = snapshot_dir.resolve(&request.into_inner().destination)?;
+    let lean_db = lean_chain.read().await.store.clone();
+
+    // Copying the database reads every table, keep it off the async workers.
+    let path = spawn_blocking(move || lean_db.snapshot(&destination))
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Snapshot task failed: {err:?}")))?
+        .map_err(|err| match err {
+            StoreError::SnapshotDestinationExists(_) => ApiError::BadRequest(err.to_string()),
+            err => ApiError::InternalError(format!("Could not write snapshot: {err}")),
+        })?;
+
+    Ok(HttpResponse::Ok().json(SnapshotResponse { path }))
+}
+
+// POST /lean/v0/admin/prune
+#[post("/prune")]
+pub async fn post_prune(
+    request: Json<PruneRequest>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let lean_db = lean_chain.read().await.store.clone();
+    let before_slot = request.into_inner().before_slot;
+
+    let finalized_slot = lean_db
+        .latest_finalized_provider()
+        .get()
+        .map_err(|err| ApiError::InternalError(format!("No latest finalized hash: {err:?}")))?
+        .slot;
+    if before_slot > finalized_slot {
+        return Err(ApiError::BadRequest(format!(
+            "Refusing to prune past the latest finalized slot {finalized_slot}"
+        )));
+    }
+
+    let pruned = spawn_blocking(move || lean_db.prune_before_slot(before_slot))
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Prune task failed: {err:?}")))?
+        .map_err(|err| ApiError::InternalError(format!("Could not prune database: {err}")))?;
+    info!("Pruned {pruned} block(s) before slot {before_slot}");
+
+    Ok(HttpResponse::Ok().json(PruneResponse { pruned }))
+}
+
+// POST /lean/v0/admin/trusted_peers
+#[post("/trusted_peers")]
+pub async fn post_trusted_peer(
+    request: Json<TrustedPeerRequest>,
+    p2p_sender: Data<mpsc::UnboundedSender<LeanP2PRequest>>,
+) -> Result<impl Responder, ApiError> {
+    let address = request.into_inner().address;
+    let address = Multiaddr::from_str(&address)
+        .map_err(|err| ApiError::BadRequest(format!("Invalid multiaddress {address}: {err}")))?;
+
+    send_p2p_request(&p2p_sender, LeanP2PRequest::AddTrustedPeer(address))?;
+    Ok(HttpResponse::Accepted().finish())
+}
+
+// DELETE /lean/v0/admin/trusted_peers/{peer_id}
+#[delete("/trusted_peers/{peer_id}")]
+pub async fn delete_trusted_peer(
+    peer_id: Path<String>,
+    p2p_sender: Data<mpsc::UnboundedSender<LeanP2PRequest>>,
+) -> Result<impl Responder, ApiError> {
+    let peer_id = parse_peer_id(&peer_id)?;
+    send_p2p_request(&p2p_sender, LeanP2PRequest::RemoveTrustedPeer(peer_id))?;
+    Ok(HttpResponse::Accepted().finish())
+}
+
+// POST /lean/v0/admin/peers/{peer_id}/ban
+#[post("/peers/{peer_id}/ban")]
+pub async fn post_ban_peer(
+    peer_id: Path<String>,
+    p2p_sender: Data<mpsc::UnboundedSender<LeanP2PRequest>>,
+) -> Result<impl Responder, ApiError> {
+    let peer_id = parse_peer_id(&peer_id)?;
+    send_p2p_request(&p2p_sender, LeanP2PRequest::BanPeer(peer_id))?;
+    Ok(HttpResponse::Accepted().finish())
+}
+
+// POST /lean/v0/admin/network_faults
+#[cfg(feature = "testing")]
+#[post("/network_faults")]
+pub async fn post_network_faults(
+    request: Json<NetworkFaultsRequest>,
+    p2p_sender: Data<mpsc::UnboundedSender<LeanP2PRequest>>,
+) -> Result<impl Responder, ApiError> {
+    let NetworkFaultsRequest {
+        peer_id,
+        drop_percentage,
+        delay_percentage,
+        delay_ms,
+    } = request.into_inner();
+    if drop_percentage > 100 || delay_percentage > 100 {
+        return Err(ApiError::BadRequest(
+            "Percentages must be at most 100".to_string(),
+        ));
+    }
+    let peer_id = peer_id.as_deref().map(parse_peer_id).transpose()?;
+
+    send_p2p_request(
+        &p2p_sender,
+        LeanP2PRequest::SetNetworkFaults {
+            peer_id,
+            faults: NetworkFaults {
+                drop_percentage,
+                delay_percentage,
+                delay_ms,
+            },
+        },
+    )?;
+    Ok(HttpResponse::Accepted().finish())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+
+    use super::SnapshotDir;
+
+    #[test]
+    fn test_snapshot_destination_stays_in_snapshot_dir() {
+        let snapshot_dir = SnapshotDir(PathBuf::from("/data/snapshots"));
+        assert_eq!(
+            snapshot_dir.resolve(Path::new("daily/1")).unwrap(),
+            PathBuf::from("/data/snapshots/daily/1")
+        );
+        for destination in [
+            "",
+            "/tmp/snapshot",
+            "../snapshot",
+            "daily/../../snapshot",
+            "./",
+        ] {
+            assert!(
+                snapshot_dir.resolve(Path::new(destination)).is_err(),
+                "{destination:?} was accepted"
+            );
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, get,
+    middleware::from_fn,
+    post,
+    web::{Bytes, Data},
+};
+use ream_api_types_common::error::ApiError;
+use ream_chain_lean::messages::LeanChainServiceMessage;
+use ream_consensus_lean::attestation::SignedAttestation;
+use ream_fork_choice_lean::store::LeanStoreReader;
+use ream_storage::tables::{field::REDBField, table::REDBTable};
+use tokio::sync::mpsc;
+use tree_hash::TreeHash;
+
+use crate::{
+    auth::require_admin_token,
+    content::{decode_body, encode_response},
+};
+
+// GET /lean/v0/attestations/aggregates
+#[get("/attestations/aggregates")]
+pub async fn get_aggregated_attestations(
+    http_request: HttpRequest,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let attestation_pool = lean_chain.read().await.attestation_pool.clone();
+    let aggregates = tokio::task::spawn_blocking(move || attestation_pool.aggregate_known())
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Aggregation task failed: {err}")))?
+        .map_err(|err| {
+            ApiError::InternalError(format!("Failed to aggregate attestations: {err:?}"))
+        })?;
+    Ok(encode_response(&http_request, &aggregates))
+}
+
+// POST /lean/v0/attestations
+#[post("/attestations", wrap = "from_fn(require_admin_token)")]
+pub async fn post_attestation(
+    http_request: HttpRequest,
+    body: Bytes,
+    lean_chain: Data<LeanStoreReader>,
+    chain_sender: Data<mpsc::UnboundedSender<LeanChainServiceMessage>>,
+) -> Result<impl Responder, ApiError> {
+    let signed_attestation = decode_body::<SignedAttestation>(&http_request, &body)?;
+    validate_attestation(&signed_attestation, &lean_chain).await?;
+
+    chain_sender
+        .send(LeanChainServiceMessage::ProcessAttestation {
+            signed_attestation: Box::new(signed_attestation),
+            need_gossip: true,
+            from_peer: false,
+            validation: None,
+        })
+        .map_err(|err| ApiError::InternalError(format!("Chain service is unavailable: {err}")))?;
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Rejects attestations which fork choice would never accept, or which aren't signed by the
+/// validator they claim to come from.
+async fn validate_attestation(
+    signed_attestation: &SignedAttestation,
+    lean_chain: &LeanStoreReader,
+) -> Result<(), ApiError> {
+    let lean_chain = lean_chain.read().await;
+    lean_chain
+        .validate_attestation(signed_attestation)
+        .await
+        .map_err(|err| ApiError::BadRequest(format!("Invalid attestation: {err:?}")))?;
+
+    let lean_db = lean_chain.store.clone();
+    drop(lean_chain);
+
+    let head_root = lean_db
+        .head_provider()
+        .get()
+        .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}")))?;
+    let head_state = lean_db
+        .run_blocking(move |lean_db| lean_db.state_provider().get(head_root))
+        .await
+        .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?
+        .ok_or_else(|| ApiError::InternalError("Head state not found".to_string()))?;
+
+    let attestation = &signed_attestation.message;
+    let public_key = head_state
+        .validator_public_key_at(attestation.validator_id, attestation.data.slot)
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!("Unknown validator {}", attestation.validator_id))
+        })?;
+    let is_valid = signed_attestation
+        .signature
+        .verify(
+            public_key,
+            attestation.data.slot as u32,
+            &attestation.tree_hash_root(),
+        )
+        .map_err(|err| ApiError::BadRequest(format!("Malformed signature: {err:?}")))?;
+    if !is_valid {
+        return Err(ApiError::BadRequest(
+            "Invalid attestation signature".to_string(),
+        ));
+    }
+
+    Ok(())
+}
@@ -1,25 +1,223 @@
+use std::collections::HashSet;
+
 use actix_web::{
-    HttpResponse, Responder, get,
-    web::{Data, Path},
+    HttpRequest, HttpResponse, Responder, get,
+    middleware::from_fn,
+    post,
+    web::{Bytes, Data, Path},
 };
+use alloy_primitives::B256;
 use ream_api_types_common::{error::ApiError, id::ID};
-use ream_consensus_lean::block::Block;
+use ream_api_types_lean::block::BlockSummary;
+use ream_chain_lean::messages::LeanChainServiceMessage;
+use ream_consensus_lean::{
+    block::{Block, SignedBlockWithAttestation},
+    checkpoint::Checkpoint,
+    state::LeanState,
+};
 use ream_fork_choice_lean::store::LeanStoreReader;
 use ream_storage::tables::{field::REDBField, table::REDBTable};
+use tokio::sync::mpsc;
+use tree_hash::TreeHash;
+
+use crate::{
+    auth::require_admin_token,
+    content::{decode_body, encode_response},
+};
 
 // GET /lean/v0/blocks/{block_id}
 #[get("/blocks/{block_id}")]
 pub async fn get_block(
+    http_request: HttpRequest,
     block_id: Path<ID>,
     lean_chain: Data<LeanStoreReader>,
 ) -> Result<impl Responder, ApiError> {
-    Ok(HttpResponse::Ok().json(
-        get_block_by_id(block_id.into_inner(), lean_chain)
+    Ok(encode_response(
+        &http_request,
+        &get_block_by_id(block_id.into_inner(), lean_chain)
             .await?
             .ok_or_else(|| ApiError::NotFound("Block not found".to_string()))?,
     ))
 }
 
+// GET /lean/v0/blocks/{block_id}/attestations
+#[get("/blocks/{block_id}/attestations")]
+pub async fn get_block_attestations(
+    http_request: HttpRequest,
+    block_id: Path<ID>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let block = get_block_by_id(block_id.into_inner(), lean_chain)
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Block not found".to_string()))?;
+    Ok(encode_response(&http_request, &block.body.attestations))
+}
+
+// GET /lean/v0/blocks/{block_id}/summary
+#[get("/blocks/{block_id}/summary")]
+pub async fn get_block_summary(
+    block_id: Path<ID>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let block = get_block_by_id(block_id.into_inner(), lean_chain.clone())
+        .await?
+        .ok_or_else(|| ApiError::NotFound("Block not found".to_string()))?;
+    let block_root = block.tree_hash_root();
+
+    // The genesis block has no parent state to diff against, nothing was justified before it.
+    if block.parent_root == B256::ZERO {
+        let state = get_block_state(block_root, &lean_chain).await?;
+        return Ok(HttpResponse::Ok().json(block_summary(block_root, &block, &state, &state)));
+    }
+
+    // Only the parent state is looked up, the block is applied to it for the post state rather
+    // than regenerating both.
+    let pre_state = get_block_state(block.parent_root, &lean_chain).await?;
+    let (pre_state, post_state) = tokio::task::spawn_blocking({
+        let block = block.clone();
+        move || {
+            let mut post_state = pre_state.clone();
+            post_state.state_transition(&block, true)?;
+            anyhow::Ok((pre_state, post_state))
+        }
+    })
+    .await
+    .map_err(|err| ApiError::InternalError(format!("State transition task failed: {err}")))?
+    .map_err(|err| ApiError::InternalError(format!("Failed to apply block: {err:?}")))?;
+
+    Ok(HttpResponse::Ok().json(block_summary(block_root, &block, &pre_state, &post_state)))
+}
+
+/// Summarizes what `block` changed between its `pre_state` and `post_state`.
+fn block_summary(
+    block_root: B256,
+    block: &Block,
+    pre_state: &LeanState,
+    post_state: &LeanState,
+) -> BlockSummary {
+    let attestations = &block.body.attestations;
+    let unique_voters = attestations
+        .iter()
+        .map(|attestation| attestation.validator_id)
+        .collect::<HashSet<_>>()
+        .len();
+    let new_justifications = (0..post_state.justified_slots.len())
+        .filter(|slot| {
+            post_state.justified_slots.get(*slot).unwrap_or(false)
+                && !pre_state.justified_slots.get(*slot).unwrap_or(false)
+        })
+        .filter_map(|slot| {
+            post_state
+                .historical_block_hashes
+                .get(slot)
+                .map(|root| Checkpoint {
+                    root: *root,
+                    slot: slot as u64,
+                })
+        })
+        .collect();
+
+    BlockSummary {
+        block_root,
+        slot: block.slot,
+        proposer_index: block.proposer_index,
+        attestation_count: attestations.len() as u64,
+        unique_voters: unique_voters as u64,
+        new_justifications,
+        new_finalization: (post_state.latest_finalized != pre_state.latest_finalized)
+            .then_some(post_state.latest_finalized),
+    }
+}
+
+/// Returns the post state of the block at `block_root`, regenerating it if it's no longer stored.
+async fn get_block_state(
+    block_root: B256,
+    lean_chain: &LeanStoreReader,
+) -> Result<LeanState, ApiError> {
+    // Replaying blocks can take a while, don't hold the store while doing so.
+    let state_regenerator = lean_chain.read().await.state_regenerator.clone();
+    state_regenerator
+        .get_state_at(block_root)
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to get state: {err:?}")))?
+        .ok_or_else(|| ApiError::NotFound(format!("State not found for block {block_root}")))
+}
+
+// POST /lean/v0/blocks
+#[post("/blocks", wrap = "from_fn(require_admin_token)")]
+pub async fn post_block(
+    http_request: HttpRequest,
+    body: Bytes,
+    lean_chain: Data<LeanStoreReader>,
+    chain_sender: Data<mpsc::UnboundedSender<LeanChainServiceMessage>>,
+) -> Result<impl Responder, ApiError> {
+    let signed_block_with_attestation =
+        decode_body::<SignedBlockWithAttestation>(&http_request, &body)?;
+    validate_block(&signed_block_with_attestation, &lean_chain).await?;
+
+    chain_sender
+        .send(LeanChainServiceMessage::ProcessBlock {
+            signed_block_with_attestation: Box::new(signed_block_with_attestation),
+            need_gossip: true,
+            peer_id: None,
+            validation: None,
+        })
+        .map_err(|err| ApiError::InternalError(format!("Chain service is unavailable: {err}")))?;
+
+    Ok(HttpResponse::Accepted().finish())
+}
+
+/// Rejects blocks which could never be imported: blocks building on an unknown parent, blocks
+/// from the wrong proposer and blocks with invalid signatures.
+async fn validate_block(
+    signed_block_with_attestation: &SignedBlockWithAttestation,
+    lean_chain: &LeanStoreReader,
+) -> Result<(), ApiError> {
+    let lean_db = lean_chain.read().await.store.clone();
+    let block = &signed_block_with_attestation.message.block;
+
+    if lean_db
+        .block_provider()
+        .contains_key(block.tree_hash_root())
+    {
+        return Err(ApiError::BadRequest("Block is already known".to_string()));
+    }
+
+    let parent_root = block.parent_root;
+    let parent_state = lean_db
+        .run_blocking(move |lean_db| lean_db.state_provider().get(parent_root))
+        .await
+        .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?
+        .ok_or_else(|| ApiError::BadRequest(format!("Unknown parent block: {parent_root}")))?;
+
+    if block.slot <= parent_state.slot {
+        return Err(ApiError::BadRequest(format!(
+            "Block slot {} is not after its parent slot {}",
+            block.slot, parent_state.slot
+        )));
+    }
+    let proposer_index = parent_state
+        .proposer_index_at(block.slot)
+        .map_err(|err| ApiError::InternalError(format!("No proposer for the block: {err:?}")))?;
+    if block.proposer_index != proposer_index {
+        return Err(ApiError::BadRequest(format!(
+            "Validator {} is not the proposer for slot {}",
+            block.proposer_index, block.slot
+        )));
+    }
+
+    // Signature verification is CPU heavy, keep it off the async workers.
+    let signed_block_with_attestation = signed_block_with_attestation.clone();
+    tokio::task::spawn_blocking(move || {
+        signed_block_with_attestation.verify_signatures(&parent_state, true)
+    })
+    .await
+    .map_err(|err| ApiError::InternalError(format!("Signature verification failed: {err}")))?
+    .map_err(|err| ApiError::BadRequest(format!("Invalid block signatures: {err:?}")))?;
+
+    Ok(())
+}
+
 // Retrieve a block from the lean chain by its block ID.
 pub async fn get_block_by_id(
     block_id: ID,
@@ -29,28 +227,22 @@ pub async fn get_block_by_id(
     let block_root = match block_id {
         ID::Finalized => lean_chain
             .store
-            .lock()
-            .await
             .latest_finalized_provider()
             .get()
             .map(|checkpoint| checkpoint.root)
             .map_err(|err| ApiError::InternalError(format!("No latest finalized hash: {err:?}"))),
-        ID::Genesis => {
-            return Err(ApiError::NotFound(
-                "This ID type is currently not supported".to_string(),
-            ));
-        }
+        // Nodes started from a checkpoint don't have the genesis block.
+        ID::Genesis => lean_chain
+            .get_block_id_by_slot(0)
+            .await
+            .map_err(|err| ApiError::NotFound(format!("Genesis block not found: {err:?}"))),
         ID::Head => lean_chain
             .store
-            .lock()
-            .await
             .head_provider()
             .get()
             .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}"))),
         ID::Justified => lean_chain
             .store
-            .lock()
-            .await
             .latest_justified_provider()
             .get()
             .map(|checkpoint| checkpoint.root)
@@ -62,7 +254,7 @@ pub async fn get_block_by_id(
         ID::Root(root) => Ok(root),
     };
 
-    let provider = lean_chain.store.clone().lock().await.block_provider();
+    let provider = lean_chain.store.block_provider();
     provider
         .get(block_root?)
         .map(|maybe_signed_block| {
@@ -70,3 +262,68 @@ pub async fn get_block_by_id(
         })
         .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))
 }
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        http::StatusCode,
+        test::{TestRequest, call_and_read_body_json, call_service, init_service},
+        web::Data,
+    };
+    use ream_api_types_lean::block::BlockSummary;
+    use tree_hash::TreeHash;
+
+    use super::get_block_summary;
+    use crate::test_utils::lean_chain;
+
+    #[actix_web::test]
+    async fn test_get_block_summary() {
+        let (lean_chain, chain, roots) = lean_chain(3).await;
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(lean_chain))
+                .service(get_block_summary),
+        )
+        .await;
+
+        let summary: BlockSummary = call_and_read_body_json(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/blocks/{}/summary", roots[1]))
+                .to_request(),
+        )
+        .await;
+        let block = &chain.block(roots[1]).unwrap().message.block;
+        assert_eq!(summary.block_root, block.tree_hash_root());
+        assert_eq!(summary.slot, 2);
+        assert_eq!(summary.proposer_index, block.proposer_index);
+        assert_eq!(summary.attestation_count, 0);
+
+        // The genesis block is summarized against its own state.
+        for block_id in ["genesis", "0"] {
+            let summary: BlockSummary = call_and_read_body_json(
+                &app,
+                TestRequest::get()
+                    .uri(&format!("/blocks/{block_id}/summary"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(summary.block_root, chain.genesis_root());
+            assert!(summary.new_justifications.is_empty());
+            assert_eq!(summary.new_finalization, None);
+        }
+
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!(
+                    "/blocks/{}/summary",
+                    alloy_primitives::B256::repeat_byte(9)
+                ))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
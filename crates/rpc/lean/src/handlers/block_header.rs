@@ -1,22 +1,112 @@
 use actix_web::{
-    HttpResponse, Responder, get,
-    web::{Data, Path},
+    HttpRequest, Responder, get,
+    web::{Data, Path, Query},
 };
 use ream_api_types_common::{error::ApiError, id::ID};
+use ream_api_types_lean::query::SlotRangeQuery;
 use ream_consensus_lean::block::BlockHeader;
 use ream_fork_choice_lean::store::LeanStoreReader;
 
 use super::block::get_block_by_id;
+use crate::content::encode_response;
+
+/// Most headers returned by a single request for a slot range.
+pub const MAX_HEADERS_PER_REQUEST: u64 = 1024;
+
+// GET /lean/v0/headers?from_slot={from_slot}&to_slot={to_slot}
+#[get("/headers")]
+pub async fn get_block_headers(
+    http_request: HttpRequest,
+    query: Query<SlotRangeQuery>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let SlotRangeQuery { from_slot, to_slot } = query.into_inner();
+    if to_slot < from_slot || to_slot - from_slot >= MAX_HEADERS_PER_REQUEST {
+        return Err(ApiError::BadRequest(format!(
+            "Slot range must be ascending and at most {MAX_HEADERS_PER_REQUEST} slots long"
+        )));
+    }
+
+    let lean_db = lean_chain.read().await.store.clone();
+    let headers = lean_db
+        .run_blocking(move |lean_db| {
+            lean_db
+                .canonical_chain_iter(from_slot, to_slot)?
+                .map(|entry| entry.map(|(_, _, block)| BlockHeader::from(block.message.block)))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to read headers: {err}")))?;
+    Ok(encode_response(&http_request, &headers))
+}
 
 // GET /lean/v0/headers/{block_id}
 #[get("/headers/{block_id}")]
 pub async fn get_block_header(
+    http_request: HttpRequest,
     block_id: Path<ID>,
     lean_chain: Data<LeanStoreReader>,
 ) -> Result<impl Responder, ApiError> {
-    Ok(HttpResponse::Ok().json(BlockHeader::from(
-        get_block_by_id(block_id.into_inner(), lean_chain)
-            .await?
-            .ok_or_else(|| ApiError::NotFound("Block not found".to_string()))?,
-    )))
+    Ok(encode_response(
+        &http_request,
+        &BlockHeader::from(
+            get_block_by_id(block_id.into_inner(), lean_chain)
+                .await?
+                .ok_or_else(|| ApiError::NotFound("Block not found".to_string()))?,
+        ),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        http::StatusCode,
+        test::{TestRequest, call_and_read_body_json, call_service, init_service},
+        web::Data,
+    };
+    use ream_consensus_lean::block::BlockHeader;
+
+    use super::{MAX_HEADERS_PER_REQUEST, get_block_headers};
+    use crate::test_utils::lean_chain;
+
+    #[actix_web::test]
+    async fn test_get_block_headers() {
+        let (lean_chain, chain, roots) = lean_chain(3).await;
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(lean_chain))
+                .service(get_block_headers),
+        )
+        .await;
+
+        let headers: Vec<BlockHeader> = call_and_read_body_json(
+            &app,
+            TestRequest::get()
+                .uri("/headers?from_slot=1&to_slot=5")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            headers,
+            roots
+                .iter()
+                .map(|root| BlockHeader::from(chain.block(*root).unwrap().message.block.clone()))
+                .collect::<Vec<_>>()
+        );
+
+        for query in [
+            "from_slot=3&to_slot=1".to_string(),
+            format!("from_slot=0&to_slot={MAX_HEADERS_PER_REQUEST}"),
+        ] {
+            let response = call_service(
+                &app,
+                TestRequest::get()
+                    .uri(&format!("/headers?{query}"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
 }
@@ -0,0 +1,23 @@
+use actix_web::{HttpResponse, Responder, get, web::Data};
+use ream_api_types_common::error::ApiError;
+use ream_fork_choice_lean::store::LeanStoreReader;
+use ream_metrics::slot_report::last_slot_report;
+
+// GET /lean/v0/debug/forks
+#[get("/debug/forks")]
+pub async fn get_forks(lean_chain: Data<LeanStoreReader>) -> Result<impl Responder, ApiError> {
+    Ok(HttpResponse::Ok().json(
+        lean_chain.read().await.branch_tips().await.map_err(|err| {
+            ApiError::InternalError(format!("Could not get branch tips: {err:?}"))
+        })?,
+    ))
+}
+
+// GET /lean/v0/debug/slot_report
+#[get("/debug/slot_report")]
+pub async fn get_slot_report() -> Result<impl Responder, ApiError> {
+    Ok(HttpResponse::Ok().json(
+        last_slot_report()
+            .ok_or_else(|| ApiError::NotFound("No slot has finished yet".to_string()))?,
+    ))
+}
@@ -0,0 +1,133 @@
+use std::collections::HashSet;
+
+use actix_web::{
+    HttpResponse, Responder, get,
+    web::{Data, Path},
+};
+use ream_api_types_common::error::ApiError;
+use ream_api_types_lean::duties::{AttesterDuty, ProposerDuty, SlotRange};
+use ream_consensus_lean::{
+    proposer_schedule::{PROPOSER_LOOKAHEAD_SLOTS, ProposerSchedule},
+    state::LeanState,
+};
+use ream_fork_choice_lean::store::LeanStoreReader;
+use ream_network_spec::networks::lean_network_spec;
+use ream_storage::tables::{field::REDBField, table::REDBTable};
+
+/// Indices of the validators whose keys are loaded by this node.
+#[derive(Debug, Clone, Default)]
+pub struct LocalValidators {
+    pub indices: HashSet<u64>,
+}
+
+impl LocalValidators {
+    pub fn new(indices: impl IntoIterator<Item = u64>) -> Self {
+        Self {
+            indices: indices.into_iter().collect(),
+        }
+    }
+}
+
+/// The post state of the head block, whose active validators have the duties of the next slots.
+pub(crate) async fn head_state(lean_chain: &LeanStoreReader) -> Result<LeanState, ApiError> {
+    let lean_db = lean_chain.read().await.store.clone();
+    lean_db
+        .run_blocking(|lean_db| {
+            let head = lean_db.head_provider().get()?;
+            lean_db.state_provider().get(head)
+        })
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to get head state: {err}")))?
+        .ok_or_else(|| ApiError::NotFound("Head state not found".to_string()))
+}
+
+async fn head_proposer_schedule(
+    lean_chain: &LeanStoreReader,
+) -> Result<ProposerSchedule, ApiError> {
+    ProposerSchedule::new(&head_state(lean_chain).await?)
+        .map_err(|err| ApiError::InternalError(format!("No proposer schedule: {err:?}")))
+}
+
+fn proposer_duty(
+    slot: u64,
+    validator_index: u64,
+    local_validators: &LocalValidators,
+) -> ProposerDuty {
+    ProposerDuty {
+        slot,
+        validator_index,
+        is_local: local_validators.indices.contains(&validator_index),
+    }
+}
+
+// GET /lean/v0/validator/duties/proposer/{slot_range}
+#[get("/validator/duties/proposer/{slot_range}")]
+pub async fn get_proposer_duties(
+    slot_range: Path<String>,
+    local_validators: Data<LocalValidators>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let slot_range = slot_range
+        .parse::<SlotRange>()
+        .map_err(ApiError::BadRequest)?;
+    let proposer_schedule = head_proposer_schedule(&lean_chain).await?;
+
+    Ok(HttpResponse::Ok().json(
+        slot_range
+            .slots()
+            .map(|slot| {
+                proposer_duty(
+                    slot,
+                    proposer_schedule.proposer_index(slot),
+                    &local_validators,
+                )
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
+
+// GET /lean/v0/validator/proposer_schedule
+#[get("/validator/proposer_schedule")]
+pub async fn get_proposer_schedule(
+    local_validators: Data<LocalValidators>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let current_slot = lean_network_spec().current_slot();
+    let proposer_schedule = head_proposer_schedule(&lean_chain).await?;
+
+    Ok(HttpResponse::Ok().json(
+        proposer_schedule
+            .upcoming(current_slot, PROPOSER_LOOKAHEAD_SLOTS + 1)
+            .map(|(slot, validator_index)| proposer_duty(slot, validator_index, &local_validators))
+            .collect::<Vec<_>>(),
+    ))
+}
+
+// GET /lean/v0/validator/duties/attester/{slot}
+#[get("/validator/duties/attester/{slot}")]
+pub async fn get_attester_duties(
+    slot: Path<u64>,
+    local_validators: Data<LocalValidators>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let slot = slot.into_inner();
+    let head_state = head_state(&lean_chain).await?;
+    let proposer_index = head_state
+        .proposer_index_at(slot)
+        .map_err(|err| ApiError::InternalError(format!("No proposer for slot {slot}: {err:?}")))?;
+
+    // Every validator except the proposer attests in each slot, the proposer's attestation is
+    // part of its block.
+    Ok(HttpResponse::Ok().json(
+        head_state
+            .active_validator_indices()
+            .into_iter()
+            .filter(|validator_index| *validator_index != proposer_index)
+            .map(|validator_index| AttesterDuty {
+                slot,
+                validator_index,
+                is_local: local_validators.indices.contains(&validator_index),
+            })
+            .collect::<Vec<_>>(),
+    ))
+}
@@ -12,8 +12,6 @@ pub async fn get_head(lean_chain: Data<LeanStoreReader>) -> Result<impl Responde
             .read()
             .await
             .store
-            .lock()
-            .await
             .head_provider()
             .get()
             .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}")))?,
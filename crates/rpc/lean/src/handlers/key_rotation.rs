@@ -0,0 +1,55 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, post,
+    web::{Bytes, Data},
+};
+use ream_api_types_common::error::ApiError;
+use ream_consensus_lean::validator::SignedKeyRotation;
+use ream_fork_choice_lean::store::LeanStoreReader;
+use ream_storage::tables::{field::REDBField, table::REDBTable};
+
+use crate::content::decode_body;
+
+// POST /lean/v0/key_rotations
+#[post("/key_rotations")]
+pub async fn post_key_rotation(
+    http_request: HttpRequest,
+    body: Bytes,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let signed_key_rotation = decode_body::<SignedKeyRotation>(&http_request, &body)?;
+
+    let lean_chain = lean_chain.read().await;
+    let lean_db = lean_chain.store.clone();
+    let head_root = lean_db
+        .head_provider()
+        .get()
+        .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}")))?;
+    let head_state = lean_db
+        .run_blocking(move |lean_db| lean_db.state_provider().get(head_root))
+        .await
+        .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?
+        .ok_or_else(|| ApiError::InternalError("Head state not found".to_string()))?;
+
+    // The rotation has to be includable in the next block, and signed by the current key.
+    let rotation = &signed_key_rotation.message;
+    head_state
+        .validate_key_rotation(rotation)
+        .map_err(|err| ApiError::BadRequest(format!("Invalid key rotation: {err:?}")))?;
+    let public_key = head_state
+        .validator_public_key_at(rotation.validator_index, head_state.slot)
+        .ok_or_else(|| {
+            ApiError::BadRequest(format!("Unknown validator {}", rotation.validator_index))
+        })?;
+    let is_valid = signed_key_rotation.verify(public_key).map_err(|err| {
+        ApiError::BadRequest(format!("Failed to verify key rotation signature: {err:?}"))
+    })?;
+    if !is_valid {
+        return Err(ApiError::BadRequest(format!(
+            "Key rotation isn't signed by validator {}",
+            rotation.validator_index
+        )));
+    }
+
+    lean_chain.key_rotation_pool.insert(signed_key_rotation);
+    Ok(HttpResponse::Accepted().finish())
+}
@@ -0,0 +1,80 @@
+use actix_web::{
+    HttpResponse, Responder, delete, get, post,
+    web::{Data, Json},
+};
+use ream_api_types_common::error::ApiError;
+use ream_api_types_lean::keymanager::{
+    DeleteKeystoresRequest, ImportKeystoresRequest, ImportKeystoresResponse, ListKeystoresResponse,
+};
+use ream_validator_lean::keymanager::KeymanagerMessage;
+use tokio::sync::{mpsc, oneshot};
+
+fn send_keymanager_message(
+    keymanager_sender: &mpsc::UnboundedSender<KeymanagerMessage>,
+    message: KeymanagerMessage,
+) -> Result<(), ApiError> {
+    keymanager_sender
+        .send(message)
+        .map_err(|err| ApiError::InternalError(format!("Validator service is unavailable: {err}")))
+}
+
+// GET /lean/v0/keystores
+#[get("/keystores")]
+pub async fn get_keystores(
+    keymanager_sender: Data<mpsc::UnboundedSender<KeymanagerMessage>>,
+) -> Result<impl Responder, ApiError> {
+    let (sender, receiver) = oneshot::channel();
+    send_keymanager_message(
+        &keymanager_sender,
+        KeymanagerMessage::ListKeystores { sender },
+    )?;
+
+    let data = receiver
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to list keystores: {err}")))?;
+    Ok(HttpResponse::Ok().json(ListKeystoresResponse { data }))
+}
+
+// POST /lean/v0/keystores
+#[post("/keystores")]
+pub async fn post_keystores(
+    request: Json<ImportKeystoresRequest>,
+    keymanager_sender: Data<mpsc::UnboundedSender<KeymanagerMessage>>,
+) -> Result<impl Responder, ApiError> {
+    let (sender, receiver) = oneshot::channel();
+    send_keymanager_message(
+        &keymanager_sender,
+        KeymanagerMessage::ImportKeystores {
+            request: request.into_inner(),
+            sender,
+        },
+    )?;
+
+    let data = receiver
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to import keystores: {err}")))?
+        .map_err(|err| ApiError::BadRequest(format!("Could not import keystores: {err}")))?;
+    Ok(HttpResponse::Ok().json(ImportKeystoresResponse { data }))
+}
+
+// DELETE /lean/v0/keystores
+#[delete("/keystores")]
+pub async fn delete_keystores(
+    request: Json<DeleteKeystoresRequest>,
+    keymanager_sender: Data<mpsc::UnboundedSender<KeymanagerMessage>>,
+) -> Result<impl Responder, ApiError> {
+    let (sender, receiver) = oneshot::channel();
+    send_keymanager_message(
+        &keymanager_sender,
+        KeymanagerMessage::DeleteKeystores {
+            public_keys: request.into_inner().pubkeys,
+            sender,
+        },
+    )?;
+
+    let response = receiver
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to delete keystores: {err}")))?
+        .map_err(|err| ApiError::InternalError(format!("Could not delete keystores: {err}")))?;
+    Ok(HttpResponse::Ok().json(response))
+}
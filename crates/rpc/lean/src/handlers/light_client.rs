@@ -0,0 +1,190 @@
+use actix_web::{
+    HttpRequest, Responder, get,
+    web::{Data, Path, Query},
+};
+use alloy_primitives::B256;
+use ream_api_types_common::error::ApiError;
+use ream_api_types_lean::query::SlotRangeQuery;
+use ream_fork_choice_lean::{
+    light_client::{
+        light_client_bootstrap, light_client_finality_update, light_client_optimistic_update,
+        light_client_updates,
+    },
+    store::LeanStoreReader,
+};
+use ream_storage::tables::field::REDBField;
+
+use crate::content::encode_response;
+
+/// Most slots covered by a single request for light client updates.
+pub const MAX_LIGHT_CLIENT_UPDATE_SLOTS: u64 = 128;
+
+// GET /lean/v0/light_client/bootstrap/{block_root}
+#[get("/light_client/bootstrap/{block_root}")]
+pub async fn get_light_client_bootstrap(
+    http_request: HttpRequest,
+    block_root: Path<B256>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let lean_db = lean_chain.read().await.store.clone();
+    let bootstrap = light_client_bootstrap(&lean_db, block_root.into_inner())
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to build bootstrap: {err:?}")))?
+        .ok_or_else(|| ApiError::NotFound("Block or its state not found".to_string()))?;
+    Ok(encode_response(&http_request, &bootstrap))
+}
+
+// GET /lean/v0/light_client/updates?from_slot={from_slot}&to_slot={to_slot}
+#[get("/light_client/updates")]
+pub async fn get_light_client_updates(
+    http_request: HttpRequest,
+    query: Query<SlotRangeQuery>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let SlotRangeQuery { from_slot, to_slot } = query.into_inner();
+    if to_slot < from_slot || to_slot - from_slot >= MAX_LIGHT_CLIENT_UPDATE_SLOTS {
+        return Err(ApiError::BadRequest(format!(
+            "Slot range must be ascending and at most {MAX_LIGHT_CLIENT_UPDATE_SLOTS} slots long"
+        )));
+    }
+
+    let lean_db = lean_chain.read().await.store.clone();
+    let updates = light_client_updates(&lean_db, from_slot, to_slot)
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to build updates: {err:?}")))?;
+    Ok(encode_response(&http_request, &updates))
+}
+
+// GET /lean/v0/light_client/finality_update
+#[get("/light_client/finality_update")]
+pub async fn get_light_client_finality_update(
+    http_request: HttpRequest,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let lean_db = lean_chain.read().await.store.clone();
+    let head = lean_db
+        .head_provider()
+        .get()
+        .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}")))?;
+    let update = light_client_finality_update(&lean_db, head)
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to build update: {err:?}")))?
+        .ok_or_else(|| ApiError::NotFound("No block has been finalized yet".to_string()))?;
+    Ok(encode_response(&http_request, &update))
+}
+
+// GET /lean/v0/light_client/optimistic_update
+#[get("/light_client/optimistic_update")]
+pub async fn get_light_client_optimistic_update(
+    http_request: HttpRequest,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let lean_db = lean_chain.read().await.store.clone();
+    let head = lean_db
+        .head_provider()
+        .get()
+        .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}")))?;
+    let update = light_client_optimistic_update(&lean_db, head)
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to build update: {err:?}")))?
+        .ok_or_else(|| ApiError::NotFound("Head block not found".to_string()))?;
+    Ok(encode_response(&http_request, &update))
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        App,
+        http::StatusCode,
+        test::{TestRequest, call_and_read_body_json, call_service, init_service},
+        web::Data,
+    };
+    use alloy_primitives::B256;
+    use ream_consensus_lean::light_client::{
+        LightClientBootstrap, LightClientFinalityUpdate, LightClientOptimisticUpdate,
+    };
+
+    use super::{
+        MAX_LIGHT_CLIENT_UPDATE_SLOTS, get_light_client_bootstrap,
+        get_light_client_finality_update, get_light_client_optimistic_update,
+        get_light_client_updates,
+    };
+    use crate::test_utils::lean_chain;
+
+    #[actix_web::test]
+    async fn test_light_client_handlers() {
+        let (lean_chain, chain, roots) = lean_chain(3).await;
+        let app = init_service(
+            App::new()
+                .app_data(Data::new(lean_chain))
+                .service(get_light_client_bootstrap)
+                .service(get_light_client_updates)
+                .service(get_light_client_finality_update)
+                .service(get_light_client_optimistic_update),
+        )
+        .await;
+
+        let block = &chain.block(roots[1]).unwrap().message.block;
+        let bootstrap: LightClientBootstrap = call_and_read_body_json(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/light_client/bootstrap/{}", roots[1]))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(
+            bootstrap,
+            LightClientBootstrap::new(block, chain.state(roots[1]).unwrap()).unwrap()
+        );
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/light_client/bootstrap/{}", B256::repeat_byte(1)))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let head = &chain.block(roots[2]).unwrap().message.block;
+        let optimistic_update: LightClientOptimisticUpdate = call_and_read_body_json(
+            &app,
+            TestRequest::get()
+                .uri("/light_client/optimistic_update")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(optimistic_update, LightClientOptimisticUpdate::from(head));
+
+        // Empty blocks don't justify, so nothing after genesis is finalized.
+        let response = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/light_client/finality_update")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let updates: Vec<LightClientFinalityUpdate> = call_and_read_body_json(
+            &app,
+            TestRequest::get()
+                .uri("/light_client/updates?from_slot=0&to_slot=3")
+                .to_request(),
+        )
+        .await;
+        assert!(updates.is_empty());
+
+        for query in [
+            "from_slot=3&to_slot=1".to_string(),
+            format!("from_slot=0&to_slot={MAX_LIGHT_CLIENT_UPDATE_SLOTS}"),
+        ] {
+            let response = call_service(
+                &app,
+                TestRequest::get()
+                    .uri(&format!("/light_client/updates?{query}"))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
+}
@@ -1,5 +1,15 @@
+pub mod admin;
+pub mod attestation;
 pub mod block;
 pub mod block_header;
+pub mod debug;
+pub mod duties;
 pub mod head;
+pub mod key_rotation;
+pub mod keymanager;
+pub mod light_client;
+pub mod node;
 pub mod peer;
+pub mod read_only;
 pub mod state;
+pub mod validator;
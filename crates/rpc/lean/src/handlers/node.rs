@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, Responder, get, web::Data};
+use ream_api_types_common::error::ApiError;
+use ream_api_types_lean::node::{Identity, SyncStatus};
+use ream_network_state_lean::NetworkState;
+
+fn sync_status(network_state: &NetworkState) -> SyncStatus {
+    SyncStatus::new(
+        network_state.head_checkpoint.read().slot,
+        network_state.highest_peer_head_slot(),
+    )
+}
+
+// GET /lean/v0/node/identity
+#[get("/node/identity")]
+pub async fn get_identity(
+    network_state: Data<Arc<NetworkState>>,
+) -> Result<impl Responder, ApiError> {
+    let local_node = network_state
+        .local_node
+        .read()
+        .clone()
+        .ok_or_else(|| ApiError::InternalError("Network service is not started".to_string()))?;
+
+    Ok(HttpResponse::Ok().json(Identity {
+        peer_id: local_node.peer_id.to_string(),
+        enr: local_node.enr,
+        p2p_addresses: local_node
+            .listen_addresses
+            .iter()
+            .map(ToString::to_string)
+            .collect(),
+    }))
+}
+
+// GET /lean/v0/node/syncing
+#[get("/node/syncing")]
+pub async fn get_syncing(
+    network_state: Data<Arc<NetworkState>>,
+) -> Result<impl Responder, ApiError> {
+    Ok(HttpResponse::Ok().json(sync_status(&network_state)))
+}
+
+// GET /lean/v0/node/health
+#[get("/node/health")]
+pub async fn get_health(network_state: Data<Arc<NetworkState>>) -> impl Responder {
+    match sync_status(&network_state).is_syncing {
+        true => HttpResponse::ServiceUnavailable().finish(),
+        false => HttpResponse::Ok().finish(),
+    }
+}
@@ -1,16 +1,89 @@
 use std::sync::Arc;
 
-use actix_web::{HttpResponse, Responder, get, web::Data};
+use actix_web::{
+    HttpResponse, Responder, get,
+    web::{Data, Query},
+};
+use libp2p::{Multiaddr, PeerId};
 use ream_api_types_common::error::ApiError;
-use ream_network_state_lean::NetworkState;
-use ream_peer::{ConnectionState, PeerCount};
+use ream_api_types_lean::query::{ConnectionStateQuery, DirectionQuery};
+use ream_consensus_lean::checkpoint::Checkpoint;
+use ream_network_state_lean::{NetworkState, cached_peer::CachedPeer};
+use ream_peer::{ConnectionState, Direction, PeerCount};
+use serde::Serialize;
 
 // /lean/v0/node/peers
 #[get("/node/peers")]
 pub async fn list_peers(
     network_state: Data<Arc<NetworkState>>,
+    state: Query<ConnectionStateQuery>,
+    direction: Query<DirectionQuery>,
 ) -> Result<impl Responder, ApiError> {
-    Ok(HttpResponse::Ok().json(network_state.peer_table.lock().clone()))
+    let peers: Vec<Peer> = network_state
+        .peer_table
+        .lock()
+        .values()
+        .filter(|cached_peer| {
+            // Filter by state if provided
+            if let Some(ref states) = state.state
+                && !states.contains(&cached_peer.state)
+            {
+                return false;
+            }
+
+            // Filter by direction if provided, unknown directions never match
+            if let Some(ref directions) = direction.direction
+                && (cached_peer.direction == Direction::Unknown
+                    || !directions.contains(&cached_peer.direction))
+            {
+                return false;
+            }
+
+            true
+        })
+        .map(Peer::from)
+        .collect();
+
+    Ok(HttpResponse::Ok().json(peers))
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Peer {
+    /// libp2p peer ID
+    pub peer_id: PeerId,
+
+    /// Last known multiaddress observed for the peer
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_seen_p2p_address: Option<Multiaddr>,
+
+    /// Current known connection state
+    pub state: ConnectionState,
+
+    /// Direction of the most recent connection (inbound/outbound)
+    pub direction: Direction,
+
+    /// Seconds since we last received a message from this peer
+    pub last_seen: u64,
+
+    /// Head checkpoint from the peer's latest status
+    pub head_checkpoint: Option<Checkpoint>,
+
+    /// Finalized checkpoint from the peer's latest status
+    pub finalized_checkpoint: Option<Checkpoint>,
+}
+
+impl From<&CachedPeer> for Peer {
+    fn from(cached_peer: &CachedPeer) -> Self {
+        Self {
+            peer_id: cached_peer.peer_id,
+            last_seen_p2p_address: cached_peer.last_seen_p2p_address.clone(),
+            state: cached_peer.state,
+            direction: cached_peer.direction,
+            last_seen: cached_peer.last_seen.elapsed().as_secs(),
+            head_checkpoint: cached_peer.head_checkpoint,
+            finalized_checkpoint: cached_peer.finalized_checkpoint,
+        }
+    }
 }
 
 // /lean/v0/node/peer_count
@@ -0,0 +1,92 @@
+use actix_web::{
+    HttpRequest, HttpResponse, Responder, get,
+    web::{Data, Path},
+};
+use alloy_primitives::B256;
+use ream_api_types_common::{error::ApiError, id::ID};
+use ream_api_types_lean::head::Head;
+use ream_storage::db::read_only::ReadOnlyLeanDB;
+
+use crate::content::encode_response;
+
+/// Resolves an ID to a block root. State roots are looked up through the state root index when
+/// `by_state_root` is set, mirroring the `/states/{state_id}` endpoint of a full node.
+fn resolve_block_root(
+    id: ID,
+    lean_db: &ReadOnlyLeanDB,
+    by_state_root: bool,
+) -> Result<B256, ApiError> {
+    match id {
+        ID::Finalized => lean_db
+            .latest_finalized()
+            .map(|checkpoint| checkpoint.root)
+            .map_err(|err| ApiError::InternalError(format!("No latest finalized hash: {err:?}"))),
+        ID::Genesis => Err(ApiError::NotFound(
+            "This ID type is currently not supported".to_string(),
+        )),
+        ID::Head => lean_db
+            .head()
+            .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}"))),
+        ID::Justified => lean_db
+            .latest_justified()
+            .map(|checkpoint| checkpoint.root)
+            .map_err(|err| ApiError::InternalError(format!("No latest justified hash: {err:?}"))),
+        ID::Slot(slot) => lean_db
+            .block_root_at_slot(slot)
+            .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?
+            .ok_or_else(|| ApiError::NotFound(format!("No block for slot {slot}"))),
+        ID::Root(root) if by_state_root => lean_db
+            .block_root_by_state_root(root)
+            .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?
+            .ok_or_else(|| {
+                ApiError::NotFound(format!("Block ID not found for state root: {root:?}"))
+            }),
+        ID::Root(root) => Ok(root),
+    }
+}
+
+// GET /lean/v0/head
+#[get("/head")]
+pub async fn get_head(lean_db: Data<ReadOnlyLeanDB>) -> Result<impl Responder, ApiError> {
+    Ok(HttpResponse::Ok().json(Head {
+        head: lean_db
+            .head()
+            .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}")))?,
+    }))
+}
+
+// GET /lean/v0/blocks/{block_id}
+#[get("/blocks/{block_id}")]
+pub async fn get_block(
+    http_request: HttpRequest,
+    block_id: Path<ID>,
+    lean_db: Data<ReadOnlyLeanDB>,
+) -> Result<impl Responder, ApiError> {
+    let block_root = resolve_block_root(block_id.into_inner(), &lean_db, false)?;
+    Ok(encode_response(
+        &http_request,
+        &lean_db
+            .block(block_root)
+            .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?
+            .ok_or_else(|| ApiError::NotFound("Block not found".to_string()))?
+            .message
+            .block,
+    ))
+}
+
+// GET /lean/v0/states/{state_id}
+#[get("/states/{state_id}")]
+pub async fn get_state(
+    http_request: HttpRequest,
+    state_id: Path<ID>,
+    lean_db: Data<ReadOnlyLeanDB>,
+) -> Result<impl Responder, ApiError> {
+    let block_root = resolve_block_root(state_id.into_inner(), &lean_db, true)?;
+    Ok(encode_response(
+        &http_request,
+        &lean_db
+            .state(block_root)
+            .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?
+            .ok_or_else(|| ApiError::NotFound("Lean state not found".to_string()))?,
+    ))
+}
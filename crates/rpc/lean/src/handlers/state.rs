@@ -1,22 +1,68 @@
 use actix_web::{
-    HttpResponse, Responder, get,
+    HttpRequest, Responder, get,
     web::{Data, Path},
 };
 use ream_api_types_common::{error::ApiError, id::ID};
+use ream_api_types_lean::state::FinalityCheckpoints;
+use ream_consensus_lean::state::LeanState;
 use ream_fork_choice_lean::store::LeanStoreReader;
 use ream_storage::tables::{field::REDBField, table::REDBTable};
 
+use crate::content::encode_response;
+
 // GET /lean/v0/states/{state_id}
 #[get("/states/{state_id}")]
 pub async fn get_state(
+    http_request: HttpRequest,
+    state_id: Path<ID>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    Ok(encode_response(
+        &http_request,
+        &get_state_by_id(state_id.into_inner(), &lean_chain).await?,
+    ))
+}
+
+// GET /lean/v0/states/{state_id}/validators
+#[get("/states/{state_id}/validators")]
+pub async fn get_state_validators(
+    http_request: HttpRequest,
+    state_id: Path<ID>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let state = get_state_by_id(state_id.into_inner(), &lean_chain).await?;
+    Ok(encode_response(&http_request, &state.validators))
+}
+
+// GET /lean/v0/states/{state_id}/finality_checkpoints
+#[get("/states/{state_id}/finality_checkpoints")]
+pub async fn get_state_finality_checkpoints(
+    http_request: HttpRequest,
     state_id: Path<ID>,
     lean_chain: Data<LeanStoreReader>,
 ) -> Result<impl Responder, ApiError> {
+    let state = get_state_by_id(state_id.into_inner(), &lean_chain).await?;
+    Ok(encode_response(
+        &http_request,
+        &FinalityCheckpoints {
+            slot: state.slot,
+            latest_justified: state.latest_justified,
+            latest_finalized: state.latest_finalized,
+        },
+    ))
+}
+
+/// Resolves a state ID to the post state of its block, regenerating states which are no longer
+/// stored, such as those of past slots.
+async fn get_state_by_id(
+    state_id: ID,
+    lean_chain: &LeanStoreReader,
+) -> Result<LeanState, ApiError> {
     let lean_chain = lean_chain.read().await;
 
-    let block_root = match state_id.into_inner() {
+    let block_root = match state_id {
         ID::Finalized => {
-            let db = lean_chain.store.lock().await;
+            let db = &lean_chain.store;
             Ok(db
                 .latest_finalized_provider()
                 .get()
@@ -32,13 +78,11 @@ pub async fn get_state(
         }
         ID::Head => lean_chain
             .store
-            .lock()
-            .await
             .head_provider()
             .get()
             .map_err(|err| ApiError::InternalError(format!("Could not get head: {err:?}"))),
         ID::Justified => {
-            let db = lean_chain.store.lock().await;
+            let db = &lean_chain.store;
             Ok(db
                 .latest_justified_provider()
                 .get()
@@ -52,7 +96,7 @@ pub async fn get_state(
             .await
             .map_err(|err| ApiError::InternalError(format!("No block for slot {slot}: {err:?}"))),
         ID::Root(root) => {
-            let provider = lean_chain.store.lock().await.state_root_index_provider();
+            let provider = lean_chain.store.state_root_index_provider();
 
             provider
                 .get(root)
@@ -63,12 +107,13 @@ pub async fn get_state(
         }
     };
 
-    let provider = lean_chain.store.clone().lock().await.state_provider();
-
-    Ok(HttpResponse::Ok().json(
-        provider
-            .get(block_root?)
-            .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?
-            .ok_or_else(|| ApiError::NotFound("Lean state not found".to_string()))?,
-    ))
+    // Replaying blocks can take a while, don't hold the store while doing so.
+    let block_root = block_root?;
+    let state_regenerator = lean_chain.state_regenerator.clone();
+    drop(lean_chain);
+    state_regenerator
+        .get_state_at(block_root)
+        .await
+        .map_err(|err| ApiError::InternalError(format!("Failed to get state: {err:?}")))?
+        .ok_or_else(|| ApiError::NotFound("Lean state not found".to_string()))
 }
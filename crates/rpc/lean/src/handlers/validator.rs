@@ -0,0 +1,104 @@
+use actix_web::{
+    HttpResponse, Responder, get,
+    middleware::from_fn,
+    web::{Data, Path},
+};
+use ream_api_types_common::error::ApiError;
+use ream_api_types_lean::performance::ValidatorPerformance;
+use ream_chain_lean::messages::LeanChainServiceMessage;
+use ream_fork_choice_lean::store::LeanStoreReader;
+use ream_network_spec::networks::lean_network_spec;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{auth::require_admin_token, handlers::duties::head_state};
+
+/// How many slots away from the current slot blocks and attestation data can be requested for,
+/// to make up for clock differences between the node and its validator client.
+const MAX_DUTY_SLOT_DISTANCE: u64 = 1;
+
+/// Rejects slots the node wouldn't build a block or attestation for, so a request can't make it
+/// advance a state over an arbitrary number of slots.
+fn ensure_near_current_slot(slot: u64) -> Result<(), ApiError> {
+    let current_slot = lean_network_spec().current_slot();
+    if slot.abs_diff(current_slot) > MAX_DUTY_SLOT_DISTANCE {
+        return Err(ApiError::BadRequest(format!(
+            "Slot {slot} is too far from the current slot {current_slot}"
+        )));
+    }
+    Ok(())
+}
+
+// GET /lean/v0/validator/blocks/{slot}
+#[get("/validator/blocks/{slot}", wrap = "from_fn(require_admin_token)")]
+pub async fn get_produce_block(
+    slot: Path<u64>,
+    chain_sender: Data<mpsc::UnboundedSender<LeanChainServiceMessage>>,
+) -> Result<impl Responder, ApiError> {
+    let slot = slot.into_inner();
+    ensure_near_current_slot(slot)?;
+
+    let (sender, receiver) = oneshot::channel();
+    chain_sender
+        .send(LeanChainServiceMessage::ProduceBlock { slot, sender })
+        .map_err(|err| ApiError::InternalError(format!("Chain service is unavailable: {err}")))?;
+
+    Ok(HttpResponse::Ok().json(
+        receiver
+            .await
+            .map_err(|err| ApiError::InternalError(format!("Failed to produce block: {err}")))?,
+    ))
+}
+
+// GET /lean/v0/validator/attestation_data/{slot}
+#[get(
+    "/validator/attestation_data/{slot}",
+    wrap = "from_fn(require_admin_token)"
+)]
+pub async fn get_attestation_data(
+    slot: Path<u64>,
+    chain_sender: Data<mpsc::UnboundedSender<LeanChainServiceMessage>>,
+) -> Result<impl Responder, ApiError> {
+    let slot = slot.into_inner();
+    ensure_near_current_slot(slot)?;
+
+    let (sender, receiver) = oneshot::channel();
+    chain_sender
+        .send(LeanChainServiceMessage::BuildAttestationData { slot, sender })
+        .map_err(|err| ApiError::InternalError(format!("Chain service is unavailable: {err}")))?;
+
+    Ok(HttpResponse::Ok().json(receiver.await.map_err(|err| {
+        ApiError::InternalError(format!("Failed to build attestation data: {err}"))
+    })?))
+}
+
+// GET /lean/v0/validator/{validator_index}/performance
+#[get("/validator/{validator_index}/performance")]
+pub async fn get_validator_performance(
+    validator_index: Path<u64>,
+    lean_chain: Data<LeanStoreReader>,
+) -> Result<impl Responder, ApiError> {
+    let validator_index = validator_index.into_inner();
+    if validator_index >= head_state(&lean_chain).await?.validators.len() as u64 {
+        return Err(ApiError::NotFound(format!(
+            "Validator {validator_index} doesn't exist"
+        )));
+    }
+
+    let record = lean_chain
+        .read()
+        .await
+        .store
+        .validator_performance_provider()
+        .get_record(validator_index)
+        .map_err(|err| ApiError::InternalError(format!("DB error: {err}")))?;
+
+    Ok(HttpResponse::Ok().json(ValidatorPerformance {
+        validator_index,
+        first_slot: record.slots.first().map(|slot| slot.slot),
+        last_slot: record.slots.last().map(|slot| slot.slot),
+        proposals_made: record.proposals_made(),
+        proposals_missed: record.proposals_missed(),
+        attestations_included: record.attestations_included(),
+        attestations_missed: record.attestations_missed(),
+    }))
+}
@@ -1,3 +1,7 @@
+pub mod auth;
+pub mod content;
 pub mod handlers;
 pub mod routes;
 pub mod server;
+#[cfg(test)]
+mod test_utils;
@@ -0,0 +1,29 @@
+use actix_web::{
+    middleware::from_fn,
+    web::{ServiceConfig, scope},
+};
+
+use crate::{
+    auth::require_admin_token,
+    handlers::admin::{
+        delete_log_level, delete_trusted_peer, post_ban_peer, post_log_level, post_prune,
+        post_snapshot, post_trusted_peer,
+    },
+};
+
+/// Creates and returns all `/admin` routes. Every admin route requires a bearer token.
+pub fn register_admin_routes(cfg: &mut ServiceConfig) {
+    let admin = scope("/admin")
+        .wrap(from_fn(require_admin_token))
+        .service(post_log_level)
+        .service(delete_log_level)
+        .service(post_snapshot)
+        .service(post_prune)
+        .service(post_trusted_peer)
+        .service(delete_trusted_peer)
+        .service(post_ban_peer);
+    // Injecting network faults is only for testing builds, never for production nodes.
+    #[cfg(feature = "testing")]
+    let admin = admin.service(crate::handlers::admin::post_network_faults);
+    cfg.service(admin);
+}
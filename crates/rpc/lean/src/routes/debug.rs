@@ -0,0 +1,8 @@
+use actix_web::web::ServiceConfig;
+
+use crate::handlers::debug::{get_forks, get_slot_report};
+
+/// Creates and returns all `/debug` routes.
+pub fn register_debug_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_forks).service(get_slot_report);
+}
@@ -0,0 +1,21 @@
+use actix_web::{
+    middleware::from_fn,
+    web::{ServiceConfig, scope},
+};
+
+use crate::{
+    auth::require_admin_token,
+    handlers::keymanager::{delete_keystores, get_keystores, post_keystores},
+};
+
+/// Creates and returns the keymanager routes of a validator client. Every route requires a bearer
+/// token.
+pub fn register_keymanager_routes(cfg: &mut ServiceConfig) {
+    cfg.service(
+        scope("/lean/v0")
+            .wrap(from_fn(require_admin_token))
+            .service(get_keystores)
+            .service(post_keystores)
+            .service(delete_keystores),
+    );
+}
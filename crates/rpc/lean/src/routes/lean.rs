@@ -1,13 +1,43 @@
 use actix_web::web::ServiceConfig;
 
 use crate::handlers::{
-    block::get_block, block_header::get_block_header, head::get_head, state::get_state,
+    attestation::{get_aggregated_attestations, post_attestation},
+    block::{get_block, get_block_attestations, get_block_summary, post_block},
+    block_header::{get_block_header, get_block_headers},
+    duties::{get_attester_duties, get_proposer_duties, get_proposer_schedule},
+    head::get_head,
+    key_rotation::post_key_rotation,
+    light_client::{
+        get_light_client_bootstrap, get_light_client_finality_update,
+        get_light_client_optimistic_update, get_light_client_updates,
+    },
+    state::{get_state, get_state_finality_checkpoints, get_state_validators},
+    validator::{get_attestation_data, get_produce_block, get_validator_performance},
 };
 
 /// Creates and returns all `/lean` routes.
 pub fn register_lean_routes(cfg: &mut ServiceConfig) {
     cfg.service(get_head)
         .service(get_block)
+        .service(get_block_attestations)
+        .service(get_block_summary)
+        .service(post_block)
+        .service(get_block_headers)
         .service(get_block_header)
-        .service(get_state);
+        .service(get_state)
+        .service(get_state_validators)
+        .service(get_state_finality_checkpoints)
+        .service(get_aggregated_attestations)
+        .service(post_attestation)
+        .service(post_key_rotation)
+        .service(get_proposer_duties)
+        .service(get_attester_duties)
+        .service(get_proposer_schedule)
+        .service(get_produce_block)
+        .service(get_attestation_data)
+        .service(get_validator_performance)
+        .service(get_light_client_bootstrap)
+        .service(get_light_client_updates)
+        .service(get_light_client_finality_update)
+        .service(get_light_client_optimistic_update);
 }
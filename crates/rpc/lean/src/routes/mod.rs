@@ -1,15 +1,42 @@
+pub mod admin;
+pub mod debug;
+pub mod keymanager;
 pub mod lean;
 pub mod node;
+pub mod read_only;
 use actix_web::web::{ServiceConfig, scope};
+use ream_rpc_common::handlers::metrics::get_metrics;
 
-pub fn get_v0_routes(config: &mut ServiceConfig) {
-    config.service(
-        scope("/lean/v0")
-            .configure(lean::register_lean_routes)
-            .configure(node::register_node_routes),
-    );
+/// The optional namespaces of the lean API.
+#[derive(Debug, Clone, Copy)]
+pub struct Namespaces {
+    pub debug: bool,
+    pub admin: bool,
+    /// `GET /metrics`, outside of the versioned API.
+    pub metrics: bool,
 }
 
-pub fn register_routers(config: &mut ServiceConfig) {
-    config.configure(get_v0_routes);
+pub fn get_v0_routes(config: &mut ServiceConfig, namespaces: Namespaces) {
+    let mut v0 = scope("/lean/v0")
+        .configure(lean::register_lean_routes)
+        .configure(node::register_node_routes);
+    if namespaces.admin {
+        v0 = v0.configure(admin::register_admin_routes);
+    }
+    if namespaces.debug {
+        v0 = v0.configure(debug::register_debug_routes);
+    }
+    config.service(v0);
+}
+
+pub fn register_routers(config: &mut ServiceConfig, namespaces: Namespaces) {
+    config.configure(|config| get_v0_routes(config, namespaces));
+    if namespaces.metrics {
+        config.service(get_metrics);
+    }
+}
+
+/// Routes served by a read-only node, which has no fork choice store or network.
+pub fn register_read_only_routers(config: &mut ServiceConfig) {
+    config.service(scope("/lean/v0").configure(read_only::register_read_only_routes));
 }
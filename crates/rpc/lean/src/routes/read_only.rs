@@ -0,0 +1,12 @@
+use actix_web::web::ServiceConfig;
+use ream_rpc_common::handlers::version::get_version;
+
+use crate::handlers::read_only::{get_block, get_head, get_state};
+
+/// Creates and returns the `/lean` and `/node` routes a read-only node can serve.
+pub fn register_read_only_routes(cfg: &mut ServiceConfig) {
+    cfg.service(get_version)
+        .service(get_head)
+        .service(get_block)
+        .service(get_state);
+}
@@ -1,22 +1,88 @@
 use std::{io::Result, sync::Arc};
 
+use ream_chain_lean::{messages::LeanChainServiceMessage, p2p_request::LeanP2PRequest};
+use ream_executor::ShutdownSignal;
 use ream_fork_choice_lean::store::LeanStoreReader;
 use ream_network_state_lean::NetworkState;
 use ream_rpc_common::{config::RpcServerConfig, server::RpcServerBuilder};
+use ream_storage::db::read_only::ReadOnlyLeanDB;
+use ream_validator_lean::keymanager::KeymanagerMessage;
+use tokio::sync::mpsc;
 
-use crate::routes::register_routers;
+use crate::{
+    auth::AdminAuth,
+    handlers::{admin::SnapshotDir, duties::LocalValidators},
+    routes::{
+        Namespaces, keymanager::register_keymanager_routes, register_read_only_routers,
+        register_routers,
+    },
+};
 
-/// Start the Lean API server.
+/// Start the Lean API server. It stops once `shutdown` fires and in-flight requests finish.
+#[allow(clippy::too_many_arguments)]
 pub async fn start(
     server_config: RpcServerConfig,
     lean_chain: LeanStoreReader,
     network_state: Arc<NetworkState>,
+    local_validators: LocalValidators,
+    chain_sender: mpsc::UnboundedSender<LeanChainServiceMessage>,
+    p2p_sender: mpsc::UnboundedSender<LeanP2PRequest>,
+    admin_auth: AdminAuth,
+    snapshot_dir: SnapshotDir,
+    shutdown: ShutdownSignal,
 ) -> Result<()> {
+    let namespaces = Namespaces {
+        debug: server_config.enable_debug_api,
+        admin: server_config.enable_admin_api,
+        metrics: server_config.enable_metrics_endpoint,
+    };
     RpcServerBuilder::new(server_config.http_socket_address)
         .allow_origin(server_config.http_allow_origin)
+        .allowed_origins(server_config.http_allowed_origins)
+        .tls(server_config.tls)
+        .shutdown_signal(shutdown)
         .with_data(lean_chain)
         .with_data(network_state)
-        .configure(register_routers)
+        .with_data(local_validators)
+        .with_data(chain_sender)
+        .with_data(p2p_sender)
+        .with_data(admin_auth)
+        .with_data(snapshot_dir)
+        .configure(move |config| register_routers(config, namespaces))
+        .start()
+        .await
+}
+
+/// Start the Lean API server backed only by a read-only database.
+pub async fn start_read_only(
+    server_config: RpcServerConfig,
+    lean_db: ReadOnlyLeanDB,
+) -> Result<()> {
+    RpcServerBuilder::new(server_config.http_socket_address)
+        .allow_origin(server_config.http_allow_origin)
+        .allowed_origins(server_config.http_allowed_origins)
+        .tls(server_config.tls)
+        .with_data(lean_db)
+        .configure(register_read_only_routers)
+        .start()
+        .await
+}
+
+/// Start the keymanager API of a lean validator client, authenticated with `admin_auth`.
+pub async fn start_keymanager(
+    server_config: RpcServerConfig,
+    keymanager_sender: mpsc::UnboundedSender<KeymanagerMessage>,
+    admin_auth: AdminAuth,
+    shutdown: ShutdownSignal,
+) -> Result<()> {
+    RpcServerBuilder::new(server_config.http_socket_address)
+        .allow_origin(server_config.http_allow_origin)
+        .allowed_origins(server_config.http_allowed_origins)
+        .tls(server_config.tls)
+        .shutdown_signal(shutdown)
+        .with_data(keymanager_sender)
+        .with_data(admin_auth)
+        .configure(register_keymanager_routes)
         .start()
         .await
 }
@@ -0,0 +1,32 @@
+use alloy_primitives::B256;
+use ream_fork_choice_lean::store::{LeanStoreReader, Store};
+use ream_network_spec::networks::lean_network_spec;
+use ream_storage::tables::field::REDBField;
+use ream_sync::rwlock::Writer;
+use ream_test_utils::{chain::ChainBuilder, db_setup, genesis};
+
+/// Imports a block at every slot from 1 to `last_slot` on top of genesis through fork choice, so
+/// the head, indices and states are set like on a running node. Returns the store, the builder
+/// of the chain and the roots of the imported blocks.
+pub(crate) async fn lean_chain(last_slot: u64) -> (LeanStoreReader, ChainBuilder, Vec<B256>) {
+    let (genesis_block, genesis_state) = genesis(4);
+    let mut chain = ChainBuilder::new(genesis_block.clone(), genesis_state.clone());
+    let roots = chain.extend(chain.genesis_root(), 1..=last_slot).unwrap();
+
+    let mut store =
+        Store::get_forkchoice_store(genesis_block, genesis_state, db_setup(), None).unwrap();
+    store
+        .store
+        .time_provider()
+        .insert(last_slot * lean_network_spec().intervals_per_slot)
+        .unwrap();
+    for root in &roots {
+        store
+            .on_block(chain.block(*root).unwrap(), false)
+            .await
+            .unwrap();
+    }
+
+    let (_, lean_chain) = Writer::new(store);
+    (lean_chain, chain, roots)
+}
@@ -0,0 +1,85 @@
+use alloy_primitives::B256;
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use ream_consensus_lean::state::LeanState;
+use ream_storage::{
+    db::ReamDB,
+    tables::{field::REDBField, table::REDBTable},
+};
+use ream_test_utils::sample_block;
+use tempdir::TempDir;
+
+/// Compares the writes of a block import done one table at a time with the same writes done in a
+/// single batch.
+fn bench_block_import_writes(c: &mut Criterion) {
+    let dir = TempDir::new("write_batch_bench").expect("Failed to create temp dir");
+    let lean_db = ReamDB::new(dir.path().to_path_buf())
+        .expect("Failed to open database")
+        .init_lean_db()
+        .expect("Failed to init lean tables");
+    let state = LeanState::generate_genesis(0, None);
+
+    let mut group = c.benchmark_group("block_import_writes");
+    let mut slot = 0;
+    group.bench_function(BenchmarkId::new("individual", "block"), |b| {
+        b.iter(|| {
+            slot += 1;
+            let block_root = B256::left_padding_from(&slot.to_be_bytes());
+            let checkpoint = Checkpoint {
+                root: block_root,
+                slot,
+            };
+            lean_db
+                .block_provider()
+                .insert(
+                    block_root,
+                    sample_block(
+                        slot,
+                        0,
+                        B256::left_padding_from(&slot.saturating_sub(1).to_be_bytes()),
+                    ),
+                )
+                .expect("Failed to insert block");
+            lean_db
+                .state_provider()
+                .insert(block_root, state.clone())
+                .expect("Failed to insert state");
+            lean_db
+                .latest_justified_provider()
+                .insert(checkpoint)
+                .expect("Failed to insert justified checkpoint");
+            lean_db
+                .latest_finalized_provider()
+                .insert(checkpoint)
+                .expect("Failed to insert finalized checkpoint");
+        })
+    });
+    group.bench_function(BenchmarkId::new("batched", "block"), |b| {
+        b.iter(|| {
+            slot += 1;
+            let block_root = B256::left_padding_from(&slot.to_be_bytes());
+            let checkpoint = Checkpoint {
+                root: block_root,
+                slot,
+            };
+            lean_db
+                .with_write_batch(|batch| {
+                    batch.insert_block(
+                        block_root,
+                        &sample_block(
+                            slot,
+                            0,
+                            B256::left_padding_from(&slot.saturating_sub(1).to_be_bytes()),
+                        ),
+                    )?;
+                    batch.insert_state(block_root, &state)?;
+                    batch.set_latest_justified(checkpoint)?;
+                    batch.set_latest_finalized(checkpoint)
+                })
+                .expect("Failed to write batch");
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_block_import_writes);
+criterion_main!(benches);
@@ -1,18 +1,430 @@
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
-use redb::Database;
+use alloy_primitives::B256;
+use ream_consensus_lean::{block::SignedBlockWithAttestation, checkpoint::Checkpoint};
+use ream_metrics::slot_report::record_db_write;
+use redb::{
+    Database, Key, MultimapTableDefinition, MultimapTableHandle, ReadOnlyTable, ReadTransaction,
+    ReadableDatabase, ReadableTable, ReadableTableMetadata, TableDefinition, TableHandle, Value,
+};
+use tokio::task::spawn_blocking;
 
-use crate::tables::lean::{
-    latest_finalized::LatestFinalizedField, latest_justified::LatestJustifiedField,
-    latest_known_attestation::LatestKnownAttestationTable, lean_block::LeanBlockTable,
-    lean_head::LeanHeadField, lean_latest_new_attestations::LeanLatestNewAttestationsTable,
-    lean_safe_target::LeanSafeTargetField, lean_state::LeanStateTable, lean_time::LeanTimeField,
-    slot_index::LeanSlotIndexTable, state_root_index::LeanStateRootIndexTable,
+use crate::{
+    db::{snapshot::snapshot_database, write_batch::WriteBatch},
+    durability::begin_write,
+    errors::StoreError,
+    tables::{
+        field::REDBField,
+        lean::{
+            blob_sidecar::LeanBlobSidecarTable, latest_finalized::LatestFinalizedField,
+            latest_justified::LatestJustifiedField,
+            latest_known_attestation::LatestKnownAttestationTable, lean_block::LeanBlockTable,
+            lean_head::LeanHeadField, lean_latest_new_attestations::LeanLatestNewAttestationsTable,
+            lean_proposer_boost_root::LeanProposerBoostRootField,
+            lean_safe_target::LeanSafeTargetField, lean_state::LeanStateTable,
+            lean_time::LeanTimeField, slot_index::LeanSlotIndexTable,
+            state_root_index::LeanStateRootIndexTable,
+            validator_performance::LeanValidatorPerformanceTable,
+        },
+        schema::{TableVisitor, visit_lean_tables},
+        ssz_encoder::{CompressedSSZEncoding, SSZEncoding},
+        table::REDBTable,
+    },
 };
 
+/// Number of entries and bytes stored in a single lean table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSummary {
+    pub name: String,
+    pub entries: u64,
+    pub stored_bytes: u64,
+}
+
+/// An index entry which doesn't agree with the block table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexInconsistency {
+    /// The slot index points at a block which isn't stored.
+    SlotIndexMissingBlock { slot: u64, block_root: B256 },
+    /// The slot index points at a block from a different slot.
+    SlotIndexWrongSlot {
+        slot: u64,
+        block_root: B256,
+        block_slot: u64,
+    },
+    /// The state root index points at a block which isn't stored.
+    StateRootIndexMissingBlock { state_root: B256, block_root: B256 },
+    /// The state root index points at a block committing to a different state root.
+    StateRootIndexWrongStateRoot {
+        state_root: B256,
+        block_root: B256,
+        block_state_root: B256,
+    },
+}
+
+impl fmt::Display for IndexInconsistency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::SlotIndexMissingBlock { slot, block_root } => {
+                write!(f, "slot index {slot} points at missing block {block_root}")
+            }
+            Self::SlotIndexWrongSlot {
+                slot,
+                block_root,
+                block_slot,
+            } => write!(
+                f,
+                "slot index {slot} points at block {block_root} from slot {block_slot}"
+            ),
+            Self::StateRootIndexMissingBlock {
+                state_root,
+                block_root,
+            } => write!(
+                f,
+                "state root index {state_root} points at missing block {block_root}"
+            ),
+            Self::StateRootIndexWrongStateRoot {
+                state_root,
+                block_root,
+                block_state_root,
+            } => write!(
+                f,
+                "state root index {state_root} points at block {block_root} with state root {block_state_root}"
+            ),
+        }
+    }
+}
+
+/// A problem [LeanDB::verify_integrity] found in the lean tables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IntegrityIssue {
+    /// An index entry doesn't agree with the block table, [LeanDB::rebuild_indices] repairs it.
+    Index(IndexInconsistency),
+    /// The head points at a block which isn't stored.
+    MissingHeadBlock { block_root: B256 },
+    /// The post state of the head block isn't stored.
+    MissingHeadState { block_root: B256 },
+    /// The latest justified checkpoint points at a block which isn't stored.
+    MissingJustifiedBlock { checkpoint: Checkpoint },
+    /// The latest finalized checkpoint points at a block which isn't stored.
+    MissingFinalizedBlock { checkpoint: Checkpoint },
+}
+
+impl IntegrityIssue {
+    /// Whether [LeanDB::rebuild_indices] fixes the issue.
+    pub fn is_repairable(&self) -> bool {
+        matches!(self, Self::Index(_))
+    }
+}
+
+impl fmt::Display for IntegrityIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Index(inconsistency) => inconsistency.fmt(f),
+            Self::MissingHeadBlock { block_root } => {
+                write!(f, "head points at missing block {block_root}")
+            }
+            Self::MissingHeadState { block_root } => {
+                write!(f, "state of head block {block_root} is missing")
+            }
+            Self::MissingJustifiedBlock { checkpoint } => write!(
+                f,
+                "latest justified checkpoint points at missing block {} at slot {}",
+                checkpoint.root, checkpoint.slot
+            ),
+            Self::MissingFinalizedBlock { checkpoint } => write!(
+                f,
+                "latest finalized checkpoint points at missing block {} at slot {}",
+                checkpoint.root, checkpoint.slot
+            ),
+        }
+    }
+}
+
+/// Collects the number of entries and stored bytes of the tables it visits.
+struct TableSummaries<'a> {
+    read_txn: &'a ReadTransaction,
+    summaries: Vec<TableSummary>,
+}
+
+impl TableVisitor for TableSummaries<'_> {
+    fn table<K: Key + 'static, V: Value + 'static>(
+        &mut self,
+        definition: TableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        let table = self.read_txn.open_table(definition)?;
+        self.summaries.push(TableSummary {
+            name: definition.name().to_string(),
+            entries: table.len()?,
+            stored_bytes: table.stats()?.stored_bytes(),
+        });
+        Ok(())
+    }
+
+    fn multimap_table<K: Key + 'static, V: Key + 'static>(
+        &mut self,
+        definition: MultimapTableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        let table = self.read_txn.open_multimap_table(definition)?;
+        self.summaries.push(TableSummary {
+            name: definition.name().to_string(),
+            entries: table.len()?,
+            stored_bytes: table.stats()?.stored_bytes(),
+        });
+        Ok(())
+    }
+}
+
+/// Returns the number of entries and stored bytes of every lean table.
+pub(crate) fn table_stats(read_txn: &ReadTransaction) -> Result<Vec<TableSummary>, StoreError> {
+    let mut summaries = TableSummaries {
+        read_txn,
+        summaries: vec![],
+    };
+    visit_lean_tables(&mut summaries)?;
+    Ok(summaries.summaries)
+}
+
+/// Checks that the slot index entries from `from_slot` on point at a stored block of their slot.
+fn verify_slot_index(
+    read_txn: &ReadTransaction,
+    from_slot: u64,
+) -> Result<Vec<IndexInconsistency>, StoreError> {
+    let block_table = read_txn.open_table(LeanBlockTable::TABLE_DEFINITION)?;
+    let slot_index_table = read_txn.open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+    let mut inconsistencies = vec![];
+    for entry in slot_index_table.range(from_slot..)? {
+        let (slot_entry, root_entry) = entry?;
+        let (slot, block_root) = (slot_entry.value(), root_entry.value());
+        match block_table.get(block_root)? {
+            None => {
+                inconsistencies.push(IndexInconsistency::SlotIndexMissingBlock { slot, block_root })
+            }
+            Some(block) => {
+                let block_slot = block.value().message.block.slot;
+                if block_slot != slot {
+                    inconsistencies.push(IndexInconsistency::SlotIndexWrongSlot {
+                        slot,
+                        block_root,
+                        block_slot,
+                    });
+                }
+            }
+        }
+    }
+    Ok(inconsistencies)
+}
+
+/// Checks that every slot index and state root index entry points at a stored block which agrees
+/// with it.
+pub(crate) fn verify_indices(
+    read_txn: &ReadTransaction,
+) -> Result<Vec<IndexInconsistency>, StoreError> {
+    let mut inconsistencies = verify_slot_index(read_txn, 0)?;
+
+    let block_table = read_txn.open_table(LeanBlockTable::TABLE_DEFINITION)?;
+    let state_root_index_table = read_txn.open_table(LeanStateRootIndexTable::TABLE_DEFINITION)?;
+    for entry in state_root_index_table.iter()? {
+        let (state_root_entry, root_entry) = entry?;
+        let (state_root, block_root) = (state_root_entry.value(), root_entry.value());
+        match block_table.get(block_root)? {
+            None => inconsistencies.push(IndexInconsistency::StateRootIndexMissingBlock {
+                state_root,
+                block_root,
+            }),
+            Some(block) => {
+                let block_state_root = block.value().message.block.state_root;
+                if block_state_root != state_root {
+                    inconsistencies.push(IndexInconsistency::StateRootIndexWrongStateRoot {
+                        state_root,
+                        block_root,
+                        block_state_root,
+                    });
+                }
+            }
+        }
+    }
+    Ok(inconsistencies)
+}
+
+/// Checks the indices with [verify_indices], and that the head block and its state and the
+/// blocks of the latest justified and finalized checkpoints are stored. Fields which were never
+/// set aren't checked.
+pub(crate) fn verify_integrity(
+    read_txn: &ReadTransaction,
+) -> Result<Vec<IntegrityIssue>, StoreError> {
+    let mut issues = verify_indices(read_txn)?
+        .into_iter()
+        .map(IntegrityIssue::Index)
+        .collect::<Vec<_>>();
+    verify_head_and_checkpoints(read_txn, &mut issues)?;
+    Ok(issues)
+}
+
+/// Like [verify_integrity], but only checks the slot index entries of the last `slots` indexed
+/// slots instead of scanning every index. Blocks are stored in the same transaction as their
+/// index entries, so only a crash of an older version can have left entries pointing at missing
+/// blocks, and only near the head it was writing.
+pub(crate) fn verify_recent_integrity(
+    read_txn: &ReadTransaction,
+    slots: u64,
+) -> Result<Vec<IntegrityIssue>, StoreError> {
+    let last_slot = read_txn
+        .open_table(LeanSlotIndexTable::TABLE_DEFINITION)?
+        .last()?
+        .map(|(slot, _)| slot.value())
+        .unwrap_or_default();
+    let mut issues = verify_slot_index(read_txn, last_slot.saturating_sub(slots))?
+        .into_iter()
+        .map(IntegrityIssue::Index)
+        .collect::<Vec<_>>();
+    verify_head_and_checkpoints(read_txn, &mut issues)?;
+    Ok(issues)
+}
+
+/// Checks that the head block and its state and the blocks of the latest justified and finalized
+/// checkpoints are stored.
+fn verify_head_and_checkpoints(
+    read_txn: &ReadTransaction,
+    issues: &mut Vec<IntegrityIssue>,
+) -> Result<(), StoreError> {
+    let block_table = read_txn.open_table(LeanBlockTable::TABLE_DEFINITION)?;
+    let state_table = read_txn.open_table(LeanStateTable::TABLE_DEFINITION)?;
+    let head = read_txn
+        .open_table(LeanHeadField::FIELD_DEFINITION)?
+        .get(LeanHeadField::KEY)?
+        .map(|head| head.value());
+    if let Some(block_root) = head {
+        if block_table.get(block_root)?.is_none() {
+            issues.push(IntegrityIssue::MissingHeadBlock { block_root });
+        } else if state_table.get(block_root)?.is_none() {
+            issues.push(IntegrityIssue::MissingHeadState { block_root });
+        }
+    }
+
+    let latest_justified = read_txn
+        .open_table(LatestJustifiedField::FIELD_DEFINITION)?
+        .get(LatestJustifiedField::KEY)?
+        .map(|checkpoint| checkpoint.value());
+    if let Some(checkpoint) = latest_justified
+        && block_table.get(checkpoint.root)?.is_none()
+    {
+        issues.push(IntegrityIssue::MissingJustifiedBlock { checkpoint });
+    }
+
+    let latest_finalized = read_txn
+        .open_table(LatestFinalizedField::FIELD_DEFINITION)?
+        .get(LatestFinalizedField::KEY)?
+        .map(|checkpoint| checkpoint.value());
+    if let Some(checkpoint) = latest_finalized
+        && block_table.get(checkpoint.root)?.is_none()
+    {
+        issues.push(IntegrityIssue::MissingFinalizedBlock { checkpoint });
+    }
+    Ok(())
+}
+
+type ReadOnlyBlockTable =
+    ReadOnlyTable<SSZEncoding<B256>, CompressedSSZEncoding<SignedBlockWithAttestation>>;
+
+/// The canonical blocks of a slot range in ascending slot order, read from a single read
+/// transaction, see [canonical_chain].
+pub struct CanonicalChainIter {
+    block_table: ReadOnlyBlockTable,
+    roots: std::vec::IntoIter<(u64, B256)>,
+}
+
+impl Iterator for CanonicalChainIter {
+    type Item = Result<(u64, B256, SignedBlockWithAttestation), StoreError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (slot, block_root) = self.roots.next()?;
+        self.block_table
+            .get(block_root)
+            .map(|block| block.map(|block| (slot, block_root, block.value())))
+            .map_err(StoreError::from)
+            .transpose()
+    }
+}
+
+/// Returns the root, slot and parent root of the stored block at `block_root`.
+fn block_parent(
+    block_table: &ReadOnlyBlockTable,
+    block_root: B256,
+) -> Result<Option<(B256, u64, B256)>, StoreError> {
+    Ok(block_table.get(block_root)?.map(|block| {
+        let block = block.value().message.block;
+        (block_root, block.slot, block.parent_root)
+    }))
+}
+
+/// Walks the slot index from `to_slot` down to `from_slot` (inclusive) and keeps the slots whose
+/// block is an ancestor of the head. The slot index points at the last block stored for a slot,
+/// which can be a fork, so a slot after the latest finalized slot is resolved to the ancestor of
+/// the head at that slot instead, and skipped if the head's chain has no block there. The walk
+/// from the head ends at the finalized slot: finalized slots are indexed to the finalized chain,
+/// see [WriteBatch::index_finalized_chain], and so are trusted like slots without a head.
+pub(crate) fn canonical_chain(
+    read_txn: &ReadTransaction,
+    from_slot: u64,
+    to_slot: u64,
+) -> Result<CanonicalChainIter, StoreError> {
+    let block_table = read_txn.open_table(LeanBlockTable::TABLE_DEFINITION)?;
+    let slot_index_table = read_txn.open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+    let head = read_txn
+        .open_table(LeanHeadField::FIELD_DEFINITION)?
+        .get(LeanHeadField::KEY)?
+        .map(|head| head.value());
+    let finalized_slot = read_txn
+        .open_table(LatestFinalizedField::FIELD_DEFINITION)?
+        .get(LatestFinalizedField::KEY)?
+        .map(|checkpoint| checkpoint.value().slot);
+
+    let mut roots = vec![];
+    if from_slot <= to_slot {
+        // The ancestor of the head the walk has reached.
+        let mut ancestor = match head {
+            Some(head) => block_parent(&block_table, head)?,
+            None => None,
+        };
+        for entry in slot_index_table.range(from_slot..=to_slot)?.rev() {
+            let (slot_entry, root_entry) = entry?;
+            let (slot, indexed_root) = (slot_entry.value(), root_entry.value());
+            if head.is_none() || finalized_slot.is_some_and(|finalized_slot| slot <= finalized_slot)
+            {
+                roots.push((slot, indexed_root));
+                continue;
+            }
+
+            while let Some((_, ancestor_slot, parent_root)) = ancestor
+                && ancestor_slot > slot
+            {
+                ancestor = block_parent(&block_table, parent_root)?;
+            }
+            if let Some((block_root, ancestor_slot, _)) = ancestor
+                && ancestor_slot == slot
+            {
+                roots.push((slot, block_root));
+            }
+        }
+        roots.reverse();
+    }
+
+    Ok(CanonicalChainIter {
+        block_table,
+        roots: roots.into_iter(),
+    })
+}
+
 #[derive(Clone, Debug)]
 pub struct LeanDB {
     pub db: Arc<Database>,
+    /// The name of the database file in the data directory, which snapshots are written as.
+    pub(crate) file_name: String,
 }
 
 impl LeanDB {
@@ -75,9 +487,457 @@ impl LeanDB {
         }
     }
 
+    pub fn proposer_boost_root_provider(&self) -> LeanProposerBoostRootField {
+        LeanProposerBoostRootField {
+            db: self.db.clone(),
+        }
+    }
+
     pub fn latest_new_attestations_provider(&self) -> LeanLatestNewAttestationsTable {
         LeanLatestNewAttestationsTable {
             db: self.db.clone(),
         }
     }
+
+    pub fn validator_performance_provider(&self) -> LeanValidatorPerformanceTable {
+        LeanValidatorPerformanceTable {
+            db: self.db.clone(),
+        }
+    }
+
+    pub fn blob_sidecar_provider(&self) -> LeanBlobSidecarTable {
+        LeanBlobSidecarTable {
+            db: self.db.clone(),
+        }
+    }
+
+    /// Runs `operation` on tokio's blocking thread pool, so redb I/O done from async code doesn't
+    /// stall the runtime's worker threads. Use it for full table scans and large values such as
+    /// states.
+    pub async fn run_blocking<T, F>(&self, operation: F) -> Result<T, StoreError>
+    where
+        T: Send + 'static,
+        F: FnOnce(LeanDB) -> Result<T, StoreError> + Send + 'static,
+    {
+        let lean_db = self.clone();
+        spawn_blocking(move || operation(lean_db)).await?
+    }
+
+    /// Runs `write` against a single write transaction and commits it once, so every write in the
+    /// batch is persisted with one fsync. Nothing is written if `write` fails.
+    pub fn with_write_batch<T>(
+        &self,
+        write: impl FnOnce(&WriteBatch) -> Result<T, StoreError>,
+    ) -> Result<T, StoreError> {
+        let write_txn = begin_write(&self.db)?;
+        let result = write(&WriteBatch {
+            write_txn: &write_txn,
+        })?;
+        write_txn.commit()?;
+        record_db_write();
+        Ok(result)
+    }
+
+    /// Moves every new attestation into the known attestations in a single write transaction, so
+    /// a crash can't lose the attestations between draining one table and filling the other. A
+    /// validator without a known attestation is only added while there are fewer than
+    /// `max_known` known attestations. Returns how many attestations were dropped for that, and
+    /// how many known attestations there are after the move.
+    pub fn move_new_to_known(&self, max_known: u64) -> Result<(u64, u64), StoreError> {
+        let write_txn = begin_write(&self.db)?;
+
+        let mut new_table =
+            write_txn.open_table(LeanLatestNewAttestationsTable::TABLE_DEFINITION)?;
+        let mut known_table =
+            write_txn.open_table(LatestKnownAttestationTable::TABLE_DEFINITION)?;
+        let mut known_count = known_table.len()?;
+        let mut dropped = 0;
+        while let Some((validator_id, signed_attestation)) = new_table.pop_first()? {
+            let (validator_id, signed_attestation) =
+                (validator_id.value(), signed_attestation.value());
+            if known_table.get(validator_id)?.is_none() {
+                if known_count >= max_known {
+                    dropped += 1;
+                    continue;
+                }
+                known_count += 1;
+            }
+            known_table.insert(validator_id, signed_attestation)?;
+        }
+
+        drop((new_table, known_table));
+        write_txn.commit()?;
+        record_db_write();
+        Ok((dropped, known_count))
+    }
+
+    /// Writes a consistent copy of the database to `dest_dir` while the node keeps running, under
+    /// the file name it was opened from, so it opens with the same options. See
+    /// [ReamDB::snapshot](crate::db::ReamDB::snapshot) to also copy beacon blobs.
+    pub fn snapshot(&self, dest_dir: &Path) -> Result<PathBuf, StoreError> {
+        snapshot_database(&self.db, dest_dir, &self.file_name, || Ok(()))
+    }
+
+    /// Returns the number of entries and stored bytes of every lean table.
+    pub fn table_stats(&self) -> Result<Vec<TableSummary>, StoreError> {
+        table_stats(&self.db.begin_read()?)
+    }
+
+    /// Removes every block older than `slot`, together with its state and index entries, in a
+    /// single write transaction. Returns the number of blocks removed.
+    pub fn prune_before_slot(&self, slot: u64) -> Result<u64, StoreError> {
+        let write_txn = begin_write(&self.db)?;
+        let pruned = {
+            let mut block_table = write_txn.open_table(LeanBlockTable::TABLE_DEFINITION)?;
+            let mut state_table = write_txn.open_table(LeanStateTable::TABLE_DEFINITION)?;
+            let mut slot_index_table =
+                write_txn.open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+            let mut state_root_index_table =
+                write_txn.open_table(LeanStateRootIndexTable::TABLE_DEFINITION)?;
+
+            let mut stale_blocks = vec![];
+            for entry in block_table.iter()? {
+                let (root_entry, block_entry) = entry?;
+                let block = block_entry.value().message.block;
+                if block.slot < slot {
+                    stale_blocks.push((root_entry.value(), block.state_root));
+                }
+            }
+
+            for (block_root, state_root) in &stale_blocks {
+                block_table.remove(block_root)?;
+                state_table.remove(block_root)?;
+                state_root_index_table.remove(state_root)?;
+            }
+
+            let stale_slots = slot_index_table
+                .range(..slot)?
+                .map(|entry| entry.map(|(slot, _)| slot.value()))
+                .collect::<Result<Vec<_>, _>>()?;
+            for stale_slot in stale_slots {
+                slot_index_table.remove(stale_slot)?;
+            }
+
+            stale_blocks.len() as u64
+        };
+        write_txn.commit()?;
+        record_db_write();
+        Ok(pruned)
+    }
+
+    /// Checks that every slot index and state root index entry points at a stored block which
+    /// agrees with it.
+    pub fn verify_indices(&self) -> Result<Vec<IndexInconsistency>, StoreError> {
+        verify_indices(&self.db.begin_read()?)
+    }
+
+    /// Checks the indices, and that the head, its state and the latest justified and finalized
+    /// blocks are stored.
+    pub fn verify_integrity(&self) -> Result<Vec<IntegrityIssue>, StoreError> {
+        verify_integrity(&self.db.begin_read()?)
+    }
+
+    /// Checks the slot index entries of the last `slots` indexed slots, and that the head, its
+    /// state and the latest justified and finalized blocks are stored. Cheap enough to run on
+    /// every start, see [LeanDB::verify_integrity] for the full check.
+    pub fn verify_recent_integrity(&self, slots: u64) -> Result<Vec<IntegrityIssue>, StoreError> {
+        verify_recent_integrity(&self.db.begin_read()?, slots)
+    }
+
+    /// Returns the slot, root and block of every block on the chain of the head between
+    /// `from_slot` and `to_slot` (inclusive), in ascending slot order. Blocks of forks which the
+    /// slot index points at are left out.
+    pub fn canonical_chain_iter(
+        &self,
+        from_slot: u64,
+        to_slot: u64,
+    ) -> Result<CanonicalChainIter, StoreError> {
+        canonical_chain(&self.db.begin_read()?, from_slot, to_slot)
+    }
+
+    /// Rebuilds the slot and state root indices from the block table in a single write
+    /// transaction. A slot with several blocks is indexed to the one on the chain of the head,
+    /// and left out of the slot index if the head's chain has no block there, rather than picking
+    /// one of the forks. Returns the number of blocks indexed.
+    pub fn rebuild_indices(&self) -> Result<u64, StoreError> {
+        let write_txn = begin_write(&self.db)?;
+        let indexed = {
+            let block_table = write_txn.open_table(LeanBlockTable::TABLE_DEFINITION)?;
+            let mut slot_index_table =
+                write_txn.open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+            let mut state_root_index_table =
+                write_txn.open_table(LeanStateRootIndexTable::TABLE_DEFINITION)?;
+            slot_index_table.retain(|_, _| false)?;
+            state_root_index_table.retain(|_, _| false)?;
+
+            let mut slot_roots = BTreeMap::new();
+            let mut parents = HashMap::new();
+            for entry in block_table.iter()? {
+                let (root_entry, block_entry) = entry?;
+                let (block_root, block) = (root_entry.value(), block_entry.value().message.block);
+                state_root_index_table.insert(block.state_root, block_root)?;
+                slot_roots
+                    .entry(block.slot)
+                    .and_modify(|root: &mut Option<B256>| *root = None)
+                    .or_insert(Some(block_root));
+                parents.insert(block_root, (block.slot, block.parent_root));
+            }
+
+            let mut block_root = write_txn
+                .open_table(LeanHeadField::FIELD_DEFINITION)?
+                .get(LeanHeadField::KEY)?
+                .map(|head| head.value())
+                .unwrap_or_default();
+            while let Some((slot, parent_root)) = parents.get(&block_root) {
+                slot_roots.insert(*slot, Some(block_root));
+                block_root = *parent_root;
+            }
+            for (slot, block_root) in slot_roots {
+                if let Some(block_root) = block_root {
+                    slot_index_table.insert(slot, block_root)?;
+                }
+            }
+
+            parents.len() as u64
+        };
+        write_txn.commit()?;
+        record_db_write();
+        Ok(indexed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ream_consensus_lean::checkpoint::Checkpoint;
+    use ream_test_utils::sample_block;
+    use tempdir::TempDir;
+    use tree_hash::TreeHash;
+
+    use super::{IndexInconsistency, IntegrityIssue};
+    use crate::{
+        db::ReamDB,
+        errors::StoreError,
+        tables::{field::REDBField, table::REDBTable},
+    };
+
+    #[test]
+    fn test_verify_integrity_and_rebuild_indices() {
+        let dir = TempDir::new("lean_integrity").unwrap();
+        let lean_db = ReamDB::new(dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        assert!(lean_db.verify_integrity().unwrap().is_empty());
+
+        let genesis = sample_block(0, 0, B256::ZERO);
+        let genesis_root = genesis.message.block.tree_hash_root();
+        let child = sample_block(1, 0, genesis_root);
+        let child_root = child.message.block.tree_hash_root();
+        let fork = sample_block(1, 1, genesis_root);
+        lean_db
+            .block_provider()
+            .insert(genesis_root, genesis)
+            .unwrap();
+        lean_db.block_provider().insert(child_root, child).unwrap();
+        lean_db
+            .block_provider()
+            .insert(fork.message.block.tree_hash_root(), fork)
+            .unwrap();
+        lean_db.head_provider().insert(child_root).unwrap();
+        lean_db
+            .latest_finalized_provider()
+            .insert(Checkpoint {
+                root: genesis_root,
+                slot: 0,
+            })
+            .unwrap();
+        lean_db
+            .slot_index_provider()
+            .insert(7, B256::repeat_byte(7))
+            .unwrap();
+
+        let issues = lean_db.verify_integrity().unwrap();
+        assert_eq!(
+            issues,
+            vec![
+                IntegrityIssue::Index(IndexInconsistency::SlotIndexMissingBlock {
+                    slot: 7,
+                    block_root: B256::repeat_byte(7),
+                }),
+                IntegrityIssue::MissingHeadState {
+                    block_root: child_root
+                },
+            ]
+        );
+        assert!(!issues[1].is_repairable());
+
+        assert_eq!(lean_db.rebuild_indices().unwrap(), 3);
+        assert_eq!(
+            lean_db.verify_integrity().unwrap(),
+            vec![IntegrityIssue::MissingHeadState {
+                block_root: child_root
+            }]
+        );
+        // The fork was stored last, but slot 1 is indexed to the block on the chain of the head.
+        assert_eq!(
+            lean_db.slot_index_provider().get(1).unwrap(),
+            Some(child_root)
+        );
+        assert_eq!(lean_db.slot_index_provider().get(7).unwrap(), None);
+
+        // Off the chain of the head, a slot is only indexed if a single block is stored there.
+        let orphan = sample_block(2, 0, B256::repeat_byte(0xaa));
+        let orphan_root = orphan.message.block.tree_hash_root();
+        let first_fork = sample_block(3, 1, B256::repeat_byte(0xbb));
+        let second_fork = sample_block(3, 2, B256::repeat_byte(0xbb));
+        for block in [orphan, first_fork, second_fork] {
+            lean_db
+                .block_provider()
+                .insert(block.message.block.tree_hash_root(), block)
+                .unwrap();
+        }
+        assert_eq!(lean_db.rebuild_indices().unwrap(), 6);
+        assert_eq!(
+            lean_db.slot_index_provider().get(2).unwrap(),
+            Some(orphan_root)
+        );
+        assert_eq!(lean_db.slot_index_provider().get(3).unwrap(), None);
+    }
+
+    #[test]
+    fn test_verify_recent_integrity() {
+        let lean_db = ReamDB::in_memory().unwrap().init_lean_db().unwrap();
+        for slot in [7, 200] {
+            lean_db
+                .slot_index_provider()
+                .insert(slot, B256::with_last_byte(slot as u8))
+                .unwrap();
+        }
+
+        // Only the slot index entries near the last indexed slot are checked.
+        assert_eq!(
+            lean_db.verify_recent_integrity(64).unwrap(),
+            vec![IntegrityIssue::Index(
+                IndexInconsistency::SlotIndexMissingBlock {
+                    slot: 200,
+                    block_root: B256::with_last_byte(200),
+                }
+            )]
+        );
+        assert_eq!(lean_db.verify_integrity().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_canonical_chain_iter() {
+        let dir = TempDir::new("lean_canonical_chain").unwrap();
+        let lean_db = ReamDB::new(dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+
+        let genesis = sample_block(0, 0, B256::ZERO);
+        let genesis_root = genesis.message.block.tree_hash_root();
+        let child = sample_block(1, 0, genesis_root);
+        let child_root = child.message.block.tree_hash_root();
+        let fork = sample_block(1, 1, genesis_root);
+        let fork_root = fork.message.block.tree_hash_root();
+        let fork_child = sample_block(2, 0, fork_root);
+        let fork_child_root = fork_child.message.block.tree_hash_root();
+        let head = sample_block(3, 0, child_root);
+        let head_root = head.message.block.tree_hash_root();
+        for block in [genesis, child, fork, fork_child, head] {
+            lean_db
+                .block_provider()
+                .insert(block.message.block.tree_hash_root(), block)
+                .unwrap();
+        }
+
+        let canonical_roots = |from_slot, to_slot| {
+            lean_db
+                .canonical_chain_iter(from_slot, to_slot)
+                .unwrap()
+                .map(|entry| entry.map(|(slot, block_root, _)| (slot, block_root)))
+                .collect::<Result<Vec<_>, _>>()
+                .unwrap()
+        };
+        // Without a head the slot index is trusted, which points at the fork for slots 1 and 2.
+        assert_eq!(
+            canonical_roots(1, 2),
+            vec![(1, fork_root), (2, fork_child_root)]
+        );
+
+        lean_db.head_provider().insert(head_root).unwrap();
+        assert_eq!(
+            canonical_roots(0, 3),
+            vec![(0, genesis_root), (1, child_root), (3, head_root)]
+        );
+        assert_eq!(canonical_roots(1, 2), vec![(1, child_root)]);
+        assert!(canonical_roots(3, 2).is_empty());
+
+        // Finalizing the child indexes slot 1 to it, and the fork stored later for a finalized
+        // slot doesn't take its place again.
+        let finalized = Checkpoint {
+            root: child_root,
+            slot: 1,
+        };
+        lean_db
+            .with_write_batch(|batch| {
+                batch.set_latest_finalized(finalized)?;
+                batch.index_finalized_chain(0, finalized)
+            })
+            .unwrap();
+        assert_eq!(
+            lean_db.slot_index_provider().get(1).unwrap(),
+            Some(child_root)
+        );
+        let late_fork = sample_block(1, 2, genesis_root);
+        lean_db
+            .with_write_batch(|batch| {
+                batch.insert_block(late_fork.message.block.tree_hash_root(), &late_fork)
+            })
+            .unwrap();
+        assert_eq!(
+            canonical_roots(0, 3),
+            vec![(0, genesis_root), (1, child_root), (3, head_root)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_blocking() {
+        let lean_db = ReamDB::in_memory().unwrap().init_lean_db().unwrap();
+        let genesis = sample_block(0, 0, B256::ZERO);
+        let genesis_root = genesis.message.block.tree_hash_root();
+
+        // Writes on the blocking pool are seen by the caller, and the result is passed back.
+        let stored = lean_db
+            .run_blocking(move |lean_db| {
+                lean_db.block_provider().insert(genesis_root, genesis)?;
+                lean_db.block_provider().get(genesis_root)
+            })
+            .await
+            .unwrap();
+        assert!(stored.is_some());
+        assert!(
+            lean_db
+                .block_provider()
+                .get(genesis_root)
+                .unwrap()
+                .is_some()
+        );
+
+        assert!(matches!(
+            lean_db
+                .run_blocking(|_| Err::<(), _>(StoreError::FieldNotInitilized))
+                .await,
+            Err(StoreError::FieldNotInitilized)
+        ));
+        // A panicking operation fails the call instead of the runtime.
+        assert!(matches!(
+            lean_db
+                .run_blocking(|_| -> Result<(), StoreError> { panic!("operation panicked") })
+                .await,
+            Err(StoreError::BlockingTask(_))
+        ));
+    }
 }
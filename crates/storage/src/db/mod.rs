@@ -1,89 +1,243 @@
 pub mod beacon;
 pub mod lean;
+pub mod read_only;
+pub mod snapshot;
+pub mod write_batch;
 
-use std::{fs, io, path::PathBuf, sync::Arc};
+use std::{
+    fs,
+    io::{self, IsTerminal},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Arc,
+};
 
-use anyhow::Result;
+use anyhow::{Result, anyhow, bail};
 use beacon::BeaconDB;
 use lean::LeanDB;
-use redb::{Builder, Database};
+use read_only::ReadOnlyLeanDB;
+use redb::{
+    Builder, Database, Key, MultimapTableDefinition, ReadTransaction, ReadableDatabase,
+    ReadableTableMetadata, TableDefinition, TableError, Value, backends::InMemoryBackend,
+};
+use snapshot::snapshot_database;
 use tracing::info;
 
 use crate::{
+    durability,
     errors::StoreError,
+    migrations::{CURRENT_SCHEMA_VERSION, MIGRATIONS, run_migrations, schema_version},
     tables::{
-        beacon::{
-            beacon_block::BeaconBlockTable, beacon_state::BeaconStateTable,
-            blobs_and_proofs::BLOB_FOLDER_NAME, block_timeliness::BlockTimelinessTable,
-            checkpoint_states::CheckpointStatesTable,
-            equivocating_indices::EQUIVOCATING_INDICES_FIELD,
-            finalized_checkpoint::FinalizedCheckpointField, genesis_time::GenesisTimeField,
-            justified_checkpoint::JustifiedCheckpointField, latest_messages::LatestMessagesTable,
-            parent_root_index::PARENT_ROOT_INDEX_MULTIMAP_TABLE,
-            proposer_boost_root::ProposerBoostRootField, slot_index::BeaconSlotIndexTable,
-            state_root_index::BeaconStateRootIndexTable, time::TimeField,
-            unrealized_finalized_checkpoint::UnrealizedFinalizedCheckpointField,
-            unrealized_justifications::UnrealizedJustificationsTable,
-            unrealized_justified_checkpoint::UnrealizedJustifiedCheckpointField,
+        beacon::blobs_and_proofs::BLOB_FOLDER_NAME,
+        schema::{
+            TableCreator, TableDeleter, TableVisitor, visit_beacon_tables, visit_lean_tables,
         },
-        field::REDBField,
-        lean::{
-            latest_finalized::LatestFinalizedField, latest_justified::LatestJustifiedField,
-            latest_known_attestation::LatestKnownAttestationTable, lean_block::LeanBlockTable,
-            lean_head::LeanHeadField, lean_latest_new_attestations::LeanLatestNewAttestationsTable,
-            lean_safe_target::LeanSafeTargetField, lean_state::LeanStateTable,
-            lean_time::LeanTimeField, slot_index::LeanSlotIndexTable,
-            state_root_index::LeanStateRootIndexTable,
-        },
-        table::REDBTable,
     },
 };
 
 pub const REDB_FILE: &str = "ream.redb";
 
+/// The database file of the lean chain when the chains get separate databases.
+pub const LEAN_REDB_FILE: &str = "lean.redb";
+
+/// The database file of the beacon chain when the chains get separate databases.
+pub const BEACON_REDB_FILE: &str = "beacon.redb";
+
+/// What [reset_db] removes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ResetScope {
+    /// Everything in the data directory.
+    #[default]
+    All,
+    /// Only the lean tables, or the separate lean database file, keeping the beacon tables and
+    /// the other files in the data directory.
+    Lean,
+    /// Only the beacon tables, or the separate beacon database file, and blobs, keeping the lean
+    /// tables and the other files.
+    Beacon,
+}
+
+impl FromStr for ResetScope {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "all" => Ok(ResetScope::All),
+            "lean" => Ok(ResetScope::Lean),
+            "beacon" => Ok(ResetScope::Beacon),
+            _ => Err(anyhow!(
+                "Unknown reset scope {s}, options are all, lean and beacon"
+            )),
+        }
+    }
+}
+
 /// The size of the cache for the database
 ///
 /// 1 GiB
 pub const REDB_CACHE_SIZE: usize = 1_024 * 1_024 * 1_024;
 
+/// The chains which can each be given a database file of their own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chain {
+    Lean,
+    Beacon,
+}
+
+impl Chain {
+    /// The database file of the chain when it doesn't share [REDB_FILE] with the other one.
+    pub fn redb_file(self) -> &'static str {
+        match self {
+            Chain::Lean => LEAN_REDB_FILE,
+            Chain::Beacon => BEACON_REDB_FILE,
+        }
+    }
+}
+
+/// Which file in the data directory a [ReamDB] opens, and with how much cache.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatabaseOptions {
+    pub file_name: String,
+    pub cache_size: usize,
+}
+
+impl Default for DatabaseOptions {
+    fn default() -> Self {
+        Self {
+            file_name: REDB_FILE.to_string(),
+            cache_size: REDB_CACHE_SIZE,
+        }
+    }
+}
+
+impl DatabaseOptions {
+    /// The options of `chain`, in its own file when `separate` is set and in the shared
+    /// [REDB_FILE] otherwise.
+    pub fn for_chain(chain: Chain, separate: bool, cache_size: usize) -> Self {
+        let file_name = if separate {
+            chain.redb_file()
+        } else {
+            REDB_FILE
+        };
+        Self {
+            file_name: file_name.to_string(),
+            cache_size,
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct ReamDB {
     db: Arc<Database>,
     data_dir: PathBuf,
+    file_name: String,
 }
 
 impl ReamDB {
     pub fn new(data_dir: PathBuf) -> Result<Self, StoreError> {
+        Self::with_options(data_dir, &DatabaseOptions::default())
+    }
+
+    /// Opens or creates the database file of `options` in `data_dir`, so the lean and beacon
+    /// chains can each keep a file with its own cache size and lifecycle.
+    ///
+    /// Creating the separate file of a chain fails if the shared [REDB_FILE] already holds data
+    /// of that chain, rather than starting the chain over from an empty database.
+    pub fn with_options(data_dir: PathBuf, options: &DatabaseOptions) -> Result<Self, StoreError> {
+        if let Some(chain) = [Chain::Lean, Chain::Beacon]
+            .into_iter()
+            .find(|chain| chain.redb_file() == options.file_name)
+            && !data_dir.join(&options.file_name).exists()
+        {
+            ensure_not_in_shared_db(&data_dir, chain)?;
+        }
         let db = Builder::new()
-            .set_cache_size(REDB_CACHE_SIZE)
-            .create(data_dir.join(REDB_FILE))?;
+            .set_cache_size(options.cache_size)
+            .create(data_dir.join(&options.file_name))?;
+        run_migrations(&db, MIGRATIONS)?;
 
         Ok(ReamDB {
             db: Arc::new(db),
             data_dir,
+            file_name: options.file_name.clone(),
+        })
+    }
+
+    /// Creates a database which is kept in memory and lost on drop, for benchmarks and tests. It
+    /// has no data directory, so only the lean tables can be used with it.
+    pub fn in_memory() -> Result<Self, StoreError> {
+        let db = Builder::new().create_with_backend(InMemoryBackend::new())?;
+        run_migrations(&db, MIGRATIONS)?;
+
+        Ok(ReamDB {
+            db: Arc::new(db),
+            data_dir: PathBuf::new(),
+            file_name: DatabaseOptions::default().file_name,
         })
     }
 
+    /// Makes every commit so far durable, see
+    /// [DurabilityProfile::Eventual](crate::durability::DurabilityProfile::Eventual).
+    pub fn flush(&self) -> Result<(), StoreError> {
+        durability::flush(&self.db)
+    }
+
+    /// The directory the database and blobs are stored in.
+    pub fn data_dir(&self) -> &Path {
+        &self.data_dir
+    }
+
+    /// Opens the lean tables of an existing database without taking a write transaction, so
+    /// nothing is migrated or created. The database must already be at
+    /// [CURRENT_SCHEMA_VERSION].
+    pub fn open_read_only(data_dir: PathBuf) -> Result<ReadOnlyLeanDB, StoreError> {
+        Self::open_read_only_with_options(data_dir, &DatabaseOptions::default())
+    }
+
+    /// [ReamDB::open_read_only] for the database file of `options`.
+    pub fn open_read_only_with_options(
+        data_dir: PathBuf,
+        options: &DatabaseOptions,
+    ) -> Result<ReadOnlyLeanDB, StoreError> {
+        let db = Builder::new()
+            .set_cache_size(options.cache_size)
+            .open_read_only(data_dir.join(&options.file_name))?;
+
+        let version = schema_version(&db)?;
+        if version != Some(CURRENT_SCHEMA_VERSION) {
+            return Err(StoreError::ReadOnlySchemaMismatch {
+                found: version,
+                expected: CURRENT_SCHEMA_VERSION,
+            });
+        }
+
+        Ok(ReadOnlyLeanDB { db: Arc::new(db) })
+    }
+
+    /// Writes a consistent copy of the database, and of the blobs stored next to it, to
+    /// `dest_dir` while the node keeps running.
+    pub fn snapshot(&self, dest_dir: &Path) -> Result<PathBuf, StoreError> {
+        let blob_dir = self.data_dir.join(BLOB_FOLDER_NAME);
+        // The blobs are copied before the tables' read transaction ends, so pruning can't remove
+        // the blobs of copied blocks in between.
+        let dest_file = snapshot_database(&self.db, dest_dir, &self.file_name, || {
+            if blob_dir.is_dir() {
+                let dest_blob_dir = dest_dir.join(BLOB_FOLDER_NAME);
+                fs::create_dir_all(&dest_blob_dir)?;
+                for entry in fs::read_dir(&blob_dir)? {
+                    let entry = entry?;
+                    fs::copy(entry.path(), dest_blob_dir.join(entry.file_name()))?;
+                }
+            }
+            Ok(())
+        })?;
+
+        Ok(dest_file)
+    }
+
     pub fn init_beacon_db(&self) -> Result<BeaconDB, StoreError> {
         let write_txn = self.db.begin_write()?;
-
-        write_txn.open_table(BeaconBlockTable::TABLE_DEFINITION)?;
-        write_txn.open_table(BeaconStateTable::TABLE_DEFINITION)?;
-        write_txn.open_table(BlockTimelinessTable::TABLE_DEFINITION)?;
-        write_txn.open_table(CheckpointStatesTable::TABLE_DEFINITION)?;
-        write_txn.open_table(EQUIVOCATING_INDICES_FIELD)?;
-        write_txn.open_table(FinalizedCheckpointField::FIELD_DEFINITION)?;
-        write_txn.open_table(GenesisTimeField::FIELD_DEFINITION)?;
-        write_txn.open_table(JustifiedCheckpointField::FIELD_DEFINITION)?;
-        write_txn.open_table(LatestMessagesTable::TABLE_DEFINITION)?;
-        write_txn.open_multimap_table(PARENT_ROOT_INDEX_MULTIMAP_TABLE)?;
-        write_txn.open_table(ProposerBoostRootField::FIELD_DEFINITION)?;
-        write_txn.open_table(BeaconSlotIndexTable::TABLE_DEFINITION)?;
-        write_txn.open_table(BeaconStateRootIndexTable::TABLE_DEFINITION)?;
-        write_txn.open_table(TimeField::FIELD_DEFINITION)?;
-        write_txn.open_table(UnrealizedFinalizedCheckpointField::FIELD_DEFINITION)?;
-        write_txn.open_table(UnrealizedJustificationsTable::TABLE_DEFINITION)?;
-        write_txn.open_table(UnrealizedJustifiedCheckpointField::FIELD_DEFINITION)?;
+        visit_beacon_tables(&mut TableCreator(&write_txn))?;
         write_txn.commit()?;
 
         fs::create_dir_all(self.data_dir.join(BLOB_FOLDER_NAME))?;
@@ -94,52 +248,301 @@ impl ReamDB {
         })
     }
 
-    pub fn init_lean_db(&self) -> Result<LeanDB, StoreError> {
+    /// Deletes every lean table in a single write transaction. [ReamDB::init_lean_db] creates
+    /// them again.
+    pub fn clear_lean_tables(&self) -> Result<(), StoreError> {
+        let write_txn = self.db.begin_write()?;
+        visit_lean_tables(&mut TableDeleter(&write_txn))?;
+        write_txn.commit()?;
+
+        Ok(())
+    }
+
+    /// Deletes every beacon table in a single write transaction, and then the blobs.
+    /// [ReamDB::init_beacon_db] creates them again.
+    pub fn clear_beacon_tables(&self) -> Result<(), StoreError> {
         let write_txn = self.db.begin_write()?;
+        visit_beacon_tables(&mut TableDeleter(&write_txn))?;
+        write_txn.commit()?;
+
+        let blob_dir = self.data_dir.join(BLOB_FOLDER_NAME);
+        if blob_dir.is_dir() {
+            fs::remove_dir_all(blob_dir)?;
+        }
 
-        write_txn.open_table(LatestFinalizedField::FIELD_DEFINITION)?;
-        write_txn.open_table(LatestJustifiedField::FIELD_DEFINITION)?;
-        write_txn.open_table(LeanBlockTable::TABLE_DEFINITION)?;
-        write_txn.open_table(LeanStateTable::TABLE_DEFINITION)?;
-        write_txn.open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
-        write_txn.open_table(LeanStateRootIndexTable::TABLE_DEFINITION)?;
-        write_txn.open_table(LeanTimeField::FIELD_DEFINITION)?;
-        write_txn.open_table(LeanHeadField::FIELD_DEFINITION)?;
-        write_txn.open_table(LeanSafeTargetField::FIELD_DEFINITION)?;
-        write_txn.open_table(LeanLatestNewAttestationsTable::TABLE_DEFINITION)?;
-        write_txn.open_table(LatestKnownAttestationTable::TABLE_DEFINITION)?;
+        Ok(())
+    }
+
+    pub fn init_lean_db(&self) -> Result<LeanDB, StoreError> {
+        let write_txn = self.db.begin_write()?;
+        visit_lean_tables(&mut TableCreator(&write_txn))?;
         write_txn.commit()?;
 
         Ok(LeanDB {
             db: self.db.clone(),
+            file_name: self.file_name.clone(),
         })
     }
 }
 
-pub fn reset_db(db_path: &PathBuf) -> anyhow::Result<()> {
+/// Counts the entries of the tables it visits, skipping the ones which don't exist.
+struct EntryCounter<'a> {
+    read_txn: &'a ReadTransaction,
+    entries: u64,
+}
+
+impl TableVisitor for EntryCounter<'_> {
+    fn table<K: Key + 'static, V: Value + 'static>(
+        &mut self,
+        definition: TableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        match self.read_txn.open_table(definition) {
+            Ok(table) => self.entries += table.len()?,
+            Err(TableError::TableDoesNotExist(_)) => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(())
+    }
+
+    fn multimap_table<K: Key + 'static, V: Key + 'static>(
+        &mut self,
+        definition: MultimapTableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        match self.read_txn.open_multimap_table(definition) {
+            Ok(table) => self.entries += table.len()?,
+            Err(TableError::TableDoesNotExist(_)) => {}
+            Err(err) => return Err(err.into()),
+        }
+        Ok(())
+    }
+}
+
+/// Fails if the shared [REDB_FILE] in `data_dir` holds anything in the tables of `chain`.
+fn ensure_not_in_shared_db(data_dir: &Path, chain: Chain) -> Result<(), StoreError> {
+    let shared_path = data_dir.join(REDB_FILE);
+    if !shared_path.exists() {
+        return Ok(());
+    }
+    let shared_db = Builder::new().open_read_only(&shared_path)?;
+    let read_txn = shared_db.begin_read()?;
+    let mut counter = EntryCounter {
+        read_txn: &read_txn,
+        entries: 0,
+    };
+    match chain {
+        Chain::Lean => visit_lean_tables(&mut counter)?,
+        Chain::Beacon => visit_beacon_tables(&mut counter)?,
+    }
+    if counter.entries > 0 {
+        return Err(StoreError::ChainInSharedDatabase {
+            chain,
+            path: shared_path,
+        });
+    }
+    Ok(())
+}
+
+/// Removes the separate database file of `chain` from `db_path`, if there is one.
+fn remove_chain_file(db_path: &Path, chain: Chain) -> io::Result<()> {
+    let path = db_path.join(chain.redb_file());
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Removes what `scope` covers from the data directory at `db_path`, and returns whether it was
+/// removed.
+///
+/// Asks for confirmation on the terminal unless `confirmed` is set. Without a terminal to ask on,
+/// as in containers and under systemd, it fails rather than waiting on stdin.
+pub fn reset_db(db_path: &PathBuf, scope: ResetScope, confirmed: bool) -> anyhow::Result<bool> {
     if fs::read_dir(db_path)?.next().is_none() {
         info!("Data directory at {db_path:?} is already empty.");
-        return Ok(());
+        return Ok(true);
     }
 
-    info!(
-        "Are you sure you want to clear the contents of the data directory at {db_path:?}? (y/n):"
-    );
-    let mut input = String::new();
-    io::stdin().read_line(&mut input)?;
-    if input.trim().eq_ignore_ascii_case("y") {
-        for entry in fs::read_dir(db_path)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.is_dir() {
-                fs::remove_dir_all(&path)?;
-            } else {
-                fs::remove_file(&path)?;
+    let target = match scope {
+        ResetScope::All => "the contents of the data directory",
+        ResetScope::Lean => "the lean tables of the database",
+        ResetScope::Beacon => "the beacon tables and blobs of the database",
+    };
+    if !confirmed {
+        if !io::stdin().is_terminal() {
+            bail!(
+                "Refusing to clear {target} at {db_path:?} without confirmation, pass --yes to \
+                 skip it"
+            );
+        }
+        info!("Are you sure you want to clear {target} at {db_path:?}? (y/n):");
+        let mut input = String::new();
+        io::stdin().read_line(&mut input)?;
+        if !input.trim().eq_ignore_ascii_case("y") {
+            info!("Operation canceled by user.");
+            return Ok(false);
+        }
+    }
+
+    match scope {
+        ResetScope::All => {
+            for entry in fs::read_dir(db_path)? {
+                let entry = entry?;
+                let path = entry.path();
+                if path.is_dir() {
+                    fs::remove_dir_all(&path)?;
+                } else {
+                    fs::remove_file(&path)?;
+                }
+            }
+        }
+        ResetScope::Lean => {
+            remove_chain_file(db_path, Chain::Lean)?;
+            if db_path.join(REDB_FILE).exists() {
+                ReamDB::new(db_path.clone())?.clear_lean_tables()?;
+            }
+        }
+        ResetScope::Beacon => {
+            remove_chain_file(db_path, Chain::Beacon)?;
+            if db_path.join(REDB_FILE).exists() {
+                ReamDB::new(db_path.clone())?.clear_beacon_tables()?;
+            }
+            let blob_dir = db_path.join(BLOB_FOLDER_NAME);
+            if blob_dir.is_dir() {
+                fs::remove_dir_all(blob_dir)?;
             }
         }
-        info!("Database contents cleared successfully.");
-    } else {
-        info!("Operation canceled by user.");
     }
-    Ok(())
+    info!("Cleared {target} successfully.");
+    Ok(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use tempdir::TempDir;
+
+    use super::{
+        BEACON_REDB_FILE, Chain, DatabaseOptions, LEAN_REDB_FILE, REDB_CACHE_SIZE, REDB_FILE,
+        ReamDB, ResetScope, reset_db,
+    };
+    use crate::{errors::StoreError, tables::field::REDBField};
+
+    #[test]
+    fn test_in_memory() {
+        let lean_db = ReamDB::in_memory().unwrap().init_lean_db().unwrap();
+        lean_db
+            .head_provider()
+            .insert(B256::repeat_byte(1))
+            .unwrap();
+        assert_eq!(lean_db.head_provider().get().unwrap(), B256::repeat_byte(1));
+    }
+
+    #[test]
+    fn test_reset_lean_tables() {
+        let dir = TempDir::new("reset_db").unwrap();
+        let ream_db = ReamDB::new(dir.path().to_path_buf()).unwrap();
+        let lean_db = ream_db.init_lean_db().unwrap();
+        let beacon_db = ream_db.init_beacon_db().unwrap();
+        lean_db
+            .head_provider()
+            .insert(B256::repeat_byte(1))
+            .unwrap();
+        beacon_db.genesis_time_provider().insert(42).unwrap();
+        drop((ream_db, lean_db, beacon_db));
+
+        reset_db(&dir.path().to_path_buf(), ResetScope::Lean, true).unwrap();
+
+        let ream_db = ReamDB::new(dir.path().to_path_buf()).unwrap();
+        assert!(
+            ream_db
+                .init_lean_db()
+                .unwrap()
+                .head_provider()
+                .get()
+                .is_err()
+        );
+        assert_eq!(
+            ream_db
+                .init_beacon_db()
+                .unwrap()
+                .genesis_time_provider()
+                .get()
+                .unwrap(),
+            42
+        );
+    }
+
+    #[test]
+    fn test_separate_chain_databases() {
+        let dir = TempDir::new("separate_dbs").unwrap();
+        let lean_options = DatabaseOptions::for_chain(Chain::Lean, true, REDB_CACHE_SIZE);
+        let beacon_options = DatabaseOptions::for_chain(Chain::Beacon, true, REDB_CACHE_SIZE);
+        {
+            let lean_db = ReamDB::with_options(dir.path().to_path_buf(), &lean_options)
+                .unwrap()
+                .init_lean_db()
+                .unwrap();
+            let beacon_db = ReamDB::with_options(dir.path().to_path_buf(), &beacon_options)
+                .unwrap()
+                .init_beacon_db()
+                .unwrap();
+            lean_db
+                .head_provider()
+                .insert(B256::repeat_byte(1))
+                .unwrap();
+            beacon_db.genesis_time_provider().insert(42).unwrap();
+        }
+        assert!(dir.path().join(LEAN_REDB_FILE).exists());
+        assert!(dir.path().join(BEACON_REDB_FILE).exists());
+        assert!(!dir.path().join(REDB_FILE).exists());
+
+        reset_db(&dir.path().to_path_buf(), ResetScope::Lean, true).unwrap();
+
+        assert!(!dir.path().join(LEAN_REDB_FILE).exists());
+        let beacon_db = ReamDB::with_options(dir.path().to_path_buf(), &beacon_options)
+            .unwrap()
+            .init_beacon_db()
+            .unwrap();
+        assert_eq!(beacon_db.genesis_time_provider().get().unwrap(), 42);
+
+        // Snapshots keep the file name, so they open with the same options.
+        let lean_db = ReamDB::with_options(dir.path().to_path_buf(), &lean_options)
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        let snapshot_dir = TempDir::new("separate_dbs_snapshot").unwrap();
+        assert_eq!(
+            lean_db.snapshot(snapshot_dir.path()).unwrap(),
+            snapshot_dir.path().join(LEAN_REDB_FILE)
+        );
+    }
+
+    #[test]
+    fn test_separate_database_refuses_shared_chain() {
+        let dir = TempDir::new("shared_chain").unwrap();
+        ReamDB::new(dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap()
+            .head_provider()
+            .insert(B256::repeat_byte(1))
+            .unwrap();
+
+        // The lean chain is in the shared database, so it can't move to a separate one, but the
+        // beacon chain, which the shared database has no data of, can.
+        let lean_options = DatabaseOptions::for_chain(Chain::Lean, true, REDB_CACHE_SIZE);
+        assert!(matches!(
+            ReamDB::with_options(dir.path().to_path_buf(), &lean_options),
+            Err(StoreError::ChainInSharedDatabase {
+                chain: Chain::Lean,
+                ..
+            })
+        ));
+        assert!(!dir.path().join(LEAN_REDB_FILE).exists());
+        let beacon_options = DatabaseOptions::for_chain(Chain::Beacon, true, REDB_CACHE_SIZE);
+        assert!(ReamDB::with_options(dir.path().to_path_buf(), &beacon_options).is_ok());
+
+        reset_db(&dir.path().to_path_buf(), ResetScope::Lean, true).unwrap();
+        assert!(ReamDB::with_options(dir.path().to_path_buf(), &lean_options).is_ok());
+    }
 }
@@ -0,0 +1,167 @@
+use std::sync::Arc;
+
+use alloy_primitives::B256;
+use ream_consensus_lean::{
+    block::SignedBlockWithAttestation, checkpoint::Checkpoint, state::LeanState,
+};
+use redb::{ReadOnlyDatabase, ReadableDatabase, ReadableTable};
+
+use crate::{
+    db::lean::{
+        CanonicalChainIter, IndexInconsistency, IntegrityIssue, TableSummary, canonical_chain,
+        table_stats, verify_indices, verify_integrity,
+    },
+    errors::StoreError,
+    tables::{
+        field::REDBField,
+        lean::{
+            latest_finalized::LatestFinalizedField, latest_justified::LatestJustifiedField,
+            lean_block::LeanBlockTable, lean_head::LeanHeadField, lean_state::LeanStateTable,
+            slot_index::LeanSlotIndexTable, state_root_index::LeanStateRootIndexTable,
+        },
+        table::REDBTable,
+    },
+};
+
+/// Read-only view of the lean tables, which never opens a write transaction.
+///
+/// redb takes a shared lock for read-only handles, so several processes can read the same
+/// database, but not while a node has it open for writing. Point tooling at a stopped node or at
+/// a snapshot of a running one.
+#[derive(Clone, Debug)]
+pub struct ReadOnlyLeanDB {
+    pub db: Arc<ReadOnlyDatabase>,
+}
+
+impl ReadOnlyLeanDB {
+    pub fn block(
+        &self,
+        block_root: B256,
+    ) -> Result<Option<SignedBlockWithAttestation>, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(LeanBlockTable::TABLE_DEFINITION)?;
+        Ok(table.get(block_root)?.map(|block| block.value()))
+    }
+
+    pub fn state(&self, block_root: B256) -> Result<Option<LeanState>, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(LeanStateTable::TABLE_DEFINITION)?;
+        Ok(table.get(block_root)?.map(|state| state.value()))
+    }
+
+    pub fn block_root_at_slot(&self, slot: u64) -> Result<Option<B256>, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+        Ok(table.get(slot)?.map(|root| root.value()))
+    }
+
+    pub fn block_root_by_state_root(&self, state_root: B256) -> Result<Option<B256>, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(LeanStateRootIndexTable::TABLE_DEFINITION)?;
+        Ok(table.get(state_root)?.map(|root| root.value()))
+    }
+
+    pub fn head(&self) -> Result<B256, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(LeanHeadField::FIELD_DEFINITION)?;
+        Ok(table
+            .get(LeanHeadField::KEY)?
+            .ok_or(StoreError::FieldNotInitilized)?
+            .value())
+    }
+
+    pub fn latest_justified(&self) -> Result<Checkpoint, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(LatestJustifiedField::FIELD_DEFINITION)?;
+        Ok(table
+            .get(LatestJustifiedField::KEY)?
+            .ok_or(StoreError::FieldNotInitilized)?
+            .value())
+    }
+
+    pub fn latest_finalized(&self) -> Result<Checkpoint, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(LatestFinalizedField::FIELD_DEFINITION)?;
+        Ok(table
+            .get(LatestFinalizedField::KEY)?
+            .ok_or(StoreError::FieldNotInitilized)?
+            .value())
+    }
+
+    /// Returns the number of entries and stored bytes of every lean table.
+    pub fn table_stats(&self) -> Result<Vec<TableSummary>, StoreError> {
+        table_stats(&self.db.begin_read()?)
+    }
+
+    /// Checks that every slot index and state root index entry points at a stored block which
+    /// agrees with it.
+    pub fn verify_indices(&self) -> Result<Vec<IndexInconsistency>, StoreError> {
+        verify_indices(&self.db.begin_read()?)
+    }
+
+    /// Checks the indices, and that the head, its state and the latest justified and finalized
+    /// blocks are stored.
+    pub fn verify_integrity(&self) -> Result<Vec<IntegrityIssue>, StoreError> {
+        verify_integrity(&self.db.begin_read()?)
+    }
+
+    /// Returns every block on the chain of the head between `from_slot` and `to_slot`
+    /// (inclusive), see [LeanDB](crate::db::lean::LeanDB::canonical_chain_iter).
+    pub fn canonical_chain_iter(
+        &self,
+        from_slot: u64,
+        to_slot: u64,
+    ) -> Result<CanonicalChainIter, StoreError> {
+        canonical_chain(&self.db.begin_read()?, from_slot, to_slot)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use tempdir::TempDir;
+
+    use crate::{
+        db::ReamDB,
+        errors::StoreError,
+        tables::{field::REDBField, table::REDBTable},
+    };
+
+    #[test]
+    fn test_open_read_only() {
+        let dir = TempDir::new("read_only").unwrap();
+        {
+            let lean_db = ReamDB::new(dir.path().to_path_buf())
+                .unwrap()
+                .init_lean_db()
+                .unwrap();
+            lean_db
+                .head_provider()
+                .insert(B256::repeat_byte(1))
+                .unwrap();
+            lean_db
+                .slot_index_provider()
+                .insert(3, B256::repeat_byte(2))
+                .unwrap();
+        }
+
+        let read_only_db = ReamDB::open_read_only(dir.path().to_path_buf()).unwrap();
+        assert_eq!(read_only_db.head().unwrap(), B256::repeat_byte(1));
+        assert_eq!(
+            read_only_db.block_root_at_slot(3).unwrap(),
+            Some(B256::repeat_byte(2))
+        );
+        assert!(matches!(
+            read_only_db.latest_finalized(),
+            Err(StoreError::FieldNotInitilized)
+        ));
+
+        // Several read-only handles can be open at once.
+        let second_read_only_db = ReamDB::open_read_only(dir.path().to_path_buf()).unwrap();
+        assert_eq!(
+            second_read_only_db.verify_indices().unwrap().len(),
+            1,
+            "The slot index points at a block which was never stored"
+        );
+    }
+}
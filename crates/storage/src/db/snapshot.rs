@@ -0,0 +1,222 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use redb::{
+    Database, Key, MultimapTableDefinition, ReadTransaction, ReadableDatabase,
+    ReadableMultimapTable, ReadableTable, TableDefinition, TableError, Value, WriteTransaction,
+};
+use tracing::info;
+
+use crate::{
+    errors::StoreError,
+    tables::{
+        field::REDBField,
+        schema::{TableVisitor, visit_beacon_tables, visit_lean_tables},
+        schema_version::SchemaVersionField,
+    },
+};
+
+fn copy_table<K: Key + 'static, V: Value + 'static>(
+    read_txn: &ReadTransaction,
+    write_txn: &WriteTransaction,
+    definition: TableDefinition<K, V>,
+) -> Result<(), StoreError> {
+    let source = match read_txn.open_table(definition) {
+        Ok(source) => source,
+        Err(TableError::TableDoesNotExist(_)) => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut destination = write_txn.open_table(definition)?;
+    for entry in source.iter()? {
+        let (key, value) = entry?;
+        destination.insert(key.value(), value.value())?;
+    }
+    Ok(())
+}
+
+fn copy_multimap_table<K: Key + 'static, V: Key + 'static>(
+    read_txn: &ReadTransaction,
+    write_txn: &WriteTransaction,
+    definition: MultimapTableDefinition<K, V>,
+) -> Result<(), StoreError> {
+    let source = match read_txn.open_multimap_table(definition) {
+        Ok(source) => source,
+        Err(TableError::TableDoesNotExist(_)) => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut destination = write_txn.open_multimap_table(definition)?;
+    for entry in source.iter()? {
+        let (key, values) = entry?;
+        for value in values {
+            destination.insert(key.value(), value?.value())?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the tables it visits from a read transaction of the source database.
+struct TableCopier<'a> {
+    read_txn: &'a ReadTransaction,
+    write_txn: &'a WriteTransaction,
+}
+
+impl TableVisitor for TableCopier<'_> {
+    fn table<K: Key + 'static, V: Value + 'static>(
+        &mut self,
+        definition: TableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        copy_table(self.read_txn, self.write_txn, definition)
+    }
+
+    fn multimap_table<K: Key + 'static, V: Key + 'static>(
+        &mut self,
+        definition: MultimapTableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        copy_multimap_table(self.read_txn, self.write_txn, definition)
+    }
+}
+
+/// Copies every table of `db` into a new database file `file_name` in `dest_dir`, returning the
+/// path of the new redb file.
+///
+/// All tables are read from a single read transaction, so the copy is consistent even while the
+/// node keeps writing to `db`. `copy_files` runs while that transaction is still open, for files
+/// stored next to the database which have to match the copied tables. The copy is compacted
+/// before it is closed, and `dest_dir` can be used as the `--data-dir` of another node.
+pub fn snapshot_database(
+    db: &Database,
+    dest_dir: &Path,
+    file_name: &str,
+    copy_files: impl FnOnce() -> Result<(), StoreError>,
+) -> Result<PathBuf, StoreError> {
+    let dest_file = dest_dir.join(file_name);
+    if dest_file.exists() {
+        return Err(StoreError::SnapshotDestinationExists(dest_file));
+    }
+    fs::create_dir_all(dest_dir)?;
+
+    let mut snapshot = Database::create(&dest_file)?;
+    let read_txn = db.begin_read()?;
+    let write_txn = snapshot.begin_write()?;
+
+    let mut copier = TableCopier {
+        read_txn: &read_txn,
+        write_txn: &write_txn,
+    };
+    copier.table(SchemaVersionField::FIELD_DEFINITION)?;
+    visit_lean_tables(&mut copier)?;
+    visit_beacon_tables(&mut copier)?;
+    copy_files()?;
+
+    write_txn.commit()?;
+    drop(read_txn);
+    snapshot.compact()?;
+
+    info!("Database snapshot written to {}", dest_file.display());
+    Ok(dest_file)
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use redb::{Database, MultimapTableHandle, ReadableDatabase, TableHandle};
+    use tempdir::TempDir;
+
+    use crate::{
+        db::ReamDB,
+        errors::StoreError,
+        tables::{
+            field::REDBField,
+            lean::validator_performance::{LeanValidatorPerformanceTable, SlotPerformance},
+            table::REDBTable,
+        },
+    };
+
+    #[test]
+    fn test_snapshot_while_open() {
+        let source_dir = TempDir::new("snapshot_source").unwrap();
+        let ream_db = ReamDB::new(source_dir.path().to_path_buf()).unwrap();
+        let lean_db = ream_db.init_lean_db().unwrap();
+        lean_db
+            .head_provider()
+            .insert(B256::repeat_byte(1))
+            .unwrap();
+        lean_db
+            .slot_index_provider()
+            .insert(7, B256::repeat_byte(2))
+            .unwrap();
+
+        let dest_dir = TempDir::new("snapshot_dest").unwrap();
+        let dest = dest_dir.path().join("snapshot");
+        ream_db.snapshot(&dest).unwrap();
+
+        // The source stays writable, and later writes don't reach the snapshot.
+        lean_db
+            .head_provider()
+            .insert(B256::repeat_byte(3))
+            .unwrap();
+
+        let snapshot = ReamDB::open_read_only(dest.clone()).unwrap();
+        assert_eq!(snapshot.head().unwrap(), B256::repeat_byte(1));
+        assert_eq!(
+            snapshot.block_root_at_slot(7).unwrap(),
+            Some(B256::repeat_byte(2))
+        );
+
+        assert!(matches!(
+            ream_db.snapshot(&dest),
+            Err(StoreError::SnapshotDestinationExists(_))
+        ));
+    }
+
+    #[test]
+    fn test_snapshot_copies_every_table() {
+        let source_dir = TempDir::new("snapshot_source").unwrap();
+        let ream_db = ReamDB::new(source_dir.path().to_path_buf()).unwrap();
+        let lean_db = ream_db.init_lean_db().unwrap();
+        let _beacon_db = ream_db.init_beacon_db().unwrap();
+        let performance = SlotPerformance {
+            slot: 3,
+            is_proposer: true,
+            proposed: true,
+            attestation_included: false,
+        };
+        lean_db
+            .validator_performance_provider()
+            .record_slot([(1, performance)], 8)
+            .unwrap();
+
+        let dest_dir = TempDir::new("snapshot_dest").unwrap();
+        let dest_file = ream_db.snapshot(dest_dir.path()).unwrap();
+        drop(lean_db);
+
+        let table_names = |db: &Database| {
+            let read_txn = db.begin_read().unwrap();
+            let mut names = read_txn
+                .list_tables()
+                .unwrap()
+                .map(|table| table.name().to_string())
+                .chain(
+                    read_txn
+                        .list_multimap_tables()
+                        .unwrap()
+                        .map(|table| table.name().to_string()),
+                )
+                .collect::<Vec<_>>();
+            names.sort();
+            names
+        };
+        let snapshot = Database::open(dest_file).unwrap();
+        assert_eq!(table_names(&ream_db.db), table_names(&snapshot));
+        assert_eq!(
+            LeanValidatorPerformanceTable {
+                db: snapshot.into()
+            }
+            .get((1, 3))
+            .unwrap(),
+            Some(performance)
+        );
+    }
+}
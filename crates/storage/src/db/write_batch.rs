@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use alloy_primitives::B256;
+use ream_consensus_lean::{
+    attestation::SignedAttestation, block::SignedBlockWithAttestation, checkpoint::Checkpoint,
+    state::LeanState,
+};
+use redb::{ReadableTable, WriteTransaction};
+
+use crate::{
+    errors::StoreError,
+    tables::{
+        field::REDBField,
+        lean::{
+            latest_finalized::LatestFinalizedField, latest_justified::LatestJustifiedField,
+            latest_known_attestation::LatestKnownAttestationTable, lean_block::LeanBlockTable,
+            lean_latest_new_attestations::LeanLatestNewAttestationsTable,
+            lean_state::LeanStateTable, slot_index::LeanSlotIndexTable,
+            state_root_index::LeanStateRootIndexTable,
+        },
+        table::REDBTable,
+    },
+};
+
+/// Writes to the lean tables which become visible together, when the enclosing
+/// [LeanDB::with_write_batch](crate::db::lean::LeanDB::with_write_batch) commits.
+pub struct WriteBatch<'a> {
+    pub(crate) write_txn: &'a WriteTransaction,
+}
+
+impl WriteBatch<'_> {
+    /// Inserts a block together with its slot index and state root index entries, like
+    /// [LeanBlockTable::insert] does. A finalized slot keeps the block it is indexed to, which
+    /// [WriteBatch::index_finalized_chain] set to the finalized chain.
+    pub fn insert_block(
+        &self,
+        block_root: B256,
+        block: &SignedBlockWithAttestation,
+    ) -> Result<(), StoreError> {
+        let slot = block.message.block.slot;
+        let finalized_slot = self
+            .write_txn
+            .open_table(LatestFinalizedField::FIELD_DEFINITION)?
+            .get(LatestFinalizedField::KEY)?
+            .map(|checkpoint| checkpoint.value().slot);
+        let mut slot_index_table = self
+            .write_txn
+            .open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+        if finalized_slot.is_none_or(|finalized_slot| slot > finalized_slot)
+            || slot_index_table.get(slot)?.is_none()
+        {
+            slot_index_table.insert(slot, block_root)?;
+        }
+        drop(slot_index_table);
+        self.write_txn
+            .open_table(LeanStateRootIndexTable::TABLE_DEFINITION)?
+            .insert(block.message.block.state_root, block_root)?;
+        self.write_txn
+            .open_table(LeanBlockTable::TABLE_DEFINITION)?
+            .insert(block_root, block)?;
+        Ok(())
+    }
+
+    pub fn insert_state(&self, block_root: B256, state: &LeanState) -> Result<(), StoreError> {
+        self.write_txn
+            .open_table(LeanStateTable::TABLE_DEFINITION)?
+            .insert(block_root, state)?;
+        Ok(())
+    }
+
+    pub fn insert_latest_known_attestation(
+        &self,
+        validator_id: u64,
+        attestation: &SignedAttestation,
+    ) -> Result<(), StoreError> {
+        self.write_txn
+            .open_table(LatestKnownAttestationTable::TABLE_DEFINITION)?
+            .insert(validator_id, attestation)?;
+        Ok(())
+    }
+
+    pub fn insert_latest_new_attestation(
+        &self,
+        validator_id: u64,
+        attestation: &SignedAttestation,
+    ) -> Result<(), StoreError> {
+        self.write_txn
+            .open_table(LeanLatestNewAttestationsTable::TABLE_DEFINITION)?
+            .insert(validator_id, attestation)?;
+        Ok(())
+    }
+
+    pub fn remove_latest_new_attestation(&self, validator_id: u64) -> Result<(), StoreError> {
+        self.write_txn
+            .open_table(LeanLatestNewAttestationsTable::TABLE_DEFINITION)?
+            .remove(validator_id)?;
+        Ok(())
+    }
+
+    pub fn set_latest_justified(&self, checkpoint: Checkpoint) -> Result<(), StoreError> {
+        self.write_txn
+            .open_table(LatestJustifiedField::FIELD_DEFINITION)?
+            .insert(LatestJustifiedField::KEY, checkpoint)?;
+        Ok(())
+    }
+
+    pub fn set_latest_finalized(&self, checkpoint: Checkpoint) -> Result<(), StoreError> {
+        self.write_txn
+            .open_table(LatestFinalizedField::FIELD_DEFINITION)?
+            .insert(LatestFinalizedField::KEY, checkpoint)?;
+        Ok(())
+    }
+
+    /// Points the slot index of the slots after `previous_finalized_slot` up to the `finalized`
+    /// block at the blocks of the finalized chain, and drops the entries of forks there. The
+    /// finalized chain never changes, so [canonical_chain](crate::db::lean::canonical_chain)
+    /// trusts the slot index for finalized slots instead of walking back to them from the head.
+    pub fn index_finalized_chain(
+        &self,
+        previous_finalized_slot: u64,
+        finalized: Checkpoint,
+    ) -> Result<(), StoreError> {
+        if finalized.slot <= previous_finalized_slot {
+            return Ok(());
+        }
+        let block_table = self
+            .write_txn
+            .open_table(LeanBlockTable::TABLE_DEFINITION)?;
+        let mut slot_index_table = self
+            .write_txn
+            .open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+
+        let mut finalized_chain = HashMap::new();
+        let mut current = finalized.root;
+        while let Some(block) = block_table.get(current)? {
+            let block = block.value().message.block;
+            if block.slot <= previous_finalized_slot {
+                break;
+            }
+            finalized_chain.insert(block.slot, current);
+            current = block.parent_root;
+        }
+
+        let indexed_slots = slot_index_table
+            .range(previous_finalized_slot + 1..=finalized.slot)?
+            .map(|entry| entry.map(|(slot, _)| slot.value()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for slot in indexed_slots {
+            if !finalized_chain.contains_key(&slot) {
+                slot_index_table.remove(slot)?;
+            }
+        }
+        for (slot, block_root) in finalized_chain {
+            slot_index_table.insert(slot, block_root)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ream_consensus_lean::checkpoint::Checkpoint;
+    use tempdir::TempDir;
+
+    use crate::{
+        db::ReamDB,
+        errors::StoreError,
+        tables::{field::REDBField, table::REDBTable},
+    };
+
+    #[test]
+    fn test_write_batch_is_atomic() {
+        let dir = TempDir::new("write_batch").unwrap();
+        let lean_db = ReamDB::new(dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        let checkpoint = Checkpoint {
+            root: B256::repeat_byte(1),
+            slot: 5,
+        };
+
+        let result = lean_db.with_write_batch(|batch| {
+            batch.set_latest_justified(checkpoint)?;
+            batch.set_latest_finalized(checkpoint)?;
+            Err::<(), _>(StoreError::FieldNotInitilized)
+        });
+        assert!(result.is_err());
+        assert!(lean_db.latest_justified_provider().get().is_err());
+        assert!(lean_db.latest_finalized_provider().get().is_err());
+
+        lean_db
+            .with_write_batch(|batch| {
+                batch.set_latest_justified(checkpoint)?;
+                batch.set_latest_finalized(checkpoint)
+            })
+            .unwrap();
+        assert_eq!(
+            lean_db.latest_justified_provider().get().unwrap(),
+            checkpoint
+        );
+        assert_eq!(
+            lean_db.latest_finalized_provider().get().unwrap(),
+            checkpoint
+        );
+        assert_eq!(lean_db.slot_index_provider().get(5).unwrap(), None);
+    }
+}
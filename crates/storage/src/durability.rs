@@ -0,0 +1,105 @@
+//! How soon a committed write transaction reaches the disk. Every table and field of the crate
+//! opens its write transactions with [begin_write], so the profile set at startup with
+//! [set_durability_profile] applies to all of them.
+
+use std::{
+    str::FromStr,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use anyhow::anyhow;
+use redb::{Database, Durability, WriteTransaction};
+
+use crate::errors::StoreError;
+
+static EVENTUAL: AtomicBool = AtomicBool::new(false);
+
+/// The durability write transactions are committed with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DurabilityProfile {
+    /// Every commit is fsynced before it returns, so a crash never loses a committed write. Each
+    /// commit pays for an fsync, which bounds how many commits a second the node can make.
+    #[default]
+    Immediate,
+    /// Commits return without an fsync and only become durable with the next [flush], which the
+    /// caller has to run periodically and on shutdown. A crash or power loss drops every commit
+    /// since the last flush, but redb recovers the database to the last durable commit, so it
+    /// still opens consistently. The commits in between also keep their old pages allocated
+    /// until the flush, so the file grows faster the longer flushes are apart.
+    Eventual,
+}
+
+impl FromStr for DurabilityProfile {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "immediate" => Ok(DurabilityProfile::Immediate),
+            "eventual" => Ok(DurabilityProfile::Eventual),
+            _ => Err(anyhow!(
+                "Unknown durability profile {s}, options are immediate and eventual"
+            )),
+        }
+    }
+}
+
+/// Sets the profile of every write transaction opened from now on, in every database.
+pub fn set_durability_profile(profile: DurabilityProfile) {
+    EVENTUAL.store(profile == DurabilityProfile::Eventual, Ordering::Relaxed);
+}
+
+pub fn durability_profile() -> DurabilityProfile {
+    if EVENTUAL.load(Ordering::Relaxed) {
+        DurabilityProfile::Eventual
+    } else {
+        DurabilityProfile::Immediate
+    }
+}
+
+/// Begins a write transaction on `db` with the durability of the current profile.
+pub(crate) fn begin_write(db: &Database) -> Result<WriteTransaction, StoreError> {
+    let mut write_txn = db.begin_write()?;
+    write_txn.set_durability(match durability_profile() {
+        DurabilityProfile::Immediate => Durability::Immediate,
+        DurabilityProfile::Eventual => Durability::None,
+    })?;
+    Ok(write_txn)
+}
+
+/// Makes every commit to `db` so far durable, by committing an empty transaction with
+/// [Durability::Immediate].
+pub fn flush(db: &Database) -> Result<(), StoreError> {
+    let mut write_txn = db.begin_write()?;
+    write_txn.set_durability(Durability::Immediate)?;
+    write_txn.commit()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use tempdir::TempDir;
+
+    use super::{DurabilityProfile, set_durability_profile};
+    use crate::{db::ReamDB, tables::field::REDBField};
+
+    #[test]
+    fn test_eventual_durability() {
+        let dir = TempDir::new("durability").unwrap();
+        let ream_db = ReamDB::new(dir.path().to_path_buf()).unwrap();
+        let lean_db = ream_db.init_lean_db().unwrap();
+
+        set_durability_profile(DurabilityProfile::Eventual);
+        let result = lean_db.head_provider().insert(B256::repeat_byte(1));
+        set_durability_profile(DurabilityProfile::Immediate);
+        result.unwrap();
+        ream_db.flush().unwrap();
+
+        drop((ream_db, lean_db));
+        let lean_db = ReamDB::new(dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        assert_eq!(lean_db.head_provider().get().unwrap(), B256::repeat_byte(1));
+    }
+}
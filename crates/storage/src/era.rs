@@ -0,0 +1,540 @@
+//! Era-style archives of finalized lean chain data.
+//!
+//! An archive is a sequence of e2store-style records. Each record is an 8 byte header (2 byte
+//! type, 4 byte little endian length, 2 reserved zero bytes) followed by the record data. The
+//! first record is always a version record. Blocks and states are SSZ encoded and snappy
+//! compressed, and a state record belongs to the block record immediately before it. The first
+//! block record is always followed by its state, which the later blocks are replayed on.
+
+use std::io::{self, Read, Write};
+
+use alloy_primitives::B256;
+use ream_consensus_lean::{
+    block::SignedBlockWithAttestation, checkpoint::Checkpoint, state::LeanState,
+};
+use snap::raw::{Decoder, Encoder};
+use ssz::{Decode, Encode};
+use tree_hash::TreeHash;
+
+use crate::{
+    db::{lean::LeanDB, read_only::ReadOnlyLeanDB},
+    errors::StoreError,
+    tables::{field::REDBField, table::REDBTable},
+};
+
+pub const ERA_FILE_EXTENSION: &str = "era";
+
+pub const VERSION_RECORD: [u8; 2] = [0x65, 0x32];
+pub const COMPRESSED_BLOCK_RECORD: [u8; 2] = [0x10, 0x00];
+pub const COMPRESSED_STATE_RECORD: [u8; 2] = [0x11, 0x00];
+
+const HEADER_LENGTH: usize = 8;
+
+/// Records longer than this are rejected rather than read, so a corrupt length can't make an
+/// import allocate gigabytes.
+pub const MAX_RECORD_LENGTH: usize = 256 * 1024 * 1024;
+
+/// Blocks and states written to or read from an archive.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct EraSummary {
+    pub blocks: u64,
+    pub states: u64,
+}
+
+pub fn write_record<W: Write>(
+    writer: &mut W,
+    record_type: [u8; 2],
+    data: &[u8],
+) -> Result<(), StoreError> {
+    let length = u32::try_from(data.len())
+        .map_err(|err| StoreError::InvalidEra(format!("Record is too large: {err}")))?;
+    let mut header = [0u8; HEADER_LENGTH];
+    header[..2].copy_from_slice(&record_type);
+    header[2..6].copy_from_slice(&length.to_le_bytes());
+    writer.write_all(&header)?;
+    writer.write_all(data)?;
+    Ok(())
+}
+
+/// Reads the next record, returning `None` once the reader is exhausted.
+pub fn read_record<R: Read>(reader: &mut R) -> Result<Option<([u8; 2], Vec<u8>)>, StoreError> {
+    let mut header = [0u8; HEADER_LENGTH];
+    match reader.read_exact(&mut header) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+    if header[6..] != [0, 0] {
+        return Err(StoreError::InvalidEra(
+            "Reserved header bytes must be zero".to_string(),
+        ));
+    }
+
+    let record_type = [header[0], header[1]];
+    let length = u32::from_le_bytes([header[2], header[3], header[4], header[5]]) as usize;
+    if length > MAX_RECORD_LENGTH {
+        return Err(StoreError::InvalidEra(format!(
+            "Record of {length} bytes is longer than {MAX_RECORD_LENGTH} bytes"
+        )));
+    }
+    // The buffer grows as data is read, so a truncated archive doesn't allocate the full length.
+    let mut data = vec![];
+    reader.take(length as u64).read_to_end(&mut data)?;
+    if data.len() != length {
+        return Err(StoreError::InvalidEra(format!(
+            "Record of {length} bytes is truncated to {} bytes",
+            data.len()
+        )));
+    }
+    Ok(Some((record_type, data)))
+}
+
+/// Writes the canonical blocks between `from_slot` and `to_slot` (inclusive) to `writer`.
+///
+/// The archive starts at the first of those blocks whose state is stored, so an import has a state
+/// to start from. The state of every later block whose slot is a multiple of `state_interval` is
+/// written after its block too.
+pub fn export_era<W: Write>(
+    lean_db: &ReadOnlyLeanDB,
+    from_slot: u64,
+    to_slot: u64,
+    state_interval: u64,
+    writer: &mut W,
+) -> Result<EraSummary, StoreError> {
+    let mut summary = EraSummary::default();
+    write_record(writer, VERSION_RECORD, &[])?;
+
+    let mut encoder = Encoder::new();
+    for entry in lean_db.canonical_chain_iter(from_slot, to_slot)? {
+        let (slot, block_root, block) = entry?;
+        let state = if summary.blocks == 0 || slot.is_multiple_of(state_interval) {
+            lean_db.state(block_root)?
+        } else {
+            None
+        };
+        if summary.blocks == 0 && state.is_none() {
+            continue;
+        }
+
+        write_record(
+            writer,
+            COMPRESSED_BLOCK_RECORD,
+            &encoder.compress_vec(&block.as_ssz_bytes())?,
+        )?;
+        summary.blocks += 1;
+        if let Some(state) = state {
+            write_record(
+                writer,
+                COMPRESSED_STATE_RECORD,
+                &encoder.compress_vec(&state.as_ssz_bytes())?,
+            )?;
+            summary.states += 1;
+        }
+    }
+
+    writer.flush()?;
+    Ok(summary)
+}
+
+/// Imports every block and state in the archive read from `reader` into `lean_db`.
+///
+/// The first block is checked against the state after it, and every later block is replayed on
+/// the state of the block before it, with its signatures checked if `verify_signatures` is set,
+/// so a block which doesn't match its state root is rejected. The state of the last block is
+/// always stored. As the archive holds finalized blocks, the head and the finalized and justified
+/// checkpoints then move to the last block, unless the database already finalized a later one.
+pub fn import_era<R: Read>(
+    lean_db: &LeanDB,
+    reader: &mut R,
+    verify_signatures: bool,
+) -> Result<EraSummary, StoreError> {
+    match read_record(reader)? {
+        Some((VERSION_RECORD, _)) => {}
+        _ => {
+            return Err(StoreError::InvalidEra(
+                "Archive doesn't start with a version record".to_string(),
+            ));
+        }
+    }
+
+    let mut summary = EraSummary::default();
+    let mut decoder = Decoder::new();
+    // The first block, until the state it is checked against is read.
+    let mut first_block: Option<SignedBlockWithAttestation> = None;
+    // The root and post state of the last imported block, and whether that state is stored.
+    let mut last_block: Option<(B256, LeanState, bool)> = None;
+    while let Some((record_type, data)) = read_record(reader)? {
+        match record_type {
+            COMPRESSED_BLOCK_RECORD => {
+                let block =
+                    SignedBlockWithAttestation::from_ssz_bytes(&decoder.decompress_vec(&data)?)?;
+                let slot = block.message.block.slot;
+                let Some((last_root, state, state_stored)) = last_block.as_mut() else {
+                    if first_block.is_some() {
+                        return Err(StoreError::InvalidEra(
+                            "First block isn't followed by its state".to_string(),
+                        ));
+                    }
+                    first_block = Some(block);
+                    continue;
+                };
+                if block.message.block.parent_root != *last_root {
+                    return Err(StoreError::InvalidEra(format!(
+                        "Block at slot {slot} doesn't build on the block before it"
+                    )));
+                }
+                block
+                    .verify_signatures(state, verify_signatures)
+                    .map_err(|err| {
+                        StoreError::InvalidEra(format!(
+                            "Invalid signatures in block at slot {slot}: {err:#}"
+                        ))
+                    })?;
+                state
+                    .state_transition(&block.message.block, true)
+                    .map_err(|err| {
+                        StoreError::InvalidEra(format!("Invalid block at slot {slot}: {err}"))
+                    })?;
+
+                let block_root = block.message.block.tree_hash_root();
+                lean_db.block_provider().insert(block_root, block)?;
+                (*last_root, *state_stored) = (block_root, false);
+                summary.blocks += 1;
+            }
+            COMPRESSED_STATE_RECORD => {
+                let state = LeanState::from_ssz_bytes(&decoder.decompress_vec(&data)?)?;
+                let state_root = state.tree_hash_root();
+                if let Some(block) = first_block.take() {
+                    if block.message.block.state_root != state_root {
+                        return Err(StoreError::InvalidEra(
+                            "First state doesn't match the state root of its block".to_string(),
+                        ));
+                    }
+                    let block_root = block.message.block.tree_hash_root();
+                    lean_db.block_provider().insert(block_root, block)?;
+                    lean_db.state_provider().insert(block_root, state.clone())?;
+                    summary.blocks += 1;
+                    summary.states += 1;
+                    last_block = Some((block_root, state, true));
+                    continue;
+                }
+                let Some((block_root, last_state, state_stored)) = last_block.as_mut() else {
+                    return Err(StoreError::InvalidEra(
+                        "State record without a preceding block".to_string(),
+                    ));
+                };
+                // The replayed state already matches the state root of the block.
+                if last_state.tree_hash_root() != state_root {
+                    return Err(StoreError::InvalidEra(format!(
+                        "State doesn't match the block at slot {}",
+                        last_state.slot
+                    )));
+                }
+                if !*state_stored {
+                    lean_db.state_provider().insert(*block_root, state)?;
+                    *state_stored = true;
+                    summary.states += 1;
+                }
+            }
+            // Unknown record types are skipped, as in e2store.
+            _ => {}
+        }
+    }
+
+    if first_block.is_some() {
+        return Err(StoreError::InvalidEra(
+            "First block isn't followed by its state".to_string(),
+        ));
+    }
+    let Some((block_root, state, state_stored)) = last_block else {
+        return Ok(summary);
+    };
+    let checkpoint = Checkpoint {
+        root: block_root,
+        slot: state.slot,
+    };
+    if !state_stored {
+        lean_db.state_provider().insert(block_root, state)?;
+        summary.states += 1;
+    }
+    update_checkpoints(lean_db, checkpoint)?;
+
+    Ok(summary)
+}
+
+/// Moves the finalized and justified checkpoints, and the head, to the finalized block
+/// `checkpoint`, unless they already are at a later block.
+fn update_checkpoints(lean_db: &LeanDB, checkpoint: Checkpoint) -> Result<(), StoreError> {
+    let slot_of = |checkpoint: Result<Checkpoint, StoreError>| match checkpoint {
+        Ok(checkpoint) => Ok(Some(checkpoint.slot)),
+        Err(StoreError::FieldNotInitilized) => Ok(None),
+        Err(err) => Err(err),
+    };
+    if slot_of(lean_db.latest_finalized_provider().get())?
+        .is_some_and(|finalized_slot| finalized_slot >= checkpoint.slot)
+    {
+        return Ok(());
+    }
+
+    lean_db.latest_finalized_provider().insert(checkpoint)?;
+    if slot_of(lean_db.latest_justified_provider().get())?
+        .is_none_or(|justified_slot| justified_slot < checkpoint.slot)
+    {
+        lean_db.latest_justified_provider().insert(checkpoint)?;
+    }
+    let head_slot = match lean_db.head_provider().get() {
+        Ok(head) => lean_db
+            .block_provider()
+            .get(head)?
+            .map(|block| block.message.block.slot),
+        Err(StoreError::FieldNotInitilized) => None,
+        Err(err) => return Err(err),
+    };
+    if head_slot.is_none_or(|head_slot| head_slot < checkpoint.slot) {
+        lean_db.head_provider().insert(checkpoint.root)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use ream_consensus_lean::{
+        attestation::{Attestation, AttestationData},
+        block::{Block, BlockBody, BlockWithAttestation},
+        utils::generate_default_validators,
+    };
+    use ream_network_spec::networks::initialize_test_lean_network_spec;
+    use ream_post_quantum_crypto::leansig::signature::Signature;
+    use ssz_types::VariableList;
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::db::ReamDB;
+
+    /// Builds a genesis block and `slots` empty blocks on it, with the post state of each.
+    fn build_chain(slots: u64) -> Vec<(SignedBlockWithAttestation, LeanState)> {
+        initialize_test_lean_network_spec();
+        let mut state = LeanState::generate_genesis(0, Some(generate_default_validators(4)));
+        let mut parent_root = B256::ZERO;
+        let mut chain = vec![];
+        for slot in 0..=slots {
+            let mut block = Block {
+                slot,
+                proposer_index: slot % 4,
+                parent_root,
+                state_root: B256::ZERO,
+                body: BlockBody::default(),
+            };
+            if slot > 0 {
+                state.process_slots(slot).unwrap();
+                state.process_block(&block).unwrap();
+            }
+            block.state_root = state.tree_hash_root();
+            parent_root = block.tree_hash_root();
+            let signed_block = SignedBlockWithAttestation {
+                message: BlockWithAttestation {
+                    proposer_attestation: Attestation {
+                        validator_id: block.proposer_index,
+                        data: AttestationData {
+                            slot,
+                            head: Checkpoint::default(),
+                            target: Checkpoint::default(),
+                            source: Checkpoint::default(),
+                        },
+                    },
+                    block,
+                },
+                signature: VariableList::try_from(vec![Signature::blank()]).unwrap(),
+            };
+            chain.push((signed_block, state.clone()));
+        }
+        chain
+    }
+
+    fn archive(records: &[([u8; 2], Vec<u8>)]) -> Vec<u8> {
+        let mut buffer = vec![];
+        write_record(&mut buffer, VERSION_RECORD, &[]).unwrap();
+        for (record_type, data) in records {
+            write_record(&mut buffer, *record_type, data).unwrap();
+        }
+        buffer
+    }
+
+    fn compressed(bytes: Vec<u8>) -> Vec<u8> {
+        Encoder::new().compress_vec(&bytes).unwrap()
+    }
+
+    #[test]
+    fn test_record_round_trip() {
+        let mut buffer = vec![];
+        write_record(&mut buffer, VERSION_RECORD, &[]).unwrap();
+        write_record(&mut buffer, COMPRESSED_BLOCK_RECORD, &[1, 2, 3]).unwrap();
+        assert_eq!(buffer.len(), 2 * HEADER_LENGTH + 3);
+
+        let mut reader = Cursor::new(buffer);
+        assert_eq!(
+            read_record(&mut reader).unwrap(),
+            Some((VERSION_RECORD, vec![]))
+        );
+        assert_eq!(
+            read_record(&mut reader).unwrap(),
+            Some((COMPRESSED_BLOCK_RECORD, vec![1, 2, 3]))
+        );
+        assert_eq!(read_record(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn test_record_length_is_bounded() {
+        let mut header = vec![0u8; HEADER_LENGTH];
+        header[..2].copy_from_slice(&COMPRESSED_BLOCK_RECORD);
+        header[2..6].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(matches!(
+            read_record(&mut Cursor::new(header.clone())),
+            Err(StoreError::InvalidEra(_))
+        ));
+
+        header[2..6].copy_from_slice(&16u32.to_le_bytes());
+        header.extend([1, 2, 3]);
+        assert!(matches!(
+            read_record(&mut Cursor::new(header)),
+            Err(StoreError::InvalidEra(_))
+        ));
+    }
+
+    #[test]
+    fn test_export_import_round_trip() {
+        let chain = build_chain(4);
+        let block_root = |slot: usize| chain[slot].0.message.block.tree_hash_root();
+        let head = Checkpoint {
+            root: block_root(4),
+            slot: 4,
+        };
+
+        let source_dir = TempDir::new().unwrap();
+        {
+            let lean_db = ReamDB::new(source_dir.path().to_path_buf())
+                .unwrap()
+                .init_lean_db()
+                .unwrap();
+            for (block, _) in &chain {
+                lean_db
+                    .block_provider()
+                    .insert(block.message.block.tree_hash_root(), block.clone())
+                    .unwrap();
+            }
+            // The states of the blocks between them are left out, as after pruning.
+            for slot in [0, 4] {
+                lean_db
+                    .state_provider()
+                    .insert(block_root(slot), chain[slot].1.clone())
+                    .unwrap();
+            }
+            lean_db.head_provider().insert(head.root).unwrap();
+            lean_db.latest_finalized_provider().insert(head).unwrap();
+        }
+
+        let mut buffer = vec![];
+        let read_only_db = ReamDB::open_read_only(source_dir.path().to_path_buf()).unwrap();
+        assert_eq!(
+            export_era(&read_only_db, 0, 4, 2, &mut buffer).unwrap(),
+            EraSummary {
+                blocks: 5,
+                states: 2
+            }
+        );
+
+        let lean_db = ReamDB::in_memory().unwrap().init_lean_db().unwrap();
+        assert_eq!(
+            import_era(&lean_db, &mut Cursor::new(buffer), false).unwrap(),
+            EraSummary {
+                blocks: 5,
+                states: 2
+            }
+        );
+        for (slot, (block, _)) in chain.iter().enumerate() {
+            assert_eq!(
+                lean_db.block_provider().get(block_root(slot)).unwrap(),
+                Some(block.clone())
+            );
+        }
+        assert!(
+            lean_db
+                .state_provider()
+                .get(block_root(2))
+                .unwrap()
+                .is_none()
+        );
+        assert_eq!(
+            lean_db.state_provider().get(head.root).unwrap(),
+            Some(chain[4].1.clone())
+        );
+        assert_eq!(lean_db.head_provider().get().unwrap(), head.root);
+        assert_eq!(lean_db.latest_finalized_provider().get().unwrap(), head);
+        assert_eq!(lean_db.latest_justified_provider().get().unwrap(), head);
+    }
+
+    #[test]
+    fn test_import_rejects_invalid_blocks() {
+        let chain = build_chain(2);
+        let block = |slot: usize| {
+            (
+                COMPRESSED_BLOCK_RECORD,
+                compressed(chain[slot].0.as_ssz_bytes()),
+            )
+        };
+        let state = |slot: usize| {
+            (
+                COMPRESSED_STATE_RECORD,
+                compressed(chain[slot].1.as_ssz_bytes()),
+            )
+        };
+        let import = |records: &[([u8; 2], Vec<u8>)]| {
+            let lean_db = ReamDB::in_memory().unwrap().init_lean_db().unwrap();
+            import_era(&lean_db, &mut Cursor::new(archive(records)), false)
+        };
+
+        assert!(import(&[block(0), state(0), block(1), block(2)]).is_ok());
+        // The first block needs its state.
+        assert!(matches!(
+            import(&[block(0), block(1)]),
+            Err(StoreError::InvalidEra(_))
+        ));
+        assert!(matches!(
+            import(&[block(0), state(1)]),
+            Err(StoreError::InvalidEra(_))
+        ));
+        // Later blocks must build on the block before them and match their state root.
+        assert!(matches!(
+            import(&[block(0), state(0), block(2)]),
+            Err(StoreError::InvalidEra(_))
+        ));
+        let mut tampered = chain[1].0.clone();
+        tampered.message.block.state_root = B256::repeat_byte(1);
+        assert!(matches!(
+            import(&[
+                block(0),
+                state(0),
+                (COMPRESSED_BLOCK_RECORD, compressed(tampered.as_ssz_bytes()))
+            ]),
+            Err(StoreError::InvalidEra(_))
+        ));
+    }
+
+    #[test]
+    fn test_import_rejects_missing_version() {
+        let mut buffer = vec![];
+        write_record(&mut buffer, COMPRESSED_BLOCK_RECORD, &[1, 2, 3]).unwrap();
+
+        let tmp_dir = TempDir::new().unwrap();
+        let lean_db = ReamDB::new(tmp_dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        assert!(matches!(
+            import_era(&lean_db, &mut Cursor::new(buffer), true),
+            Err(StoreError::InvalidEra(_))
+        ));
+    }
+}
@@ -16,6 +16,36 @@ pub enum StoreError {
 
     #[error("SnappyError not found {0}")]
     SnappyError(#[from] snap::Error),
+
+    #[error("Invalid era archive: {0}")]
+    InvalidEra(String),
+
+    #[error(
+        "Database schema version {found} is newer than the latest supported version {supported}, upgrade ream to open it"
+    )]
+    UnsupportedSchemaVersion { found: u64, supported: u64 },
+
+    #[error("No migration registered from database schema version {0}")]
+    MissingMigration(u64),
+
+    #[error(
+        "Database schema version {found:?} doesn't match {expected}, open it writable once to migrate it before reading it read-only"
+    )]
+    ReadOnlySchemaMismatch { found: Option<u64>, expected: u64 },
+
+    #[error("Blocking storage task failed: {0}")]
+    BlockingTask(#[from] tokio::task::JoinError),
+
+    #[error("Snapshot destination {0:?} already exists")]
+    SnapshotDestinationExists(std::path::PathBuf),
+
+    #[error(
+        "The shared database {path:?} holds the {chain:?} chain, which a separate database would start over empty. Drop --separate-dbs to keep using it, or purge the chain from it with --purge-db and --purge-scope first"
+    )]
+    ChainInSharedDatabase {
+        chain: crate::db::Chain,
+        path: std::path::PathBuf,
+    },
 }
 
 impl From<redb::Error> for StoreError {
@@ -60,6 +90,12 @@ impl From<redb::DatabaseError> for StoreError {
     }
 }
 
+impl From<redb::CompactionError> for StoreError {
+    fn from(err: redb::CompactionError) -> Self {
+        StoreError::Redb(Box::new(err.into()))
+    }
+}
+
 impl From<ssz::DecodeError> for StoreError {
     fn from(value: ssz::DecodeError) -> Self {
         StoreError::DecodeError(format!("{value:?}"))
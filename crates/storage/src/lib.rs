@@ -1,5 +1,8 @@
 pub mod cache;
 pub mod db;
 pub mod dir;
+pub mod durability;
+pub mod era;
 pub mod errors;
+pub mod migrations;
 pub mod tables;
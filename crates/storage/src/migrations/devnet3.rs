@@ -0,0 +1,308 @@
+//! The layouts of lean states and blocks written at schema version 4, when validator exits and
+//! key rotations were stored in the validators and blocks always had validator operations.
+//!
+//! The validators hash differently in the current layout, so the roots of upgraded states no
+//! longer match the state roots of the blocks which led to them.
+
+use alloy_primitives::B256;
+use ream_consensus_lean::{
+    attestation::Attestation,
+    block::{Block, BlockBody, BlockHeader, BlockWithAttestation, SignedBlockWithAttestation},
+    checkpoint::Checkpoint,
+    config::Config,
+    state::LeanState,
+    validator::{FAR_FUTURE_SLOT, SignedKeyRotation, Validator},
+};
+use ream_post_quantum_crypto::leansig::{public_key::PublicKey, signature::Signature};
+use ssz::Decode;
+use ssz_derive::{Decode, Encode};
+use ssz_types::{
+    BitList, VariableList,
+    typenum::{U16, U4096, U262144, U1073741824},
+};
+
+use crate::errors::StoreError;
+
+#[derive(Debug, Encode, Decode)]
+struct LegacyValidator {
+    public_key: PublicKey,
+    index: u64,
+    exit_slot: u64,
+    next_public_key: PublicKey,
+    next_public_key_slot: u64,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct LegacyLeanState {
+    config: Config,
+    slot: u64,
+    latest_block_header: BlockHeader,
+    latest_justified: Checkpoint,
+    latest_finalized: Checkpoint,
+    historical_block_hashes: VariableList<B256, U262144>,
+    justified_slots: BitList<U262144>,
+    validators: VariableList<LegacyValidator, U4096>,
+    justifications_roots: VariableList<B256, U262144>,
+    justifications_validators: BitList<U1073741824>,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct LegacyValidatorRegistration {
+    public_key: PublicKey,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct LegacyValidatorExit {
+    validator_index: u64,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct LegacyBlockBody {
+    attestations: VariableList<Attestation, U4096>,
+    validator_registrations: VariableList<LegacyValidatorRegistration, U16>,
+    validator_exits: VariableList<LegacyValidatorExit, U16>,
+    key_rotations: VariableList<SignedKeyRotation, U16>,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct LegacyBlock {
+    slot: u64,
+    proposer_index: u64,
+    parent_root: B256,
+    state_root: B256,
+    body: LegacyBlockBody,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct LegacyBlockWithAttestation {
+    block: LegacyBlock,
+    proposer_attestation: Attestation,
+}
+
+#[derive(Debug, Encode, Decode)]
+struct LegacySignedBlockWithAttestation {
+    message: LegacyBlockWithAttestation,
+    signature: VariableList<Signature, U4096>,
+}
+
+/// The error for chains which used validator operations before they were gated behind the
+/// Devnet3 fork, which can't be upgraded as their registrations and exits weren't signed.
+fn unsupported_operations() -> StoreError {
+    StoreError::DecodeError(
+        "The database has validator registrations, exits or key rotations of an unsupported \
+         layout, resync with --purge-db"
+            .to_string(),
+    )
+}
+
+pub fn upgrade_state(bytes: &[u8]) -> Result<LeanState, StoreError> {
+    let state = LegacyLeanState::from_ssz_bytes(bytes)?;
+    if state.validators.iter().any(|validator| {
+        validator.exit_slot != FAR_FUTURE_SLOT || validator.next_public_key_slot != FAR_FUTURE_SLOT
+    }) {
+        return Err(unsupported_operations());
+    }
+
+    let validators = state
+        .validators
+        .iter()
+        .map(|validator| Validator {
+            public_key: validator.public_key,
+            index: validator.index,
+        })
+        .collect::<Vec<_>>();
+    Ok(LeanState {
+        config: state.config,
+        slot: state.slot,
+        latest_block_header: state.latest_block_header,
+        latest_justified: state.latest_justified,
+        latest_finalized: state.latest_finalized,
+        historical_block_hashes: state.historical_block_hashes,
+        justified_slots: state.justified_slots,
+        validators: VariableList::try_from(validators)
+            .map_err(|err| StoreError::DecodeError(format!("{err:?}")))?,
+        justifications_roots: state.justifications_roots,
+        justifications_validators: state.justifications_validators,
+        validator_statuses: None,
+    })
+}
+
+pub fn upgrade_block(bytes: &[u8]) -> Result<SignedBlockWithAttestation, StoreError> {
+    let signed_block = LegacySignedBlockWithAttestation::from_ssz_bytes(bytes)?;
+    let LegacyBlockWithAttestation {
+        block,
+        proposer_attestation,
+    } = signed_block.message;
+    if !block.body.validator_registrations.is_empty()
+        || !block.body.validator_exits.is_empty()
+        || !block.body.key_rotations.is_empty()
+    {
+        return Err(unsupported_operations());
+    }
+
+    Ok(SignedBlockWithAttestation {
+        message: BlockWithAttestation {
+            block: Block {
+                slot: block.slot,
+                proposer_index: block.proposer_index,
+                parent_root: block.parent_root,
+                state_root: block.state_root,
+                body: BlockBody::new(block.body.attestations),
+            },
+            proposer_attestation,
+        },
+        signature: signed_block.signature,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ream_consensus_lean::{
+        attestation::{Attestation, AttestationData},
+        block::{BlockHeader, SignedBlockWithAttestation},
+        checkpoint::Checkpoint,
+        config::Config,
+        state::LeanState,
+        validator::FAR_FUTURE_SLOT,
+    };
+    use ream_post_quantum_crypto::leansig::public_key::PublicKey;
+    use redb::{Database, TableDefinition};
+    use ssz::Encode;
+    use ssz_types::{BitList, VariableList};
+    use tempdir::TempDir;
+
+    use super::{
+        LegacyBlock, LegacyBlockBody, LegacyBlockWithAttestation, LegacyLeanState,
+        LegacySignedBlockWithAttestation, LegacyValidator, LegacyValidatorExit,
+    };
+    use crate::{
+        db::{REDB_FILE, ReamDB},
+        errors::StoreError,
+        migrations::{LegacySSZBytes, write_schema_version},
+        tables::{ssz_encoder::SSZEncoding, table::REDBTable},
+    };
+
+    fn legacy_state() -> LegacyLeanState {
+        LegacyLeanState {
+            config: Config { genesis_time: 0 },
+            slot: 3,
+            latest_block_header: BlockHeader {
+                slot: 3,
+                proposer_index: 0,
+                parent_root: B256::repeat_byte(2),
+                state_root: B256::ZERO,
+                body_root: B256::repeat_byte(4),
+            },
+            latest_justified: Checkpoint::default(),
+            latest_finalized: Checkpoint::default(),
+            historical_block_hashes: VariableList::try_from(vec![B256::repeat_byte(2)]).unwrap(),
+            justified_slots: BitList::with_capacity(1).unwrap(),
+            validators: VariableList::try_from(vec![LegacyValidator {
+                public_key: PublicKey::default(),
+                index: 0,
+                exit_slot: FAR_FUTURE_SLOT,
+                next_public_key: PublicKey::default(),
+                next_public_key_slot: FAR_FUTURE_SLOT,
+            }])
+            .unwrap(),
+            justifications_roots: VariableList::empty(),
+            justifications_validators: BitList::with_capacity(0).unwrap(),
+        }
+    }
+
+    fn legacy_block(validator_exits: Vec<LegacyValidatorExit>) -> LegacySignedBlockWithAttestation {
+        LegacySignedBlockWithAttestation {
+            message: LegacyBlockWithAttestation {
+                block: LegacyBlock {
+                    slot: 3,
+                    proposer_index: 0,
+                    parent_root: B256::repeat_byte(2),
+                    state_root: B256::repeat_byte(3),
+                    body: LegacyBlockBody {
+                        attestations: VariableList::empty(),
+                        validator_registrations: VariableList::empty(),
+                        validator_exits: VariableList::try_from(validator_exits).unwrap(),
+                        key_rotations: VariableList::empty(),
+                    },
+                },
+                proposer_attestation: Attestation {
+                    validator_id: 0,
+                    data: AttestationData {
+                        slot: 3,
+                        head: Checkpoint::default(),
+                        target: Checkpoint::default(),
+                        source: Checkpoint::default(),
+                    },
+                },
+            },
+            signature: VariableList::empty(),
+        }
+    }
+
+    /// Creates a database at schema version 4 holding `state` and `block`.
+    fn fixture(dir: &TempDir, state: &LegacyLeanState, block: &LegacySignedBlockWithAttestation) {
+        let db = Database::create(dir.path().join(REDB_FILE)).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_schema_version(&write_txn, 4).unwrap();
+        write_txn
+            .open_table(TableDefinition::<
+                SSZEncoding<B256>,
+                LegacySSZBytes<LeanState>,
+            >::new("lean_state"))
+            .unwrap()
+            .insert(B256::repeat_byte(1), state.as_ssz_bytes())
+            .unwrap();
+        write_txn
+            .open_table(TableDefinition::<
+                SSZEncoding<B256>,
+                LegacySSZBytes<SignedBlockWithAttestation>,
+            >::new("lean_block"))
+            .unwrap()
+            .insert(B256::repeat_byte(1), block.as_ssz_bytes())
+            .unwrap();
+        write_txn.commit().unwrap();
+    }
+
+    #[test]
+    fn test_legacy_states_and_blocks_are_upgraded() {
+        let dir = TempDir::new("devnet3_upgrade").unwrap();
+        fixture(&dir, &legacy_state(), &legacy_block(vec![]));
+
+        let lean_db = ReamDB::new(dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        let state = lean_db
+            .state_provider()
+            .get(B256::repeat_byte(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(state.slot, 3);
+        assert_eq!(state.validators.len(), 1);
+        assert_eq!(state.validator_statuses, None);
+
+        let block = lean_db
+            .block_provider()
+            .get(B256::repeat_byte(1))
+            .unwrap()
+            .unwrap();
+        assert_eq!(block.message.block.state_root, B256::repeat_byte(3));
+        assert!(!block.message.block.body.has_validator_operations());
+    }
+
+    #[test]
+    fn test_legacy_validator_operations_are_refused() {
+        let dir = TempDir::new("devnet3_operations").unwrap();
+        fixture(
+            &dir,
+            &legacy_state(),
+            &legacy_block(vec![LegacyValidatorExit { validator_index: 0 }]),
+        );
+
+        assert!(matches!(
+            ReamDB::new(dir.path().to_path_buf()),
+            Err(StoreError::DecodeError(_))
+        ));
+    }
+}
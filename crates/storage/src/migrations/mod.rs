@@ -0,0 +1,445 @@
+//! Versioning of the on-disk layout of `ream.redb`.
+//!
+//! The schema version is stored in [SchemaVersionField]. Opening a database runs every
+//! registered migration from the stored version up to [CURRENT_SCHEMA_VERSION], one step per
+//! write transaction, and refuses to open databases written by a newer version of ream.
+
+mod devnet3;
+
+use std::{fmt::Debug, marker::PhantomData};
+
+use redb::{
+    Database, Durability, ReadableDatabase, ReadableTable, TableDefinition, TableHandle, TypeName,
+    Value, WriteTransaction,
+};
+use ssz::{Decode, Encode};
+use tracing::info;
+
+use crate::{
+    errors::StoreError,
+    tables::{
+        field::REDBField,
+        lean::{
+            blob_sidecar::LeanBlobSidecarTable,
+            lean_block::LeanBlockTable,
+            lean_state::LeanStateTable,
+            validator_performance::{
+                LeanValidatorPerformanceTable, SlotPerformance, ValidatorPerformanceRecord,
+            },
+        },
+        schema_version::SchemaVersionField,
+        ssz_encoder::{CompressedSSZEncoding, SSZEncoding, UNCOMPRESSED_VERSION, decompress},
+        table::REDBTable,
+    },
+};
+
+/// Version of the layout written by this build.
+pub const CURRENT_SCHEMA_VERSION: u64 = 6;
+
+/// Version assumed for databases created before schema versioning was introduced.
+pub const LEGACY_SCHEMA_VERSION: u64 = 0;
+
+/// The validator performance table before schema version 6, with a record of every slot per
+/// validator.
+const LEGACY_VALIDATOR_PERFORMANCE_TABLE: TableDefinition<
+    '_,
+    u64,
+    SSZEncoding<ValidatorPerformanceRecord>,
+> = TableDefinition::new("lean_validator_performance");
+
+/// Upgrades a database from `from` to `from + 1`.
+pub struct Migration {
+    pub from: u64,
+    pub description: &'static str,
+    pub migrate: fn(&WriteTransaction) -> Result<(), StoreError>,
+}
+
+/// Every migration, ordered by the version it upgrades from.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "record the schema version of databases created before versioning",
+        // The layout didn't change, the version field is written by `run_migrations`.
+        migrate: |_| Ok(()),
+    },
+    Migration {
+        from: 1,
+        description: "add the lean validator performance table",
+        migrate: |write_txn| {
+            write_txn.open_table(LEGACY_VALIDATOR_PERFORMANCE_TABLE)?;
+            Ok(())
+        },
+    },
+    Migration {
+        from: 2,
+        description: "add the lean blob sidecar table",
+        migrate: |write_txn| {
+            write_txn.open_table(LeanBlobSidecarTable::TABLE_DEFINITION)?;
+            Ok(())
+        },
+    },
+    Migration {
+        from: 3,
+        description: "compress the lean states and blocks",
+        migrate: |write_txn| {
+            compress_table(write_txn, LeanStateTable::TABLE_DEFINITION)?;
+            compress_table(write_txn, LeanBlockTable::TABLE_DEFINITION)
+        },
+    },
+    Migration {
+        from: 4,
+        description: "move the lean validator exits and key rotations out of the validators",
+        migrate: |write_txn| {
+            rewrite_table(
+                write_txn,
+                LeanStateTable::TABLE_DEFINITION,
+                devnet3::upgrade_state,
+            )?;
+            rewrite_table(
+                write_txn,
+                LeanBlockTable::TABLE_DEFINITION,
+                devnet3::upgrade_block,
+            )
+        },
+    },
+    Migration {
+        from: 5,
+        description: "store the lean validator performance per slot",
+        migrate: split_validator_performance,
+    },
+];
+
+/// Rewrites the validator performance records, one per validator, as one entry per validator and
+/// slot, so tracking a slot no longer rewrites every record.
+fn split_validator_performance(write_txn: &WriteTransaction) -> Result<(), StoreError> {
+    let staging_definition = TableDefinition::<(u64, u64), SSZEncoding<SlotPerformance>>::new(
+        "lean_validator_performance_upgraded",
+    );
+    {
+        let legacy_table = write_txn.open_table(LEGACY_VALIDATOR_PERFORMANCE_TABLE)?;
+        let mut staging_table = write_txn.open_table(staging_definition)?;
+        for entry in legacy_table.iter()? {
+            let (validator_index, record) = entry?;
+            let validator_index = validator_index.value();
+            for performance in record.value().slots {
+                staging_table.insert((validator_index, performance.slot), performance)?;
+            }
+        }
+    }
+    write_txn.delete_table(LEGACY_VALIDATOR_PERFORMANCE_TABLE)?;
+    write_txn.rename_table(
+        staging_definition,
+        LeanValidatorPerformanceTable::TABLE_DEFINITION,
+    )?;
+    Ok(())
+}
+
+/// Rewrites the table of `definition`, written before its values were compressed, with
+/// [CompressedSSZEncoding] values. The values are copied to a staging table which then replaces
+/// the table, so they don't all have to fit in memory at once.
+fn compress_table<K, V>(
+    write_txn: &WriteTransaction,
+    definition: TableDefinition<'_, SSZEncoding<K>, CompressedSSZEncoding<V>>,
+) -> Result<(), StoreError>
+where
+    K: Debug + Encode + Decode + Ord + 'static,
+    V: Debug + Encode + Decode + 'static,
+{
+    let name = definition.name();
+    let legacy_definition = TableDefinition::<SSZEncoding<K>, SSZEncoding<V>>::new(name);
+    let staging_name = format!("{name}_compressed");
+    let staging_definition =
+        TableDefinition::<SSZEncoding<K>, CompressedSSZEncoding<V>>::new(&staging_name);
+
+    {
+        let legacy_table = write_txn.open_table(legacy_definition)?;
+        let mut staging_table = write_txn.open_table(staging_definition)?;
+        for entry in legacy_table.iter()? {
+            let (key, value) = entry?;
+            staging_table.insert(key.value(), value.value())?;
+        }
+    }
+    write_txn.delete_table(legacy_definition)?;
+    write_txn.rename_table(staging_definition, definition)?;
+    Ok(())
+}
+
+/// Reads the [CompressedSSZEncoding] values of a table as SSZ bytes, for values written at a
+/// layout of `V` which no longer decodes.
+#[derive(Debug)]
+struct LegacySSZBytes<V>(PhantomData<V>);
+
+impl<V> Value for LegacySSZBytes<V>
+where
+    V: Debug + Encode + Decode,
+{
+    type SelfType<'a>
+        = Vec<u8>
+    where
+        Self: 'a;
+
+    type AsBytes<'a>
+        = Vec<u8>
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Self::SelfType<'a>
+    where
+        Self: 'a,
+    {
+        decompress(data).into_owned()
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Self::AsBytes<'a>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        [&[UNCOMPRESSED_VERSION][..], value.as_slice()].concat()
+    }
+
+    fn type_name() -> TypeName {
+        CompressedSSZEncoding::<V>::type_name()
+    }
+}
+
+/// Rewrites the table of `definition`, whose values were written at a previous layout, with the
+/// values `upgrade` decodes from their SSZ bytes. Like [compress_table], it goes through a staging
+/// table.
+fn rewrite_table<K, V>(
+    write_txn: &WriteTransaction,
+    definition: TableDefinition<'_, SSZEncoding<K>, CompressedSSZEncoding<V>>,
+    upgrade: fn(&[u8]) -> Result<V, StoreError>,
+) -> Result<(), StoreError>
+where
+    K: Debug + Encode + Decode + Ord + 'static,
+    V: Debug + Encode + Decode + 'static,
+{
+    let name = definition.name();
+    let legacy_definition = TableDefinition::<SSZEncoding<K>, LegacySSZBytes<V>>::new(name);
+    let staging_name = format!("{name}_upgraded");
+    let staging_definition =
+        TableDefinition::<SSZEncoding<K>, CompressedSSZEncoding<V>>::new(&staging_name);
+
+    {
+        let legacy_table = write_txn.open_table(legacy_definition)?;
+        let mut staging_table = write_txn.open_table(staging_definition)?;
+        for entry in legacy_table.iter()? {
+            let (key, value) = entry?;
+            staging_table.insert(key.value(), upgrade(&value.value())?)?;
+        }
+    }
+    write_txn.delete_table(legacy_definition)?;
+    write_txn.rename_table(staging_definition, definition)?;
+    Ok(())
+}
+
+/// Returns the schema version of `db`, or `None` for a freshly created database.
+pub fn schema_version(db: &impl ReadableDatabase) -> Result<Option<u64>, StoreError> {
+    let read_txn = db.begin_read()?;
+    if read_txn.list_tables()?.next().is_none() {
+        return Ok(None);
+    }
+
+    match read_txn.open_table(SchemaVersionField::FIELD_DEFINITION) {
+        Ok(table) => Ok(Some(
+            table
+                .get(SchemaVersionField::KEY)?
+                .map(|version| version.value())
+                .unwrap_or(LEGACY_SCHEMA_VERSION),
+        )),
+        Err(redb::TableError::TableDoesNotExist(_)) => Ok(Some(LEGACY_SCHEMA_VERSION)),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn write_schema_version(write_txn: &WriteTransaction, version: u64) -> Result<(), StoreError> {
+    let mut table = write_txn.open_table(SchemaVersionField::FIELD_DEFINITION)?;
+    table.insert(SchemaVersionField::KEY, version)?;
+    Ok(())
+}
+
+/// Brings `db` up to [CURRENT_SCHEMA_VERSION] using `migrations`.
+pub fn run_migrations(db: &Database, migrations: &[Migration]) -> Result<(), StoreError> {
+    let Some(mut version) = schema_version(db)? else {
+        let write_txn = db.begin_write()?;
+        write_schema_version(&write_txn, CURRENT_SCHEMA_VERSION)?;
+        write_txn.commit()?;
+        return Ok(());
+    };
+
+    if version > CURRENT_SCHEMA_VERSION {
+        return Err(StoreError::UnsupportedSchemaVersion {
+            found: version,
+            supported: CURRENT_SCHEMA_VERSION,
+        });
+    }
+
+    while version < CURRENT_SCHEMA_VERSION {
+        let migration = migrations
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or(StoreError::MissingMigration(version))?;
+        info!(
+            "Migrating database schema from version {version} to {}: {}",
+            version + 1,
+            migration.description
+        );
+
+        let mut write_txn = db.begin_write()?;
+        write_txn.set_durability(Durability::Immediate)?;
+        (migration.migrate)(&write_txn)?;
+        write_schema_version(&write_txn, version + 1)?;
+        write_txn.commit()?;
+        version += 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ream_consensus_lean::state::LeanState;
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::db::{REDB_FILE, ReamDB};
+
+    /// A table written by databases at [LEGACY_SCHEMA_VERSION].
+    const LEGACY_TABLE: TableDefinition<'_, &str, u64> = TableDefinition::new("lean_time");
+
+    fn stored_version(dir: &TempDir) -> Option<u64> {
+        let db = Database::create(dir.path().join(REDB_FILE)).unwrap();
+        schema_version(&db).unwrap()
+    }
+
+    /// Creates a database laid out like one written before schema versioning.
+    fn legacy_fixture(dir: &TempDir) {
+        let db = Database::create(dir.path().join(REDB_FILE)).unwrap();
+        let write_txn = db.begin_write().unwrap();
+        write_txn
+            .open_table(LEGACY_TABLE)
+            .unwrap()
+            .insert("lean_time_key", 42)
+            .unwrap();
+        write_txn.commit().unwrap();
+    }
+
+    #[test]
+    fn test_fresh_database_uses_current_version() {
+        let dir = TempDir::new("fresh_schema").unwrap();
+        drop(ReamDB::new(dir.path().to_path_buf()).unwrap());
+        assert_eq!(stored_version(&dir), Some(CURRENT_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_legacy_database_is_migrated() {
+        let dir = TempDir::new("legacy_schema").unwrap();
+        legacy_fixture(&dir);
+
+        let ream_db = ReamDB::new(dir.path().to_path_buf()).unwrap();
+        let lean_db = ream_db.init_lean_db().unwrap();
+        assert_eq!(lean_db.time_provider().get().unwrap(), 42);
+
+        drop((ream_db, lean_db));
+        assert_eq!(stored_version(&dir), Some(CURRENT_SCHEMA_VERSION));
+    }
+
+    #[test]
+    fn test_uncompressed_states_are_migrated() {
+        let dir = TempDir::new("uncompressed_states").unwrap();
+        let state = LeanState::generate_genesis(0, None);
+        {
+            let db = Database::create(dir.path().join(REDB_FILE)).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            write_schema_version(&write_txn, 3).unwrap();
+            write_txn
+                .open_table(
+                    TableDefinition::<SSZEncoding<B256>, SSZEncoding<LeanState>>::new("lean_state"),
+                )
+                .unwrap()
+                .insert(B256::repeat_byte(1), &state)
+                .unwrap();
+            write_txn.commit().unwrap();
+        }
+
+        let lean_db = ReamDB::new(dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        assert_eq!(
+            lean_db.state_provider().get(B256::repeat_byte(1)).unwrap(),
+            Some(state)
+        );
+    }
+
+    #[test]
+    fn test_validator_performance_is_split_per_slot() {
+        let dir = TempDir::new("validator_performance_records").unwrap();
+        let slots = (3..6)
+            .map(|slot| SlotPerformance {
+                slot,
+                is_proposer: slot == 4,
+                proposed: false,
+                attestation_included: true,
+            })
+            .collect::<Vec<_>>();
+        {
+            let db = Database::create(dir.path().join(REDB_FILE)).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            write_schema_version(&write_txn, 5).unwrap();
+            write_txn
+                .open_table(LEGACY_VALIDATOR_PERFORMANCE_TABLE)
+                .unwrap()
+                .insert(
+                    2,
+                    ValidatorPerformanceRecord {
+                        slots: slots.clone(),
+                    },
+                )
+                .unwrap();
+            write_txn.commit().unwrap();
+        }
+
+        let lean_db = ReamDB::new(dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        let performance_provider = lean_db.validator_performance_provider();
+        assert_eq!(performance_provider.get_record(2).unwrap().slots, slots);
+        assert_eq!(performance_provider.get((2, 4)).unwrap(), Some(slots[1]));
+    }
+
+    #[test]
+    fn test_newer_database_is_rejected() {
+        let dir = TempDir::new("newer_schema").unwrap();
+        {
+            let db = Database::create(dir.path().join(REDB_FILE)).unwrap();
+            let write_txn = db.begin_write().unwrap();
+            write_schema_version(&write_txn, CURRENT_SCHEMA_VERSION + 1).unwrap();
+            write_txn.commit().unwrap();
+        }
+
+        assert!(matches!(
+            ReamDB::new(dir.path().to_path_buf()),
+            Err(StoreError::UnsupportedSchemaVersion { .. })
+        ));
+    }
+
+    #[test]
+    fn test_missing_migration_is_reported() {
+        let dir = TempDir::new("missing_migration").unwrap();
+        legacy_fixture(&dir);
+
+        let db = Database::create(dir.path().join(REDB_FILE)).unwrap();
+        assert!(matches!(
+            run_migrations(&db, &[]),
+            Err(StoreError::MissingMigration(LEGACY_SCHEMA_VERSION))
+        ));
+    }
+}
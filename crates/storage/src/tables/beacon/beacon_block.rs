@@ -2,11 +2,12 @@ use std::sync::Arc;
 
 use alloy_primitives::B256;
 use ream_consensus_beacon::electra::beacon_block::SignedBeaconBlock;
-use redb::{Database, Durability, TableDefinition};
+use redb::{Database, TableDefinition};
 use tree_hash::TreeHash;
 
 use super::parent_root_index::ParentRootIndexMultimapTable;
 use crate::{
+    durability::begin_write,
     errors::StoreError,
     tables::{
         beacon::{slot_index::BeaconSlotIndexTable, state_root_index::BeaconStateRootIndexTable},
@@ -58,8 +59,7 @@ impl REDBTable for BeaconBlockTable {
             db: self.db.clone(),
         };
         parent_root_index_table.insert(value.message.parent_root, block_root)?;
-        let mut write_txn = self.db.begin_write()?;
-        write_txn.set_durability(Durability::Immediate)?;
+        let write_txn = begin_write(&self.db)?;
         let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
         table.insert(key, value)?;
         drop(table);
@@ -68,7 +68,7 @@ impl REDBTable for BeaconBlockTable {
     }
 
     fn remove(&self, key: Self::Key) -> Result<Option<Self::Value>, StoreError> {
-        let write_txn = self.db.begin_write()?;
+        let write_txn = begin_write(&self.db)?;
         let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
         let value = table.remove(key)?.map(|v| v.value());
         if let Some(block) = &value {
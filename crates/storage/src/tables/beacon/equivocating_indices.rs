@@ -1,9 +1,9 @@
 use std::sync::Arc;
 
 use alloy_primitives::map::HashSet;
-use redb::{Database, Durability, ReadableDatabase, TableDefinition};
+use redb::{Database, ReadableDatabase, TableDefinition};
 
-use crate::{errors::StoreError, tables::field::CustomField};
+use crate::{durability::begin_write, errors::StoreError, tables::field::CustomField};
 
 /// Table definition for the Equivocating_Indices table
 ///
@@ -31,8 +31,7 @@ impl CustomField for EquivocatingIndicesField {
     }
 
     fn insert(&self, value: Self::Value) -> Result<(), StoreError> {
-        let mut write_txn = self.db.begin_write()?;
-        write_txn.set_durability(Durability::Immediate)?;
+        let write_txn = begin_write(&self.db)?;
         let mut table = write_txn.open_table(EQUIVOCATING_INDICES_FIELD)?;
         table.insert(
             EQUIVOCATING_INDICES_KEY,
@@ -44,7 +43,7 @@ impl CustomField for EquivocatingIndicesField {
     }
 
     fn remove(&self) -> Result<Option<Self::Value>, StoreError> {
-        let write_txn = self.db.begin_write()?;
+        let write_txn = begin_write(&self.db)?;
         let mut table = write_txn.open_table(EQUIVOCATING_INDICES_FIELD)?;
         let value = table
             .remove(EQUIVOCATING_INDICES_KEY)?
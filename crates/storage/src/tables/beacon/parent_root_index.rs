@@ -1,9 +1,10 @@
 use std::sync::Arc;
 
 use alloy_primitives::B256;
-use redb::{Database, Durability, MultimapTableDefinition, ReadableDatabase};
+use redb::{Database, MultimapTableDefinition, ReadableDatabase};
 
 use crate::{
+    durability::begin_write,
     errors::StoreError,
     tables::{multimap_table::MultimapTable, ssz_encoder::SSZEncoding},
 };
@@ -41,8 +42,7 @@ impl MultimapTable for ParentRootIndexMultimapTable {
     }
 
     fn insert(&self, key: Self::Key, value: Self::InsertValue) -> Result<(), StoreError> {
-        let mut write_txn = self.db.begin_write()?;
-        write_txn.set_durability(Durability::Immediate)?;
+        let write_txn = begin_write(&self.db)?;
         let mut table = write_txn.open_multimap_table(PARENT_ROOT_INDEX_MULTIMAP_TABLE)?;
         table.insert(key, value)?;
         drop(table);
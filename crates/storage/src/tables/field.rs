@@ -1,9 +1,10 @@
 use std::{fmt::Debug, sync::Arc};
 
-use redb::{Database, Durability, ReadableDatabase, TableDefinition};
+use ream_metrics::slot_report::record_db_write;
+use redb::{Database, ReadableDatabase, TableDefinition};
 use ssz::{Decode, Encode};
 
-use crate::errors::StoreError;
+use crate::{durability::begin_write, errors::StoreError};
 
 pub trait REDBField
 where
@@ -32,18 +33,18 @@ where
         &self,
         value: <Self::ValueFieldDefinition as redb::Value>::SelfType<'a>,
     ) -> Result<(), StoreError> {
-        let mut write_txn = self.database().begin_write()?;
-        write_txn.set_durability(Durability::Immediate)?;
+        let write_txn = begin_write(&self.database())?;
         {
             let mut table = write_txn.open_table(Self::FIELD_DEFINITION)?;
             table.insert(Self::KEY, value)?;
         }
         write_txn.commit()?;
+        record_db_write();
         Ok(())
     }
 
     fn remove(&self) -> Result<Option<Self::Value>, StoreError> {
-        let write_txn = self.database().begin_write()?;
+        let write_txn = begin_write(&self.database())?;
         let value = {
             let mut table = write_txn.open_table(Self::FIELD_DEFINITION)?;
             table
@@ -51,6 +52,7 @@ where
                 .map(|v| Self::Value::from(v.value()))
         };
         write_txn.commit()?;
+        record_db_write();
         Ok(value)
     }
 }
@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use alloy_primitives::B256;
+use ream_consensus_lean::blob_sidecar::{BlobIdentifier, BlobSidecar};
+use ream_metrics::slot_report::record_db_write;
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
+
+use crate::{
+    durability::begin_write,
+    errors::StoreError,
+    tables::{ssz_encoder::SSZEncoding, table::REDBTable},
+};
+
+pub struct LeanBlobSidecarTable {
+    pub db: Arc<Database>,
+}
+
+/// Table definition for the Lean Blob Sidecar table
+///
+/// Key: [BlobIdentifier]
+/// Value: [BlobSidecar]
+impl REDBTable for LeanBlobSidecarTable {
+    const TABLE_DEFINITION: TableDefinition<
+        '_,
+        SSZEncoding<BlobIdentifier>,
+        SSZEncoding<BlobSidecar>,
+    > = TableDefinition::new("lean_blob_sidecar");
+
+    type Key = BlobIdentifier;
+
+    type KeyTableDefinition = SSZEncoding<BlobIdentifier>;
+
+    type Value = BlobSidecar;
+
+    type ValueTableDefinition = SSZEncoding<BlobSidecar>;
+
+    fn database(&self) -> Arc<Database> {
+        self.db.clone()
+    }
+}
+
+fn block_range(block_root: B256) -> std::ops::RangeInclusive<BlobIdentifier> {
+    BlobIdentifier {
+        block_root,
+        index: 0,
+    }..=BlobIdentifier {
+        block_root,
+        index: u64::MAX,
+    }
+}
+
+impl LeanBlobSidecarTable {
+    /// Returns the stored sidecars of the block at `block_root`, ordered by index.
+    pub fn get_block_sidecars(&self, block_root: B256) -> Result<Vec<BlobSidecar>, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::TABLE_DEFINITION)?;
+        table
+            .range(block_range(block_root))?
+            .map(|entry| Ok(entry?.1.value()))
+            .collect()
+    }
+
+    /// Removes every sidecar of the block at `block_root`, returning how many were removed.
+    pub fn remove_block_sidecars(&self, block_root: B256) -> Result<u64, StoreError> {
+        let write_txn = begin_write(&self.db)?;
+        let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
+
+        let mut removed = 0;
+        table.retain_in(block_range(block_root), |_, _| {
+            removed += 1;
+            false
+        })?;
+        drop(table);
+        write_txn.commit()?;
+        record_db_write();
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ssz_types::VariableList;
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::db::ReamDB;
+
+    fn sidecar(block_root: B256, index: u64) -> BlobSidecar {
+        BlobSidecar {
+            block_root,
+            index,
+            blob_count: 2,
+            blob: VariableList::empty(),
+        }
+    }
+
+    #[test]
+    fn test_block_sidecars() {
+        let temp_dir = TempDir::new("lean_blob_sidecar").unwrap();
+        let lean_db = ReamDB::new(temp_dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        let table = lean_db.blob_sidecar_provider();
+
+        let block_root = B256::repeat_byte(1);
+        let other_root = B256::repeat_byte(2);
+        for sidecar in [
+            sidecar(block_root, 1),
+            sidecar(block_root, 0),
+            sidecar(other_root, 0),
+        ] {
+            table.insert(sidecar.identifier(), sidecar).unwrap();
+        }
+
+        assert_eq!(
+            table.get_block_sidecars(block_root).unwrap(),
+            vec![sidecar(block_root, 0), sidecar(block_root, 1)]
+        );
+        assert_eq!(table.remove_block_sidecars(block_root).unwrap(), 2);
+        assert!(table.get_block_sidecars(block_root).unwrap().is_empty());
+        assert_eq!(table.get_block_sidecars(other_root).unwrap().len(), 1);
+    }
+}
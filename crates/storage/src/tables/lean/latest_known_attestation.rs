@@ -1,9 +1,11 @@
 use std::{collections::HashMap, sync::Arc};
 
 use ream_consensus_lean::attestation::SignedAttestation;
-use redb::{Database, Durability, ReadableDatabase, ReadableTable, TableDefinition};
+use ream_metrics::slot_report::record_db_write;
+use redb::{Database, ReadableDatabase, ReadableTable, ReadableTableMetadata, TableDefinition};
 
 use crate::{
+    durability::begin_write,
     errors::StoreError,
     tables::{ssz_encoder::SSZEncoding, table::REDBTable},
 };
@@ -39,8 +41,7 @@ impl LatestKnownAttestationTable {
         &self,
         values: impl IntoIterator<Item = (u64, SignedAttestation)>,
     ) -> Result<(), StoreError> {
-        let mut write_txn = self.db.begin_write()?;
-        write_txn.set_durability(Durability::Immediate)?;
+        let write_txn = begin_write(&self.db)?;
 
         let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
 
@@ -50,6 +51,7 @@ impl LatestKnownAttestationTable {
 
         drop(table);
         write_txn.commit()?;
+        record_db_write();
 
         Ok(())
     }
@@ -67,4 +69,29 @@ impl LatestKnownAttestationTable {
             })
             .collect()
     }
+
+    pub fn count(&self) -> Result<u64, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::TABLE_DEFINITION)?;
+        Ok(table.len()?)
+    }
+
+    /// Removes the attestations for slots before `slot`, returning how many were removed.
+    pub fn remove_before_slot(&self, slot: u64) -> Result<u64, StoreError> {
+        let write_txn = begin_write(&self.db)?;
+        let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
+
+        let mut removed = 0;
+        table.retain(|_, attestation| {
+            let keep = attestation.message.data.slot >= slot;
+            if !keep {
+                removed += 1;
+            }
+            keep
+        })?;
+        drop(table);
+        write_txn.commit()?;
+        record_db_write();
+        Ok(removed)
+    }
 }
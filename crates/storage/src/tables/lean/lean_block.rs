@@ -2,13 +2,18 @@ use std::{collections::HashMap, sync::Arc};
 
 use alloy_primitives::B256;
 use ream_consensus_lean::block::SignedBlockWithAttestation;
-use redb::{Database, Durability, ReadableDatabase, ReadableTable, TableDefinition};
+use ream_metrics::slot_report::record_db_write;
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
 use tree_hash::TreeHash;
 
 use super::{slot_index::LeanSlotIndexTable, state_root_index::LeanStateRootIndexTable};
 use crate::{
+    durability::begin_write,
     errors::StoreError,
-    tables::{ssz_encoder::SSZEncoding, table::REDBTable},
+    tables::{
+        ssz_encoder::{CompressedSSZEncoding, SSZEncoding},
+        table::REDBTable,
+    },
 };
 
 pub struct LeanBlockTable {
@@ -23,7 +28,7 @@ impl REDBTable for LeanBlockTable {
     const TABLE_DEFINITION: TableDefinition<
         '_,
         SSZEncoding<B256>,
-        SSZEncoding<SignedBlockWithAttestation>,
+        CompressedSSZEncoding<SignedBlockWithAttestation>,
     > = TableDefinition::new("lean_block");
 
     type Key = B256;
@@ -32,51 +37,55 @@ impl REDBTable for LeanBlockTable {
 
     type Value = SignedBlockWithAttestation;
 
-    type ValueTableDefinition = SSZEncoding<SignedBlockWithAttestation>;
+    type ValueTableDefinition = CompressedSSZEncoding<SignedBlockWithAttestation>;
 
     fn database(&self) -> Arc<Database> {
         self.db.clone()
     }
 
+    /// Inserts the block and its slot and state root index entries in one transaction, so a
+    /// crash never leaves an index entry pointing at a block which isn't stored.
     fn insert(&self, key: Self::Key, value: Self::Value) -> Result<(), StoreError> {
-        // insert entry to slot_index table
         let block_root = value.message.block.tree_hash_root();
-        let slot_index_table = LeanSlotIndexTable {
-            db: self.db.clone(),
-        };
-        slot_index_table.insert(value.message.block.slot, block_root)?;
-
-        // insert entry to state root index table
-        let state_root_index_table = LeanStateRootIndexTable {
-            db: self.db.clone(),
-        };
-        state_root_index_table.insert(value.message.block.state_root, block_root)?;
-
-        let mut write_txn = self.db.begin_write()?;
-        write_txn.set_durability(Durability::Immediate)?;
-        let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
-        table.insert(key, value)?;
-        drop(table);
+        let write_txn = begin_write(&self.db)?;
+        write_txn
+            .open_table(LeanSlotIndexTable::TABLE_DEFINITION)?
+            .insert(value.message.block.slot, block_root)?;
+        write_txn
+            .open_table(LeanStateRootIndexTable::TABLE_DEFINITION)?
+            .insert(value.message.block.state_root, block_root)?;
+        write_txn
+            .open_table(Self::TABLE_DEFINITION)?
+            .insert(key, value)?;
         write_txn.commit()?;
+        record_db_write();
         Ok(())
     }
 
+    /// Removes the block and its index entries in one transaction. Index entries which point at
+    /// another block, like the block of another fork at the same slot, are kept.
     fn remove(&self, key: Self::Key) -> Result<Option<Self::Value>, StoreError> {
-        let write_txn = self.db.begin_write()?;
+        let write_txn = begin_write(&self.db)?;
         let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
         let value = table.remove(key)?.map(|v| v.value());
+        drop(table);
         if let Some(block) = &value {
-            let slot_index_table = LeanSlotIndexTable {
-                db: self.db.clone(),
-            };
-            slot_index_table.remove(block.message.block.slot)?;
-            let state_root_index_table = LeanStateRootIndexTable {
-                db: self.db.clone(),
-            };
-            state_root_index_table.remove(block.message.block.state_root)?;
+            let mut slot_index = write_txn.open_table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+            let slot = block.message.block.slot;
+            if slot_index.get(slot)?.map(|root| root.value()) == Some(key) {
+                slot_index.remove(slot)?;
+            }
+            drop(slot_index);
+
+            let mut state_root_index =
+                write_txn.open_table(LeanStateRootIndexTable::TABLE_DEFINITION)?;
+            let state_root = block.message.block.state_root;
+            if state_root_index.get(state_root)?.map(|root| root.value()) == Some(key) {
+                state_root_index.remove(state_root)?;
+            }
         }
-        drop(table);
         write_txn.commit()?;
+        record_db_write();
         Ok(value)
     }
 }
@@ -115,4 +124,69 @@ impl LeanBlockTable {
         }
         Ok(children_map)
     }
+
+    /// Returns a map of every known block root to its slot and parent root, without keeping the
+    /// full blocks around.
+    pub fn get_parent_map(&self) -> Result<HashMap<B256, (u64, B256)>, StoreError> {
+        let mut parent_map = HashMap::new();
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::TABLE_DEFINITION)?;
+
+        for entry in table.iter()? {
+            let (hash_entry, block_entry) = entry?;
+            let block = block_entry.value().message.block;
+            parent_map.insert(hash_entry.value(), (block.slot, block.parent_root));
+        }
+        Ok(parent_map)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+    use ream_test_utils::sample_block;
+    use tree_hash::TreeHash;
+
+    use crate::{db::ReamDB, tables::table::REDBTable};
+
+    #[test]
+    fn test_remove_block() {
+        let lean_db = ReamDB::in_memory().unwrap().init_lean_db().unwrap();
+        let (block_provider, slot_index_provider, state_root_index_provider) = (
+            lean_db.block_provider(),
+            lean_db.slot_index_provider(),
+            lean_db.state_root_index_provider(),
+        );
+        // Two forks with a block at the same slot, the second one is indexed.
+        let (fork_block, indexed_block) = (
+            sample_block(1, 1, B256::ZERO),
+            sample_block(1, 2, B256::ZERO),
+        );
+        let (fork_root, indexed_root) = (
+            fork_block.message.block.tree_hash_root(),
+            indexed_block.message.block.tree_hash_root(),
+        );
+        let fork_state_root = fork_block.message.block.state_root;
+        block_provider
+            .insert(fork_root, fork_block.clone())
+            .unwrap();
+        block_provider
+            .insert(indexed_root, indexed_block.clone())
+            .unwrap();
+
+        // Removing the other fork's block keeps the index of the slot.
+        assert_eq!(block_provider.remove(fork_root).unwrap(), Some(fork_block));
+        assert_eq!(slot_index_provider.get(1).unwrap(), Some(indexed_root));
+        assert_eq!(
+            state_root_index_provider.get(fork_state_root).unwrap(),
+            None
+        );
+
+        assert_eq!(
+            block_provider.remove(indexed_root).unwrap(),
+            Some(indexed_block)
+        );
+        assert_eq!(slot_index_provider.get(1).unwrap(), None);
+        assert_eq!(block_provider.remove(indexed_root).unwrap(), None);
+    }
 }
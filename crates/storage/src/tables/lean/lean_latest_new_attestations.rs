@@ -1,9 +1,11 @@
 use std::{collections::HashMap, sync::Arc};
 
 use ream_consensus_lean::attestation::SignedAttestation;
-use redb::{Database, ReadableDatabase, TableDefinition};
+use ream_metrics::slot_report::record_db_write;
+use redb::{Database, ReadableDatabase, ReadableTable, ReadableTableMetadata, TableDefinition};
 
 use crate::{
+    durability::begin_write,
     errors::StoreError,
     tables::{ssz_encoder::SSZEncoding, table::REDBTable},
 };
@@ -48,8 +50,22 @@ impl LeanLatestNewAttestationsTable {
             }))
     }
 
+    /// Get all attestations.
+    pub fn get_all_attestations(&self) -> Result<HashMap<u64, SignedAttestation>, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::TABLE_DEFINITION)?;
+
+        table
+            .iter()?
+            .map(|entry| {
+                let (k, v) = entry?;
+                Ok((k.value(), v.value()))
+            })
+            .collect()
+    }
+
     pub fn drain(&self) -> Result<HashMap<u64, SignedAttestation>, StoreError> {
-        let write_txn = self.db.begin_write()?;
+        let write_txn = begin_write(&self.db)?;
         let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
 
         let mut result = HashMap::new();
@@ -58,6 +74,32 @@ impl LeanLatestNewAttestationsTable {
         }
         drop(table);
         write_txn.commit()?;
+        record_db_write();
         Ok(result)
     }
+
+    pub fn count(&self) -> Result<u64, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::TABLE_DEFINITION)?;
+        Ok(table.len()?)
+    }
+
+    /// Removes the attestations for slots before `slot`, returning how many were removed.
+    pub fn remove_before_slot(&self, slot: u64) -> Result<u64, StoreError> {
+        let write_txn = begin_write(&self.db)?;
+        let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
+
+        let mut removed = 0;
+        table.retain(|_, attestation| {
+            let keep = attestation.message.data.slot >= slot;
+            if !keep {
+                removed += 1;
+            }
+            keep
+        })?;
+        drop(table);
+        write_txn.commit()?;
+        record_db_write();
+        Ok(removed)
+    }
 }
@@ -0,0 +1,28 @@
+use std::sync::Arc;
+
+use alloy_primitives::B256;
+use redb::{Database, TableDefinition};
+
+use crate::tables::{field::REDBField, ssz_encoder::SSZEncoding};
+
+pub struct LeanProposerBoostRootField {
+    pub db: Arc<Database>,
+}
+
+/// Table definition for the Lean Proposer Boost Root table
+///
+/// Value: B256, zero while no block is boosted
+impl REDBField for LeanProposerBoostRootField {
+    const FIELD_DEFINITION: TableDefinition<'_, &str, SSZEncoding<B256>> =
+        TableDefinition::new("lean_proposer_boost_root");
+
+    const KEY: &str = "lean_proposer_boost_root_key";
+
+    type Value = B256;
+
+    type ValueFieldDefinition = SSZEncoding<B256>;
+
+    fn database(&self) -> Arc<Database> {
+        self.db.clone()
+    }
+}
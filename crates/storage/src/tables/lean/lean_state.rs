@@ -6,7 +6,10 @@ use redb::{Database, ReadableDatabase, TableDefinition};
 
 use crate::{
     errors::StoreError,
-    tables::{ssz_encoder::SSZEncoding, table::REDBTable},
+    tables::{
+        ssz_encoder::{CompressedSSZEncoding, SSZEncoding},
+        table::REDBTable,
+    },
 };
 
 pub struct LeanStateTable {
@@ -18,8 +21,11 @@ pub struct LeanStateTable {
 /// Key: block_root
 /// Value: [LeanState]
 impl REDBTable for LeanStateTable {
-    const TABLE_DEFINITION: TableDefinition<'_, SSZEncoding<B256>, SSZEncoding<LeanState>> =
-        TableDefinition::new("lean_state");
+    const TABLE_DEFINITION: TableDefinition<
+        '_,
+        SSZEncoding<B256>,
+        CompressedSSZEncoding<LeanState>,
+    > = TableDefinition::new("lean_state");
 
     type Key = B256;
 
@@ -27,7 +33,7 @@ impl REDBTable for LeanStateTable {
 
     type Value = LeanState;
 
-    type ValueTableDefinition = SSZEncoding<LeanState>;
+    type ValueTableDefinition = CompressedSSZEncoding<LeanState>;
 
     fn database(&self) -> Arc<Database> {
         self.db.clone()
@@ -1,11 +1,14 @@
+pub mod blob_sidecar;
 pub mod latest_finalized;
 pub mod latest_justified;
 pub mod latest_known_attestation;
 pub mod lean_block;
 pub mod lean_head;
 pub mod lean_latest_new_attestations;
+pub mod lean_proposer_boost_root;
 pub mod lean_safe_target;
 pub mod lean_state;
 pub mod lean_time;
 pub mod slot_index;
 pub mod state_root_index;
+pub mod validator_performance;
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+
+use ream_metrics::slot_report::record_db_write;
+use redb::{Database, ReadableDatabase, ReadableTable, TableDefinition};
+use ssz_derive::{Decode, Encode};
+
+use crate::{
+    durability::begin_write,
+    errors::StoreError,
+    tables::{ssz_encoder::SSZEncoding, table::REDBTable},
+};
+
+/// What a validator did in a single slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub struct SlotPerformance {
+    pub slot: u64,
+    /// Whether the validator was due to propose the block of the slot.
+    pub is_proposer: bool,
+    /// Whether the validator's block for the slot is on the canonical chain.
+    pub proposed: bool,
+    /// Whether the validator's attestation for the slot was included in a canonical block.
+    pub attestation_included: bool,
+}
+
+/// The slots of a validator within the tracking window, oldest first. Before schema version 6,
+/// the table stored one of these per validator.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct ValidatorPerformanceRecord {
+    pub slots: Vec<SlotPerformance>,
+}
+
+impl ValidatorPerformanceRecord {
+    pub fn proposals_made(&self) -> u64 {
+        self.count(|slot| slot.is_proposer && slot.proposed)
+    }
+
+    pub fn proposals_missed(&self) -> u64 {
+        self.count(|slot| slot.is_proposer && !slot.proposed)
+    }
+
+    pub fn attestations_included(&self) -> u64 {
+        self.count(|slot| slot.attestation_included)
+    }
+
+    pub fn attestations_missed(&self) -> u64 {
+        self.count(|slot| !slot.attestation_included)
+    }
+
+    fn count(&self, predicate: impl Fn(&SlotPerformance) -> bool) -> u64 {
+        self.slots.iter().filter(|slot| predicate(slot)).count() as u64
+    }
+}
+
+pub struct LeanValidatorPerformanceTable {
+    pub db: Arc<Database>,
+}
+
+/// Table definition for the Validator Performance table
+///
+/// Key: (u64, u64) (validator index, slot)
+/// Value: [SlotPerformance]
+impl REDBTable for LeanValidatorPerformanceTable {
+    const TABLE_DEFINITION: TableDefinition<'_, (u64, u64), SSZEncoding<SlotPerformance>> =
+        TableDefinition::new("lean_validator_performance");
+
+    type Key = (u64, u64);
+
+    type KeyTableDefinition = (u64, u64);
+
+    type Value = SlotPerformance;
+
+    type ValueTableDefinition = SSZEncoding<SlotPerformance>;
+
+    fn database(&self) -> Arc<Database> {
+        self.db.clone()
+    }
+}
+
+impl LeanValidatorPerformanceTable {
+    /// Returns the slots of the validator at `validator_index` within the tracking window.
+    pub fn get_record(
+        &self,
+        validator_index: u64,
+    ) -> Result<ValidatorPerformanceRecord, StoreError> {
+        let read_txn = self.db.begin_read()?;
+        let table = read_txn.open_table(Self::TABLE_DEFINITION)?;
+        let slots = table
+            .range((validator_index, 0)..=(validator_index, u64::MAX))?
+            .map(|entry| Ok(entry?.1.value()))
+            .collect::<Result<_, StoreError>>()?;
+        Ok(ValidatorPerformanceRecord { slots })
+    }
+
+    /// Records the performance of each validator in a slot in a single transaction, dropping the
+    /// slots which fall out of the last `window_slots` slots. Only the slot is written, the
+    /// earlier slots of each validator are left as they are.
+    pub fn record_slot(
+        &self,
+        performances: impl IntoIterator<Item = (u64, SlotPerformance)>,
+        window_slots: u64,
+    ) -> Result<(), StoreError> {
+        let write_txn = begin_write(&self.db)?;
+
+        let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
+        for (validator_index, performance) in performances {
+            let window_start = performance
+                .slot
+                .saturating_sub(window_slots.saturating_sub(1));
+            table.retain_in(
+                (validator_index, 0)..(validator_index, window_start),
+                |_, _| false,
+            )?;
+            table.insert((validator_index, performance.slot), performance)?;
+        }
+
+        drop(table);
+        write_txn.commit()?;
+        record_db_write();
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::db::ReamDB;
+
+    #[test]
+    fn test_record_slot_keeps_window() {
+        let temp_dir = TempDir::new("lean_validator_performance").unwrap();
+        let lean_db = ReamDB::new(temp_dir.path().to_path_buf())
+            .unwrap()
+            .init_lean_db()
+            .unwrap();
+        let table = lean_db.validator_performance_provider();
+
+        for slot in 0..5 {
+            table
+                .record_slot(
+                    [(
+                        1,
+                        SlotPerformance {
+                            slot,
+                            is_proposer: slot % 2 == 1,
+                            proposed: slot == 1,
+                            attestation_included: slot != 4,
+                        },
+                    )],
+                    3,
+                )
+                .unwrap();
+        }
+
+        let record = table.get_record(1).unwrap();
+        assert_eq!(
+            record
+                .slots
+                .iter()
+                .map(|slot| slot.slot)
+                .collect::<Vec<_>>(),
+            vec![2, 3, 4]
+        );
+        assert_eq!(record.proposals_made(), 0);
+        assert_eq!(record.proposals_missed(), 1);
+        assert_eq!(record.attestations_included(), 2);
+        assert_eq!(record.attestations_missed(), 1);
+        assert!(table.get_record(0).unwrap().slots.is_empty());
+
+        // Recording a slot again replaces it.
+        table
+            .record_slot(
+                [(
+                    1,
+                    SlotPerformance {
+                        slot: 4,
+                        is_proposer: false,
+                        proposed: false,
+                        attestation_included: true,
+                    },
+                )],
+                3,
+            )
+            .unwrap();
+        assert_eq!(table.get_record(1).unwrap().attestations_missed(), 0);
+    }
+}
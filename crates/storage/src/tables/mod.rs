@@ -2,5 +2,7 @@ pub mod beacon;
 pub mod field;
 pub mod lean;
 pub mod multimap_table;
+pub mod schema;
+pub mod schema_version;
 pub mod ssz_encoder;
 pub mod table;
@@ -0,0 +1,133 @@
+//! The tables of each chain, listed once so creating, copying and inspecting a database covers
+//! every table, including the ones added later.
+
+use redb::{Key, MultimapTableDefinition, TableDefinition, Value};
+
+use crate::{
+    errors::StoreError,
+    tables::{
+        beacon::{
+            beacon_block::BeaconBlockTable, beacon_state::BeaconStateTable,
+            block_timeliness::BlockTimelinessTable, checkpoint_states::CheckpointStatesTable,
+            equivocating_indices::EQUIVOCATING_INDICES_FIELD,
+            finalized_checkpoint::FinalizedCheckpointField, genesis_time::GenesisTimeField,
+            justified_checkpoint::JustifiedCheckpointField, latest_messages::LatestMessagesTable,
+            parent_root_index::PARENT_ROOT_INDEX_MULTIMAP_TABLE,
+            proposer_boost_root::ProposerBoostRootField, slot_index::BeaconSlotIndexTable,
+            state_root_index::BeaconStateRootIndexTable, time::TimeField,
+            unrealized_finalized_checkpoint::UnrealizedFinalizedCheckpointField,
+            unrealized_justifications::UnrealizedJustificationsTable,
+            unrealized_justified_checkpoint::UnrealizedJustifiedCheckpointField,
+        },
+        field::REDBField,
+        lean::{
+            blob_sidecar::LeanBlobSidecarTable, latest_finalized::LatestFinalizedField,
+            latest_justified::LatestJustifiedField,
+            latest_known_attestation::LatestKnownAttestationTable, lean_block::LeanBlockTable,
+            lean_head::LeanHeadField, lean_latest_new_attestations::LeanLatestNewAttestationsTable,
+            lean_proposer_boost_root::LeanProposerBoostRootField,
+            lean_safe_target::LeanSafeTargetField, lean_state::LeanStateTable,
+            lean_time::LeanTimeField, slot_index::LeanSlotIndexTable,
+            state_root_index::LeanStateRootIndexTable,
+            validator_performance::LeanValidatorPerformanceTable,
+        },
+        table::REDBTable,
+    },
+};
+
+/// Does something with each table of a schema, see [visit_lean_tables] and
+/// [visit_beacon_tables].
+pub(crate) trait TableVisitor {
+    fn table<K: Key + 'static, V: Value + 'static>(
+        &mut self,
+        definition: TableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError>;
+
+    fn multimap_table<K: Key + 'static, V: Key + 'static>(
+        &mut self,
+        definition: MultimapTableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError>;
+}
+
+/// Visits every table of the lean chain.
+pub(crate) fn visit_lean_tables(visitor: &mut impl TableVisitor) -> Result<(), StoreError> {
+    visitor.table(LatestFinalizedField::FIELD_DEFINITION)?;
+    visitor.table(LatestJustifiedField::FIELD_DEFINITION)?;
+    visitor.table(LeanBlockTable::TABLE_DEFINITION)?;
+    visitor.table(LeanStateTable::TABLE_DEFINITION)?;
+    visitor.table(LeanSlotIndexTable::TABLE_DEFINITION)?;
+    visitor.table(LeanStateRootIndexTable::TABLE_DEFINITION)?;
+    visitor.table(LeanTimeField::FIELD_DEFINITION)?;
+    visitor.table(LeanHeadField::FIELD_DEFINITION)?;
+    visitor.table(LeanSafeTargetField::FIELD_DEFINITION)?;
+    visitor.table(LeanProposerBoostRootField::FIELD_DEFINITION)?;
+    visitor.table(LeanLatestNewAttestationsTable::TABLE_DEFINITION)?;
+    visitor.table(LatestKnownAttestationTable::TABLE_DEFINITION)?;
+    visitor.table(LeanValidatorPerformanceTable::TABLE_DEFINITION)?;
+    visitor.table(LeanBlobSidecarTable::TABLE_DEFINITION)?;
+    Ok(())
+}
+
+/// Visits every table of the beacon chain.
+pub(crate) fn visit_beacon_tables(visitor: &mut impl TableVisitor) -> Result<(), StoreError> {
+    visitor.table(BeaconBlockTable::TABLE_DEFINITION)?;
+    visitor.table(BeaconStateTable::TABLE_DEFINITION)?;
+    visitor.table(BlockTimelinessTable::TABLE_DEFINITION)?;
+    visitor.table(CheckpointStatesTable::TABLE_DEFINITION)?;
+    visitor.table(EQUIVOCATING_INDICES_FIELD)?;
+    visitor.table(FinalizedCheckpointField::FIELD_DEFINITION)?;
+    visitor.table(GenesisTimeField::FIELD_DEFINITION)?;
+    visitor.table(JustifiedCheckpointField::FIELD_DEFINITION)?;
+    visitor.table(LatestMessagesTable::TABLE_DEFINITION)?;
+    visitor.multimap_table(PARENT_ROOT_INDEX_MULTIMAP_TABLE)?;
+    visitor.table(ProposerBoostRootField::FIELD_DEFINITION)?;
+    visitor.table(BeaconSlotIndexTable::TABLE_DEFINITION)?;
+    visitor.table(BeaconStateRootIndexTable::TABLE_DEFINITION)?;
+    visitor.table(TimeField::FIELD_DEFINITION)?;
+    visitor.table(UnrealizedFinalizedCheckpointField::FIELD_DEFINITION)?;
+    visitor.table(UnrealizedJustificationsTable::TABLE_DEFINITION)?;
+    visitor.table(UnrealizedJustifiedCheckpointField::FIELD_DEFINITION)?;
+    Ok(())
+}
+
+/// Creates the tables it visits which don't exist yet.
+pub(crate) struct TableCreator<'a>(pub &'a redb::WriteTransaction);
+
+impl TableVisitor for TableCreator<'_> {
+    fn table<K: Key + 'static, V: Value + 'static>(
+        &mut self,
+        definition: TableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        self.0.open_table(definition)?;
+        Ok(())
+    }
+
+    fn multimap_table<K: Key + 'static, V: Key + 'static>(
+        &mut self,
+        definition: MultimapTableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        self.0.open_multimap_table(definition)?;
+        Ok(())
+    }
+}
+
+/// Deletes the tables it visits.
+pub(crate) struct TableDeleter<'a>(pub &'a redb::WriteTransaction);
+
+impl TableVisitor for TableDeleter<'_> {
+    fn table<K: Key + 'static, V: Value + 'static>(
+        &mut self,
+        definition: TableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        self.0.delete_table(definition)?;
+        Ok(())
+    }
+
+    fn multimap_table<K: Key + 'static, V: Key + 'static>(
+        &mut self,
+        definition: MultimapTableDefinition<'static, K, V>,
+    ) -> Result<(), StoreError> {
+        self.0.delete_multimap_table(definition)?;
+        Ok(())
+    }
+}
@@ -1,5 +1,6 @@
-use std::{any::type_name, fmt::Debug};
+use std::{any::type_name, borrow::Cow, fmt::Debug};
 
+use ream_metrics::{STORAGE_ENCODED_BYTES_TOTAL, inc_int_counter_vec_by};
 use redb::{Key, TypeName, Value};
 use ssz::{Decode, Encode};
 
@@ -53,3 +54,127 @@ where
         TypeName::new(&format!("SSZEncoding<{}>", type_name::<T>()))
     }
 }
+
+/// Version byte of a [CompressedSSZEncoding] value stored as plain SSZ.
+pub(crate) const UNCOMPRESSED_VERSION: u8 = 0;
+
+/// Version byte of a [CompressedSSZEncoding] value stored as zstd compressed SSZ.
+const ZSTD_VERSION: u8 = 1;
+
+const ZSTD_LEVEL: i32 = 3;
+
+/// Wrapper type to handle values using SSZ encoding compressed with zstd, for large values like
+/// states which compress well.
+///
+/// The encoding starts with a version byte, so values which don't get smaller are stored as plain
+/// SSZ, and the format can change without rewriting every value. Databases written before values
+/// were compressed are rewritten by a migration, as plain SSZ can't be told apart from a version
+/// byte.
+#[derive(Debug)]
+pub struct CompressedSSZEncoding<T>(pub T);
+
+impl<T> Value for CompressedSSZEncoding<T>
+where
+    T: Debug + Encode + Decode,
+{
+    type SelfType<'a>
+        = T
+    where
+        Self: 'a;
+
+    type AsBytes<'a>
+        = Vec<u8>
+    where
+        Self: 'a;
+
+    fn fixed_width() -> Option<usize> {
+        None
+    }
+
+    fn from_bytes<'a>(data: &'a [u8]) -> Self::SelfType<'a>
+    where
+        Self: 'a,
+    {
+        Self::SelfType::from_ssz_bytes(&decompress(data))
+            .expect("Failed to decode SSZ bytes, data corruption?")
+    }
+
+    fn as_bytes<'a, 'b: 'a>(value: &'a Self::SelfType<'b>) -> Self::AsBytes<'a>
+    where
+        Self: 'a,
+        Self: 'b,
+    {
+        let ssz_bytes = value.as_ssz_bytes();
+        let value_type = short_type_name::<T>();
+        inc_int_counter_vec_by(
+            &STORAGE_ENCODED_BYTES_TOTAL,
+            ssz_bytes.len() as u64,
+            &[value_type, "uncompressed"],
+        );
+
+        let bytes = match zstd::encode_all(ssz_bytes.as_slice(), ZSTD_LEVEL) {
+            Ok(compressed) if compressed.len() < ssz_bytes.len() => {
+                [&[ZSTD_VERSION][..], compressed.as_slice()].concat()
+            }
+            _ => [&[UNCOMPRESSED_VERSION][..], ssz_bytes.as_slice()].concat(),
+        };
+        inc_int_counter_vec_by(
+            &STORAGE_ENCODED_BYTES_TOTAL,
+            bytes.len() as u64,
+            &[value_type, "stored"],
+        );
+        bytes
+    }
+
+    fn type_name() -> TypeName {
+        TypeName::new(&format!("CompressedSSZEncoding<{}>", type_name::<T>()))
+    }
+}
+
+/// Returns the SSZ bytes of a [CompressedSSZEncoding] value.
+pub(crate) fn decompress(data: &[u8]) -> Cow<'_, [u8]> {
+    let (version, data) = data
+        .split_first()
+        .expect("Missing version byte, data corruption?");
+    match *version {
+        UNCOMPRESSED_VERSION => Cow::Borrowed(data),
+        ZSTD_VERSION => Cow::Owned(
+            zstd::decode_all(data).expect("Failed to decompress zstd bytes, data corruption?"),
+        ),
+        version => panic!("Unknown encoding version {version}, data corruption?"),
+    }
+}
+
+/// The name of `T` without its module path, like `LeanState`.
+fn short_type_name<T>() -> &'static str {
+    let name = type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use redb::Value;
+    use ssz_types::{VariableList, typenum::U1024};
+
+    use super::{CompressedSSZEncoding, UNCOMPRESSED_VERSION, ZSTD_VERSION};
+
+    #[test]
+    fn test_compressed_ssz_encoding() {
+        let value = VariableList::<u64, U1024>::try_from(vec![7; 1024]).unwrap();
+        let bytes = CompressedSSZEncoding::<VariableList<u64, U1024>>::as_bytes(&value);
+        assert_eq!(bytes[0], ZSTD_VERSION);
+        assert!(bytes.len() < 1024);
+        assert_eq!(
+            CompressedSSZEncoding::<VariableList<u64, U1024>>::from_bytes(&bytes),
+            value
+        );
+
+        // Values which don't get smaller are stored as they are.
+        let bytes = CompressedSSZEncoding::<u64>::as_bytes(&7);
+        assert_eq!(
+            bytes,
+            [&[UNCOMPRESSED_VERSION][..], &7_u64.to_le_bytes()].concat()
+        );
+        assert_eq!(CompressedSSZEncoding::<u64>::from_bytes(&bytes), 7);
+    }
+}
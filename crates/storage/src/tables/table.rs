@@ -1,9 +1,10 @@
 use std::{fmt::Debug, sync::Arc};
 
-use redb::{Database, Durability, ReadableDatabase, TableDefinition};
+use ream_metrics::slot_report::record_db_write;
+use redb::{Database, ReadableDatabase, TableDefinition};
 use ssz::{Decode, Encode};
 
-use crate::errors::StoreError;
+use crate::{durability::begin_write, errors::StoreError};
 
 pub trait REDBTable
 where
@@ -41,13 +42,13 @@ where
         key: <Self::KeyTableDefinition as redb::Value>::SelfType<'a>,
         value: <Self::ValueTableDefinition as redb::Value>::SelfType<'a>,
     ) -> Result<(), StoreError> {
-        let mut write_txn = self.database().begin_write()?;
-        write_txn.set_durability(Durability::Immediate)?;
+        let write_txn = begin_write(&self.database())?;
         {
             let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
             table.insert(key, value)?;
         }
         write_txn.commit()?;
+        record_db_write();
         Ok(())
     }
 
@@ -55,7 +56,7 @@ where
         &self,
         key: <Self::KeyTableDefinition as redb::Value>::SelfType<'a>,
     ) -> Result<Option<Self::Value>, StoreError> {
-        let write_txn = self.database().begin_write()?;
+        let write_txn = begin_write(&self.database())?;
         let value = {
             let mut table = write_txn.open_table(Self::TABLE_DEFINITION)?;
             table
@@ -63,6 +64,7 @@ where
                 .map(|value| Self::Value::from(value.value()))
         };
         write_txn.commit()?;
+        record_db_write();
         Ok(value)
     }
 }
@@ -0,0 +1,3 @@
+pub mod network;
+pub mod node;
+pub mod simulation;
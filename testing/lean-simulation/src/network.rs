@@ -0,0 +1,200 @@
+use std::collections::BTreeMap;
+
+use alloy_primitives::B256;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+use ream_consensus_lean::{attestation::SignedAttestation, block::SignedBlockWithAttestation};
+
+/// A message between two simulated nodes.
+#[derive(Debug, Clone)]
+pub enum Message {
+    Block(Box<SignedBlockWithAttestation>),
+    Attestation(Box<SignedAttestation>),
+    /// Asks for the block with this root, sent for the missing parent of a received block.
+    BlockRequest(B256),
+}
+
+/// A message on its way from `from` to `to`.
+#[derive(Debug, Clone)]
+pub struct Envelope {
+    pub from: usize,
+    pub to: usize,
+    pub message: Message,
+}
+
+/// How messages travel between any two nodes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LinkConfig {
+    /// Messages take between `min_delay` and `max_delay` intervals to arrive, inclusive.
+    pub min_delay: u64,
+    pub max_delay: u64,
+    /// Chance of a message being lost, from 0 to 1.
+    pub drop_rate: f64,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            min_delay: 0,
+            max_delay: 0,
+            drop_rate: 0.0,
+        }
+    }
+}
+
+/// Splits the nodes into groups which can't reach each other from `start_slot` until
+/// `end_slot`. A node which isn't in any group is cut off from every other node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Partition {
+    pub start_slot: u64,
+    pub end_slot: u64,
+    pub groups: Vec<Vec<usize>>,
+}
+
+impl Partition {
+    fn separates(&self, slot: u64, from: usize, to: usize) -> bool {
+        if !(self.start_slot..self.end_slot).contains(&slot) {
+            return false;
+        }
+        !self
+            .groups
+            .iter()
+            .any(|group| group.contains(&from) && group.contains(&to))
+    }
+}
+
+/// An in-memory transport between simulated nodes. Delays and drops are drawn from a seeded
+/// generator, so a simulation with the same seed delivers the same messages at the same times.
+#[derive(Debug)]
+pub struct SimulatedNetwork {
+    link: LinkConfig,
+    partitions: Vec<Partition>,
+    rng: ChaCha20Rng,
+    /// Messages in flight keyed by their delivery interval, then by the order they were sent in.
+    in_flight: BTreeMap<(u64, u64), Envelope>,
+    sent: u64,
+    dropped: u64,
+}
+
+impl SimulatedNetwork {
+    pub fn new(link: LinkConfig, partitions: Vec<Partition>, seed: u64) -> Self {
+        Self {
+            link,
+            partitions,
+            rng: ChaCha20Rng::seed_from_u64(seed),
+            in_flight: BTreeMap::new(),
+            sent: 0,
+            dropped: 0,
+        }
+    }
+
+    /// Sends `envelope` at interval `time` of `slot`, unless a partition separates the nodes or
+    /// the link drops it.
+    pub fn send(&mut self, time: u64, slot: u64, envelope: Envelope) {
+        self.sent += 1;
+        if envelope.from != envelope.to
+            && (self
+                .partitions
+                .iter()
+                .any(|partition| partition.separates(slot, envelope.from, envelope.to))
+                || self.rng.random_bool(self.link.drop_rate))
+        {
+            self.dropped += 1;
+            return;
+        }
+        let delay = self
+            .rng
+            .random_range(self.link.min_delay..=self.link.max_delay);
+        self.in_flight.insert((time + delay, self.sent), envelope);
+    }
+
+    /// Sends `message` from `from` to every other one of `num_nodes` nodes.
+    pub fn broadcast(
+        &mut self,
+        time: u64,
+        slot: u64,
+        from: usize,
+        num_nodes: usize,
+        message: Message,
+    ) {
+        for to in (0..num_nodes).filter(|to| *to != from) {
+            self.send(
+                time,
+                slot,
+                Envelope {
+                    from,
+                    to,
+                    message: message.clone(),
+                },
+            );
+        }
+    }
+
+    /// Takes the next message due by interval `time`, earliest first.
+    pub fn next_delivery(&mut self, time: u64) -> Option<Envelope> {
+        let (key, _) = self.in_flight.first_key_value()?;
+        if key.0 > time {
+            return None;
+        }
+        self.in_flight.pop_first().map(|(_, envelope)| envelope)
+    }
+
+    pub fn sent(&self) -> u64 {
+        self.sent
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloy_primitives::B256;
+
+    use super::{Envelope, LinkConfig, Message, Partition, SimulatedNetwork};
+
+    fn request(from: usize, to: usize) -> Envelope {
+        Envelope {
+            from,
+            to,
+            message: Message::BlockRequest(B256::ZERO),
+        }
+    }
+
+    #[test]
+    fn test_delays_and_partitions() {
+        let link = LinkConfig {
+            min_delay: 1,
+            max_delay: 3,
+            drop_rate: 0.0,
+        };
+        let partition = Partition {
+            start_slot: 2,
+            end_slot: 4,
+            groups: vec![vec![0, 1], vec![2]],
+        };
+        let mut network = SimulatedNetwork::new(link, vec![partition], 7);
+
+        network.send(0, 0, request(0, 2));
+        assert!(network.next_delivery(0).is_none());
+        assert_eq!(
+            network.next_delivery(3).map(|envelope| envelope.to),
+            Some(2)
+        );
+
+        network.send(8, 2, request(0, 1));
+        network.send(8, 2, request(0, 2));
+        network.send(16, 4, request(0, 2));
+        assert_eq!(network.dropped(), 1);
+        assert_eq!(
+            network.next_delivery(11).map(|envelope| envelope.to),
+            Some(1)
+        );
+        assert!(network.next_delivery(11).is_none());
+        assert_eq!(
+            network.next_delivery(19).map(|envelope| envelope.to),
+            Some(2)
+        );
+    }
+}
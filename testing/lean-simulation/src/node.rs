@@ -0,0 +1,170 @@
+use std::collections::BTreeMap;
+
+use alloy_primitives::B256;
+use anyhow::anyhow;
+use ream_consensus_lean::{
+    attestation::{Attestation, SignedAttestation},
+    block::{BlockWithAttestation, BlockWithSignatures, SignedBlockWithAttestation},
+    checkpoint::Checkpoint,
+    state::LeanState,
+};
+use ream_fork_choice_lean::store::{AttestationSource, Store};
+use ream_post_quantum_crypto::leansig::signature::Signature;
+use ream_storage::tables::{field::REDBField, table::REDBTable};
+use ream_test_utils::{attestation::signed_attestations, db_setup};
+use tree_hash::TreeHash;
+
+/// Where a node's fork choice stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct NodeSummary {
+    pub head: Checkpoint,
+    pub latest_justified: Checkpoint,
+    pub latest_finalized: Checkpoint,
+}
+
+/// A node of the simulation: a fork choice store with its own database, and the validators it
+/// runs. Signatures are blank and aren't verified.
+#[derive(Debug)]
+pub struct SimulatedNode {
+    pub index: usize,
+    pub store: Store,
+    validator_ids: Vec<u64>,
+    /// Received blocks whose parent is unknown, keyed by their root.
+    orphans: BTreeMap<B256, SignedBlockWithAttestation>,
+}
+
+impl SimulatedNode {
+    pub fn new(
+        index: usize,
+        genesis_block: SignedBlockWithAttestation,
+        genesis_state: LeanState,
+        validator_ids: Vec<u64>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            index,
+            store: Store::get_forkchoice_store(genesis_block, genesis_state, db_setup(), None)?,
+            validator_ids,
+            orphans: BTreeMap::new(),
+        })
+    }
+
+    /// Returns whether the proposer of `slot` runs on this node.
+    pub async fn is_proposer(&self, slot: u64) -> anyhow::Result<bool> {
+        let proposer_index = self.store.proposer_index(slot).await?;
+        Ok(self.validator_ids.contains(&proposer_index))
+    }
+
+    /// Produces the block of `slot` if its proposer runs on this node, imports it and returns it
+    /// for broadcasting.
+    pub async fn propose(
+        &mut self,
+        slot: u64,
+    ) -> anyhow::Result<Option<SignedBlockWithAttestation>> {
+        let proposer_index = self.store.proposer_index(slot).await?;
+        if !self.validator_ids.contains(&proposer_index) {
+            return Ok(None);
+        }
+        let BlockWithSignatures {
+            block,
+            mut signatures,
+        } = self
+            .store
+            .produce_block_with_signatures(slot, proposer_index)
+            .await?;
+        let attestation_data = self.store.produce_attestation_data(slot).await?;
+        signatures
+            .push(Signature::blank())
+            .map_err(|err| anyhow!("Failed to push signature {err:?}"))?;
+
+        let signed_block = SignedBlockWithAttestation {
+            message: BlockWithAttestation {
+                block,
+                proposer_attestation: Attestation {
+                    validator_id: proposer_index,
+                    data: attestation_data,
+                },
+            },
+            signature: signatures,
+        };
+        self.store.on_block(&signed_block, false).await?;
+        Ok(Some(signed_block))
+    }
+
+    /// Attests to the head with every validator of this node except the proposer of `slot`, which
+    /// attested in its block, and returns the attestations for broadcasting.
+    pub async fn attest(&self, slot: u64) -> anyhow::Result<Vec<SignedAttestation>> {
+        let proposer_index = self.store.proposer_index(slot).await?;
+        let attestation_data = self.store.produce_attestation_data(slot).await?;
+        let attestations = signed_attestations(
+            self.validator_ids
+                .iter()
+                .copied()
+                .filter(|validator_id| *validator_id != proposer_index),
+            &attestation_data,
+        );
+        for attestation in &attestations {
+            self.store
+                .on_attestation(attestation.clone(), AttestationSource::Api)
+                .await?;
+        }
+        Ok(attestations)
+    }
+
+    /// Imports `block` and the orphans descending from it. If its parent is unknown, the block is
+    /// kept as an orphan, and the root of its oldest missing ancestor is returned to be requested.
+    pub async fn import_block(
+        &mut self,
+        block: SignedBlockWithAttestation,
+    ) -> anyhow::Result<Option<B256>> {
+        let block_provider = self.store.store.block_provider();
+        let parent_root = block.message.block.parent_root;
+        if !block_provider.contains_key(parent_root) {
+            self.orphans
+                .insert(block.message.block.tree_hash_root(), block);
+            let mut missing_root = parent_root;
+            while let Some(orphan) = self.orphans.get(&missing_root) {
+                missing_root = orphan.message.block.parent_root;
+            }
+            return Ok(Some(missing_root));
+        }
+
+        let mut blocks = vec![block];
+        while let Some(block) = blocks.pop() {
+            let block_root = block.message.block.tree_hash_root();
+            self.store.on_block(&block, false).await?;
+            let children = self
+                .orphans
+                .iter()
+                .filter(|(_, orphan)| orphan.message.block.parent_root == block_root)
+                .map(|(root, _)| *root)
+                .collect::<Vec<_>>();
+            blocks.extend(
+                children
+                    .iter()
+                    .filter_map(|child_root| self.orphans.remove(child_root)),
+            );
+        }
+        Ok(None)
+    }
+
+    pub fn block(&self, block_root: B256) -> anyhow::Result<Option<SignedBlockWithAttestation>> {
+        Ok(self.store.store.block_provider().get(block_root)?)
+    }
+
+    pub fn summary(&self) -> anyhow::Result<NodeSummary> {
+        let db = &self.store.store;
+        let head_root = db.head_provider().get()?;
+        let head_block = db
+            .block_provider()
+            .get(head_root)?
+            .ok_or_else(|| anyhow!("Head block {head_root} not found"))?;
+        Ok(NodeSummary {
+            head: Checkpoint {
+                root: head_root,
+                slot: head_block.message.block.slot,
+            },
+            latest_justified: db.latest_justified_provider().get()?,
+            latest_finalized: db.latest_finalized_provider().get()?,
+        })
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::ensure;
+use ream_fork_choice_lean::store::AttestationSource;
+use ream_network_spec::networks::{IntervalDuty, lean_network_spec};
+use ream_test_utils::genesis;
+use tracing::debug;
+
+use crate::{
+    network::{Envelope, LinkConfig, Message, Partition, SimulatedNetwork},
+    node::{NodeSummary, SimulatedNode},
+};
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfig {
+    pub num_nodes: usize,
+    /// Slots to run after genesis.
+    pub slots: u64,
+    /// Seeds the delays and drops of the network.
+    pub seed: u64,
+    pub link: LinkConfig,
+    pub partitions: Vec<Partition>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            num_nodes: 3,
+            slots: 24,
+            seed: 0,
+            link: LinkConfig::default(),
+            partitions: vec![],
+        }
+    }
+}
+
+/// Where every node stands at the end of a simulation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SimulationReport {
+    pub nodes: Vec<NodeSummary>,
+    pub messages_sent: u64,
+    pub messages_dropped: u64,
+    /// Messages a node failed to import, such as attestations for a block it hasn't received.
+    pub messages_rejected: u64,
+}
+
+impl SimulationReport {
+    pub fn agree_on_head(&self) -> bool {
+        self.nodes
+            .windows(2)
+            .all(|pair| pair[0].head == pair[1].head)
+    }
+
+    pub fn agree_on_finality(&self) -> bool {
+        self.nodes
+            .windows(2)
+            .all(|pair| pair[0].latest_finalized == pair[1].latest_finalized)
+    }
+
+    /// Returns the lowest finalized slot of any node.
+    pub fn finalized_slot(&self) -> u64 {
+        self.nodes
+            .iter()
+            .map(|node| node.latest_finalized.slot)
+            .min()
+            .unwrap_or_default()
+    }
+}
+
+/// Runs nodes in one process on a virtual clock, which advances an interval at a time, with the
+/// messages between them going through a [SimulatedNetwork]. Nothing depends on the system clock,
+/// so a run is reproduced exactly by its config.
+///
+/// The nodes run the validators of the network spec, which has to be set beforehand, dealt out
+/// between them in turn.
+#[derive(Debug)]
+pub struct Simulation {
+    config: SimulationConfig,
+    nodes: Vec<SimulatedNode>,
+    network: SimulatedNetwork,
+    /// Intervals since genesis.
+    time: u64,
+    messages_rejected: u64,
+}
+
+impl Simulation {
+    pub fn new(config: SimulationConfig) -> anyhow::Result<Self> {
+        ensure!(config.num_nodes > 0, "A simulation needs at least one node");
+        let num_validators = lean_network_spec().num_validators;
+        let (genesis_block, genesis_state) = genesis(num_validators as usize);
+        let nodes = (0..config.num_nodes)
+            .map(|index| {
+                SimulatedNode::new(
+                    index,
+                    genesis_block.clone(),
+                    genesis_state.clone(),
+                    (0..num_validators)
+                        .filter(|validator_id| *validator_id as usize % config.num_nodes == index)
+                        .collect(),
+                )
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let network = SimulatedNetwork::new(config.link, config.partitions.clone(), config.seed);
+
+        Ok(Self {
+            config,
+            nodes,
+            network,
+            time: 0,
+            messages_rejected: 0,
+        })
+    }
+
+    pub fn nodes(&self) -> &[SimulatedNode] {
+        &self.nodes
+    }
+
+    /// Runs every interval of the configured slots. Messages still in flight at the end are then
+    /// delivered and every node counts the attestations it received, so the report shows where
+    /// the nodes settle.
+    pub async fn run(&mut self) -> anyhow::Result<SimulationReport> {
+        let end_time = (self.config.slots + 1) * lean_network_spec().intervals_per_slot;
+        while self.time < end_time {
+            self.step().await?;
+        }
+
+        self.deliver(u64::MAX).await?;
+        for node in &self.nodes {
+            node.store.accept_new_attestations().await?;
+        }
+        self.report()
+    }
+
+    /// Advances every node by one interval, runs the validator duty of the interval and delivers
+    /// the messages due.
+    pub async fn step(&mut self) -> anyhow::Result<()> {
+        let spec = lean_network_spec();
+        self.time += 1;
+        let slot = self.time / spec.intervals_per_slot;
+        let duty = spec.interval_duty(self.time % spec.intervals_per_slot);
+
+        for node in &self.nodes {
+            let has_proposal =
+                duty == Some(IntervalDuty::Propose) && node.is_proposer(slot).await?;
+            node.store.tick_interval(has_proposal).await?;
+        }
+
+        match duty {
+            Some(IntervalDuty::Propose) => {
+                for index in 0..self.nodes.len() {
+                    if let Some(block) = self.nodes[index].propose(slot).await? {
+                        self.network.broadcast(
+                            self.time,
+                            slot,
+                            index,
+                            self.nodes.len(),
+                            Message::Block(Box::new(block)),
+                        );
+                    }
+                }
+            }
+            Some(IntervalDuty::Attest) => {
+                for index in 0..self.nodes.len() {
+                    for attestation in self.nodes[index].attest(slot).await? {
+                        self.network.broadcast(
+                            self.time,
+                            slot,
+                            index,
+                            self.nodes.len(),
+                            Message::Attestation(Box::new(attestation)),
+                        );
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        self.deliver(self.time).await
+    }
+
+    /// Delivers the messages due by interval `until`, including the replies they cause.
+    async fn deliver(&mut self, until: u64) -> anyhow::Result<()> {
+        let slot = self.time / lean_network_spec().intervals_per_slot;
+        while let Some(Envelope { from, to, message }) = self.network.next_delivery(until) {
+            let node = &mut self.nodes[to];
+            match message {
+                Message::Block(block) => match node.import_block(*block).await {
+                    Ok(Some(missing_root)) => self.network.send(
+                        self.time,
+                        slot,
+                        Envelope {
+                            from: to,
+                            to: from,
+                            message: Message::BlockRequest(missing_root),
+                        },
+                    ),
+                    Ok(None) => {}
+                    Err(err) => {
+                        self.messages_rejected += 1;
+                        debug!(node = to, "Rejected block from node {from}: {err:?}");
+                    }
+                },
+                Message::Attestation(attestation) => {
+                    if let Err(err) = node
+                        .store
+                        .on_attestation(*attestation, AttestationSource::Gossip)
+                        .await
+                    {
+                        self.messages_rejected += 1;
+                        debug!(node = to, "Rejected attestation from node {from}: {err:?}");
+                    }
+                }
+                Message::BlockRequest(block_root) => {
+                    if let Some(block) = node.block(block_root)? {
+                        self.network.send(
+                            self.time,
+                            slot,
+                            Envelope {
+                                from: to,
+                                to: from,
+                                message: Message::Block(Box::new(block)),
+                            },
+                        );
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn report(&self) -> anyhow::Result<SimulationReport> {
+        Ok(SimulationReport {
+            nodes: self
+                .nodes
+                .iter()
+                .map(SimulatedNode::summary)
+                .collect::<anyhow::Result<Vec<_>>>()?,
+            messages_sent: self.network.sent(),
+            messages_dropped: self.network.dropped(),
+            messages_rejected: self.messages_rejected,
+        })
+    }
+}
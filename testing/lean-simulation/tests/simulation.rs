@@ -0,0 +1,73 @@
+use lean_simulation::{
+    network::{LinkConfig, Partition},
+    simulation::{Simulation, SimulationConfig, SimulationReport},
+};
+use ream_network_spec::networks::{LeanNetworkSpec, set_lean_network_spec};
+
+async fn run(config: SimulationConfig) -> SimulationReport {
+    set_lean_network_spec(LeanNetworkSpec::ephemery().into());
+    Simulation::new(config).unwrap().run().await.unwrap()
+}
+
+#[tokio::test]
+async fn test_nodes_converge() {
+    let report = run(SimulationConfig::default()).await;
+
+    assert!(report.agree_on_head(), "{report:?}");
+    assert!(report.agree_on_finality(), "{report:?}");
+    assert!(report.finalized_slot() > 0, "{report:?}");
+    assert_eq!(report.messages_dropped, 0);
+}
+
+#[tokio::test]
+async fn test_nodes_converge_with_delays_and_drops() {
+    let report = run(SimulationConfig {
+        slots: 48,
+        seed: 1,
+        link: LinkConfig {
+            min_delay: 0,
+            max_delay: 2,
+            drop_rate: 0.1,
+        },
+        ..Default::default()
+    })
+    .await;
+
+    // A node can miss the last block for good if it's dropped, so only finality has to agree.
+    assert!(report.messages_dropped > 0);
+    assert!(report.agree_on_finality(), "{report:?}");
+    assert!(report.finalized_slot() > 0, "{report:?}");
+}
+
+#[tokio::test]
+async fn test_nodes_converge_after_partition() {
+    let report = run(SimulationConfig {
+        slots: 32,
+        partitions: vec![Partition {
+            start_slot: 4,
+            end_slot: 12,
+            groups: vec![vec![0, 1], vec![2]],
+        }],
+        ..Default::default()
+    })
+    .await;
+
+    assert!(report.agree_on_head(), "{report:?}");
+    assert!(report.agree_on_finality(), "{report:?}");
+    assert!(report.finalized_slot() > 0, "{report:?}");
+}
+
+#[tokio::test]
+async fn test_simulation_is_deterministic() {
+    let config = SimulationConfig {
+        seed: 7,
+        link: LinkConfig {
+            min_delay: 0,
+            max_delay: 3,
+            drop_rate: 0.2,
+        },
+        ..Default::default()
+    };
+
+    assert_eq!(run(config.clone()).await, run(config).await);
+}
@@ -0,0 +1,197 @@
+//! Differential testing against the reference leanSpec implementation.
+//!
+//! A trace is recorded by running a block/attestation sequence through the reference store and
+//! writing down its head, justified and finalized checkpoints after every event. Replaying the
+//! trace through [Store] must give the same view after every event. When it doesn't, the trace is
+//! cut down to the events up to the first divergence, which is the smallest repro the recorded
+//! expectations allow.
+
+use std::{fmt, path::Path};
+
+use anyhow::anyhow;
+use ream_consensus_lean::{
+    attestation::{Attestation, SignedAttestation},
+    block::{Block, BlockWithAttestation, SignedBlockWithAttestation},
+    checkpoint::Checkpoint,
+};
+use ream_fork_choice_lean::store::{AttestationSource, Store};
+use ream_post_quantum_crypto::leansig::signature::Signature;
+use ream_storage::tables::field::REDBField;
+use serde_json::Value;
+use ssz_types::VariableList;
+use tracing::{debug, info};
+
+use crate::{
+    fork_choice::initialize_store,
+    types::{
+        TestFixture,
+        differential::{DifferentialTrace, ReferenceOutcome, TraceEvent},
+    },
+};
+
+/// The first point where ream and the reference implementation disagree
+#[derive(Debug)]
+pub struct Divergence {
+    pub step: usize,
+    pub field: &'static str,
+    pub expected: String,
+    pub actual: String,
+    /// The trace cut down to the events up to and including the diverging one
+    pub repro: Value,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} diverged from the reference at step {}: expected {}, got {}",
+            self.field, self.step, self.expected, self.actual
+        )
+    }
+}
+
+/// Load a differential trace file, keeping the raw JSON of every trace for repros
+pub fn load_differential_traces(
+    path: impl AsRef<Path>,
+) -> anyhow::Result<TestFixture<(DifferentialTrace, Value)>> {
+    let content = std::fs::read_to_string(path.as_ref()).map_err(|err| {
+        anyhow!(
+            "Failed to read trace file {:?}: {err}",
+            path.as_ref().display()
+        )
+    })?;
+
+    let raw: TestFixture<Value> = serde_json::from_str(&content).map_err(|err| {
+        anyhow!(
+            "Failed to parse trace file {:?}: {err}",
+            path.as_ref().display()
+        )
+    })?;
+
+    raw.into_iter()
+        .map(|(name, value)| {
+            let trace = serde_json::from_value(value.clone())
+                .map_err(|err| anyhow!("Failed to parse trace {name}: {err}"))?;
+            Ok((name, (trace, value)))
+        })
+        .collect()
+}
+
+/// Replay a trace through [Store], returning the first divergence from the reference, if any
+pub async fn run_differential_trace(
+    test_name: &str,
+    trace: DifferentialTrace,
+    raw_trace: &Value,
+) -> anyhow::Result<Option<Divergence>> {
+    info!("Running differential trace: {test_name}");
+    info!("  Network: {}", trace.network);
+    info!("  Number of steps: {}", trace.steps.len());
+
+    let (mut store, _) = initialize_store(trace.anchor_state, &trace.anchor_block)?;
+
+    for (index, step) in trace.steps.iter().enumerate() {
+        // Rejected events are compared like accepted ones, as the reference may reject them too
+        if let Err(err) = apply_event(&mut store, &step.event).await {
+            debug!("  Step {index}: event rejected: {err:?}");
+        }
+
+        if let Some((field, expected, actual)) = compare_outcome(&store, &step.expected)? {
+            return Ok(Some(Divergence {
+                step: index,
+                field,
+                expected,
+                actual,
+                repro: minimized_repro(raw_trace, index),
+            }));
+        }
+    }
+
+    info!("Trace matches the reference");
+    Ok(None)
+}
+
+async fn apply_event(store: &mut Store, event: &TraceEvent) -> anyhow::Result<()> {
+    match event {
+        TraceEvent::Tick { time } => store.on_tick(*time, false).await,
+        TraceEvent::Block { block } => {
+            let ream_block = Block::try_from(&block.block)
+                .map_err(|err| anyhow!("Failed to convert block: {err}"))?;
+
+            // Blank signatures for block body attestations + 1 for proposer attestation
+            let num_signatures = ream_block.body.attestations.len() + 1;
+            let signatures = VariableList::try_from(vec![Signature::blank(); num_signatures])
+                .map_err(|err| anyhow!("Failed to create signatures VariableList: {err}"))?;
+
+            store
+                .on_block(
+                    &SignedBlockWithAttestation {
+                        message: BlockWithAttestation {
+                            proposer_attestation: Attestation::from(&block.proposer_attestation),
+                            block: ream_block,
+                        },
+                        signature: signatures,
+                    },
+                    false,
+                )
+                .await
+                .map_err(anyhow::Error::from)
+        }
+        TraceEvent::Attestation { attestation } => store
+            .on_attestation(
+                SignedAttestation {
+                    message: Attestation::from(attestation),
+                    signature: Signature::blank(),
+                },
+                AttestationSource::Gossip,
+            )
+            .await
+            .map_err(anyhow::Error::from),
+    }
+}
+
+fn compare_outcome(
+    store: &Store,
+    expected: &ReferenceOutcome,
+) -> anyhow::Result<Option<(&'static str, String, String)>> {
+    let head_root = store.store.head_provider().get()?;
+    if head_root != expected.head_root {
+        return Ok(Some((
+            "Head",
+            expected.head_root.to_string(),
+            head_root.to_string(),
+        )));
+    }
+
+    let checkpoints = [
+        (
+            "Justified checkpoint",
+            Checkpoint::from(&expected.justified),
+            store.store.latest_justified_provider().get()?,
+        ),
+        (
+            "Finalized checkpoint",
+            Checkpoint::from(&expected.finalized),
+            store.store.latest_finalized_provider().get()?,
+        ),
+    ];
+    for (field, expected, actual) in checkpoints {
+        if expected != actual {
+            return Ok(Some((
+                field,
+                format!("{expected:?}"),
+                format!("{actual:?}"),
+            )));
+        }
+    }
+
+    Ok(None)
+}
+
+/// The raw trace with every step after `divergent_step` removed
+pub fn minimized_repro(raw_trace: &Value, divergent_step: usize) -> Value {
+    let mut repro = raw_trace.clone();
+    if let Some(steps) = repro.get_mut("steps").and_then(Value::as_array_mut) {
+        steps.truncate(divergent_step + 1);
+    }
+    repro
+}
@@ -7,7 +7,7 @@ use ream_consensus_lean::{
     checkpoint::Checkpoint,
     state::LeanState,
 };
-use ream_fork_choice_lean::store::Store;
+use ream_fork_choice_lean::store::{AttestationSource, Store};
 use ream_network_spec::networks::LeanNetworkSpec;
 use ream_post_quantum_crypto::leansig::signature::Signature;
 use ream_storage::{
@@ -20,8 +20,8 @@ use tracing::{debug, info};
 use tree_hash::TreeHash;
 
 use crate::types::{
-    TestFixture,
-    fork_choice::{ForkChoiceStep, ForkChoiceTest, StoreChecks},
+    self, State, TestFixture,
+    fork_choice::{AttestationLocation, ForkChoiceStep, ForkChoiceTest, StoreChecks},
 };
 
 /// Load a fork choice test fixture from a JSON file
@@ -45,32 +45,25 @@ pub fn load_fork_choice_test(
     Ok(fixture)
 }
 
-/// Run a single fork choice test case
-pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyhow::Result<()> {
-    info!("Running fork choice test: {test_name}");
-
-    // Extract values needed before consuming anchor_state
-    let anchor_state_slot = test.anchor_state.slot;
-
+/// Set the network spec for the anchor state's genesis time and initialize a store backed by a
+/// fresh database from the anchor state and block
+pub(crate) fn initialize_store(
+    anchor_state: State,
+    anchor_block: &types::Block,
+) -> anyhow::Result<(Store, LeanNetworkSpec)> {
     // Initialize network spec if not already set
     let mut network_spec = LeanNetworkSpec::ephemery();
     // For spec tests, use genesis_time from the test fixture's state config
-    network_spec.genesis_time = test.anchor_state.config.genesis_time;
+    network_spec.genesis_time = anchor_state.config.genesis_time;
     ream_network_spec::networks::set_lean_network_spec(std::sync::Arc::new(network_spec.clone()));
 
     // Convert anchor state and block
-    let state = LeanState::try_from(test.anchor_state)
+    let state = LeanState::try_from(anchor_state)
         .map_err(|err| anyhow!("Failed to convert anchor state: {err}"))?;
 
-    let block = Block::try_from(&test.anchor_block)
+    let block = Block::try_from(anchor_block)
         .map_err(|err| anyhow!("Failed to convert anchor block: {err}"))?;
 
-    // Create anchor checkpoint for use as source in attestations
-    let source_checkpoint = Checkpoint {
-        root: block.tree_hash_root(),
-        slot: block.slot,
-    };
-
     // Setup test database
     let test_dir = setup_data_dir("spec_tests", None, true)
         .map_err(|err| anyhow!("Failed to setup test directory: {err}"))?;
@@ -80,7 +73,7 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
         .map_err(|err| anyhow!("Failed to initialize LeanDB: {err}"))?;
 
     // Initialize store with anchor state and block
-    let mut store = Store::get_forkchoice_store(
+    let store = Store::get_forkchoice_store(
         SignedBlockWithAttestation {
             message: BlockWithAttestation {
                 proposer_attestation: Attestation {
@@ -104,6 +97,21 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
         None,
     )?;
 
+    Ok((store, network_spec))
+}
+
+/// Run a single fork choice test case
+pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyhow::Result<()> {
+    info!("Running fork choice test: {test_name}");
+
+    // Extract values needed before consuming anchor_state
+    let anchor_state_slot = test.anchor_state.slot;
+
+    let (mut store, network_spec) = initialize_store(test.anchor_state, &test.anchor_block)?;
+
+    // The anchor checkpoint, used as source in attestations
+    let source_checkpoint = store.store.latest_finalized_provider().get()?;
+
     info!("  Network: {}", test.network);
     info!("  Anchor state slot: {}", anchor_state_slot);
     info!("  Anchor block slot: {}", test.anchor_block.slot);
@@ -115,7 +123,7 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
             ForkChoiceStep::Tick { time, .. } => {
                 debug!("  Step {index}: Tick to time {time}");
                 // Update store time
-                let db = store.store.lock().await;
+                let db = &store.store;
                 db.time_provider().insert(*time)?;
             }
 
@@ -137,7 +145,7 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
                 store.on_tick(time, true).await?;
 
                 // Get the parent state and parent block to extract the correct checkpoints
-                let db = store.store.lock().await;
+                let db = &store.store;
 
                 let parent_block = db
                     .block_provider()
@@ -151,8 +159,6 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
 
                 let parent_slot = parent_block.message.block.slot;
 
-                drop(db);
-
                 // Create blank signatures for block body attestations + 1 for proposer attestation
                 let num_signatures = ream_block.body.attestations.len() + 1;
                 let signatures = VariableList::try_from(vec![Signature::blank(); num_signatures])
@@ -191,11 +197,20 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
                     result.map_err(|err| {
                         anyhow!("Block at slot {} should be valid: {err}", block.block.slot)
                     })?;
-                } else if result.is_ok() {
-                    bail!(
-                        "Block at slot {} should be invalid but was accepted",
-                        block.block.slot
-                    );
+                } else {
+                    match result {
+                        Ok(()) => bail!(
+                            "Block at slot {} should be invalid but was accepted",
+                            block.block.slot
+                        ),
+                        // The block itself has to be at fault, not the node.
+                        Err(err) if err.is_internal() => bail!(
+                            "Block at slot {} should be invalid but failed with an internal \
+                             error: {err}",
+                            block.block.slot
+                        ),
+                        Err(_) => {}
+                    }
                 }
 
                 // Validate checks if present
@@ -208,9 +223,10 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
                 valid,
                 attestation,
                 checks,
+                is_from_block,
             } => {
                 debug!(
-                    "  Step {index}: Attestation from validator {} (expect valid: {valid})",
+                    "  Step {index}: Attestation from validator {} (expect valid: {valid}, from block: {is_from_block})",
                     attestation.validator_id
                 );
 
@@ -219,11 +235,12 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
                     signature: Signature::blank(),
                 };
 
-                // Add attestation to new attestations
-                let db = store.store.lock().await;
-                let result = db
-                    .latest_new_attestations_provider()
-                    .insert(signed_attestation.message.validator_id, signed_attestation);
+                let source = if *is_from_block {
+                    AttestationSource::Block
+                } else {
+                    AttestationSource::Gossip
+                };
+                let result = store.on_attestation(signed_attestation, source).await;
 
                 if *valid {
                     result.map_err(|err| {
@@ -232,11 +249,19 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
                             attestation.validator_id
                         )
                     })?;
-                } else if result.is_ok() {
-                    bail!(
-                        "Attestation from validator {} should be invalid but was accepted",
-                        attestation.validator_id
-                    );
+                } else {
+                    match result {
+                        Ok(()) => bail!(
+                            "Attestation from validator {} should be invalid but was accepted",
+                            attestation.validator_id
+                        ),
+                        Err(err) if err.is_internal() => bail!(
+                            "Attestation from validator {} should be invalid but failed with an \
+                             internal error: {err}",
+                            attestation.validator_id
+                        ),
+                        Err(_) => {}
+                    }
                 }
 
                 if let Some(checks) = checks {
@@ -256,7 +281,7 @@ pub async fn run_fork_choice_test(test_name: &str, test: ForkChoiceTest) -> anyh
 
 /// Validate store checks
 async fn validate_checks(store: &Store, checks: &StoreChecks) -> anyhow::Result<()> {
-    let db = store.store.lock().await;
+    let db = &store.store;
 
     if let Some(expected_head_slot) = checks.head_slot {
         let head_root = db.head_provider().get()?;
@@ -312,5 +337,53 @@ async fn validate_checks(store: &Store, checks: &StoreChecks) -> anyhow::Result<
         debug!("Finalized checkpoint: slot {}", actual_finalized.slot);
     }
 
+    if let Some(expected_proposer_boost_root) = checks.proposer_boost_root {
+        let actual_proposer_boost_root = db.proposer_boost_root_provider().get()?;
+        ensure!(
+            actual_proposer_boost_root == expected_proposer_boost_root,
+            "Proposer boost root mismatch: expected {expected_proposer_boost_root}, got {actual_proposer_boost_root}"
+        );
+        debug!("Proposer boost root: {actual_proposer_boost_root}");
+    }
+
+    for check in &checks.attestation_checks {
+        let attestation = match check.location {
+            AttestationLocation::New => {
+                db.latest_new_attestations_provider().get(check.validator)?
+            }
+            AttestationLocation::Known => db
+                .latest_known_attestations_provider()
+                .get(check.validator)?,
+        }
+        .ok_or_else(|| {
+            anyhow!(
+                "No {:?} attestation found for validator {}",
+                check.location,
+                check.validator
+            )
+        })?;
+        let data = &attestation.message.data;
+
+        ensure!(
+            data.slot == check.attestation_slot,
+            "Attestation slot mismatch for validator {}: expected {}, got {}",
+            check.validator,
+            check.attestation_slot,
+            data.slot
+        );
+        if let Some(expected_target_slot) = check.target_slot {
+            ensure!(
+                data.target.slot == expected_target_slot,
+                "Attestation target slot mismatch for validator {}: expected {expected_target_slot}, got {}",
+                check.validator,
+                data.target.slot
+            );
+        }
+        debug!(
+            "{:?} attestation for validator {}: slot {}",
+            check.location, check.validator, data.slot
+        );
+    }
+
     Ok(())
 }
@@ -1,3 +1,4 @@
+pub mod differential;
 pub mod fork_choice;
 pub mod state_transition;
 pub mod types;
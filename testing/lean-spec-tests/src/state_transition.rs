@@ -1,8 +1,14 @@
 use std::path::Path;
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail, ensure};
+use ream_consensus_lean::{block::Block, state::LeanState};
+use ream_network_spec::networks::{LeanNetworkSpec, set_lean_network_spec};
+use tracing::{debug, info};
 
-use crate::types::{TestFixture, state_transition::StateTransitionTest};
+use crate::types::{
+    self, TestFixture,
+    state_transition::{StateExpectation, StateTransitionTest},
+};
 
 /// Load a state transition test fixture from a JSON file
 pub fn load_state_transition_test(
@@ -25,3 +31,96 @@ pub fn load_state_transition_test(
 
     Ok(fixture)
 }
+
+/// Run a single state transition test case
+pub fn run_state_transition_test(test_name: &str, test: StateTransitionTest) -> Result<()> {
+    info!("Running state transition test: {test_name}");
+    info!("  Network: {}", test.network);
+    info!("  Pre state slot: {}", test.pre.slot);
+    info!("  Number of blocks: {}", test.blocks.len());
+
+    // For spec tests, use genesis_time from the test fixture's state config
+    let mut network_spec = LeanNetworkSpec::ephemery();
+    network_spec.genesis_time = test.pre.config.genesis_time;
+    set_lean_network_spec(std::sync::Arc::new(network_spec));
+
+    let mut state = LeanState::try_from(test.pre)
+        .map_err(|err| anyhow!("Failed to convert pre state: {err}"))?;
+
+    let result = apply_blocks(&mut state, &test.blocks);
+
+    match (&test.expect_exception, result) {
+        (Some(exception), Ok(())) => {
+            bail!("Expected state transition to fail with {exception}, but it succeeded")
+        }
+        (Some(exception), Err(err)) => {
+            debug!("State transition failed as expected ({exception}): {err:?}");
+        }
+        (None, Err(err)) => bail!("State transition should succeed: {err:?}"),
+        (None, Ok(())) => {
+            if let Some(post) = &test.post {
+                validate_post_state(&state, post)?;
+            }
+        }
+    }
+
+    info!("Test passed");
+    Ok(())
+}
+
+/// Apply every block of the test to `state` in order, stopping at the first failure
+fn apply_blocks(state: &mut LeanState, blocks: &[types::Block]) -> Result<()> {
+    for (index, block) in blocks.iter().enumerate() {
+        let ream_block = Block::try_from(block)
+            .map_err(|err| anyhow!("Failed to convert block {index}: {err}"))?;
+        debug!("  Block {index}: slot {}", ream_block.slot);
+
+        // Spec test blocks aren't signed, so signatures are treated as valid
+        state
+            .state_transition(&ream_block, true)
+            .map_err(|err| anyhow!("Block {index} at slot {}: {err:?}", ream_block.slot))?;
+    }
+
+    Ok(())
+}
+
+/// Validate the post state against the expected values
+fn validate_post_state(state: &LeanState, post: &StateExpectation) -> Result<()> {
+    if let Some(expected_slot) = post.slot {
+        ensure!(
+            state.slot == expected_slot,
+            "Slot mismatch: expected {expected_slot}, got {}",
+            state.slot
+        );
+        debug!("Slot: {}", state.slot);
+    }
+
+    if let Some(expected_slot) = post.latest_block_header_slot {
+        let actual_slot = state.latest_block_header.slot;
+        ensure!(
+            actual_slot == expected_slot,
+            "Latest block header slot mismatch: expected {expected_slot}, got {actual_slot}"
+        );
+        debug!("Latest block header slot: {actual_slot}");
+    }
+
+    if let Some(expected_state_root) = post.latest_block_header_state_root {
+        let actual_state_root = state.latest_block_header.state_root;
+        ensure!(
+            actual_state_root == expected_state_root,
+            "Latest block header state root mismatch: expected {expected_state_root}, got {actual_state_root}"
+        );
+        debug!("Latest block header state root: {actual_state_root}");
+    }
+
+    if let Some(expected_count) = post.historical_block_hashes_count {
+        let actual_count = state.historical_block_hashes.len();
+        ensure!(
+            actual_count == expected_count,
+            "Historical block hashes count mismatch: expected {expected_count}, got {actual_count}"
+        );
+        debug!("Historical block hashes count: {actual_count}");
+    }
+
+    Ok(())
+}
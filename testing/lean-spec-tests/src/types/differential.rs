@@ -0,0 +1,42 @@
+use alloy_primitives::B256;
+use serde::Deserialize;
+
+use crate::types::{
+    Attestation, Block, Checkpoint, State, fork_choice::BlockWithProposerAttestation,
+};
+
+/// Trace recorded by running a block/attestation sequence through the reference implementation
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DifferentialTrace {
+    pub network: String,
+    pub anchor_state: State,
+    pub anchor_block: Block,
+    pub steps: Vec<TraceStep>,
+}
+
+/// An event applied to the store, and the reference store's view after applying it
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TraceStep {
+    pub event: TraceEvent,
+    pub expected: ReferenceOutcome,
+}
+
+/// Trace event - can be tick, block or gossip attestation
+#[derive(Debug, Deserialize)]
+#[serde(tag = "eventType", rename_all = "lowercase")]
+pub enum TraceEvent {
+    Tick { time: u64 },
+    Block { block: BlockWithProposerAttestation },
+    Attestation { attestation: Attestation },
+}
+
+/// Head choice, justification and finalization of the reference store
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReferenceOutcome {
+    pub head_root: B256,
+    pub justified: Checkpoint,
+    pub finalized: Checkpoint,
+}
@@ -37,6 +37,10 @@ pub enum ForkChoiceStep {
         valid: bool,
         checks: Option<StoreChecks>,
         attestation: Attestation,
+        /// Whether the attestation is processed as part of a block rather than received over
+        /// gossip. Gossip is assumed when the fixture doesn't say.
+        #[serde(default, rename = "isFromBlock")]
+        is_from_block: bool,
     },
     Checks {
         checks: StoreChecks,
@@ -72,7 +76,17 @@ pub struct AttestationCheck {
     pub validator: u64,
     pub attestation_slot: u64,
     pub target_slot: Option<u64>,
-    pub location: String,
+    pub location: AttestationLocation,
+}
+
+/// The store table an attestation is expected to be found in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AttestationLocation {
+    /// `latest_new_attestations`, attestations received over gossip that aren't counted yet
+    New,
+    /// `latest_known_attestations`, attestations counted by fork choice
+    Known,
 }
 
 // TryFrom implementation for converting State to LeanState
@@ -161,6 +175,7 @@ impl TryFrom<State> for LeanState {
                 .map_err(|err| anyhow!("Failed to create validators VariableList: {err}"))?,
             justifications_roots,
             justifications_validators,
+            validator_statuses: None,
         })
     }
 }
@@ -1,3 +1,4 @@
+pub mod differential;
 pub mod fork_choice;
 pub mod state_transition;
 
@@ -199,10 +200,10 @@ impl TryFrom<&Block> for ReamBlock {
             proposer_index: block.proposer_index,
             parent_root: block.parent_root,
             state_root: block.state_root,
-            body: ReamBlockBody {
-                attestations: VariableList::try_from(attestations)
+            body: ReamBlockBody::new(
+                VariableList::try_from(attestations)
                     .map_err(|err| anyhow!("Failed to create attestations VariableList: {err}"))?,
-            },
+            ),
         })
     }
 }
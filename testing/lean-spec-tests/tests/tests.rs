@@ -1,6 +1,10 @@
 use std::{env, fs, path::PathBuf};
 
-use lean_spec_tests::fork_choice::{load_fork_choice_test, run_fork_choice_test};
+use lean_spec_tests::{
+    differential::{load_differential_traces, run_differential_trace},
+    fork_choice::{load_fork_choice_test, run_fork_choice_test},
+    state_transition::{load_state_transition_test, run_state_transition_test},
+};
 use tracing::{debug, error, info, warn};
 use tracing_subscriber::EnvFilter;
 
@@ -32,14 +36,20 @@ fn find_json_files(dir: &str) -> Vec<PathBuf> {
     files
 }
 
-#[tokio::test]
-async fn test_all_fork_choice_fixtures() {
-    // Initialize tracing subscriber for test output
+/// Initialize tracing subscriber for test output, shared by every test in this binary
+fn init_tracing() {
     let env_filter = match env::var(EnvFilter::DEFAULT_ENV) {
         Ok(filter) => EnvFilter::builder().parse_lossy(filter),
         Err(_) => EnvFilter::new("info"),
     };
-    tracing_subscriber::fmt().with_env_filter(env_filter).init();
+    let _ = tracing_subscriber::fmt()
+        .with_env_filter(env_filter)
+        .try_init();
+}
+
+#[tokio::test]
+async fn test_all_fork_choice_fixtures() {
+    init_tracing();
 
     let fixtures = find_json_files("fixtures/consensus/fork_choice");
 
@@ -90,3 +100,129 @@ async fn test_all_fork_choice_fixtures() {
 
     assert_eq!(failed, 0, "Some fork choice tests failed");
 }
+
+#[tokio::test]
+async fn test_all_state_transition_fixtures() {
+    init_tracing();
+
+    let fixtures = find_json_files("fixtures/consensus/state_transition");
+
+    if fixtures.is_empty() {
+        info!(
+            "No state transition fixtures found. Skipping tests. Run 'make test' in lean-spec-tests to download fixtures."
+        );
+        return;
+    }
+
+    info!("Found {} state transition test fixtures", fixtures.len());
+
+    let mut total_tests = 0;
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for fixture_path in fixtures {
+        debug!("\n=== Loading fixture: {:?} ===", fixture_path.file_name());
+
+        match load_state_transition_test(&fixture_path) {
+            Ok(fixture) => {
+                for (test_name, test) in fixture {
+                    total_tests += 1;
+                    info!("Starting test: {}", test_name);
+                    match run_state_transition_test(&test_name, test) {
+                        Ok(_) => {
+                            passed += 1;
+                            info!("PASSED: {}", test_name);
+                        }
+                        Err(err) => {
+                            failed += 1;
+                            error!("FAILED: {test_name} - {err:?}");
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Failed to load fixture {fixture_path:?}: {err:?}");
+                failed += 1;
+            }
+        }
+    }
+
+    info!("\n=== State Transition Test Summary ===");
+    info!("Total tests: {total_tests}");
+    info!("Passed: {passed}");
+    info!("Failed: {failed}");
+
+    assert_eq!(failed, 0, "Some state transition tests failed");
+}
+
+/// Replays traces recorded from the reference leanSpec implementation. Set `LEAN_SPEC_TRACES_DIR`
+/// to use traces outside the fixtures directory. Divergences are written as minimized repros to
+/// the target tmp directory.
+#[tokio::test]
+async fn test_all_differential_traces() {
+    init_tracing();
+
+    let traces_dir =
+        env::var("LEAN_SPEC_TRACES_DIR").unwrap_or_else(|_| "fixtures/differential".to_string());
+    let trace_files = find_json_files(&traces_dir);
+
+    if trace_files.is_empty() {
+        info!("No differential traces found in {traces_dir}. Skipping tests.");
+        return;
+    }
+
+    info!("Found {} differential trace files", trace_files.len());
+
+    let repro_dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("differential_repros");
+    let mut total_tests = 0;
+    let mut passed = 0;
+    let mut failed = 0;
+
+    for trace_path in trace_files {
+        debug!("\n=== Loading traces: {:?} ===", trace_path.file_name());
+
+        match load_differential_traces(&trace_path) {
+            Ok(traces) => {
+                for (test_name, (trace, raw_trace)) in traces {
+                    total_tests += 1;
+                    info!("Starting trace: {}", test_name);
+                    match run_differential_trace(&test_name, trace, &raw_trace).await {
+                        Ok(None) => {
+                            passed += 1;
+                            info!("PASSED: {}", test_name);
+                        }
+                        Ok(Some(divergence)) => {
+                            failed += 1;
+                            let repro_path = repro_dir.join(format!("{test_name}.json"));
+                            let repro = serde_json::json!({ test_name.clone(): divergence.repro });
+                            if let Err(err) = fs::create_dir_all(&repro_dir)
+                                .and_then(|_| fs::write(&repro_path, repro.to_string()))
+                            {
+                                error!("Failed to write repro {repro_path:?}: {err:?}");
+                            }
+                            error!("DIVERGED: {test_name} - {divergence}, repro: {repro_path:?}");
+                        }
+                        Err(err) => {
+                            failed += 1;
+                            error!("FAILED: {test_name} - {err:?}");
+                        }
+                    }
+                }
+            }
+            Err(err) => {
+                error!("Failed to load traces {trace_path:?}: {err:?}");
+                failed += 1;
+            }
+        }
+    }
+
+    info!("\n=== Differential Test Summary ===");
+    info!("Total traces: {total_tests}");
+    info!("Matched: {passed}");
+    info!("Diverged or failed: {failed}");
+
+    assert_eq!(
+        failed, 0,
+        "Some traces diverged from the reference implementation"
+    );
+}
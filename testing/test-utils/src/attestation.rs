@@ -0,0 +1,31 @@
+use ream_consensus_lean::attestation::{Attestation, AttestationData, SignedAttestation};
+use ream_post_quantum_crypto::leansig::signature::Signature;
+
+/// Returns an attestation to `data` from each of `validator_ids`, for block bodies.
+pub fn attestations(
+    validator_ids: impl IntoIterator<Item = u64>,
+    data: &AttestationData,
+) -> Vec<Attestation> {
+    validator_ids
+        .into_iter()
+        .map(|validator_id| Attestation {
+            validator_id,
+            data: data.clone(),
+        })
+        .collect()
+}
+
+/// Returns an attestation to `data` from each of `validator_ids` with a blank signature, as they
+/// arrive over gossip.
+pub fn signed_attestations(
+    validator_ids: impl IntoIterator<Item = u64>,
+    data: &AttestationData,
+) -> Vec<SignedAttestation> {
+    attestations(validator_ids, data)
+        .into_iter()
+        .map(|message| SignedAttestation {
+            message,
+            signature: Signature::blank(),
+        })
+        .collect()
+}
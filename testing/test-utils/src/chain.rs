@@ -0,0 +1,193 @@
+use std::collections::HashMap;
+
+use alloy_primitives::B256;
+use anyhow::anyhow;
+use ream_consensus_lean::{
+    attestation::{Attestation, AttestationData},
+    block::{Block, BlockBody, SignedBlockWithAttestation},
+    checkpoint::Checkpoint,
+    state::LeanState,
+};
+use ream_network_spec::networks::initialize_test_lean_network_spec;
+use ream_storage::{db::lean::LeanDB, tables::table::REDBTable};
+use ssz_types::VariableList;
+use tree_hash::TreeHash;
+
+use crate::build_signed_block_with_attestation;
+
+/// Builds blocks with valid state roots on top of a genesis block, so they pass the state
+/// transition when given to fork choice.
+///
+/// A block can be built on any block the builder knows, so a history forks by building on a
+/// block which already has a child. Blocks on different branches need different slots, as blocks
+/// with the same parent, slot and attestations have the same root.
+#[derive(Debug, Clone)]
+pub struct ChainBuilder {
+    blocks: HashMap<B256, SignedBlockWithAttestation>,
+    states: HashMap<B256, LeanState>,
+    /// Roots of the built blocks in the order they were built, so parents come first.
+    built_roots: Vec<B256>,
+    genesis_root: B256,
+}
+
+impl ChainBuilder {
+    /// Starts a chain at `genesis_block`. Unless a test already set up a network, it sets up one
+    /// without scheduled forks for the state transition.
+    pub fn new(genesis_block: SignedBlockWithAttestation, genesis_state: LeanState) -> Self {
+        initialize_test_lean_network_spec();
+        let genesis_root = genesis_block.message.block.tree_hash_root();
+        Self {
+            blocks: HashMap::from([(genesis_root, genesis_block)]),
+            states: HashMap::from([(genesis_root, genesis_state)]),
+            built_roots: vec![],
+            genesis_root,
+        }
+    }
+
+    pub fn genesis_root(&self) -> B256 {
+        self.genesis_root
+    }
+
+    /// Builds the block of `slot` on `parent_root` including `attestations`, and returns its root.
+    /// The proposer attests to the new block as head and target, from the justified checkpoint of
+    /// its post state.
+    pub fn build_block(
+        &mut self,
+        parent_root: B256,
+        slot: u64,
+        attestations: Vec<Attestation>,
+    ) -> anyhow::Result<B256> {
+        let mut state = self
+            .states
+            .get(&parent_root)
+            .ok_or_else(|| anyhow!("Unknown parent block {parent_root}"))?
+            .clone();
+        state.process_slots(slot)?;
+
+        let mut block = Block {
+            slot,
+            proposer_index: slot % state.validators.len() as u64,
+            parent_root,
+            state_root: B256::ZERO,
+            body: BlockBody::new(
+                VariableList::try_from(attestations)
+                    .map_err(|err| anyhow!("Too many attestations: {err:?}"))?,
+            ),
+        };
+        state.process_block(&block)?;
+        block.state_root = state.tree_hash_root();
+
+        let root = block.tree_hash_root();
+        let checkpoint = Checkpoint { root, slot };
+        let signed_block = build_signed_block_with_attestation(
+            AttestationData {
+                slot,
+                head: checkpoint,
+                target: checkpoint,
+                source: state.latest_justified,
+            },
+            block,
+            VariableList::default(),
+        );
+        self.blocks.insert(root, signed_block);
+        self.states.insert(root, state);
+        self.built_roots.push(root);
+        Ok(root)
+    }
+
+    /// Builds an empty block for each of `slots` in turn, the first on `parent_root` and each
+    /// following on the one before, and returns their roots.
+    pub fn extend(
+        &mut self,
+        parent_root: B256,
+        slots: impl IntoIterator<Item = u64>,
+    ) -> anyhow::Result<Vec<B256>> {
+        let mut parent_root = parent_root;
+        let mut roots = vec![];
+        for slot in slots {
+            parent_root = self.build_block(parent_root, slot, vec![])?;
+            roots.push(parent_root);
+        }
+        Ok(roots)
+    }
+
+    pub fn block(&self, root: B256) -> Option<&SignedBlockWithAttestation> {
+        self.blocks.get(&root)
+    }
+
+    /// Returns the post state of the block at `root`.
+    pub fn state(&self, root: B256) -> Option<&LeanState> {
+        self.states.get(&root)
+    }
+
+    /// Forgets the post state of the block at `root`, so a long chain doesn't keep every state in
+    /// memory. No block can be built on it afterwards.
+    pub fn forget_state(&mut self, root: B256) {
+        self.states.remove(&root);
+    }
+
+    pub fn checkpoint(&self, root: B256) -> Option<Checkpoint> {
+        self.blocks.get(&root).map(|block| Checkpoint {
+            root,
+            slot: block.message.block.slot,
+        })
+    }
+
+    /// Returns attestation data of `slot` voting for the block at `head_root` as head and target,
+    /// from the justified checkpoint of its post state.
+    pub fn attestation_data(&self, head_root: B256, slot: u64) -> Option<AttestationData> {
+        let head = self.checkpoint(head_root)?;
+        Some(AttestationData {
+            slot,
+            head,
+            target: head,
+            source: self.states.get(&head_root)?.latest_justified,
+        })
+    }
+
+    /// Returns the built blocks, parents before their children. The genesis block isn't included.
+    pub fn blocks(&self) -> impl Iterator<Item = (B256, &SignedBlockWithAttestation)> {
+        self.built_roots
+            .iter()
+            .filter_map(|root| self.blocks.get(root).map(|block| (*root, block)))
+    }
+
+    /// Writes the built blocks to the block table of `lean_db`, bypassing fork choice. Their
+    /// states aren't written, so they can be regenerated or inserted separately.
+    pub fn store_blocks(&self, lean_db: &LeanDB) -> anyhow::Result<()> {
+        let block_provider = lean_db.block_provider();
+        for (root, block) in self.blocks() {
+            block_provider.insert(root, block.clone())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ChainBuilder;
+    use crate::genesis;
+
+    #[test]
+    fn test_build_forked_history() {
+        let (genesis_block, genesis_state) = genesis(4);
+        let mut chain = ChainBuilder::new(genesis_block, genesis_state);
+        let genesis_root = chain.genesis_root();
+
+        let canonical = chain.extend(genesis_root, 1..=3).unwrap();
+        let fork = chain.extend(canonical[0], [4, 5]).unwrap();
+
+        assert_eq!(chain.blocks().count(), 5);
+        assert_eq!(
+            chain.block(fork[0]).unwrap().message.block.parent_root,
+            canonical[0]
+        );
+        // Every block replays onto its parent's state.
+        for (root, block) in chain.blocks() {
+            let block = &block.message.block;
+            let mut state = chain.state(block.parent_root).unwrap().clone();
+            state.state_transition(block, true).unwrap();
+            assert_eq!(Some(&state), chain.state(root));
+        }
+    }
+}
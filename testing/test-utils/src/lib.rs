@@ -0,0 +1,104 @@
+pub mod attestation;
+pub mod chain;
+
+use alloy_primitives::B256;
+use ream_consensus_lean::{
+    attestation::{Attestation, AttestationData},
+    block::{Block, BlockBody, BlockWithAttestation, SignedBlockWithAttestation},
+    checkpoint::Checkpoint,
+    state::LeanState,
+    utils::generate_default_validators,
+};
+use ream_network_spec::networks::initialize_test_lean_network_spec;
+use ream_post_quantum_crypto::leansig::signature::Signature;
+use ream_storage::db::{ReamDB, lean::LeanDB};
+use ssz_types::{VariableList, typenum::U4096};
+use tree_hash::TreeHash;
+
+/// Opens a fresh lean database in memory, which is dropped with the last handle to it.
+pub fn db_setup() -> LeanDB {
+    ReamDB::in_memory()
+        .expect("unable to init Ream Database")
+        .init_lean_db()
+        .expect("unable to init Lean Database")
+}
+
+/// Returns the genesis block, signed the way fork choice stores it, and the genesis state of
+/// `no_of_validators` default validators with a genesis time of 0. Unless a test already set up a
+/// network, it sets up one without scheduled forks for the state transition.
+pub fn genesis(no_of_validators: usize) -> (SignedBlockWithAttestation, LeanState) {
+    initialize_test_lean_network_spec();
+    let genesis_state =
+        LeanState::generate_genesis(0, Some(generate_default_validators(no_of_validators)));
+    let genesis_block = Block::genesis(genesis_state.tree_hash_root());
+
+    let checkpoint = Checkpoint {
+        slot: genesis_block.slot,
+        root: genesis_block.tree_hash_root(),
+    };
+    let signed_genesis_block = build_signed_block_with_attestation(
+        AttestationData {
+            slot: genesis_block.slot,
+            head: checkpoint,
+            target: checkpoint,
+            source: checkpoint,
+        },
+        genesis_block,
+        VariableList::default(),
+    );
+    (signed_genesis_block, genesis_state)
+}
+
+/// Wraps `block` with the proposer's attestation to `attestation_data`. A blank signature is
+/// appended to `signatures` for the proposer attestation.
+pub fn build_signed_block_with_attestation(
+    attestation_data: AttestationData,
+    block: Block,
+    mut signatures: VariableList<Signature, U4096>,
+) -> SignedBlockWithAttestation {
+    signatures
+        .push(Signature::blank())
+        .expect("signatures should have room for the proposer signature");
+    SignedBlockWithAttestation {
+        message: BlockWithAttestation {
+            proposer_attestation: Attestation {
+                validator_id: block.proposer_index,
+                data: attestation_data,
+            },
+            block,
+        },
+        signature: signatures,
+    }
+}
+
+/// Returns a block at `slot` by `proposer_index` on top of `parent_root`, with an empty body and
+/// no signatures. Its state root is unique to the slot and proposer.
+pub fn sample_block(
+    slot: u64,
+    proposer_index: u64,
+    parent_root: B256,
+) -> SignedBlockWithAttestation {
+    SignedBlockWithAttestation {
+        message: BlockWithAttestation {
+            block: Block {
+                slot,
+                proposer_index,
+                parent_root,
+                state_root: B256::left_padding_from(
+                    &[proposer_index.to_be_bytes(), slot.to_be_bytes()].concat(),
+                ),
+                body: BlockBody::default(),
+            },
+            proposer_attestation: Attestation {
+                validator_id: proposer_index,
+                data: AttestationData {
+                    slot,
+                    head: Checkpoint::default(),
+                    target: Checkpoint::default(),
+                    source: Checkpoint::default(),
+                },
+            },
+        },
+        signature: VariableList::empty(),
+    }
+}